@@ -1,25 +1,60 @@
-use std::io::Write;
-
-use color_eyre::eyre::WrapErr;
-
-use lib::applebooks::Platform;
+use std::collections::HashMap;
+use std::io::{IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use chrono::{DateTime, Utc};
+use clap::ValueEnum;
+use color_eyre::eyre::{bail, WrapErr};
+
+use lib::analysis::cluster::CLUSTERS_FILENAME;
+use lib::analysis::heatmap::HEATMAP_FILENAME;
+use lib::conflict::WriteSummary;
+use lib::contexts::reading_log::ReadingLogContext;
+use lib::contexts::review::ReviewContext;
+use lib::epub::EpubCache;
+use lib::models::entry::BookSortKey;
+use lib::pack::{PackBook, PackIndex};
+use lib::process::pre::TagMap;
+use lib::process::SortKey;
+use lib::render::engine::RenderEngine;
+use lib::render::progress::RenderProgress;
 use lib::render::renderer::Renderer;
+use lib::render::sandbox::Sandbox;
+use lib::render::template::Render;
+use lib::search::{IndexUpdate, SearchIndex, INDEX_FILENAME};
+use lib::snapshot::Snapshot;
+use lib::sync::{hypothesis, notion, webhook};
+use lib::upload::UploadTarget;
 
 use crate::CliResult;
 
 use super::args::{
-    BackupOptions, ExportOptions, FilterOptions, PostProcessOptions, PreProcessOptions,
-    RenderOptions,
+    BackupOptions, ClusterOptions, ExportOptions, FilterOptions, HeatmapOptions, HypothesisOptions,
+    IdScheme, InfoFormat, ListItems, NgramFormat, NgramOptions, NotionOptions, Platform,
+    PostProcessOptions, PreProcessOptions, ReadingLogOptions, RenderOptions, ReviewOptions,
+    SortOptions, WebhookOptions,
 };
 use super::config::Config;
 use super::data::Data;
+use super::hooks::RunSummary;
+use super::limits::OutputLimits;
+use super::progress::Progress;
+use super::report::RunStats;
+use super::table::Table;
 
 /// Extension for an new [`App`].
 pub struct ExtNone;
 
 /// Extension for an [`App`] that renders templates.
+#[allow(clippy::struct_excessive_bools)]
 pub struct ExtRender {
     renderer: Renderer,
+    resume: bool,
+    single_file: bool,
+    sandbox: bool,
+    stream: bool,
+    limits: OutputLimits,
 }
 
 /// Extension for an [`App`] that exports data.
@@ -32,220 +67,1516 @@ pub struct ExtBackup {
     options: BackupOptions,
 }
 
+/// Extension for an [`App`] that syncs data to Notion.
+pub struct ExtSync {
+    options: NotionOptions,
+}
+
+/// Extension for an [`App`] that syncs data to Hypothes.is.
+pub struct ExtHypothesis {
+    options: HypothesisOptions,
+}
+
+/// Extension for an [`App`] that syncs data to a webhook.
+pub struct ExtWebhook {
+    options: WebhookOptions,
+}
+
+/// Extension for an [`App`] that clusters annotations.
+pub struct ExtCluster {
+    options: ClusterOptions,
+}
+
+/// Extension for an [`App`] that selects annotations to review.
+pub struct ExtReview {
+    options: ReviewOptions,
+}
+
+/// Extension for an [`App`] that prints a reading log.
+pub struct ExtReadingLog {
+    options: ReadingLogOptions,
+}
+
+/// Extension for an [`App`] that builds the search index.
+pub struct ExtIndex;
+
+/// Extension for an [`App`] that counts n-grams.
+pub struct ExtNgrams {
+    options: NgramOptions,
+}
+
+/// Extension for an [`App`] that aggregates annotation positions.
+pub struct ExtHeatmap {
+    options: HeatmapOptions,
+}
+
+/// Extension for an [`App`] that lists data.
+pub struct ExtList {
+    items: ListItems,
+}
+
+/// Extension for an [`App`] that prints a single book.
+pub struct ExtInfo {
+    book_id: String,
+    format: InfoFormat,
+}
+
+/// Extension for an [`App`] that snapshots data.
+pub struct ExtSnapshot;
+
 /// The main application struct.
 pub struct App<Ext> {
     /// The application's configuration.
     config: Config,
 
-    /// The application's data.
-    data: Data,
+    /// The application's data.
+    data: Data,
+
+    /// The order in which books are output.
+    sort_books: BookSortKey,
+
+    /// The order in which each book's annotations are output, if set. Otherwise annotations are
+    /// output in the order they appear in the book.
+    sort_annotations: Option<SortKey>,
+
+    /// The stats collected while running, reported via `--report`.
+    stats: RunStats,
+
+    /// The application's capability extension.
+    extension: Ext,
+}
+
+impl App<ExtNone> {
+    /// Creates a new instance of [`App`].
+    pub fn new(config: Config) -> CliResult<Self> {
+        let mut app = Self {
+            config,
+            data: Data::default(),
+            sort_books: BookSortKey::default(),
+            sort_annotations: None,
+            stats: RunStats::default(),
+            extension: ExtNone,
+        };
+
+        let start = Instant::now();
+
+        app.init_data()?;
+
+        app.stats.record("load", start);
+
+        Ok(app)
+    }
+
+    /// Turns the [`App`] into one that renders templates.
+    pub fn into_render(self, options: RenderOptions) -> CliResult<App<ExtRender>> {
+        let resume = options.resume;
+        let single_file = options.single_file;
+        let sandbox = options.sandbox;
+        let stream = options.stream;
+        let limits = OutputLimits {
+            max_files: options.max_files,
+            max_total_size: options.max_total_size,
+        };
+        let template = options.preset.unwrap_or_default().template();
+        let mut renderer = Renderer::new(options, template.into());
+
+        renderer
+            .init()
+            .wrap_err("Failed while initializing template(s)")?;
+
+        Ok(App {
+            config: self.config,
+            data: self.data,
+            sort_books: self.sort_books,
+            sort_annotations: self.sort_annotations,
+            stats: self.stats,
+            extension: ExtRender {
+                renderer,
+                resume,
+                single_file,
+                sandbox,
+                stream,
+                limits,
+            },
+        })
+    }
+
+    /// Turns the [`App`] into one that exports data.
+    pub fn into_export(self, options: ExportOptions) -> App<ExtExport> {
+        App {
+            config: self.config,
+            data: self.data,
+            sort_books: self.sort_books,
+            sort_annotations: self.sort_annotations,
+            stats: self.stats,
+            extension: ExtExport { options },
+        }
+    }
+
+    /// Turns the [`App`] into one that backs-up data.
+    pub fn into_backup(self, options: BackupOptions) -> App<ExtBackup> {
+        App {
+            config: self.config,
+            data: self.data,
+            sort_books: self.sort_books,
+            sort_annotations: self.sort_annotations,
+            stats: self.stats,
+            extension: ExtBackup { options },
+        }
+    }
+
+    /// Turns the [`App`] into one that snapshots data.
+    pub fn into_snapshot(self) -> App<ExtSnapshot> {
+        App {
+            config: self.config,
+            data: self.data,
+            sort_books: self.sort_books,
+            sort_annotations: self.sort_annotations,
+            stats: self.stats,
+            extension: ExtSnapshot,
+        }
+    }
+
+    /// Turns the [`App`] into one that lists data.
+    pub fn into_list(self, items: ListItems) -> App<ExtList> {
+        App {
+            config: self.config,
+            data: self.data,
+            sort_books: self.sort_books,
+            sort_annotations: self.sort_annotations,
+            stats: self.stats,
+            extension: ExtList { items },
+        }
+    }
+
+    /// Turns the [`App`] into one that prints a single book.
+    pub fn into_info(self, book_id: String, format: InfoFormat) -> App<ExtInfo> {
+        App {
+            config: self.config,
+            data: self.data,
+            sort_books: self.sort_books,
+            sort_annotations: self.sort_annotations,
+            stats: self.stats,
+            extension: ExtInfo { book_id, format },
+        }
+    }
+
+    /// Turns the [`App`] into one that clusters annotations.
+    pub fn into_cluster(self, options: ClusterOptions) -> App<ExtCluster> {
+        App {
+            config: self.config,
+            data: self.data,
+            sort_books: self.sort_books,
+            sort_annotations: self.sort_annotations,
+            stats: self.stats,
+            extension: ExtCluster { options },
+        }
+    }
+
+    /// Turns the [`App`] into one that selects annotations to review.
+    pub fn into_review(self, options: ReviewOptions) -> App<ExtReview> {
+        App {
+            config: self.config,
+            data: self.data,
+            sort_books: self.sort_books,
+            sort_annotations: self.sort_annotations,
+            stats: self.stats,
+            extension: ExtReview { options },
+        }
+    }
+
+    /// Turns the [`App`] into one that prints a reading log.
+    pub fn into_reading_log(self, options: ReadingLogOptions) -> App<ExtReadingLog> {
+        App {
+            config: self.config,
+            data: self.data,
+            sort_books: self.sort_books,
+            sort_annotations: self.sort_annotations,
+            stats: self.stats,
+            extension: ExtReadingLog { options },
+        }
+    }
+
+    /// Turns the [`App`] into one that builds the search index.
+    pub fn into_index(self) -> App<ExtIndex> {
+        App {
+            config: self.config,
+            data: self.data,
+            sort_books: self.sort_books,
+            sort_annotations: self.sort_annotations,
+            stats: self.stats,
+            extension: ExtIndex,
+        }
+    }
+
+    /// Turns the [`App`] into one that counts n-grams.
+    pub fn into_ngrams(self, options: NgramOptions) -> App<ExtNgrams> {
+        App {
+            config: self.config,
+            data: self.data,
+            sort_books: self.sort_books,
+            sort_annotations: self.sort_annotations,
+            stats: self.stats,
+            extension: ExtNgrams { options },
+        }
+    }
+
+    /// Turns the [`App`] into one that aggregates annotation positions.
+    pub fn into_heatmap(self, options: HeatmapOptions) -> App<ExtHeatmap> {
+        App {
+            config: self.config,
+            data: self.data,
+            sort_books: self.sort_books,
+            sort_annotations: self.sort_annotations,
+            stats: self.stats,
+            extension: ExtHeatmap { options },
+        }
+    }
+
+    /// Turns the [`App`] into one that syncs data to Notion.
+    pub fn into_sync(self, options: NotionOptions) -> App<ExtSync> {
+        App {
+            config: self.config,
+            data: self.data,
+            sort_books: self.sort_books,
+            sort_annotations: self.sort_annotations,
+            stats: self.stats,
+            extension: ExtSync { options },
+        }
+    }
+
+    /// Turns the [`App`] into one that syncs data to Hypothes.is.
+    pub fn into_hypothesis(self, options: HypothesisOptions) -> App<ExtHypothesis> {
+        App {
+            config: self.config,
+            data: self.data,
+            sort_books: self.sort_books,
+            sort_annotations: self.sort_annotations,
+            stats: self.stats,
+            extension: ExtHypothesis { options },
+        }
+    }
+
+    /// Turns the [`App`] into one that syncs data to a webhook.
+    pub fn into_webhook(self, options: WebhookOptions) -> App<ExtWebhook> {
+        App {
+            config: self.config,
+            data: self.data,
+            sort_books: self.sort_books,
+            sort_annotations: self.sort_annotations,
+            stats: self.stats,
+            extension: ExtWebhook { options },
+        }
+    }
+
+    /// Initializes the application's data.
+    fn init_data(&mut self) -> CliResult<()> {
+        match (&self.config.snapshot, &self.config.platform) {
+            (Some(path), _) => {
+                self.data
+                    .init_snapshot(path)
+                    .wrap_err("Failed while loading snapshot")?;
+            }
+            (None, Platform::MacOs) => {
+                self.data
+                    .init_macos(&self.config.data_directory)
+                    .wrap_err("Failed while initializing macOS's Apple Books databases data")?;
+            }
+            (None, Platform::IOs) => {
+                self.data
+                    .init_ios(&self.config.data_directory)
+                    .wrap_err("Failed while initializing iOS's Apple Books plists data")?;
+            }
+            (None, Platform::All) => {
+                self.data
+                    .init_macos(&self.config.data_directory)
+                    .wrap_err("Failed while initializing macOS's Apple Books databases data")?;
+
+                if let Some(directory) = &self.config.ios_data_directory {
+                    self.data
+                        .merge_ios(directory)
+                        .wrap_err("Failed while merging iOS's Apple Books plists data")?;
+                }
+            }
+        }
+
+        // Audiobooks are only stored in macOS's Apple Books databases.
+        if self.config.include_audiobooks
+            && self.config.snapshot.is_none()
+            && matches!(self.config.platform, Platform::MacOs | Platform::All)
+        {
+            self.data
+                .init_macos_audiobooks(&self.config.data_directory)
+                .wrap_err("Failed while initializing macOS's Apple Books audiobooks data")?;
+        }
+
+        if matches!(self.config.id_scheme, IdScheme::Hash) {
+            self.data.remap_ids(self.config.id_scheme.into());
+        }
+
+        if !self.config.include_unannotated {
+            self.data.discard_unannotated();
+        }
+
+        if let Some(directory) = &self.config.books_directory {
+            lib::epub::run(&mut self.data, directory);
+        }
+
+        if let Some(path) = &self.config.calibre_library {
+            let matched = lib::calibre::run(&mut self.data, path)
+                .wrap_err("Failed while reading Calibre library")?;
+
+            tracing::debug!(books = matched, path = %path.display(), "matched Calibre books");
+        }
+
+        Ok(())
+    }
+}
+
+/// Implementation of shared methods between different extention types.
+impl<Ext> App<Ext> {
+    /// Runs filters on all [`Entry`][entry]s.
+    ///
+    /// [entry]: lib::models::entry::Entry
+    pub fn run_filters(&mut self, filter_options: &FilterOptions) {
+        let start = Instant::now();
+        let books = self.data.count_books();
+        let annotations = self.data.count_annotations();
+
+        // TODO(feat): It might be good to clone `self.data` to allow for filter revisions.
+        for filter_type in filter_options.filters() {
+            lib::filter::run(filter_type, &mut self.data);
+        }
+
+        self.stats.books_filtered += books - self.data.count_books();
+        self.stats.annotations_filtered += annotations - self.data.count_annotations();
+        self.stats.record("filter", start);
+    }
+
+    /// Sets the order in which books and annotations are output and optionally limits the number
+    /// of books. Annotations are sorted after pre-processing. See [`App::run_preprocesses()`].
+    pub fn run_sort(&mut self, options: SortOptions) {
+        self.sort_books = options.sort_books.into();
+        self.sort_annotations = options.sort;
+
+        if let Some(limit) = options.limit {
+            self.data.truncate(self.sort_books, limit);
+        }
+    }
+
+    /// Runs pre-processes on all [`Entry`][entry]s.
+    ///
+    ///
+    /// Annotations are then sorted if an order was set. This runs after pre-processing as it sorts
+    /// annotations by their location first, which merging adjacent annotations relies on.
+    ///
+    /// [entry]: lib::models::entry::Entry
+    pub fn run_preprocesses(&mut self, options: &PreProcessOptions) -> CliResult<()> {
+        let start = Instant::now();
+
+        let mut lib_options: lib::process::pre::PreProcessOptions = options.clone().into();
+
+        if let Some(path) = &options.tag_map {
+            lib_options.tag_map =
+                Some(TagMap::from_path(path).wrap_err("Failed while reading tag map")?);
+        }
+
+        let stats = lib::process::pre::run(&mut self.data, lib_options);
+
+        for (tag, count) in &stats.tags_mapped {
+            self.print(format!("Mapped tag {tag} on {count} annotations"));
+        }
+
+        if options.extract_favorites {
+            self.print(format!(
+                "Marked {} annotations as favorites",
+                stats.favorites_marked
+            ));
+        }
+
+        if options.dedupe {
+            self.print(format!(
+                "Removed {} duplicate annotations",
+                stats.duplicates_removed
+            ));
+        }
+
+        if options.merge_adjacent {
+            self.print(format!(
+                "Merged {} adjacent annotations",
+                stats.adjacent_merged
+            ));
+        }
+
+        if options.link_mentions {
+            self.print(format!(
+                "Linked {} annotations to the books they mention",
+                stats.mentions_linked
+            ));
+        }
+
+        if let Some(key) = &self.sort_annotations {
+            for entry in self.data.values_mut() {
+                lib::process::sort_annotations_by(entry, key)
+                    .wrap_err("Failed while sorting annotations")?;
+            }
+        }
+
+        self.stats.record("preprocess", start);
+
+        Ok(())
+    }
+
+    /// Returns a [`RunSummary`] of the current data. The output is set to the output directory.
+    pub fn summary(&self) -> RunSummary {
+        RunSummary {
+            books: self.data.count_books(),
+            annotations: self.data.count_annotations(),
+            output: Some(self.config.output_directory.clone()),
+            stats: self.stats.clone(),
+        }
+    }
+
+    /// Returns the stats collected so far. See [`RunStats`].
+    pub fn stats(&self) -> RunStats {
+        self.stats.clone()
+    }
+
+    /// Prints to the terminal. Allows muting.
+    pub fn print<S>(&self, message: S)
+    where
+        S: AsRef<str>,
+    {
+        let message: &str = message.as_ref();
+
+        if !self.config.is_quiet {
+            println!("{message}");
+        }
+    }
+
+    /// Uploads a file or directory to a remote target.
+    ///
+    /// # Arguments
+    ///
+    /// * `target` - Where to upload to.
+    /// * `source` - The file or directory to upload.
+    pub fn upload(&self, target: &UploadTarget, source: &Path) -> CliResult<()> {
+        let uploaded = lib::upload::run(target, source)
+            .wrap_err(format!("Failed while uploading to {target}"))?;
+
+        self.print(format!("Uploaded {} files to {target}", uploaded.len()));
+
+        Ok(())
+    }
+
+    // TODO(0.7.0): Redesign this.
+    /// Prompts the user to confirm the filter results.
+    pub fn confirm_filter_results(&self) -> CliResult<bool> {
+        let indent = " ".repeat(3);
+        let line = "-".repeat(64);
+
+        println!("{indent}{line}");
+
+        let count_books = self.data.count_books();
+
+        if count_books == 0 {
+            println!("{indent}No annotations found.");
+            println!("{indent}{line}");
+            return Ok(false);
+        }
+
+        let count_annotations = self.data.count_annotations();
+
+        println!(
+            "{indent}Found {count_annotations} annotation{} from {count_books} book{}:",
+            if count_annotations == 1 { "" } else { "s" },
+            if count_books == 1 { "" } else { "s" },
+        );
+
+        for book in self.data.iter_books() {
+            println!("{indent} • {} by {}", book.title, book.author);
+        }
+
+        println!("{indent}{line}");
+
+        self::prompt_continue(&indent)
+    }
+}
+
+impl App<ExtRender> {
+    /// Renders templates, runs post-processes and writes the results to disk.
+    ///
+    /// Books are rendered and written one at a time. The progress is saved after each book so an
+    /// interrupted run can be resumed without re-rendering the books that were already completed.
+    ///
+    /// With `--sandbox`, everything is written into a temporary directory first which is only
+    /// synced into the output directory once the entire render succeeds.
+    pub fn render(&mut self, options: PostProcessOptions) -> CliResult<()> {
+        let output_directory = self.config.output_directory.clone();
+
+        std::fs::create_dir_all(&output_directory)?;
+
+        let start = Instant::now();
+
+        if !self.extension.sandbox {
+            if let Some(written) = self.render_into(&output_directory, None, options)? {
+                self.stats.files.merge(written);
+                self.stats.record("render", start);
+            }
+
+            return Ok(());
+        }
+
+        let sandbox = Sandbox::new(self.extension.renderer.requested_template_ids())
+            .wrap_err("Failed while creating render sandbox")?;
+
+        // The output directory is left untouched if the user declines to continue.
+        if self
+            .render_into(sandbox.path(), Some(&sandbox), options)?
+            .is_none()
+        {
+            return Ok(());
+        }
+
+        let summary = sandbox
+            .sync(&output_directory)
+            .wrap_err("Failed while syncing render sandbox")?;
+
+        // Files are always written into a fresh sandbox so only the sync tells what changed.
+        self.stats.files.merge(WriteSummary {
+            created: summary.added,
+            rewritten: summary.updated,
+            skipped: summary.unchanged,
+        });
+        self.stats.record("render", start);
+
+        self.print(format!(
+            "Synced {}: {} added, {} updated, {} deleted, {} unchanged",
+            output_directory.display(),
+            summary.added,
+            summary.updated,
+            summary.deleted,
+            summary.unchanged,
+        ));
+
+        Ok(())
+    }
+
+    /// Renders all books into a dated review pack along with an index file listing each book and
+    /// its rendered files. See [`lib::pack`] for more information.
+    ///
+    /// Returns the path to the pack's directory, or archive if `zip` is set, and its index.
+    ///
+    /// # Arguments
+    ///
+    /// * `since` - The start of the pack's period.
+    /// * `zip` - Whether to write the pack into a `zip` archive.
+    /// * `options` - The post-process options.
+    pub fn pack(
+        &mut self,
+        since: DateTime<Utc>,
+        zip: bool,
+        options: PostProcessOptions,
+    ) -> CliResult<(PathBuf, PackIndex)> {
+        let until = Utc::now();
+        let directory = self
+            .config
+            .output_directory
+            .join(lib::pack::name(&since, &until));
+
+        // Packs are named after their period so one made earlier on the same day is replaced.
+        if directory.exists() {
+            std::fs::remove_dir_all(&directory)?;
+        }
+
+        std::fs::create_dir_all(&directory)?;
+
+        let start = Instant::now();
+        let mut index = PackIndex::new(since, until);
+
+        for entry in self.data.sorted(self.sort_books) {
+            let renderer = &mut self.extension.renderer;
+
+            renderer
+                .render(entry)
+                .wrap_err("Failed while rendering template(s)")?;
+
+            lib::process::post::run(renderer.templates_rendered_mut().collect(), options);
+
+            self.stats.files.merge(
+                renderer
+                    .write(&directory)
+                    .wrap_err("Failed while writing template(s)")?,
+            );
+
+            index.books.push(PackBook {
+                title: entry.book.title.clone(),
+                author: entry.book.author.clone(),
+                annotations: entry.annotations.len(),
+                files: renderer
+                    .templates_rendered()
+                    .map(|render| render.path.join(&render.filename))
+                    .collect(),
+            });
+
+            renderer.clear_rendered();
+        }
+
+        index
+            .write(&directory)
+            .wrap_err("Failed while writing pack index")?;
+
+        self.stats.record("pack", start);
+
+        if !zip {
+            return Ok((directory, index));
+        }
+
+        let archive = directory.with_extension("zip");
+
+        lib::pack::zip::write_directory(&directory, &archive)
+            .wrap_err("Failed while archiving pack")?;
+
+        std::fs::remove_dir_all(&directory)?;
+
+        Ok((archive, index))
+    }
+
+    /// Renders templates, runs post-processes and writes the results into a directory.
+    ///
+    /// Returns how many files were written or skipped, or `None` if the user declines to
+    /// continue.
+    ///
+    /// # Arguments
+    ///
+    /// * `directory` - The directory to write into.
+    /// * `sandbox` - The sandbox `directory` belongs to, if any. Files that are appended to are
+    ///   copied into it from the output directory before they're written.
+    /// * `options` - The post-process options.
+    #[allow(clippy::too_many_lines)]
+    fn render_into(
+        &mut self,
+        directory: &Path,
+        sandbox: Option<&Sandbox>,
+        options: PostProcessOptions,
+    ) -> CliResult<Option<WriteSummary>> {
+        if self.extension.single_file {
+            return self.render_single_file(directory, options);
+        }
+
+        let output_directory = self.config.output_directory.clone();
+
+        let templates = self.extension.renderer.requested_template_ids();
+
+        let mut progress = if self.extension.resume {
+            RenderProgress::load(directory, templates)
+                .wrap_err("Failed while loading render progress")?
+        } else {
+            RenderProgress::new(directory, templates)
+        };
+
+        if progress.count_completed() > 0 {
+            self.print(format!(
+                "Resuming: skipped {} already rendered books",
+                progress.count_completed()
+            ));
+        }
+
+        // Without any limits, books are rendered one at a time as they're written.
+        let mut pending = if self.extension.limits.is_empty() {
+            None
+        } else {
+            let pending = self.render_pending(&progress)?;
+
+            if !self.confirm_limits(pending.count_files(), pending.size())? {
+                return Ok(None);
+            }
+
+            Some(pending)
+        };
+
+        let mut written = WriteSummary::default();
+        let mut reporter = Progress::new(
+            self.config.progress,
+            "render",
+            self.data.len() - progress.count_completed(),
+        );
+
+        for entry in self.data.sorted(self.sort_books) {
+            let id = &entry.book.metadata.id;
+
+            if progress.is_completed(id) {
+                continue;
+            }
+
+            let renderer = &mut self.extension.renderer;
+            let mut files = 0;
+
+            let mut write = |mut render: Render| {
+                files += 1;
+                lib::process::post::run(vec![&mut render], options);
+
+                if let Some(sandbox) = sandbox {
+                    sandbox.seed(&output_directory, [&render])?;
+                }
+
+                written.merge(renderer.write_render(directory, &render)?);
+                Ok(())
+            };
+
+            if let Some(renders) = pending
+                .as_mut()
+                .and_then(|pending| pending.books.remove(id))
+            {
+                renders
+                    .into_iter()
+                    .try_for_each(&mut write)
+                    .wrap_err("Failed while writing template(s)")?;
+            } else if self.extension.stream {
+                renderer
+                    .stream(entry, write)
+                    .wrap_err("Failed while rendering template(s)")?;
+            } else {
+                renderer
+                    .render(entry)
+                    .wrap_err("Failed while rendering template(s)")?;
+
+                lib::process::post::run(renderer.templates_rendered_mut().collect(), options);
+
+                if let Some(sandbox) = sandbox {
+                    sandbox
+                        .seed(&output_directory, renderer.templates_rendered())
+                        .wrap_err("Failed while seeding render sandbox")?;
+                }
+
+                written.merge(
+                    renderer
+                        .write(directory)
+                        .wrap_err("Failed while writing template(s)")?,
+                );
+
+                files = renderer.count_templates_rendered();
+
+                renderer.clear_rendered();
+            }
+
+            progress
+                .complete(id)
+                .wrap_err("Failed while saving render progress")?;
+
+            reporter.book(&entry.book.title, entry.annotations.len(), files);
+        }
+
+        // Tag templates span all books so they're rendered once every book has been. Bookmark
+        // templates are rendered alongside them as audiobooks aren't entries.
+        let renderer = &mut self.extension.renderer;
+        let entries = self.data.sorted(self.sort_books);
+        let audiobooks = self.data.audiobooks();
+        let mut files = 0;
+
+        let mut write = |mut render: Render| {
+            files += 1;
+            lib::process::post::run(vec![&mut render], options);
+            written.merge(renderer.write_render(directory, &render)?);
+            Ok(())
+        };
+
+        if let Some(pending) = pending {
+            pending
+                .rest
+                .into_iter()
+                .try_for_each(&mut write)
+                .wrap_err("Failed while writing template(s)")?;
+        } else if self.extension.stream {
+            renderer
+                .stream_tags(entries, &mut write)
+                .wrap_err("Failed while rendering template(s)")?;
+
+            renderer
+                .stream_bookmarks(audiobooks, &mut write)
+                .wrap_err("Failed while rendering template(s)")?;
+        } else {
+            renderer
+                .render_tags(entries)
+                .wrap_err("Failed while rendering template(s)")?;
+
+            renderer
+                .render_bookmarks(audiobooks)
+                .wrap_err("Failed while rendering template(s)")?;
+
+            lib::process::post::run(renderer.templates_rendered_mut().collect(), options);
+
+            written.merge(
+                renderer
+                    .write(directory)
+                    .wrap_err("Failed while writing template(s)")?,
+            );
+
+            files = renderer.count_templates_rendered();
+
+            renderer.clear_rendered();
+        }
+
+        reporter.files(files);
+        reporter.finish();
+
+        progress
+            .finish()
+            .wrap_err("Failed while removing render progress")?;
+
+        Ok(Some(written))
+    }
+
+    /// Renders all books into a single file per template, runs post-processes and writes the
+    /// results to disk.
+    ///
+    /// Unlike [`App::render()`], all books are held in memory until they're combined and written.
+    ///
+    /// Returns how many files were written or skipped, or `None` if the user declines to
+    /// continue.
+    ///
+    /// # Arguments
+    ///
+    /// * `directory` - The directory to write into.
+    /// * `options` - The post-process options.
+    fn render_single_file(
+        &mut self,
+        directory: &Path,
+        options: PostProcessOptions,
+    ) -> CliResult<Option<WriteSummary>> {
+        let renderer = &mut self.extension.renderer;
+        let mut reporter = Progress::new(self.config.progress, "render", self.data.len());
+
+        for entry in self.data.sorted(self.sort_books) {
+            renderer
+                .render(entry)
+                .wrap_err("Failed while rendering template(s)")?;
+
+            reporter.book(&entry.book.title, entry.annotations.len(), 0);
+        }
+
+        renderer.combine();
+
+        let files = renderer.count_templates_rendered();
+        let size = renderer
+            .templates_rendered()
+            .map(|render| render.contents.len() as u64)
+            .sum();
+
+        if !self.confirm_limits(files, size)? {
+            return Ok(None);
+        }
+
+        let renderer = &mut self.extension.renderer;
+
+        lib::process::post::run(renderer.templates_rendered_mut().collect(), options);
+
+        let written = renderer
+            .write(directory)
+            .wrap_err("Failed while writing template(s)")?;
+
+        reporter.files(files);
+        reporter.finish();
+
+        Ok(Some(written))
+    }
+
+    /// Renders all remaining books, without writing them, so the render's output can be checked
+    /// against the `--max-files` and `--max-total-size` limits. The renders are kept so they're
+    /// written without being rendered again. See [`App::confirm_limits()`] for more information.
+    ///
+    /// # Arguments
+    ///
+    /// * `progress` - The render's progress. Completed books are skipped.
+    fn render_pending(&self, progress: &RenderProgress) -> CliResult<PendingRenders> {
+        let mut pending = PendingRenders::default();
+        let renderer = &self.extension.renderer;
+
+        for entry in self.data.sorted(self.sort_books) {
+            let id = &entry.book.metadata.id;
+
+            if progress.is_completed(id) {
+                continue;
+            }
+
+            let mut renders = Vec::new();
+
+            renderer
+                .stream(entry, |render| {
+                    renders.push(render);
+                    Ok(())
+                })
+                .wrap_err("Failed while rendering template(s)")?;
+
+            pending.books.insert(id.clone(), renders);
+        }
+
+        let mut keep = |render| {
+            pending.rest.push(render);
+            Ok(())
+        };
+
+        renderer
+            .stream_tags(self.data.sorted(self.sort_books), &mut keep)
+            .wrap_err("Failed while rendering template(s)")?;
+
+        renderer
+            .stream_bookmarks(self.data.audiobooks(), &mut keep)
+            .wrap_err("Failed while rendering template(s)")?;
+
+        Ok(pending)
+    }
+
+    /// Checks the render's output against the `--max-files` and `--max-total-size` limits. If a
+    /// limit is exceeded, the user is prompted to continue. When not run from a terminal, e.g.
+    /// when scheduled, the render is aborted instead.
+    ///
+    /// Returns `false` if the user declines to continue.
+    ///
+    /// # Arguments
+    ///
+    /// * `files` - The number of files to write.
+    /// * `size` - The total size of all files to write in bytes.
+    fn confirm_limits(&self, files: usize, size: u64) -> CliResult<bool> {
+        let exceeded = self.extension.limits.exceeded(files, size);
+
+        if exceeded.is_empty() {
+            return Ok(true);
+        }
+
+        if !std::io::stdin().is_terminal() {
+            bail!("Render output is too large: {}", exceeded.join(", "));
+        }
+
+        let indent = " ".repeat(3);
+        let line = "-".repeat(64);
+
+        println!("{indent}{line}");
+        println!("{indent}Render output is larger than expected:");
+
+        for message in exceeded {
+            println!("{indent} • {message}");
+        }
+
+        println!("{indent}{line}");
+
+        self::prompt_continue(&indent)
+    }
+}
+
+/// A struct representing renders kept from checking the output limits. See
+/// [`App::render_pending()`].
+#[derive(Debug, Default)]
+struct PendingRenders {
+    /// The renders of each book keyed by the book's id.
+    books: HashMap<String, Vec<Render>>,
+
+    /// The renders spanning all books e.g. tags and bookmarks.
+    rest: Vec<Render>,
+}
+
+impl PendingRenders {
+    /// Returns the number of files to write.
+    fn count_files(&self) -> usize {
+        self.books.values().map(Vec::len).sum::<usize>() + self.rest.len()
+    }
+
+    /// Returns the total size of all files to write in bytes.
+    fn size(&self) -> u64 {
+        self.books
+            .values()
+            .flatten()
+            .chain(&self.rest)
+            .map(|render| render.contents.len() as u64)
+            .sum()
+    }
+}
+
+impl App<ExtExport> {
+    /// Exports data to disk and returns the path to the export.
+    pub fn export(&mut self) -> CliResult<PathBuf> {
+        let options = lib::export::ExportOptions {
+            sort_books: self.sort_books,
+            // FIXME: Avoid clone?
+            ..self.extension.options.clone().into()
+        };
+
+        let start = Instant::now();
+
+        // Exporters write all books at once so progress is only reported once they're done.
+        let mut reporter = Progress::new(self.config.progress, "export", self.data.len());
+
+        lib::export::run(&mut self.data, &self.config.output_directory, options)
+            .wrap_err("Failed while exporting data")?;
+
+        for entry in self.data.sorted(self.sort_books) {
+            reporter.book(&entry.book.title, entry.annotations.len(), 0);
+        }
+
+        reporter.finish();
+
+        self.stats.record("export", start);
+
+        Ok(self.config.output_directory.clone())
+    }
+}
+
+impl App<ExtBackup> {
+    /// Backs-up source data to disk and returns the path to the back-up.
+    pub fn backup(&mut self) -> CliResult<PathBuf> {
+        let start = Instant::now();
+        let path = lib::backup::run(
+            self.config.platform.try_into()?,
+            &self.config.data_directory,
+            &self.config.output_directory,
+            self.extension.options.clone(),
+            // FIXME: Avoid clone? ^^^^^^^
+        )?;
+
+        self.stats.record("backup", start);
 
-    /// The application's capability extension.
-    extension: Ext,
+        Ok(path)
+    }
 }
 
-impl App<ExtNone> {
-    /// Creates a new instance of [`App`].
-    pub fn new(config: Config) -> CliResult<Self> {
-        let mut app = Self {
-            config,
-            data: Data::default(),
-            extension: ExtNone,
-        };
+impl App<ExtSnapshot> {
+    /// Writes a snapshot of the data to the output directory. Returns the path to the snapshot.
+    pub fn snapshot(&mut self) -> CliResult<PathBuf> {
+        let start = Instant::now();
+        let platform = self
+            .config
+            .platform
+            .to_possible_value()
+            .map(|value| value.get_name().to_owned())
+            .unwrap_or_default();
 
-        app.init_data()?;
+        let snapshot = Snapshot::new(&self.data, &platform);
+        let path = self.config.output_directory.join(snapshot.filename());
 
-        Ok(app)
-    }
+        std::fs::create_dir_all(&self.config.output_directory)?;
 
-    /// Turns the [`App`] into one that renders templates.
-    pub fn into_render(self, options: RenderOptions) -> CliResult<App<ExtRender>> {
-        let mut renderer = Renderer::new(options, super::defaults::TEMPLATE.into());
+        snapshot
+            .write(&path)
+            .wrap_err("Failed while writing snapshot")?;
 
-        renderer
-            .init()
-            .wrap_err("Failed while initializing template(s)")?;
+        self.stats.record("snapshot", start);
 
-        Ok(App {
-            config: self.config,
-            data: self.data,
-            extension: ExtRender { renderer },
-        })
+        Ok(path)
     }
+}
 
-    /// Turns the [`App`] into one that exports data.
-    pub fn into_export(self, options: ExportOptions) -> App<ExtExport> {
-        App {
-            config: self.config,
-            data: self.data,
-            extension: ExtExport { options },
+impl App<ExtList> {
+    /// Prints a table of books/annotations to the terminal.
+    ///
+    /// Unlike [`App::print()`], this is not affected by the quiet flag as the table is the
+    /// command's output.
+    pub fn list(&self) {
+        let table = self.table();
+
+        if table.is_empty() {
+            match self.extension.items {
+                ListItems::Books => println!("No books found."),
+                ListItems::Annotations => println!("No annotations found."),
+            }
+            return;
         }
-    }
 
-    /// Turns the [`App`] into one that backs-up data.
-    pub fn into_backup(self, options: BackupOptions) -> App<ExtBackup> {
-        App {
-            config: self.config,
-            data: self.data,
-            extension: ExtBackup { options },
-        }
+        print!("{table}");
     }
 
-    /// Initializes the application's data.
-    fn init_data(&mut self) -> CliResult<()> {
-        match &self.config.platform {
-            Platform::MacOs => {
-                self.data
-                    .init_macos(&self.config.data_directory)
-                    .wrap_err("Failed while initializing macOS's Apple Books databases data")?;
+    /// Builds a table of books/annotations.
+    fn table(&self) -> Table {
+        let mut entries: Vec<_> = self.data.values().collect();
+        entries.sort_by(|a, b| a.book.title.cmp(&b.book.title));
+
+        match self.extension.items {
+            ListItems::Books => {
+                let mut table = Table::new(["Title", "Author", "Annotations", "Last Opened"]);
+
+                for entry in entries {
+                    let last_opened = entry
+                        .book
+                        .metadata
+                        .last_opened
+                        .as_ref()
+                        .map_or_else(String::new, |date| {
+                            date.format(lib::defaults::DATE_FORMAT_TEMPLATE).to_string()
+                        });
+
+                    table.add_row([
+                        entry.book.title.clone(),
+                        entry.book.author.clone(),
+                        entry.annotations.len().to_string(),
+                        last_opened,
+                    ]);
+                }
+
+                table
             }
-            Platform::IOs => {
-                self.data
-                    .init_ios(&self.config.data_directory)
-                    .wrap_err("Failed while initializing iOS's Apple Books plists data")?;
+            ListItems::Annotations => {
+                let mut table = Table::new(["Title", "Created", "Style", "Body"]);
+
+                for entry in entries {
+                    let mut annotations: Vec<_> = entry.annotations.iter().collect();
+                    annotations.sort();
+
+                    for annotation in annotations {
+                        table.add_row([
+                            entry.book.title.clone(),
+                            annotation
+                                .metadata
+                                .created
+                                .format(lib::defaults::DATE_FORMAT_TEMPLATE)
+                                .to_string(),
+                            format!("{:?}", annotation.style).to_lowercase(),
+                            annotation.body.clone(),
+                        ]);
+                    }
+                }
+
+                table
             }
         }
+    }
+}
+
+impl App<ExtInfo> {
+    /// Prints a book's metadata, stats and annotations to the terminal.
+    ///
+    /// Unlike [`App::print()`], this is not affected by the quiet flag as the book is the
+    /// command's output.
+    pub fn info(&self) -> CliResult<()> {
+        let entry = lib::info::find(&self.data, &self.extension.book_id)?;
+        let info = lib::info::BookInfo::from(entry);
+
+        match self.extension.format {
+            InfoFormat::Text => print!("{}", lib::info::to_text(&info)),
+            InfoFormat::Json => println!("{}", serde_json::to_string_pretty(&info)?),
+        }
 
         Ok(())
     }
 }
 
-/// Implementation of shared methods between different extention types.
-impl<Ext> App<Ext> {
-    /// Runs filters on all [`Entry`][entry]s.
+impl App<ExtCluster> {
+    /// Clusters similar annotations, writes the clusters to disk and returns the number of
+    /// clusters and the path to the file.
+    pub fn cluster(&mut self) -> CliResult<(usize, PathBuf)> {
+        let start = Instant::now();
+        let clusters = lib::analysis::cluster::run(
+            self.data.sorted(self.sort_books),
+            self.extension.options.into(),
+        );
+
+        std::fs::create_dir_all(&self.config.output_directory)?;
+
+        let path = self.config.output_directory.join(CLUSTERS_FILENAME);
+        let file = std::fs::File::create(&path)?;
+
+        serde_json::to_writer_pretty(std::io::BufWriter::new(file), &clusters)
+            .wrap_err("Failed while writing clusters")?;
+
+        self.stats.record("cluster", start);
+
+        Ok((clusters.len(), path))
+    }
+}
+
+impl App<ExtReview> {
+    /// Prints the annotations selected for review to the terminal, rendered with a template if
+    /// one is set.
     ///
-    /// [entry]: lib::models::entry::Entry
-    pub fn run_filters(&mut self, filter_options: &FilterOptions) {
-        // TODO(feat): It might be good to clone `self.data` to allow for filter revisions.
-        for filter_type in &filter_options.filter_types {
-            // TODO(refactor): Can we qvoid this clone?
-            lib::filter::run(filter_type.clone(), &mut self.data);
+    /// Unlike [`App::print()`], this is not affected by the quiet flag as the review is the
+    /// command's output.
+    pub fn review(&mut self) -> CliResult<()> {
+        let start = Instant::now();
+
+        let options: lib::review::ReviewOptions = self.extension.options.clone().into();
+        let items = lib::review::run(self.data.sorted(self.sort_books), &options);
+
+        if let Some(path) = &self.extension.options.template {
+            let template = std::fs::read_to_string(path)
+                .wrap_err_with(|| format!("Failed while reading template: {}", path.display()))?;
+
+            let context = ReviewContext::new(options.date, &items);
+            let string = RenderEngine::default()
+                .render_str(&template, context)
+                .wrap_err("Failed while rendering review")?;
+
+            print!("{string}");
+        } else if items.is_empty() {
+            println!("No annotations to review.");
+        } else {
+            for item in &items {
+                println!("\"{}\"", item.annotation.body);
+                println!("  — {} by {}", item.book.title, item.book.author);
+
+                if !item.annotation.notes.is_empty() {
+                    println!("  {}", item.annotation.notes);
+                }
+
+                println!();
+            }
         }
+
+        self.stats.record("review", start);
+
+        Ok(())
     }
+}
 
-    /// Runs pre-processes on all [`Entry`][entry]s.
+impl App<ExtReadingLog> {
+    /// Prints the reading log to the terminal, rendered with a template if one is set.
     ///
-    /// [entry]: lib::models::entry::Entry
-    pub fn run_preprocesses(&mut self, options: PreProcessOptions) {
-        lib::process::pre::run(&mut self.data, options);
-    }
+    /// Unlike [`App::print()`], this is not affected by the quiet flag as the reading log is the
+    /// command's output.
+    pub fn reading_log(&mut self) -> CliResult<()> {
+        let start = Instant::now();
+
+        let log = lib::reading_log::run(&self.data);
+
+        if let Some(path) = &self.extension.options.template {
+            let template = std::fs::read_to_string(path)
+                .wrap_err_with(|| format!("Failed while reading template: {}", path.display()))?;
+
+            let context = ReadingLogContext::from(&log);
+            let string = RenderEngine::default()
+                .render_str(&template, context)
+                .wrap_err("Failed while rendering reading log")?;
+
+            print!("{string}");
+        } else if log.is_empty() {
+            println!("No finished or partially read books.");
+        } else {
+            for year in &log.years {
+                println!("{} ({} finished)", year.year, year.len());
+
+                for month in &year.months {
+                    for book in &month.books {
+                        let date = book
+                            .metadata
+                            .date_finished
+                            .map(|date| date.format("%Y-%m-%d").to_string())
+                            .unwrap_or_default();
+
+                        println!("  {date}  {} by {}", book.title, book.author);
+                    }
+                }
 
-    /// Prints to the terminal. Allows muting.
-    pub fn print<S>(&self, message: S)
-    where
-        S: AsRef<str>,
-    {
-        let message: &str = message.as_ref();
+                println!();
+            }
 
-        if !self.config.is_quiet {
-            println!("{message}");
+            if !log.undated.is_empty() {
+                println!("Finished, undated ({})", log.undated.len());
+
+                for book in &log.undated {
+                    println!("  {} by {}", book.title, book.author);
+                }
+
+                println!();
+            }
+
+            if !log.in_progress.is_empty() {
+                println!("In progress ({})", log.in_progress.len());
+
+                for book in &log.in_progress {
+                    let progress = book.metadata.progress.unwrap_or_default() * 100.0;
+                    println!("  {progress:>5.1}%  {} by {}", book.title, book.author);
+                }
+
+                println!();
+            }
         }
+
+        self.stats.record("reading-log", start);
+
+        Ok(())
     }
+}
 
-    // TODO(0.7.0): Redesign this.
-    /// Prompts the user to confirm the filter results.
-    pub fn confirm_filter_results(&self) -> bool {
-        let indent = " ".repeat(3);
-        let line = "-".repeat(64);
+impl App<ExtIndex> {
+    /// Builds the search index from scratch, writes it to disk and returns the number of indexed
+    /// annotations and the path to the index.
+    pub fn build_index(&mut self) -> CliResult<(usize, PathBuf)> {
+        let start = Instant::now();
 
-        println!("{indent}{line}");
+        let index = SearchIndex::build(self.data.sorted(self.sort_books));
 
-        let count_books = self.data.count_books();
+        let path = self.config.output_directory.join(INDEX_FILENAME);
 
-        if count_books == 0 {
-            println!("{indent}No annotations found.");
-            println!("{indent}{line}");
-            return false;
-        }
+        index
+            .write(&path)
+            .wrap_err("Failed while writing search index")?;
 
-        let count_annotations = self.data.count_annotations();
+        self.stats.record("index", start);
 
-        println!(
-            "{indent}Found {count_annotations} annotation{} from {count_books} book{}:",
-            if count_annotations == 1 { "" } else { "s" },
-            if count_books == 1 { "" } else { "s" },
-        );
+        Ok((index.len(), path))
+    }
 
-        for book in self.data.iter_books() {
-            println!("{indent} • {} by {}", book.title, book.author);
-        }
+    /// Updates the search index, building it if it doesn't exist yet, writes it to disk and returns
+    /// the changes made and the path to the index.
+    pub fn update_index(&mut self) -> CliResult<(IndexUpdate, PathBuf)> {
+        let start = Instant::now();
 
-        println!("{indent}{line}");
+        let path = self.config.output_directory.join(INDEX_FILENAME);
 
-        print!("{indent}Continue? [y/N]: ");
+        let mut index = if path.exists() {
+            SearchIndex::read(&path).wrap_err("Failed while reading search index")?
+        } else {
+            SearchIndex::build([])
+        };
+
+        let update = index.update(self.data.sorted(self.sort_books));
 
-        let mut confirm = String::new();
-        std::io::stdout().flush().unwrap();
-        std::io::stdin().read_line(&mut confirm).unwrap();
+        index
+            .write(&path)
+            .wrap_err("Failed while writing search index")?;
 
-        println!();
+        self.stats.record("index", start);
 
-        matches!(confirm.trim().to_lowercase().as_str(), "y" | "yes")
+        Ok((update, path))
     }
 }
 
-impl App<ExtRender> {
-    /// Renders templates.
-    pub fn render(&mut self) -> CliResult<()> {
-        self.data.values_mut().try_for_each(|entry| {
-            self.extension
-                .renderer
-                .render(entry)
-                .wrap_err("Failed while rendering template(s)")
-        })
+impl App<ExtNgrams> {
+    /// Prints the most frequent n-grams across all annotations to the terminal.
+    ///
+    /// Unlike [`App::print()`], this is not affected by the quiet flag as the n-grams are the
+    /// command's output.
+    pub fn ngrams(&self) {
+        let ngrams = lib::analysis::ngrams::run(
+            self.data.sorted(self.sort_books),
+            self.extension.options.into(),
+        );
+
+        match self.extension.options.format {
+            NgramFormat::Table => {
+                if ngrams.is_empty() {
+                    println!("No n-grams found.");
+                    return;
+                }
+
+                let mut table = Table::new(["N-gram", "Count", "Books"]);
+
+                for ngram in ngrams {
+                    table.add_row([ngram.text, ngram.count.to_string(), ngram.books.to_string()]);
+                }
+
+                print!("{table}");
+            }
+            NgramFormat::Csv => print!("{}", lib::analysis::ngrams::to_csv(&ngrams)),
+        }
     }
+}
+
+impl App<ExtHeatmap> {
+    /// Aggregates where in each book annotations were made, writes the heatmaps to disk and
+    /// returns the number of books and the path to the file.
+    pub fn heatmap(&mut self) -> CliResult<(usize, PathBuf)> {
+        let start = Instant::now();
+
+        let Some(directory) = &self.config.books_directory else {
+            bail!("No Apple Books EPUBs found to locate annotations in");
+        };
+
+        let heatmaps = lib::analysis::heatmap::run(
+            self.data.sorted(self.sort_books),
+            &mut EpubCache::new(directory),
+            self.extension.options.into(),
+        );
 
-    /// Writes templates to disk.
-    pub fn write(&self) -> CliResult<()> {
         std::fs::create_dir_all(&self.config.output_directory)?;
 
-        self.extension
-            .renderer
-            .write(&self.config.output_directory)
-            .wrap_err("Failed while writing template(s)")
+        let path = self.config.output_directory.join(HEATMAP_FILENAME);
+        let file = std::fs::File::create(&path)?;
+
+        serde_json::to_writer_pretty(std::io::BufWriter::new(file), &heatmaps)
+            .wrap_err("Failed while writing heatmaps")?;
+
+        self.stats.record("heatmap", start);
+
+        Ok((heatmaps.len(), path))
     }
+}
 
-    /// Runs post-processes on all [`Render`][render]s.
-    ///
-    /// [render]: lib::render::template::Render
-    pub fn run_postprocesses(&mut self, options: PostProcessOptions) {
-        lib::process::post::run(
-            self.extension.renderer.templates_rendered_mut().collect(),
-            options,
-        );
+impl App<ExtSync> {
+    /// Syncs books and annotations to a Notion database and returns the sync's stats.
+    pub fn sync(&mut self) -> CliResult<notion::SyncStats> {
+        let start = Instant::now();
+
+        let stats = notion::run(
+            self.data.sorted(self.sort_books),
+            &self.config.output_directory,
+            self.extension.options.clone(),
+        )
+        .wrap_err("Failed while syncing to Notion")?;
+
+        self.stats.record("sync", start);
+
+        Ok(stats)
     }
 }
 
-impl App<ExtExport> {
-    /// Exports data to disk.
-    pub fn export(&mut self) -> CliResult<()> {
-        lib::export::run(
-            &mut self.data,
+impl App<ExtHypothesis> {
+    /// Syncs annotations to Hypothes.is and returns the sync's stats.
+    pub fn sync(&mut self) -> CliResult<hypothesis::SyncStats> {
+        let start = Instant::now();
+
+        let stats = hypothesis::run(
+            self.data.sorted(self.sort_books),
             &self.config.output_directory,
             self.extension.options.clone(),
-            // FIXME: Avoid clone? ^^^^^^^
         )
-        .wrap_err("Failed while exporting data")?;
+        .wrap_err("Failed while syncing to Hypothes.is")?;
 
-        Ok(())
+        self.stats.record("sync", start);
+
+        Ok(stats)
     }
 }
 
-impl App<ExtBackup> {
-    /// Backs-up source data to disk.
-    pub fn backup(&self) -> CliResult<()> {
-        lib::backup::run(
-            self.config.platform,
-            &self.config.data_directory,
+impl App<ExtWebhook> {
+    /// Sends new annotations to a webhook and returns the sync's stats.
+    pub fn sync(&mut self) -> CliResult<webhook::SyncStats> {
+        let start = Instant::now();
+
+        let stats = webhook::run(
+            self.data.sorted(self.sort_books),
             &self.config.output_directory,
             self.extension.options.clone(),
-            // FIXME: Avoid clone? ^^^^^^^
-        )?;
+        )
+        .wrap_err("Failed while syncing to webhook")?;
 
-        Ok(())
+        self.stats.record("sync", start);
+
+        Ok(stats)
     }
 }
 
+/// Prompts the user to continue and returns `true` if they answer yes.
+///
+/// # Arguments
+///
+/// * `indent` - The indent to print the prompt with.
+///
+/// # Errors
+///
+/// Will return `Err` if the prompt cannot be printed or the answer cannot be read.
+fn prompt_continue(indent: &str) -> CliResult<bool> {
+    print!("{indent}Continue? [y/N]: ");
+
+    let mut confirm = String::new();
+    std::io::stdout().flush()?;
+    std::io::stdin().read_line(&mut confirm)?;
+
+    println!();
+
+    Ok(matches!(
+        confirm.trim().to_lowercase().as_str(),
+        "y" | "yes"
+    ))
+}
+
 #[cfg(test)]
 mod test {
 
@@ -279,6 +1610,17 @@ mod test {
             assert_eq!(app.data.iter_annotations().count(), 0);
         }
 
+        // Tests that un-annotated books are kept when requested.
+        #[test]
+        fn test_books_new_include_unannotated() {
+            let mut config = TestConfig::macos_new();
+            config.include_unannotated = true;
+            let app = App::new(config).unwrap();
+
+            assert_eq!(app.data.iter_books().count(), 3);
+            assert_eq!(app.data.iter_annotations().count(), 0);
+        }
+
         // Tests that annotated books return non-zero books and non-zero annotations.
         #[test]
         fn test_books_annotated() {
@@ -296,7 +1638,7 @@ mod test {
             let mut app = App::new(config).unwrap();
 
             // The pre-processor sorts the annotations.
-            app.run_preprocesses(PreProcessOptions::default());
+            app.run_preprocesses(&PreProcessOptions::default()).unwrap();
 
             for entry in app.data.values() {
                 for annotations in entry.annotations.windows(2) {
@@ -332,6 +1674,17 @@ mod test {
             assert_eq!(app.data.iter_annotations().count(), 0);
         }
 
+        // Tests that un-annotated books are kept when requested.
+        #[test]
+        fn test_books_new_include_unannotated() {
+            let mut config = TestConfig::ios_new();
+            config.include_unannotated = true;
+            let app = App::new(config).unwrap();
+
+            assert_eq!(app.data.iter_books().count(), 3);
+            assert_eq!(app.data.iter_annotations().count(), 0);
+        }
+
         // Tests that annotated books return non-zero books and non-zero annotations.
         #[test]
         fn test_books_annotated() {
@@ -349,7 +1702,7 @@ mod test {
             let mut app = App::new(config).unwrap();
 
             // The pre-processor sorts the annotations.
-            app.run_preprocesses(PreProcessOptions::default());
+            app.run_preprocesses(&PreProcessOptions::default()).unwrap();
 
             for entry in app.data.values() {
                 for annotations in entry.annotations.windows(2) {
@@ -384,6 +1737,7 @@ mod test {
             let filter_options = FilterOptions {
                 filter_types: vec![filter],
                 auto_confirm: true,
+                ..Default::default()
             };
 
             app.run_filters(&filter_options);
@@ -410,6 +1764,7 @@ mod test {
             let filter_options = FilterOptions {
                 filter_types: vec![filter],
                 auto_confirm: true,
+                ..Default::default()
             };
 
             app.run_filters(&filter_options);
@@ -436,6 +1791,7 @@ mod test {
             let filter_options = FilterOptions {
                 filter_types: vec![filter],
                 auto_confirm: true,
+                ..Default::default()
             };
 
             app.run_filters(&filter_options);
@@ -462,6 +1818,7 @@ mod test {
             let filter_options = FilterOptions {
                 filter_types: vec![filter],
                 auto_confirm: true,
+                ..Default::default()
             };
 
             app.run_filters(&filter_options);
@@ -488,6 +1845,7 @@ mod test {
             let filter_options = FilterOptions {
                 filter_types: vec![filter],
                 auto_confirm: true,
+                ..Default::default()
             };
 
             app.run_filters(&filter_options);
@@ -514,6 +1872,7 @@ mod test {
             let filter_options = FilterOptions {
                 filter_types: vec![filter],
                 auto_confirm: true,
+                ..Default::default()
             };
 
             app.run_filters(&filter_options);
@@ -540,13 +1899,15 @@ mod test {
             let filter_options = FilterOptions {
                 filter_types: vec![filter],
                 auto_confirm: true,
+                ..Default::default()
             };
 
             // The pre-processor extracts the tags.
-            app.run_preprocesses(PreProcessOptions {
+            app.run_preprocesses(&PreProcessOptions {
                 extract_tags: true,
                 ..Default::default()
-            });
+            })
+            .unwrap();
 
             app.run_filters(&filter_options);
 
@@ -572,13 +1933,15 @@ mod test {
             let filter_options = FilterOptions {
                 filter_types: vec![filter],
                 auto_confirm: true,
+                ..Default::default()
             };
 
             // The pre-processor extracts the tags.
-            app.run_preprocesses(PreProcessOptions {
+            app.run_preprocesses(&PreProcessOptions {
                 extract_tags: true,
                 ..Default::default()
-            });
+            })
+            .unwrap();
 
             app.run_filters(&filter_options);
 
@@ -604,13 +1967,15 @@ mod test {
             let filter_options = FilterOptions {
                 filter_types: vec![filter],
                 auto_confirm: true,
+                ..Default::default()
             };
 
             // The pre-processor extracts the tags.
-            app.run_preprocesses(PreProcessOptions {
+            app.run_preprocesses(&PreProcessOptions {
                 extract_tags: true,
                 ..Default::default()
-            });
+            })
+            .unwrap();
 
             app.run_filters(&filter_options);
 
@@ -618,4 +1983,108 @@ mod test {
             assert_eq!(app.data.iter_annotations().count(), 1);
         }
     }
+
+    // Tests dealing with listing books/annotations.
+    mod list {
+
+        use super::*;
+
+        /// Returns the number of rows in the table, excluding the header and separator.
+        fn count_rows(app: &App<ExtList>) -> usize {
+            app.table().to_string().lines().count() - 2
+        }
+
+        // Tests that there is a row for each book.
+        #[test]
+        fn test_books() {
+            let config = TestConfig::macos_annotated();
+            let app = App::new(config).unwrap().into_list(ListItems::Books);
+
+            assert_eq!(count_rows(&app), 3);
+        }
+
+        // Tests that un-annotated books are listed when requested.
+        #[test]
+        fn test_books_include_unannotated() {
+            let mut config = TestConfig::ios_new();
+            config.include_unannotated = true;
+            let app = App::new(config).unwrap().into_list(ListItems::Books);
+
+            assert_eq!(count_rows(&app), 3);
+        }
+
+        // Tests that there is a row for each annotation.
+        #[test]
+        fn test_annotations() {
+            let config = TestConfig::macos_annotated();
+            let app = App::new(config).unwrap().into_list(ListItems::Annotations);
+
+            assert_eq!(count_rows(&app), 10);
+        }
+    }
+
+    mod sort {
+
+        use super::*;
+
+        use crate::cli::args::SortBooks;
+
+        // Tests that the books kept are the ones furthest along.
+        #[test]
+        fn test_limit_progress() {
+            let config = TestConfig::macos_annotated();
+            let mut app = App::new(config)
+                .unwrap()
+                .into_export(ExportOptions::default());
+
+            app.run_sort(SortOptions {
+                sort_books: SortBooks::Progress,
+                limit: Some(1),
+                ..Default::default()
+            });
+
+            let titles: Vec<&str> = app
+                .data
+                .iter_books()
+                .map(|book| book.title.as_str())
+                .collect();
+
+            assert_eq!(titles, ["\"Surely You're Joking, Mr. Feynman!\""]);
+        }
+
+        // Tests that all books are kept if no limit is set.
+        #[test]
+        fn test_no_limit() {
+            let config = TestConfig::macos_annotated();
+            let mut app = App::new(config)
+                .unwrap()
+                .into_export(ExportOptions::default());
+
+            app.run_sort(SortOptions::default());
+
+            assert_eq!(app.data.len(), 3);
+        }
+
+        // Tests that annotations are sorted after pre-processing.
+        #[test]
+        fn test_sort_annotations_created() {
+            let config = TestConfig::macos_annotated();
+            let mut app = App::new(config)
+                .unwrap()
+                .into_export(ExportOptions::default());
+
+            app.run_sort(SortOptions {
+                sort: Some(SortKey::Created),
+                ..Default::default()
+            });
+            app.run_preprocesses(&PreProcessOptions::default()).unwrap();
+
+            for entry in app.data.values() {
+                assert!(entry
+                    .annotations
+                    .windows(2)
+                    .all(|pair| { *pair[0].metadata.created <= *pair[1].metadata.created }));
+            }
+        }
+    }
 }
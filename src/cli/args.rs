@@ -1,8 +1,10 @@
 use std::path::PathBuf;
 
+use chrono::{DateTime, Utc};
 use clap::builder::styling::AnsiColor;
 use clap::builder::Styles;
-use clap::{Parser, Subcommand, ValueEnum};
+use clap::{ArgGroup, Parser, Subcommand, ValueEnum};
+use serde::Deserialize;
 
 #[derive(Debug, Parser)]
 #[command(
@@ -10,12 +12,18 @@ use clap::{Parser, Subcommand, ValueEnum};
     version,
     about,
     disable_help_subcommand = true,
+    args_conflicts_with_subcommands = true,
+    arg_required_else_help = true,
     after_help = "See the documentation for more information: https://tnahs.github.io/readstor",
     styles = styles(),
 )]
 pub struct Args {
+    /// Read a command from stdin as JSON and write its result to stdout as JSON
+    #[arg(long)]
+    pub shortcut_json: bool,
+
     #[clap(subcommand)]
-    pub command: Command,
+    pub command: Option<Command>,
 }
 
 #[derive(Debug, Subcommand)]
@@ -30,6 +38,9 @@ pub enum Command {
         #[clap(flatten)]
         filter_options: FilterOptions,
 
+        #[clap(flatten)]
+        sort_options: SortOptions,
+
         #[clap(flatten)]
         preprocess_options: PreProcessOptions,
 
@@ -50,9 +61,15 @@ pub enum Command {
         #[clap(flatten)]
         filter_options: FilterOptions,
 
+        #[clap(flatten)]
+        sort_options: SortOptions,
+
         #[clap(flatten)]
         preprocess_options: PreProcessOptions,
 
+        #[clap(flatten)]
+        upload_options: UploadOptions,
+
         #[clap(flatten)]
         global_options: GlobalOptions,
     },
@@ -64,90 +81,1403 @@ pub enum Command {
         #[clap(flatten)]
         backup_options: BackupOptions,
 
+        #[clap(flatten)]
+        upload_options: UploadOptions,
+
+        #[clap(flatten)]
+        global_options: GlobalOptions,
+    },
+
+    /// List books/annotations
+    List {
+        items: ListItems,
+
+        platform: Platform,
+
+        #[clap(flatten)]
+        filter_options: FilterOptions,
+
+        #[clap(flatten)]
+        global_options: GlobalOptions,
+    },
+
+    /// Print a book's metadata, stats and annotations
+    Info {
+        /// The book's id, asset id or a unique prefix of either
+        #[arg(value_name = "BOOK_ID")]
+        book_id: String,
+
+        #[arg(default_value = "macos")]
+        platform: Platform,
+
+        /// Set the output format
+        #[arg(long, value_name = "FORMAT", default_value = "text")]
+        format: InfoFormat,
+
+        #[clap(flatten)]
+        global_options: GlobalOptions,
+    },
+
+    /// Restore Apple Books data from a back-up
+    Restore {
+        platform: Platform,
+
+        /// The back-up directory to restore from
+        #[arg(value_name = "BACKUP", value_parser(validate_path_exists))]
+        backup_directory: PathBuf,
+
+        #[clap(flatten)]
+        restore_options: RestoreOptions,
+
+        #[clap(flatten)]
+        global_options: GlobalOptions,
+    },
+
+    /// Compare two exports or two back-ups
+    Diff {
+        /// The old export or back-up
+        #[arg(value_name = "OLD", value_parser(validate_path_exists))]
+        old: PathBuf,
+
+        /// The new export or back-up
+        #[arg(value_name = "NEW", value_parser(validate_path_exists))]
+        new: PathBuf,
+
+        #[clap(flatten)]
+        diff_options: DiffOptions,
+    },
+
+    /// Bundle everything highlighted within a period for review
+    Pack {
+        platform: Platform,
+
+        #[clap(flatten)]
+        pack_options: PackOptions,
+
+        #[clap(flatten)]
+        filter_options: FilterOptions,
+
+        #[clap(flatten)]
+        preprocess_options: PreProcessOptions,
+
+        #[clap(flatten)]
+        postprocess_options: PostProcessOptions,
+
+        #[clap(flatten)]
+        global_options: GlobalOptions,
+    },
+
+    /// Cluster similar annotations across books
+    Cluster {
+        platform: Platform,
+
+        #[clap(flatten)]
+        cluster_options: ClusterOptions,
+
+        #[clap(flatten)]
+        filter_options: FilterOptions,
+
+        #[clap(flatten)]
+        preprocess_options: PreProcessOptions,
+
+        #[clap(flatten)]
+        global_options: GlobalOptions,
+    },
+
+    /// Print a daily selection of past annotations to review
+    Review {
+        platform: Platform,
+
+        #[clap(flatten)]
+        review_options: ReviewOptions,
+
+        #[clap(flatten)]
+        filter_options: FilterOptions,
+
+        #[clap(flatten)]
+        preprocess_options: PreProcessOptions,
+
+        #[clap(flatten)]
+        global_options: GlobalOptions,
+    },
+
+    /// Print a history of finished books by month and year
+    ReadingLog {
+        platform: Platform,
+
+        #[clap(flatten)]
+        reading_log_options: ReadingLogOptions,
+
         #[clap(flatten)]
         global_options: GlobalOptions,
     },
+
+    /// Search annotations using the search index
+    Search {
+        /// The query e.g. 'paint* tag:#favorite'
+        #[arg(value_name = "QUERY")]
+        query: String,
+
+        #[clap(flatten)]
+        search_options: SearchOptions,
+
+        /// Set the output directory the index was built in
+        #[arg(
+            short = 'o',
+            long,
+            value_name = "PATH",
+            value_parser(validate_path_exists)
+        )]
+        output_directory: Option<PathBuf>,
+    },
+
+    /// Build or update the search index
+    Index {
+        #[clap(subcommand)]
+        action: IndexAction,
+    },
+
+    /// Analyze the text of annotations
+    Analyze {
+        #[clap(subcommand)]
+        analysis: Analysis,
+    },
+
+    /// Sync books and annotations to a third-party service
+    Sync {
+        #[clap(subcommand)]
+        service: SyncService,
+    },
+
+    /// Create or load a snapshot of extracted books and annotations
+    Snapshot {
+        #[clap(subcommand)]
+        action: SnapshotAction,
+    },
+
+    /// Schedule a command to run periodically
+    Schedule {
+        #[clap(subcommand)]
+        action: ScheduleAction,
+    },
+
+    /// Inspect templates
+    Templates {
+        #[clap(subcommand)]
+        action: TemplatesAction,
+    },
+
+    /// Check that Apple Books' data can be read
+    Doctor {
+        /// Check a directory containing macOS's Apple Books databases [default: Apple Books']
+        #[arg(long, value_name = "PATH", value_parser(validate_path_exists))]
+        data_directory: Option<PathBuf>,
+    },
+
+    /// Print a shell completion script
+    Completions {
+        /// The shell to generate the script for
+        #[arg(value_name = "SHELL")]
+        shell: clap_complete::Shell,
+    },
+
+    /// Print the man page
+    Man {
+        /// Write a man page for each command to a directory instead
+        #[arg(short = 'o', long, value_name = "PATH")]
+        output_directory: Option<PathBuf>,
+    },
+
+    /// Import books and annotations from outside of Apple Books as a snapshot
+    Import {
+        #[clap(subcommand)]
+        action: ImportAction,
+    },
+
+    /// Upgrade an export written by an older version to the current format
+    Migrate {
+        /// The export directory or file
+        #[arg(value_name = "PATH", value_parser(validate_path_exists))]
+        path: PathBuf,
+
+        #[clap(flatten)]
+        migrate_options: MigrateOptions,
+    },
+
+    /// Print the schemas of exported `book.json` and `annotations.json` files
+    Schema {
+        /// Set the schema format
+        #[arg(long, value_name = "FORMAT", default_value = "json-schema")]
+        format: SchemaFormat,
+
+        /// Write the schemas to a directory instead
+        #[arg(short = 'o', long, value_name = "PATH")]
+        output_directory: Option<PathBuf>,
+    },
 }
 
-#[derive(Debug, Clone, Copy, ValueEnum)]
-pub enum Platform {
-    #[value(name = "macos")]
-    MacOs,
+#[derive(Debug, Subcommand)]
+pub enum Analysis {
+    /// List the most frequent words or phrases across annotations
+    Ngrams {
+        platform: Platform,
 
-    #[value(name = "ios")]
-    IOs,
+        #[clap(flatten)]
+        ngram_options: NgramOptions,
+
+        #[clap(flatten)]
+        filter_options: FilterOptions,
+
+        #[clap(flatten)]
+        preprocess_options: PreProcessOptions,
+
+        #[clap(flatten)]
+        global_options: GlobalOptions,
+    },
+
+    /// Export where in each book annotations were made
+    Heatmap {
+        platform: Platform,
+
+        #[clap(flatten)]
+        heatmap_options: HeatmapOptions,
+
+        #[clap(flatten)]
+        filter_options: FilterOptions,
+
+        #[clap(flatten)]
+        global_options: GlobalOptions,
+    },
 }
 
-#[derive(Debug, Clone, Parser)]
-pub struct GlobalOptions {
-    /// Set a custom output directory
-    #[arg(
-        short = 'o',
-        long,
-        value_name = "PATH",
-        value_parser(validate_path_exists),
-        help_heading = "Global Options"
-    )]
-    pub output_directory: Option<PathBuf>,
+#[derive(Debug, Subcommand)]
+pub enum SyncService {
+    /// Sync books and annotations to a Notion database
+    Notion {
+        #[arg(default_value = "macos")]
+        platform: Platform,
+
+        #[clap(flatten)]
+        notion_options: NotionOptions,
+
+        #[clap(flatten)]
+        filter_options: FilterOptions,
+
+        #[clap(flatten)]
+        sort_options: SortOptions,
+
+        #[clap(flatten)]
+        preprocess_options: PreProcessOptions,
+
+        #[clap(flatten)]
+        global_options: GlobalOptions,
+    },
+
+    /// Sync annotations to Hypothes.is
+    Hypothesis {
+        #[arg(default_value = "macos")]
+        platform: Platform,
+
+        #[clap(flatten)]
+        hypothesis_options: HypothesisOptions,
+
+        #[clap(flatten)]
+        filter_options: FilterOptions,
+
+        #[clap(flatten)]
+        sort_options: SortOptions,
+
+        #[clap(flatten)]
+        preprocess_options: PreProcessOptions,
+
+        #[clap(flatten)]
+        global_options: GlobalOptions,
+    },
+
+    /// Send new annotations to a webhook
+    Webhook {
+        #[arg(default_value = "macos")]
+        platform: Platform,
+
+        #[clap(flatten)]
+        webhook_options: WebhookOptions,
+
+        #[clap(flatten)]
+        filter_options: FilterOptions,
+
+        #[clap(flatten)]
+        sort_options: SortOptions,
+
+        #[clap(flatten)]
+        preprocess_options: PreProcessOptions,
+
+        #[clap(flatten)]
+        global_options: GlobalOptions,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+#[allow(clippy::large_enum_variant)]
+pub enum SnapshotAction {
+    /// Write a snapshot of extracted books and annotations to the output directory
+    Create {
+        platform: Platform,
+
+        #[clap(flatten)]
+        global_options: GlobalOptions,
+    },
+
+    /// Check that a snapshot can be loaded and print what it contains
+    Load {
+        /// The snapshot to load
+        #[arg(value_name = "SNAPSHOT", value_parser(validate_path_exists))]
+        snapshot: PathBuf,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ImportAction {
+    /// Import a previous JSON export
+    Json {
+        /// The export directory or file
+        #[arg(value_name = "PATH", value_parser(validate_path_exists))]
+        path: PathBuf,
+
+        /// Set the output directory the snapshot is written to
+        #[arg(
+            short = 'o',
+            long,
+            value_name = "PATH",
+            value_parser(validate_output_path)
+        )]
+        output_directory: Option<PathBuf>,
+    },
+
+    /// Import a Kindle's `My Clippings.txt` file
+    Kindle {
+        /// The clippings file
+        #[arg(value_name = "PATH", value_parser(validate_path_exists))]
+        path: PathBuf,
+
+        /// Set the output directory the snapshot is written to
+        #[arg(
+            short = 'o',
+            long,
+            value_name = "PATH",
+            value_parser(validate_output_path)
+        )]
+        output_directory: Option<PathBuf>,
+    },
+
+    /// Import a Kobo's `KoboReader.sqlite` database
+    Kobo {
+        /// The database file
+        #[arg(value_name = "PATH", value_parser(validate_path_exists))]
+        path: PathBuf,
+
+        /// Set the output directory the snapshot is written to
+        #[arg(
+            short = 'o',
+            long,
+            value_name = "PATH",
+            value_parser(validate_output_path)
+        )]
+        output_directory: Option<PathBuf>,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum IndexAction {
+    /// Build the search index from scratch in the output directory
+    Build {
+        platform: Platform,
+
+        #[clap(flatten)]
+        filter_options: FilterOptions,
+
+        #[clap(flatten)]
+        preprocess_options: PreProcessOptions,
+
+        #[clap(flatten)]
+        global_options: GlobalOptions,
+    },
+
+    /// Add new and modified annotations to the search index and remove deleted ones
+    Update {
+        platform: Platform,
+
+        #[clap(flatten)]
+        filter_options: FilterOptions,
+
+        #[clap(flatten)]
+        preprocess_options: PreProcessOptions,
+
+        #[clap(flatten)]
+        global_options: GlobalOptions,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ScheduleAction {
+    /// Install a launchd agent that runs a command periodically
+    Install {
+        /// Set how often to run the command
+        #[arg(long, value_name = "INTERVAL")]
+        interval: Interval,
+
+        /// Set the command to run e.g. "export macos --force"
+        #[arg(long, value_name = "COMMAND")]
+        command: String,
+
+        /// Set the schedule's name [default: the command's name]
+        #[arg(long, value_name = "NAME")]
+        name: Option<String>,
+    },
+
+    /// Remove a scheduled command
+    Remove {
+        /// The schedule's name
+        name: String,
+    },
+
+    /// List all scheduled commands
+    List,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum TemplatesAction {
+    /// List all templates and their groups, context and structure modes and names
+    List {
+        /// Set a custom templates directory [default: the config file's]
+        #[arg(
+            short = 't',
+            long,
+            value_name = "PATH",
+            value_parser(validate_path_exists)
+        )]
+        templates_directory: Option<PathBuf>,
+
+        /// Read defaults from a config file [default: ~/.config/readstor/config.toml]
+        #[arg(
+            long = "config",
+            value_name = "PATH",
+            value_parser(validate_path_exists)
+        )]
+        config_path: Option<PathBuf>,
+    },
+}
+
+impl Command {
+    /// Returns the command's name.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Render { .. } => "render",
+            Self::Export { .. } => "export",
+            Self::Backup { .. } => "backup",
+            Self::List { .. } => "list",
+            Self::Info { .. } => "info",
+            Self::Restore { .. } => "restore",
+            Self::Diff { .. } => "diff",
+            Self::Pack { .. } => "pack",
+            Self::Cluster { .. } => "cluster",
+            Self::Review { .. } => "review",
+            Self::ReadingLog { .. } => "reading-log",
+            Self::Search { .. } => "search",
+            Self::Index { .. } => "index",
+            Self::Analyze { .. } => "analyze",
+            Self::Sync { .. } => "sync",
+            Self::Snapshot { .. } => "snapshot",
+            Self::Schedule { .. } => "schedule",
+            Self::Templates { .. } => "templates",
+            Self::Doctor { .. } => "doctor",
+            Self::Completions { .. } => "completions",
+            Self::Man { .. } => "man",
+            Self::Import { .. } => "import",
+            Self::Migrate { .. } => "migrate",
+            Self::Schema { .. } => "schema",
+        }
+    }
+
+    /// Returns the command's global options, if it has any.
+    pub fn global_options(&self) -> Option<&GlobalOptions> {
+        match self {
+            Self::Render { global_options, .. }
+            | Self::Export { global_options, .. }
+            | Self::Backup { global_options, .. }
+            | Self::List { global_options, .. }
+            | Self::Info { global_options, .. }
+            | Self::Restore { global_options, .. }
+            | Self::Pack { global_options, .. }
+            | Self::Cluster { global_options, .. }
+            | Self::Review { global_options, .. }
+            | Self::ReadingLog { global_options, .. }
+            | Self::Index {
+                action:
+                    IndexAction::Build { global_options, .. }
+                    | IndexAction::Update { global_options, .. },
+            }
+            | Self::Analyze {
+                analysis:
+                    Analysis::Ngrams { global_options, .. } | Analysis::Heatmap { global_options, .. },
+            }
+            | Self::Sync {
+                service:
+                    SyncService::Notion { global_options, .. }
+                    | SyncService::Hypothesis { global_options, .. }
+                    | SyncService::Webhook { global_options, .. },
+            }
+            | Self::Snapshot {
+                action: SnapshotAction::Create { global_options, .. },
+            } => Some(global_options),
+            Self::Diff { .. }
+            | Self::Search { .. }
+            | Self::Snapshot {
+                action: SnapshotAction::Load { .. },
+            }
+            | Self::Schedule { .. }
+            | Self::Templates { .. }
+            | Self::Doctor { .. }
+            | Self::Completions { .. }
+            | Self::Man { .. }
+            | Self::Import { .. }
+            | Self::Migrate { .. }
+            | Self::Schema { .. } => None,
+        }
+    }
+
+    /// Returns the command's global options mutably, if it has any.
+    pub fn global_options_mut(&mut self) -> Option<&mut GlobalOptions> {
+        match self {
+            Self::Render { global_options, .. }
+            | Self::Export { global_options, .. }
+            | Self::Backup { global_options, .. }
+            | Self::List { global_options, .. }
+            | Self::Info { global_options, .. }
+            | Self::Restore { global_options, .. }
+            | Self::Pack { global_options, .. }
+            | Self::Cluster { global_options, .. }
+            | Self::Review { global_options, .. }
+            | Self::ReadingLog { global_options, .. }
+            | Self::Index {
+                action:
+                    IndexAction::Build { global_options, .. }
+                    | IndexAction::Update { global_options, .. },
+            }
+            | Self::Analyze {
+                analysis:
+                    Analysis::Ngrams { global_options, .. } | Analysis::Heatmap { global_options, .. },
+            }
+            | Self::Sync {
+                service:
+                    SyncService::Notion { global_options, .. }
+                    | SyncService::Hypothesis { global_options, .. }
+                    | SyncService::Webhook { global_options, .. },
+            }
+            | Self::Snapshot {
+                action: SnapshotAction::Create { global_options, .. },
+            } => Some(global_options),
+            Self::Diff { .. }
+            | Self::Search { .. }
+            | Self::Snapshot {
+                action: SnapshotAction::Load { .. },
+            }
+            | Self::Schedule { .. }
+            | Self::Templates { .. }
+            | Self::Doctor { .. }
+            | Self::Completions { .. }
+            | Self::Man { .. }
+            | Self::Import { .. }
+            | Self::Migrate { .. }
+            | Self::Schema { .. } => None,
+        }
+    }
+
+    /// Returns the command's filter options mutably, if it has any.
+    pub fn filter_options_mut(&mut self) -> Option<&mut FilterOptions> {
+        match self {
+            Self::Render { filter_options, .. }
+            | Self::Export { filter_options, .. }
+            | Self::List { filter_options, .. }
+            | Self::Pack { filter_options, .. }
+            | Self::Cluster { filter_options, .. }
+            | Self::Review { filter_options, .. }
+            | Self::Index {
+                action:
+                    IndexAction::Build { filter_options, .. }
+                    | IndexAction::Update { filter_options, .. },
+            }
+            | Self::Analyze {
+                analysis:
+                    Analysis::Ngrams { filter_options, .. } | Analysis::Heatmap { filter_options, .. },
+            }
+            | Self::Sync {
+                service:
+                    SyncService::Notion { filter_options, .. }
+                    | SyncService::Hypothesis { filter_options, .. }
+                    | SyncService::Webhook { filter_options, .. },
+            } => Some(filter_options),
+            Self::Backup { .. }
+            | Self::Info { .. }
+            | Self::Restore { .. }
+            | Self::ReadingLog { .. }
+            | Self::Diff { .. }
+            | Self::Search { .. }
+            | Self::Snapshot { .. }
+            | Self::Schedule { .. }
+            | Self::Templates { .. }
+            | Self::Doctor { .. }
+            | Self::Completions { .. }
+            | Self::Man { .. }
+            | Self::Import { .. }
+            | Self::Migrate { .. }
+            | Self::Schema { .. } => None,
+        }
+    }
+
+    /// Returns the command's platform, if it has one.
+    pub fn platform(&self) -> Option<Platform> {
+        match self {
+            Self::Render { platform, .. }
+            | Self::Export { platform, .. }
+            | Self::Backup { platform, .. }
+            | Self::List { platform, .. }
+            | Self::Info { platform, .. }
+            | Self::Restore { platform, .. }
+            | Self::Pack { platform, .. }
+            | Self::Cluster { platform, .. }
+            | Self::Review { platform, .. }
+            | Self::ReadingLog { platform, .. }
+            | Self::Index {
+                action: IndexAction::Build { platform, .. } | IndexAction::Update { platform, .. },
+            }
+            | Self::Analyze {
+                analysis: Analysis::Ngrams { platform, .. } | Analysis::Heatmap { platform, .. },
+            }
+            | Self::Sync {
+                service:
+                    SyncService::Notion { platform, .. }
+                    | SyncService::Hypothesis { platform, .. }
+                    | SyncService::Webhook { platform, .. },
+            }
+            | Self::Snapshot {
+                action: SnapshotAction::Create { platform, .. },
+            } => Some(*platform),
+            Self::Diff { .. }
+            | Self::Search { .. }
+            | Self::Snapshot {
+                action: SnapshotAction::Load { .. },
+            }
+            | Self::Schedule { .. }
+            | Self::Templates { .. }
+            | Self::Doctor { .. }
+            | Self::Completions { .. }
+            | Self::Man { .. }
+            | Self::Import { .. }
+            | Self::Migrate { .. }
+            | Self::Schema { .. } => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum Platform {
+    #[value(name = "macos")]
+    MacOs,
+
+    #[value(name = "ios")]
+    IOs,
+
+    #[value(name = "all")]
+    All,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum ListItems {
+    #[value(name = "books")]
+    Books,
+
+    #[value(name = "annotations")]
+    Annotations,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum Interval {
+    #[value(name = "hourly")]
+    Hourly,
+
+    #[value(name = "daily")]
+    Daily,
+
+    #[value(name = "weekly")]
+    Weekly,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum LogLevel {
+    #[value(name = "error")]
+    Error,
+
+    #[value(name = "warn")]
+    Warn,
+
+    #[value(name = "info")]
+    Info,
+
+    #[value(name = "debug")]
+    Debug,
+
+    #[value(name = "trace")]
+    Trace,
+}
+
+#[derive(Debug, Clone, Copy, Default, ValueEnum)]
+pub enum SortBooks {
+    #[default]
+    #[value(name = "title")]
+    Title,
+
+    #[value(name = "author")]
+    Author,
+
+    #[value(name = "last-opened")]
+    LastOpened,
+
+    #[value(name = "progress")]
+    Progress,
+}
+
+#[derive(Debug, Clone, Copy, Default, ValueEnum)]
+pub enum IdScheme {
+    #[default]
+    #[value(name = "uuid")]
+    Uuid,
+
+    #[value(name = "hash")]
+    Hash,
+}
+
+#[derive(Debug, Clone, Copy, Default, ValueEnum)]
+pub enum Preset {
+    #[default]
+    #[value(name = "basic")]
+    Basic,
+
+    #[value(name = "org")]
+    Org,
+
+    #[value(name = "logseq")]
+    Logseq,
+
+    #[value(name = "review")]
+    Review,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ExportFormat {
+    #[value(name = "json")]
+    Json,
+
+    #[value(name = "jsonl")]
+    JsonLines,
+
+    #[value(name = "epub")]
+    Epub,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum RedactField {
+    #[value(name = "notes")]
+    Notes,
+
+    #[value(name = "body")]
+    Body,
+
+    #[value(name = "titles")]
+    Titles,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TextField {
+    #[value(name = "title")]
+    Title,
+
+    #[value(name = "author")]
+    Author,
+
+    #[value(name = "body")]
+    Body,
+
+    #[value(name = "notes")]
+    Notes,
+}
+
+#[derive(Debug, Clone, Copy, Default, ValueEnum)]
+pub enum RedactMode {
+    #[default]
+    #[value(name = "hash")]
+    Hash,
+
+    #[value(name = "omit")]
+    Omit,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum ConflictMode {
+    #[value(name = "skip")]
+    Skip,
+
+    #[value(name = "overwrite")]
+    Overwrite,
+
+    #[value(name = "update")]
+    Update,
+
+    #[value(name = "backup")]
+    Backup,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum InfoFormat {
+    #[value(name = "text")]
+    Text,
+
+    #[value(name = "json")]
+    Json,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum SchemaFormat {
+    #[value(name = "json-schema")]
+    JsonSchema,
+
+    #[value(name = "typescript")]
+    TypeScript,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum NgramFormat {
+    #[value(name = "table")]
+    Table,
+
+    #[value(name = "csv")]
+    Csv,
+}
+
+#[derive(Debug, Clone, Copy, Default, ValueEnum)]
+pub enum DiffFormat {
+    #[default]
+    #[value(name = "text")]
+    Text,
+
+    #[value(name = "json")]
+    Json,
+
+    #[value(name = "markdown")]
+    Markdown,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum Compression {
+    #[value(name = "gzip")]
+    Gzip,
+
+    #[value(name = "zstd")]
+    Zstd,
+}
+
+#[derive(Debug, Clone, Parser)]
+#[allow(clippy::struct_excessive_bools)]
+pub struct GlobalOptions {
+    /// Read defaults from a config file [default: ~/.config/readstor/config.toml]
+    #[arg(
+        long = "config",
+        value_name = "PATH",
+        value_parser(validate_path_exists),
+        help_heading = "Global Options"
+    )]
+    pub config_path: Option<PathBuf>,
+
+    /// Set a custom output directory
+    #[arg(
+        short = 'o',
+        long,
+        value_name = "PATH",
+        value_parser(validate_output_path),
+        help_heading = "Global Options"
+    )]
+    pub output_directory: Option<PathBuf>,
+
+    /// Set a custom source data directory
+    #[arg(
+        short = 'd',
+        long,
+        value_name = "PATH",
+        value_parser(validate_path_exists),
+        help_heading = "Global Options"
+    )]
+    pub data_directory: Option<PathBuf>,
+
+    /// Read iOS data from a Finder/iTunes back-up instead of a connected device
+    #[arg(
+        long,
+        value_name = "PATH",
+        value_parser(validate_path_exists),
+        help_heading = "Global Options"
+    )]
+    pub ios_backup: Option<PathBuf>,
+
+    /// Read macOS data from an Apple Books container, by path or by its index in the list of
+    /// containers found on disk
+    #[arg(
+        long,
+        value_name = "PATH|INDEX",
+        conflicts_with = "data_directory",
+        help_heading = "Global Options"
+    )]
+    pub container: Option<String>,
+
+    /// Read books and annotations from a snapshot instead of Apple Books
+    #[arg(
+        long,
+        value_name = "PATH",
+        value_parser(validate_path_exists),
+        conflicts_with_all = ["data_directory", "ios_backup", "container"],
+        help_heading = "Global Options"
+    )]
+    pub snapshot: Option<PathBuf>,
+
+    /// Enrich books with metadata from a Calibre library
+    #[arg(
+        long,
+        value_name = "PATH",
+        value_parser(validate_path_exists),
+        help_heading = "Global Options"
+    )]
+    pub calibre_library: Option<PathBuf>,
+
+    /// Set how book and annotation ids are generated
+    #[arg(
+        long,
+        value_name = "SCHEME",
+        default_value = "uuid",
+        help_heading = "Global Options"
+    )]
+    pub id_scheme: IdScheme,
+
+    /// Run command even if Apple Books is currently running
+    #[arg(short = 'F', long = "force", help_heading = "Global Options")]
+    pub is_force: bool,
+
+    /// Include books without annotations
+    #[arg(long, help_heading = "Global Options")]
+    pub include_unannotated: bool,
+
+    /// Include audiobooks and their bookmarks (macOS only)
+    #[arg(long, help_heading = "Global Options")]
+    pub include_audiobooks: bool,
+
+    /// Silence output messages
+    #[arg(short = 'q', long = "quiet", help_heading = "Global Options")]
+    pub is_quiet: bool,
+
+    /// Print where each option's value came from
+    #[arg(short = 'v', long = "verbose", help_heading = "Global Options")]
+    pub is_verbose: bool,
+
+    /// Print progress as JSON lines instead of a progress bar, silencing other output messages
+    #[arg(long, help_heading = "Global Options")]
+    pub json_progress: bool,
+
+    /// Show a notification when the command finishes or fails
+    #[arg(long, help_heading = "Global Options")]
+    pub notify: bool,
+
+    /// Send a POST request to a URL when the command finishes or fails
+    #[arg(long, value_name = "URL", help_heading = "Global Options")]
+    pub notify_webhook: Option<String>,
+
+    /// Run a shell command after the command finishes successfully
+    #[arg(long, value_name = "COMMAND", help_heading = "Global Options")]
+    pub on_success: Option<String>,
+
+    /// Run a shell command after the command fails
+    #[arg(long, value_name = "COMMAND", help_heading = "Global Options")]
+    pub on_failure: Option<String>,
+
+    /// Write a JSON summary of the run to a file, or to stdout if set to '-'
+    #[arg(long, value_name = "PATH", help_heading = "Global Options")]
+    pub report: Option<PathBuf>,
+
+    /// Append log messages to a file instead of printing them to stderr
+    #[arg(long, value_name = "PATH", help_heading = "Global Options")]
+    pub log_file: Option<PathBuf>,
+
+    /// Set the log level [default: error, or info with --log-file]
+    #[arg(long, value_name = "LEVEL", help_heading = "Global Options")]
+    pub log_level: Option<LogLevel>,
+}
+
+#[derive(Debug, Clone, Default, Parser)]
+#[allow(clippy::struct_excessive_bools)]
+pub struct RenderOptions {
+    /// Set a custom templates directory
+    #[arg(
+        short = 't',
+        long,
+        value_name = "PATH",
+        value_parser(validate_path_exists)
+    )]
+    pub templates_directory: Option<PathBuf>,
+
+    /// Render a built-in template preset [default: basic]
+    #[arg(long, value_name = "PRESET", conflicts_with = "templates_directory")]
+    pub preset: Option<Preset>,
+
+    /// Render specified template-group(s)
+    #[arg(short = 'g', long = "template-group", value_name = "GROUP")]
+    pub template_groups: Vec<String>,
+
+    /// Expose an environment variable to templates as env.NAME
+    #[arg(long = "template-env", value_name = "NAME")]
+    pub template_env: Vec<String>,
+
+    /// Overwrite existing files
+    #[arg(short = 'O', long)]
+    pub overwrite_existing: bool,
+
+    /// Set what to do when a file already exists [default: skip]
+    #[arg(long, value_name = "MODE", conflicts_with = "overwrite_existing")]
+    pub on_conflict: Option<ConflictMode>,
+
+    /// Skip books completed by a previous interrupted render
+    #[arg(long)]
+    pub resume: bool,
+
+    /// Render all books into a single file with a table of contents
+    #[arg(long, conflicts_with = "resume")]
+    pub single_file: bool,
+
+    /// Render into a temporary directory and only sync into the output directory if successful
+    #[arg(long, conflicts_with = "resume")]
+    pub sandbox: bool,
+
+    /// Write each file as soon as it's rendered instead of once per book
+    #[arg(long, conflicts_with = "single_file")]
+    pub stream: bool,
+
+    /// Wrap rendered files in markers and only replace the marked region on re-render
+    #[arg(long, conflicts_with = "sandbox")]
+    pub managed_regions: bool,
+
+    /// Escape Markdown characters in annotation bodies rendered with Markdown templates
+    #[arg(long)]
+    pub escape_markdown: bool,
+
+    /// Stop if the render would write more than N files
+    #[arg(long, value_name = "N")]
+    pub max_files: Option<usize>,
+
+    /// Stop if the render would write more than SIZE in total e.g. 500MB
+    #[arg(long, value_name = "SIZE", value_parser(super::limits::parse_size))]
+    pub max_total_size: Option<u64>,
+
+    /// Fail if a template takes longer than SECONDS to render
+    #[arg(long, value_name = "SECONDS")]
+    pub template_timeout: Option<u64>,
+
+    /// Fail if a template renders more than SIZE, 0 to disable
+    #[arg(
+        long,
+        value_name = "SIZE",
+        default_value = "64MB",
+        value_parser(super::limits::parse_size)
+    )]
+    pub template_max_size: u64,
+}
+
+#[derive(Debug, Clone, Default, Parser)]
+#[allow(clippy::struct_excessive_bools)]
+pub struct ExportOptions {
+    /// Set the export format
+    #[arg(long, value_name = "FORMAT")]
+    pub format: Option<ExportFormat>,
+
+    /// Set the output directory template
+    #[arg(short = 't', long, value_name = "TEMPLATE")]
+    pub directory_template: Option<String>,
+
+    /// Write all files into the output directory instead of one directory per book
+    #[arg(long, conflicts_with = "directory_template")]
+    pub flat: bool,
+
+    /// Set the path template for each book's files, without their extensions
+    #[arg(
+        long,
+        value_name = "TEMPLATE",
+        conflicts_with_all = [
+            "directory_template",
+            "flat",
+            "book_filename_template",
+            "annotations_filename_template",
+        ]
+    )]
+    pub output_template: Option<String>,
+
+    /// Compress the exported files
+    #[arg(long, value_name = "FORMAT")]
+    pub compress_output: Option<Compression>,
+
+    /// Write the export into a single archive
+    #[arg(long, conflicts_with_all = ["overwrite_existing", "on_conflict"])]
+    pub archive: bool,
+
+    /// Write all books into a single library.json or library.epub file
+    #[arg(
+        long,
+        conflicts_with_all = [
+            "directory_template",
+            "flat",
+            "output_template",
+            "book_filename_template",
+            "annotations_filename_template",
+        ]
+    )]
+    pub single_file: bool,
+
+    /// Set the book filename template
+    #[arg(long, value_name = "TEMPLATE")]
+    pub book_filename_template: Option<String>,
+
+    /// Set the annotations filename template
+    #[arg(long, value_name = "TEMPLATE")]
+    pub annotations_filename_template: Option<String>,
+
+    /// Overwrite existing files
+    #[arg(short = 'O', long)]
+    pub overwrite_existing: bool,
+
+    /// Set what to do when a file already exists [default: skip]
+    #[arg(long, value_name = "MODE", conflicts_with = "overwrite_existing")]
+    pub on_conflict: Option<ConflictMode>,
+
+    /// Copy each book's cover image alongside its book.json
+    #[arg(long, conflicts_with = "single_file")]
+    pub include_covers: bool,
+
+    /// Redact a field from the export
+    #[arg(long = "redact", value_name = "FIELD")]
+    pub redact: Vec<RedactField>,
+
+    /// Set how redacted fields are written
+    #[arg(long, value_name = "MODE", default_value = "hash", requires = "redact")]
+    pub redact_mode: RedactMode,
+
+    /// Write canonical JSON with sorted keys so exports diff cleanly
+    #[arg(long)]
+    pub canonical_json: bool,
+
+    /// The name of the file to export to. This is set when the output directory is a file e.g.
+    /// `-o highlights.jsonl`. See [`Config::merge_export_options()`][merge].
+    ///
+    /// [merge]: super::config::Config::merge_export_options()
+    #[arg(skip)]
+    pub filename: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Parser)]
+pub struct BackupOptions {
+    /// Set the output directory template
+    #[arg(short = 't', long, value_name = "TEMPLATE")]
+    pub directory_template: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Parser)]
+pub struct PackOptions {
+    /// Include annotations created on or after a date e.g. 2024-01-01 or 30d
+    #[arg(
+        long,
+        value_name = "DATE",
+        default_value = "30d",
+        value_parser(super::filter::parse_date)
+    )]
+    pub since: DateTime<Utc>,
+
+    /// Render the pack with a built-in template preset
+    #[arg(long = "template", value_name = "PRESET", default_value = "review")]
+    pub preset: Preset,
+
+    /// Write the pack into a zip archive instead of a directory
+    #[arg(long)]
+    pub zip: bool,
+}
+
+#[derive(Debug, Clone, Default, Parser)]
+pub struct UploadOptions {
+    /// Upload the output to remote storage
+    #[arg(long, value_name = "TARGET", help_heading = "Upload")]
+    pub upload: Option<lib::upload::UploadTarget>,
+}
+
+#[derive(Debug, Clone, Default, Parser)]
+pub struct NotionOptions {
+    /// Set the Notion integration token, otherwise read from the environment
+    #[arg(long, value_name = "TOKEN")]
+    pub token: Option<String>,
+
+    /// Set the id of the Notion database to sync to
+    #[arg(long, value_name = "ID")]
+    pub database_id: String,
+}
+
+#[derive(Debug, Clone, Default, Parser)]
+pub struct HypothesisOptions {
+    /// Set the Hypothes.is developer token, otherwise read from the environment
+    #[arg(long, value_name = "TOKEN")]
+    pub token: Option<String>,
+
+    /// Set the id of the Hypothes.is group to post to, otherwise annotations are private
+    #[arg(long, value_name = "ID")]
+    pub group: Option<String>,
+}
+
+#[derive(Debug, Clone, Parser)]
+pub struct WebhookOptions {
+    /// Set the URL to POST annotations to
+    #[arg(long, value_name = "URL")]
+    pub url: String,
+
+    /// Add a header to each request e.g. 'Authorization: Bearer TOKEN'
+    #[arg(long, value_name = "HEADER", value_parser(validate_header))]
+    pub header: Vec<(String, String)>,
+
+    /// Set the maximum number of annotations sent per request
+    #[arg(
+        long,
+        value_name = "N",
+        default_value_t = lib::sync::webhook::DEFAULT_BATCH_SIZE,
+        value_parser(validate_batch_size)
+    )]
+    pub batch_size: usize,
+}
+
+#[derive(Debug, Clone, Copy, Parser)]
+pub struct ClusterOptions {
+    /// Set the minimum similarity, between 0.0 and 1.0, of clustered annotations
+    #[arg(
+        long,
+        value_name = "SIMILARITY",
+        default_value_t = 0.5,
+        value_parser(validate_similarity)
+    )]
+    pub threshold: f64,
+
+    /// Set the minimum number of annotations in a cluster
+    #[arg(long, value_name = "N", default_value_t = 2)]
+    pub min_size: usize,
+}
+
+#[derive(Debug, Clone, Parser)]
+pub struct ReviewOptions {
+    /// Set the number of annotations to review
+    #[arg(long, value_name = "N", default_value_t = lib::review::DEFAULT_REVIEW_COUNT)]
+    pub count: usize,
+
+    /// Set the date to select annotations for e.g. 2024-01-01 [default: today]
+    #[arg(long, value_name = "DATE", value_parser(super::filter::parse_date))]
+    pub date: Option<DateTime<Utc>>,
+
+    /// Review annotations with a tag more often e.g. '#favorite'
+    #[arg(long, value_name = "TAG")]
+    pub weight_tag: Vec<String>,
+
+    /// Render the review with a template instead of printing it
+    #[arg(long, value_name = "PATH", value_parser(validate_path_exists))]
+    pub template: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Parser)]
+pub struct ReadingLogOptions {
+    /// Render the reading log with a template instead of printing it
+    #[arg(long, value_name = "PATH", value_parser(validate_path_exists))]
+    pub template: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Copy, Parser)]
+pub struct SearchOptions {
+    /// Set the maximum number of matches to print
+    #[arg(long, value_name = "N", default_value_t = 20)]
+    pub limit: usize,
+}
 
-    /// Set a custom source data directory
+#[derive(Debug, Clone, Copy, Parser)]
+pub struct NgramOptions {
+    /// Set the number of words in each n-gram, between 1 and 3
     #[arg(
-        short = 'd',
         long,
-        value_name = "PATH",
-        value_parser(validate_path_exists),
-        help_heading = "Global Options"
+        value_name = "N",
+        default_value_t = 1,
+        value_parser(validate_ngram_size)
     )]
-    pub data_directory: Option<PathBuf>,
+    pub size: usize,
 
-    /// Run command even if Apple Books is currently running
-    #[arg(short = 'F', long = "force", help_heading = "Global Options")]
-    pub is_force: bool,
+    /// Set the number of n-grams to list
+    #[arg(long, value_name = "N", default_value_t = 50)]
+    pub top: usize,
 
-    /// Silence output messages
-    #[arg(short = 'q', long = "quiet", help_heading = "Global Options")]
-    pub is_quiet: bool,
+    /// Set the output format
+    #[arg(long, value_name = "FORMAT", default_value = "table")]
+    pub format: NgramFormat,
 }
 
-#[derive(Debug, Clone, Default, Parser)]
-pub struct RenderOptions {
-    /// Set a custom templates directory
+#[derive(Debug, Clone, Copy, Parser)]
+pub struct HeatmapOptions {
+    /// Set the number of equally sized buckets to count positions into
     #[arg(
-        short = 't',
         long,
-        value_name = "PATH",
-        value_parser(validate_path_exists)
+        value_name = "N",
+        default_value_t = 10,
+        value_parser(validate_buckets)
     )]
-    pub templates_directory: Option<PathBuf>,
-
-    /// Render specified template-group(s)
-    #[arg(short = 'g', long = "template-group", value_name = "GROUP")]
-    pub template_groups: Vec<String>,
-
-    /// Overwrite existing files
-    #[arg(short = 'O', long)]
-    pub overwrite_existing: bool,
+    pub buckets: usize,
 }
 
-#[derive(Debug, Clone, Default, Parser)]
-pub struct ExportOptions {
-    /// Set the output directory template
-    #[arg(short = 't', long, value_name = "TEMPLATE")]
-    pub directory_template: Option<String>,
+#[derive(Debug, Clone, Copy, Default, Parser)]
+pub struct RestoreOptions {
+    /// List the files to restore without writing anything
+    #[arg(long)]
+    pub dry_run: bool,
+}
 
-    /// Overwrite existing files
-    #[arg(short = 'O', long)]
-    pub overwrite_existing: bool,
+#[derive(Debug, Clone, Copy, Default, Parser)]
+pub struct MigrateOptions {
+    /// List the files to migrate without writing anything
+    #[arg(long)]
+    pub dry_run: bool,
 }
 
-#[derive(Debug, Clone, Default, Parser)]
-pub struct BackupOptions {
-    /// Set the output directory template
-    #[arg(short = 't', long, value_name = "TEMPLATE")]
-    pub directory_template: Option<String>,
+#[derive(Debug, Clone, Copy, Default, Parser)]
+pub struct DiffOptions {
+    /// Set the output format
+    #[arg(long, value_name = "FORMAT", default_value = "text")]
+    pub format: DiffFormat,
 }
 
 #[derive(Debug, Clone, Default, Parser)]
+#[command(group(
+    ArgGroup::new("filters")
+        .multiple(true)
+        .args([
+            "filter_types",
+            "filter_set",
+            "books_from",
+            "created_after",
+            "created_before",
+            "modified_after",
+            "modified_before",
+            "favorites",
+        ])
+))]
 pub struct FilterOptions {
     /// Filter books/annotations before outputting
     #[arg(
@@ -158,23 +1488,176 @@ pub struct FilterOptions {
     )]
     pub filter_types: Vec<super::filter::FilterType>,
 
+    /// Apply a filter set defined in the config file
+    #[arg(long, value_name = "NAME", help_heading = "Filter")]
+    pub filter_set: Option<String>,
+
+    /// Keep/discard books listed in a file by id or title, one per line
+    #[arg(
+        long,
+        value_name = "FILE",
+        value_parser(super::filter::parse_book_list),
+        help_heading = "Filter"
+    )]
+    pub books_from: Option<lib::filter::BookList>,
+
+    /// Keep annotations created on or after a date e.g. 2024-01-01 or 30d
+    #[arg(
+        long = "filter-created-after",
+        value_name = "DATE",
+        value_parser(super::filter::parse_date),
+        help_heading = "Filter"
+    )]
+    pub created_after: Option<DateTime<Utc>>,
+
+    /// Keep annotations created before a date e.g. 2024-01-01 or 30d
+    #[arg(
+        long = "filter-created-before",
+        value_name = "DATE",
+        value_parser(super::filter::parse_date),
+        help_heading = "Filter"
+    )]
+    pub created_before: Option<DateTime<Utc>>,
+
+    /// Keep annotations modified on or after a date e.g. 2024-01-01 or 30d
+    #[arg(
+        long = "filter-modified-after",
+        value_name = "DATE",
+        value_parser(super::filter::parse_date),
+        help_heading = "Filter"
+    )]
+    pub modified_after: Option<DateTime<Utc>>,
+
+    /// Keep annotations modified before a date e.g. 2024-01-01 or 30d
+    #[arg(
+        long = "filter-modified-before",
+        value_name = "DATE",
+        value_parser(super::filter::parse_date),
+        help_heading = "Filter"
+    )]
+    pub modified_before: Option<DateTime<Utc>>,
+
+    /// Keep annotations marked as favorites e.g. with '*' or '#fav' in their notes
+    #[arg(long = "filter-favorites", help_heading = "Filter")]
+    pub favorites: bool,
+
     /// Auto-confirm filter results
     #[arg(
         short = 'A', // Capital lettes for critical options
         long = "auto-confirm-filter",
-        requires = "filter_types",
+        requires = "filters",
         help_heading = "Filter"
     )]
     pub auto_confirm: bool,
 }
 
-#[derive(Debug, Clone, Copy, Default, Parser)]
+impl FilterOptions {
+    /// Returns all filters, including any date-range filters.
+    pub fn filters(&self) -> Vec<super::filter::FilterType> {
+        let mut filters = self.filter_types.clone();
+
+        if let Some(list) = &self.books_from {
+            filters.push(super::filter::FilterType::BookList(list.clone()));
+        }
+
+        if self.created_after.is_some() || self.created_before.is_some() {
+            filters.push(super::filter::FilterType::DateRange {
+                field: super::filter::DateField::Created,
+                from: self.created_after,
+                to: self.created_before,
+            });
+        }
+
+        if self.modified_after.is_some() || self.modified_before.is_some() {
+            filters.push(super::filter::FilterType::DateRange {
+                field: super::filter::DateField::Modified,
+                from: self.modified_after,
+                to: self.modified_before,
+            });
+        }
+
+        if self.favorites {
+            filters.push(super::filter::FilterType::Favorites);
+        }
+
+        filters
+    }
+
+    /// Returns `true` if no filters were set.
+    pub fn is_empty(&self) -> bool {
+        self.filters().is_empty()
+    }
+}
+
+#[derive(Debug, Clone, Default, Parser)]
+pub struct SortOptions {
+    /// Set the order in which books are output
+    #[arg(
+        long,
+        value_name = "KEY",
+        default_value = "title",
+        help_heading = "Sort"
+    )]
+    pub sort_books: SortBooks,
+
+    /// Set the order in which each book's annotations are output
+    #[arg(long, value_name = "KEY", help_heading = "Sort")]
+    pub sort: Option<lib::process::SortKey>,
+
+    /// Output at most N books
+    #[arg(long, value_name = "N", help_heading = "Sort")]
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Clone, Default, Parser)]
 #[allow(clippy::struct_excessive_bools)]
 pub struct PreProcessOptions {
     /// Extract #tags from annotation notes
     #[arg(short = 'e', long, help_heading = "Pre-process")]
     pub extract_tags: bool,
 
+    /// Set the character tags start with e.g. '@' [default: #]
+    #[arg(
+        long,
+        value_name = "CHAR",
+        value_parser(validate_tag_prefix),
+        help_heading = "Pre-process"
+    )]
+    pub tag_prefix: Option<char>,
+
+    /// Only allow letters, numbers, '_' and these characters within tags e.g. '-/'
+    #[arg(long, value_name = "CHARS", help_heading = "Pre-process")]
+    pub tag_chars: Option<String>,
+
+    /// Lowercase extracted tags
+    #[arg(long, help_heading = "Pre-process")]
+    pub lowercase_tags: bool,
+
+    /// Remove the prefix from extracted tags e.g. '#tag' becomes 'tag'
+    #[arg(long, help_heading = "Pre-process")]
+    pub strip_tag_prefix: bool,
+
+    /// Rename extracted tags using a TOML or YAML file of `old = new` pairs
+    #[arg(
+        long,
+        value_name = "FILE",
+        value_parser(validate_path_exists),
+        help_heading = "Pre-process"
+    )]
+    pub tag_map: Option<PathBuf>,
+
+    /// Extract `key:: value` properties from annotation notes
+    #[arg(long, help_heading = "Pre-process")]
+    pub extract_properties: bool,
+
+    /// Extract `key: value` fields from annotation notes e.g. 'rating: 5'
+    #[arg(long, help_heading = "Pre-process")]
+    pub extract_fields: bool,
+
+    /// Mark annotations with '*' or '#fav' in their notes as favorites
+    #[arg(long, help_heading = "Pre-process")]
+    pub extract_favorites: bool,
+
     /// Normalize whitespace in annotation body
     #[arg(short = 'n', long, help_heading = "Pre-process")]
     pub normalize_whitespace: bool,
@@ -196,6 +1679,39 @@ pub struct PreProcessOptions {
         help_heading = "Pre-process"
     )]
     pub convert_symbols_to_ascii: bool,
+
+    /// Convert curly quotes to straight quotes
+    #[arg(long, help_heading = "Pre-process")]
+    pub straighten_quotes: bool,
+
+    /// Expand '…' into '...'
+    #[arg(long, help_heading = "Pre-process")]
+    pub expand_ellipses: bool,
+
+    /// Replace non-breaking spaces with regular spaces
+    #[arg(long, help_heading = "Pre-process")]
+    pub replace_nbsp: bool,
+
+    /// Set the fields text conversions apply to [default: title,author,body]
+    #[arg(
+        long,
+        value_name = "FIELDS",
+        value_delimiter = ',',
+        help_heading = "Pre-process"
+    )]
+    pub text_fields: Option<Vec<TextField>>,
+
+    /// Remove overlapping annotations, keeping the longest
+    #[arg(long, help_heading = "Pre-process")]
+    pub dedupe: bool,
+
+    /// Merge annotations that continue one another into a single annotation
+    #[arg(long, help_heading = "Pre-process")]
+    pub merge_adjacent: bool,
+
+    /// Link annotations to the other books whose titles they mention
+    #[arg(long, help_heading = "Pre-process")]
+    pub link_mentions: bool,
 }
 
 #[derive(Debug, Clone, Copy, Default, Parser)]
@@ -221,20 +1737,149 @@ pub fn validate_path_exists(value: &str) -> std::result::Result<PathBuf, String>
     std::fs::canonicalize(value).map_err(|_| "path does not exist".into())
 }
 
+/// Validates an output path. Unlike [`validate_path_exists()`], this also accepts a file with an
+/// extension that doesn't exist yet e.g. `highlights.jsonl`, as long as its parent directory does.
+pub fn validate_output_path(value: &str) -> std::result::Result<PathBuf, String> {
+    if let Ok(path) = std::fs::canonicalize(value) {
+        return Ok(path);
+    }
+
+    let path = std::path::Path::new(value);
+
+    let (Some(parent), Some(filename), Some(_)) =
+        (path.parent(), path.file_name(), path.extension())
+    else {
+        return Err("path does not exist".into());
+    };
+
+    // `Path::parent` returns an empty path for relative paths without a parent.
+    let parent = if parent.as_os_str().is_empty() {
+        std::path::Path::new(".")
+    } else {
+        parent
+    };
+
+    std::fs::canonicalize(parent)
+        .map(|parent| parent.join(filename))
+        .map_err(|_| "path does not exist".into())
+}
+
+pub fn validate_similarity(value: &str) -> std::result::Result<f64, String> {
+    match value.parse::<f64>() {
+        Ok(similarity) if (0.0..=1.0).contains(&similarity) => Ok(similarity),
+        _ => Err("expected a number between 0.0 and 1.0".into()),
+    }
+}
+
+pub fn validate_ngram_size(value: &str) -> std::result::Result<usize, String> {
+    let max = lib::analysis::ngrams::MAX_NGRAM_SIZE;
+
+    match value.parse::<usize>() {
+        Ok(size) if (1..=max).contains(&size) => Ok(size),
+        _ => Err(format!("expected a number between 1 and {max}")),
+    }
+}
+
+pub fn validate_buckets(value: &str) -> std::result::Result<usize, String> {
+    match value.parse::<usize>() {
+        Ok(buckets) if buckets > 0 => Ok(buckets),
+        _ => Err("expected a number greater than 0".into()),
+    }
+}
+
+pub fn validate_batch_size(value: &str) -> std::result::Result<usize, String> {
+    match value.parse::<usize>() {
+        Ok(size) if size > 0 => Ok(size),
+        _ => Err("expected a number greater than 0".into()),
+    }
+}
+
+pub fn validate_tag_prefix(value: &str) -> std::result::Result<char, String> {
+    let mut chars = value.chars();
+
+    match (chars.next(), chars.next()) {
+        (Some(prefix), None) if !prefix.is_alphanumeric() && !prefix.is_whitespace() => Ok(prefix),
+        _ => Err("expected a single character that isn't a letter, number or space".into()),
+    }
+}
+
+pub fn validate_header(value: &str) -> std::result::Result<(String, String), String> {
+    match value.split_once(':') {
+        Some((name, value)) if !name.trim().is_empty() => {
+            Ok((name.trim().to_owned(), value.trim().to_owned()))
+        }
+        _ => Err("expected a header formatted as 'Name: Value'".into()),
+    }
+}
+
+/// Resolves `--on-conflict`, treating `--overwrite-existing` as `--on-conflict overwrite`.
+fn on_conflict(
+    overwrite_existing: bool,
+    on_conflict: Option<ConflictMode>,
+) -> lib::conflict::ConflictMode {
+    match on_conflict {
+        Some(mode) => mode.into(),
+        None if overwrite_existing => lib::conflict::ConflictMode::Overwrite,
+        None => lib::conflict::ConflictMode::Skip,
+    }
+}
+
 impl std::fmt::Display for Platform {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Self::MacOs => write!(f, "macOS"),
             Self::IOs => write!(f, "iOS"),
+            Self::All => write!(f, "macOS and iOS"),
+        }
+    }
+}
+
+impl Preset {
+    /// Returns the preset's template string.
+    pub fn template(self) -> &'static str {
+        match self {
+            Self::Basic => super::defaults::TEMPLATE,
+            Self::Org => super::defaults::TEMPLATE_ORG,
+            Self::Logseq => super::defaults::TEMPLATE_LOGSEQ,
+            Self::Review => super::defaults::TEMPLATE_REVIEW,
+        }
+    }
+}
+
+impl Interval {
+    /// Returns the interval in seconds.
+    pub fn seconds(self) -> u64 {
+        match self {
+            Self::Hourly => 60 * 60,
+            Self::Daily => 60 * 60 * 24,
+            Self::Weekly => 60 * 60 * 24 * 7,
+        }
+    }
+}
+
+impl From<LogLevel> for tracing_subscriber::filter::LevelFilter {
+    fn from(level: LogLevel) -> Self {
+        match level {
+            LogLevel::Error => Self::ERROR,
+            LogLevel::Warn => Self::WARN,
+            LogLevel::Info => Self::INFO,
+            LogLevel::Debug => Self::DEBUG,
+            LogLevel::Trace => Self::TRACE,
         }
     }
 }
 
-impl From<Platform> for lib::applebooks::Platform {
-    fn from(platform: Platform) -> Self {
+impl TryFrom<Platform> for lib::applebooks::Platform {
+    type Error = color_eyre::Report;
+
+    /// Converts a single [`Platform`]. `all` is only supported by commands that read data.
+    fn try_from(platform: Platform) -> Result<Self, Self::Error> {
         match platform {
-            Platform::MacOs => Self::MacOs,
-            Platform::IOs => Self::IOs,
+            Platform::MacOs => Ok(Self::MacOs),
+            Platform::IOs => Ok(Self::IOs),
+            Platform::All => Err(color_eyre::eyre::eyre!(
+                "The `all` platform is only supported when reading data e.g. `render` or `export`"
+            )),
         }
     }
 }
@@ -244,7 +1889,31 @@ impl From<RenderOptions> for lib::render::renderer::RenderOptions {
         Self {
             templates_directory: options.templates_directory,
             template_groups: options.template_groups,
-            overwrite_existing: options.overwrite_existing,
+            on_conflict: self::on_conflict(options.overwrite_existing, options.on_conflict),
+            managed_regions: options.managed_regions,
+            single_file: options.single_file,
+            max_render_time: options
+                .template_timeout
+                .filter(|seconds| *seconds > 0)
+                .map(std::time::Duration::from_secs),
+            max_render_size: usize::try_from(options.template_max_size)
+                .ok()
+                .filter(|size| *size > 0),
+            env: options.template_env,
+            escape_markdown: options.escape_markdown,
+        }
+    }
+}
+
+impl From<PackOptions> for RenderOptions {
+    fn from(options: PackOptions) -> Self {
+        Self {
+            preset: Some(options.preset),
+            // A pack is always rendered into a new directory.
+            overwrite_existing: true,
+            // Matches the default of `render`.
+            template_max_size: 64 << 20,
+            ..Default::default()
         }
     }
 }
@@ -253,7 +1922,119 @@ impl From<ExportOptions> for lib::export::ExportOptions {
     fn from(options: ExportOptions) -> Self {
         Self {
             directory_template: options.directory_template,
-            overwrite_existing: options.overwrite_existing,
+            book_filename_template: options.book_filename_template,
+            annotations_filename_template: options.annotations_filename_template,
+            output_template: options.output_template,
+            flat: options.flat,
+            compression: options.compress_output.map(Into::into),
+            archive: options.archive,
+            single_file: options.single_file,
+            sort_books: lib::models::entry::BookSortKey::default(),
+            on_conflict: self::on_conflict(options.overwrite_existing, options.on_conflict),
+            format: options.format.map(Into::into).unwrap_or_default(),
+            include_covers: options.include_covers,
+            filename: options.filename,
+            redact: options.redact.into_iter().map(Into::into).collect(),
+            redact_mode: options.redact_mode.into(),
+            canonical_json: options.canonical_json,
+        }
+    }
+}
+
+impl From<ExportFormat> for lib::export::ExportFormat {
+    fn from(format: ExportFormat) -> Self {
+        match format {
+            ExportFormat::Json => Self::Json,
+            ExportFormat::JsonLines => Self::JsonLines,
+            ExportFormat::Epub => Self::Epub,
+        }
+    }
+}
+
+impl From<lib::export::ExportFormat> for ExportFormat {
+    fn from(format: lib::export::ExportFormat) -> Self {
+        match format {
+            lib::export::ExportFormat::Json => Self::Json,
+            lib::export::ExportFormat::JsonLines => Self::JsonLines,
+            lib::export::ExportFormat::Epub => Self::Epub,
+        }
+    }
+}
+
+impl From<SchemaFormat> for lib::schema::SchemaFormat {
+    fn from(format: SchemaFormat) -> Self {
+        match format {
+            SchemaFormat::JsonSchema => Self::JsonSchema,
+            SchemaFormat::TypeScript => Self::TypeScript,
+        }
+    }
+}
+
+impl From<RedactField> for lib::export::redact::RedactField {
+    fn from(field: RedactField) -> Self {
+        match field {
+            RedactField::Notes => Self::Notes,
+            RedactField::Body => Self::Body,
+            RedactField::Titles => Self::Titles,
+        }
+    }
+}
+
+impl From<RedactMode> for lib::export::redact::RedactMode {
+    fn from(mode: RedactMode) -> Self {
+        match mode {
+            RedactMode::Hash => Self::Hash,
+            RedactMode::Omit => Self::Omit,
+        }
+    }
+}
+
+impl From<ConflictMode> for lib::conflict::ConflictMode {
+    fn from(mode: ConflictMode) -> Self {
+        match mode {
+            ConflictMode::Skip => Self::Skip,
+            ConflictMode::Overwrite => Self::Overwrite,
+            ConflictMode::Update => Self::Update,
+            ConflictMode::Backup => Self::Backup,
+        }
+    }
+}
+
+impl From<DiffFormat> for lib::diff::DiffFormat {
+    fn from(format: DiffFormat) -> Self {
+        match format {
+            DiffFormat::Text => Self::Text,
+            DiffFormat::Json => Self::Json,
+            DiffFormat::Markdown => Self::Markdown,
+        }
+    }
+}
+
+impl From<SortBooks> for lib::models::entry::BookSortKey {
+    fn from(sort_books: SortBooks) -> Self {
+        match sort_books {
+            SortBooks::Title => Self::Title,
+            SortBooks::Author => Self::Author,
+            SortBooks::LastOpened => Self::LastOpened,
+            SortBooks::Progress => Self::Progress,
+        }
+    }
+}
+
+impl From<IdScheme> for lib::models::id::IdScheme {
+    fn from(id_scheme: IdScheme) -> Self {
+        match id_scheme {
+            IdScheme::Uuid => Self::Uuid,
+            IdScheme::Hash => Self::Hash,
+        }
+    }
+}
+
+impl From<Compression> for lib::export::Compression {
+    fn from(compression: Compression) -> Self {
+        match compression {
+            Compression::Gzip => Self::Gzip,
+            Compression::Zstd => Self::Zstd,
         }
     }
 }
@@ -266,13 +2047,133 @@ impl From<BackupOptions> for lib::backup::BackupOptions {
     }
 }
 
+impl From<NotionOptions> for lib::sync::notion::NotionOptions {
+    fn from(options: NotionOptions) -> Self {
+        Self {
+            token: options.token,
+            database_id: options.database_id,
+        }
+    }
+}
+
+impl From<HypothesisOptions> for lib::sync::hypothesis::HypothesisOptions {
+    fn from(options: HypothesisOptions) -> Self {
+        Self {
+            token: options.token,
+            group: options.group,
+        }
+    }
+}
+
+impl From<WebhookOptions> for lib::sync::webhook::WebhookOptions {
+    fn from(options: WebhookOptions) -> Self {
+        Self {
+            url: options.url,
+            headers: options.header,
+            batch_size: options.batch_size,
+        }
+    }
+}
+
+impl From<ReviewOptions> for lib::review::ReviewOptions {
+    fn from(options: ReviewOptions) -> Self {
+        Self {
+            count: options.count,
+            date: options.date.map_or_else(
+                || chrono::Local::now().date_naive(),
+                |date| date.with_timezone(&chrono::Local).date_naive(),
+            ),
+            weight_tags: options
+                .weight_tag
+                .into_iter()
+                .map(|tag| format!("#{}", tag.trim_start_matches('#')))
+                .collect(),
+        }
+    }
+}
+
+impl From<ClusterOptions> for lib::analysis::cluster::ClusterOptions {
+    fn from(options: ClusterOptions) -> Self {
+        Self {
+            threshold: options.threshold,
+            min_size: options.min_size,
+        }
+    }
+}
+
+impl From<NgramOptions> for lib::analysis::ngrams::NgramOptions {
+    fn from(options: NgramOptions) -> Self {
+        Self {
+            size: options.size,
+            top: options.top,
+        }
+    }
+}
+
+impl From<HeatmapOptions> for lib::analysis::heatmap::HeatmapOptions {
+    fn from(options: HeatmapOptions) -> Self {
+        Self {
+            buckets: options.buckets,
+        }
+    }
+}
+
+impl From<RestoreOptions> for lib::restore::RestoreOptions {
+    fn from(options: RestoreOptions) -> Self {
+        Self {
+            dry_run: options.dry_run,
+        }
+    }
+}
+
+impl From<MigrateOptions> for lib::migrate::MigrateOptions {
+    fn from(options: MigrateOptions) -> Self {
+        Self {
+            dry_run: options.dry_run,
+        }
+    }
+}
+
 impl From<PreProcessOptions> for lib::process::pre::PreProcessOptions {
     fn from(options: PreProcessOptions) -> Self {
         Self {
             extract_tags: options.extract_tags,
+            tag_syntax: {
+                let mut syntax = lib::strings::TagSyntax::new(
+                    options
+                        .tag_prefix
+                        .unwrap_or(lib::strings::DEFAULT_TAG_PREFIX),
+                    options.tag_chars.as_deref(),
+                );
+                syntax.lowercase = options.lowercase_tags;
+                syntax.strip_prefix = options.strip_tag_prefix;
+                syntax
+            },
+            // The tag map is read by the app as reading it can fail.
+            tag_map: None,
+            extract_properties: options.extract_properties,
+            extract_fields: options.extract_fields,
+            extract_favorites: options.extract_favorites,
             normalize_whitespace: options.normalize_whitespace,
             convert_all_to_ascii: options.convert_all_to_ascii,
             convert_symbols_to_ascii: options.convert_symbols_to_ascii,
+            typography: lib::process::pre::TypographyOptions {
+                straighten_quotes: options.straighten_quotes,
+                expand_ellipses: options.expand_ellipses,
+                replace_nbsp: options.replace_nbsp,
+            },
+            text_fields: options.text_fields.map_or_else(
+                lib::process::pre::TextFields::default,
+                |fields| lib::process::pre::TextFields {
+                    title: fields.contains(&TextField::Title),
+                    author: fields.contains(&TextField::Author),
+                    body: fields.contains(&TextField::Body),
+                    notes: fields.contains(&TextField::Notes),
+                },
+            ),
+            dedupe: options.dedupe,
+            merge_adjacent: options.merge_adjacent,
+            link_mentions: options.link_mentions,
         }
     }
 }
@@ -0,0 +1,46 @@
+use std::path::Path;
+
+use clap::CommandFactory;
+use clap_complete::Shell;
+
+use crate::CliResult;
+
+use super::args::Args;
+
+/// Prints a completion script for a shell to stdout.
+///
+/// The script is generated from the CLI's definition at runtime so it always includes every
+/// command and option of the running version.
+///
+/// # Arguments
+///
+/// * `shell` - The shell to generate the script for.
+pub fn print(shell: Shell) {
+    let mut command = Args::command();
+    let name = command.get_name().to_owned();
+
+    clap_complete::generate(shell, &mut command, name, &mut std::io::stdout());
+}
+
+/// Prints the man page to stdout or, if a directory is set, writes one man page per command to it.
+///
+/// # Arguments
+///
+/// * `directory` - The directory to write the man pages to.
+///
+/// # Errors
+///
+/// Will return `Err` if any IO errors are encountered.
+pub fn man(directory: Option<&Path>) -> CliResult<()> {
+    let command = Args::command();
+
+    match directory {
+        Some(directory) => {
+            std::fs::create_dir_all(directory)?;
+            clap_mangen::generate_to(command, directory)?;
+        }
+        None => clap_mangen::Man::new(command).render(&mut std::io::stdout())?,
+    }
+
+    Ok(())
+}
@@ -1,31 +1,88 @@
-use std::path::PathBuf;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
 
-use color_eyre::eyre::Context;
+use clap::ValueEnum;
+use color_eyre::eyre::{bail, eyre, Context};
+use lib::applebooks;
 use lib::applebooks::ios::ABPlist;
-use lib::applebooks::macos::ABDatabase;
-use lib::applebooks::Platform;
+use lib::applebooks::macos::{containers, ABDatabase};
+use serde::Deserialize;
 
-use super::args::GlobalOptions;
-use super::{utils, CliResult};
+use super::args::{
+    validate_tag_prefix, ExportFormat, ExportOptions, FilterOptions, GlobalOptions, IdScheme,
+    Platform, PostProcessOptions, PreProcessOptions, RenderOptions, TextField,
+};
+use super::paths::{self, Directory};
+use super::progress::ProgressMode;
+use super::{filter, utils, CliResult};
+
+/// The name of the config file within the config directory.
+const CONFIG_FILENAME: &str = "config.toml";
 
 #[derive(Debug)]
+#[allow(clippy::struct_excessive_bools)]
 pub struct Config {
     /// The Apple Books platform.
     pub platform: Platform,
 
-    /// The data directory.
+    /// The data directory. For the `all` platform, this is macOS's data directory. Empty if reading
+    /// from a snapshot.
     pub data_directory: PathBuf,
 
+    /// iOS's data directory, only set for the `all` platform. Its data is merged into macOS's.
+    pub ios_data_directory: Option<PathBuf>,
+
+    /// The snapshot to read books and annotations from instead of Apple Books, if set.
+    pub snapshot: Option<PathBuf>,
+
+    /// The directory containing the books' unzipped EPUBs, if it exists.
+    pub books_directory: Option<PathBuf>,
+
+    /// The Calibre library to enrich books with, if any.
+    pub calibre_library: Option<PathBuf>,
+
     /// The path to the output directory.
     pub output_directory: PathBuf,
 
     /// Flag to enable/disable terminal output.
     pub is_quiet: bool,
+
+    /// How `render` and `export` report their progress.
+    pub progress: ProgressMode,
+
+    /// Flag to keep/discard books without annotations.
+    pub include_unannotated: bool,
+
+    /// Flag to extract audiobooks and their bookmarks.
+    pub include_audiobooks: bool,
+
+    /// The scheme used to set book and annotation ids.
+    pub id_scheme: IdScheme,
+
+    /// Flag to print where each option's value came from.
+    pub is_verbose: bool,
+
+    /// The values read from the config file.
+    file: ConfigFile,
+
+    /// The path to the config file, if one was read.
+    file_path: Option<PathBuf>,
+
+    /// The name of the file to write to if the output directory set on the command line is a file
+    /// that doesn't exist yet e.g. `highlights.jsonl`. Only `export` can write to a file.
+    output_file: Option<String>,
+
+    /// The name, value and source of each option merged so far.
+    sources: Vec<(&'static str, String, Source)>,
 }
 
 impl Config {
     /// Creates a new instance of [`Config`].
     ///
+    /// The config file is read from `--config` if set, otherwise from
+    /// `~/.config/readstor/config.toml` if it exists. Options set on the command line take
+    /// precedence over the config file. See [`ConfigFile`] for more information.
+    ///
     /// # Arguments
     ///
     /// * `platform` - Which platform to build for.
@@ -35,44 +92,627 @@ impl Config {
     ///
     /// Will return `Err` if:
     /// * Any IO errors are encountered.
+    /// * The config file cannot be parsed.
     /// * There are any errors finding/reading the iOS device.
     pub fn new(platform: Platform, options: GlobalOptions) -> CliResult<Self> {
-        let data_directory = Self::get_data_directory(platform, options.data_directory)
-            .wrap_err("Failed while retrieving source data directory")?;
+        let (file, file_path) = ConfigFile::load(options.config_path.as_deref())?;
+
+        let mut sources = Vec::new();
 
-        let output_directory = Self::get_output_directory(options.output_directory);
+        let (output_directory, output_file) = Self::split_output_file(options.output_directory);
+
+        let output_directory = self::merge_option(
+            &mut sources,
+            "output-directory",
+            output_directory,
+            file.output_directory.clone(),
+        );
+
+        let calibre_library = self::merge_option(
+            &mut sources,
+            "calibre-library",
+            options.calibre_library,
+            file.calibre_library.clone(),
+        );
+
+        // EPUBs are only read from macOS so they're also used when merging both platforms.
+        let books_platform = match platform {
+            Platform::All => applebooks::Platform::MacOs,
+            platform => platform.try_into()?,
+        };
+
+        let books_directory =
+            Self::get_books_directory(books_platform, options.data_directory.as_deref());
+
+        let container = options
+            .container
+            .as_deref()
+            .map(Self::get_container)
+            .transpose()?;
+
+        let (data_directory, ios_data_directory) = match platform {
+            // A snapshot replaces Apple Books' data so there's nothing to retrieve.
+            _ if options.snapshot.is_some() => (PathBuf::new(), None),
+            // `--data-directory` sets macOS's data while iOS's is read from `--ios-backup` or the
+            // connected device.
+            Platform::All => {
+                let data_directory = Self::get_data_directory(
+                    applebooks::Platform::MacOs,
+                    options.data_directory,
+                    None,
+                    container,
+                )
+                .wrap_err("Failed while retrieving macOS's source data directory")?;
+
+                let ios_data_directory = Self::get_data_directory(
+                    applebooks::Platform::IOs,
+                    None,
+                    options.ios_backup,
+                    None,
+                )
+                .wrap_err("Failed while retrieving iOS's source data directory")?;
+
+                (data_directory, Some(ios_data_directory))
+            }
+            platform => {
+                let data_directory = Self::get_data_directory(
+                    platform.try_into()?,
+                    options.data_directory,
+                    options.ios_backup,
+                    container,
+                )
+                .wrap_err("Failed while retrieving source data directory")?;
+
+                (data_directory, None)
+            }
+        };
+
+        let output_directory = Self::get_output_directory(output_directory);
+
+        let progress = if options.json_progress {
+            ProgressMode::Json
+        } else if options.is_quiet {
+            ProgressMode::Hidden
+        } else {
+            ProgressMode::Bar
+        };
 
         Ok(Self {
             platform,
             data_directory,
+            ios_data_directory,
+            snapshot: options.snapshot,
+            books_directory,
+            calibre_library,
             output_directory,
-            is_quiet: options.is_quiet,
+            is_quiet: options.is_quiet || options.json_progress,
+            progress,
+            include_unannotated: options.include_unannotated,
+            include_audiobooks: options.include_audiobooks,
+            id_scheme: options.id_scheme,
+            is_verbose: options.is_verbose,
+            file,
+            file_path,
+            output_file,
+            sources,
         })
     }
 
-    fn get_output_directory(path: Option<PathBuf>) -> PathBuf {
+    /// Sets the export's filename and infers its format when the output directory set on the
+    /// command line is a file e.g. `-o highlights.jsonl`.
+    ///
+    /// The format is inferred from the file's extension unless `--format` is set. A `json` or
+    /// `epub` file is exported as a single file.
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - The export options to merge into.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if:
+    /// * No export format writes files with the output file's extension.
+    /// * `--archive` is set.
+    pub fn merge_export_options(&mut self, options: &mut ExportOptions) -> CliResult<()> {
+        let Some(filename) = &self.output_file else {
+            return Ok(());
+        };
+
+        if options.archive {
+            bail!("Cannot use --archive when exporting to a file: {filename}");
+        }
+
+        let format = if let Some(format) = options.format {
+            format
+        } else {
+            let format = self::infer_export_format(filename)?;
+
+            let name = format
+                .to_possible_value()
+                .map(|value| value.get_name().to_owned())
+                .unwrap_or_default();
+
+            self.sources.push(("format", name, Source::Inferred));
+
+            format
+        };
+
+        options.format = Some(format);
+        options.single_file = matches!(format, ExportFormat::Json | ExportFormat::Epub);
+        options.filename = Some(filename.clone());
+
+        Ok(())
+    }
+
+    /// Returns the templates directory set on the command line, otherwise the config file's.
+    ///
+    /// Unlike [`Config::new()`], this doesn't read any Apple Books data so it can be used by
+    /// commands that only work with templates.
+    ///
+    /// # Arguments
+    ///
+    /// * `config_path` - An optional path to the config file.
+    /// * `templates_directory` - The templates directory set on the command line.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the config file cannot be read or parsed.
+    pub fn templates_directory(
+        config_path: Option<&Path>,
+        templates_directory: Option<PathBuf>,
+    ) -> CliResult<Option<PathBuf>> {
+        if templates_directory.is_some() {
+            return Ok(templates_directory);
+        }
+
+        let (file, _) = ConfigFile::load(config_path)?;
+
+        Ok(file.templates_directory)
+    }
+
+    /// Fills in the render options not set on the command line from the config file.
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - The render options to merge into.
+    pub fn merge_render_options(&mut self, options: &mut RenderOptions) {
+        // A preset replaces the templates directory so the config file's is ignored.
+        let file_templates_directory = if options.preset.is_some() {
+            None
+        } else {
+            self.file.templates_directory.clone()
+        };
+
+        options.templates_directory = self::merge_option(
+            &mut self.sources,
+            "templates-directory",
+            options.templates_directory.take(),
+            file_templates_directory,
+        );
+
+        let (template_groups, source) = if !options.template_groups.is_empty() {
+            (options.template_groups.clone(), Source::CommandLine)
+        } else if !self.file.template_groups.is_empty() {
+            (self.file.template_groups.clone(), Source::File)
+        } else {
+            (Vec::new(), Source::Default)
+        };
+
+        self.sources
+            .push(("template-groups", template_groups.join(", "), source));
+
+        options.template_groups = template_groups;
+
+        let (template_env, source) = if !options.template_env.is_empty() {
+            (options.template_env.clone(), Source::CommandLine)
+        } else if !self.file.template_env.is_empty() {
+            (self.file.template_env.clone(), Source::File)
+        } else {
+            (Vec::new(), Source::Default)
+        };
+
+        self.sources
+            .push(("template-env", template_env.join(", "), source));
+
+        options.template_env = template_env;
+    }
+
+    /// Applies the filter set selected with `--filter-set`, if any.
+    ///
+    /// The filter set's filters are added to the ones set on the command line. Its date ranges are
+    /// only used if they aren't set on the command line.
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - The filter options to merge into.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the filter set doesn't exist or contains invalid filters.
+    pub fn merge_filter_options(&mut self, options: &mut FilterOptions) -> CliResult<()> {
+        let Some(name) = &options.filter_set else {
+            return Ok(());
+        };
+
+        let Some(set) = self.file.filter_sets.get(name) else {
+            bail!("No filter set named '{name}' in the config file");
+        };
+
+        for query in &set.filters {
+            let filter_type = query.parse().map_err(|error| {
+                eyre!("Invalid filter '{query}' in filter set '{name}': {error}")
+            })?;
+
+            options.filter_types.push(filter_type);
+        }
+
+        let parse_date = |date: &Option<String>| {
+            date.as_deref()
+                .map(filter::parse_date)
+                .transpose()
+                .map_err(|error| eyre!("Invalid date in filter set '{name}': {error}"))
+        };
+
+        options.favorites |= set.favorites;
+        options.created_after = options.created_after.or(parse_date(&set.created_after)?);
+        options.created_before = options.created_before.or(parse_date(&set.created_before)?);
+        options.modified_after = options.modified_after.or(parse_date(&set.modified_after)?);
+        options.modified_before = options
+            .modified_before
+            .or(parse_date(&set.modified_before)?);
+
+        self.sources
+            .push(("filter-set", name.clone(), Source::File));
+
+        Ok(())
+    }
+
+    /// Fills in the pre-process options not set on the command line from the config file.
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - The pre-process options to merge into.
+    #[allow(clippy::too_many_lines)]
+    pub fn merge_preprocess_options(&mut self, options: &mut PreProcessOptions) {
+        let file = &self.file.preprocess;
+        let sources = &mut self.sources;
+
+        options.extract_tags = self::merge_flag(
+            sources,
+            "extract-tags",
+            options.extract_tags,
+            file.extract_tags,
+        );
+        options.extract_properties = self::merge_flag(
+            sources,
+            "extract-properties",
+            options.extract_properties,
+            file.extract_properties,
+        );
+        options.extract_fields = self::merge_flag(
+            sources,
+            "extract-fields",
+            options.extract_fields,
+            file.extract_fields,
+        );
+        options.tag_prefix =
+            self::merge_option(sources, "tag-prefix", options.tag_prefix, file.tag_prefix);
+        options.tag_chars = self::merge_option(
+            sources,
+            "tag-chars",
+            options.tag_chars.take(),
+            file.tag_chars.clone(),
+        );
+        options.lowercase_tags = self::merge_flag(
+            sources,
+            "lowercase-tags",
+            options.lowercase_tags,
+            file.lowercase_tags,
+        );
+        options.strip_tag_prefix = self::merge_flag(
+            sources,
+            "strip-tag-prefix",
+            options.strip_tag_prefix,
+            file.strip_tag_prefix,
+        );
+        options.tag_map = self::merge_option(
+            sources,
+            "tag-map",
+            options.tag_map.take(),
+            file.tag_map.clone(),
+        );
+        options.extract_favorites = self::merge_flag(
+            sources,
+            "extract-favorites",
+            options.extract_favorites,
+            file.extract_favorites,
+        );
+        options.normalize_whitespace = self::merge_flag(
+            sources,
+            "normalize-whitespace",
+            options.normalize_whitespace,
+            file.normalize_whitespace,
+        );
+
+        // The two ASCII conversions conflict so the config file's are ignored if either is set on
+        // the command line.
+        let is_ascii_set = options.convert_all_to_ascii || options.convert_symbols_to_ascii;
+
+        options.convert_all_to_ascii = self::merge_flag(
+            sources,
+            "ascii-all",
+            options.convert_all_to_ascii,
+            file.ascii_all.filter(|_| !is_ascii_set),
+        );
+        options.convert_symbols_to_ascii = self::merge_flag(
+            sources,
+            "ascii-symbols",
+            options.convert_symbols_to_ascii,
+            file.ascii_symbols
+                .filter(|_| !is_ascii_set && !options.convert_all_to_ascii),
+        );
+        options.straighten_quotes = self::merge_flag(
+            sources,
+            "straighten-quotes",
+            options.straighten_quotes,
+            file.straighten_quotes,
+        );
+        options.expand_ellipses = self::merge_flag(
+            sources,
+            "expand-ellipses",
+            options.expand_ellipses,
+            file.expand_ellipses,
+        );
+        options.replace_nbsp = self::merge_flag(
+            sources,
+            "replace-nbsp",
+            options.replace_nbsp,
+            file.replace_nbsp,
+        );
+        options.text_fields = self::merge_option(
+            sources,
+            "text-fields",
+            options.text_fields.take(),
+            file.text_fields.clone(),
+        );
+        options.dedupe = self::merge_flag(sources, "dedupe", options.dedupe, file.dedupe);
+        options.merge_adjacent = self::merge_flag(
+            sources,
+            "merge-adjacent",
+            options.merge_adjacent,
+            file.merge_adjacent,
+        );
+        options.link_mentions = self::merge_flag(
+            sources,
+            "link-mentions",
+            options.link_mentions,
+            file.link_mentions,
+        );
+    }
+
+    /// Fills in the post-process options not set on the command line from the config file.
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - The post-process options to merge into.
+    pub fn merge_postprocess_options(&mut self, options: &mut PostProcessOptions) {
+        let file = &self.file.postprocess;
+
+        options.trim_blocks = self::merge_flag(
+            &mut self.sources,
+            "trim-blocks",
+            options.trim_blocks,
+            file.trim_blocks,
+        );
+        options.wrap_text = self::merge_option(
+            &mut self.sources,
+            "wrap-text",
+            options.wrap_text,
+            file.wrap_text,
+        );
+    }
+
+    /// Prints where each option's value came from if `--verbose` is set.
+    pub fn print_sources(&self) {
+        if !self.is_verbose {
+            return;
+        }
+
+        match &self.file_path {
+            Some(path) => println!("Config file: {}", path.display()),
+            None => println!("Config file: none"),
+        }
+
+        for (name, value, source) in &self.sources {
+            if value.is_empty() {
+                println!(" • {name} ({source})");
+            } else {
+                println!(" • {name} = {value} ({source})");
+            }
+        }
+    }
+
+    /// Returns the data directory to restore to.
+    ///
+    /// Returns `None` if the default Apple Books data directory should be used. During
+    /// development, the temporary data directory is used instead.
+    ///
+    /// # Arguments
+    ///
+    /// * `platform` - Which platform to restore for.
+    /// * `path` - An optional custom data directory.
+    pub fn get_restore_directory(
+        platform: applebooks::Platform,
+        path: Option<PathBuf>,
+    ) -> Option<PathBuf> {
+        if path.is_some() || !utils::is_development_env() {
+            return path;
+        }
+
+        let name = match platform {
+            applebooks::Platform::MacOs => "macos-data",
+            applebooks::Platform::IOs => "ios-data",
+        };
+
+        Some(lib::defaults::TEMP_OUTPUT_DIRECTORY.join(name))
+    }
+
+    /// Returns the output directory.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - An optional custom output directory.
+    pub fn get_output_directory(path: Option<PathBuf>) -> PathBuf {
         if let Some(path) = path {
             return path;
         }
 
+        if utils::is_development_env() {
+            return lib::defaults::TEMP_OUTPUT_DIRECTORY.to_owned();
+        }
+
+        let legacy = &*super::defaults::LEGACY_OUTPUT_DIRECTORY;
+        let destination = &*super::defaults::OUTPUT_DIRECTORY;
+
+        match paths::migrate(legacy, destination) {
+            Ok(true) => println!(
+                "Moved output directory {} to {}",
+                legacy.display(),
+                destination.display()
+            ),
+            Ok(false) => {}
+            // The legacy directory is left as-is so nothing is lost.
+            Err(error) => {
+                tracing::warn!("{error:?}");
+                return legacy.to_owned();
+            }
+        }
+
+        destination.to_owned()
+    }
+
+    /// Splits an output path that doesn't exist into its parent directory and filename. Paths that
+    /// don't exist have been validated by [`validate_output_path()`][validate] to be files.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The output path set on the command line.
+    ///
+    /// [validate]: super::args::validate_output_path()
+    fn split_output_file(path: Option<PathBuf>) -> (Option<PathBuf>, Option<String>) {
+        let Some(path) = path else {
+            return (None, None);
+        };
+
+        if path.exists() {
+            return (Some(path), None);
+        }
+
+        match (path.parent(), path.file_name()) {
+            (Some(parent), Some(filename)) => (
+                Some(parent.to_owned()),
+                Some(filename.to_string_lossy().into_owned()),
+            ),
+            _ => (Some(path), None),
+        }
+    }
+
+    /// Returns the directory source data is copied to before being read.
+    fn get_cache_directory() -> PathBuf {
         if utils::is_development_env() {
             lib::defaults::TEMP_OUTPUT_DIRECTORY.to_owned()
         } else {
-            super::defaults::OUTPUT_DIRECTORY.to_owned()
+            super::defaults::CACHE_DIRECTORY.to_owned()
         }
     }
 
-    fn get_data_directory(platform: Platform, path: Option<PathBuf>) -> CliResult<PathBuf> {
+    /// Returns the directory containing the books' unzipped EPUBs, if it exists.
+    ///
+    /// If a data directory is set, this is its `Books` subdirectory. Otherwise, this is Apple
+    /// Books for macOS's books directory. EPUBs aren't read from iOS devices.
+    ///
+    /// # Arguments
+    ///
+    /// * `platform` - The platform to get the directory for.
+    /// * `data_directory` - The data directory set on the command line.
+    fn get_books_directory(
+        platform: applebooks::Platform,
+        data_directory: Option<&Path>,
+    ) -> Option<PathBuf> {
+        let path = match (data_directory, platform) {
+            (Some(path), _) => path.join("Books"),
+            (None, applebooks::Platform::MacOs) if utils::is_development_env() => {
+                super::defaults::TEST_DATABASES_DIRECTORY
+                    .join("books-annotated")
+                    .join("Books")
+            }
+            (None, applebooks::Platform::MacOs) => {
+                lib::applebooks::macos::defaults::BOOKS_DIRECTORY.to_owned()
+            }
+            (None, applebooks::Platform::IOs) => return None,
+        };
+
+        path.is_dir().then_some(path)
+    }
+
+    /// Returns the directory to read source data from.
+    ///
+    /// If a data directory is set, it's used as is. Otherwise, the data is copied to the cache
+    /// directory from an iOS back-up or a macOS container, if set, or from Apple Books/the
+    /// connected iOS device.
+    ///
+    /// # Arguments
+    ///
+    /// * `platform` - The platform to get the directory for.
+    /// * `path` - The data directory set on the command line.
+    /// * `ios_backup` - The iOS back-up set on the command line.
+    /// * `container` - The macOS container set on the command line.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if:
+    /// * Both a data directory and an iOS back-up are set.
+    /// * An iOS back-up is set for the `macos` platform.
+    /// * A container is set for the `ios` platform.
+    /// * Any IO errors are encountered.
+    /// * There are any errors reading the iOS back-up or finding/reading the iOS device.
+    fn get_data_directory(
+        platform: applebooks::Platform,
+        path: Option<PathBuf>,
+        ios_backup: Option<PathBuf>,
+        container: Option<PathBuf>,
+    ) -> CliResult<PathBuf> {
+        if path.is_some() && ios_backup.is_some() {
+            bail!("The `--data-directory` and `--ios-backup` options can't be used together");
+        }
+
         if let Some(path) = path {
             return Ok(path);
         }
 
+        if container.is_some() && matches!(platform, applebooks::Platform::IOs) {
+            bail!("The `--container` option can't be used with the `ios` platform");
+        }
+
+        if let Some(backup) = ios_backup {
+            if matches!(platform, applebooks::Platform::MacOs) {
+                bail!("The `--ios-backup` option can only be used with the `ios` platform");
+            }
+
+            let destination = Self::get_cache_directory().join("ios-data");
+            ABPlist::save_from_backup(&backup, &destination)?;
+
+            return Ok(destination);
+        }
+
         let path = match platform {
-            Platform::MacOs => {
-                let destination = lib::defaults::TEMP_OUTPUT_DIRECTORY.join("macos-data");
+            applebooks::Platform::MacOs => {
+                let destination = Self::get_cache_directory().join("macos-data");
                 std::fs::create_dir_all(&destination)?;
 
-                if utils::is_development_env() {
+                if let Some(container) = container {
+                    ABDatabase::save_to(&destination, Some(&container))?;
+                } else if utils::is_development_env() {
                     let source = super::defaults::TEST_DATABASES_DIRECTORY.join("books-annotated");
                     ABDatabase::save_to(&destination, Some(&source))?;
                 } else {
@@ -81,8 +721,8 @@ impl Config {
 
                 destination
             }
-            Platform::IOs => {
-                let destination = lib::defaults::TEMP_OUTPUT_DIRECTORY.join("ios-data");
+            applebooks::Platform::IOs => {
+                let destination = Self::get_cache_directory().join("ios-data");
                 std::fs::create_dir_all(&destination)?;
 
                 if utils::is_development_env() {
@@ -98,6 +738,496 @@ impl Config {
 
         Ok(path)
     }
+
+    /// Returns the macOS container to read data from.
+    ///
+    /// A number selects a container by its index, starting at 1, in the list of containers found
+    /// on disk. Anything else is treated as a path to a container.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - The container set on the command line.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if:
+    /// * There's no container at the index. The error lists the containers found on disk.
+    /// * The path isn't a container.
+    fn get_container(value: &str) -> CliResult<PathBuf> {
+        let Ok(index) = value.parse::<usize>() else {
+            let path = self::expand_home(PathBuf::from(value));
+
+            if !containers::is_container(&path) {
+                bail!("No Apple Books container found at: {}", path.display());
+            }
+
+            return Ok(path);
+        };
+
+        let containers = containers::find();
+
+        if let Some(container) = index.checked_sub(1).and_then(|index| containers.get(index)) {
+            return Ok(container.to_owned());
+        }
+
+        if containers.is_empty() {
+            bail!("No Apple Books containers found");
+        }
+
+        let candidates: Vec<String> = containers
+            .iter()
+            .enumerate()
+            .map(|(index, container)| format!("  {}: {}", index + 1, container.display()))
+            .collect();
+
+        bail!(
+            "No Apple Books container at index {index}. Found:\n{}",
+            candidates.join("\n")
+        );
+    }
+}
+
+/// A struct representing the contents of a config file. All values are optional.
+///
+/// ```toml
+/// output-directory = "~/Documents/readstor"
+/// calibre-library = "~/Calibre Library"
+/// templates-directory = "~/Documents/readstor/templates"
+/// template-groups = ["basic"]
+/// template-env = ["OBSIDIAN_VAULT"]
+///
+/// [preprocess]
+/// extract-tags = true
+/// normalize-whitespace = true
+///
+/// [postprocess]
+/// trim-blocks = true
+/// wrap-text = 80
+///
+/// [filter-sets.favorites]
+/// filters = ["tag:#favorite"]
+/// created-after = "2024-01-01"
+/// ```
+///
+/// Paths starting with `~` are expanded to the user's home directory.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct ConfigFile {
+    /// The default output directory.
+    output_directory: Option<PathBuf>,
+
+    /// The default Calibre library.
+    calibre_library: Option<PathBuf>,
+
+    /// The default templates directory.
+    templates_directory: Option<PathBuf>,
+
+    /// The default template-groups to render.
+    template_groups: Vec<String>,
+
+    /// The environment variables to expose to templates.
+    template_env: Vec<String>,
+
+    /// The default pre-process options.
+    preprocess: PreProcessConfig,
+
+    /// The default post-process options.
+    postprocess: PostProcessConfig,
+
+    /// Named sets of filters selectable with `--filter-set`.
+    filter_sets: BTreeMap<String, FilterSetConfig>,
+}
+
+impl ConfigFile {
+    /// Reads and parses a config file.
+    ///
+    /// Returns the default config and no path if no path is set and the default config file
+    /// doesn't exist. The default config file is ignored during development so a developer's
+    /// config doesn't affect tests.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - An optional path to the config file.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the config file cannot be read or parsed.
+    fn load(path: Option<&Path>) -> CliResult<(Self, Option<PathBuf>)> {
+        let path = match path {
+            Some(path) => path.to_owned(),
+            None if utils::is_development_env() => return Ok((Self::default(), None)),
+            None => {
+                let path = paths::get(Directory::Config).join(CONFIG_FILENAME);
+
+                if !path.exists() {
+                    return Ok((Self::default(), None));
+                }
+
+                path
+            }
+        };
+
+        let contents = std::fs::read_to_string(&path).wrap_err(format!(
+            "Failed while reading config file {}",
+            path.display()
+        ))?;
+
+        let file = Self::parse(&contents).wrap_err(format!(
+            "Failed while parsing config file {}",
+            path.display()
+        ))?;
+
+        tracing::debug!(path = %path.display(), "loaded config file");
+
+        Ok((file, Some(path)))
+    }
+
+    /// Parses the contents of a config file.
+    ///
+    /// # Arguments
+    ///
+    /// * `contents` - The contents of the config file.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the contents aren't valid TOML or contain unknown options.
+    fn parse(contents: &str) -> CliResult<Self> {
+        let mut file: Self = toml::from_str(contents)?;
+
+        file.output_directory = file.output_directory.map(self::expand_home);
+        file.templates_directory = file.templates_directory.map(self::expand_home);
+        file.calibre_library = file.calibre_library.map(self::expand_home);
+        file.preprocess.tag_map = file.preprocess.tag_map.map(self::expand_home);
+
+        if let Some(prefix) = file.preprocess.tag_prefix {
+            validate_tag_prefix(&prefix.to_string())
+                .map_err(|error| eyre!("Invalid tag-prefix '{prefix}': {error}"))?;
+        }
+
+        Ok(file)
+    }
+}
+
+/// A struct representing the pre-process options of a config file.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+struct PreProcessConfig {
+    extract_tags: Option<bool>,
+    tag_prefix: Option<char>,
+    tag_chars: Option<String>,
+    lowercase_tags: Option<bool>,
+    strip_tag_prefix: Option<bool>,
+    tag_map: Option<PathBuf>,
+    extract_properties: Option<bool>,
+    extract_fields: Option<bool>,
+    extract_favorites: Option<bool>,
+    normalize_whitespace: Option<bool>,
+    ascii_all: Option<bool>,
+    ascii_symbols: Option<bool>,
+    straighten_quotes: Option<bool>,
+    expand_ellipses: Option<bool>,
+    replace_nbsp: Option<bool>,
+    text_fields: Option<Vec<TextField>>,
+    dedupe: Option<bool>,
+    merge_adjacent: Option<bool>,
+    link_mentions: Option<bool>,
+}
+
+/// A struct representing the post-process options of a config file.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+struct PostProcessConfig {
+    trim_blocks: Option<bool>,
+    wrap_text: Option<usize>,
+}
+
+/// A struct representing a named set of filters in a config file. Filters and dates use the same
+/// syntax as their command-line options.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+struct FilterSetConfig {
+    filters: Vec<String>,
+    favorites: bool,
+    created_after: Option<String>,
+    created_before: Option<String>,
+    modified_after: Option<String>,
+    modified_before: Option<String>,
+}
+
+/// An enum representing where an option's value came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Source {
+    /// The option wasn't set.
+    Default,
+
+    /// The option was set in the config file.
+    File,
+
+    /// The option was set on the command line.
+    CommandLine,
+
+    /// The option was inferred from another option.
+    Inferred,
+}
+
+impl std::fmt::Display for Source {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Default => write!(f, "default"),
+            Self::File => write!(f, "config file"),
+            Self::CommandLine => write!(f, "command line"),
+            Self::Inferred => write!(f, "inferred"),
+        }
+    }
+}
+
+/// Merges an optional value, preferring the command line over the config file, and records its
+/// source.
+///
+/// # Arguments
+///
+/// * `sources` - The sources to record to.
+/// * `name` - The option's name.
+/// * `cli` - The value set on the command line.
+/// * `file` - The value set in the config file.
+fn merge_option<T>(
+    sources: &mut Vec<(&'static str, String, Source)>,
+    name: &'static str,
+    cli: Option<T>,
+    file: Option<T>,
+) -> Option<T>
+where
+    T: DisplayValue,
+{
+    let (value, source) = match (cli, file) {
+        (Some(value), _) => (Some(value), Source::CommandLine),
+        (None, Some(value)) => (Some(value), Source::File),
+        (None, None) => (None, Source::Default),
+    };
+
+    let display = value
+        .as_ref()
+        .map(DisplayValue::display_value)
+        .unwrap_or_default();
+
+    sources.push((name, display, source));
+
+    value
+}
+
+/// Merges a flag, which is set if it's set on either the command line or in the config file, and
+/// records its source.
+///
+/// # Arguments
+///
+/// * `sources` - The sources to record to.
+/// * `name` - The option's name.
+/// * `cli` - Whether the flag was set on the command line.
+/// * `file` - The value set in the config file.
+fn merge_flag(
+    sources: &mut Vec<(&'static str, String, Source)>,
+    name: &'static str,
+    cli: bool,
+    file: Option<bool>,
+) -> bool {
+    let (value, source) = match (cli, file) {
+        (true, _) => (true, Source::CommandLine),
+        (false, Some(value)) => (value, Source::File),
+        (false, None) => (false, Source::Default),
+    };
+
+    sources.push((name, value.to_string(), source));
+
+    value
+}
+
+/// A trait for displaying option values, as [`PathBuf`] doesn't implement [`std::fmt::Display`].
+trait DisplayValue {
+    fn display_value(&self) -> String;
+}
+
+impl DisplayValue for PathBuf {
+    fn display_value(&self) -> String {
+        self.display().to_string()
+    }
+}
+
+impl DisplayValue for usize {
+    fn display_value(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl DisplayValue for char {
+    fn display_value(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl DisplayValue for String {
+    fn display_value(&self) -> String {
+        self.clone()
+    }
+}
+
+impl DisplayValue for Vec<TextField> {
+    fn display_value(&self) -> String {
+        self.iter()
+            .filter_map(ValueEnum::to_possible_value)
+            .map(|value| value.get_name().to_owned())
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+}
+
+/// Infers the export format from a filename's extension.
+///
+/// # Arguments
+///
+/// * `filename` - The name of the file to export to.
+///
+/// # Errors
+///
+/// Will return `Err` if no export format writes files with the extension.
+fn infer_export_format(filename: &str) -> CliResult<ExportFormat> {
+    let extension = Path::new(filename)
+        .extension()
+        .map(|extension| extension.to_string_lossy())
+        .unwrap_or_default();
+
+    let Some(format) = lib::export::ExportFormat::from_extension(&extension) else {
+        let supported = ExportFormat::value_variants()
+            .iter()
+            .filter_map(ValueEnum::to_possible_value)
+            .map(|value| format!(".{}", value.get_name()))
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        bail!("Cannot infer an export format from '{filename}'. Use a file ending in: {supported}");
+    };
+
+    Ok(format.into())
+}
+
+/// Expands a leading `~` to the user's home directory.
+///
+/// # Arguments
+///
+/// * `path` - The path to expand.
+pub fn expand_home(path: PathBuf) -> PathBuf {
+    match path.strip_prefix("~") {
+        Ok(rest) => lib::defaults::HOME_DIRECTORY.join(rest),
+        Err(_) => path,
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    #[test]
+    fn parse_config_file() {
+        let file = ConfigFile::parse(
+            "output-directory = \"~/readstor\"\n\
+             calibre-library = \"~/Calibre Library\"\n\
+             template-groups = [\"basic\"]\n\
+             template-env = [\"OBSIDIAN_VAULT\"]\n\
+             [postprocess]\n\
+             wrap-text = 80\n\
+             [filter-sets.favorites]\n\
+             filters = [\"tag:#favorite\"]\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            file.output_directory,
+            Some(lib::defaults::HOME_DIRECTORY.join("readstor"))
+        );
+        assert_eq!(
+            file.calibre_library,
+            Some(lib::defaults::HOME_DIRECTORY.join("Calibre Library"))
+        );
+        assert_eq!(file.template_groups, ["basic"]);
+        assert_eq!(file.template_env, ["OBSIDIAN_VAULT"]);
+        assert_eq!(file.postprocess.wrap_text, Some(80));
+        assert_eq!(file.filter_sets["favorites"].filters, ["tag:#favorite"]);
+    }
+
+    #[test]
+    fn infer_export_format_from_extension() {
+        assert_eq!(
+            infer_export_format("highlights.jsonl").unwrap(),
+            ExportFormat::JsonLines
+        );
+        assert_eq!(
+            infer_export_format("library.json").unwrap(),
+            ExportFormat::Json
+        );
+        assert_eq!(
+            infer_export_format("highlights.epub").unwrap(),
+            ExportFormat::Epub
+        );
+        assert!(infer_export_format("library.sqlite").is_err());
+    }
+
+    #[test]
+    fn parse_config_file_unknown_option() {
+        assert!(ConfigFile::parse("unknown = true").is_err());
+    }
+
+    #[test]
+    fn parse_config_file_tag_prefix() {
+        let file =
+            ConfigFile::parse("[preprocess]\ntag-prefix = \"@\"\ntag-chars = \"-/\"\n").unwrap();
+
+        assert_eq!(file.preprocess.tag_prefix, Some('@'));
+        assert_eq!(file.preprocess.tag_chars.as_deref(), Some("-/"));
+        assert!(ConfigFile::parse("[preprocess]\ntag-prefix = \"a\"").is_err());
+    }
+
+    #[test]
+    fn parse_config_file_text_fields() {
+        let file =
+            ConfigFile::parse("[preprocess]\ntext-fields = [\"body\", \"notes\"]\n").unwrap();
+
+        assert_eq!(
+            file.preprocess.text_fields,
+            Some(vec![TextField::Body, TextField::Notes])
+        );
+        assert!(ConfigFile::parse("[preprocess]\ntext-fields = [\"chapter\"]").is_err());
+    }
+
+    // Tests that the command line takes precedence over the config file.
+    #[test]
+    fn merge_precedence() {
+        let mut sources = Vec::new();
+
+        assert_eq!(
+            merge_option(&mut sources, "a", Some(1_usize), Some(2)),
+            Some(1)
+        );
+        assert_eq!(
+            merge_option(&mut sources, "b", None, Some(2_usize)),
+            Some(2)
+        );
+        assert_eq!(merge_option::<usize>(&mut sources, "c", None, None), None);
+        assert!(!merge_flag(&mut sources, "d", false, Some(false)));
+        assert!(merge_flag(&mut sources, "e", true, Some(false)));
+
+        let sources: Vec<Source> = sources.into_iter().map(|(_, _, source)| source).collect();
+
+        assert_eq!(
+            sources,
+            [
+                Source::CommandLine,
+                Source::File,
+                Source::Default,
+                Source::File,
+                Source::CommandLine
+            ]
+        );
+    }
 }
 
 #[cfg(test)]
@@ -116,8 +1246,21 @@ pub mod testing {
             Self {
                 platform: Platform::MacOs,
                 data_directory: databases.into(),
+                ios_data_directory: None,
+                snapshot: None,
+                books_directory: None,
+                calibre_library: None,
                 output_directory,
                 is_quiet: true,
+                progress: ProgressMode::Hidden,
+                include_unannotated: false,
+                include_audiobooks: false,
+                id_scheme: IdScheme::default(),
+                is_verbose: false,
+                file: ConfigFile::default(),
+                file_path: None,
+                output_file: None,
+                sources: Vec::new(),
             }
         }
 
@@ -129,8 +1272,21 @@ pub mod testing {
             Self {
                 platform: Platform::IOs,
                 data_directory: plists.into(),
+                ios_data_directory: None,
+                snapshot: None,
+                books_directory: None,
+                calibre_library: None,
                 output_directory,
                 is_quiet: true,
+                progress: ProgressMode::Hidden,
+                include_unannotated: false,
+                include_audiobooks: false,
+                id_scheme: IdScheme::default(),
+                is_verbose: false,
+                file: ConfigFile::default(),
+                file_path: None,
+                output_file: None,
+                sources: Vec::new(),
             }
         }
     }
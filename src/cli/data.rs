@@ -6,17 +6,28 @@ use lib::applebooks::macos::{ABDatabase, ABMacOs};
 use lib::filter::filters;
 use lib::models::annotation::Annotation;
 use lib::models::book::Book;
-use lib::models::entry::{Entries, Entry};
+use lib::models::bookmark::{Audiobook, Bookmark};
+use lib::models::entry::Entries;
+use lib::snapshot::Snapshot;
 
 use super::CliResult;
 
-/// A container struct for storing and managing [`Entry`]s.
+/// A container struct for storing and managing [`Entry`][entry]s.
+///
+/// [entry]: lib::models::entry::Entry
 #[derive(Debug, Default)]
-pub struct Data(Entries);
+pub struct Data {
+    /// The books and their annotations.
+    entries: Entries,
+
+    /// The audiobooks and their bookmarks. Only extracted when requested. See
+    /// [`Data::init_macos_audiobooks()`].
+    audiobooks: Vec<Audiobook>,
+}
 
 impl Data {
     /// Builds [`Book`]s and [`Annotation`]s from macOS's Apple Books databases, converts them to
-    /// [`Entry`]s and appends them to the data model.
+    /// [`Entry`][entry]s and appends them to the data model.
     ///
     /// # Arguments
     ///
@@ -28,31 +39,66 @@ impl Data {
     ///
     /// See [`ABMacOs::extract_books()`] and [`ABMacOs::extract_annotations()`] for information as
     /// these are the only sources of possible errors.
+    ///
+    /// [entry]: lib::models::entry::Entry
     pub fn init_macos(&mut self, path: &Path) -> CliResult<()> {
         let books = ABMacOs::extract_books(path)?;
         let annotations = ABMacOs::extract_annotations(path)?;
 
-        log::debug!(
-            "found {} book(s) in {}",
-            books.len(),
-            ABDatabase::Books.to_string()
+        tracing::debug!(
+            books = books.len(),
+            source = %ABDatabase::Books,
+            "extracted books"
         );
 
-        log::debug!(
-            "found {} annotation(s) in {}",
-            annotations.len(),
-            ABDatabase::Annotations.to_string()
+        tracing::debug!(
+            annotations = annotations.len(),
+            source = %ABDatabase::Annotations,
+            "extracted annotations"
         );
 
         let entries = Self::build_entries(books, annotations);
 
-        self.0.extend(entries);
+        self.entries.extend(entries);
+
+        Ok(())
+    }
+
+    /// Builds [`Audiobook`]s and their [`Bookmark`]s from macOS's Apple Books databases and
+    /// appends them to the data model. Audiobooks without bookmarks are discarded.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path to a directory containing macOS's Apple Books databases.
+    ///
+    /// # Errors
+    ///
+    /// See [`ABMacOs::extract_books()`] and [`ABMacOs::extract_annotations()`] for information as
+    /// these are the only sources of possible errors.
+    pub fn init_macos_audiobooks(&mut self, path: &Path) -> CliResult<()> {
+        let audiobooks = ABMacOs::extract_books::<Audiobook>(path)?;
+        let bookmarks = ABMacOs::extract_annotations::<Bookmark>(path)?;
+
+        tracing::debug!(
+            audiobooks = audiobooks.len(),
+            source = %ABDatabase::Books,
+            "extracted audiobooks"
+        );
+
+        tracing::debug!(
+            bookmarks = bookmarks.len(),
+            source = %ABDatabase::Annotations,
+            "extracted bookmarks"
+        );
+
+        self.audiobooks
+            .extend(Audiobook::from_parts(audiobooks, bookmarks));
 
         Ok(())
     }
 
     /// Builds [`Book`]s and [`Annotation`]s from iOS's Apple Books plists, converts them to
-    /// [`Entry`]s and appends them to the data model.
+    /// [`Entry`][entry]s and appends them to the data model.
     ///
     /// # Arguments
     ///
@@ -64,57 +110,99 @@ impl Data {
     ///
     /// See [`ABIOs::extract_books()`] and [`ABIOs::extract_annotations()`] for information as these
     /// are the only sources of possible errors.
+    ///
+    /// [entry]: lib::models::entry::Entry
     pub fn init_ios(&mut self, path: &Path) -> CliResult<()> {
         let books = ABIOs::extract_books(path)?;
         let annotations = ABIOs::extract_annotations(path)?;
 
-        log::debug!(
-            "found {} book(s) in {}",
-            books.len(),
-            ABPlist::Books.to_string()
+        tracing::debug!(
+            books = books.len(),
+            source = %ABPlist::Books,
+            "extracted books"
         );
 
-        log::debug!(
-            "found {} annotation(s) in {}",
-            annotations.len(),
-            ABPlist::Annotations.to_string()
+        tracing::debug!(
+            annotations = annotations.len(),
+            source = %ABPlist::Annotations,
+            "extracted annotations"
         );
 
         let entries = Self::build_entries(books, annotations);
 
-        self.0.extend(entries);
+        self.entries.extend(entries);
 
         Ok(())
     }
 
-    /// Converts [`Book`]s and [`Annotation`]s to [`Entry`]s, then sorts and filters them before
-    /// adding them to the data model.
+    /// Reads [`Entry`][entry]s from a snapshot and appends them to the data model.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path to a snapshot.
+    ///
+    /// # Errors
+    ///
+    /// See [`Snapshot::read()`] for information as this is the only source of possible errors.
+    ///
+    /// [entry]: lib::models::entry::Entry
+    pub fn init_snapshot(&mut self, path: &Path) -> CliResult<()> {
+        let snapshot = Snapshot::read(path)?;
+
+        tracing::debug!(
+            books = snapshot.header.books,
+            annotations = snapshot.header.annotations,
+            created = %*snapshot.header.created,
+            platform = %snapshot.header.platform,
+            "read snapshot"
+        );
+
+        self.entries.extend(snapshot.into_entries());
+
+        Ok(())
+    }
+
+    /// Builds [`Book`]s and [`Annotation`]s from iOS's Apple Books plists and merges them into the
+    /// data model. See [`Entries::merge()`] for how books and annotations are matched.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path to a directory containing iOS's Apple Books plists.
+    ///
+    /// # Errors
+    ///
+    /// See [`Data::init_ios()`] for information on possible errors.
+    pub fn merge_ios(&mut self, path: &Path) -> CliResult<()> {
+        let mut ios = Self::default();
+        ios.init_ios(path)?;
+
+        self.entries.merge(ios.entries);
+
+        Ok(())
+    }
+
+    /// Removes all [`Entry`][entry]s that have no [`Annotation`]s.
+    ///
+    /// [entry]: lib::models::entry::Entry
+    pub fn discard_unannotated(&mut self) {
+        filters::contains_no_annotations(&mut self.entries);
+    }
+
+    /// Converts [`Book`]s and [`Annotation`]s to [`Entry`][entry]s before adding them to the data
+    /// model.
+    ///
+    /// [entry]: lib::models::entry::Entry
     fn build_entries(books: Vec<Book>, annotations: Vec<Annotation>) -> Entries {
-        // `Entry`s are created from `Book`s. Note that `book.metadata.id` is set as the key for
-        // each entry into the `Data`. This is later used to compare with each `Annotation` to
-        // determine if the `Annotation` belongs to a `Book` and therefore its `Entry`.
-        //
-        // See https://stackoverflow.com/q/69274529/16968574
-        let mut data: Entries = books
-            .into_iter()
-            .map(|book| (book.metadata.id.clone(), Entry::from(book)))
-            .collect();
-
-        // `Annotation`s are pushed onto an `Entry` based on their `book_id`.
-        for annotation in annotations {
-            if let Some(entry) = data.get_mut(&annotation.metadata.book_id) {
-                entry.annotations.push(annotation);
-            }
-        }
-
-        // Remove `Entry`s that have no `Annotation`s.
-        filters::contains_no_annotations(&mut data);
+        let data = Entries::from_parts(books, annotations);
 
         let count_books = Self::iter_books_inner(&data).count();
         let count_annotations = Self::iter_annotations_inner(&data).count();
 
-        log::debug!("created {count_books} Book(s)",);
-        log::debug!("created {count_annotations} Annotation(s)",);
+        tracing::debug!(
+            books = count_books,
+            annotations = count_annotations,
+            "loaded data"
+        );
 
         data
     }
@@ -129,14 +217,19 @@ impl Data {
         self.iter_annotations().count()
     }
 
+    /// Returns all [`Audiobook`]s.
+    pub fn audiobooks(&self) -> &[Audiobook] {
+        &self.audiobooks
+    }
+
     /// Returns an iterator over all [`Book`]s.
     pub fn iter_books(&self) -> impl Iterator<Item = &Book> {
-        Self::iter_books_inner(&self.0)
+        Self::iter_books_inner(&self.entries)
     }
 
     /// Returns an iterator over all [`Annotation`]s.
     pub fn iter_annotations(&self) -> impl Iterator<Item = &Annotation> {
-        Self::iter_annotations_inner(&self.0)
+        Self::iter_annotations_inner(&self.entries)
     }
 
     /// Returns an iterator over all [`Annotation`]s given an [`Entries`] type.
@@ -154,12 +247,12 @@ impl Deref for Data {
     type Target = Entries;
 
     fn deref(&self) -> &Self::Target {
-        &self.0
+        &self.entries
     }
 }
 
 impl DerefMut for Data {
     fn deref_mut(&mut self) -> &mut Entries {
-        &mut self.0
+        &mut self.entries
     }
 }
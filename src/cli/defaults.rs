@@ -2,24 +2,65 @@ use std::path::PathBuf;
 
 use once_cell::sync::Lazy;
 
+use super::paths::{self, Directory};
+
 /// Defines the environment variable key used to determine whether the application is being
 /// developed on or not. If so, the Apple Books databases path is bypassed and redirected to a local
 /// testing/dev database.
 pub const READSTOR_DEV: &str = "READSTOR_DEV";
 
 /// Defines the environment variable key used to set the application's log level. Valid values are:
-/// `error`, `warn`, `info`, `debug` and `trace`.
+/// `error`, `warn`, `info`, `debug` and `trace`, optionally per-module e.g. `warn,lib::sync=debug`.
+/// Overridden by `--log-level`.
 pub const READSTOR_LOG: &str = "READSTOR_LOG";
 
-/// Defines the default output directory.
+/// Defines the default output directory. See [`paths::get()`] for more information.
+///
+/// The full path:
+/// ```plaintext
+/// /users/[user]/Library/Application Support/readstor
+/// ```
+pub static OUTPUT_DIRECTORY: Lazy<PathBuf> = Lazy::new(|| paths::get(Directory::Data));
+
+/// Defines the output directory used before the standard directories were adopted. This is moved
+/// to [`OUTPUT_DIRECTORY`] the first time the default output directory is used.
 ///
 /// The full path:
 /// ```plaintext
 /// /users/[user]/.readstor
 /// ```
-pub static OUTPUT_DIRECTORY: Lazy<PathBuf> =
+pub static LEGACY_OUTPUT_DIRECTORY: Lazy<PathBuf> =
     Lazy::new(|| lib::defaults::HOME_DIRECTORY.join(".readstor"));
 
+/// Defines the directory source data is copied to before being read. See [`paths::get()`] for more
+/// information.
+///
+/// The full path:
+/// ```plaintext
+/// /users/[user]/Library/Caches/readstor
+/// ```
+pub static CACHE_DIRECTORY: Lazy<PathBuf> = Lazy::new(|| paths::get(Directory::Cache));
+
+/// Defines the directory launchd agents are installed to.
+///
+/// The full path:
+/// ```plaintext
+/// /users/[user]/Library/LaunchAgents
+/// ```
+pub static LAUNCH_AGENTS_DIRECTORY: Lazy<PathBuf> = Lazy::new(|| {
+    lib::defaults::HOME_DIRECTORY
+        .join("Library")
+        .join("LaunchAgents")
+});
+
+/// Defines the directory scheduled commands log to. See [`paths::get()`] for more information.
+///
+/// The full path:
+/// ```plaintext
+/// /users/[user]/Library/Logs/readstor
+/// ```
+pub static LOGS_DIRECTORY: Lazy<PathBuf> = Lazy::new(|| paths::get(Directory::Logs));
+
 /// Defines the default template string. This is used as a fallback if the user doesn't supply a
 /// templates directory.
 pub static TEMPLATE: &str = include_str!(concat!(
@@ -27,6 +68,27 @@ pub static TEMPLATE: &str = include_str!(concat!(
     "/templates/basic/basic.jinja2"
 ));
 
+/// Defines the org-mode preset's template string. Each book is rendered as a top-level heading and
+/// each annotation as a nested heading with a properties drawer.
+pub static TEMPLATE_ORG: &str = include_str!(concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/templates/org/org.jinja2"
+));
+
+/// Defines the Logseq preset's template string. Each book is rendered as a top-level block and each
+/// annotation as a nested block with properties.
+pub static TEMPLATE_LOGSEQ: &str = include_str!(concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/templates/logseq/logseq.jinja2"
+));
+
+/// Defines the review preset's template string. Each book is rendered with its annotations' dates
+/// and a checkbox to mark them as reviewed. This is the default template for `pack`.
+pub static TEMPLATE_REVIEW: &str = include_str!(concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/templates/review/review.jinja2"
+));
+
 /// Defines the root path to the test/mock databases.
 pub static TEST_DATABASES_DIRECTORY: Lazy<PathBuf> = Lazy::new(|| {
     let mut path = lib::defaults::CRATE_ROOT.to_owned();
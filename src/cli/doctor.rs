@@ -0,0 +1,166 @@
+use std::path::{Path, PathBuf};
+
+use color_eyre::eyre::bail;
+use color_eyre::{Report, Section};
+
+use lib::applebooks::macos::permissions;
+use lib::applebooks::macos::utils::{applebooks_is_running, APPLEBOOKS_VERSION};
+use lib::applebooks::macos::ABDatabase;
+use lib::result::Error;
+
+use crate::CliResult;
+
+use super::{defaults, utils};
+
+/// Step-by-step instructions for granting Full Disk Access.
+const FULL_DISK_ACCESS_GUIDANCE: &str = "\
+macOS is blocking access to Apple Books' data. To grant access:
+  1. Open System Settings > Privacy & Security > Full Disk Access.
+  2. Enable the app running readstor e.g. Terminal or iTerm. Use '+' to add it if it's missing.
+  3. Quit and re-open the app for the change to take effect.
+Run `readstor doctor` to check again.";
+
+/// An enum representing the result of a single check.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Status {
+    Ok,
+    Warn,
+    Fail,
+}
+
+impl std::fmt::Display for Status {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Ok => f.pad("ok"),
+            Self::Warn => f.pad("warn"),
+            Self::Fail => f.pad("fail"),
+        }
+    }
+}
+
+/// Checks whether macOS's Apple Books data can be read and prints the result of each check.
+///
+/// # Arguments
+///
+/// * `data_directory` - An optional directory containing macOS's Apple Books databases. Defaults
+///   to Apple Books' container.
+///
+/// # Errors
+///
+/// Will return `Err` if any checks fail.
+pub fn run(data_directory: Option<PathBuf>) -> CliResult<()> {
+    let data_directory = data_directory.unwrap_or_else(|| {
+        if utils::is_development_env() {
+            defaults::TEST_DATABASES_DIRECTORY.join("books-annotated")
+        } else {
+            lib::applebooks::macos::defaults::DATA_DIRECTORY.to_owned()
+        }
+    });
+
+    println!(
+        "Checking Apple Books data at {}\n",
+        data_directory.display()
+    );
+
+    let (checks, is_permission_denied) = self::checks(&data_directory);
+
+    for (status, message) in &checks {
+        println!("  {status:<4}  {message}");
+    }
+
+    if is_permission_denied {
+        println!("\n{FULL_DISK_ACCESS_GUIDANCE}");
+    }
+
+    let failed = checks
+        .iter()
+        .filter(|(status, _)| *status == Status::Fail)
+        .count();
+
+    if failed > 0 {
+        bail!("{failed} check(s) failed");
+    }
+
+    println!("\nAll checks passed.");
+
+    Ok(())
+}
+
+/// Attaches instructions for granting Full Disk Access to an error caused by macOS denying access
+/// to Apple Books' data.
+///
+/// # Arguments
+///
+/// * `report` - The error.
+pub fn with_guidance(report: Report) -> Report {
+    let is_permission_denied = report.chain().any(|error| {
+        matches!(
+            error.downcast_ref::<Error>(),
+            Some(Error::MacOsPermissionDenied { .. })
+        )
+    });
+
+    if is_permission_denied {
+        report.suggestion(FULL_DISK_ACCESS_GUIDANCE)
+    } else {
+        report
+    }
+}
+
+/// Runs each check in order and returns their results along with whether any failed because macOS
+/// denied access. The database checks are skipped if the data directory can't be read.
+///
+/// # Arguments
+///
+/// * `data_directory` - The directory containing macOS's Apple Books databases.
+fn checks(data_directory: &Path) -> (Vec<(Status, String)>, bool) {
+    let mut checks = Vec::new();
+
+    if APPLEBOOKS_VERSION.as_str() == "v?" {
+        checks.push((Status::Warn, "Could not find Apple Books".to_owned()));
+    } else {
+        checks.push((
+            Status::Ok,
+            format!("Found Apple Books {}", APPLEBOOKS_VERSION.as_str()),
+        ));
+    }
+
+    if applebooks_is_running() {
+        checks.push((
+            Status::Warn,
+            "Apple Books is running. Quit it or use `-f, --force`".to_owned(),
+        ));
+    }
+
+    if let Err(error) = permissions::check_directory(data_directory) {
+        if matches!(error, Error::MacOsPermissionDenied { .. }) {
+            checks.push((Status::Fail, error.to_string()));
+            return (checks, true);
+        }
+
+        checks.push((
+            Status::Fail,
+            format!("Could not read data directory: {error}"),
+        ));
+        return (checks, false);
+    }
+
+    checks.push((Status::Ok, "Data directory is readable".to_owned()));
+
+    let mut is_permission_denied = false;
+
+    for database in [ABDatabase::Books, ABDatabase::Annotations] {
+        match permissions::check_database(data_directory, database) {
+            Ok(path) => checks.push((
+                Status::Ok,
+                format!("Found {database} database at {}", path.display()),
+            )),
+            Err(error) => {
+                is_permission_denied |= matches!(error, Error::MacOsPermissionDenied { .. });
+                checks.push((Status::Fail, format!("{database}: {error}")));
+            }
+        }
+    }
+
+    (checks, is_permission_denied)
+}
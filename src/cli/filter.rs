@@ -1,8 +1,11 @@
 use std::str::FromStr;
 
+use chrono::{DateTime, Duration, Local, Months, NaiveDate, Utc};
 use once_cell::sync::Lazy;
 use regex::Regex;
 
+use lib::models::annotation::AnnotationKind;
+
 static RE_FILTER_QUERY: Lazy<Regex> = Lazy::new(|| {
     Regex::new(r"^(?P<operator>[?*=]?)(?P<field>\w*):(?P<query>.*)$").unwrap()
     //            └───┬──────────────┘└───────────┬┘ └───┬───────┘
@@ -21,6 +24,9 @@ static RE_FILTER_QUERY: Lazy<Regex> = Lazy::new(|| {
     //   The query string.
 });
 
+static RE_RELATIVE_DATE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^(?P<amount>\d+)(?P<unit>[dwmy])$").unwrap());
+
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub enum FilterType {
     /// Filter books by their title
@@ -40,6 +46,37 @@ pub enum FilterType {
         query: Vec<String>,
         operator: FilterOperator,
     },
+
+    /// Filter annotations by their body/notes
+    Body {
+        query: Vec<String>,
+        operator: FilterOperator,
+    },
+
+    /// Filter annotations by their created/modified dates
+    DateRange {
+        field: DateField,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+    },
+
+    /// Filter books by a list of ids/title patterns
+    BookList(lib::filter::BookList),
+
+    /// Filter annotations by their kind
+    Kind(Vec<AnnotationKind>),
+
+    /// Filter annotations marked as favorites
+    Favorites,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum DateField {
+    /// Filter by the annotation's created date
+    Created,
+
+    /// Filter by the annotation's modified date
+    Modified,
 }
 
 #[derive(Debug, Default, Clone, Copy, Eq, PartialEq)]
@@ -87,6 +124,19 @@ impl FromStr for FilterType {
             "title" => Self::Title { query, operator },
             "author" => Self::Author { query, operator },
             "tags" | "tag" => Self::Tags { query, operator },
+            "body" | "text" => Self::Body { query, operator },
+            "kind" => {
+                if operator != FilterOperator::Any {
+                    return Err("the 'kind' field only supports the '?' operator".into());
+                }
+
+                Self::Kind(
+                    query
+                        .iter()
+                        .map(|kind| parse_kind(kind))
+                        .collect::<Result<_, _>>()?,
+                )
+            }
             _ => return Err(format!("invalid field: '{field}'")),
         };
 
@@ -109,6 +159,99 @@ impl FromStr for FilterOperator {
     }
 }
 
+/// Parses an annotation kind used for filtering. Accepts `highlight`, `underline` or `note`.
+fn parse_kind(value: &str) -> Result<AnnotationKind, String> {
+    match value.to_lowercase().as_str() {
+        "highlight" | "highlights" => Ok(AnnotationKind::Highlight),
+        "underline" | "underlines" => Ok(AnnotationKind::Underline),
+        "note" | "notes" => Ok(AnnotationKind::Note),
+        _ => Err(format!(
+            "invalid kind: '{value}', expected 'highlight', 'underline' or 'note'"
+        )),
+    }
+}
+
+/// Parses a date used for filtering.
+///
+/// Accepts either an ISO date e.g. `2024-01-01`, an RFC 3339 datetime e.g.
+/// `2024-01-01T12:00:00Z` or a relative date e.g. `30d`. Relative dates are counted back from now
+/// and support the following units: `d` (days), `w` (weeks), `m` (months) and `y` (years). ISO
+/// dates are interpreted as midnight in the local timezone.
+pub fn parse_date(value: &str) -> Result<DateTime<Utc>, String> {
+    parse_date_from(value, Utc::now())
+}
+
+/// Reads and parses a file listing books to include and exclude. See [`lib::filter::BookList`] for
+/// the file's syntax.
+pub fn parse_book_list(value: &str) -> Result<lib::filter::BookList, String> {
+    let contents =
+        std::fs::read_to_string(value).map_err(|error| format!("cannot read file: {error}"))?;
+
+    let list = lib::filter::BookList::parse(&contents);
+
+    if list.is_empty() {
+        return Err("file doesn't list any books".into());
+    }
+
+    Ok(list)
+}
+
+/// Parses a date used for filtering relative to `now`. See [`parse_date`] for more information.
+fn parse_date_from(value: &str, now: DateTime<Utc>) -> Result<DateTime<Utc>, String> {
+    let value = value.trim();
+
+    if let Some(captures) = RE_RELATIVE_DATE.captures(value) {
+        // These unwraps are safe as they will only panic if the capture-group name does not exist.
+        // These are all defined above.
+        let amount = captures.name("amount").unwrap().as_str();
+        let unit = captures.name("unit").unwrap().as_str();
+
+        let amount: u32 = amount
+            .parse()
+            .map_err(|_| format!("invalid relative date: '{value}'"))?;
+
+        let date = match unit {
+            "d" => now.checked_sub_signed(Duration::days(amount.into())),
+            "w" => now.checked_sub_signed(Duration::weeks(amount.into())),
+            "m" => now.checked_sub_months(Months::new(amount)),
+            "y" => amount
+                .checked_mul(12)
+                .and_then(|months| now.checked_sub_months(Months::new(months))),
+            _ => None,
+        };
+
+        return date.ok_or_else(|| format!("invalid relative date: '{value}'"));
+    }
+
+    if let Ok(date) = DateTime::parse_from_rfc3339(value) {
+        return Ok(date.with_timezone(&Utc));
+    }
+
+    if let Ok(date) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        // This unwrap is safe as midnight is always a valid time.
+        let date = date.and_hms_opt(0, 0, 0).unwrap();
+
+        if let Some(date) = date.and_local_timezone(Local).earliest() {
+            return Ok(date.with_timezone(&Utc));
+        }
+
+        return Ok(date.and_utc());
+    }
+
+    Err(format!(
+        "invalid date: '{value}', expected 'YYYY-MM-DD' or a relative date e.g. '30d'"
+    ))
+}
+
+impl From<DateField> for lib::filter::DateField {
+    fn from(field: DateField) -> Self {
+        match field {
+            DateField::Created => Self::Created,
+            DateField::Modified => Self::Modified,
+        }
+    }
+}
+
 impl From<FilterOperator> for lib::filter::FilterOperator {
     fn from(filter_operator: FilterOperator) -> Self {
         match filter_operator {
@@ -134,6 +277,18 @@ impl From<FilterType> for lib::filter::FilterType {
                 query,
                 operator: operator.into(),
             },
+            FilterType::Body { query, operator } => Self::Body {
+                query,
+                operator: operator.into(),
+            },
+            FilterType::DateRange { field, from, to } => Self::DateRange {
+                field: field.into(),
+                from,
+                to,
+            },
+            FilterType::BookList(list) => Self::BookList(list),
+            FilterType::Kind(kinds) => Self::Kind(kinds),
+            FilterType::Favorites => Self::Favorites,
         }
     }
 }
@@ -251,4 +406,121 @@ mod test {
             );
         }
     }
+
+    // Tests that body filters are properly parsed.
+    mod parse_filter_body {
+
+        use super::*;
+
+        #[test]
+        fn body_any() {
+            assert_eq!(
+                FilterType::from_str("?body:picture done").unwrap(),
+                FilterType::Body {
+                    query: vec!["picture".to_string(), "done".to_string()],
+                    operator: FilterOperator::Any,
+                }
+            );
+        }
+
+        #[test]
+        fn body_exact_alias() {
+            assert_eq!(
+                FilterType::from_str("=text:not made for you").unwrap(),
+                FilterType::Body {
+                    query: vec![
+                        "not".to_string(),
+                        "made".to_string(),
+                        "for".to_string(),
+                        "you".to_string(),
+                    ],
+                    operator: FilterOperator::Exact,
+                }
+            );
+        }
+
+        #[test]
+        fn kind() {
+            assert_eq!(
+                FilterType::from_str("kind:highlight Underline").unwrap(),
+                FilterType::Kind(vec![AnnotationKind::Highlight, AnnotationKind::Underline])
+            );
+        }
+
+        #[test]
+        fn kind_invalid() {
+            assert!(FilterType::from_str("kind:bookmark").is_err());
+            assert!(FilterType::from_str("*kind:highlight underline").is_err());
+        }
+    }
+
+    // Tests that dates are properly parsed.
+    mod parse_date {
+
+        use super::*;
+
+        fn now() -> DateTime<Utc> {
+            "2024-03-31T12:00:00Z".parse().unwrap()
+        }
+
+        #[test]
+        fn rfc3339() {
+            assert_eq!(
+                parse_date_from("2024-01-01T08:30:00Z", now()).unwrap(),
+                "2024-01-01T08:30:00Z".parse::<DateTime<Utc>>().unwrap()
+            );
+        }
+
+        #[test]
+        fn iso_date() {
+            let expected = NaiveDate::from_ymd_opt(2024, 1, 1)
+                .unwrap()
+                .and_hms_opt(0, 0, 0)
+                .unwrap()
+                .and_local_timezone(Local)
+                .earliest()
+                .unwrap();
+
+            assert_eq!(parse_date_from("2024-01-01", now()).unwrap(), expected);
+        }
+
+        #[test]
+        fn relative_days() {
+            assert_eq!(
+                parse_date_from("30d", now()).unwrap(),
+                "2024-03-01T12:00:00Z".parse::<DateTime<Utc>>().unwrap()
+            );
+        }
+
+        #[test]
+        fn relative_weeks() {
+            assert_eq!(
+                parse_date_from("2w", now()).unwrap(),
+                "2024-03-17T12:00:00Z".parse::<DateTime<Utc>>().unwrap()
+            );
+        }
+
+        #[test]
+        fn relative_months() {
+            assert_eq!(
+                parse_date_from("1m", now()).unwrap(),
+                "2024-02-29T12:00:00Z".parse::<DateTime<Utc>>().unwrap()
+            );
+        }
+
+        #[test]
+        fn relative_years() {
+            assert_eq!(
+                parse_date_from("1y", now()).unwrap(),
+                "2023-03-31T12:00:00Z".parse::<DateTime<Utc>>().unwrap()
+            );
+        }
+
+        #[test]
+        fn invalid() {
+            assert!(parse_date_from("yesterday", now()).is_err());
+            assert!(parse_date_from("30x", now()).is_err());
+            assert!(parse_date_from("2024-13-01", now()).is_err());
+        }
+    }
 }
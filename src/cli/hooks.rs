@@ -0,0 +1,157 @@
+use std::path::PathBuf;
+
+use color_eyre::eyre::{bail, WrapErr};
+
+use crate::CliResult;
+
+use super::report::RunStats;
+
+/// A struct representing the outcome of a run. This is passed to hooks via environment variables.
+#[derive(Debug, Default)]
+pub struct RunSummary {
+    /// The number of books processed.
+    pub books: usize,
+
+    /// The number of annotations processed.
+    pub annotations: usize,
+
+    /// The path the command wrote to, if any.
+    pub output: Option<PathBuf>,
+
+    /// The stats collected while the command ran. Only written to the `--report`.
+    pub stats: RunStats,
+}
+
+/// Runs a user-defined shell command after a run.
+///
+/// The command is run with `sh -c` and inherits the terminal's output. The following environment
+/// variables describe the run:
+///
+/// * `READSTOR_COMMAND` - The name of the command that was run e.g. `export`.
+/// * `READSTOR_STATUS` - Either `success` or `failure`.
+/// * `READSTOR_BOOKS` - The number of books processed.
+/// * `READSTOR_ANNOTATIONS` - The number of annotations processed.
+/// * `READSTOR_OUTPUT` - The path the command wrote to. Only set if the command writes to disk.
+/// * `READSTOR_ERROR` - The error message. Only set if the command failed.
+///
+/// # Arguments
+///
+/// * `hook` - The shell command to run.
+/// * `command` - The name of the command that was run.
+/// * `result` - The command's result.
+/// * `summary` - The run's summary.
+///
+/// # Errors
+///
+/// Will return `Err` if the hook cannot be run or exits with an error.
+pub fn run(
+    hook: &str,
+    command: &str,
+    result: &CliResult<()>,
+    summary: &RunSummary,
+) -> CliResult<()> {
+    tracing::debug!(%hook, "running hook");
+
+    let status = std::process::Command::new("sh")
+        .args(["-c", hook])
+        .envs(self::environment(command, result, summary))
+        .status()
+        .wrap_err(format!("Failed while running hook: '{hook}'"))?;
+
+    if !status.success() {
+        bail!("Hook exited with {status}: '{hook}'");
+    }
+
+    Ok(())
+}
+
+/// Returns the environment variables describing a run.
+///
+/// # Arguments
+///
+/// * `command` - The name of the command that was run.
+/// * `result` - The command's result.
+/// * `summary` - The run's summary.
+fn environment(
+    command: &str,
+    result: &CliResult<()>,
+    summary: &RunSummary,
+) -> Vec<(&'static str, String)> {
+    let mut environment = vec![
+        ("READSTOR_COMMAND", command.to_owned()),
+        (
+            "READSTOR_STATUS",
+            if result.is_ok() { "success" } else { "failure" }.to_owned(),
+        ),
+        ("READSTOR_BOOKS", summary.books.to_string()),
+        ("READSTOR_ANNOTATIONS", summary.annotations.to_string()),
+    ];
+
+    if let Some(output) = &summary.output {
+        environment.push(("READSTOR_OUTPUT", output.display().to_string()));
+    }
+
+    if let Err(error) = result {
+        environment.push(("READSTOR_ERROR", error.to_string()));
+    }
+
+    environment
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    use color_eyre::eyre::eyre;
+
+    #[test]
+    fn environment_success() {
+        let summary = RunSummary {
+            books: 2,
+            annotations: 10,
+            output: Some(PathBuf::from("/tmp/readstor")),
+            stats: RunStats::default(),
+        };
+
+        assert_eq!(
+            environment("export", &Ok(()), &summary),
+            [
+                ("READSTOR_COMMAND", "export".to_string()),
+                ("READSTOR_STATUS", "success".to_string()),
+                ("READSTOR_BOOKS", "2".to_string()),
+                ("READSTOR_ANNOTATIONS", "10".to_string()),
+                ("READSTOR_OUTPUT", "/tmp/readstor".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn environment_failure() {
+        let environment = environment(
+            "backup",
+            &Err(eyre!("Failed while backing-up")),
+            &RunSummary::default(),
+        );
+
+        assert!(environment.contains(&("READSTOR_STATUS", "failure".to_string())));
+        assert!(environment.contains(&("READSTOR_ERROR", "Failed while backing-up".to_string())));
+        assert!(!environment
+            .iter()
+            .any(|(name, _)| *name == "READSTOR_OUTPUT"));
+    }
+
+    #[test]
+    fn run_exit_status() {
+        let summary = RunSummary::default();
+
+        assert!(run(
+            "test \"$READSTOR_STATUS\" = success",
+            "export",
+            &Ok(()),
+            &summary
+        )
+        .is_ok());
+        assert!(run("exit 1", "export", &Ok(()), &summary).is_err());
+    }
+}
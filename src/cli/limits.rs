@@ -0,0 +1,157 @@
+/// The units accepted by [`parse_size()`] and used by [`format_size()`], in ascending order.
+const SIZE_UNITS: [&str; 4] = ["B", "KB", "MB", "GB"];
+
+/// A struct representing the limits a render's output must stay within.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct OutputLimits {
+    /// The maximum number of files to write.
+    pub max_files: Option<usize>,
+
+    /// The maximum total size of all files to write in bytes.
+    pub max_total_size: Option<u64>,
+}
+
+impl OutputLimits {
+    /// Returns `true` if no limits are set.
+    pub fn is_empty(&self) -> bool {
+        self.max_files.is_none() && self.max_total_size.is_none()
+    }
+
+    /// Returns a message for each limit the output exceeds.
+    ///
+    /// # Arguments
+    ///
+    /// * `files` - The number of files to write.
+    /// * `size` - The total size of all files to write in bytes.
+    pub fn exceeded(&self, files: usize, size: u64) -> Vec<String> {
+        let mut exceeded = Vec::new();
+
+        if let Some(max) = self.max_files.filter(|max| files > *max) {
+            exceeded.push(format!("{files} files exceed --max-files {max}"));
+        }
+
+        if let Some(max) = self.max_total_size.filter(|max| size > *max) {
+            exceeded.push(format!(
+                "{} exceeds --max-total-size {}",
+                self::format_size(size),
+                self::format_size(max)
+            ));
+        }
+
+        exceeded
+    }
+}
+
+/// Parses a size e.g. `500MB` or `1.5GB` into bytes. Units are case-insensitive and powers of
+/// 1024. A number without a unit is in bytes.
+///
+/// # Arguments
+///
+/// * `value` - The size to parse.
+///
+/// # Errors
+///
+/// Will return `Err` if the size is invalid.
+pub fn parse_size(value: &str) -> Result<u64, String> {
+    let value = value.trim().to_uppercase();
+
+    let split = value
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(value.len());
+
+    let (number, unit) = value.split_at(split);
+
+    let unit = match unit.trim() {
+        "" => "B",
+        "K" => "KB",
+        "M" => "MB",
+        "G" => "GB",
+        unit => unit,
+    };
+
+    let exponent = SIZE_UNITS
+        .iter()
+        .position(|u| *u == unit)
+        .ok_or_else(|| format!("invalid unit, expected one of: {}", SIZE_UNITS.join(", ")))?;
+
+    let number: f64 = number.parse().map_err(|_| "invalid size".to_owned())?;
+
+    let multiplier = 1_u64 << (10 * exponent);
+
+    #[allow(
+        clippy::cast_possible_truncation,
+        clippy::cast_precision_loss,
+        clippy::cast_sign_loss
+    )]
+    let bytes = (number * multiplier as f64).round() as u64;
+
+    Ok(bytes)
+}
+
+/// Formats a size in bytes with the largest unit that keeps it above one e.g. `1.5 MB`.
+///
+/// # Arguments
+///
+/// * `bytes` - The size in bytes.
+pub fn format_size(bytes: u64) -> String {
+    #[allow(clippy::cast_precision_loss)]
+    let mut size = bytes as f64;
+    let mut unit = 0;
+
+    while size >= 1024.0 && unit < SIZE_UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+
+    if unit == 0 {
+        format!("{bytes} B")
+    } else {
+        format!("{size:.1} {}", SIZE_UNITS[unit])
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    #[test]
+    fn parse_size_valid() {
+        assert_eq!(parse_size("512"), Ok(512));
+        assert_eq!(parse_size("2KB"), Ok(2048));
+        assert_eq!(parse_size("1.5 mb"), Ok(1_572_864));
+        assert_eq!(parse_size("1G"), Ok(1_073_741_824));
+    }
+
+    #[test]
+    fn parse_size_invalid() {
+        assert!(parse_size("").is_err());
+        assert!(parse_size("MB").is_err());
+        assert!(parse_size("10TB").is_err());
+        assert!(parse_size("1.2.3MB").is_err());
+    }
+
+    #[test]
+    fn format_size_units() {
+        assert_eq!(format_size(512), "512 B");
+        assert_eq!(format_size(1_572_864), "1.5 MB");
+        assert_eq!(format_size(1_073_741_824), "1.0 GB");
+    }
+
+    #[test]
+    fn exceeded() {
+        let limits = OutputLimits {
+            max_files: Some(10),
+            max_total_size: Some(1024),
+        };
+
+        assert!(limits.exceeded(10, 1024).is_empty());
+        assert_eq!(
+            limits.exceeded(11, 2048),
+            [
+                "11 files exceed --max-files 10",
+                "2.0 KB exceeds --max-total-size 1.0 KB"
+            ]
+        );
+    }
+}
@@ -1,110 +1,1037 @@
 pub mod app;
 pub mod args;
+pub mod completions;
 pub mod config;
 pub mod data;
 pub mod defaults;
+pub mod doctor;
 pub mod filter;
+pub mod hooks;
+pub mod limits;
+pub mod notify;
+pub mod paths;
+pub mod progress;
+pub mod report;
+pub mod schedule;
+pub mod shortcut;
+pub mod table;
+pub mod templates;
 pub mod utils;
 
+use std::time::Instant;
+
+use color_eyre::eyre::{bail, WrapErr};
+
 use lib::applebooks::macos::utils::applebooks_is_running;
 
 use app::App;
-use args::{Command, Platform};
+use args::{
+    Analysis, Command, GlobalOptions, ImportAction, IndexAction, Platform, ScheduleAction,
+    SnapshotAction, SyncService, TemplatesAction,
+};
 use config::Config;
+use hooks::RunSummary;
 
 pub type CliResult<T> = color_eyre::Result<T>;
 
 pub fn run(command: Command) -> CliResult<()> {
-    log::debug!("{:#?}", &command);
+    self::run_with_summary(command)
+        .0
+        .map_err(doctor::with_guidance)
+}
+
+/// Runs a command and returns its result along with a summary of the run.
+///
+/// # Arguments
+///
+/// * `command` - The command to run.
+pub fn run_with_summary(command: Command) -> (CliResult<()>, RunSummary) {
+    let name = command.name();
+
+    // Every message logged during the run is tagged with the command's name.
+    let _span = tracing::info_span!("run", command = name).entered();
+
+    tracing::info!("started");
+    tracing::debug!(?command, "running command");
+
+    let options = command.global_options();
+    let notify = options.is_some_and(|options| options.notify);
+    let webhook = options.and_then(|options| options.notify_webhook.clone());
+    let on_success = options.and_then(|options| options.on_success.clone());
+    let on_failure = options.and_then(|options| options.on_failure.clone());
+    let report = options.and_then(|options| options.report.clone());
+
+    let start = Instant::now();
+    let mut summary = RunSummary::default();
+    let mut result = self::run_command(command, &mut summary);
+    let elapsed = start.elapsed();
+
+    match &result {
+        Ok(()) => tracing::info!(
+            books = summary.books,
+            annotations = summary.annotations,
+            ?elapsed,
+            "finished"
+        ),
+        Err(error) => tracing::error!(?elapsed, "failed: {error}"),
+    }
+
+    match (&result, on_success, on_failure) {
+        // A failing `--on-success` hook fails the run so that e.g. a failed `git push` is noticed.
+        (Ok(()), Some(hook), _) => {
+            result = hooks::run(&hook, name, &result, &summary)
+                .wrap_err("Failed while running --on-success hook");
+        }
+        // The original error is kept so a failing `--on-failure` hook is only logged.
+        (Err(_), _, Some(hook)) => {
+            if let Err(error) = hooks::run(&hook, name, &result, &summary) {
+                tracing::warn!(%error, "failed while running --on-failure hook");
+            }
+        }
+        _ => {}
+    }
+
+    if notify {
+        notify::send_notification(name, &result);
+    }
+
+    if let Some(url) = webhook {
+        notify::send_webhook(&url, name, &result);
+    }
+
+    if let Some(path) = report {
+        if let Err(error) = report::write(&path, name, &result, &summary, elapsed) {
+            if result.is_ok() {
+                result = Err(error);
+            } else {
+                tracing::warn!(%error, "failed while writing --report");
+            }
+        }
+    }
+
+    (result, summary)
+}
+
+#[allow(clippy::too_many_lines)]
+fn run_command(command: Command, summary: &mut RunSummary) -> CliResult<()> {
+    // Only `export` can write to a file, see `Config::merge_export_options()`.
+    if !matches!(command, Command::Export { .. }) {
+        if let Some(path) = command
+            .global_options()
+            .and_then(|options| options.output_directory.as_ref())
+            .filter(|path| !path.exists())
+        {
+            bail!("Output directory does not exist: {}", path.display());
+        }
+    }
 
     match command {
         Command::Render {
             platform,
-            render_options,
-            filter_options,
-            preprocess_options,
-            postprocess_options,
+            mut render_options,
+            mut filter_options,
+            sort_options,
+            mut preprocess_options,
+            mut postprocess_options,
             global_options,
         } => {
-            if warn_and_exit(platform, global_options.is_force) {
+            if warn_and_exit(platform, &global_options) {
                 return Ok(());
             }
 
-            let config = Config::new(platform.into(), global_options)?;
+            let mut config = Config::new(platform, global_options)?;
+            config.merge_render_options(&mut render_options);
+            config.merge_filter_options(&mut filter_options)?;
+            config.merge_preprocess_options(&mut preprocess_options);
+            config.merge_postprocess_options(&mut postprocess_options);
+            config.print_sources();
 
             let mut app = App::new(config)?.into_render(render_options)?;
 
-            if !filter_options.filter_types.is_empty() {
-                app.run_filters(&filter_options);
+            app.run_filters(&filter_options);
+            app.run_sort(sort_options);
 
-                if !filter_options.auto_confirm && !app.confirm_filter_results() {
-                    return Ok(());
-                }
+            *summary = app.summary();
+
+            if !filter_options.is_empty()
+                && !filter_options.auto_confirm
+                && !app.confirm_filter_results()?
+            {
+                return Ok(());
             }
 
             app.print(format!("Rendering {platform} annotations..."));
 
-            app.run_preprocesses(preprocess_options);
-            app.render()?;
-            app.run_postprocesses(postprocess_options);
-            app.write()?;
+            app.run_preprocesses(&preprocess_options)?;
+            app.render(postprocess_options)?;
+
+            summary.stats = app.stats();
         }
         Command::Export {
             platform,
-            export_options,
-            filter_options,
-            preprocess_options,
+            mut export_options,
+            mut filter_options,
+            sort_options,
+            mut preprocess_options,
+            upload_options,
             global_options,
         } => {
-            if warn_and_exit(platform, global_options.is_force) {
+            if warn_and_exit(platform, &global_options) {
                 return Ok(());
             }
 
-            let config = Config::new(platform.into(), global_options)?;
+            let mut config = Config::new(platform, global_options)?;
+            config.merge_export_options(&mut export_options)?;
+            config.merge_filter_options(&mut filter_options)?;
+            config.merge_preprocess_options(&mut preprocess_options);
+            config.print_sources();
 
             let mut app = App::new(config)?.into_export(export_options);
 
-            if !filter_options.filter_types.is_empty() {
-                app.run_filters(&filter_options);
+            app.run_filters(&filter_options);
+            app.run_sort(sort_options);
 
-                if !filter_options.auto_confirm && !app.confirm_filter_results() {
-                    return Ok(());
-                }
+            *summary = app.summary();
+
+            if !filter_options.is_empty()
+                && !filter_options.auto_confirm
+                && !app.confirm_filter_results()?
+            {
+                return Ok(());
             }
 
             app.print(format!("Exporting {platform} annotations..."));
 
-            app.run_preprocesses(preprocess_options);
-            app.export()?;
+            app.run_preprocesses(&preprocess_options)?;
+            let path = app.export()?;
+
+            summary.stats = app.stats();
+
+            summary.output = Some(path.clone());
+
+            if let Some(target) = upload_options.upload {
+                app.print(format!("Uploading export to {target}..."));
+                app.upload(&target, &path)?;
+            }
         }
         Command::Backup {
             platform,
             backup_options,
+            upload_options,
             global_options,
         } => {
-            if warn_and_exit(platform, global_options.is_force) {
+            if let Platform::All = platform {
+                bail!("The `all` platform isn't supported by the `backup` command");
+            }
+
+            if global_options.snapshot.is_some() {
+                bail!("The `--snapshot` option isn't supported by the `backup` command");
+            }
+
+            if warn_and_exit(platform, &global_options) {
                 return Ok(());
             }
 
-            let config = Config::new(platform.into(), global_options)?;
+            let config = Config::new(platform, global_options)?;
+            config.print_sources();
 
-            let app = App::new(config)?.into_backup(backup_options);
+            let mut app = App::new(config)?.into_backup(backup_options);
 
             app.print(format!("Backing-up {platform} data..."));
 
-            app.backup()?;
+            *summary = app.summary();
+
+            let path = app.backup()?;
+
+            summary.stats = app.stats();
+
+            summary.output = Some(path.clone());
+
+            if let Some(target) = upload_options.upload {
+                app.print(format!("Uploading back-up to {target}..."));
+                app.upload(&target, &path)?;
+            }
         }
+        Command::List {
+            items,
+            platform,
+            mut filter_options,
+            global_options,
+        } => {
+            if warn_and_exit(platform, &global_options) {
+                return Ok(());
+            }
+
+            let mut config = Config::new(platform, global_options)?;
+            config.merge_filter_options(&mut filter_options)?;
+            config.print_sources();
+
+            let mut app = App::new(config)?.into_list(items);
+
+            app.run_filters(&filter_options);
+            app.list();
+
+            *summary = RunSummary {
+                output: None,
+                ..app.summary()
+            };
+        }
+        Command::Info {
+            book_id,
+            platform,
+            format,
+            global_options,
+        } => {
+            if warn_and_exit(platform, &global_options) {
+                return Ok(());
+            }
+
+            let config = Config::new(platform, global_options)?;
+            config.print_sources();
+
+            let app = App::new(config)?.into_info(book_id, format);
+
+            app.info()?;
+
+            *summary = RunSummary {
+                output: None,
+                ..app.summary()
+            };
+        }
+        Command::Restore {
+            platform,
+            backup_directory,
+            restore_options,
+            global_options,
+        } => {
+            if let Platform::All = platform {
+                bail!("The `all` platform isn't supported by the `restore` command");
+            }
+
+            if global_options.snapshot.is_some() {
+                bail!("The `--snapshot` option isn't supported by the `restore` command");
+            }
+
+            if warn_and_exit(platform, &global_options) {
+                return Ok(());
+            }
+
+            let data_directory =
+                Config::get_restore_directory(platform.try_into()?, global_options.data_directory);
+            let output_directory = Config::get_output_directory(global_options.output_directory);
+
+            let print = |message: String| {
+                if !global_options.is_quiet {
+                    println!("{message}");
+                }
+            };
+
+            if restore_options.dry_run {
+                print(format!("Restoring {platform} data (dry-run)..."));
+            } else {
+                print(format!("Restoring {platform} data..."));
+            }
+
+            let restored = lib::restore::run(
+                platform.try_into()?,
+                &backup_directory,
+                data_directory.as_deref(),
+                &output_directory,
+                restore_options,
+            )
+            .wrap_err("Failed while restoring data")?;
+
+            for path in restored {
+                print(format!(" • {}", path.display()));
+            }
+        }
+        Command::Diff {
+            old,
+            new,
+            diff_options,
+        } => {
+            let old =
+                lib::diff::load(&old).wrap_err("Failed while reading old export or back-up")?;
+            let new =
+                lib::diff::load(&new).wrap_err("Failed while reading new export or back-up")?;
+
+            let diff = lib::diff::run(&old, &new);
+
+            print!("{}", lib::diff::render(&diff, diff_options.format.into())?);
+        }
+        Command::Pack {
+            platform,
+            pack_options,
+            mut filter_options,
+            mut preprocess_options,
+            mut postprocess_options,
+            global_options,
+        } => {
+            if filter_options.created_after.is_some() {
+                bail!("The `--since` and `--filter-created-after` options can't be used together");
+            }
+
+            if warn_and_exit(platform, &global_options) {
+                return Ok(());
+            }
+
+            let mut config = Config::new(platform, global_options)?;
+            config.merge_filter_options(&mut filter_options)?;
+            config.merge_preprocess_options(&mut preprocess_options);
+            config.merge_postprocess_options(&mut postprocess_options);
+            config.print_sources();
+
+            // Only filters set by the user are confirmed, not the period itself.
+            let confirm = !filter_options.is_empty() && !filter_options.auto_confirm;
+
+            filter_options.created_after = Some(pack_options.since);
+
+            let mut app = App::new(config)?.into_render(pack_options.into())?;
+
+            app.run_filters(&filter_options);
+
+            *summary = app.summary();
+
+            if confirm && !app.confirm_filter_results()? {
+                return Ok(());
+            }
+
+            app.print(format!(
+                "Packing {platform} annotations since {}...",
+                pack_options
+                    .since
+                    .format(lib::defaults::DATE_FORMAT_TEMPLATE)
+            ));
+
+            app.run_preprocesses(&preprocess_options)?;
+            let (path, index) =
+                app.pack(pack_options.since, pack_options.zip, postprocess_options)?;
+
+            summary.stats = app.stats();
+
+            summary.output = Some(path.clone());
+
+            app.print(format!(
+                "Packed {} annotations from {} books: {}",
+                index.count_annotations(),
+                index.books.len(),
+                path.display()
+            ));
+        }
+        Command::Cluster {
+            platform,
+            cluster_options,
+            mut filter_options,
+            mut preprocess_options,
+            global_options,
+        } => {
+            if warn_and_exit(platform, &global_options) {
+                return Ok(());
+            }
+
+            let mut config = Config::new(platform, global_options)?;
+            config.merge_filter_options(&mut filter_options)?;
+            config.merge_preprocess_options(&mut preprocess_options);
+            config.print_sources();
+
+            let mut app = App::new(config)?.into_cluster(cluster_options);
+
+            app.run_filters(&filter_options);
+
+            *summary = app.summary();
+
+            if !filter_options.is_empty()
+                && !filter_options.auto_confirm
+                && !app.confirm_filter_results()?
+            {
+                return Ok(());
+            }
+
+            app.print(format!("Clustering {platform} annotations..."));
+
+            app.run_preprocesses(&preprocess_options)?;
+            let (count, path) = app.cluster()?;
+
+            summary.stats = app.stats();
+
+            summary.output = Some(path.clone());
+
+            app.print(format!(
+                "Found {count} clusters of similar annotations: {}",
+                path.display()
+            ));
+        }
+        Command::Review {
+            platform,
+            review_options,
+            mut filter_options,
+            mut preprocess_options,
+            global_options,
+        } => {
+            if warn_and_exit(platform, &global_options) {
+                return Ok(());
+            }
+
+            let mut config = Config::new(platform, global_options)?;
+            config.merge_filter_options(&mut filter_options)?;
+            config.merge_preprocess_options(&mut preprocess_options);
+            config.print_sources();
+
+            let mut app = App::new(config)?.into_review(review_options);
+
+            app.run_filters(&filter_options);
+            app.run_preprocesses(&preprocess_options)?;
+            app.review()?;
+
+            *summary = RunSummary {
+                output: None,
+                ..app.summary()
+            };
+        }
+        Command::ReadingLog {
+            platform,
+            reading_log_options,
+            global_options,
+        } => {
+            if warn_and_exit(platform, &global_options) {
+                return Ok(());
+            }
+
+            let mut config = Config::new(platform, global_options)?;
+            // The log is built from books alone so books without annotations are kept.
+            config.include_unannotated = true;
+            config.print_sources();
+
+            let mut app = App::new(config)?.into_reading_log(reading_log_options);
+
+            app.reading_log()?;
+
+            *summary = RunSummary {
+                output: None,
+                ..app.summary()
+            };
+        }
+        Command::Search {
+            query,
+            search_options,
+            output_directory,
+        } => {
+            let path =
+                Config::get_output_directory(output_directory).join(lib::search::INDEX_FILENAME);
+
+            if !path.exists() {
+                bail!(
+                    "No search index found at: {}. Run `readstor index build` to build one",
+                    path.display()
+                );
+            }
+
+            let index = lib::search::SearchIndex::read(&path)
+                .wrap_err("Failed while reading search index")?;
+
+            let matches = index.search(&query, search_options.limit);
+
+            if matches.is_empty() {
+                println!("No matches found.");
+            }
+
+            for result in matches {
+                println!("{} — {}", result.document.title, result.document.author);
+                println!("  {}", result.context);
+                println!();
+            }
+        }
+        Command::Index { action } => {
+            let (platform, mut filter_options, mut preprocess_options, global_options, update) =
+                match action {
+                    IndexAction::Build {
+                        platform,
+                        filter_options,
+                        preprocess_options,
+                        global_options,
+                    } => (
+                        platform,
+                        filter_options,
+                        preprocess_options,
+                        global_options,
+                        false,
+                    ),
+                    IndexAction::Update {
+                        platform,
+                        filter_options,
+                        preprocess_options,
+                        global_options,
+                    } => (
+                        platform,
+                        filter_options,
+                        preprocess_options,
+                        global_options,
+                        true,
+                    ),
+                };
+
+            if warn_and_exit(platform, &global_options) {
+                return Ok(());
+            }
+
+            let mut config = Config::new(platform, global_options)?;
+            config.merge_filter_options(&mut filter_options)?;
+            config.merge_preprocess_options(&mut preprocess_options);
+            config.print_sources();
+
+            let mut app = App::new(config)?.into_index();
+
+            app.run_filters(&filter_options);
+
+            *summary = app.summary();
+
+            if !filter_options.is_empty()
+                && !filter_options.auto_confirm
+                && !app.confirm_filter_results()?
+            {
+                return Ok(());
+            }
+
+            app.print(format!("Indexing {platform} annotations..."));
+
+            app.run_preprocesses(&preprocess_options)?;
+
+            let path = if update {
+                let (update, path) = app.update_index()?;
+
+                app.print(format!(
+                    "Added {} annotations, updated {} and removed {}: {}",
+                    update.added,
+                    update.updated,
+                    update.removed,
+                    path.display()
+                ));
+
+                path
+            } else {
+                let (count, path) = app.build_index()?;
+
+                app.print(format!("Indexed {count} annotations: {}", path.display()));
+
+                path
+            };
+
+            summary.stats = app.stats();
+
+            summary.output = Some(path);
+        }
+        Command::Analyze { analysis } => match analysis {
+            Analysis::Ngrams {
+                platform,
+                ngram_options,
+                mut filter_options,
+                mut preprocess_options,
+                global_options,
+            } => {
+                if warn_and_exit(platform, &global_options) {
+                    return Ok(());
+                }
+
+                let mut config = Config::new(platform, global_options)?;
+                config.merge_filter_options(&mut filter_options)?;
+                config.merge_preprocess_options(&mut preprocess_options);
+                config.print_sources();
+
+                let mut app = App::new(config)?.into_ngrams(ngram_options);
+
+                app.run_filters(&filter_options);
+                app.run_preprocesses(&preprocess_options)?;
+                app.ngrams();
+
+                *summary = RunSummary {
+                    output: None,
+                    ..app.summary()
+                };
+            }
+            Analysis::Heatmap {
+                platform,
+                heatmap_options,
+                mut filter_options,
+                global_options,
+            } => {
+                if warn_and_exit(platform, &global_options) {
+                    return Ok(());
+                }
+
+                let mut config = Config::new(platform, global_options)?;
+                config.merge_filter_options(&mut filter_options)?;
+                config.print_sources();
+
+                let mut app = App::new(config)?.into_heatmap(heatmap_options);
+
+                app.run_filters(&filter_options);
+
+                *summary = app.summary();
+
+                if !filter_options.is_empty()
+                    && !filter_options.auto_confirm
+                    && !app.confirm_filter_results()?
+                {
+                    return Ok(());
+                }
+
+                app.print(format!("Locating {platform} annotations..."));
+
+                let (count, path) = app.heatmap()?;
+
+                summary.stats = app.stats();
+
+                summary.output = Some(path.clone());
+
+                app.print(format!(
+                    "Wrote annotation positions for {count} books: {}",
+                    path.display()
+                ));
+            }
+        },
+        Command::Sync { service } => match service {
+            SyncService::Notion {
+                platform,
+                notion_options,
+                mut filter_options,
+                sort_options,
+                mut preprocess_options,
+                global_options,
+            } => {
+                if warn_and_exit(platform, &global_options) {
+                    return Ok(());
+                }
+
+                let mut config = Config::new(platform, global_options)?;
+                config.merge_filter_options(&mut filter_options)?;
+                config.merge_preprocess_options(&mut preprocess_options);
+                config.print_sources();
+
+                let mut app = App::new(config)?.into_sync(notion_options);
+
+                app.run_filters(&filter_options);
+                app.run_sort(sort_options);
+
+                *summary = RunSummary {
+                    output: None,
+                    ..app.summary()
+                };
+
+                if !filter_options.is_empty()
+                    && !filter_options.auto_confirm
+                    && !app.confirm_filter_results()?
+                {
+                    return Ok(());
+                }
+
+                app.print(format!("Syncing {platform} annotations to Notion..."));
+
+                app.run_preprocesses(&preprocess_options)?;
+                let stats = app.sync()?;
+
+                summary.stats = app.stats();
+
+                app.print(format!(
+                    "Created {} pages, updated {} pages and appended {} annotations",
+                    stats.pages_created, stats.pages_updated, stats.annotations_appended
+                ));
+            }
+            SyncService::Hypothesis {
+                platform,
+                hypothesis_options,
+                mut filter_options,
+                sort_options,
+                mut preprocess_options,
+                global_options,
+            } => {
+                if warn_and_exit(platform, &global_options) {
+                    return Ok(());
+                }
+
+                let mut config = Config::new(platform, global_options)?;
+                config.merge_filter_options(&mut filter_options)?;
+                config.merge_preprocess_options(&mut preprocess_options);
+                config.print_sources();
+
+                let mut app = App::new(config)?.into_hypothesis(hypothesis_options);
+
+                app.run_filters(&filter_options);
+                app.run_sort(sort_options);
+
+                *summary = RunSummary {
+                    output: None,
+                    ..app.summary()
+                };
+
+                if !filter_options.is_empty()
+                    && !filter_options.auto_confirm
+                    && !app.confirm_filter_results()?
+                {
+                    return Ok(());
+                }
+
+                app.print(format!("Syncing {platform} annotations to Hypothes.is..."));
+
+                app.run_preprocesses(&preprocess_options)?;
+                let stats = app.sync()?;
+
+                summary.stats = app.stats();
+
+                app.print(format!(
+                    "Created {} annotations and updated {} annotations",
+                    stats.annotations_created, stats.annotations_updated
+                ));
+            }
+            SyncService::Webhook {
+                platform,
+                webhook_options,
+                mut filter_options,
+                sort_options,
+                mut preprocess_options,
+                global_options,
+            } => {
+                if warn_and_exit(platform, &global_options) {
+                    return Ok(());
+                }
+
+                let mut config = Config::new(platform, global_options)?;
+                config.merge_filter_options(&mut filter_options)?;
+                config.merge_preprocess_options(&mut preprocess_options);
+                config.print_sources();
+
+                let mut app = App::new(config)?.into_webhook(webhook_options);
+
+                app.run_filters(&filter_options);
+                app.run_sort(sort_options);
+
+                *summary = RunSummary {
+                    output: None,
+                    ..app.summary()
+                };
+
+                if !filter_options.is_empty()
+                    && !filter_options.auto_confirm
+                    && !app.confirm_filter_results()?
+                {
+                    return Ok(());
+                }
+
+                app.print(format!("Syncing {platform} annotations to webhook..."));
+
+                app.run_preprocesses(&preprocess_options)?;
+                let stats = app.sync()?;
+
+                summary.stats = app.stats();
+
+                app.print(format!(
+                    "Sent {} annotations in {} requests",
+                    stats.annotations_sent, stats.requests
+                ));
+            }
+        },
+        Command::Snapshot { action } => match action {
+            SnapshotAction::Create {
+                platform,
+                global_options,
+            } => {
+                if warn_and_exit(platform, &global_options) {
+                    return Ok(());
+                }
+
+                let config = Config::new(platform, global_options)?;
+                config.print_sources();
+
+                let mut app = App::new(config)?.into_snapshot();
+
+                app.print(format!("Snapshotting {platform} annotations..."));
+
+                *summary = app.summary();
+
+                let path = app.snapshot()?;
+
+                summary.stats = app.stats();
+
+                summary.output = Some(path.clone());
+
+                app.print(format!(
+                    "Wrote {} annotations from {} books: {}",
+                    summary.annotations,
+                    summary.books,
+                    path.display()
+                ));
+            }
+            SnapshotAction::Load { snapshot } => {
+                let snapshot = lib::snapshot::Snapshot::read(&snapshot)
+                    .wrap_err("Failed while loading snapshot")?;
+
+                let header = &snapshot.header;
+
+                println!(
+                    "Loaded {} annotations from {} books",
+                    header.annotations, header.books
+                );
+                println!(
+                    "Created {} from {} with readstor v{} (format v{})",
+                    header.created.format(lib::defaults::DATE_FORMAT_TEMPLATE),
+                    header.platform,
+                    header.readstor_version,
+                    header.version,
+                );
+            }
+        },
+        Command::Schedule { action } => match action {
+            ScheduleAction::Install {
+                interval,
+                command,
+                name,
+            } => {
+                let path = schedule::install(interval, &command, name.as_deref())?;
+                println!("Installed schedule at {}", path.display());
+            }
+            ScheduleAction::Remove { name } => {
+                let path = schedule::remove(&name)?;
+                println!("Removed schedule at {}", path.display());
+            }
+            ScheduleAction::List => {
+                schedule::list()?;
+            }
+        },
+        Command::Templates { action } => match action {
+            TemplatesAction::List {
+                templates_directory,
+                config_path,
+            } => {
+                let templates_directory =
+                    Config::templates_directory(config_path.as_deref(), templates_directory)?;
+
+                templates::list(templates_directory)?;
+            }
+        },
+        Command::Doctor { data_directory } => {
+            doctor::run(data_directory)?;
+        }
+        Command::Completions { shell } => completions::print(shell),
+        Command::Man { output_directory } => {
+            completions::man(output_directory.as_deref())?;
+
+            if let Some(directory) = output_directory {
+                println!("Wrote man pages to: {}", directory.display());
+            }
+        }
+        Command::Import { action } => {
+            let (entries, platform, output_directory) = match action {
+                ImportAction::Json {
+                    path,
+                    output_directory,
+                } => (
+                    lib::import::run(&path).wrap_err("Failed while importing export")?,
+                    "import",
+                    output_directory,
+                ),
+                ImportAction::Kindle {
+                    path,
+                    output_directory,
+                } => (
+                    lib::import::kindle::run(&path)
+                        .wrap_err("Failed while importing Kindle clippings")?,
+                    "kindle",
+                    output_directory,
+                ),
+                ImportAction::Kobo {
+                    path,
+                    output_directory,
+                } => (
+                    lib::import::kobo::run(&path)
+                        .wrap_err("Failed while importing Kobo database")?,
+                    "kobo",
+                    output_directory,
+                ),
+            };
+
+            let snapshot = lib::snapshot::Snapshot::new(&entries, platform);
+            let output_directory = Config::get_output_directory(output_directory);
+            let snapshot_path = output_directory.join(snapshot.filename());
+
+            std::fs::create_dir_all(&output_directory)?;
+
+            snapshot
+                .write(&snapshot_path)
+                .wrap_err("Failed while writing snapshot")?;
+
+            println!(
+                "Imported {} annotations from {} books: {}",
+                snapshot.header.annotations,
+                snapshot.header.books,
+                snapshot_path.display()
+            );
+            println!(
+                "Pass it to any other command with `--snapshot {}`",
+                snapshot_path.display()
+            );
+        }
+        Command::Migrate {
+            path,
+            migrate_options,
+        } => {
+            if migrate_options.dry_run {
+                println!("Migrating export (dry-run)...");
+            } else {
+                println!("Migrating export...");
+            }
+
+            let summary = lib::migrate::run(&path, migrate_options)
+                .wrap_err("Failed while migrating export")?;
+
+            for path in &summary.migrated {
+                println!(" • {}", path.display());
+            }
+
+            for path in &summary.skipped {
+                tracing::warn!("skipped '{}' as it isn't part of an export", path.display());
+            }
+
+            let verb = if migrate_options.dry_run {
+                "Would migrate"
+            } else {
+                "Migrated"
+            };
+
+            println!(
+                "{verb} {} file(s), {} already current.",
+                summary.migrated.len(),
+                summary.current
+            );
+        }
+        Command::Schema {
+            format,
+            output_directory,
+        } => match output_directory {
+            Some(directory) => {
+                let paths = lib::schema::write(&directory, format.into())
+                    .wrap_err("Failed while writing schemas")?;
+
+                for path in paths {
+                    println!("Wrote schema to: {}", path.display());
+                }
+            }
+            None => println!("{}", lib::schema::to_string(format.into())?),
+        },
     };
 
     Ok(())
 }
 
-fn warn_and_exit(platform: Platform, is_force: bool) -> bool {
+fn warn_and_exit(platform: Platform, options: &GlobalOptions) -> bool {
     if let Platform::IOs = platform {
         return false;
     }
 
-    if !is_force && applebooks_is_running() {
+    // Snapshots are read without touching Apple Books' data.
+    if options.snapshot.is_some() {
+        return false;
+    }
+
+    if !options.is_force && applebooks_is_running() {
         println!("Apple Books is currently running. To ignore this, use the `-f, --force` flag.");
         return true;
     }
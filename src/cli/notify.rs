@@ -0,0 +1,154 @@
+use chrono::Local;
+use serde::Serialize;
+
+use crate::CliResult;
+
+/// The title shown on notifications.
+const NOTIFICATION_TITLE: &str = "ReadStor";
+
+/// Shows a macOS user notification with the command's outcome.
+///
+/// Any errors are logged and otherwise ignored as a failed notification shouldn't fail the run.
+///
+/// # Arguments
+///
+/// * `command` - The name of the command that was run.
+/// * `result` - The command's result.
+pub fn send_notification(command: &str, result: &CliResult<()>) {
+    let message = self::message(command, result);
+
+    let script = format!(
+        "display notification \"{}\" with title \"{}\"",
+        self::escape_applescript(&message),
+        self::escape_applescript(NOTIFICATION_TITLE),
+    );
+
+    match std::process::Command::new("osascript")
+        .args(["-e", &script])
+        .output()
+    {
+        Ok(output) if output.status.success() => tracing::debug!("sent notification: {message}"),
+        Ok(output) => tracing::warn!(
+            "failed to send notification: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ),
+        Err(error) => tracing::warn!("failed to send notification: {error}"),
+    }
+}
+
+/// Sends a JSON `POST` request with the command's outcome to a webhook.
+///
+/// Any errors are logged and otherwise ignored as a failed notification shouldn't fail the run.
+///
+/// # Arguments
+///
+/// * `url` - The webhook's URL.
+/// * `command` - The name of the command that was run.
+/// * `result` - The command's result.
+pub fn send_webhook(url: &str, command: &str, result: &CliResult<()>) {
+    let payload = WebhookPayload::new(command, result);
+
+    let body = match serde_json::to_string(&payload) {
+        Ok(body) => body,
+        Err(error) => {
+            tracing::warn!("failed to serialize webhook payload: {error}");
+            return;
+        }
+    };
+
+    match ureq::post(url)
+        .set("Content-Type", "application/json")
+        .send_string(&body)
+    {
+        Ok(_) => tracing::debug!(%url, "sent webhook"),
+        Err(error) => tracing::warn!(%url, %error, "failed to send webhook"),
+    }
+}
+
+/// The JSON body sent to a webhook.
+#[derive(Debug, Serialize)]
+struct WebhookPayload {
+    /// The name of the command that was run.
+    command: String,
+
+    /// Either `success` or `failure`.
+    status: &'static str,
+
+    /// A human-readable summary of the outcome.
+    message: String,
+
+    /// The time the command finished in RFC 3339 format.
+    timestamp: String,
+}
+
+impl WebhookPayload {
+    /// Creates a new instance of [`WebhookPayload`].
+    ///
+    /// # Arguments
+    ///
+    /// * `command` - The name of the command that was run.
+    /// * `result` - The command's result.
+    fn new(command: &str, result: &CliResult<()>) -> Self {
+        Self {
+            command: command.to_owned(),
+            status: if result.is_ok() { "success" } else { "failure" },
+            message: self::message(command, result),
+            timestamp: Local::now().to_rfc3339(),
+        }
+    }
+}
+
+/// Returns a human-readable summary of a command's outcome.
+///
+/// # Arguments
+///
+/// * `command` - The name of the command that was run.
+/// * `result` - The command's result.
+fn message(command: &str, result: &CliResult<()>) -> String {
+    match result {
+        Ok(()) => format!("`{command}` finished successfully"),
+        // Only the outermost context is included to keep the message short.
+        Err(error) => format!("`{command}` failed: {error}"),
+    }
+}
+
+/// Escapes a string for use inside an `AppleScript` string literal.
+///
+/// # Arguments
+///
+/// * `string` - The string to escape.
+fn escape_applescript(string: &str) -> String {
+    string.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    use color_eyre::eyre::eyre;
+
+    #[test]
+    fn payload_success() {
+        let payload = WebhookPayload::new("export", &Ok(()));
+
+        assert_eq!(payload.status, "success");
+        assert_eq!(payload.message, "`export` finished successfully");
+    }
+
+    #[test]
+    fn payload_failure() {
+        let payload = WebhookPayload::new("backup", &Err(eyre!("Failed while backing-up")));
+
+        assert_eq!(payload.status, "failure");
+        assert_eq!(payload.message, "`backup` failed: Failed while backing-up");
+    }
+
+    #[test]
+    fn escape() {
+        assert_eq!(
+            escape_applescript(r#"say "hi" \ bye"#),
+            r#"say \"hi\" \\ bye"#
+        );
+    }
+}
@@ -0,0 +1,228 @@
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+use color_eyre::eyre::WrapErr;
+
+use crate::CliResult;
+
+/// The name of the application's directory within each standard directory.
+const APPLICATION_NAME: &str = "readstor";
+
+/// An enum representing the standard directories used by the application.
+#[derive(Debug, Clone, Copy)]
+pub enum Directory {
+    /// Where user-facing data e.g. renders, exports and back-ups are written by default.
+    Data,
+
+    /// Where regenerable data is stored.
+    Cache,
+
+    /// Where logs are written.
+    Logs,
+
+    /// Where the config file is read from.
+    Config,
+}
+
+/// Returns the path to a standard directory.
+///
+/// On macOS the Apple-recommended locations are used:
+///
+/// ```plaintext
+/// Data   -> ~/Library/Application Support/readstor
+/// Cache  -> ~/Library/Caches/readstor
+/// Logs   -> ~/Library/Logs/readstor
+/// ```
+///
+/// On other platforms the [XDG Base Directory][xdg] locations are used:
+///
+/// ```plaintext
+/// Data   -> $XDG_DATA_HOME/readstor   (~/.local/share/readstor)
+/// Cache  -> $XDG_CACHE_HOME/readstor  (~/.cache/readstor)
+/// Logs   -> $XDG_STATE_HOME/readstor  (~/.local/state/readstor)
+/// ```
+///
+/// The config directory uses the XDG location on all platforms, as is common for command-line
+/// tools:
+///
+/// ```plaintext
+/// Config -> $XDG_CONFIG_HOME/readstor (~/.config/readstor)
+/// ```
+///
+/// # Arguments
+///
+/// * `directory` - The directory to return.
+///
+/// [xdg]: https://specifications.freedesktop.org/basedir-spec/latest/
+pub fn get(directory: Directory) -> PathBuf {
+    self::resolve(
+        directory,
+        &lib::defaults::HOME_DIRECTORY,
+        cfg!(target_os = "macos"),
+        |key| std::env::var_os(key),
+    )
+}
+
+/// Moves the legacy `~/.readstor` directory to its new location if it exists and the new location
+/// doesn't.
+///
+/// Returns `true` if the directory was moved.
+///
+/// # Arguments
+///
+/// * `legacy` - The legacy directory.
+/// * `destination` - The new directory.
+///
+/// # Errors
+///
+/// Will return `Err` if any IO errors are encountered.
+pub fn migrate(legacy: &Path, destination: &Path) -> CliResult<bool> {
+    if !legacy.is_dir() || destination.exists() {
+        return Ok(false);
+    }
+
+    if let Some(parent) = destination.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    std::fs::rename(legacy, destination).wrap_err(format!(
+        "Failed while moving {} to {}",
+        legacy.display(),
+        destination.display()
+    ))?;
+
+    tracing::info!(
+        "moved legacy directory {} to {}",
+        legacy.display(),
+        destination.display()
+    );
+
+    Ok(true)
+}
+
+/// Resolves the path to a standard directory.
+///
+/// # Arguments
+///
+/// * `directory` - The directory to resolve.
+/// * `home` - The user's home directory.
+/// * `is_macos` - Toggles using the macOS locations over the XDG ones.
+/// * `var` - A function to read environment variables.
+fn resolve<F>(directory: Directory, home: &Path, is_macos: bool, var: F) -> PathBuf
+where
+    F: Fn(&str) -> Option<OsString>,
+{
+    if is_macos {
+        let library = home.join("Library");
+
+        let root = match directory {
+            Directory::Data => Some(library.join("Application Support")),
+            Directory::Cache => Some(library.join("Caches")),
+            Directory::Logs => Some(library.join("Logs")),
+            // The config directory falls through to its XDG location.
+            Directory::Config => None,
+        };
+
+        if let Some(root) = root {
+            return root.join(APPLICATION_NAME);
+        }
+    }
+
+    let (key, fallback) = match directory {
+        Directory::Data => ("XDG_DATA_HOME", [".local", "share"].as_slice()),
+        Directory::Cache => ("XDG_CACHE_HOME", [".cache"].as_slice()),
+        Directory::Logs => ("XDG_STATE_HOME", [".local", "state"].as_slice()),
+        Directory::Config => ("XDG_CONFIG_HOME", [".config"].as_slice()),
+    };
+
+    // The spec requires relative paths to be ignored.
+    let root = var(key)
+        .map(PathBuf::from)
+        .filter(|path| path.is_absolute())
+        .unwrap_or_else(|| {
+            fallback
+                .iter()
+                .fold(home.to_owned(), |path, p| path.join(p))
+        });
+
+    root.join(APPLICATION_NAME)
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    use lib::defaults::TEMP_OUTPUT_DIRECTORY;
+
+    #[test]
+    fn resolve_macos() {
+        let home = Path::new("/Users/user");
+
+        assert_eq!(
+            resolve(Directory::Data, home, true, |_| None),
+            Path::new("/Users/user/Library/Application Support/readstor")
+        );
+        assert_eq!(
+            resolve(Directory::Cache, home, true, |_| None),
+            Path::new("/Users/user/Library/Caches/readstor")
+        );
+        assert_eq!(
+            resolve(Directory::Logs, home, true, |_| None),
+            Path::new("/Users/user/Library/Logs/readstor")
+        );
+        assert_eq!(
+            resolve(Directory::Config, home, true, |_| None),
+            Path::new("/Users/user/.config/readstor")
+        );
+    }
+
+    #[test]
+    fn resolve_xdg() {
+        let home = Path::new("/home/user");
+
+        let var = |key: &str| match key {
+            "XDG_STATE_HOME" => Some(OsString::from("/var/user")),
+            "XDG_CACHE_HOME" => Some(OsString::from("relative/cache")),
+            _ => None,
+        };
+
+        assert_eq!(
+            resolve(Directory::Data, home, false, var),
+            Path::new("/home/user/.local/share/readstor")
+        );
+        assert_eq!(
+            resolve(Directory::Logs, home, false, var),
+            Path::new("/var/user/readstor")
+        );
+        assert_eq!(
+            resolve(Directory::Cache, home, false, var),
+            Path::new("/home/user/.cache/readstor")
+        );
+    }
+
+    // Tests that the legacy directory is moved only if the new location doesn't exist.
+    #[test]
+    fn migrate_legacy() {
+        let root = TEMP_OUTPUT_DIRECTORY.join("tests-paths").join("migrate");
+
+        if root.exists() {
+            std::fs::remove_dir_all(&root).unwrap();
+        }
+
+        let legacy = root.join(".readstor");
+        let destination = root.join("Application Support").join("readstor");
+
+        std::fs::create_dir_all(&legacy).unwrap();
+        std::fs::write(legacy.join("book.md"), "").unwrap();
+
+        assert!(migrate(&legacy, &destination).unwrap());
+        assert!(!legacy.exists());
+        assert!(destination.join("book.md").exists());
+
+        std::fs::create_dir_all(&legacy).unwrap();
+
+        assert!(!migrate(&legacy, &destination).unwrap());
+        assert!(legacy.exists());
+    }
+}
@@ -0,0 +1,219 @@
+use std::io::Write;
+
+use indicatif::{ProgressBar, ProgressStyle};
+use serde::Serialize;
+
+/// The template used to draw the progress bar.
+const PROGRESS_BAR_TEMPLATE: &str = "[{bar:30}] {pos}/{len} books  {msg}";
+
+/// An enum representing how a command reports its progress.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ProgressMode {
+    /// Draws a progress bar to stderr. Nothing is drawn if stderr isn't a terminal.
+    #[default]
+    Bar,
+
+    /// Prints a JSON object to stdout for each progress event. See [`ProgressEvent`].
+    Json,
+
+    /// Reports nothing.
+    Hidden,
+}
+
+/// A struct representing a single progress event. Printed as a line of JSON in
+/// [`ProgressMode::Json`].
+///
+/// The counts are running totals since the command started.
+#[derive(Debug, Serialize)]
+struct ProgressEvent<'a> {
+    /// Either `start`, `book` or `finish`.
+    event: &'static str,
+
+    /// The name of the command reporting progress e.g. `render`.
+    command: &'a str,
+
+    /// The title of the book that was just processed. Only set for `book` events.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    title: Option<&'a str>,
+
+    /// The number of books processed.
+    books: usize,
+
+    /// The number of books to process.
+    total: usize,
+
+    /// The number of annotations processed.
+    annotations: usize,
+
+    /// The number of files written.
+    files: usize,
+}
+
+/// A struct for reporting the number of books and annotations processed and files written while a
+/// command runs.
+pub struct Progress {
+    mode: ProgressMode,
+    command: &'static str,
+    bar: Option<ProgressBar>,
+    books: usize,
+    total: usize,
+    annotations: usize,
+    files: usize,
+}
+
+impl Progress {
+    /// Creates a new instance of [`Progress`] and reports that the command started.
+    ///
+    /// # Arguments
+    ///
+    /// * `mode` - How to report progress.
+    /// * `command` - The name of the command reporting progress.
+    /// * `total` - The number of books to process.
+    pub fn new(mode: ProgressMode, command: &'static str, total: usize) -> Self {
+        let bar = (mode == ProgressMode::Bar).then(|| {
+            let bar = ProgressBar::new(total as u64);
+
+            if let Ok(style) = ProgressStyle::with_template(PROGRESS_BAR_TEMPLATE) {
+                bar.set_style(style.progress_chars("=> "));
+            }
+
+            bar
+        });
+
+        let progress = Self {
+            mode,
+            command,
+            bar,
+            books: 0,
+            total,
+            annotations: 0,
+            files: 0,
+        };
+
+        progress.emit("start", None);
+
+        progress
+    }
+
+    /// Reports that a book was processed.
+    ///
+    /// # Arguments
+    ///
+    /// * `title` - The book's title.
+    /// * `annotations` - The number of the book's annotations that were processed.
+    /// * `files` - The number of files written for the book.
+    pub fn book(&mut self, title: &str, annotations: usize, files: usize) {
+        self.books += 1;
+        self.annotations += annotations;
+        self.files += files;
+
+        if let Some(bar) = &self.bar {
+            bar.set_position(self.books as u64);
+            bar.set_message(self.message());
+        }
+
+        self.emit("book", Some(title));
+    }
+
+    /// Reports files written that don't belong to a single book e.g. tag pages.
+    ///
+    /// # Arguments
+    ///
+    /// * `files` - The number of files written.
+    pub fn files(&mut self, files: usize) {
+        self.files += files;
+
+        if let Some(bar) = &self.bar {
+            bar.set_message(self.message());
+        }
+    }
+
+    /// Reports that the command finished and clears the progress bar.
+    pub fn finish(self) {
+        if let Some(bar) = &self.bar {
+            bar.finish_and_clear();
+        }
+
+        self.emit("finish", None);
+    }
+
+    /// Returns the message shown next to the progress bar.
+    fn message(&self) -> String {
+        format!("{} annotations, {} files", self.annotations, self.files)
+    }
+
+    /// Prints a progress event as a line of JSON if the mode is [`ProgressMode::Json`].
+    ///
+    /// # Arguments
+    ///
+    /// * `event` - The event's name.
+    /// * `title` - The title of the book that was just processed, if any.
+    fn emit(&self, event: &'static str, title: Option<&str>) {
+        if self.mode != ProgressMode::Json {
+            return;
+        }
+
+        let event = ProgressEvent {
+            event,
+            command: self.command,
+            title,
+            books: self.books,
+            total: self.total,
+            annotations: self.annotations,
+            files: self.files,
+        };
+
+        if let Ok(line) = serde_json::to_string(&event) {
+            let mut stdout = std::io::stdout().lock();
+            let _ = writeln!(stdout, "{line}");
+            let _ = stdout.flush();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    // Tests that events are serialized with running totals and that `title` is only set for books.
+    #[test]
+    fn events() {
+        let event = ProgressEvent {
+            event: "book",
+            command: "render",
+            title: Some("The Art Spirit"),
+            books: 1,
+            total: 3,
+            annotations: 12,
+            files: 2,
+        };
+
+        assert_eq!(
+            serde_json::to_string(&event).unwrap(),
+            r#"{"event":"book","command":"render","title":"The Art Spirit","books":1,"total":3,"annotations":12,"files":2}"#
+        );
+
+        let event = ProgressEvent {
+            event: "finish",
+            title: None,
+            ..event
+        };
+
+        assert!(!serde_json::to_string(&event).unwrap().contains("title"));
+    }
+
+    // Tests that the counts accumulate across books and files.
+    #[test]
+    fn counts() {
+        let mut progress = Progress::new(ProgressMode::Hidden, "render", 2);
+
+        progress.book("The Art Spirit", 12, 2);
+        progress.book("Think on These Things", 3, 1);
+        progress.files(4);
+
+        assert_eq!(progress.books, 2);
+        assert_eq!(progress.annotations, 15);
+        assert_eq!(progress.files, 7);
+    }
+}
@@ -0,0 +1,212 @@
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use color_eyre::eyre::WrapErr;
+use lib::conflict::WriteSummary;
+use serde::Serialize;
+
+use crate::CliResult;
+
+use super::hooks::RunSummary;
+
+/// The path that writes the report to stdout instead of a file.
+const STDOUT: &str = "-";
+
+/// A struct representing the stats collected while a command runs.
+#[derive(Debug, Default, Clone)]
+pub struct RunStats {
+    /// The number of files written or skipped.
+    pub files: WriteSummary,
+
+    /// The number of books removed by filters.
+    pub books_filtered: usize,
+
+    /// The number of annotations removed by filters.
+    pub annotations_filtered: usize,
+
+    /// The name of each phase of the run and how long it took, in the order they ran.
+    pub phases: Vec<(&'static str, Duration)>,
+}
+
+impl RunStats {
+    /// Records how long a phase took.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The phase's name e.g. `render`.
+    /// * `start` - When the phase started.
+    pub fn record(&mut self, name: &'static str, start: Instant) {
+        self.phases.push((name, start.elapsed()));
+    }
+}
+
+/// A struct representing a machine-readable summary of a run.
+#[derive(Debug, Serialize)]
+struct Report<'a> {
+    /// The name of the command that was run e.g. `render`.
+    command: &'a str,
+
+    /// Either `success` or `failure`.
+    status: &'static str,
+
+    /// The error message. Only set if the command failed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+
+    /// The number of books processed.
+    books: usize,
+
+    /// The number of annotations processed.
+    annotations: usize,
+
+    /// The number of files created or rewritten.
+    files_written: usize,
+
+    /// The number of existing files left untouched.
+    files_skipped: usize,
+
+    /// The number of books removed by filters.
+    books_filtered_out: usize,
+
+    /// The number of annotations removed by filters.
+    annotations_filtered_out: usize,
+
+    /// The path the command wrote to, if any.
+    output: Option<&'a PathBuf>,
+
+    /// How long the whole run took, in seconds.
+    seconds: f64,
+
+    /// How long each phase took, in seconds.
+    phases: Vec<ReportPhase>,
+}
+
+/// A struct representing how long a single phase of a run took.
+#[derive(Debug, Serialize)]
+struct ReportPhase {
+    /// The phase's name e.g. `render`.
+    name: &'static str,
+
+    /// How long the phase took, in seconds.
+    seconds: f64,
+}
+
+/// Writes a JSON summary of a run to a file or, if the path is `-`, to stdout.
+///
+/// # Arguments
+///
+/// * `path` - The path to write to.
+/// * `command` - The name of the command that was run.
+/// * `result` - The command's result.
+/// * `summary` - The run's summary.
+/// * `elapsed` - How long the whole run took.
+///
+/// # Errors
+///
+/// Will return `Err` if the report cannot be serialized or written.
+pub fn write(
+    path: &Path,
+    command: &str,
+    result: &CliResult<()>,
+    summary: &RunSummary,
+    elapsed: Duration,
+) -> CliResult<()> {
+    let report = self::build(command, result, summary, elapsed);
+    let json = serde_json::to_string_pretty(&report)?;
+
+    if path.as_os_str() == STDOUT {
+        let mut stdout = std::io::stdout().lock();
+        writeln!(stdout, "{json}")?;
+        return Ok(());
+    }
+
+    std::fs::write(path, format!("{json}\n"))
+        .wrap_err(format!("Failed while writing report: {}", path.display()))
+}
+
+/// Builds a [`Report`] from a run.
+///
+/// # Arguments
+///
+/// * `command` - The name of the command that was run.
+/// * `result` - The command's result.
+/// * `summary` - The run's summary.
+/// * `elapsed` - How long the whole run took.
+fn build<'a>(
+    command: &'a str,
+    result: &CliResult<()>,
+    summary: &'a RunSummary,
+    elapsed: Duration,
+) -> Report<'a> {
+    let stats = &summary.stats;
+
+    Report {
+        command,
+        status: if result.is_ok() { "success" } else { "failure" },
+        error: result.as_ref().err().map(ToString::to_string),
+        books: summary.books,
+        annotations: summary.annotations,
+        files_written: stats.files.written(),
+        files_skipped: stats.files.skipped,
+        books_filtered_out: stats.books_filtered,
+        annotations_filtered_out: stats.annotations_filtered,
+        output: summary.output.as_ref(),
+        seconds: elapsed.as_secs_f64(),
+        phases: stats
+            .phases
+            .iter()
+            .map(|(name, duration)| ReportPhase {
+                name,
+                seconds: duration.as_secs_f64(),
+            })
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    use color_eyre::eyre::eyre;
+
+    // Tests that a report includes the run's counts, phases and, only on failure, its error.
+    #[test]
+    fn report() {
+        let summary = RunSummary {
+            books: 3,
+            annotations: 42,
+            output: None,
+            stats: RunStats {
+                files: WriteSummary {
+                    created: 2,
+                    rewritten: 1,
+                    skipped: 4,
+                },
+                books_filtered: 1,
+                annotations_filtered: 5,
+                phases: vec![("load", Duration::from_millis(1500))],
+            },
+        };
+
+        let report = build("render", &Ok(()), &summary, Duration::from_secs(2));
+        let json = serde_json::to_value(&report).unwrap();
+
+        assert_eq!(json["status"], "success");
+        assert!(json.get("error").is_none());
+        assert_eq!(json["files_written"], 3);
+        assert_eq!(json["files_skipped"], 4);
+        assert_eq!(json["books_filtered_out"], 1);
+        assert_eq!(json["annotations_filtered_out"], 5);
+        assert_eq!(json["seconds"], 2.0);
+        assert_eq!(json["phases"][0]["name"], "load");
+        assert_eq!(json["phases"][0]["seconds"], 1.5);
+
+        let report = build("render", &Err(eyre!("Oops")), &summary, Duration::ZERO);
+        let json = serde_json::to_value(&report).unwrap();
+
+        assert_eq!(json["status"], "failure");
+        assert_eq!(json["error"], "Oops");
+    }
+}
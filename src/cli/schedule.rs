@@ -0,0 +1,350 @@
+use std::path::{Path, PathBuf};
+
+use clap::Parser;
+use color_eyre::eyre::{bail, eyre, WrapErr};
+use serde::{Deserialize, Serialize};
+
+use crate::CliResult;
+
+use super::args::{Args, Command, Interval};
+use super::defaults;
+use super::table::Table;
+
+/// The prefix for all launchd agent labels. The schedule's name is appended to this.
+const LABEL_PREFIX: &str = "com.tnahs.readstor.";
+
+/// Installs and loads a launchd agent that runs a command periodically.
+///
+/// Returns the path to the agent's plist.
+///
+/// # Arguments
+///
+/// * `interval` - How often to run the command.
+/// * `command` - The command to run e.g. `export macos --force`.
+/// * `name` - An optional name for the schedule. Defaults to the command's name.
+///
+/// # Errors
+///
+/// Will return `Err` if:
+/// * The command or name are invalid.
+/// * Any IO errors are encountered.
+/// * The agent cannot be loaded.
+pub fn install(interval: Interval, command: &str, name: Option<&str>) -> CliResult<PathBuf> {
+    let arguments = self::parse_command(command)?;
+
+    let name = match name {
+        Some(name) => self::validate_name(name)?,
+        None => arguments[0].clone(),
+    };
+
+    let executable =
+        std::env::current_exe().wrap_err("Failed while locating the readstor executable")?;
+
+    let agent = LaunchAgent::new(&name, &executable, arguments, interval);
+    let path = self::plist_path(&name);
+
+    if path.exists() {
+        bail!("A schedule named '{name}' already exists, remove it first");
+    }
+
+    std::fs::create_dir_all(&*defaults::LAUNCH_AGENTS_DIRECTORY)?;
+    std::fs::create_dir_all(&*defaults::LOGS_DIRECTORY)?;
+
+    plist::to_file_xml(&path, &agent).wrap_err("Failed while writing launchd plist")?;
+
+    self::launchctl("load", &path)?;
+
+    Ok(path)
+}
+
+/// Unloads and removes a launchd agent.
+///
+/// Returns the path to the removed agent's plist.
+///
+/// # Arguments
+///
+/// * `name` - The schedule's name.
+///
+/// # Errors
+///
+/// Will return `Err` if:
+/// * No schedule exists with the given name.
+/// * Any IO errors are encountered.
+/// * The agent cannot be unloaded.
+pub fn remove(name: &str) -> CliResult<PathBuf> {
+    let name = self::validate_name(name)?;
+    let path = self::plist_path(&name);
+
+    if !path.exists() {
+        bail!("No schedule named '{name}'");
+    }
+
+    self::launchctl("unload", &path)?;
+
+    std::fs::remove_file(&path)?;
+
+    Ok(path)
+}
+
+/// Prints a table of all installed schedules.
+///
+/// # Errors
+///
+/// Will return `Err` if any IO errors are encountered.
+pub fn list() -> CliResult<()> {
+    let mut table = Table::new(["Name", "Interval", "Command"]);
+
+    if defaults::LAUNCH_AGENTS_DIRECTORY.exists() {
+        let mut paths: Vec<PathBuf> = std::fs::read_dir(&*defaults::LAUNCH_AGENTS_DIRECTORY)?
+            .filter_map(std::result::Result::ok)
+            .map(|entry| entry.path())
+            .filter(|path| {
+                path.file_name()
+                    .and_then(|name| name.to_str())
+                    .is_some_and(|name| name.starts_with(LABEL_PREFIX))
+            })
+            .collect();
+
+        paths.sort();
+
+        for path in paths {
+            let agent: LaunchAgent = match plist::from_file(&path) {
+                Ok(agent) => agent,
+                Err(error) => {
+                    tracing::warn!(path = %path.display(), %error, "skipped unreadable plist");
+                    continue;
+                }
+            };
+
+            table.add_row([
+                agent.name().to_owned(),
+                self::describe_interval(agent.start_interval),
+                agent.program_arguments[1..].join(" "),
+            ]);
+        }
+    }
+
+    if table.is_empty() {
+        println!("No schedules found.");
+    } else {
+        print!("{table}");
+    }
+
+    Ok(())
+}
+
+/// A struct representing a launchd agent's plist.
+///
+/// See `man launchd.plist` for more information.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct LaunchAgent {
+    /// The agent's unique label e.g. `com.tnahs.readstor.export`.
+    label: String,
+
+    /// The executable followed by its arguments.
+    program_arguments: Vec<String>,
+
+    /// How often to run the agent in seconds.
+    start_interval: u64,
+
+    /// Toggles running the agent as soon as it's loaded.
+    run_at_load: bool,
+
+    /// Where to write the agent's standard output.
+    standard_out_path: PathBuf,
+
+    /// Where to write the agent's standard error.
+    standard_error_path: PathBuf,
+}
+
+impl LaunchAgent {
+    /// Creates a new instance of [`LaunchAgent`].
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The schedule's name.
+    /// * `executable` - The path to the readstor executable.
+    /// * `arguments` - The command's arguments.
+    /// * `interval` - How often to run the command.
+    fn new(name: &str, executable: &Path, arguments: Vec<String>, interval: Interval) -> Self {
+        let program_arguments = std::iter::once(executable.display().to_string())
+            .chain(arguments)
+            .collect();
+
+        Self {
+            label: format!("{LABEL_PREFIX}{name}"),
+            program_arguments,
+            start_interval: interval.seconds(),
+            run_at_load: false,
+            standard_out_path: defaults::LOGS_DIRECTORY.join(format!("{name}.log")),
+            standard_error_path: defaults::LOGS_DIRECTORY.join(format!("{name}.error.log")),
+        }
+    }
+
+    /// Returns the schedule's name.
+    fn name(&self) -> &str {
+        self.label.strip_prefix(LABEL_PREFIX).unwrap_or(&self.label)
+    }
+}
+
+/// Splits and validates a command.
+///
+/// A leading `readstor` is optional. The command is parsed the same way it would be when run,
+/// so that any errors are caught before it's scheduled.
+///
+/// # Arguments
+///
+/// * `command` - The command to parse e.g. `export macos --force`.
+///
+/// # Errors
+///
+/// Will return `Err` if the command is invalid.
+fn parse_command(command: &str) -> CliResult<Vec<String>> {
+    let mut arguments =
+        shlex::split(command).ok_or_else(|| eyre!("Invalid command: '{command}'"))?;
+
+    if arguments
+        .first()
+        .is_some_and(|argument| argument == "readstor")
+    {
+        arguments.remove(0);
+    }
+
+    let args =
+        Args::try_parse_from(std::iter::once("readstor".to_owned()).chain(arguments.clone()))
+            .map_err(|error| eyre!("Invalid command: '{command}'\n\n{error}"))?;
+
+    match args.command {
+        Some(Command::Schedule { .. }) => bail!("Cannot schedule the `schedule` command"),
+        None => bail!("Cannot schedule `--shortcut-json` as it reads from stdin"),
+        Some(_) => {}
+    }
+
+    Ok(arguments)
+}
+
+/// Validates that a schedule's name only contains alphanumeric characters, `-` or `_`.
+///
+/// # Arguments
+///
+/// * `name` - The name to validate.
+///
+/// # Errors
+///
+/// Will return `Err` if the name is invalid.
+fn validate_name(name: &str) -> CliResult<String> {
+    let is_valid = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+
+    if !is_valid {
+        bail!("Invalid schedule name: '{name}'");
+    }
+
+    Ok(name.to_owned())
+}
+
+/// Returns the path to a schedule's plist.
+///
+/// # Arguments
+///
+/// * `name` - The schedule's name.
+fn plist_path(name: &str) -> PathBuf {
+    defaults::LAUNCH_AGENTS_DIRECTORY.join(format!("{LABEL_PREFIX}{name}.plist"))
+}
+
+/// Returns a human-readable description of an interval in seconds.
+///
+/// # Arguments
+///
+/// * `seconds` - The interval in seconds.
+fn describe_interval(seconds: u64) -> String {
+    [Interval::Hourly, Interval::Daily, Interval::Weekly]
+        .into_iter()
+        .find(|interval| interval.seconds() == seconds)
+        .map_or_else(
+            || format!("every {seconds}s"),
+            |interval| format!("{interval:?}").to_lowercase(),
+        )
+}
+
+/// Runs `launchctl` with a subcommand on a plist.
+///
+/// # Arguments
+///
+/// * `subcommand` - The subcommand to run e.g. `load` or `unload`.
+/// * `path` - The path to the plist.
+///
+/// # Errors
+///
+/// Will return `Err` if `launchctl` cannot be run or exits with an error.
+fn launchctl(subcommand: &str, path: &Path) -> CliResult<()> {
+    let output = std::process::Command::new("launchctl")
+        .arg(subcommand)
+        .arg("-w")
+        .arg(path)
+        .output()
+        .wrap_err("Failed while running launchctl")?;
+
+    if !output.status.success() {
+        bail!(
+            "Failed while running `launchctl {subcommand}`: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    #[test]
+    fn parse_command_valid() {
+        assert_eq!(
+            parse_command("readstor export macos --filter '=title:the art spirit'").unwrap(),
+            ["export", "macos", "--filter", "=title:the art spirit"]
+        );
+    }
+
+    #[test]
+    fn parse_command_invalid() {
+        assert!(parse_command("export").is_err());
+        assert!(parse_command("schedule list").is_err());
+        assert!(parse_command("export 'macos").is_err());
+    }
+
+    #[test]
+    fn name_invalid() {
+        assert!(validate_name("").is_err());
+        assert!(validate_name("../export").is_err());
+        assert!(validate_name("daily-export_1").is_ok());
+    }
+
+    // Tests that the plist contains the executable followed by the command's arguments.
+    #[test]
+    fn launch_agent_plist() {
+        let agent = LaunchAgent::new(
+            "export",
+            Path::new("/usr/local/bin/readstor"),
+            vec!["export".to_string(), "macos".to_string()],
+            Interval::Daily,
+        );
+
+        let mut buffer = Vec::new();
+        plist::to_writer_xml(&mut buffer, &agent).unwrap();
+        let agent: LaunchAgent = plist::from_bytes(&buffer).unwrap();
+
+        assert_eq!(agent.label, "com.tnahs.readstor.export");
+        assert_eq!(agent.name(), "export");
+        assert_eq!(
+            agent.program_arguments,
+            ["/usr/local/bin/readstor", "export", "macos"]
+        );
+        assert_eq!(describe_interval(agent.start_interval), "daily");
+    }
+}
@@ -0,0 +1,293 @@
+use std::io::Read;
+use std::path::PathBuf;
+
+use clap::Parser;
+use color_eyre::eyre::{bail, WrapErr};
+use serde::{Deserialize, Serialize};
+
+use lib::applebooks::macos::utils::applebooks_is_running;
+
+use super::args::{Args, Command, Platform, SnapshotAction};
+use super::hooks::RunSummary;
+use crate::CliResult;
+
+/// A struct representing a request read from stdin in `--shortcut-json` mode.
+///
+/// ```json
+/// {
+///   "command": "export",
+///   "args": ["macos"],
+///   "options": {
+///     "filter": ["tag:#favorite"],
+///     "output-directory": "~/Desktop/readstor.jsonl"
+///   }
+/// }
+/// ```
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Request {
+    /// The command to run e.g. `export` or `sync notion`.
+    command: String,
+
+    /// The command's positional arguments e.g. the platform.
+    #[serde(default)]
+    args: Vec<String>,
+
+    /// The command's options keyed by their long names, without the leading `--`.
+    #[serde(default)]
+    options: serde_json::Map<String, serde_json::Value>,
+}
+
+/// A struct representing the response written to stdout in `--shortcut-json` mode.
+#[derive(Debug, Serialize)]
+struct Response {
+    /// Either `success` or `failure`.
+    status: &'static str,
+
+    /// The name of the command that was run, if the request could be parsed.
+    command: Option<&'static str>,
+
+    /// The number of books processed.
+    books: usize,
+
+    /// The number of annotations processed.
+    annotations: usize,
+
+    /// The path the command wrote to, if any.
+    output: Option<PathBuf>,
+
+    /// The error message, if the command failed.
+    error: Option<String>,
+}
+
+impl Response {
+    /// Creates a new instance of [`Response`].
+    ///
+    /// # Arguments
+    ///
+    /// * `command` - The name of the command that was run.
+    /// * `result` - The command's result.
+    /// * `summary` - The run's summary.
+    fn new(command: Option<&'static str>, result: &CliResult<()>, summary: RunSummary) -> Self {
+        Self {
+            status: if result.is_ok() { "success" } else { "failure" },
+            command,
+            books: summary.books,
+            annotations: summary.annotations,
+            output: summary.output,
+            error: result.as_ref().err().map(|error| format!("{error:#}")),
+        }
+    }
+}
+
+/// Runs a single command described by JSON read from stdin and writes its result to stdout as JSON.
+///
+/// The command never prompts: filter results are auto-confirmed and all output messages are
+/// silenced so stdout only contains the response. Failures are reported in the response rather
+/// than the exit code so automations e.g. Apple Shortcuts can inspect them.
+///
+/// # Errors
+///
+/// Will return `Err` if the response cannot be written to stdout.
+pub fn run() -> CliResult<()> {
+    let (name, result, summary) = match self::read_command() {
+        Ok(command) => {
+            let name = command.name();
+            let (result, summary) = self::run_command(command);
+            (Some(name), result, summary)
+        }
+        Err(error) => (None, Err(error), RunSummary::default()),
+    };
+
+    let response = Response::new(name, &result, summary);
+
+    println!("{}", serde_json::to_string(&response)?);
+
+    Ok(())
+}
+
+/// Reads a request from stdin and parses it into a [`Command`].
+fn read_command() -> CliResult<Command> {
+    let mut input = String::new();
+
+    std::io::stdin()
+        .read_to_string(&mut input)
+        .wrap_err("Failed while reading request from stdin")?;
+
+    let request: Request =
+        serde_json::from_str(&input).wrap_err("Failed while parsing request from stdin")?;
+
+    let args = match Args::try_parse_from(self::to_argv(&request)?) {
+        Ok(args) => args,
+        Err(error) => {
+            // Only the first line is kept as the rest is usage information for the terminal.
+            let error = error.render().to_string();
+            let message = error.lines().next().unwrap_or_default();
+            bail!("Invalid request: {}", message.trim_start_matches("error: "));
+        }
+    };
+
+    let Some(command) = args.command else {
+        bail!("Invalid request: missing command");
+    };
+
+    if matches!(
+        command,
+        Command::List { .. }
+            | Command::Info { .. }
+            | Command::Review { .. }
+            | Command::ReadingLog { .. }
+            | Command::Search { .. }
+            | Command::Diff { .. }
+            | Command::Snapshot {
+                action: SnapshotAction::Load { .. },
+            }
+            | Command::Schedule { .. }
+            | Command::Templates { .. }
+            | Command::Doctor { .. }
+            | Command::Completions { .. }
+            | Command::Man { .. }
+            | Command::Import { .. }
+            | Command::Migrate { .. }
+            | Command::Schema { .. }
+    ) {
+        bail!(
+            "The `{}` command isn't supported with --shortcut-json",
+            command.name()
+        );
+    }
+
+    Ok(command)
+}
+
+/// Runs a command without prompting or printing to stdout.
+///
+/// # Arguments
+///
+/// * `command` - The command to run.
+fn run_command(mut command: Command) -> (CliResult<()>, RunSummary) {
+    let is_force = command
+        .global_options()
+        .is_some_and(|options| options.is_force || options.snapshot.is_some());
+
+    // `warn_and_exit()` prints to stdout and exits successfully so this is reported as a failure.
+    if matches!(command.platform(), Some(Platform::MacOs | Platform::All))
+        && !is_force
+        && applebooks_is_running()
+    {
+        let error = color_eyre::eyre::eyre!(
+            "Apple Books is currently running. To ignore this, set the `force` option."
+        );
+        return (Err(error), RunSummary::default());
+    }
+
+    if let Some(options) = command.global_options_mut() {
+        options.is_quiet = true;
+        options.is_verbose = false;
+        options.json_progress = false;
+    }
+
+    if let Some(options) = command.filter_options_mut() {
+        options.auto_confirm = true;
+    }
+
+    super::run_with_summary(command)
+}
+
+/// Converts a request into command-line arguments.
+///
+/// Options set to `true` become flags, options set to `false` or `null` are left out and arrays
+/// repeat the option for each value. Values starting with `~/` are expanded to the user's home
+/// directory.
+///
+/// # Arguments
+///
+/// * `request` - The request to convert.
+///
+/// # Errors
+///
+/// Will return `Err` if an option's value is an object or an array containing non-scalar values.
+fn to_argv(request: &Request) -> CliResult<Vec<String>> {
+    let mut argv = vec![lib::defaults::NAME.to_owned()];
+
+    argv.extend(request.command.split_whitespace().map(str::to_owned));
+    argv.extend(request.args.iter().cloned());
+
+    for (name, value) in &request.options {
+        let values = match value {
+            serde_json::Value::Array(values) => values.iter().collect(),
+            value => vec![value],
+        };
+
+        for value in values {
+            match value {
+                serde_json::Value::Bool(true) => argv.push(format!("--{name}")),
+                serde_json::Value::Bool(false) | serde_json::Value::Null => {}
+                // There's no shell to expand `~` so paths are expanded here.
+                serde_json::Value::String(value) if value.starts_with("~/") => {
+                    let path = super::config::expand_home(PathBuf::from(value));
+                    argv.push(format!("--{name}={}", path.display()));
+                }
+                serde_json::Value::String(value) => argv.push(format!("--{name}={value}")),
+                serde_json::Value::Number(value) => argv.push(format!("--{name}={value}")),
+                serde_json::Value::Array(_) | serde_json::Value::Object(_) => {
+                    bail!("Invalid value for option `{name}`: {value}");
+                }
+            }
+        }
+    }
+
+    Ok(argv)
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    fn request(json: &str) -> Request {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn to_argv_options() {
+        let request = request(
+            r#"{
+                "command": "sync notion",
+                "args": ["macos"],
+                "options": {
+                    "filter": ["tag:#one", "tag:#two"],
+                    "force": true,
+                    "quiet": false,
+                    "max-files": 10
+                }
+            }"#,
+        );
+
+        assert_eq!(
+            to_argv(&request).unwrap(),
+            [
+                lib::defaults::NAME,
+                "sync",
+                "notion",
+                "macos",
+                "--filter=tag:#one",
+                "--filter=tag:#two",
+                "--force",
+                "--max-files=10",
+            ]
+        );
+    }
+
+    #[test]
+    fn to_argv_invalid_value() {
+        let request = request(r##"{"command": "export", "options": {"filter": {"tag": "#one"}}}"##);
+
+        assert!(to_argv(&request).is_err());
+    }
+
+    #[test]
+    fn request_unknown_field() {
+        assert!(serde_json::from_str::<Request>(r#"{"command": "export", "flags": []}"#).is_err());
+    }
+}
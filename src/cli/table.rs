@@ -0,0 +1,152 @@
+/// The maximum number of characters a single cell can display before being truncated.
+const MAX_CELL_WIDTH: usize = 48;
+
+/// A minimal plain-text table for printing data to the terminal.
+#[derive(Debug, Default)]
+pub struct Table {
+    /// The column headers.
+    headers: Vec<String>,
+
+    /// The table's rows. Each row should have the same number of cells as there are headers.
+    rows: Vec<Vec<String>>,
+}
+
+impl Table {
+    /// Creates a new instance of [`Table`].
+    ///
+    /// # Arguments
+    ///
+    /// * `headers` - The column headers.
+    pub fn new<I, S>(headers: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self {
+            headers: headers.into_iter().map(Into::into).collect(),
+            rows: Vec::new(),
+        }
+    }
+
+    /// Appends a row to the table.
+    ///
+    /// # Arguments
+    ///
+    /// * `row` - The row's cells.
+    pub fn add_row<I, S>(&mut self, row: I)
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.rows.push(row.into_iter().map(Into::into).collect());
+    }
+
+    /// Returns `true` if the table contains no rows.
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+
+    /// Returns the display width of each column.
+    fn widths(&self) -> Vec<usize> {
+        let mut widths: Vec<usize> = self.headers.iter().map(|h| h.chars().count()).collect();
+
+        for row in &self.rows {
+            for (index, cell) in row.iter().enumerate().take(widths.len()) {
+                widths[index] = widths[index].max(Self::truncate(cell).chars().count());
+            }
+        }
+
+        widths
+    }
+
+    /// Collapses whitespace and truncates a cell to [`MAX_CELL_WIDTH`] characters.
+    ///
+    /// # Arguments
+    ///
+    /// * `cell` - The cell's contents.
+    fn truncate(cell: &str) -> String {
+        let cell = cell.split_whitespace().collect::<Vec<_>>().join(" ");
+
+        if cell.chars().count() <= MAX_CELL_WIDTH {
+            return cell;
+        }
+
+        let mut truncated: String = cell.chars().take(MAX_CELL_WIDTH - 1).collect();
+        truncated.push('…');
+        truncated
+    }
+
+    /// Writes a single line of padded cells.
+    fn write_line<S>(
+        f: &mut std::fmt::Formatter<'_>,
+        cells: &[S],
+        widths: &[usize],
+    ) -> std::fmt::Result
+    where
+        S: AsRef<str>,
+    {
+        let line = widths
+            .iter()
+            .enumerate()
+            .map(|(index, width)| {
+                let cell = cells.get(index).map_or("", AsRef::as_ref);
+                let cell = Self::truncate(cell);
+                let padding = width.saturating_sub(cell.chars().count());
+                format!("{cell}{}", " ".repeat(padding))
+            })
+            .collect::<Vec<_>>()
+            .join("  ");
+
+        writeln!(f, "{}", line.trim_end())
+    }
+}
+
+impl std::fmt::Display for Table {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let widths = self.widths();
+
+        Self::write_line(f, &self.headers, &widths)?;
+
+        let separators: Vec<String> = widths.iter().map(|width| "-".repeat(*width)).collect();
+        Self::write_line(f, &separators, &widths)?;
+
+        for row in &self.rows {
+            Self::write_line(f, row, &widths)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    // Tests that columns are padded to their widest cell.
+    #[test]
+    fn columns_aligned() {
+        let mut table = Table::new(["Title", "Count"]);
+        table.add_row(["The Art Spirit", "4"]);
+        table.add_row(["Think on These Things", "12"]);
+
+        assert_eq!(
+            table.to_string(),
+            "Title                  Count\n\
+             ---------------------  -----\n\
+             The Art Spirit         4\n\
+             Think on These Things  12\n"
+        );
+    }
+
+    // Tests that long cells are truncated and whitespace is collapsed.
+    #[test]
+    fn cells_truncated() {
+        let long = "word ".repeat(20);
+        let truncated = Table::truncate(&long);
+
+        assert_eq!(truncated.chars().count(), MAX_CELL_WIDTH);
+        assert!(truncated.ends_with('…'));
+        assert_eq!(Table::truncate("a\n  b"), "a b");
+    }
+}
@@ -0,0 +1,116 @@
+use std::collections::BTreeSet;
+use std::path::PathBuf;
+
+use color_eyre::eyre::WrapErr;
+
+use lib::render::renderer::{RenderOptions, Renderer};
+use lib::render::template::Template;
+
+use crate::CliResult;
+
+use super::defaults;
+
+/// Prints every template in a templates directory along with its config. If no directory is set,
+/// the default template is printed instead.
+///
+/// # Arguments
+///
+/// * `templates_directory` - An optional path to a directory containing user-generated templates.
+///
+/// # Errors
+///
+/// Will return `Err` if any templates fail to build. See [`Renderer::init()`].
+pub fn list(templates_directory: Option<PathBuf>) -> CliResult<()> {
+    let is_default = templates_directory.is_none();
+
+    let options = RenderOptions {
+        templates_directory,
+        ..Default::default()
+    };
+
+    let mut renderer = Renderer::new(options, defaults::TEMPLATE.into());
+
+    renderer
+        .init()
+        .wrap_err("Failed while initializing template(s)")?;
+
+    let mut templates: Vec<&Template> = renderer.templates().iter().collect();
+    templates.sort_by(|a, b| a.id.cmp(&b.id));
+
+    if templates.is_empty() {
+        println!("No templates found.");
+        return Ok(());
+    }
+
+    if is_default {
+        println!("No templates directory set. Showing the default template.\n");
+    }
+
+    for template in &templates {
+        println!("{}\n", self::describe(template));
+    }
+
+    let groups: BTreeSet<&str> = templates
+        .iter()
+        .map(|template| template.group.as_str())
+        .collect();
+
+    println!(
+        "Found {} template(s) in {} group(s): {}",
+        templates.len(),
+        groups.len(),
+        groups.into_iter().collect::<Vec<_>>().join(", ")
+    );
+
+    Ok(())
+}
+
+/// Returns a template's id followed by its config, one field per line.
+///
+/// # Arguments
+///
+/// * `template` - The template to describe.
+fn describe(template: &Template) -> String {
+    let fields = [
+        ("group", template.group.clone()),
+        ("context", template.context_mode.to_string()),
+        ("structure", template.structure_mode.to_string()),
+        ("extension", template.extension.clone()),
+        ("encoding", template.encoding.to_string()),
+        ("newline", template.newline.to_string()),
+        (
+            "output-directory",
+            template.root_directory().display().to_string(),
+        ),
+        ("skip-if", template.skip_if.clone().unwrap_or_default()),
+        (
+            "only-tags",
+            template
+                .only_tags
+                .iter()
+                .map(String::as_str)
+                .collect::<Vec<_>>()
+                .join(" "),
+        ),
+        ("names.book", template.names.book.clone()),
+        ("names.annotation", template.names.annotation.clone()),
+        ("names.tag", template.names.tag.clone()),
+        ("names.directory", template.names.directory.clone()),
+        (
+            "names.dated-directory",
+            template.names.dated_directory.clone(),
+        ),
+    ];
+
+    let width = fields.iter().map(|(key, _)| key.len()).max().unwrap_or(0);
+
+    let mut lines = vec![template.id.clone()];
+
+    lines.extend(
+        fields
+            .into_iter()
+            .map(|(key, value)| format!("  {key:<width$}  {value}")),
+    );
+
+    lines.join("\n")
+}
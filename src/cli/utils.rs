@@ -1,8 +1,60 @@
+use std::fs::OpenOptions;
+use std::sync::Mutex;
+
+use color_eyre::eyre::WrapErr;
+use tracing_subscriber::filter::{EnvFilter, LevelFilter};
+
 use crate::cli;
+use crate::cli::args::GlobalOptions;
+use crate::cli::CliResult;
+
+/// Initializes the logger. Log messages are printed to stderr or, if `--log-file` is set, appended
+/// to a file.
+///
+/// The log level is taken from `--log-level` and then the [`cli::defaults::READSTOR_LOG`]
+/// environment variable. If neither is set, only errors are logged, or, when logging to a file,
+/// everything from `info` and up so the file can be used to audit a run after the fact.
+///
+/// # Arguments
+///
+/// * `options` - The command's global options, if any.
+///
+/// # Errors
+///
+/// Will return `Err` if the log file cannot be opened.
+pub fn init_logger(options: Option<&GlobalOptions>) -> CliResult<()> {
+    let log_file = options.and_then(|options| options.log_file.as_ref());
+
+    let filter = match options.and_then(|options| options.log_level) {
+        Some(level) => EnvFilter::default().add_directive(LevelFilter::from(level).into()),
+        None => EnvFilter::builder()
+            .with_default_directive(if log_file.is_some() {
+                LevelFilter::INFO.into()
+            } else {
+                LevelFilter::ERROR.into()
+            })
+            .with_env_var(cli::defaults::READSTOR_LOG)
+            .from_env_lossy(),
+    };
+
+    let subscriber = tracing_subscriber::fmt().with_env_filter(filter);
+
+    if let Some(path) = log_file {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .wrap_err(format!("Failed while opening log file: {}", path.display()))?;
+
+        subscriber
+            .with_ansi(false)
+            .with_writer(Mutex::new(file))
+            .init();
+    } else {
+        subscriber.with_writer(std::io::stderr).init();
+    }
 
-pub fn init_logger() {
-    let env = env_logger::Env::default().filter(cli::defaults::READSTOR_LOG);
-    env_logger::init_from_env(env);
+    Ok(())
 }
 
 /// Returns a `bool` representing if the application is being developed or not. The state is
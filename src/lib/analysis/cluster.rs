@@ -0,0 +1,382 @@
+//! Defines types for clustering annotations by textual similarity.
+//!
+//! Each annotation is reduced to a set of tokens and a [MinHash][minhash] signature. Signatures are
+//! split into bands and annotations sharing a band become candidate pairs. Each candidate pair is
+//! then verified by the exact [Jaccard similarity][jaccard] of their tokens. This avoids comparing
+//! every annotation with every other annotation in large libraries.
+//!
+//! [minhash]: https://en.wikipedia.org/wiki/MinHash
+//! [jaccard]: https://en.wikipedia.org/wiki/Jaccard_index
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+
+use serde::Serialize;
+
+use crate::models::annotation::Annotation;
+use crate::models::entry::Entry;
+
+/// The name of the file clusters are written to.
+pub const CLUSTERS_FILENAME: &str = "clusters.json";
+
+/// The number of hash functions in each annotation's signature.
+const SIGNATURE_LENGTH: usize = 128;
+
+/// The number of rows per band. With 128 hashes this results in 32 bands which finds most pairs
+/// with a similarity above ~0.4.
+const BAND_ROWS: usize = 4;
+
+/// The maximum number of shared terms listed per cluster.
+const MAX_CLUSTER_TERMS: usize = 5;
+
+/// A struct representing options for clustering annotations.
+#[derive(Debug, Clone, Copy)]
+pub struct ClusterOptions {
+    /// The minimum Jaccard similarity, between `0.0` and `1.0`, for two annotations to be placed
+    /// in the same cluster.
+    pub threshold: f64,
+
+    /// The minimum number of annotations in a cluster.
+    pub min_size: usize,
+}
+
+impl Default for ClusterOptions {
+    fn default() -> Self {
+        Self {
+            threshold: 0.5,
+            min_size: 2,
+        }
+    }
+}
+
+/// A struct representing a group of similar annotations.
+#[derive(Debug, Serialize)]
+pub struct Cluster<'a> {
+    /// The terms shared by the most annotations in the cluster, most common first.
+    pub terms: Vec<String>,
+
+    /// The cluster's annotations.
+    pub annotations: Vec<ClusterMember<'a>>,
+}
+
+/// A struct representing an annotation within a [`Cluster`].
+#[derive(Debug, Serialize)]
+pub struct ClusterMember<'a> {
+    /// The title of the annotation's book.
+    pub title: &'a str,
+
+    /// The author of the annotation's book.
+    pub author: &'a str,
+
+    /// The annotation.
+    #[serde(flatten)]
+    pub annotation: &'a Annotation,
+}
+
+/// Clusters annotations by the similarity of their text across all books.
+///
+/// Two annotations are similar if the Jaccard similarity of their tokens is at least
+/// [`ClusterOptions::threshold`]. Clusters are formed transitively i.e. if `A` is similar to `B`
+/// and `B` to `C`, all three are placed in the same cluster. Annotations without any similar
+/// annotations are left out.
+///
+/// Clusters are returned largest first. Annotations within a cluster are ordered by book and then
+/// by their position within the book.
+///
+/// # Arguments
+///
+/// * `entries` - The entries to cluster.
+/// * `options` - The cluster options.
+pub fn run<'a, I>(entries: I, options: ClusterOptions) -> Vec<Cluster<'a>>
+where
+    I: IntoIterator<Item = &'a Entry>,
+{
+    let members: Vec<ClusterMember<'a>> = entries
+        .into_iter()
+        .flat_map(|entry| {
+            entry.annotations.iter().map(|annotation| ClusterMember {
+                title: &entry.book.title,
+                author: &entry.book.author,
+                annotation,
+            })
+        })
+        .collect();
+
+    let tokens: Vec<BTreeSet<String>> = members
+        .iter()
+        .map(|member| self::tokenize(&member.annotation.body))
+        .collect();
+
+    let mut groups = UnionFind::new(members.len());
+
+    for (a, b) in self::candidate_pairs(&tokens) {
+        if self::jaccard(&tokens[a], &tokens[b]) >= options.threshold {
+            groups.union(a, b);
+        }
+    }
+
+    let mut clusters: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+
+    for index in 0..members.len() {
+        clusters.entry(groups.find(index)).or_default().push(index);
+    }
+
+    let mut members: Vec<Option<ClusterMember<'a>>> = members.into_iter().map(Some).collect();
+
+    let mut clusters: Vec<Cluster<'a>> = clusters
+        .into_values()
+        .filter(|indices| indices.len() >= options.min_size.max(2))
+        .map(|indices| {
+            let terms = self::shared_terms(indices.iter().map(|index| &tokens[*index]));
+
+            let mut annotations: Vec<ClusterMember<'a>> = indices
+                .into_iter()
+                .filter_map(|index| members[index].take())
+                .collect();
+
+            annotations.sort_by(|a, b| {
+                a.title
+                    .cmp(b.title)
+                    .then_with(|| a.author.cmp(b.author))
+                    .then_with(|| a.annotation.cmp(b.annotation))
+            });
+
+            Cluster { terms, annotations }
+        })
+        .collect();
+
+    clusters.sort_by_key(|cluster| std::cmp::Reverse(cluster.annotations.len()));
+
+    clusters
+}
+
+/// Splits a string into a set of lowercase tokens, ignoring short tokens and stop-words. See
+/// [`super::words()`] and [`super::is_significant()`].
+///
+/// # Arguments
+///
+/// * `string` - The string to tokenize.
+fn tokenize(string: &str) -> BTreeSet<String> {
+    super::words(string)
+        .filter(|token| super::is_significant(token))
+        .collect()
+}
+
+/// Returns the Jaccard similarity of two sets of tokens.
+///
+/// # Arguments
+///
+/// * `a` - The first set of tokens.
+/// * `b` - The second set of tokens.
+#[allow(clippy::cast_precision_loss)]
+fn jaccard(a: &BTreeSet<String>, b: &BTreeSet<String>) -> f64 {
+    let union = a.union(b).count();
+
+    if union == 0 {
+        return 0.0;
+    }
+
+    a.intersection(b).count() as f64 / union as f64
+}
+
+/// Returns the [MinHash][minhash] signature of a set of tokens. Returns `None` if the set is empty.
+///
+/// # Arguments
+///
+/// * `tokens` - The set of tokens.
+///
+/// [minhash]: https://en.wikipedia.org/wiki/MinHash
+fn signature(tokens: &BTreeSet<String>) -> Option<[u64; SIGNATURE_LENGTH]> {
+    if tokens.is_empty() {
+        return None;
+    }
+
+    let mut signature = [u64::MAX; SIGNATURE_LENGTH];
+
+    for token in tokens {
+        for (seed, minimum) in signature.iter_mut().enumerate() {
+            let mut hasher = DefaultHasher::new();
+            seed.hash(&mut hasher);
+            token.hash(&mut hasher);
+
+            *minimum = (*minimum).min(hasher.finish());
+        }
+    }
+
+    Some(signature)
+}
+
+/// Returns the pairs of indices whose signatures share at least one band.
+///
+/// # Arguments
+///
+/// * `tokens` - The set of tokens for each annotation.
+fn candidate_pairs(tokens: &[BTreeSet<String>]) -> HashSet<(usize, usize)> {
+    let mut buckets: HashMap<(usize, &[u64]), Vec<usize>> = HashMap::new();
+
+    let signatures: Vec<Option<[u64; SIGNATURE_LENGTH]>> =
+        tokens.iter().map(self::signature).collect();
+
+    for (index, signature) in signatures.iter().enumerate() {
+        let Some(signature) = signature else {
+            continue;
+        };
+
+        for (band, rows) in signature.chunks(BAND_ROWS).enumerate() {
+            buckets.entry((band, rows)).or_default().push(index);
+        }
+    }
+
+    let mut pairs = HashSet::new();
+
+    for indices in buckets.values() {
+        for (i, a) in indices.iter().enumerate() {
+            for b in &indices[i + 1..] {
+                pairs.insert((*a, *b));
+            }
+        }
+    }
+
+    pairs
+}
+
+/// Returns the terms shared by the most sets of tokens, most common first. Terms that appear in
+/// only one set are left out.
+///
+/// # Arguments
+///
+/// * `tokens` - The sets of tokens.
+fn shared_terms<'a, I>(tokens: I) -> Vec<String>
+where
+    I: Iterator<Item = &'a BTreeSet<String>>,
+{
+    let mut counts: BTreeMap<&str, usize> = BTreeMap::new();
+
+    for token in tokens.flatten() {
+        *counts.entry(token).or_default() += 1;
+    }
+
+    let mut counts: Vec<(&str, usize)> = counts.into_iter().filter(|(_, n)| *n > 1).collect();
+
+    // The sort is stable so terms with equal counts stay in alphabetical order.
+    counts.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+
+    counts
+        .into_iter()
+        .take(MAX_CLUSTER_TERMS)
+        .map(|(term, _)| term.to_owned())
+        .collect()
+}
+
+/// A minimal union-find used to group similar annotations transitively.
+struct UnionFind(Vec<usize>);
+
+impl UnionFind {
+    /// Creates a new instance of [`UnionFind`] where each index is in its own group.
+    fn new(len: usize) -> Self {
+        Self((0..len).collect())
+    }
+
+    /// Returns the representative index of an index's group.
+    fn find(&mut self, index: usize) -> usize {
+        let mut root = index;
+
+        while self.0[root] != root {
+            root = self.0[root];
+        }
+
+        // Compresses the path so subsequent lookups are faster.
+        let mut index = index;
+
+        while self.0[index] != root {
+            index = std::mem::replace(&mut self.0[index], root);
+        }
+
+        root
+    }
+
+    /// Merges the groups of two indices.
+    fn union(&mut self, a: usize, b: usize) {
+        let (a, b) = (self.find(a), self.find(b));
+
+        if a != b {
+            self.0[b] = a;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    use crate::models::book::Book;
+
+    fn entry(title: &str, bodies: &[&str]) -> Entry {
+        Entry {
+            book: Book {
+                title: title.to_string(),
+                ..Default::default()
+            },
+            annotations: bodies
+                .iter()
+                .map(|body| Annotation {
+                    body: (*body).to_string(),
+                    ..Default::default()
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn tokenize_ignores_stopwords() {
+        assert_eq!(
+            tokenize("The art of seeing, and the Art of living!"),
+            BTreeSet::from([
+                "art".to_string(),
+                "living".to_string(),
+                "seeing".to_string()
+            ])
+        );
+    }
+
+    // Tests that similar annotations across books are clustered and dissimilar ones left out.
+    #[test]
+    fn cluster_across_books() {
+        let entries = [
+            entry(
+                "First",
+                &[
+                    "Attention is the rarest and purest form of generosity",
+                    "The sea was calm that morning",
+                ],
+            ),
+            entry(
+                "Second",
+                &["Attention is the purest and rarest form of generosity we have"],
+            ),
+        ];
+
+        let clusters = run(&entries, ClusterOptions::default());
+
+        assert_eq!(clusters.len(), 1);
+        assert_eq!(clusters[0].annotations.len(), 2);
+        assert_eq!(clusters[0].annotations[0].title, "First");
+        assert_eq!(clusters[0].annotations[1].title, "Second");
+        assert_eq!(
+            clusters[0].terms,
+            ["attention", "form", "generosity", "purest", "rarest"]
+        );
+    }
+
+    #[test]
+    fn union_find_transitive() {
+        let mut groups = UnionFind::new(4);
+        groups.union(0, 1);
+        groups.union(1, 2);
+
+        assert_eq!(groups.find(0), groups.find(2));
+        assert_ne!(groups.find(0), groups.find(3));
+    }
+}
@@ -0,0 +1,178 @@
+//! Defines types for aggregating where in each book annotations were made.
+//!
+//! Each annotation's `epubcfi` is resolved to a position from `0.0` to `1.0` within its book's
+//! EPUB. See [`Epub::position()`][position]. Positions are then counted into equally sized
+//! buckets so external tools can plot where in books highlights tend to be made.
+//!
+//! [position]: crate::epub::Epub::position
+
+use serde::Serialize;
+
+use crate::epub::EpubCache;
+use crate::models::entry::Entry;
+use crate::models::id;
+
+/// The name of the file heatmaps are written to.
+pub const HEATMAP_FILENAME: &str = "heatmap.json";
+
+/// A struct representing options for aggregating annotation positions.
+#[derive(Debug, Clone, Copy)]
+pub struct HeatmapOptions {
+    /// The number of equally sized buckets positions are counted into.
+    pub buckets: usize,
+}
+
+impl Default for HeatmapOptions {
+    fn default() -> Self {
+        Self { buckets: 10 }
+    }
+}
+
+/// A struct representing where in a book its annotations were made.
+#[derive(Debug, Serialize)]
+pub struct Heatmap<'a> {
+    /// The book's id.
+    pub id: &'a str,
+
+    /// The title of the book.
+    pub title: &'a str,
+
+    /// The author of the book.
+    pub author: &'a str,
+
+    /// The position of each annotation, from `0.0` to `1.0`, in the order they appear in the book.
+    pub positions: Vec<f64>,
+
+    /// The number of annotations within each bucket, from the start of the book to the end.
+    pub buckets: Vec<usize>,
+
+    /// The number of annotations whose position couldn't be resolved e.g. if the book's EPUB isn't
+    /// downloaded.
+    pub unresolved: usize,
+}
+
+/// Aggregates the positions of each book's annotations.
+///
+/// Books are returned in the order they're passed in. A position exactly at the end of a book is
+/// counted into the last bucket.
+///
+/// # Arguments
+///
+/// * `entries` - The entries to aggregate.
+/// * `epubs` - The EPUBs to resolve positions with.
+/// * `options` - The heatmap options.
+#[allow(
+    clippy::cast_possible_truncation,
+    clippy::cast_precision_loss,
+    clippy::cast_sign_loss
+)]
+pub fn run<'a, I>(entries: I, epubs: &mut EpubCache, options: HeatmapOptions) -> Vec<Heatmap<'a>>
+where
+    I: IntoIterator<Item = &'a Entry>,
+{
+    let size = options.buckets.max(1);
+
+    entries
+        .into_iter()
+        .map(|entry| {
+            // EPUBs are named after Apple Books' id, which differs from the book's id if the ids
+            // were remapped.
+            let asset_id = id::or_existing(&entry.book.metadata.asset_id, &entry.book.metadata.id);
+            let epub = epubs.get(&asset_id);
+
+            let mut positions: Vec<f64> = entry
+                .annotations
+                .iter()
+                .filter_map(|annotation| {
+                    epub.and_then(|epub| epub.position(&annotation.metadata.epubcfi))
+                })
+                .collect();
+
+            positions.sort_by(f64::total_cmp);
+
+            let mut buckets = vec![0; size];
+
+            for position in &positions {
+                let index = ((position * size as f64) as usize).min(size - 1);
+                buckets[index] += 1;
+            }
+
+            Heatmap {
+                id: &entry.book.metadata.id,
+                title: &entry.book.title,
+                author: &entry.book.author,
+                unresolved: entry.annotations.len() - positions.len(),
+                positions,
+                buckets,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    use crate::defaults::CRATE_ROOT;
+    use crate::models::annotation::{Annotation, AnnotationMetadata};
+    use crate::models::book::{Book, BookMetadata};
+
+    fn entry(id: &str, epubcfi: &[&str]) -> Entry {
+        Entry {
+            book: Book {
+                metadata: BookMetadata {
+                    id: id.to_string(),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            annotations: epubcfi
+                .iter()
+                .map(|epubcfi| Annotation {
+                    metadata: AnnotationMetadata {
+                        epubcfi: (*epubcfi).to_string(),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                })
+                .collect(),
+        }
+    }
+
+    // Tests that positions are bucketed and annotations without an EPUB are unresolved.
+    #[test]
+    fn bucket_positions() {
+        let mut directory = CRATE_ROOT.to_owned();
+        directory.extend(["data", "databases", "books-annotated", "Books"].iter());
+
+        let entries = [
+            // The test EPUB's spine has 27 items and no content so each takes up an equal share.
+            entry(
+                "8E6660FFF4EB8789B4BDF5FAD625CCFC",
+                &[
+                    "epubcfi(/6/42[c17]!/4,/14/1:41,/20/1:359)",
+                    "epubcfi(/6/2[cover]!/4/2/1:0)",
+                    "invalid",
+                ],
+            ),
+            entry("missing", &["epubcfi(/6/2[cover]!/4/2/1:0)"]),
+        ];
+
+        let heatmaps = run(
+            &entries,
+            &mut EpubCache::new(&directory),
+            HeatmapOptions { buckets: 4 },
+        );
+
+        assert_eq!(heatmaps.len(), 2);
+
+        assert_eq!(heatmaps[0].positions, [0.0, 20.0 / 27.0]);
+        assert_eq!(heatmaps[0].buckets, [1, 0, 1, 0]);
+        assert_eq!(heatmaps[0].unresolved, 1);
+
+        assert!(heatmaps[1].positions.is_empty());
+        assert_eq!(heatmaps[1].buckets, [0, 0, 0, 0]);
+        assert_eq!(heatmaps[1].unresolved, 1);
+    }
+}
@@ -0,0 +1,70 @@
+//! Defines types for analyzing books and annotations.
+
+pub mod cluster;
+pub mod heatmap;
+pub mod ngrams;
+
+/// The minimum number of characters a token must have.
+pub(crate) const MIN_TOKEN_LENGTH: usize = 3;
+
+/// Common English words that are ignored when analyzing annotations.
+pub(crate) const STOPWORDS: &[&str] = &[
+    "about", "after", "all", "also", "and", "any", "are", "because", "been", "but", "can", "could",
+    "did", "does", "for", "from", "had", "has", "have", "her", "him", "his", "how", "into", "its",
+    "just", "more", "most", "not", "now", "one", "only", "other", "our", "out", "she", "should",
+    "some", "such", "than", "that", "the", "their", "them", "then", "there", "these", "they",
+    "this", "those", "was", "were", "what", "when", "which", "while", "who", "will", "with",
+    "would", "you", "your",
+];
+
+/// Returns an iterator over the lowercase words of a string. Words are runs of alphanumeric
+/// characters. Everything else, including punctuation, separates them.
+///
+/// This is shared by everything that splits annotations into words e.g. search, clustering and
+/// n-grams, so the same text is always split the same way.
+///
+/// # Arguments
+///
+/// * `string` - The string to split.
+pub fn words(string: &str) -> impl Iterator<Item = String> + '_ {
+    string
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+}
+
+/// Returns `true` if a lowercase word is worth analyzing i.e. it has at least
+/// [`MIN_TOKEN_LENGTH`] characters and isn't one of the [`STOPWORDS`].
+///
+/// # Arguments
+///
+/// * `word` - The lowercase word to check.
+#[must_use]
+pub fn is_significant(word: &str) -> bool {
+    word.chars().count() >= MIN_TOKEN_LENGTH && !STOPWORDS.contains(&word)
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    // Tests that words are lowercased and split on anything that isn't alphanumeric.
+    #[test]
+    fn split_words() {
+        let words: Vec<String> = words("Art is the province—of every human-being.").collect();
+
+        assert_eq!(
+            words,
+            ["art", "is", "the", "province", "of", "every", "human", "being"]
+        );
+    }
+
+    // Tests that short words and stop-words aren't significant.
+    #[test]
+    fn significant_words() {
+        assert!(is_significant("province"));
+        assert!(!is_significant("is"));
+        assert!(!is_significant("the"));
+    }
+}
@@ -0,0 +1,206 @@
+//! Defines types for counting the most frequent words and phrases across annotations.
+//!
+//! Each annotation's body is split into lowercase words. An n-gram is a run of `n` consecutive
+//! words e.g. `art spirit` is a bigram. N-grams containing a stop-word or a short word are skipped
+//! so the results surface the themes of the highlights rather than the grammar holding them
+//! together.
+
+use std::collections::{HashMap, HashSet};
+
+use serde::Serialize;
+
+use crate::models::entry::Entry;
+
+/// The maximum number of words in an n-gram.
+pub const MAX_NGRAM_SIZE: usize = 3;
+
+/// A struct representing options for counting n-grams.
+#[derive(Debug, Clone, Copy)]
+pub struct NgramOptions {
+    /// The number of words in each n-gram, between `1` and [`MAX_NGRAM_SIZE`].
+    pub size: usize,
+
+    /// The maximum number of n-grams to return.
+    pub top: usize,
+}
+
+impl Default for NgramOptions {
+    fn default() -> Self {
+        Self { size: 1, top: 50 }
+    }
+}
+
+/// A struct representing a single n-gram and how often it occurs.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Ngram {
+    /// The n-gram's words, separated by a single space.
+    pub text: String,
+
+    /// The number of times the n-gram occurs across all annotations.
+    pub count: usize,
+
+    /// The number of books the n-gram occurs in.
+    pub books: usize,
+}
+
+/// Counts the most frequent n-grams across the bodies of all annotations.
+///
+/// N-grams are returned most frequent first. Ties are ordered by the number of books the n-gram
+/// occurs in and then alphabetically. N-grams never span two annotations.
+///
+/// # Arguments
+///
+/// * `entries` - The entries to analyze.
+/// * `options` - The n-gram options.
+pub fn run<'a, I>(entries: I, options: NgramOptions) -> Vec<Ngram>
+where
+    I: IntoIterator<Item = &'a Entry>,
+{
+    let size = options.size.clamp(1, MAX_NGRAM_SIZE);
+
+    let mut counts: HashMap<String, (usize, HashSet<&str>)> = HashMap::new();
+
+    for entry in entries {
+        for annotation in &entry.annotations {
+            for ngram in self::ngrams(&annotation.body, size) {
+                let (count, books) = counts.entry(ngram).or_default();
+                *count += 1;
+                books.insert(&entry.book.metadata.id);
+            }
+        }
+    }
+
+    let mut ngrams: Vec<Ngram> = counts
+        .into_iter()
+        .map(|(text, (count, books))| Ngram {
+            text,
+            count,
+            books: books.len(),
+        })
+        .collect();
+
+    ngrams.sort_by(|a, b| {
+        b.count
+            .cmp(&a.count)
+            .then_with(|| b.books.cmp(&a.books))
+            .then_with(|| a.text.cmp(&b.text))
+    });
+
+    ngrams.truncate(options.top);
+
+    ngrams
+}
+
+/// Returns n-grams as CSV with a header row.
+///
+/// # Arguments
+///
+/// * `ngrams` - The n-grams to format.
+#[must_use]
+pub fn to_csv(ngrams: &[Ngram]) -> String {
+    let mut lines = vec!["ngram,count,books".to_string()];
+
+    lines.extend(ngrams.iter().map(|ngram| {
+        // N-grams only contain alphanumeric characters and spaces so they never need quoting.
+        format!("{},{},{}", ngram.text, ngram.count, ngram.books)
+    }));
+
+    lines.push(String::new());
+    lines.join("\n")
+}
+
+/// Splits a string into its n-grams, skipping any containing a short word or a stop-word. See
+/// [`super::words()`] and [`super::is_significant()`].
+///
+/// # Arguments
+///
+/// * `string` - The string to split.
+/// * `size` - The number of words in each n-gram.
+fn ngrams(string: &str, size: usize) -> Vec<String> {
+    let words: Vec<String> = super::words(string).collect();
+
+    words
+        .windows(size)
+        .filter(|window| window.iter().all(|word| super::is_significant(word)))
+        .map(|window| window.join(" "))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    use crate::models::annotation::Annotation;
+    use crate::models::book::{Book, BookMetadata};
+
+    fn entry(id: &str, bodies: &[&str]) -> Entry {
+        Entry {
+            book: Book {
+                metadata: BookMetadata {
+                    id: id.to_string(),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            annotations: bodies
+                .iter()
+                .map(|body| Annotation {
+                    body: (*body).to_string(),
+                    ..Default::default()
+                })
+                .collect(),
+        }
+    }
+
+    // Tests that stop-words don't join the words around them into a bigram.
+    #[test]
+    fn bigrams_skip_stopwords() {
+        assert_eq!(
+            ngrams("The art spirit is the art of living.", 2),
+            ["art spirit"]
+        );
+    }
+
+    #[test]
+    fn count_words() {
+        let entries = [
+            entry("1", &["Art is a province of every human being.", "Art!"]),
+            entry("2", &["Every human being is an artist."]),
+        ];
+
+        let ngrams = run(&entries, NgramOptions { size: 1, top: 3 });
+
+        assert_eq!(
+            ngrams,
+            [
+                Ngram {
+                    text: "being".to_string(),
+                    count: 2,
+                    books: 2,
+                },
+                Ngram {
+                    text: "every".to_string(),
+                    count: 2,
+                    books: 2,
+                },
+                Ngram {
+                    text: "human".to_string(),
+                    count: 2,
+                    books: 2,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn csv() {
+        let ngrams = [Ngram {
+            text: "human being".to_string(),
+            count: 2,
+            books: 1,
+        }];
+
+        assert_eq!(to_csv(&ngrams), "ngram,count,books\nhuman being,2,1\n");
+    }
+}
@@ -1,6 +1,7 @@
 //! Defines defaults for working with Apple Books for iOS.
 
 use std::path::PathBuf;
+use std::time::Duration;
 
 use once_cell::sync::Lazy;
 
@@ -14,3 +15,16 @@ use once_cell::sync::Lazy;
 /// /Books
 /// ```
 pub static DATA_DIRECTORY: Lazy<PathBuf> = Lazy::new(|| PathBuf::from("Books"));
+
+/// The maximum number of bytes requested per read from an iOS device.
+pub const READ_CHUNK_SIZE: u32 = 64 * 1024;
+
+/// The number of bytes read from an iOS device between each progress report.
+pub const READ_PROGRESS_INTERVAL: u64 = 1024 * 1024;
+
+/// The number of times a failed read from an iOS device is retried before giving up.
+pub const READ_RETRIES: u32 = 3;
+
+/// The delay before retrying a failed read from an iOS device. This is multiplied by the attempt
+/// number.
+pub const READ_RETRY_DELAY: Duration = Duration::from_millis(100);
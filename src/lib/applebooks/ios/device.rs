@@ -0,0 +1,388 @@
+//! Defines types for reading files from an iOS device.
+//!
+//! All reads from an iOS device go through the [`Device`] trait. This allows the extraction logic to
+//! be developed and tested against a [`MockDevice`], backed by a directory on the host, instead of
+//! a connected device. Setting the [`MOCK_DEVICE`] environment variable replaces any connected
+//! device with a [`MockDevice`].
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use rusty_libimobiledevice::idevice;
+use rusty_libimobiledevice::services::afc::{AfcClient, AfcFileMode};
+
+use crate::result::{Error, Result};
+
+/// Defines the environment variable key used to read from a [`MockDevice`] instead of a connected
+/// iOS device. The value is the path to the directory representing the device's filesystem e.g.
+/// `[directory]/Books/Books.plist`.
+pub const MOCK_DEVICE: &str = "READSTOR_IOS_MOCK_DEVICE";
+
+/// Defines the environment variable key used to set the maximum number of bytes a [`MockDevice`]
+/// returns per read. See [`MockDevice::read_size()`].
+pub const MOCK_DEVICE_READ_SIZE: &str = "READSTOR_IOS_MOCK_READ_SIZE";
+
+/// Defines the environment variable key used to set the number of reads a [`MockDevice`] fails
+/// before succeeding. See [`MockDevice::read_failures()`].
+pub const MOCK_DEVICE_READ_FAILURES: &str = "READSTOR_IOS_MOCK_READ_FAILURES";
+
+/// A trait for reading files from an iOS device's filesystem.
+///
+/// Paths are relative to the root of the filesystem exposed by the device's AFC service e.g.
+/// `Books/Books.plist`.
+pub trait Device {
+    /// Returns the size of a file in bytes. Returns `None` if the device doesn't report it.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path to the file on the device.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the file's information cannot be read.
+    fn file_size(&mut self, path: &str) -> Result<Option<u64>>;
+
+    /// Opens a file for reading and returns its handle.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path to the file on the device.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the file cannot be opened.
+    fn file_open(&mut self, path: &str) -> Result<u64>;
+
+    /// Reads up to `length` bytes from an open file. Fewer bytes may be returned even if the end
+    /// of the file hasn't been reached. An empty buffer is returned at the end of the file.
+    ///
+    /// # Arguments
+    ///
+    /// * `handle` - The handle of the open file.
+    /// * `length` - The maximum number of bytes to read.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the file cannot be read.
+    fn file_read(&mut self, handle: u64, length: u32) -> Result<Vec<u8>>;
+
+    /// Closes an open file.
+    ///
+    /// # Arguments
+    ///
+    /// * `handle` - The handle of the open file.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the file cannot be closed.
+    fn file_close(&mut self, handle: u64) -> Result<()>;
+}
+
+/// Connects to an iOS device and passes it to a function.
+///
+/// If the [`MOCK_DEVICE`] environment variable is set, a [`MockDevice`] is used instead and no
+/// device needs to be connected.
+///
+/// # Arguments
+///
+/// * `udid` - An optional UDID to connect to a specific iOS device.
+/// * `f` - The function to call with the device.
+///
+/// # Errors
+///
+/// Will return `Err` if:
+/// * No device, or no device with the UDID, is connected.
+/// * The device's AFC service cannot be started.
+/// * The mock device's environment variables are invalid.
+/// * The function returns an error.
+pub fn with_device<F, T>(udid: Option<String>, f: F) -> Result<T>
+where
+    F: FnOnce(&mut dyn Device) -> Result<T>,
+{
+    if let Some(mut device) = MockDevice::from_env()? {
+        tracing::debug!("using mock iOS device at {}", device.root.display());
+        return f(&mut device);
+    }
+
+    let device = if let Some(udid) = udid {
+        idevice::get_device(&udid).map_err(|_| Error::IOsDeviceNotFoundWithUdid { udid })?
+    } else {
+        idevice::get_first_device().map_err(|_| Error::IOsDeviceNotFound)?
+    };
+
+    let mut afc_client = device
+        .new_afc_client(crate::defaults::NAME)
+        .map_err(self::read_error)?;
+
+    f(&mut afc_client)
+}
+
+impl Device for AfcClient<'_> {
+    fn file_size(&mut self, path: &str) -> Result<Option<u64>> {
+        let file_info = self.get_file_info(path).map_err(self::read_error)?;
+
+        Ok(file_info
+            .get("st_size")
+            .and_then(|size| size.parse::<u64>().ok()))
+    }
+
+    fn file_open(&mut self, path: &str) -> Result<u64> {
+        AfcClient::file_open(self, path, AfcFileMode::ReadOnly).map_err(self::read_error)
+    }
+
+    fn file_read(&mut self, handle: u64, length: u32) -> Result<Vec<u8>> {
+        AfcClient::file_read(self, handle, length).map_err(self::read_error)
+    }
+
+    fn file_close(&mut self, handle: u64) -> Result<()> {
+        AfcClient::file_close(self, handle).map_err(self::read_error)
+    }
+}
+
+/// A struct representing an iOS device backed by a directory on the host.
+///
+/// The directory represents the root of the device's filesystem. The mock can be configured to
+/// return fewer bytes than requested and to fail reads, to simulate an unreliable connection.
+#[derive(Debug)]
+pub struct MockDevice {
+    /// The directory representing the root of the device's filesystem.
+    root: PathBuf,
+
+    /// The maximum number of bytes returned per read.
+    read_size: Option<u32>,
+
+    /// The number of reads left to fail before succeeding.
+    read_failures: usize,
+
+    /// The open files keyed by their handles.
+    files: HashMap<u64, File>,
+
+    /// The handle to assign to the next opened file.
+    next_handle: u64,
+}
+
+impl MockDevice {
+    /// Creates a new instance of [`MockDevice`].
+    ///
+    /// # Arguments
+    ///
+    /// * `root` - The directory representing the root of the device's filesystem.
+    #[must_use]
+    pub fn new(root: &Path) -> Self {
+        Self {
+            root: root.to_owned(),
+            read_size: None,
+            read_failures: 0,
+            files: HashMap::new(),
+            next_handle: 1,
+        }
+    }
+
+    /// Creates a new instance of [`MockDevice`] from the [`MOCK_DEVICE`],
+    /// [`MOCK_DEVICE_READ_SIZE`] and [`MOCK_DEVICE_READ_FAILURES`] environment variables. Returns
+    /// `None` if [`MOCK_DEVICE`] isn't set.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if [`MOCK_DEVICE_READ_SIZE`] or [`MOCK_DEVICE_READ_FAILURES`] aren't
+    /// numbers.
+    pub fn from_env() -> Result<Option<Self>> {
+        let Some(root) = std::env::var_os(MOCK_DEVICE).filter(|root| !root.is_empty()) else {
+            return Ok(None);
+        };
+
+        let parse = |name: &str| -> Result<Option<u64>> {
+            std::env::var(name)
+                .ok()
+                .map(|value| {
+                    value.parse().map_err(|_| Error::OtherError {
+                        error: format!("Invalid value for {name}: '{value}'"),
+                    })
+                })
+                .transpose()
+        };
+
+        let mut device = Self::new(Path::new(&root));
+
+        if let Some(size) = parse(MOCK_DEVICE_READ_SIZE)? {
+            device = device.read_size(u32::try_from(size).unwrap_or(u32::MAX));
+        }
+
+        if let Some(count) = parse(MOCK_DEVICE_READ_FAILURES)? {
+            device = device.read_failures(usize::try_from(count).unwrap_or(usize::MAX));
+        }
+
+        Ok(Some(device))
+    }
+
+    /// Sets the maximum number of bytes returned per read to simulate partial reads.
+    ///
+    /// # Arguments
+    ///
+    /// * `size` - The maximum number of bytes.
+    #[must_use]
+    pub fn read_size(mut self, size: u32) -> Self {
+        self.read_size = Some(size);
+        self
+    }
+
+    /// Sets the number of reads that fail before reads start succeeding.
+    ///
+    /// # Arguments
+    ///
+    /// * `count` - The number of reads to fail.
+    #[must_use]
+    pub fn read_failures(mut self, count: usize) -> Self {
+        self.read_failures = count;
+        self
+    }
+
+    /// Returns the path on the host of a file on the device.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path to the file on the device.
+    fn host_path(&self, path: &str) -> PathBuf {
+        self.root.join(path.trim_start_matches('/'))
+    }
+}
+
+impl Device for MockDevice {
+    fn file_size(&mut self, path: &str) -> Result<Option<u64>> {
+        let metadata = std::fs::metadata(self.host_path(path))
+            .map_err(|error| self::mock_error(path, &error))?;
+
+        Ok(Some(metadata.len()))
+    }
+
+    fn file_open(&mut self, path: &str) -> Result<u64> {
+        let file =
+            File::open(self.host_path(path)).map_err(|error| self::mock_error(path, &error))?;
+
+        let handle = self.next_handle;
+        self.next_handle += 1;
+        self.files.insert(handle, file);
+
+        Ok(handle)
+    }
+
+    fn file_read(&mut self, handle: u64, length: u32) -> Result<Vec<u8>> {
+        if self.read_failures > 0 {
+            self.read_failures -= 1;
+
+            return Err(Error::IOsDeviceReadError {
+                error: "mock read failure".to_owned(),
+            });
+        }
+
+        let length = self.read_size.map_or(length, |size| size.min(length));
+
+        let file = self
+            .files
+            .get_mut(&handle)
+            .ok_or_else(|| Error::IOsDeviceReadError {
+                error: format!("invalid file handle: {handle}"),
+            })?;
+
+        let mut buffer = Vec::new();
+
+        file.take(u64::from(length))
+            .read_to_end(&mut buffer)
+            .map_err(|error| Error::IOsDeviceReadError {
+                error: error.to_string(),
+            })?;
+
+        Ok(buffer)
+    }
+
+    fn file_close(&mut self, handle: u64) -> Result<()> {
+        self.files
+            .remove(&handle)
+            .map(|_| ())
+            .ok_or_else(|| Error::IOsDeviceReadError {
+                error: format!("invalid file handle: {handle}"),
+            })
+    }
+}
+
+/// Converts an error from `libimobiledevice` into an [`Error::IOsDeviceReadError`].
+fn read_error<E>(error: E) -> Error
+where
+    E: std::fmt::Display,
+{
+    Error::IOsDeviceReadError {
+        error: error.to_string(),
+    }
+}
+
+/// Converts an IO error from a [`MockDevice`] into an [`Error::IOsDeviceReadError`].
+fn mock_error(path: &str, error: &std::io::Error) -> Error {
+    Error::IOsDeviceReadError {
+        error: format!("{path}: {error}"),
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    use crate::defaults::test::MockPlists;
+
+    const PATH: &str = "Books.plist";
+
+    fn read_all(device: &mut MockDevice) -> Vec<u8> {
+        let handle = device.file_open(PATH).unwrap();
+        let mut contents = Vec::new();
+
+        loop {
+            let chunk = device.file_read(handle, 4096).unwrap();
+
+            if chunk.is_empty() {
+                break;
+            }
+
+            contents.extend(chunk);
+        }
+
+        device.file_close(handle).unwrap();
+        contents
+    }
+
+    #[test]
+    fn mock_partial_reads() {
+        let root = PathBuf::from(MockPlists::BooksAnnotated);
+        let expected = std::fs::read(root.join(PATH)).unwrap();
+
+        let mut device = MockDevice::new(&root).read_size(100);
+
+        let handle = device.file_open(PATH).unwrap();
+        assert_eq!(device.file_read(handle, 4096).unwrap().len(), 100);
+        device.file_close(handle).unwrap();
+
+        assert_eq!(device.file_size(PATH).unwrap(), Some(expected.len() as u64));
+        assert_eq!(read_all(&mut device), expected);
+    }
+
+    #[test]
+    fn mock_read_failures() {
+        let mut device =
+            MockDevice::new(&PathBuf::from(MockPlists::BooksAnnotated)).read_failures(1);
+
+        let handle = device.file_open(PATH).unwrap();
+
+        assert!(device.file_read(handle, 4096).is_err());
+        assert!(!device.file_read(handle, 4096).unwrap().is_empty());
+    }
+
+    #[test]
+    fn mock_missing_file() {
+        let mut device = MockDevice::new(&PathBuf::from(MockPlists::Empty));
+
+        assert!(device.file_size("missing.plist").is_err());
+        assert!(device.file_open("missing.plist").is_err());
+        assert!(device.file_close(1).is_err());
+    }
+}
@@ -0,0 +1,334 @@
+//! Defines functions for diagnosing why iOS's Apple Books plists failed to deserialize.
+//!
+//! When a plist doesn't match the models in [`super::models`], it's re-read as an untyped
+//! [`plist::Value`] and compared against a description of the expected structure. This reports the
+//! path to each mismatched key e.g. `Books[2].Artist` along with a summary of the plist's actual
+//! structure. The summary only contains keys and value types, never the values themselves, so it
+//! can be safely shared when reporting an issue.
+
+use std::path::Path;
+
+use plist::Value;
+
+/// The maximum number of mismatches listed in a report.
+const MAX_MISMATCHES: usize = 10;
+
+/// The maximum depth of the structure summary.
+const MAX_DEPTH: usize = 6;
+
+/// An enum representing the expected type of a value in a plist.
+#[derive(Debug, Clone, Copy)]
+pub enum Shape {
+    /// A string.
+    String,
+
+    /// An integer.
+    Integer,
+
+    /// A real number. Integers are also accepted.
+    Real,
+
+    /// An array where every item has the same shape.
+    Array(&'static Shape),
+
+    /// A dictionary with known keys. Unknown keys are ignored.
+    Dictionary(&'static [Field]),
+
+    /// A dictionary with arbitrary keys where every value has the same shape.
+    Map(&'static Shape),
+}
+
+/// A struct representing an expected key in a dictionary.
+#[derive(Debug, Clone, Copy)]
+pub struct Field {
+    /// The key's name in the plist.
+    pub key: &'static str,
+
+    /// The expected shape of the key's value.
+    pub shape: Shape,
+
+    /// Whether the key can be missing.
+    pub is_optional: bool,
+}
+
+impl Field {
+    const fn required(key: &'static str, shape: Shape) -> Self {
+        Self {
+            key,
+            shape,
+            is_optional: false,
+        }
+    }
+
+    const fn optional(key: &'static str, shape: Shape) -> Self {
+        Self {
+            key,
+            shape,
+            is_optional: true,
+        }
+    }
+}
+
+/// The expected shape of a book in the books plist. See [`super::models::BookRaw`].
+const BOOK: Shape = Shape::Dictionary(&[
+    Field::required("Artist", Shape::String),
+    Field::required("Name", Shape::String),
+    Field::required("Package Hash", Shape::String),
+]);
+
+/// The expected shape of the books plist. See [`super::models::BooksPlist`].
+pub const BOOKS_PLIST: Shape = Shape::Dictionary(&[Field::required("Books", Shape::Array(&BOOK))]);
+
+/// The expected shape of an annotation in the annotations plist. See
+/// [`super::models::AnnotationRaw`].
+const ANNOTATION: Shape = Shape::Dictionary(&[
+    Field::required("annotationSelectedText", Shape::String),
+    Field::optional("annotationRepresentativeText", Shape::String),
+    Field::required("annotationStyle", Shape::Integer),
+    Field::optional("annotationNote", Shape::String),
+    Field::required("annotationUuid", Shape::String),
+    Field::required("annotationAssetID", Shape::String),
+    Field::required("annotationCreationDate", Shape::Real),
+    Field::required("annotationModificationDate", Shape::Real),
+    Field::required("annotationLocation", Shape::String),
+    Field::required("annotationDeleted", Shape::Integer),
+]);
+
+/// The expected shape of the annotations plist. See [`super::models::AnnotationsPlist`].
+pub const ANNOTATIONS_PLIST: Shape = Shape::Map(&Shape::Dictionary(&[Field::required(
+    "Bookmarks",
+    Shape::Array(&ANNOTATION),
+)]));
+
+/// Returns a report describing why a plist doesn't match its expected shape. Returns `None` if the
+/// plist cannot be read at all.
+///
+/// # Arguments
+///
+/// * `path` - The path to the plist.
+/// * `shape` - The plist's expected shape.
+#[must_use]
+pub fn report(path: &Path, shape: &Shape) -> Option<String> {
+    let value = Value::from_file(path).ok()?;
+
+    let mut mismatches = Vec::new();
+    self::check(&value, shape, "", &mut mismatches);
+
+    let mut lines = Vec::new();
+
+    if mismatches.is_empty() {
+        lines.push("no mismatched keys found".to_owned());
+    } else {
+        lines.push("mismatched keys:".to_owned());
+        lines.extend(
+            mismatches
+                .iter()
+                .take(MAX_MISMATCHES)
+                .map(|mismatch| format!("  {mismatch}")),
+        );
+
+        if mismatches.len() > MAX_MISMATCHES {
+            lines.push(format!(
+                "  ...and {} more",
+                mismatches.len() - MAX_MISMATCHES
+            ));
+        }
+    }
+
+    lines.push("structure:".to_owned());
+    self::summarize(&value, "", 1, &mut lines);
+
+    Some(lines.join("\n"))
+}
+
+/// Compares a value against its expected shape, collecting a description of each mismatch.
+///
+/// # Arguments
+///
+/// * `value` - The value to check.
+/// * `shape` - The value's expected shape.
+/// * `path` - The path to the value e.g. `Books[2].Artist`.
+/// * `mismatches` - Where to collect the mismatches.
+fn check(value: &Value, shape: &Shape, path: &str, mismatches: &mut Vec<String>) {
+    let path_or_root = if path.is_empty() { "<root>" } else { path };
+
+    match (shape, value) {
+        (Shape::String, Value::String(_))
+        | (Shape::Integer, Value::Integer(_))
+        | (Shape::Real, Value::Real(_) | Value::Integer(_)) => {}
+        (Shape::Array(item), Value::Array(items)) => {
+            for (index, value) in items.iter().enumerate() {
+                self::check(value, item, &format!("{path}[{index}]"), mismatches);
+            }
+        }
+        (Shape::Dictionary(fields), Value::Dictionary(dictionary)) => {
+            for field in *fields {
+                let path = self::join(path, field.key);
+
+                match dictionary.get(field.key) {
+                    Some(value) => self::check(value, &field.shape, &path, mismatches),
+                    None if field.is_optional => {}
+                    None => mismatches.push(format!(
+                        "{path}: missing, expected {}",
+                        self::expected(&field.shape)
+                    )),
+                }
+            }
+        }
+        (Shape::Map(item), Value::Dictionary(dictionary)) => {
+            if dictionary.is_empty() {
+                mismatches.push(format!("{path_or_root}: empty, expected at least one key"));
+            }
+
+            for (key, value) in dictionary {
+                self::check(value, item, &self::join(path, key), mismatches);
+            }
+        }
+        (shape, value) => mismatches.push(format!(
+            "{path_or_root}: expected {}, found {}",
+            self::expected(shape),
+            self::kind(value)
+        )),
+    }
+}
+
+/// Collects an indented summary of a value's structure, containing only keys and value types.
+/// Only the first item of each array is summarized.
+///
+/// # Arguments
+///
+/// * `value` - The value to summarize.
+/// * `label` - The value's key or index, prepended to its line.
+/// * `depth` - The current depth, used for indentation.
+/// * `lines` - Where to collect the summary's lines.
+fn summarize(value: &Value, label: &str, depth: usize, lines: &mut Vec<String>) {
+    let indent = "  ".repeat(depth);
+
+    let kind = match value {
+        Value::Array(items) => format!("array ({} items)", items.len()),
+        Value::Dictionary(dictionary) => format!("dictionary ({} keys)", dictionary.len()),
+        value => self::kind(value).to_owned(),
+    };
+
+    let is_nested = matches!(value, Value::Array(_) | Value::Dictionary(_));
+
+    if is_nested && depth >= MAX_DEPTH {
+        lines.push(format!("{indent}{label}{kind} (...)"));
+        return;
+    }
+
+    lines.push(format!("{indent}{label}{kind}"));
+
+    match value {
+        Value::Array(items) => {
+            if let Some(item) = items.first() {
+                self::summarize(item, "[0]: ", depth + 1, lines);
+            }
+        }
+        Value::Dictionary(dictionary) => {
+            let mut keys: Vec<&String> = dictionary.keys().collect();
+            keys.sort();
+
+            for key in keys {
+                self::summarize(&dictionary[key], &format!("{key}: "), depth + 1, lines);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Joins a key onto a path.
+fn join(path: &str, key: &str) -> String {
+    if path.is_empty() {
+        key.to_owned()
+    } else {
+        format!("{path}.{key}")
+    }
+}
+
+/// Returns the name of an expected shape.
+fn expected(shape: &Shape) -> &'static str {
+    match shape {
+        Shape::String => "string",
+        Shape::Integer => "integer",
+        Shape::Real => "real",
+        Shape::Array(_) => "array",
+        Shape::Dictionary(_) | Shape::Map(_) => "dictionary",
+    }
+}
+
+/// Returns the name of a value's type.
+fn kind(value: &Value) -> &'static str {
+    match value {
+        Value::Array(_) => "array",
+        Value::Dictionary(_) => "dictionary",
+        Value::Boolean(_) => "boolean",
+        Value::Data(_) => "data",
+        Value::Date(_) => "date",
+        Value::Real(_) => "real",
+        Value::Integer(_) => "integer",
+        Value::String(_) => "string",
+        _ => "unknown",
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    use std::path::PathBuf;
+
+    use plist::Dictionary;
+
+    use crate::defaults::test::MockPlists;
+    use crate::defaults::TEMP_OUTPUT_DIRECTORY;
+
+    fn write(name: &str, value: &Value) -> PathBuf {
+        let directory = TEMP_OUTPUT_DIRECTORY.join("tests-ios-diagnostics");
+        std::fs::create_dir_all(&directory).unwrap();
+
+        let path = directory.join(name);
+        value.to_file_xml(&path).unwrap();
+        path
+    }
+
+    #[test]
+    fn valid_plists() {
+        let directory = PathBuf::from(MockPlists::BooksAnnotated);
+
+        let books = report(&directory.join("Books.plist"), &BOOKS_PLIST).unwrap();
+        let annotations = report(
+            &directory.join("com.apple.ibooks-sync.plist"),
+            &ANNOTATIONS_PLIST,
+        )
+        .unwrap();
+
+        assert!(books.starts_with("no mismatched keys found"));
+        assert!(annotations.starts_with("no mismatched keys found"));
+    }
+
+    // Tests that mismatches are reported with their path and values are left out of the summary.
+    #[test]
+    fn mismatched_books() {
+        let mut book = Dictionary::new();
+        book.insert("Artist".into(), Value::Integer(1.into()));
+        book.insert("Name".into(), Value::String("Secret Title".into()));
+
+        let mut plist = Dictionary::new();
+        plist.insert("Books".into(), Value::Array(vec![Value::Dictionary(book)]));
+
+        let path = write("Books.plist", &Value::Dictionary(plist));
+        let report = report(&path, &BOOKS_PLIST).unwrap();
+
+        assert!(report.contains("Books[0].Artist: expected string, found integer"));
+        assert!(report.contains("Books[0].Package Hash: missing, expected string"));
+        assert!(report.contains("Name: string"));
+        assert!(!report.contains("Secret Title"));
+    }
+
+    #[test]
+    fn unreadable_plist() {
+        assert!(report(Path::new("missing.plist"), &BOOKS_PLIST).is_none());
+    }
+}
@@ -0,0 +1,131 @@
+//! Defines functions for locating Apple Books' plists inside an iOS back-up made by Finder or
+//! iTunes.
+//!
+//! These back-ups, stored under `~/Library/Application Support/MobileSync/Backup`, keep each file
+//! under a name derived from its original path. The mapping is stored in the back-up's
+//! `Manifest.db`:
+//!
+//! ```plaintext
+//! [backup]
+//!  │
+//!  ├── Manifest.db
+//!  ├── 3d
+//!  │   └── 3d0d7e5fb2ce288813306e4d4636395e047a3d28
+//!  └── ...
+//! ```
+//!
+//! Encrypted back-ups are unsupported as their `Manifest.db` is encrypted.
+
+use std::path::{Path, PathBuf};
+
+use rusqlite::{Connection, OpenFlags};
+
+use crate::result::{Error, Result};
+
+/// The name of the back-up's manifest database.
+pub const MANIFEST_DB: &str = "Manifest.db";
+
+/// The back-up domain containing Apple Books' plists.
+pub const BOOKS_DOMAIN: &str = "BooksDomain";
+
+/// Returns the path to a file within Apple Books' domain in an iOS back-up.
+///
+/// Files are matched by name, preferring the one closest to the root of the domain.
+///
+/// # Arguments
+///
+/// * `backup` - The path to the back-up.
+/// * `name` - The name of the file e.g. `Books.plist`.
+///
+/// # Errors
+///
+/// Will return `Err` if:
+/// * The manifest cannot be opened or queried e.g. the back-up is encrypted.
+/// * The file isn't listed in the manifest or is missing from the back-up.
+pub fn locate(backup: &Path, name: &str) -> Result<PathBuf> {
+    let manifest = backup.join(MANIFEST_DB);
+
+    let manifest_error = || Error::IOsBackupManifestError {
+        path: manifest.display().to_string(),
+    };
+
+    let missing_error = || Error::IOsBackupMissingFile {
+        name: name.to_owned(),
+        path: backup.display().to_string(),
+    };
+
+    if !manifest.is_file() {
+        return Err(manifest_error());
+    }
+
+    let connection = Connection::open_with_flags(&manifest, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .map_err(|_| manifest_error())?;
+
+    let mut statement = connection
+        .prepare(
+            "SELECT fileID FROM Files \
+             WHERE domain = ?1 AND (relativePath = ?2 OR relativePath LIKE '%/' || ?2) \
+             ORDER BY length(relativePath) \
+             LIMIT 1",
+        )
+        .map_err(|_| manifest_error())?;
+
+    let file_id: String = statement
+        .query_row([BOOKS_DOMAIN, name], |row| row.get(0))
+        .map_err(|error| match error {
+            rusqlite::Error::QueryReturnedNoRows => missing_error(),
+            _ => manifest_error(),
+        })?;
+
+    // Newer back-ups group files into subdirectories named after the first two characters of
+    // their id. Older back-ups keep them all at the root.
+    let paths = [
+        backup
+            .join(file_id.get(..2).unwrap_or_default())
+            .join(&file_id),
+        backup.join(&file_id),
+    ];
+
+    paths
+        .into_iter()
+        .find(|path| path.is_file())
+        .ok_or_else(missing_error)
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    use crate::defaults::CRATE_ROOT;
+
+    fn backup() -> PathBuf {
+        let mut path = CRATE_ROOT.to_owned();
+        path.extend(["data", "mobilesync", "books-annotated"].iter());
+        path
+    }
+
+    #[test]
+    fn locate_plists() {
+        let path = locate(&backup(), "Books.plist").unwrap();
+        assert!(path.starts_with(backup()));
+
+        assert!(locate(&backup(), "com.apple.ibooks-sync.plist").is_ok());
+    }
+
+    #[test]
+    fn locate_missing() {
+        assert!(matches!(
+            locate(&backup(), "missing.plist"),
+            Err(Error::IOsBackupMissingFile { .. })
+        ));
+    }
+
+    #[test]
+    fn locate_missing_manifest() {
+        assert!(matches!(
+            locate(&CRATE_ROOT.join("data"), "Books.plist"),
+            Err(Error::IOsBackupManifestError { .. })
+        ));
+    }
+}
@@ -1,16 +1,19 @@
 //! Defines types for interacting with iOS's Apple Books plists.
 
 pub mod defaults;
+pub mod device;
+pub mod diagnostics;
+pub mod mobilesync;
 pub mod models;
 
 use std::fs::File;
 use std::io::Write;
 use std::path::Path;
 
-use rusty_libimobiledevice::{idevice, services::afc::AfcFileMode};
-
 use crate::result::{Error, Result};
 
+use self::device::Device;
+use self::diagnostics::Shape;
 use self::models::{AnnotationRaw, AnnotationsPlist, BookRaw, BooksPlist};
 
 /// A struct for interacting with iOS's Apple Books plists.
@@ -48,12 +51,14 @@ impl ABIOs {
     {
         let path = path.join(ABPlist::Books.to_string());
 
-        let data: BooksPlist = match plist::from_file(path) {
+        let data: BooksPlist = match plist::from_file(&path) {
             Ok(data) => data,
             Err(error) => {
-                return Err(Error::IOsUnsupportedAppleBooksVersion {
-                    error: error.to_string(),
-                })
+                return Err(Self::unsupported_error(
+                    &path,
+                    &diagnostics::BOOKS_PLIST,
+                    &error,
+                ));
             }
         };
 
@@ -76,33 +81,60 @@ impl ABIOs {
     /// * The plist cannot be found/opened.
     /// * Any deserialization errors are encountered.
     /// * The version of Apple Books is unsupported.
-    #[allow(clippy::missing_panics_doc)]
+    /// * The plist doesn't contain any annotations data.
     pub fn extract_annotations<T>(path: &Path) -> Result<Vec<T>>
     where
         T: From<AnnotationRaw>,
     {
         let path = path.join(ABPlist::Annotations.to_string());
 
-        let data: AnnotationsPlist = match plist::from_file(path) {
+        let data: AnnotationsPlist = match plist::from_file(&path) {
             Ok(data) => data,
             Err(error) => {
-                return Err(Error::IOsUnsupportedAppleBooksVersion {
-                    error: error.to_string(),
-                })
+                return Err(Self::unsupported_error(
+                    &path,
+                    &diagnostics::ANNOTATIONS_PLIST,
+                    &error,
+                ));
             }
         };
 
-        // This should be safe as the structure of the incoming data is enforced by `serde`.
-        // Therefore guaranteeing that the unwrap is safe. `serde` would return an error in
-        // the previous block if the structure of the plist didn't match the model used for
-        // deserializing it.
-        let mut annotations = data.into_values().next().unwrap().bookmarks;
+        // `serde` enforces the structure of each value but the plist's top-level dictionary may
+        // still be empty e.g. if the file was truncated.
+        let Some(data) = data.into_values().next() else {
+            return Err(Error::IOsInvalidPlist {
+                path: path.display().to_string(),
+                error: "missing annotations data".to_owned(),
+            });
+        };
+
+        let mut annotations = data.bookmarks;
 
         // Filter out any deleted annotations.
         annotations.retain(|annotation| annotation.is_deleted == 0);
 
         Ok(annotations.into_iter().map(T::from).collect())
     }
+
+    /// Returns an [`Error::IOsUnsupportedAppleBooksVersion`] for a plist that failed to
+    /// deserialize.
+    ///
+    /// The error includes a report of the plist's mismatched keys and an anonymized summary of its
+    /// structure. See [`diagnostics`] for more information.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path to the plist.
+    /// * `shape` - The plist's expected shape.
+    /// * `error` - The deserialization error.
+    fn unsupported_error(path: &Path, shape: &Shape, error: &plist::Error) -> Error {
+        let error = match diagnostics::report(path, shape) {
+            Some(report) => format!("{error}\n{report}"),
+            None => error.to_string(),
+        };
+
+        Error::IOsUnsupportedAppleBooksVersion { error }
+    }
 }
 
 /// An enum representing iOS's Apple Books plists.
@@ -137,7 +169,7 @@ impl ABPlist {
             // TODO(feat): Implement UDID ------^^^^
         }
 
-        log::debug!("saved iOS plists to: {destination:?}");
+        tracing::debug!("saved iOS plists to: {destination:?}");
 
         Ok(())
     }
@@ -168,8 +200,43 @@ impl ABPlist {
         Ok(())
     }
 
+    /// Copies iOS's Apple Books plists from an iOS back-up made by Finder or iTunes to a
+    /// destination directory.
+    ///
+    /// See [`mobilesync`] for more information on how the back-up is structured.
+    ///
+    /// # Arguments
+    ///
+    /// * `backup` - The path to the back-up.
+    /// * `destination` - Where to copy the plists to.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if:
+    /// * Any IO errors are encountered.
+    /// * The back-up's manifest cannot be read e.g. the back-up is encrypted.
+    /// * Any of the plists are missing from the back-up.
+    pub fn save_from_backup(backup: &Path, destination: &Path) -> Result<()> {
+        std::fs::create_dir_all(destination)?;
+
+        for variant in &[Self::Books, Self::Annotations] {
+            let name = variant.to_string();
+
+            let item_source = mobilesync::locate(backup, &name)?;
+            let item_destination = destination.join(&name);
+
+            std::fs::copy(item_source, item_destination)?;
+        }
+
+        tracing::debug!("saved iOS plists from back-up: {}", backup.display());
+
+        Ok(())
+    }
+
     /// Copies iOS's Apple Books plists from an iOS device filesystem to a destination directory.
     ///
+    /// See [`device::with_device()`] for how the device is selected.
+    ///
     /// # Arguments
     ///
     /// * `destination` - Where to copy the plists to.
@@ -181,16 +248,22 @@ impl ABPlist {
     //
     // TODO(feat): Allow users to pass UDID from the CLI.
     fn save_from_device(destination: &Path, udid: Option<String>) -> Result<()> {
-        let device = if let Some(udid) = udid {
-            idevice::get_device(&udid).map_err(|_| Error::IOsDeviceNotFoundWithUdid { udid })?
-        } else {
-            idevice::get_first_device().map_err(|_| Error::IOsDeviceNotFound)?
-        };
-
-        let afc_client = device
-            .new_afc_client(crate::defaults::NAME)
-            .map_err(|error| Error::IOsDeviceReadError { error })?;
+        device::with_device(udid, |device| Self::save_from(device, destination))
+    }
 
+    /// Copies iOS's Apple Books plists from a [`Device`] to a destination directory.
+    ///
+    /// # Arguments
+    ///
+    /// * `device` - The device to copy the plists from.
+    /// * `destination` - Where to copy the plists to.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if:
+    /// * Any IO errors are encountered.
+    /// * There are any errors reading the device.
+    pub fn save_from(device: &mut dyn Device, destination: &Path) -> Result<()> {
         std::fs::create_dir_all(destination)?;
 
         for variant in &[Self::Books, Self::Annotations] {
@@ -199,39 +272,149 @@ impl ABPlist {
             let device_path = defaults::DATA_DIRECTORY.join(&name);
             let device_path = device_path.to_string_lossy().to_string();
 
-            let file_handle = afc_client
-                .file_open(&device_path, AfcFileMode::ReadOnly)
-                .map_err(|error| Error::IOsDeviceReadError { error })?;
+            let host_path = destination.join(&name);
 
-            let file_size = {
-                let file_info = afc_client
-                    .get_file_info(&device_path)
-                    .map_err(|error| Error::IOsDeviceReadError { error })?;
+            Self::copy_from(device, &device_path, &host_path)?;
+        }
 
-                let size = file_info.get("st_size").ok_or_else(|| Error::OtherError {
-                    error: "Unable to find 'st_size' field".to_owned(),
-                })?;
+        Ok(())
+    }
 
-                let size = size.parse::<u32>().map_err(|_| Error::OtherError {
-                    error: "Failed to parse file size".to_owned(),
-                })?;
+    /// Copies a file from a [`Device`] to the host filesystem.
+    ///
+    /// The file is read in chunks of [`defaults::READ_CHUNK_SIZE`] bytes and written as it's read
+    /// so large files are never held in memory. Failed reads are retried up to
+    /// [`defaults::READ_RETRIES`] times.
+    ///
+    /// # Arguments
+    ///
+    /// * `device` - The device to copy the file from.
+    /// * `device_path` - The path to the file on the device.
+    /// * `host_path` - Where to copy the file to.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if:
+    /// * Any IO errors are encountered.
+    /// * A read still fails after retrying.
+    /// * Fewer bytes are read than the device reported.
+    fn copy_from(device: &mut dyn Device, device_path: &str, host_path: &Path) -> Result<()> {
+        // The size is only used to report progress and check for truncated reads so the file is
+        // still copied if the device doesn't report it.
+        let file_size = device.file_size(device_path).unwrap_or_else(|error| {
+            tracing::warn!("failed to read the size of {device_path}: {error}");
+            None
+        });
+
+        let file_handle = device.file_open(device_path)?;
+
+        let result = Self::read_chunks(device, device_path, file_handle, file_size, host_path);
+
+        // The handle is closed even if reading failed.
+        device.file_close(file_handle)?;
+
+        let bytes_read = result?;
+
+        if let Some(file_size) = file_size {
+            if bytes_read < file_size {
+                return Err(Error::IOsDeviceReadError {
+                    error: format!(
+                        "{device_path}: expected {file_size} bytes but only read {bytes_read}"
+                    ),
+                });
+            }
+        }
+
+        tracing::info!("copied {bytes_read} bytes from {device_path}");
+
+        Ok(())
+    }
 
-                Ok::<u32, Error>(size)
+    /// Reads an open file from a [`Device`] in chunks, writing each to a file on the host
+    /// filesystem. Returns the number of bytes read.
+    ///
+    /// Progress is reported every [`defaults::READ_PROGRESS_INTERVAL`] bytes.
+    ///
+    /// # Arguments
+    ///
+    /// * `device` - The device to read the file from.
+    /// * `device_path` - The path to the file on the device, used to report progress.
+    /// * `file_handle` - The handle of the open file.
+    /// * `file_size` - The size of the file, if known, used to report progress.
+    /// * `host_path` - Where to write the file to.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if any IO errors are encountered or a read still fails after retrying.
+    fn read_chunks(
+        device: &mut dyn Device,
+        device_path: &str,
+        file_handle: u64,
+        file_size: Option<u64>,
+        host_path: &Path,
+    ) -> Result<u64> {
+        let mut file = File::create(host_path)?;
+        let mut bytes_read = 0;
+        let mut next_report = defaults::READ_PROGRESS_INTERVAL;
+
+        loop {
+            let chunk = Self::read_chunk(device, file_handle)?;
+
+            // An empty chunk marks the end of the file.
+            if chunk.is_empty() {
+                break;
             }
-            .unwrap_or(u32::MAX);
 
-            let file_contents = afc_client
-                .file_read(file_handle, file_size)
-                .map_err(|error| Error::IOsDeviceReadError { error })?;
+            file.write_all(&chunk)?;
+            bytes_read += chunk.len() as u64;
 
-            let host_path = destination.join(&name);
+            if bytes_read < next_report {
+                continue;
+            }
 
-            let mut file = File::create(&host_path)?;
+            next_report = bytes_read + defaults::READ_PROGRESS_INTERVAL;
 
-            file.write_all(&file_contents)?;
+            if let Some(file_size) = file_size {
+                tracing::info!("read {bytes_read}/{file_size} bytes from {device_path}");
+            } else {
+                tracing::info!("read {bytes_read} bytes from {device_path}");
+            }
         }
 
-        Ok(())
+        file.flush()?;
+
+        Ok(bytes_read)
+    }
+
+    /// Reads a single chunk from an open file on a [`Device`], retrying failed reads.
+    ///
+    /// # Arguments
+    ///
+    /// * `device` - The device to read the file from.
+    /// * `file_handle` - The handle of the open file.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the read still fails after [`defaults::READ_RETRIES`] retries.
+    fn read_chunk(device: &mut dyn Device, file_handle: u64) -> Result<Vec<u8>> {
+        let mut attempt = 0;
+
+        loop {
+            match device.file_read(file_handle, defaults::READ_CHUNK_SIZE) {
+                Ok(chunk) => return Ok(chunk),
+                Err(error) if attempt < defaults::READ_RETRIES => {
+                    attempt += 1;
+
+                    tracing::warn!(
+                        "failed to read from iOS device, retrying ({attempt}/{}): {error}",
+                        defaults::READ_RETRIES
+                    );
+
+                    std::thread::sleep(defaults::READ_RETRY_DELAY * attempt);
+                }
+                Err(error) => return Err(error),
+            }
+        }
     }
 }
 
@@ -243,3 +426,117 @@ impl std::fmt::Display for ABPlist {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    use std::path::PathBuf;
+
+    use crate::defaults::test::MockPlists;
+    use crate::defaults::TEMP_OUTPUT_DIRECTORY;
+
+    use self::device::MockDevice;
+
+    /// Returns a mock device with the plists under `[root]/Books`, as on an iOS device.
+    fn mock_device(name: &str) -> MockDevice {
+        let root = TEMP_OUTPUT_DIRECTORY.join("tests-ios-device").join(name);
+        let books = root.join(&*defaults::DATA_DIRECTORY);
+
+        std::fs::create_dir_all(&books).unwrap();
+        ABPlist::save_from_disk(&PathBuf::from(MockPlists::BooksAnnotated), &books).unwrap();
+
+        MockDevice::new(&root)
+    }
+
+    #[test]
+    fn save_from_mobilesync_backup() {
+        let mut backup = crate::defaults::CRATE_ROOT.to_owned();
+        backup.extend(["data", "mobilesync", "books-annotated"].iter());
+
+        let destination = TEMP_OUTPUT_DIRECTORY
+            .join("tests-ios-device")
+            .join("mobilesync-output");
+
+        ABPlist::save_from_backup(&backup, &destination).unwrap();
+
+        let books: Vec<BookRaw> = ABIOs::extract_books(&destination).unwrap();
+        let annotations: Vec<AnnotationRaw> = ABIOs::extract_annotations(&destination).unwrap();
+
+        assert_eq!(books.len(), 3);
+        assert_eq!(annotations.len(), 7);
+    }
+
+    // Tests that a plist with a mismatched key reports the key's path.
+    #[test]
+    fn extract_books_mismatched() {
+        let directory = TEMP_OUTPUT_DIRECTORY.join("tests-ios-mismatched");
+        std::fs::create_dir_all(&directory).unwrap();
+
+        let mut plist = plist::Dictionary::new();
+        plist.insert("Books".into(), plist::Value::Integer(1.into()));
+        plist::Value::Dictionary(plist)
+            .to_file_xml(directory.join(ABPlist::Books.to_string()))
+            .unwrap();
+
+        let Err(Error::IOsUnsupportedAppleBooksVersion { error }) =
+            ABIOs::extract_books::<BookRaw>(&directory)
+        else {
+            panic!("expected an unsupported version error");
+        };
+
+        assert!(error.contains("Books: expected array, found integer"));
+    }
+
+    // Tests that an annotations plist without any data returns an error instead of panicking.
+    #[test]
+    fn extract_annotations_empty() {
+        let directory = TEMP_OUTPUT_DIRECTORY.join("tests-ios-empty");
+        std::fs::create_dir_all(&directory).unwrap();
+
+        plist::Value::Dictionary(plist::Dictionary::new())
+            .to_file_xml(directory.join(ABPlist::Annotations.to_string()))
+            .unwrap();
+
+        assert!(matches!(
+            ABIOs::extract_annotations::<AnnotationRaw>(&directory),
+            Err(Error::IOsInvalidPlist { .. })
+        ));
+    }
+
+    // Tests that files are fully copied when the device returns fewer bytes than requested and
+    // some reads fail.
+    #[test]
+    fn save_from_partial_reads() {
+        let mut device = mock_device("partial-reads").read_size(100).read_failures(2);
+        let destination = TEMP_OUTPUT_DIRECTORY
+            .join("tests-ios-device")
+            .join("partial-reads-output");
+
+        ABPlist::save_from(&mut device, &destination).unwrap();
+
+        for variant in [ABPlist::Books, ABPlist::Annotations] {
+            let name = variant.to_string();
+
+            assert_eq!(
+                std::fs::read(destination.join(&name)).unwrap(),
+                std::fs::read(PathBuf::from(MockPlists::BooksAnnotated).join(&name)).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn save_from_too_many_failures() {
+        let mut device =
+            mock_device("too-many-failures").read_failures(defaults::READ_RETRIES as usize + 1);
+        let destination = TEMP_OUTPUT_DIRECTORY
+            .join("tests-ios-device")
+            .join("too-many-failures-output");
+
+        assert!(matches!(
+            ABPlist::save_from(&mut device, &destination),
+            Err(Error::IOsDeviceReadError { .. })
+        ));
+    }
+}
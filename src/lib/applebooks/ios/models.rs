@@ -104,6 +104,10 @@ pub struct AnnotationRaw {
     #[allow(missing_docs)]
     pub body: String,
 
+    #[serde(alias = "annotationRepresentativeText")]
+    #[allow(missing_docs)]
+    pub context_text: Option<String>,
+
     #[serde(alias = "annotationStyle")]
     #[allow(missing_docs)]
     pub style: usize,
@@ -0,0 +1,122 @@
+//! Defines functions for finding macOS's Apple Books containers.
+//!
+//! A container is a directory holding a set of Apple Books databases. There's usually only one,
+//! [`DATA_DIRECTORY`], but other user accounts on the same Mac each have their own and libraries
+//! migrated from another Mac may be found in other user directories.
+
+use std::path::{Path, PathBuf};
+
+use super::defaults::DATA_DIRECTORY;
+use super::ABDatabase;
+
+/// The path to a container relative to a user's home directory.
+const CONTAINER_PATH: [&str; 5] = [
+    "Library",
+    "Containers",
+    "com.apple.iBooksX",
+    "Data",
+    "Documents",
+];
+
+/// Returns all containers found on disk. The current user's container is always first, followed by
+/// the containers of the other users, sorted by path.
+///
+/// Containers that cannot be read e.g. due to permissions are skipped.
+#[must_use]
+pub fn find() -> Vec<PathBuf> {
+    let mut containers: Vec<PathBuf> = Vec::new();
+
+    if is_container(&DATA_DIRECTORY) {
+        containers.push(DATA_DIRECTORY.to_owned());
+    }
+
+    // -> /Users
+    if let Some(homes) = crate::defaults::HOME_DIRECTORY.parent() {
+        for container in find_in(homes) {
+            if !containers.contains(&container) {
+                containers.push(container);
+            }
+        }
+    }
+
+    containers
+}
+
+/// Returns the containers of every user directory in a directory, sorted by path.
+///
+/// # Arguments
+///
+/// * `homes` - The directory containing the users' home directories e.g. `/Users`.
+#[must_use]
+pub fn find_in(homes: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(homes) else {
+        return Vec::new();
+    };
+
+    let mut containers: Vec<PathBuf> = entries
+        .filter_map(std::result::Result::ok)
+        .map(|entry| {
+            let mut path = entry.path();
+            path.extend(CONTAINER_PATH.iter());
+            path
+        })
+        .filter(|path| is_container(path))
+        .collect();
+
+    containers.sort();
+    containers
+}
+
+/// Returns whether a directory contains both of macOS's Apple Books database directories.
+///
+/// # Arguments
+///
+/// * `path` - The directory to check.
+#[must_use]
+pub fn is_container(path: &Path) -> bool {
+    [ABDatabase::Books, ABDatabase::Annotations]
+        .iter()
+        .all(|database| path.join(database.to_string()).is_dir())
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    use crate::defaults::test::MockDatabases;
+    use crate::defaults::TEMP_OUTPUT_DIRECTORY;
+
+    #[test]
+    fn is_container_mock() {
+        assert!(is_container(&PathBuf::from(MockDatabases::BooksAnnotated)));
+        assert!(!is_container(&TEMP_OUTPUT_DIRECTORY.join("missing")));
+    }
+
+    // Tests that only user directories with a container are returned, sorted by path.
+    #[test]
+    fn find_in_homes() {
+        let homes = TEMP_OUTPUT_DIRECTORY.join("tests-containers");
+
+        if homes.exists() {
+            std::fs::remove_dir_all(&homes).unwrap();
+        }
+
+        for user in ["b", "a"] {
+            let mut path = homes.join(user);
+            path.extend(CONTAINER_PATH.iter());
+
+            for database in [ABDatabase::Books, ABDatabase::Annotations] {
+                std::fs::create_dir_all(path.join(database.to_string())).unwrap();
+            }
+        }
+
+        std::fs::create_dir_all(homes.join("c")).unwrap();
+
+        let containers = find_in(&homes);
+
+        assert_eq!(containers.len(), 2);
+        assert!(containers[0].starts_with(homes.join("a")));
+        assert!(containers[1].starts_with(homes.join("b")));
+    }
+}
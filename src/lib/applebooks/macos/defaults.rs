@@ -29,6 +29,29 @@ pub static DATA_DIRECTORY: Lazy<PathBuf> = Lazy::new(|| {
     path
 });
 
+/// The directory containing the books' unzipped EPUBs.
+///
+/// The full path:
+/// ```plaintext
+/// ~/Library/Containers/com.apple.BKAgentService/Data/Documents/iBooks/Books
+/// ```
+pub static BOOKS_DIRECTORY: Lazy<PathBuf> = Lazy::new(|| {
+    let mut path = crate::defaults::HOME_DIRECTORY.to_owned();
+    path.extend(
+        [
+            "Library",
+            "Containers",
+            "com.apple.BKAgentService",
+            "Data",
+            "Documents",
+            "iBooks",
+            "Books",
+        ]
+        .iter(),
+    );
+    path
+});
+
 /// A set of all the variations of the Apple Books application name.
 pub static APPLEBOOKS_NAMES: Lazy<HashSet<String>> = Lazy::new(|| {
     ["Books", "iBooks", "Apple Books", "AppleBooks"]
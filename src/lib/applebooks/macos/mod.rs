@@ -4,7 +4,9 @@
 //! the [`ABQuery`] trait provides an interface for ! generating types from either of the Apple Books
 //! databases.
 
+pub mod containers;
 pub mod defaults;
+pub mod permissions;
 pub mod utils;
 
 use std::path::{Path, PathBuf};
@@ -90,7 +92,6 @@ impl ABMacOs {
     /// Will return `Err` if:
     /// * The database cannot be found/opened
     /// * The version of Apple Books is unsupported.
-    #[allow(clippy::missing_panics_doc)]
     fn query<T>(path: &Path, database: ABDatabase) -> Result<Vec<T>>
     where
         T: ABQuery,
@@ -100,6 +101,12 @@ impl ABMacOs {
 
         let Ok(connection) = Connection::open_with_flags(&path, OpenFlags::SQLITE_OPEN_READ_ONLY)
         else {
+            // SQLite only reports that it can't open the database so the file is opened directly
+            // to check whether it's due to missing permissions.
+            if let Err(error) = std::fs::File::open(&path) {
+                return Err(self::permissions::map_io_error(error, &path));
+            }
+
             return Err(Error::MacOsDatabaseConnectionError {
                 name: database.to_string(),
                 path: path.display().to_string(),
@@ -119,11 +126,19 @@ impl ABMacOs {
             }
         };
 
-        let items = statement
-            .query_map([], |row| Ok(T::from_row(row)))
-            // The `rusqlite` documentation for `query_map` states 'Will return Err if binding
-            // parameters fails.' So this should be safe because `query_map` is given no parameters.
-            .unwrap()
+        // The `rusqlite` documentation for `query_map` states 'Will return Err if binding
+        // parameters fails.' As no parameters are given, any error is treated like a schema change.
+        let rows = match statement.query_map([], |row| Ok(T::from_row(row))) {
+            Ok(rows) => rows,
+            Err(error) => {
+                return Err(Error::MacOsUnsupportedAppleBooksVersion {
+                    error: error.to_string(),
+                    version: APPLEBOOKS_VERSION.to_owned(),
+                });
+            }
+        };
+
+        let items = rows
             // Using `filter_map` here because we know from a few lines above that all the items
             // are wrapped in an `Ok`. At this point the there should be nothing that would fail
             // in regards to querying and creating an instance of T unless there's an error in the
@@ -142,29 +157,45 @@ impl ABMacOs {
     /// * `database` - Which database path to get.
     ///
     /// See [`ABMacOs`] for more information on how the databases directory should be structured.
-    fn get_database(path: &Path, database: ABDatabase) -> Result<PathBuf> {
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if there isn't exactly one matching database.
+    pub(crate) fn get_database(path: &Path, database: ABDatabase) -> Result<PathBuf> {
         // (a) -> `/path/to/databases/DATABASE_NAME/`
         let path = path.join(database.to_string());
 
+        let missing = || Error::MacOsMissingDatabase {
+            name: database.to_string(),
+            path: path.display().to_string(),
+        };
+
         // (b) -> `/path/to/databases/DATABASE_NAME/DATABASE_NAME*.sqlite`
-        let pattern = format!("{database}*.sqlite");
-        let pattern = path.join(pattern);
-        let pattern = pattern.to_string_lossy();
+        //
+        // The directory is escaped as user-supplied paths may contain glob characters e.g. `[`.
+        // Paths that aren't valid UTF-8 can't be matched against.
+        let directory = path.to_str().ok_or_else(missing)?;
+        let pattern = format!(
+            "{}/{database}*.sqlite",
+            glob::Pattern::escape(directory.trim_end_matches('/'))
+        );
 
         let mut databases: Vec<PathBuf> = glob::glob(&pattern)
-            // This should be safe to unwrap seeing we know the pattern is valid and in production
-            // the path (b) will always be valid UTF-8 as it's a path to a default macOS
-            // application's container.
-            .unwrap()
+            .map_err(|_| missing())?
             .filter_map(std::result::Result::ok)
             .collect();
 
         // macOS's default Apple Books database directory contains only a single database file that
         // starts with the `DATABASE_NAME` and ends with `.sqlite`. If there are more then we'd
         // possibly run into unexpected behaviors.
-        match &databases[..] {
-            [_] => Ok(databases.pop().unwrap()),
-            _ => Err(Error::MacOsMissingDefaultDatabase),
+        match databases.len() {
+            0 => Err(missing()),
+            1 => Ok(databases.remove(0)),
+            count => Err(Error::MacOsAmbiguousDatabase {
+                name: database.to_string(),
+                path: path.display().to_string(),
+                count,
+            }),
         }
     }
 }
@@ -217,7 +248,9 @@ impl ABDatabase {
     ///
     /// # Errors
     ///
-    /// Will return `Err` if any IO errors are encountered.
+    /// Will return `Err` if:
+    /// * The source cannot be read due to missing permissions. See [`permissions`].
+    /// * Any other IO errors are encountered.
     pub fn save_to(destination: &Path, source: Option<&Path>) -> Result<()> {
         let source = source.unwrap_or(&*self::defaults::DATA_DIRECTORY);
 
@@ -230,10 +263,11 @@ impl ABDatabase {
             // -> [output-directory]/[name]
             let item_destination = destination.join(&name);
 
-            crate::utils::copy_dir(item_source, item_destination)?;
+            crate::utils::copy_dir(&item_source, item_destination)
+                .map_err(|error| self::permissions::map_io_error(error, &item_source))?;
         }
 
-        log::debug!("saved macOS databases to: {destination:?}");
+        tracing::debug!("saved macOS databases to: {destination:?}");
 
         Ok(())
     }
@@ -247,3 +281,43 @@ impl std::fmt::Display for ABDatabase {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    use crate::defaults::TEMP_OUTPUT_DIRECTORY;
+
+    // Tests that databases are found in directories containing glob characters and that missing
+    // or duplicate databases are reported.
+    #[test]
+    fn get_database() {
+        let directory = TEMP_OUTPUT_DIRECTORY.join("tests-macos-[databases]");
+        let books = directory.join(ABDatabase::Books.to_string());
+
+        if directory.exists() {
+            std::fs::remove_dir_all(&directory).unwrap();
+        }
+
+        std::fs::create_dir_all(&books).unwrap();
+        std::fs::write(books.join("BKLibrary-1.sqlite"), "").unwrap();
+
+        assert_eq!(
+            ABMacOs::get_database(&directory, ABDatabase::Books).unwrap(),
+            books.join("BKLibrary-1.sqlite")
+        );
+
+        assert!(matches!(
+            ABMacOs::get_database(&directory, ABDatabase::Annotations),
+            Err(Error::MacOsMissingDatabase { .. })
+        ));
+
+        std::fs::write(books.join("BKLibrary-2.sqlite"), "").unwrap();
+
+        assert!(matches!(
+            ABMacOs::get_database(&directory, ABDatabase::Books),
+            Err(Error::MacOsAmbiguousDatabase { count: 2, .. })
+        ));
+    }
+}
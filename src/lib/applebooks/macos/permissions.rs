@@ -0,0 +1,122 @@
+//! Defines checks for whether macOS's Apple Books data can be read.
+//!
+//! Apple Books keeps its databases in a sandboxed container. macOS's privacy protections (TCC)
+//! block other apps from reading it unless the app running `readstor`, usually the terminal, has
+//! been granted Full Disk Access. The resulting IO errors are easily mistaken for missing files so
+//! they're detected here and returned as [`Error::MacOsPermissionDenied`].
+
+use std::fs::File;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::result::{Error, Result};
+
+use super::{ABDatabase, ABMacOs};
+
+/// Checks that a directory containing macOS's Apple Books databases can be read.
+///
+/// # Arguments
+///
+/// * `path` - The path to a directory containing macOS's Apple Books databases.
+///
+/// # Errors
+///
+/// Will return `Err` if:
+/// * The directory or its database directories cannot be read due to missing permissions.
+/// * The directory cannot be read due to any other IO errors.
+pub fn check_directory(path: &Path) -> Result<()> {
+    std::fs::read_dir(path).map_err(|error| self::map_io_error(error, path))?;
+
+    // Missing databases are reported by `check_database()`.
+    for database in [ABDatabase::Books, ABDatabase::Annotations] {
+        let path = path.join(database.to_string());
+
+        match std::fs::read_dir(&path) {
+            Err(error) if error.kind() == io::ErrorKind::PermissionDenied => {
+                return Err(self::map_io_error(error, &path));
+            }
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+/// Checks that one of macOS's Apple Books databases can be found and read. Returns the path to the
+/// database.
+///
+/// # Arguments
+///
+/// * `path` - The path to a directory containing macOS's Apple Books databases.
+/// * `database` - Which database to check.
+///
+/// # Errors
+///
+/// Will return `Err` if:
+/// * The database cannot be found.
+/// * The database cannot be read due to missing permissions.
+/// * Any other IO errors are encountered.
+pub fn check_database(path: &Path, database: ABDatabase) -> Result<PathBuf> {
+    let path = ABMacOs::get_database(path, database)?;
+
+    File::open(&path).map_err(|error| self::map_io_error(error, &path))?;
+
+    Ok(path)
+}
+
+/// Converts an IO error into an [`Error`], replacing permission errors with
+/// [`Error::MacOsPermissionDenied`].
+///
+/// macOS returns `EPERM` when TCC blocks access and `EACCES` for regular file permissions. Both
+/// are mapped to [`io::ErrorKind::PermissionDenied`].
+///
+/// # Arguments
+///
+/// * `error` - The IO error.
+/// * `path` - The path that was being accessed.
+pub(crate) fn map_io_error(error: io::Error, path: &Path) -> Error {
+    if error.kind() == io::ErrorKind::PermissionDenied {
+        Error::MacOsPermissionDenied {
+            path: path.display().to_string(),
+        }
+    } else {
+        Error::IoError(error)
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    use crate::defaults::test::MockDatabases;
+
+    // Tests that `EPERM` and `EACCES` are reported as permission errors and others are kept as is.
+    #[test]
+    fn map_permission_errors() {
+        let path = Path::new("/Users/user/Library/Containers/com.apple.iBooksX");
+
+        // -> EPERM, EACCES
+        for code in [1, 13] {
+            assert!(matches!(
+                map_io_error(io::Error::from_raw_os_error(code), path),
+                Error::MacOsPermissionDenied { .. }
+            ));
+        }
+
+        assert!(matches!(
+            map_io_error(io::Error::from(io::ErrorKind::NotFound), path),
+            Error::IoError(_)
+        ));
+    }
+
+    // Tests that a readable directory and its databases pass the checks.
+    #[test]
+    fn readable_databases() {
+        let path = PathBuf::from(MockDatabases::BooksAnnotated);
+
+        check_directory(&path).unwrap();
+        check_database(&path, ABDatabase::Books).unwrap();
+        check_database(&path, ABDatabase::Annotations).unwrap();
+    }
+}
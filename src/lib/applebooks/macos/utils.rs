@@ -25,7 +25,7 @@ pub static APPLEBOOKS_VERSION: Lazy<String> = Lazy::new(|| {
 
     let Ok(value) = Value::from_file(path) else {
         // This can happen if the user is on a non-macOS device.
-        log::warn!("could not determine Apple Books version");
+        tracing::warn!("could not determine Apple Books version");
         return "v?".to_owned();
     };
 
@@ -35,7 +35,7 @@ pub static APPLEBOOKS_VERSION: Lazy<String> = Lazy::new(|| {
         .and_then(|d| d.get("CFBundleShortVersionString"))
         .and_then(plist::Value::as_string)
         .unwrap_or_else(|| {
-            log::warn!("could not determine 'CFBundleShortVersionString'");
+            tracing::warn!("could not determine 'CFBundleShortVersionString'");
             "?"
         });
 
@@ -45,7 +45,7 @@ pub static APPLEBOOKS_VERSION: Lazy<String> = Lazy::new(|| {
         .and_then(|d| d.get("CFBundleVersion"))
         .and_then(plist::Value::as_string)
         .unwrap_or_else(|| {
-            log::warn!("could not determine 'CFBundleVersion'");
+            tracing::warn!("could not determine 'CFBundleVersion'");
             "?"
         });
 
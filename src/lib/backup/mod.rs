@@ -1,6 +1,6 @@
 //! Defines types for backing-up macOS's Apple Books databases.
 
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 use chrono::{DateTime, Local};
 use serde::Serialize;
@@ -64,12 +64,14 @@ pub const DIRECTORY_TEMPLATE: &str = "{{ now |  date(format='%Y-%m-%d-%H%M%S')}}
 /// * `destination` - Where to place the backup.
 /// * `options` - The back-up options.
 ///
+/// Returns the path to the newly created back-up directory.
+///
 /// # Errors
 ///
 /// Will return `Err` if any IO errors are encountered.
 ///
 /// [abmacos]: crate::applebooks::macos::ABMacOs
-pub fn run<O>(platform: Platform, source: &Path, destination: &Path, options: O) -> Result<()>
+pub fn run<O>(platform: Platform, source: &Path, destination: &Path, options: O) -> Result<PathBuf>
 where
     O: Into<BackupOptions>,
 {
@@ -100,7 +102,7 @@ where
         Platform::IOs => ABPlist::save_to(&destination, Some(source))?,
     }
 
-    Ok(())
+    Ok(destination)
 }
 
 /// Validates a template by rendering it.
@@ -0,0 +1,487 @@
+//! Defines types for reading book metadata from a Calibre library.
+//!
+//! Calibre stores its metadata in a `SQLite` database at `[library]/metadata.db`. Books are matched
+//! to the books in the library by their ISBN, if they have one, and otherwise by their title and
+//! author. Matched books are enriched with their series, publisher, identifiers and the values of
+//! the library's custom columns. See [`CalibreMetadata`].
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use rusqlite::{Connection, OpenFlags, Row};
+
+use crate::models::book::{Book, CalibreMetadata};
+use crate::models::entry::Entries;
+use crate::result::{Error, Result};
+use crate::strings;
+
+/// The name of the database within a Calibre library.
+pub const CALIBRE_DATABASE: &str = "metadata.db";
+
+/// The separator used to join the values of custom columns with multiple values.
+const MULTIPLE_SEPARATOR: &str = ", ";
+
+/// A struct representing the books within a Calibre library.
+#[derive(Debug, Default, Clone)]
+pub struct CalibreLibrary {
+    /// The library's books sorted by their id.
+    books: Vec<CalibreBook>,
+}
+
+/// A struct representing a single book within a Calibre library along with what it's matched on.
+#[derive(Debug, Default, Clone)]
+struct CalibreBook {
+    /// The book's slugified title.
+    title: String,
+
+    /// The book's slugified authors, in the order they're listed in Calibre.
+    authors: Vec<String>,
+
+    /// The book's normalized ISBNs. See [`strings::to_isbn()`].
+    isbns: Vec<String>,
+
+    /// The metadata to enrich a matched book with.
+    metadata: CalibreMetadata,
+}
+
+impl CalibreLibrary {
+    /// Reads a Calibre library.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path to the Calibre library, i.e. the directory containing `metadata.db`.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the database is missing, cannot be opened or its schema isn't
+    /// supported.
+    pub fn open(path: &Path) -> Result<Self> {
+        let database = path.join(CALIBRE_DATABASE);
+
+        let error = |error: rusqlite::Error| Error::CalibreLibraryError {
+            path: database.display().to_string(),
+            error: error.to_string(),
+        };
+
+        if !database.is_file() {
+            return Err(Error::CalibreLibraryError {
+                path: database.display().to_string(),
+                error: "missing database".to_owned(),
+            });
+        }
+
+        let connection = Connection::open_with_flags(&database, OpenFlags::SQLITE_OPEN_READ_ONLY)
+            .map_err(error)?;
+
+        Self::read(&connection).map_err(error)
+    }
+
+    /// Returns the metadata of the book in the library matching a [`Book`]. Returns `None` if
+    /// there's no match.
+    ///
+    /// Books are matched by their ISBN first. Otherwise, the title must match and the author must
+    /// match either one of the Calibre book's authors or all of them combined. Titles and authors
+    /// are compared as slugs so differences in case and punctuation are ignored.
+    ///
+    /// # Arguments
+    ///
+    /// * `book` - The book to find.
+    #[must_use]
+    pub fn find(&self, book: &Book) -> Option<&CalibreMetadata> {
+        if let Some(isbn) = &book.metadata.isbn {
+            let found = self.books.iter().find(|other| other.isbns.contains(isbn));
+
+            if let Some(found) = found {
+                return Some(&found.metadata);
+            }
+        }
+
+        let title = strings::to_slug(&book.title, true);
+        let author = strings::to_slug(&book.author, true);
+
+        self.books
+            .iter()
+            .find(|other| {
+                other.title == title
+                    && (other.authors.contains(&author) || other.authors.join("-") == author)
+            })
+            .map(|other| &other.metadata)
+    }
+
+    /// Returns the number of books in the library.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.books.len()
+    }
+
+    /// Returns `true` if the library has no books.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.books.is_empty()
+    }
+
+    /// Reads all the books in a Calibre library's database.
+    ///
+    /// # Arguments
+    ///
+    /// * `connection` - The connection to the database.
+    fn read(connection: &Connection) -> rusqlite::Result<Self> {
+        let mut books: BTreeMap<i64, CalibreBook> = BTreeMap::new();
+
+        for (id, title, isbn, series_index) in Self::query(
+            connection,
+            "SELECT id, title, isbn, series_index FROM books",
+            |row| {
+                Ok((
+                    row.get(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, Option<String>>(2)?,
+                    row.get(3)?,
+                ))
+            },
+        )? {
+            books.insert(
+                id,
+                CalibreBook {
+                    title: strings::to_slug(&title, true),
+                    isbns: isbn
+                        .as_deref()
+                        .and_then(strings::to_isbn)
+                        .into_iter()
+                        .collect(),
+                    metadata: CalibreMetadata {
+                        id,
+                        series_index,
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                },
+            );
+        }
+
+        for (id, name) in Self::query_pairs(
+            connection,
+            "SELECT link.book, authors.name FROM books_authors_link AS link
+             JOIN authors ON authors.id = link.author
+             ORDER BY link.id",
+        )? {
+            if let Some(book) = books.get_mut(&id) {
+                book.authors.push(strings::to_slug(&name, true));
+            }
+        }
+
+        for (id, name) in Self::query_pairs(
+            connection,
+            "SELECT link.book, series.name FROM books_series_link AS link
+             JOIN series ON series.id = link.series",
+        )? {
+            if let Some(book) = books.get_mut(&id) {
+                book.metadata.series = Some(name);
+            }
+        }
+
+        for (id, name) in Self::query_pairs(
+            connection,
+            "SELECT link.book, publishers.name FROM books_publishers_link AS link
+             JOIN publishers ON publishers.id = link.publisher",
+        )? {
+            if let Some(book) = books.get_mut(&id) {
+                book.metadata.publisher = Some(name);
+            }
+        }
+
+        for (id, kind, value) in Self::query(
+            connection,
+            "SELECT book, type, val FROM identifiers",
+            |row| {
+                Ok((
+                    row.get(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                ))
+            },
+        )? {
+            if let Some(book) = books.get_mut(&id) {
+                if kind == "isbn" {
+                    book.isbns.extend(strings::to_isbn(&value));
+                }
+
+                book.metadata.identifiers.insert(kind, value);
+            }
+        }
+
+        for (label, id, value) in Self::query_custom_columns(connection)? {
+            if let Some(book) = books.get_mut(&id) {
+                book.metadata
+                    .custom
+                    .entry(label)
+                    .and_modify(|values| {
+                        values.push_str(MULTIPLE_SEPARATOR);
+                        values.push_str(&value);
+                    })
+                    .or_insert(value);
+            }
+        }
+
+        // Calibre stores a missing series index as `1.0`.
+        for book in books.values_mut() {
+            if book.metadata.series.is_none() {
+                book.metadata.series_index = None;
+            }
+        }
+
+        Ok(Self {
+            books: books.into_values().collect(),
+        })
+    }
+
+    /// Returns the values of all custom columns as `(label, book-id, value)`. Composite columns
+    /// are skipped as their values are computed by Calibre and aren't stored.
+    ///
+    /// # Arguments
+    ///
+    /// * `connection` - The connection to the database.
+    fn query_custom_columns(
+        connection: &Connection,
+    ) -> rusqlite::Result<Vec<(String, i64, String)>> {
+        let columns: Vec<(i64, String, bool)> = Self::query(
+            connection,
+            "SELECT id, label, normalized FROM custom_columns
+             WHERE mark_for_delete = 0 AND datatype != 'composite'
+             ORDER BY id",
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )?;
+
+        let mut values = Vec::new();
+
+        for (id, label, is_normalized) in columns {
+            // Normalized columns store each distinct value once and link them to books.
+            let query = if is_normalized {
+                format!(
+                    "SELECT link.book, CAST(col.value AS TEXT) FROM books_custom_column_{id}_link AS link
+                     JOIN custom_column_{id} AS col ON col.id = link.value
+                     ORDER BY link.id"
+                )
+            } else {
+                format!("SELECT book, CAST(value AS TEXT) FROM custom_column_{id}")
+            };
+
+            values.extend(
+                Self::query_pairs(connection, &query)?
+                    .into_iter()
+                    .map(|(book, value)| (label.clone(), book, value)),
+            );
+        }
+
+        Ok(values)
+    }
+
+    /// Runs a query returning pairs of book ids and text values.
+    ///
+    /// # Arguments
+    ///
+    /// * `connection` - The connection to the database.
+    /// * `query` - The query to run.
+    fn query_pairs(connection: &Connection, query: &str) -> rusqlite::Result<Vec<(i64, String)>> {
+        Self::query(connection, query, |row| Ok((row.get(0)?, row.get(1)?)))
+    }
+
+    /// Runs a query and converts each row with a function.
+    ///
+    /// # Arguments
+    ///
+    /// * `connection` - The connection to the database.
+    /// * `query` - The query to run.
+    /// * `f` - The function converting each row.
+    fn query<T, F>(connection: &Connection, query: &str, f: F) -> rusqlite::Result<Vec<T>>
+    where
+        F: FnMut(&Row<'_>) -> rusqlite::Result<T>,
+    {
+        connection.prepare(query)?.query_map([], f)?.collect()
+    }
+}
+
+/// Enriches each [`Entry`][entry]'s book with its metadata from a Calibre library. Returns the
+/// number of books that were found in the library. See [`CalibreLibrary::find()`].
+///
+/// # Arguments
+///
+/// * `entries` - The entries to enrich.
+/// * `path` - The path to the Calibre library.
+///
+/// # Errors
+///
+/// Will return `Err` if the library cannot be read. See [`CalibreLibrary::open()`].
+///
+/// [entry]: crate::models::entry::Entry
+pub fn run(entries: &mut Entries, path: &Path) -> Result<usize> {
+    let library = CalibreLibrary::open(path)?;
+
+    let mut matched = 0;
+
+    for entry in entries.values_mut() {
+        entry.book.calibre = library.find(&entry.book).cloned();
+
+        if entry.book.calibre.is_some() {
+            matched += 1;
+        }
+    }
+
+    Ok(matched)
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    use crate::defaults::TEMP_OUTPUT_DIRECTORY;
+    use crate::models::book::BookMetadata;
+    use crate::models::entry::Entry;
+
+    fn library(name: &str) -> std::path::PathBuf {
+        let directory = TEMP_OUTPUT_DIRECTORY.join("tests-calibre").join(name);
+        std::fs::create_dir_all(&directory).unwrap();
+
+        let database = directory.join(CALIBRE_DATABASE);
+        let _ = std::fs::remove_file(&database);
+
+        let connection = Connection::open(&database).unwrap();
+
+        connection
+            .execute_batch(
+                "CREATE TABLE books (id INTEGER PRIMARY KEY, title TEXT, isbn TEXT,
+                    series_index REAL);
+                CREATE TABLE authors (id INTEGER PRIMARY KEY, name TEXT);
+                CREATE TABLE books_authors_link (id INTEGER PRIMARY KEY, book INTEGER,
+                    author INTEGER);
+                CREATE TABLE series (id INTEGER PRIMARY KEY, name TEXT);
+                CREATE TABLE books_series_link (id INTEGER PRIMARY KEY, book INTEGER,
+                    series INTEGER);
+                CREATE TABLE publishers (id INTEGER PRIMARY KEY, name TEXT);
+                CREATE TABLE books_publishers_link (id INTEGER PRIMARY KEY, book INTEGER,
+                    publisher INTEGER);
+                CREATE TABLE identifiers (id INTEGER PRIMARY KEY, book INTEGER, type TEXT,
+                    val TEXT);
+                CREATE TABLE custom_columns (id INTEGER PRIMARY KEY, label TEXT, datatype TEXT,
+                    mark_for_delete BOOL, normalized BOOL);
+                CREATE TABLE custom_column_1 (id INTEGER PRIMARY KEY, book INTEGER, value REAL);
+                CREATE TABLE custom_column_2 (id INTEGER PRIMARY KEY, value TEXT);
+                CREATE TABLE books_custom_column_2_link (id INTEGER PRIMARY KEY, book INTEGER,
+                    value INTEGER);
+
+                INSERT INTO books VALUES
+                    (1, 'The Art Spirit', '', 1.0),
+                    (2, 'Think on These Things', '', 3.0);
+                INSERT INTO authors VALUES (1, 'Robert Henri'), (2, 'Jiddu Krishnamurti');
+                INSERT INTO books_authors_link VALUES (1, 1, 1), (2, 2, 2);
+                INSERT INTO series VALUES (1, 'Talks');
+                INSERT INTO books_series_link VALUES (1, 2, 1);
+                INSERT INTO publishers VALUES (1, 'Harper');
+                INSERT INTO books_publishers_link VALUES (1, 2, 1);
+                INSERT INTO identifiers VALUES
+                    (1, 2, 'isbn', '978-0-06-091609-1'),
+                    (2, 2, 'goodreads', '12345');
+                INSERT INTO custom_columns VALUES
+                    (1, 'rating', 'float', 0, 0),
+                    (2, 'shelves', 'text', 0, 1);
+                INSERT INTO custom_column_1 VALUES (1, 1, 4.5);
+                INSERT INTO custom_column_2 VALUES (1, 'art'), (2, 'favorites');
+                INSERT INTO books_custom_column_2_link VALUES (1, 1, 1), (2, 1, 2);",
+            )
+            .unwrap();
+
+        directory
+    }
+
+    fn book(title: &str, author: &str, isbn: Option<&str>) -> Book {
+        Book {
+            title: title.to_string(),
+            author: author.to_string(),
+            metadata: BookMetadata {
+                isbn: isbn.map(ToString::to_string),
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    // Tests that books are matched by their title and author.
+    #[test]
+    fn find_title_author() {
+        let library = CalibreLibrary::open(&library("find_title_author")).unwrap();
+
+        let metadata = library
+            .find(&book("The Art Spirit", "robert henri", None))
+            .unwrap();
+
+        assert_eq!(metadata.id, 1);
+        assert_eq!(metadata.series, None);
+        assert_eq!(metadata.series_index, None);
+        assert_eq!(metadata.custom["rating"], "4.5");
+        assert_eq!(metadata.custom["shelves"], "art, favorites");
+
+        assert!(library
+            .find(&book("The Art Spirit", "Someone Else", None))
+            .is_none());
+    }
+
+    // Tests that books are matched by their ISBN even if their title and author differ.
+    #[test]
+    fn find_isbn() {
+        let library = CalibreLibrary::open(&library("find_isbn")).unwrap();
+
+        let metadata = library
+            .find(&book(
+                "Think on These Things",
+                "Krishnamurti",
+                Some("9780060916091"),
+            ))
+            .unwrap();
+
+        assert_eq!(metadata.id, 2);
+        assert_eq!(metadata.series.as_deref(), Some("Talks"));
+        assert_eq!(
+            metadata
+                .series_index
+                .map(|index| index.to_string())
+                .as_deref(),
+            Some("3")
+        );
+        assert_eq!(metadata.publisher.as_deref(), Some("Harper"));
+        assert_eq!(metadata.identifiers["goodreads"], "12345");
+    }
+
+    // Tests that only matched books are enriched.
+    #[test]
+    fn run() {
+        let path = library("run");
+
+        let mut entries = Entries::default();
+
+        for (id, title) in [("A", "The Art Spirit"), ("B", "Unknown")] {
+            entries.insert(
+                id.to_string(),
+                Entry {
+                    book: book(title, "Robert Henri", None),
+                    annotations: Vec::new(),
+                },
+            );
+        }
+
+        assert_eq!(super::run(&mut entries, &path).unwrap(), 1);
+        assert!(entries["A"].book.calibre.is_some());
+        assert!(entries["B"].book.calibre.is_none());
+    }
+
+    // Tests that a missing library returns an error.
+    #[test]
+    fn missing() {
+        let path = TEMP_OUTPUT_DIRECTORY.join("tests-calibre").join("missing");
+
+        assert!(matches!(
+            CalibreLibrary::open(&path),
+            Err(Error::CalibreLibraryError { .. })
+        ));
+    }
+}
@@ -0,0 +1,253 @@
+//! Defines how files are written when a file already exists at their path.
+//!
+//! Sync tools like Obsidian Sync and iCloud treat any change to a file's modification time as an
+//! edit. [`ConflictMode::Update`] and [`ConflictMode::Backup`] compare the hash of a file's new
+//! contents against the existing file's so unchanged files are left untouched.
+
+use std::ffi::OsString;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use sha2::{Digest, Sha256};
+
+/// The extension appended to a file's name when it's backed-up.
+pub const BACKUP_EXTENSION: &str = "bak";
+
+/// An enum representing what to do when writing a file that already exists.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ConflictMode {
+    /// Leaves the existing file untouched.
+    #[default]
+    Skip,
+
+    /// Always rewrites the existing file.
+    Overwrite,
+
+    /// Only rewrites the existing file if its contents changed.
+    Update,
+
+    /// Moves the existing file to `[filename].bak` before rewriting it, but only if its contents
+    /// changed. Any previous back-up is replaced.
+    Backup,
+}
+
+/// An enum representing the outcome of writing a single file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WriteOutcome {
+    /// The file didn't exist and was created.
+    Created,
+
+    /// The file existed and was rewritten.
+    Rewritten,
+
+    /// The file existed and was left untouched.
+    Skipped,
+}
+
+/// A struct representing the number of files per [`WriteOutcome`] across several writes.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct WriteSummary {
+    /// The number of files created.
+    pub created: usize,
+
+    /// The number of existing files rewritten.
+    pub rewritten: usize,
+
+    /// The number of existing files left untouched.
+    pub skipped: usize,
+}
+
+impl WriteSummary {
+    /// Counts a single write's outcome.
+    ///
+    /// # Arguments
+    ///
+    /// * `outcome` - The write's outcome.
+    pub fn add(&mut self, outcome: WriteOutcome) {
+        match outcome {
+            WriteOutcome::Created => self.created += 1,
+            WriteOutcome::Rewritten => self.rewritten += 1,
+            WriteOutcome::Skipped => self.skipped += 1,
+        }
+    }
+
+    /// Adds another summary's counts to this one.
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - The summary to add.
+    pub fn merge(&mut self, other: Self) {
+        self.created += other.created;
+        self.rewritten += other.rewritten;
+        self.skipped += other.skipped;
+    }
+
+    /// Returns the number of files that were created or rewritten.
+    #[must_use]
+    pub fn written(&self) -> usize {
+        self.created + self.rewritten
+    }
+}
+
+/// Writes a file, resolving any conflict with an existing file based on a [`ConflictMode`].
+///
+/// # Arguments
+///
+/// * `path` - The path to write to.
+/// * `contents` - The file's contents.
+/// * `mode` - What to do if the file already exists.
+///
+/// # Errors
+///
+/// Will return `Err` if any IO errors are encountered.
+pub fn write<C>(path: &Path, contents: C, mode: ConflictMode) -> io::Result<WriteOutcome>
+where
+    C: AsRef<[u8]>,
+{
+    let contents = contents.as_ref();
+
+    if !path.exists() {
+        std::fs::write(path, contents)?;
+        return Ok(WriteOutcome::Created);
+    }
+
+    let is_rewritten = match mode {
+        ConflictMode::Skip => false,
+        ConflictMode::Overwrite => true,
+        ConflictMode::Update | ConflictMode::Backup => self::is_changed(path, contents)?,
+    };
+
+    if !is_rewritten {
+        tracing::debug!(path = %path.display(), "skipped writing");
+        return Ok(WriteOutcome::Skipped);
+    }
+
+    if mode == ConflictMode::Backup {
+        std::fs::rename(path, self::backup_path(path))?;
+    }
+
+    std::fs::write(path, contents)?;
+
+    Ok(WriteOutcome::Rewritten)
+}
+
+/// Returns the path a file is backed-up to e.g. `note.md` -> `note.md.bak`.
+///
+/// # Arguments
+///
+/// * `path` - The path to the file.
+#[must_use]
+pub fn backup_path(path: &Path) -> PathBuf {
+    let mut backup = OsString::from(path.as_os_str());
+    backup.push(".");
+    backup.push(BACKUP_EXTENSION);
+
+    PathBuf::from(backup)
+}
+
+/// Returns `true` if the hash of a file's contents differs from the hash of new contents.
+///
+/// # Arguments
+///
+/// * `path` - The path to the existing file.
+/// * `contents` - The new contents.
+///
+/// # Errors
+///
+/// Will return `Err` if the existing file cannot be read.
+fn is_changed(path: &Path, contents: &[u8]) -> io::Result<bool> {
+    let existing = std::fs::read(path)?;
+
+    Ok(Sha256::digest(existing) != Sha256::digest(contents))
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    use crate::defaults::TEMP_OUTPUT_DIRECTORY;
+
+    fn setup(name: &str) -> PathBuf {
+        let directory = TEMP_OUTPUT_DIRECTORY.join("tests-conflict").join(name);
+
+        if directory.exists() {
+            std::fs::remove_dir_all(&directory).unwrap();
+        }
+
+        std::fs::create_dir_all(&directory).unwrap();
+
+        let path = directory.join("note.md");
+        std::fs::write(&path, "old").unwrap();
+
+        path
+    }
+
+    // Tests that each mode only rewrites an existing file when it should.
+    #[test]
+    fn modes() {
+        for (mode, contents, expected, outcome) in [
+            (ConflictMode::Skip, "new", "old", WriteOutcome::Skipped),
+            (
+                ConflictMode::Overwrite,
+                "old",
+                "old",
+                WriteOutcome::Rewritten,
+            ),
+            (ConflictMode::Update, "old", "old", WriteOutcome::Skipped),
+            (ConflictMode::Update, "new", "new", WriteOutcome::Rewritten),
+        ] {
+            let path = setup(&format!("{mode:?}-{contents}"));
+
+            assert_eq!(write(&path, contents, mode).unwrap(), outcome);
+            assert_eq!(std::fs::read_to_string(&path).unwrap(), expected);
+        }
+    }
+
+    // Tests that a changed file is backed-up before being rewritten.
+    #[test]
+    fn backup() {
+        let path = setup("backup");
+
+        assert_eq!(
+            write(&path, "old", ConflictMode::Backup).unwrap(),
+            WriteOutcome::Skipped
+        );
+        assert!(!backup_path(&path).exists());
+
+        assert_eq!(
+            write(&path, "new", ConflictMode::Backup).unwrap(),
+            WriteOutcome::Rewritten
+        );
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "new");
+        assert_eq!(std::fs::read_to_string(backup_path(&path)).unwrap(), "old");
+    }
+
+    // Tests that outcomes are counted and merged.
+    #[test]
+    fn summary() {
+        let mut summary = WriteSummary::default();
+
+        summary.add(WriteOutcome::Created);
+        summary.add(WriteOutcome::Skipped);
+        summary.merge(WriteSummary {
+            created: 1,
+            rewritten: 2,
+            skipped: 0,
+        });
+
+        assert_eq!(summary.written(), 4);
+        assert_eq!(summary.skipped, 1);
+    }
+
+    // Tests that a missing file is created regardless of the mode.
+    #[test]
+    fn created() {
+        let path = setup("created").with_file_name("new.md");
+
+        assert_eq!(
+            write(&path, "new", ConflictMode::Skip).unwrap(),
+            WriteOutcome::Created
+        );
+    }
+}
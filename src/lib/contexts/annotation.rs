@@ -1,12 +1,18 @@
 //! Defines the context for [`Annotation`] data.
 
-use std::collections::BTreeSet;
+use std::collections::{BTreeMap, BTreeSet};
 
+use chrono::Utc;
 use serde::Serialize;
+use serde_json::Value;
 
-use crate::models::annotation::{Annotation, AnnotationMetadata, AnnotationStyle};
+use crate::models::annotation::{Annotation, AnnotationKind, AnnotationMetadata, AnnotationStyle};
+use crate::models::epubcfi::{self, Location};
 use crate::strings;
 
+/// The maximum number of words in an [`Annotation`]'s title hint.
+pub const TITLE_HINT_WORDS: usize = 5;
+
 /// A struct representing an [`Annotation`] within a template context.
 ///
 /// See [`Annotation`] for undocumented fields.
@@ -15,32 +21,78 @@ pub struct AnnotationContext<'a> {
     #[allow(missing_docs)]
     pub body: &'a str,
     #[allow(missing_docs)]
+    pub context_text: &'a str,
+    #[allow(missing_docs)]
     pub style: &'a AnnotationStyle,
     #[allow(missing_docs)]
+    pub kind: AnnotationKind,
+    #[allow(missing_docs)]
     pub notes: &'a str,
     #[allow(missing_docs)]
     pub tags: &'a BTreeSet<String>,
     #[allow(missing_docs)]
+    pub favorite: bool,
+    #[allow(missing_docs)]
+    pub properties: &'a BTreeMap<String, String>,
+    #[allow(missing_docs)]
+    pub fields: &'a BTreeMap<String, Value>,
+    #[allow(missing_docs)]
+    pub chapter: Option<&'a str>,
+    #[allow(missing_docs)]
+    pub mentions: &'a [String],
+    #[allow(missing_docs)]
     pub metadata: &'a AnnotationMetadata,
 
+    /// A short title made from the first significant words of the [`Annotation`]'s body. See
+    /// [`strings::to_title_hint()`] for more information.
+    pub title_hint: String,
+
     /// An [`Annotation`]s slugified strings.
     pub slugs: AnnotationSlugs,
+
+    /// The number of whole days since the [`Annotation`] was created.
+    pub age_days: i64,
+
+    /// An [`Annotation`]s dates relative to now e.g. `3 months ago`.
+    pub relative: AnnotationRelativeDates,
+
+    /// An [`Annotation`]s structured location parsed from its `epubcfi`. This is `None` if the
+    /// `epubcfi` is invalid.
+    pub location: Option<Location>,
 }
 
 impl<'a> From<&'a Annotation> for AnnotationContext<'a> {
     fn from(annotation: &'a Annotation) -> Self {
+        let now = Utc::now();
+        let title_hint = strings::to_title_hint(&annotation.body, TITLE_HINT_WORDS);
+
         Self {
             body: &annotation.body,
+            context_text: &annotation.context_text,
             style: &annotation.style,
+            kind: annotation.kind,
             notes: &annotation.notes,
             tags: &annotation.tags,
+            favorite: annotation.favorite,
+            properties: &annotation.properties,
+            fields: &annotation.fields,
+            chapter: annotation.chapter.as_deref(),
+            mentions: &annotation.mentions,
             metadata: &annotation.metadata,
             slugs: AnnotationSlugs {
+                title_hint: strings::to_slug(&title_hint, true),
                 metadata: AnnotationMetadataSlugs {
                     created: strings::to_slug_date(&annotation.metadata.created),
                     modified: strings::to_slug_date(&annotation.metadata.modified),
                 },
             },
+            age_days: strings::days_since(&annotation.metadata.created, &now),
+            relative: AnnotationRelativeDates {
+                created: strings::to_relative_date(&annotation.metadata.created, &now),
+                modified: strings::to_relative_date(&annotation.metadata.modified, &now),
+            },
+            location: epubcfi::parse_location(&annotation.metadata.epubcfi),
+            title_hint,
         }
     }
 }
@@ -48,6 +100,8 @@ impl<'a> From<&'a Annotation> for AnnotationContext<'a> {
 /// A struct representing an [`Annotation`]'s slugified strings.
 #[derive(Debug, Serialize)]
 pub struct AnnotationSlugs {
+    /// The [`Annotation`]'s title hint slugified.
+    title_hint: String,
     #[allow(missing_docs)]
     metadata: AnnotationMetadataSlugs,
 }
@@ -62,3 +116,14 @@ pub struct AnnotationMetadataSlugs {
     #[allow(missing_docs)]
     modified: String,
 }
+
+/// A struct representing an [`AnnotationMetadata`]'s dates relative to now.
+///
+/// See [`AnnotationMetadata`] for undocumented fields.
+#[derive(Debug, Serialize)]
+pub struct AnnotationRelativeDates {
+    #[allow(missing_docs)]
+    created: String,
+    #[allow(missing_docs)]
+    modified: String,
+}
@@ -1,8 +1,11 @@
 //! Defines the context for [`Book`] data.
 
+use std::path::Path;
+
+use chrono::Utc;
 use serde::Serialize;
 
-use crate::models::book::{Book, BookMetadata};
+use crate::models::book::{Book, BookMetadata, CalibreMetadata};
 use crate::strings;
 
 /// A struct representing a [`Book`] within a template context.
@@ -19,6 +22,17 @@ pub struct BookContext<'a> {
 
     /// A [`Book`]s slugified strings.
     pub slugs: BookSlugs,
+
+    /// The number of whole days since the [`Book`] was last opened, if ever.
+    pub days_since_opened: Option<i64>,
+
+    /// A [`Book`]s dates relative to now e.g. `3 months ago`.
+    pub relative: BookRelativeDates,
+
+    #[allow(missing_docs)]
+    pub cover_path: Option<&'a Path>,
+    #[allow(missing_docs)]
+    pub calibre: Option<&'a CalibreMetadata>,
 }
 
 impl<'a> From<&'a Book> for BookContext<'a> {
@@ -29,6 +43,8 @@ impl<'a> From<&'a Book> for BookContext<'a> {
             String::new()
         };
 
+        let now = Utc::now();
+
         Self {
             title: &book.title,
             author: &book.author,
@@ -38,6 +54,19 @@ impl<'a> From<&'a Book> for BookContext<'a> {
                 author: strings::to_slug(&book.author, true),
                 metadata: BookMetadataSlugs { last_opened },
             },
+            days_since_opened: book
+                .metadata
+                .last_opened
+                .map(|date| strings::days_since(&date, &now)),
+            relative: BookRelativeDates {
+                last_opened: book
+                    .metadata
+                    .last_opened
+                    .map(|date| strings::to_relative_date(&date, &now))
+                    .unwrap_or_default(),
+            },
+            cover_path: book.cover_path.as_deref(),
+            calibre: book.calibre.as_ref(),
         }
     }
 }
@@ -61,3 +90,12 @@ pub struct BookMetadataSlugs {
     #[allow(missing_docs)]
     pub last_opened: String,
 }
+
+/// A struct representing a [`BookMetadata`]'s dates relative to now. Missing dates are empty.
+///
+/// See [`BookMetadata`] for undocumented fields.
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct BookRelativeDates {
+    #[allow(missing_docs)]
+    pub last_opened: String,
+}
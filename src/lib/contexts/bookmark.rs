@@ -0,0 +1,116 @@
+//! Defines the context for [`Audiobook`] and [`Bookmark`] data.
+
+use serde::Serialize;
+
+use crate::models::bookmark::{Audiobook, Bookmark, BookmarkMetadata};
+use crate::strings;
+
+use super::book::BookContext;
+
+/// A struct representing an [`Audiobook`] within a template context.
+#[derive(Debug, Serialize)]
+pub struct AudiobookContext<'a> {
+    /// The audiobook.
+    pub book: BookContext<'a>,
+
+    /// The audiobook's bookmarks sorted by their position.
+    pub bookmarks: Vec<BookmarkContext<'a>>,
+}
+
+impl<'a> From<&'a Audiobook> for AudiobookContext<'a> {
+    fn from(audiobook: &'a Audiobook) -> Self {
+        Self {
+            book: BookContext::from(&audiobook.book),
+            bookmarks: audiobook
+                .bookmarks
+                .iter()
+                .map(BookmarkContext::from)
+                .collect(),
+        }
+    }
+}
+
+/// A struct representing a [`Bookmark`] within a template context.
+///
+/// See [`Bookmark`] for undocumented fields.
+#[derive(Debug, Serialize)]
+pub struct BookmarkContext<'a> {
+    #[allow(missing_docs)]
+    pub notes: &'a str,
+    #[allow(missing_docs)]
+    pub position: f64,
+    #[allow(missing_docs)]
+    pub metadata: &'a BookmarkMetadata,
+
+    /// The [`Bookmark`]'s position formatted as `HH:MM:SS` e.g. `01:02:03`.
+    pub timestamp: String,
+
+    /// A [`Bookmark`]'s slugified strings.
+    pub slugs: BookmarkSlugs,
+}
+
+impl<'a> From<&'a Bookmark> for BookmarkContext<'a> {
+    fn from(bookmark: &'a Bookmark) -> Self {
+        Self {
+            notes: &bookmark.notes,
+            position: bookmark.position,
+            metadata: &bookmark.metadata,
+            timestamp: self::to_timestamp(bookmark.position),
+            slugs: BookmarkSlugs {
+                metadata: BookmarkMetadataSlugs {
+                    created: strings::to_slug_date(&bookmark.metadata.created),
+                    modified: strings::to_slug_date(&bookmark.metadata.modified),
+                },
+            },
+        }
+    }
+}
+
+/// A struct representing a [`Bookmark`]'s slugified strings.
+#[derive(Debug, Serialize)]
+pub struct BookmarkSlugs {
+    #[allow(missing_docs)]
+    metadata: BookmarkMetadataSlugs,
+}
+
+/// A struct representing a [`BookmarkMetadata`]'s slugified strings.
+///
+/// See [`BookmarkMetadata`] for undocumented fields.
+#[derive(Debug, Serialize)]
+pub struct BookmarkMetadataSlugs {
+    #[allow(missing_docs)]
+    created: String,
+    #[allow(missing_docs)]
+    modified: String,
+}
+
+/// Formats a position in seconds as `HH:MM:SS`. Fractions of a second are dropped.
+///
+/// # Arguments
+///
+/// * `position` - The position in seconds.
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+fn to_timestamp(position: f64) -> String {
+    // Positions are never negative. See `Bookmark::from_row()`.
+    let seconds = position.max(0.0) as u64;
+
+    format!(
+        "{:02}:{:02}:{:02}",
+        seconds / 3600,
+        seconds % 3600 / 60,
+        seconds % 60
+    )
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    #[test]
+    fn timestamp() {
+        assert_eq!(to_timestamp(0.0), "00:00:00");
+        assert_eq!(to_timestamp(3723.5), "01:02:03");
+        assert_eq!(to_timestamp(360_000.0), "100:00:00");
+    }
+}
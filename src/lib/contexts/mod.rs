@@ -5,9 +5,17 @@
 //! data onto each type that is specific only to the template context. For example, slugified strings
 //! are added to the [`Book`][book] and [`Annotation`][annotation] types for use within templates.
 //!
+//! Each context is built from its model with `From` e.g. `BookContext::from(&book)`. These can be
+//! passed to [`RenderEngine::render_str()`][render-str] to render one-off template strings.
+//!
 //! [annotation]: crate::models::annotation::Annotation
 //! [book]: crate::models::book::Book
+//! [render-str]: crate::render::engine::RenderEngine::render_str
 
 pub mod annotation;
 pub mod book;
+pub mod bookmark;
 pub mod entry;
+pub mod reading_log;
+pub mod review;
+pub mod tag;
@@ -0,0 +1,102 @@
+//! Defines the context for a reading log.
+
+use chrono::Month;
+use serde::Serialize;
+
+use crate::reading_log::{ReadingLog, ReadingLogMonth, ReadingLogYear};
+
+use super::book::BookContext;
+
+/// A struct representing a [`ReadingLog`] within a template context.
+#[derive(Debug, Serialize)]
+pub struct ReadingLogContext<'a> {
+    /// The years books were finished in, oldest first.
+    pub years: Vec<ReadingLogYearContext<'a>>,
+
+    /// Books marked as finished without a date.
+    pub undated: Vec<BookContext<'a>>,
+
+    /// Books with some reading progress that aren't marked as finished, most read first.
+    pub in_progress: Vec<BookContext<'a>>,
+
+    /// The number of finished books, including those without a date.
+    pub finished_count: usize,
+}
+
+impl<'a> From<&ReadingLog<'a>> for ReadingLogContext<'a> {
+    fn from(log: &ReadingLog<'a>) -> Self {
+        Self {
+            years: log.years.iter().map(ReadingLogYearContext::from).collect(),
+            undated: log
+                .undated
+                .iter()
+                .map(|book| BookContext::from(*book))
+                .collect(),
+            in_progress: log
+                .in_progress
+                .iter()
+                .map(|book| BookContext::from(*book))
+                .collect(),
+            finished_count: log.finished(),
+        }
+    }
+}
+
+/// A struct representing a [`ReadingLogYear`] within a template context.
+#[derive(Debug, Serialize)]
+pub struct ReadingLogYearContext<'a> {
+    #[allow(missing_docs)]
+    pub year: i32,
+
+    /// The months books were finished in, oldest first.
+    pub months: Vec<ReadingLogMonthContext<'a>>,
+
+    /// The number of books finished within the year.
+    pub count: usize,
+}
+
+impl<'a> From<&ReadingLogYear<'a>> for ReadingLogYearContext<'a> {
+    fn from(year: &ReadingLogYear<'a>) -> Self {
+        Self {
+            year: year.year,
+            months: year
+                .months
+                .iter()
+                .map(ReadingLogMonthContext::from)
+                .collect(),
+            count: year.len(),
+        }
+    }
+}
+
+/// A struct representing a [`ReadingLogMonth`] within a template context.
+#[derive(Debug, Serialize)]
+pub struct ReadingLogMonthContext<'a> {
+    #[allow(missing_docs)]
+    pub month: u32,
+
+    /// The month's name e.g. `January`.
+    pub name: &'static str,
+
+    /// The books finished within the month, in the order they were finished.
+    pub books: Vec<BookContext<'a>>,
+}
+
+impl<'a> From<&ReadingLogMonth<'a>> for ReadingLogMonthContext<'a> {
+    fn from(month: &ReadingLogMonth<'a>) -> Self {
+        let name = u8::try_from(month.month)
+            .ok()
+            .and_then(|month| Month::try_from(month).ok())
+            .map_or("", |month| month.name());
+
+        Self {
+            month: month.month,
+            name,
+            books: month
+                .books
+                .iter()
+                .map(|book| BookContext::from(*book))
+                .collect(),
+        }
+    }
+}
@@ -0,0 +1,51 @@
+//! Defines the context for annotations selected for review.
+
+use chrono::NaiveDate;
+use serde::Serialize;
+
+use crate::review::ReviewItem;
+
+use super::annotation::AnnotationContext;
+use super::book::BookContext;
+
+/// A struct representing a day's review within a template context.
+#[derive(Debug, Serialize)]
+pub struct ReviewContext<'a> {
+    /// The review's date formatted as `YYYY-MM-DD`.
+    pub date: String,
+
+    /// The annotations selected for review, each along with its book.
+    pub highlights: Vec<ReviewHighlightContext<'a>>,
+}
+
+impl<'a> ReviewContext<'a> {
+    /// Returns a new instance of [`ReviewContext`].
+    ///
+    /// # Arguments
+    ///
+    /// * `date` - The review's date.
+    /// * `items` - The annotations selected for review.
+    #[must_use]
+    pub fn new(date: NaiveDate, items: &[ReviewItem<'a>]) -> Self {
+        Self {
+            date: date.format("%Y-%m-%d").to_string(),
+            highlights: items
+                .iter()
+                .map(|item| ReviewHighlightContext {
+                    book: BookContext::from(item.book),
+                    annotation: AnnotationContext::from(item.annotation),
+                })
+                .collect(),
+        }
+    }
+}
+
+/// A struct representing an annotation selected for review along with its book.
+#[derive(Debug, Serialize)]
+pub struct ReviewHighlightContext<'a> {
+    /// The annotation's book.
+    pub book: BookContext<'a>,
+
+    /// The annotation.
+    pub annotation: AnnotationContext<'a>,
+}
@@ -0,0 +1,107 @@
+//! Defines the context for a `#tag` and its annotations across all books.
+
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+
+use crate::strings;
+
+use super::annotation::AnnotationContext;
+use super::book::BookContext;
+use super::entry::EntryContext;
+
+/// A struct representing a `#tag` within a template context.
+#[derive(Debug, Serialize)]
+pub struct TagContext<'a> {
+    /// The tag including its leading `#` e.g. `#quote`.
+    pub name: &'a str,
+
+    /// The slugified tag without its leading `#` e.g. `quote`.
+    pub slug: String,
+
+    /// The books with at least one annotation with the tag, each with only those annotations.
+    /// Books are in the order they were given.
+    pub books: Vec<TaggedBookContext<'a>>,
+
+    /// All annotations with the tag, each along with its book. Annotations are grouped by book in
+    /// the order they were given.
+    pub annotations: Vec<TaggedAnnotationContext<'a>>,
+}
+
+impl<'a> TagContext<'a> {
+    /// Returns one [`TagContext`] per `#tag` found in a set of entries, sorted by tag.
+    ///
+    /// # Arguments
+    ///
+    /// * `entries` - The entries to collect the tags from.
+    #[must_use]
+    pub fn from_entries(entries: &'a [EntryContext<'a>]) -> Vec<Self> {
+        let mut tags: BTreeMap<&str, Self> = BTreeMap::new();
+
+        for entry in entries {
+            for annotation in &entry.annotations {
+                for tag in annotation.tags {
+                    tags.entry(tag)
+                        .or_insert_with(|| Self::new(tag))
+                        .push(&entry.book, annotation);
+                }
+            }
+        }
+
+        tags.into_values().collect()
+    }
+
+    /// Returns a new instance of [`TagContext`] without any annotations.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The tag including its leading `#`.
+    #[must_use]
+    pub fn new(name: &'a str) -> Self {
+        Self {
+            name,
+            slug: strings::to_slug(name.trim_start_matches('#'), true),
+            books: Vec::new(),
+            annotations: Vec::new(),
+        }
+    }
+
+    /// Adds an annotation with the tag along with its book.
+    ///
+    /// # Arguments
+    ///
+    /// * `book` - The annotation's book.
+    /// * `annotation` - The annotation.
+    pub fn push(&mut self, book: &'a BookContext<'a>, annotation: &'a AnnotationContext<'a>) {
+        match self.books.last_mut() {
+            Some(tagged) if std::ptr::eq(tagged.book, book) => tagged.annotations.push(annotation),
+            _ => self.books.push(TaggedBookContext {
+                book,
+                annotations: vec![annotation],
+            }),
+        }
+
+        self.annotations
+            .push(TaggedAnnotationContext { book, annotation });
+    }
+}
+
+/// A struct representing a book and its annotations with a specific `#tag`.
+#[derive(Debug, Serialize)]
+pub struct TaggedBookContext<'a> {
+    /// The book.
+    pub book: &'a BookContext<'a>,
+
+    /// The book's annotations with the tag.
+    pub annotations: Vec<&'a AnnotationContext<'a>>,
+}
+
+/// A struct representing an annotation with a specific `#tag` along with its book.
+#[derive(Debug, Serialize)]
+pub struct TaggedAnnotationContext<'a> {
+    /// The annotation's book.
+    pub book: &'a BookContext<'a>,
+
+    /// The annotation.
+    pub annotation: &'a AnnotationContext<'a>,
+}
@@ -66,10 +66,35 @@ pub static UNICODE_TO_ASCII_SYMBOLS: Lazy<Vec<(char, &str)>> = Lazy::new(|| {
     .collect()
 });
 
-#[cfg(test)]
-pub(crate) mod test {
+/// A list of curly quotes and their straight ASCII equivalents.
+pub const CURLY_QUOTES_TO_ASCII: &[(char, char)] = &[
+    ('‘', '\''),
+    ('’', '\''),
+    ('‚', '\''),
+    ('‛', '\''),
+    ('“', '"'),
+    ('”', '"'),
+    ('„', '"'),
+    ('‟', '"'),
+];
+
+/// A list of non-breaking spaces: the no-break space, the figure space and the narrow no-break
+/// space.
+pub const NON_BREAKING_SPACES: &[char] = &['\u{00A0}', '\u{2007}', '\u{202F}'];
+
+/// A list of characters that are significant in Markdown and escaped when rendering annotation
+/// bodies into Markdown.
+pub const MARKDOWN_SPECIAL_CHARS: &[char] = &['\\', '*', '_', '[', ']', '`'];
+
+/// Defines the paths to the test data. Only available with the `test-utils` feature.
+///
+/// The test data is located in the `data` directory of this crate's repository and isn't included
+/// in the published package. It's only available when this crate is used from a clone of the
+/// repository e.g. as a `git` or `path` dependency.
+#[cfg(any(test, feature = "test-utils"))]
+pub mod test {
 
-    use super::*;
+    use super::{Lazy, PathBuf, CRATE_ROOT};
 
     /// Defines the root path to the example templates.
     pub static EXAMPLE_TEMPLATES_DIRECTORY: Lazy<PathBuf> = Lazy::new(|| {
@@ -121,4 +146,80 @@ pub(crate) mod test {
             TEST_TEMPLATES_DIRECTORY.join(directory.to_string())
         }
     }
+
+    /// Defines the root path to the mock macOS databases.
+    ///
+    /// The mock databases are located at: [crate-root]/data/databases/[databases]
+    pub static MOCK_DATABASES_DIRECTORY: Lazy<PathBuf> = Lazy::new(|| {
+        let mut path = CRATE_ROOT.to_owned();
+        path.extend(["data", "databases"].iter());
+        path
+    });
+
+    /// Defines the root path to the mock iOS plists.
+    ///
+    /// The mock plists are located at: [crate-root]/data/plists/[plists]
+    pub static MOCK_PLISTS_DIRECTORY: Lazy<PathBuf> = Lazy::new(|| {
+        let mut path = CRATE_ROOT.to_owned();
+        path.extend(["data", "plists"].iter());
+        path
+    });
+
+    /// An enum representing the sets of mock macOS databases.
+    #[derive(Debug, Clone, Copy)]
+    pub enum MockDatabases {
+        /// A library without any books.
+        Empty,
+
+        /// A library with books but without any annotations.
+        BooksNew,
+
+        /// A library with annotated books.
+        BooksAnnotated,
+    }
+
+    impl std::fmt::Display for MockDatabases {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            match self {
+                Self::Empty => write!(f, "empty"),
+                Self::BooksNew => write!(f, "books-new"),
+                Self::BooksAnnotated => write!(f, "books-annotated"),
+            }
+        }
+    }
+
+    impl From<MockDatabases> for PathBuf {
+        fn from(databases: MockDatabases) -> Self {
+            MOCK_DATABASES_DIRECTORY.join(databases.to_string())
+        }
+    }
+
+    /// An enum representing the sets of mock iOS plists.
+    #[derive(Debug, Clone, Copy)]
+    pub enum MockPlists {
+        /// A library without any books.
+        Empty,
+
+        /// A library with books but without any annotations.
+        BooksNew,
+
+        /// A library with annotated books.
+        BooksAnnotated,
+    }
+
+    impl std::fmt::Display for MockPlists {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            match self {
+                Self::Empty => write!(f, "empty"),
+                Self::BooksNew => write!(f, "books-new"),
+                Self::BooksAnnotated => write!(f, "books-annotated"),
+            }
+        }
+    }
+
+    impl From<MockPlists> for PathBuf {
+        fn from(plists: MockPlists) -> Self {
+            MOCK_PLISTS_DIRECTORY.join(plists.to_string())
+        }
+    }
 }
@@ -0,0 +1,669 @@
+//! Defines types for comparing two sets of entries e.g. two exports or two back-ups.
+//!
+//! Annotations are matched by their id. An annotation is added if its id only exists in the new
+//! set, removed if its id only exists in the old set and edited if any of its user-editable fields
+//! changed i.e. its body, style, notes or tags.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::applebooks::ios::{ABIOs, ABPlist};
+use crate::applebooks::macos::{ABDatabase, ABMacOs};
+use crate::models::annotation::Annotation;
+use crate::models::book::Book;
+use crate::models::entry::{Entries, Entry};
+use crate::result::{Error, Result};
+use crate::utils;
+
+/// The maximum number of characters of an annotation shown in text and Markdown diffs.
+const MAX_EXCERPT_LENGTH: usize = 72;
+
+/// A struct representing the differences between two sets of entries.
+#[derive(Debug, Default, Serialize)]
+pub struct Diff<'a> {
+    /// The number of added, removed and edited annotations across all books.
+    pub summary: DiffSummary,
+
+    /// The books with added, removed or edited annotations, sorted by author and then by title.
+    pub books: Vec<BookDiff<'a>>,
+}
+
+impl Diff<'_> {
+    /// Returns `true` if there are no differences.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.books.is_empty()
+    }
+}
+
+/// A struct representing the number of differences between two sets of entries.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct DiffSummary {
+    /// The number of annotations only in the new set.
+    pub added: usize,
+
+    /// The number of annotations only in the old set.
+    pub removed: usize,
+
+    /// The number of annotations in both sets whose user-editable fields changed.
+    pub edited: usize,
+}
+
+/// A struct representing the differences within a single book.
+#[derive(Debug, Serialize)]
+pub struct BookDiff<'a> {
+    /// The book. This is taken from the new set unless the book only exists in the old set.
+    pub book: &'a Book,
+
+    /// The annotations only in the new set.
+    pub added: Vec<&'a Annotation>,
+
+    /// The annotations only in the old set.
+    pub removed: Vec<&'a Annotation>,
+
+    /// The annotations in both sets whose user-editable fields changed.
+    pub edited: Vec<EditedAnnotation<'a>>,
+}
+
+impl<'a> BookDiff<'a> {
+    fn new(book: &'a Book) -> Self {
+        Self {
+            book,
+            added: Vec::new(),
+            removed: Vec::new(),
+            edited: Vec::new(),
+        }
+    }
+}
+
+/// A struct representing an annotation that changed between two sets of entries.
+#[derive(Debug, Serialize)]
+pub struct EditedAnnotation<'a> {
+    /// The names of the changed fields e.g. `notes` or `tags`.
+    pub fields: Vec<&'static str>,
+
+    /// The annotation in the old set.
+    pub old: &'a Annotation,
+
+    /// The annotation in the new set.
+    pub new: &'a Annotation,
+}
+
+/// An enum representing the formats a [`Diff`] can be rendered as.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum DiffFormat {
+    /// A plain-text list of changes per book.
+    #[default]
+    Text,
+
+    /// The [`Diff`] serialized as JSON.
+    Json,
+
+    /// A Markdown document with a section per book.
+    Markdown,
+}
+
+/// Compares two sets of entries by their annotations' ids.
+///
+/// Books without any differences are left out. Annotations within each book are ordered by their
+/// position within the book.
+///
+/// # Arguments
+///
+/// * `old` - The old set of entries.
+/// * `new` - The new set of entries.
+#[must_use]
+pub fn run<'a>(old: &'a Entries, new: &'a Entries) -> Diff<'a> {
+    let old_annotations = self::index(old);
+    let new_annotations = self::index(new);
+
+    let mut books: HashMap<&str, BookDiff<'a>> = HashMap::new();
+    let mut summary = DiffSummary::default();
+
+    for (id, &(book, annotation)) in &new_annotations {
+        let Some(&(_, old_annotation)) = old_annotations.get(id) else {
+            self::book_diff(&mut books, book).added.push(annotation);
+            summary.added += 1;
+            continue;
+        };
+
+        let fields = self::edited_fields(old_annotation, annotation);
+
+        if !fields.is_empty() {
+            self::book_diff(&mut books, book)
+                .edited
+                .push(EditedAnnotation {
+                    fields,
+                    old: old_annotation,
+                    new: annotation,
+                });
+            summary.edited += 1;
+        }
+    }
+
+    for (id, &(book, annotation)) in &old_annotations {
+        if !new_annotations.contains_key(id) {
+            // Prefers the book from the new set, if it still exists, so each book is only listed
+            // once.
+            let book = new.get(&book.metadata.id).map_or(book, |entry| &entry.book);
+
+            self::book_diff(&mut books, book).removed.push(annotation);
+            summary.removed += 1;
+        }
+    }
+
+    let mut books: Vec<BookDiff<'a>> = books.into_values().collect();
+
+    for book in &mut books {
+        book.added.sort();
+        book.removed.sort();
+        book.edited.sort_by(|a, b| a.new.cmp(b.new));
+    }
+
+    books.sort_by(|a, b| {
+        a.book
+            .author
+            .cmp(&b.book.author)
+            .then_with(|| a.book.title.cmp(&b.book.title))
+    });
+
+    Diff { summary, books }
+}
+
+/// Renders a [`Diff`] as a string.
+///
+/// # Arguments
+///
+/// * `diff` - The diff to render.
+/// * `format` - The format to render as.
+///
+/// # Errors
+///
+/// Will return `Err` if [`serde_json`][serde-json] encounters any errors.
+///
+/// [serde-json]: https://docs.rs/serde_json/latest/serde_json/
+pub fn render(diff: &Diff<'_>, format: DiffFormat) -> Result<String> {
+    let output = match format {
+        DiffFormat::Text => self::render_text(diff),
+        DiffFormat::Json => serde_json::to_string_pretty(diff)?,
+        DiffFormat::Markdown => self::render_markdown(diff),
+    };
+
+    Ok(output)
+}
+
+/// Loads entries from an export or a back-up.
+///
+/// The kind of source is determined by its contents:
+///
+/// * A directory containing the `BKLibrary` and `AEAnnotation` databases is read as a macOS
+///   back-up.
+/// * A directory containing `Books.plist` is read as an iOS back-up.
+/// * Any other directory is read as a JSON export. Every `*.json` file containing a book or a
+///   list of annotations is read, regardless of its name, so both nested and flat exports are
+///   supported.
+/// * A `*.json` file is read as a single-file export.
+/// * A `*.jsonl` or `*.ndjson` file is read as a JSON Lines export.
+///
+/// Compressed and archived exports must be extracted first.
+///
+/// # Arguments
+///
+/// * `path` - The path to the export or back-up.
+///
+/// # Errors
+///
+/// Will return `Err` if:
+/// * The path is neither an export nor a back-up.
+/// * Any IO errors are encountered.
+/// * [`serde_json`][serde-json] encounters any errors while reading a single-file or JSON Lines
+///   export.
+/// * The back-up cannot be read. See [`ABMacOs`] and [`ABIOs`] for more information.
+///
+/// [serde-json]: https://docs.rs/serde_json/latest/serde_json/
+pub fn load(path: &Path) -> Result<Entries> {
+    let invalid = || Error::DiffInvalidSource {
+        path: path.display().to_string(),
+    };
+
+    if path.is_dir() {
+        if ABMacOs::get_database(path, ABDatabase::Books).is_ok() {
+            let books = ABMacOs::extract_books(path)?;
+            let annotations = ABMacOs::extract_annotations(path)?;
+            return Ok(Entries::from_parts(books, annotations));
+        }
+
+        if path.join(ABPlist::Books.to_string()).is_file() {
+            let books = ABIOs::extract_books(path)?;
+            let annotations = ABIOs::extract_annotations(path)?;
+            return Ok(Entries::from_parts(books, annotations));
+        }
+
+        let entries = self::load_export_directory(path)?;
+
+        if entries.is_empty() {
+            return Err(invalid());
+        }
+
+        return Ok(entries);
+    }
+
+    match utils::get_file_extension(&path) {
+        Some("json") => {
+            let entries: Vec<Entry> = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+
+            Ok(entries
+                .into_iter()
+                .map(|entry| (entry.book.metadata.id.clone(), entry))
+                .collect())
+        }
+        Some("jsonl" | "ndjson") => {
+            let mut books = Vec::new();
+            let mut annotations = Vec::new();
+
+            for line in std::fs::read_to_string(path)?.lines() {
+                if line.trim().is_empty() {
+                    continue;
+                }
+
+                let line: AnnotationLine = serde_json::from_str(line)?;
+
+                books.push(line.book);
+                annotations.push(line.annotation);
+            }
+
+            Ok(Entries::from_parts(books, annotations))
+        }
+        _ => Err(invalid()),
+    }
+}
+
+/// Reads all the books and annotations from a JSON export directory.
+///
+/// Files that aren't a book or a list of annotations e.g. a cover image or an unrelated JSON file
+/// are skipped.
+///
+/// # Arguments
+///
+/// * `path` - The path to the export directory.
+fn load_export_directory(path: &Path) -> Result<Entries> {
+    let mut books = Vec::new();
+    let mut annotations = Vec::new();
+
+    let files = walkdir::WalkDir::new(path)
+        .into_iter()
+        .filter_map(std::result::Result::ok)
+        .filter(|entry| entry.file_type().is_file())
+        .filter(|entry| utils::get_file_extension(&entry.path()) == Some("json"));
+
+    for file in files {
+        let contents = std::fs::read_to_string(file.path())?;
+
+        match serde_json::from_str(&contents) {
+            Ok(ExportFile::Book(book)) => books.push(*book),
+            Ok(ExportFile::Annotations(items)) => annotations.extend(items),
+            Err(_) => tracing::debug!(path = %file.path().display(), "skipped reading"),
+        }
+    }
+
+    Ok(Entries::from_parts(books, annotations))
+}
+
+/// Returns every annotation keyed by its id along with its book.
+///
+/// # Arguments
+///
+/// * `entries` - The entries to index.
+fn index(entries: &Entries) -> HashMap<&str, (&Book, &Annotation)> {
+    entries
+        .values()
+        .flat_map(|entry| {
+            entry
+                .annotations
+                .iter()
+                .map(|annotation| (annotation.metadata.id.as_str(), (&entry.book, annotation)))
+        })
+        .collect()
+}
+
+/// Returns the [`BookDiff`] for a book, creating it if it doesn't exist yet.
+///
+/// # Arguments
+///
+/// * `books` - The [`BookDiff`]s keyed by their book's id.
+/// * `book` - The book.
+fn book_diff<'a, 'b>(
+    books: &'b mut HashMap<&'a str, BookDiff<'a>>,
+    book: &'a Book,
+) -> &'b mut BookDiff<'a> {
+    books
+        .entry(book.metadata.id.as_str())
+        .or_insert_with(|| BookDiff::new(book))
+}
+
+/// Returns the names of the user-editable fields that differ between two annotations.
+///
+/// # Arguments
+///
+/// * `old` - The old annotation.
+/// * `new` - The new annotation.
+fn edited_fields(old: &Annotation, new: &Annotation) -> Vec<&'static str> {
+    let mut fields = Vec::new();
+
+    if old.body != new.body {
+        fields.push("body");
+    }
+
+    if old.style != new.style {
+        fields.push("style");
+    }
+
+    if old.notes != new.notes {
+        fields.push("notes");
+    }
+
+    if old.tags != new.tags {
+        fields.push("tags");
+    }
+
+    fields
+}
+
+/// Renders a [`Diff`] as plain-text.
+///
+/// ```plaintext
+/// Robert Henri - The Art Spirit
+///   + "Art when really understood is the province of every human being."
+///   - "Do whatever you do intensely."
+///   ~ "The object isn't to make art, it's to be in that wonderful state which…" (notes)
+///
+/// 1 added, 1 removed, 1 edited
+/// ```
+///
+/// # Arguments
+///
+/// * `diff` - The diff to render.
+fn render_text(diff: &Diff<'_>) -> String {
+    let mut lines = Vec::new();
+
+    for book in &diff.books {
+        lines.push(format!("{} - {}", book.book.author, book.book.title));
+
+        for annotation in &book.added {
+            lines.push(format!("  + \"{}\"", self::excerpt(annotation)));
+        }
+
+        for annotation in &book.removed {
+            lines.push(format!("  - \"{}\"", self::excerpt(annotation)));
+        }
+
+        for edited in &book.edited {
+            lines.push(format!(
+                "  ~ \"{}\" ({})",
+                self::excerpt(edited.new),
+                edited.fields.join(", ")
+            ));
+        }
+
+        lines.push(String::new());
+    }
+
+    lines.push(self::render_summary(diff.summary));
+
+    lines.join("\n") + "\n"
+}
+
+/// Renders a [`Diff`] as Markdown with a section per book and a sub-section per kind of change.
+///
+/// # Arguments
+///
+/// * `diff` - The diff to render.
+fn render_markdown(diff: &Diff<'_>) -> String {
+    let mut lines = vec!["# Diff".to_owned(), String::new()];
+
+    lines.push(self::render_summary(diff.summary));
+
+    for book in &diff.books {
+        lines.push(String::new());
+        lines.push(format!("## {} - {}", book.book.author, book.book.title));
+
+        let sections = [
+            (
+                "Added",
+                book.added
+                    .iter()
+                    .map(|a| self::excerpt(a))
+                    .collect::<Vec<_>>(),
+            ),
+            (
+                "Removed",
+                book.removed.iter().map(|a| self::excerpt(a)).collect(),
+            ),
+            (
+                "Edited",
+                book.edited
+                    .iter()
+                    .map(|edited| {
+                        format!(
+                            "{} _({})_",
+                            self::excerpt(edited.new),
+                            edited.fields.join(", ")
+                        )
+                    })
+                    .collect(),
+            ),
+        ];
+
+        for (heading, items) in sections {
+            if items.is_empty() {
+                continue;
+            }
+
+            lines.push(String::new());
+            lines.push(format!("### {heading}"));
+            lines.push(String::new());
+            lines.extend(items.into_iter().map(|item| format!("- {item}")));
+        }
+    }
+
+    lines.join("\n") + "\n"
+}
+
+/// Renders a [`DiffSummary`] e.g. `1 added, 1 removed, 1 edited`.
+///
+/// # Arguments
+///
+/// * `summary` - The summary to render.
+fn render_summary(summary: DiffSummary) -> String {
+    format!(
+        "{} added, {} removed, {} edited",
+        summary.added, summary.removed, summary.edited
+    )
+}
+
+/// Returns a single-line excerpt of an annotation's body, or its notes if it has no body,
+/// truncated to [`MAX_EXCERPT_LENGTH`] characters.
+///
+/// # Arguments
+///
+/// * `annotation` - The annotation.
+fn excerpt(annotation: &Annotation) -> String {
+    let text = if annotation.body.trim().is_empty() {
+        &annotation.notes
+    } else {
+        &annotation.body
+    };
+
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+
+    if text.chars().count() <= MAX_EXCERPT_LENGTH {
+        return text;
+    }
+
+    let mut truncated: String = text.chars().take(MAX_EXCERPT_LENGTH - 1).collect();
+    truncated.push('…');
+    truncated
+}
+
+/// An enum representing the contents of a file in a JSON export directory.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum ExportFile {
+    /// A `book.json` file.
+    Book(Box<Book>),
+
+    /// An `annotations.json` file.
+    Annotations(Vec<Annotation>),
+}
+
+/// A struct representing a single line of a JSON Lines export.
+#[derive(Debug, Deserialize)]
+struct AnnotationLine {
+    #[serde(flatten)]
+    annotation: Annotation,
+
+    book: Book,
+}
+
+#[cfg(test)]
+mod test {
+
+    use std::path::PathBuf;
+
+    use super::*;
+
+    use crate::conflict::ConflictMode;
+    use crate::defaults::test::MockDatabases;
+    use crate::defaults::TEMP_OUTPUT_DIRECTORY;
+    use crate::export::redact::RedactMode;
+    use crate::export::{ExportFormat, ExportOptions};
+    use crate::models::entry::BookSortKey;
+    use crate::utils::testing;
+
+    fn annotation(id: &str, body: &str) -> Annotation {
+        let mut annotation = Annotation {
+            body: body.to_owned(),
+            ..Default::default()
+        };
+        annotation.metadata.id = id.to_owned();
+        annotation.metadata.book_id = "book".to_owned();
+        annotation
+    }
+
+    fn entries(annotations: Vec<Annotation>) -> Entries {
+        let mut book = Book {
+            title: "Title".to_owned(),
+            author: "Author".to_owned(),
+            ..Default::default()
+        };
+        book.metadata.id = "book".to_owned();
+
+        Entries::from_parts(vec![book], annotations)
+    }
+
+    // Tests that annotations are matched by id and only user-editable changes are reported.
+    #[test]
+    fn added_removed_edited() {
+        let mut moved = annotation("moved", "Unchanged");
+        moved.metadata.location = "2".to_owned();
+
+        let mut edited = annotation("edited", "Edited");
+        edited.notes = "A note.".to_owned();
+
+        let old = entries(vec![
+            annotation("removed", "Removed"),
+            annotation("edited", "Edited"),
+            annotation("moved", "Unchanged"),
+        ]);
+        let new = entries(vec![annotation("added", "Added"), edited, moved]);
+
+        let diff = run(&old, &new);
+
+        assert_eq!(
+            diff.summary,
+            DiffSummary {
+                added: 1,
+                removed: 1,
+                edited: 1,
+            }
+        );
+        assert_eq!(diff.books.len(), 1);
+        assert_eq!(diff.books[0].added[0].metadata.id, "added");
+        assert_eq!(diff.books[0].removed[0].metadata.id, "removed");
+        assert_eq!(diff.books[0].edited[0].fields, ["notes"]);
+    }
+
+    #[test]
+    fn identical() {
+        let old = testing::load_macos_entries(MockDatabases::BooksAnnotated);
+        let new = testing::load_macos_entries(MockDatabases::BooksAnnotated);
+
+        assert!(run(&old, &new).is_empty());
+    }
+
+    #[test]
+    fn render_text() {
+        let old = entries(vec![annotation("removed", "Removed")]);
+        let new = entries(vec![annotation("added", "Added")]);
+
+        assert_eq!(
+            render(&run(&old, &new), DiffFormat::Text).unwrap(),
+            "Author - Title\n  + \"Added\"\n  - \"Removed\"\n\n1 added, 1 removed, 0 edited\n"
+        );
+    }
+
+    #[test]
+    fn excerpt_truncated() {
+        let excerpt = excerpt(&annotation("id", &"word ".repeat(20)));
+
+        assert_eq!(excerpt.chars().count(), MAX_EXCERPT_LENGTH);
+        assert!(excerpt.ends_with('…'));
+    }
+
+    // Tests that a JSON export is read back into the same entries it was exported from.
+    #[test]
+    fn load_export() {
+        let directory = TEMP_OUTPUT_DIRECTORY.join("tests-diff").join("load-export");
+
+        if directory.exists() {
+            std::fs::remove_dir_all(&directory).unwrap();
+        }
+
+        let mut exported = testing::load_macos_entries(MockDatabases::BooksAnnotated);
+
+        let options = ExportOptions {
+            directory_template: None,
+            book_filename_template: None,
+            annotations_filename_template: None,
+            output_template: None,
+            flat: false,
+            compression: None,
+            archive: false,
+            single_file: false,
+            sort_books: BookSortKey::default(),
+            on_conflict: ConflictMode::Overwrite,
+            format: ExportFormat::default(),
+            include_covers: false,
+            filename: None,
+            redact: Vec::new(),
+            redact_mode: RedactMode::default(),
+            canonical_json: false,
+        };
+
+        crate::export::run(&mut exported, &directory, options).unwrap();
+
+        let loaded = load(&directory).unwrap();
+
+        assert!(run(&exported, &loaded).is_empty());
+        assert_eq!(loaded.len(), exported.len());
+    }
+
+    #[test]
+    fn load_backup() {
+        let entries = load(&PathBuf::from(MockDatabases::BooksAnnotated)).unwrap();
+
+        assert_eq!(entries.len(), 3);
+    }
+}
@@ -0,0 +1,404 @@
+//! Defines types for reading the EPUBs stored by Apple Books.
+//!
+//! Apple Books stores each EPUB as an unzipped directory named after the book's id e.g.
+//! `[books]/1969AF0ECA8AE4965029A34316813924.epub/`. These are used to resolve the title of the
+//! chapter each annotation was made in, the book's cover image and how far into the book each
+//! annotation was made.
+
+pub mod opf;
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::models::entry::Entries;
+use crate::models::epubcfi;
+use crate::result::{Error, Result};
+
+use self::opf::TocEntry;
+
+/// A struct representing an EPUB's spine and table of contents.
+#[derive(Debug, Default, Clone)]
+pub struct Epub {
+    /// The paths of the spine items in reading order, relative to the root of the EPUB.
+    spine: Vec<String>,
+
+    /// The table of contents with paths relative to the root of the EPUB.
+    toc: Vec<TocEntry>,
+
+    /// The absolute path to the cover image, if any.
+    cover: Option<PathBuf>,
+
+    /// The absolute path to the unzipped EPUB.
+    root: PathBuf,
+
+    /// The size in bytes of each spine item. This is `None` if any spine item is missing.
+    spine_sizes: Option<Vec<u64>>,
+}
+
+impl Epub {
+    /// Reads an unzipped EPUB.
+    ///
+    /// The table of contents is read from the NCX file if the package document references one,
+    /// otherwise from the EPUB 3 navigation document.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path to the unzipped EPUB.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if:
+    /// * Any IO errors are encountered.
+    /// * The container, package document or table of contents are invalid.
+    pub fn open(path: &Path) -> Result<Self> {
+        let read = |file: &str| std::fs::read_to_string(path.join(file));
+
+        let package_path = opf::parse_container(&read(opf::CONTAINER_PATH)?)?;
+        let package = opf::parse_package(&read(&package_path)?)?;
+
+        let href = |id: &str| {
+            package
+                .manifest
+                .get(id)
+                .map(|item| opf::resolve(&package_path, &item.href))
+        };
+
+        let spine: Vec<String> = package
+            .spine
+            .iter()
+            .map(|id| href(id).unwrap_or_default())
+            .collect();
+
+        let (toc_path, toc) = if let Some(path) = package.toc.as_deref().and_then(href) {
+            let toc = opf::parse_ncx(&read(&path)?)?;
+            (path, toc)
+        } else if let Some(item) = package.nav() {
+            let path = opf::resolve(&package_path, &item.href);
+            let toc = opf::parse_nav(&read(&path)?)?;
+            (path, toc)
+        } else {
+            return Err(Error::EpubInvalid {
+                error: "missing table of contents".to_owned(),
+            });
+        };
+
+        let toc = toc
+            .into_iter()
+            .map(|entry| TocEntry {
+                href: opf::resolve(&toc_path, &entry.href),
+                title: entry.title,
+            })
+            .collect();
+
+        let cover = package
+            .cover_image()
+            .map(|item| path.join(opf::resolve(&package_path, &item.href)))
+            .filter(|path| path.is_file());
+
+        let spine_sizes = spine
+            .iter()
+            .map(|href| {
+                std::fs::metadata(path.join(href))
+                    .ok()
+                    .map(|metadata| metadata.len())
+            })
+            .collect();
+
+        Ok(Self {
+            spine,
+            toc,
+            cover,
+            root: path.to_owned(),
+            spine_sizes,
+        })
+    }
+
+    /// Returns the absolute path to the cover image. Returns `None` if the package document
+    /// doesn't reference one or it's missing.
+    #[must_use]
+    pub fn cover(&self) -> Option<&Path> {
+        self.cover.as_deref()
+    }
+
+    /// Returns the title of the chapter containing a spine item.
+    ///
+    /// Chapters are often split across several spine items so if a spine item isn't listed in the
+    /// table of contents, the closest preceding one that is is used. Returns `None` for spine
+    /// items before the first chapter e.g. the cover.
+    ///
+    /// # Arguments
+    ///
+    /// * `spine_index` - The index of the spine item.
+    #[must_use]
+    pub fn chapter(&self, spine_index: usize) -> Option<&str> {
+        self.spine
+            .get(..=spine_index)?
+            .iter()
+            .rev()
+            .find_map(|href| {
+                self.toc
+                    .iter()
+                    .find(|entry| entry.href == *href)
+                    .map(|entry| entry.title.as_str())
+            })
+    }
+
+    /// Returns how far into the book an `epubcfi` is, from `0.0` to `1.0`. Returns `None` if the
+    /// `epubcfi` is invalid or doesn't reference a spine item.
+    ///
+    /// Each spine item is weighted by its size so longer chapters take up more of the book. The
+    /// position within a spine item is that of the element the `epubcfi` points to. See
+    /// [`opf::element_offset()`]. If any spine item is missing, each is weighted equally and the
+    /// start of the spine item is used instead.
+    ///
+    /// # Arguments
+    ///
+    /// * `epubcfi` - The `epubcfi` to locate.
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn position(&self, epubcfi: &str) -> Option<f64> {
+        let location = epubcfi::parse_location(epubcfi)?;
+        let index = location
+            .spine_index
+            .filter(|index| *index < self.spine.len())?;
+
+        let sizes = self
+            .spine_sizes
+            .as_ref()
+            .filter(|sizes| sizes.iter().sum::<u64>() > 0);
+
+        let Some(sizes) = sizes else {
+            return Some(index as f64 / self.spine.len() as f64);
+        };
+
+        let before: u64 = sizes[..index].iter().sum();
+        let total: u64 = sizes.iter().sum();
+
+        let offset = std::fs::read_to_string(self.root.join(&self.spine[index]))
+            .map(|xml| opf::element_offset(&xml, &location.steps) as u64)
+            .unwrap_or_default()
+            .min(sizes[index]);
+
+        Some((before + offset) as f64 / total as f64)
+    }
+}
+
+/// A struct for reading EPUBs from a directory, keyed by their book's id.
+///
+/// Each EPUB is read at most once. EPUBs that are missing or invalid are remembered as such.
+#[derive(Debug)]
+pub struct EpubCache {
+    /// The directory containing the EPUBs.
+    directory: PathBuf,
+
+    /// The EPUBs read so far keyed by their book's id.
+    epubs: HashMap<String, Option<Epub>>,
+}
+
+impl EpubCache {
+    /// Creates a new instance of [`EpubCache`].
+    ///
+    /// # Arguments
+    ///
+    /// * `directory` - The directory containing the EPUBs.
+    #[must_use]
+    pub fn new(directory: &Path) -> Self {
+        Self {
+            directory: directory.to_owned(),
+            epubs: HashMap::new(),
+        }
+    }
+
+    /// Returns a book's EPUB. Returns `None` if it's missing or invalid.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The book's id.
+    pub fn get(&mut self, id: &str) -> Option<&Epub> {
+        let directory = &self.directory;
+
+        self.epubs
+            .entry(id.to_owned())
+            .or_insert_with(|| {
+                let path = directory.join(format!("{id}.epub"));
+
+                if !path.is_dir() {
+                    return None;
+                }
+
+                match Epub::open(&path) {
+                    Ok(epub) => Some(epub),
+                    Err(error) => {
+                        tracing::warn!(path = %path.display(), %error, "failed to read EPUB");
+                        None
+                    }
+                }
+            })
+            .as_ref()
+    }
+}
+
+/// Sets the cover path of every book and the chapter of every annotation whose book's EPUB exists
+/// in a directory.
+///
+/// # Arguments
+///
+/// * `entries` - The [`Entries`] to update.
+/// * `directory` - The directory containing the EPUBs.
+pub fn run(entries: &mut Entries, directory: &Path) {
+    let mut cache = EpubCache::new(directory);
+
+    for (id, entry) in entries.iter_mut() {
+        let Some(epub) = cache.get(id) else {
+            continue;
+        };
+
+        entry.book.cover_path = epub.cover().map(Path::to_path_buf);
+
+        for annotation in &mut entry.annotations {
+            annotation.chapter = epubcfi::parse_location(&annotation.metadata.epubcfi)
+                .and_then(|location| location.spine_index)
+                .and_then(|index| epub.chapter(index))
+                .map(ToOwned::to_owned);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    use crate::defaults::{CRATE_ROOT, TEMP_OUTPUT_DIRECTORY};
+    use crate::models::annotation::{Annotation, AnnotationMetadata};
+    use crate::models::book::{Book, BookMetadata};
+
+    const BOOK_ID: &str = "8E6660FFF4EB8789B4BDF5FAD625CCFC";
+
+    fn directory() -> PathBuf {
+        let mut path = CRATE_ROOT.to_owned();
+        path.extend(["data", "databases", "books-annotated", "Books"].iter());
+        path
+    }
+
+    fn entries(epubcfi: &[&str]) -> Entries {
+        let book = Book {
+            metadata: BookMetadata {
+                id: BOOK_ID.to_owned(),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let annotations = epubcfi
+            .iter()
+            .map(|epubcfi| Annotation {
+                metadata: AnnotationMetadata {
+                    book_id: BOOK_ID.to_owned(),
+                    epubcfi: (*epubcfi).to_owned(),
+                    ..Default::default()
+                },
+                ..Default::default()
+            })
+            .collect();
+
+        Entries::from_parts(vec![book], annotations)
+    }
+
+    #[test]
+    fn chapter_falls_back_to_preceding() {
+        let epub = Epub {
+            spine: vec![
+                "cover.xhtml".into(),
+                "c01.xhtml".into(),
+                "c01-2.xhtml".into(),
+            ],
+            toc: vec![TocEntry {
+                title: "One".into(),
+                href: "c01.xhtml".into(),
+            }],
+            ..Default::default()
+        };
+
+        assert_eq!(epub.chapter(0), None);
+        assert_eq!(epub.chapter(1), Some("One"));
+        assert_eq!(epub.chapter(2), Some("One"));
+        assert_eq!(epub.chapter(3), None);
+    }
+
+    // Tests that spine items are weighted equally if they're missing.
+    #[test]
+    fn position_without_spine_items() {
+        let epub = Epub {
+            spine: vec!["c01.xhtml".into(), "c02.xhtml".into()],
+            ..Default::default()
+        };
+
+        assert_eq!(epub.position("epubcfi(/6/2[c01]!/4/2)"), Some(0.0));
+        assert_eq!(epub.position("epubcfi(/6/4[c02]!/4/2)"), Some(0.5));
+        assert_eq!(epub.position("epubcfi(/6/6!/4/2)"), None);
+        assert_eq!(epub.position("invalid"), None);
+    }
+
+    // Tests that spine items are weighted by their size and the position within one is that of
+    // the element the `epubcfi` points to.
+    #[test]
+    #[allow(clippy::cast_precision_loss)]
+    fn position_within_spine_item() {
+        let root = TEMP_OUTPUT_DIRECTORY.join("tests-epub-position");
+        std::fs::create_dir_all(&root).unwrap();
+
+        let first = "<html><head/><body><p>One</p></body></html>";
+        let second = "<html><head/><body><p>Two</p><p>Three</p></body></html>";
+
+        std::fs::write(root.join("c01.xhtml"), first).unwrap();
+        std::fs::write(root.join("c02.xhtml"), second).unwrap();
+
+        let epub = Epub {
+            spine: vec!["c01.xhtml".into(), "c02.xhtml".into()],
+            spine_sizes: Some(vec![first.len() as u64, second.len() as u64]),
+            root,
+            ..Default::default()
+        };
+
+        let offset = second.find("<p>Three").unwrap();
+        let expected = (first.len() + offset) as f64 / (first.len() + second.len()) as f64;
+
+        assert_eq!(epub.position("epubcfi(/6/4[c02]!/4/4/1:2)"), Some(expected));
+    }
+
+    // Tests that chapters and the cover are resolved from the test EPUB.
+    #[test]
+    fn run_from_epub() {
+        let mut entries = entries(&[
+            "epubcfi(/6/10[c01]!/4/10/3,:335,:749)",
+            "epubcfi(/6/42[c17]!/4,/14/1:41,/20/1:359)",
+            "epubcfi(/6/2[cover]!/4/2/1:0)",
+        ]);
+
+        run(&mut entries, &directory());
+
+        let chapters: Vec<Option<&str>> = entries[BOOK_ID]
+            .annotations
+            .iter()
+            .map(|annotation| annotation.chapter.as_deref())
+            .collect();
+
+        assert_eq!(chapters, [Some("Chapter 1"), Some("Chapter 17"), None]);
+        assert_eq!(
+            entries[BOOK_ID].book.cover_path,
+            Some(
+                directory()
+                    .join(format!("{BOOK_ID}.epub"))
+                    .join("OEBPS/images/cover.jpg")
+            )
+        );
+    }
+
+    #[test]
+    fn missing_epub() {
+        let mut cache = EpubCache::new(&directory());
+
+        assert!(cache.get("MISSING").is_none());
+    }
+}
@@ -0,0 +1,574 @@
+//! Defines parsers for the XML files within an EPUB: the container, the [OPF][opf] package
+//! document and either an [NCX][ncx] or an [EPUB 3 navigation document][nav] table of contents.
+//!
+//! Only the parts required to map spine items to chapter titles and to locate annotations within
+//! spine items are parsed.
+//!
+//! [opf]: https://www.w3.org/TR/epub-33/#sec-package-doc
+//! [ncx]: https://idpf.org/epub/20/spec/OPF_2.0_latest.htm#Section2.4.1
+//! [nav]: https://www.w3.org/TR/epub-33/#sec-nav
+
+use std::collections::HashMap;
+
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+
+use crate::result::{Error, Result};
+
+/// The path to the container file, relative to the root of the EPUB.
+pub const CONTAINER_PATH: &str = "META-INF/container.xml";
+
+/// A struct representing the parts of a package document required to resolve chapters.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Package {
+    /// The manifest's items keyed by their ids.
+    pub manifest: HashMap<String, ManifestItem>,
+
+    /// The ids of the manifest items in reading order.
+    pub spine: Vec<String>,
+
+    /// The id of the manifest item containing the NCX table of contents, if any.
+    pub toc: Option<String>,
+
+    /// The id of the manifest item referenced by an EPUB 2 `<meta name="cover">` element, if any.
+    pub cover: Option<String>,
+}
+
+impl Package {
+    /// Returns the manifest item of the EPUB 3 navigation document, if any.
+    #[must_use]
+    pub fn nav(&self) -> Option<&ManifestItem> {
+        self.manifest
+            .values()
+            .find(|item| item.properties.split_whitespace().any(|p| p == "nav"))
+    }
+
+    /// Returns the manifest item of the cover image, if any.
+    ///
+    /// The EPUB 3 `cover-image` property takes precedence over the EPUB 2 `<meta name="cover">`
+    /// element.
+    #[must_use]
+    pub fn cover_image(&self) -> Option<&ManifestItem> {
+        self.manifest
+            .values()
+            .find(|item| {
+                item.properties
+                    .split_whitespace()
+                    .any(|p| p == "cover-image")
+            })
+            .or_else(|| self.cover.as_deref().and_then(|id| self.manifest.get(id)))
+    }
+}
+
+/// A struct representing an item in a package document's manifest.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ManifestItem {
+    /// The item's path, relative to the package document.
+    pub href: String,
+
+    /// The item's space-separated properties e.g. `nav`.
+    pub properties: String,
+}
+
+/// A struct representing an entry in a table of contents.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TocEntry {
+    /// The entry's title.
+    pub title: String,
+
+    /// The entry's path, relative to the table of contents, including any fragment.
+    pub href: String,
+}
+
+/// Returns the path to the package document from a container file.
+///
+/// # Arguments
+///
+/// * `xml` - The contents of `META-INF/container.xml`.
+///
+/// # Errors
+///
+/// Will return `Err` if the XML is invalid or doesn't reference a package document.
+pub fn parse_container(xml: &str) -> Result<String> {
+    let mut reader = Reader::from_str(xml);
+
+    loop {
+        match reader.read_event().map_err(self::invalid)? {
+            Event::Start(element) | Event::Empty(element)
+                if element.local_name().as_ref() == b"rootfile" =>
+            {
+                if let Some(path) = self::attribute(&element, b"full-path") {
+                    return Ok(path);
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    Err(Error::EpubInvalid {
+        error: "missing package document".to_owned(),
+    })
+}
+
+/// Parses a package document's manifest and spine.
+///
+/// # Arguments
+///
+/// * `xml` - The contents of the package document.
+///
+/// # Errors
+///
+/// Will return `Err` if the XML is invalid.
+pub fn parse_package(xml: &str) -> Result<Package> {
+    let mut reader = Reader::from_str(xml);
+    let mut package = Package::default();
+
+    loop {
+        match reader.read_event().map_err(self::invalid)? {
+            Event::Start(element) | Event::Empty(element) => {
+                match element.local_name().as_ref() {
+                    b"item" => {
+                        let (Some(id), Some(href)) = (
+                            self::attribute(&element, b"id"),
+                            self::attribute(&element, b"href"),
+                        ) else {
+                            continue;
+                        };
+
+                        let properties =
+                            self::attribute(&element, b"properties").unwrap_or_default();
+
+                        package
+                            .manifest
+                            .insert(id, ManifestItem { href, properties });
+                    }
+                    // Non-linear items are kept as they still count towards the spine index.
+                    b"itemref" => {
+                        if let Some(idref) = self::attribute(&element, b"idref") {
+                            package.spine.push(idref);
+                        }
+                    }
+                    b"spine" => package.toc = self::attribute(&element, b"toc"),
+                    b"meta" if self::attribute(&element, b"name").as_deref() == Some("cover") => {
+                        package.cover = self::attribute(&element, b"content");
+                    }
+                    _ => {}
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    Ok(package)
+}
+
+/// Parses an NCX table of contents. Nested entries are flattened in document order.
+///
+/// # Arguments
+///
+/// * `xml` - The contents of the NCX file.
+///
+/// # Errors
+///
+/// Will return `Err` if the XML is invalid.
+pub fn parse_ncx(xml: &str) -> Result<Vec<TocEntry>> {
+    let mut reader = Reader::from_str(xml);
+    reader.config_mut().trim_text(true);
+
+    let mut entries = Vec::new();
+
+    // The title of each open `navPoint`, innermost last.
+    let mut titles: Vec<String> = Vec::new();
+    let mut in_label = false;
+
+    loop {
+        match reader.read_event().map_err(self::invalid)? {
+            Event::Start(element) => match element.local_name().as_ref() {
+                b"navPoint" => titles.push(String::new()),
+                b"navLabel" => in_label = true,
+                _ => {}
+            },
+            Event::End(element) => match element.local_name().as_ref() {
+                b"navPoint" => {
+                    titles.pop();
+                }
+                b"navLabel" => in_label = false,
+                _ => {}
+            },
+            Event::Text(text) if in_label => {
+                if let Some(title) = titles.last_mut() {
+                    title.push_str(&text.unescape().map_err(self::invalid)?);
+                }
+            }
+            Event::Empty(element) if element.local_name().as_ref() == b"content" => {
+                if let (Some(title), Some(href)) =
+                    (titles.last(), self::attribute(&element, b"src"))
+                {
+                    entries.push(TocEntry {
+                        title: title.trim().to_owned(),
+                        href,
+                    });
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    Ok(entries)
+}
+
+/// Parses an EPUB 3 navigation document's table of contents. Nested entries are flattened in
+/// document order.
+///
+/// # Arguments
+///
+/// * `xml` - The contents of the navigation document.
+///
+/// # Errors
+///
+/// Will return `Err` if the XML is invalid.
+pub fn parse_nav(xml: &str) -> Result<Vec<TocEntry>> {
+    let mut reader = Reader::from_str(xml);
+
+    let mut entries = Vec::new();
+
+    // The depth of `nav` elements inside the table of contents' `nav`, if inside it.
+    let mut toc_depth: Option<usize> = None;
+    let mut link: Option<TocEntry> = None;
+
+    loop {
+        match reader.read_event().map_err(self::invalid)? {
+            Event::Start(element) => match element.local_name().as_ref() {
+                b"nav" => {
+                    if let Some(depth) = toc_depth.as_mut() {
+                        *depth += 1;
+                    } else if self::attribute(&element, b"type")
+                        .is_some_and(|kind| kind.split_whitespace().any(|k| k == "toc"))
+                    {
+                        toc_depth = Some(0);
+                    }
+                }
+                b"a" if toc_depth.is_some() => {
+                    link = self::attribute(&element, b"href").map(|href| TocEntry {
+                        title: String::new(),
+                        href,
+                    });
+                }
+                _ => {}
+            },
+            Event::End(element) => match element.local_name().as_ref() {
+                b"nav" => {
+                    toc_depth = match toc_depth {
+                        Some(0) | None => None,
+                        Some(depth) => Some(depth - 1),
+                    };
+                }
+                b"a" => {
+                    if let Some(mut entry) = link.take() {
+                        entry.title = entry.title.split_whitespace().collect::<Vec<_>>().join(" ");
+                        entries.push(entry);
+                    }
+                }
+                _ => {}
+            },
+            Event::Text(text) => {
+                if let Some(entry) = link.as_mut() {
+                    entry
+                        .title
+                        .push_str(&text.unescape().map_err(self::invalid)?);
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    Ok(entries)
+}
+
+/// Resolves an `href` relative to the file it appears in, returning a path relative to the root
+/// of the EPUB. Any fragment is removed.
+///
+/// # Arguments
+///
+/// * `base` - The path to the file the `href` appears in, relative to the root of the EPUB.
+/// * `href` - The `href` to resolve.
+#[must_use]
+pub fn resolve(base: &str, href: &str) -> String {
+    let href = href.split('#').next().unwrap_or_default();
+
+    let mut segments: Vec<&str> = base.split('/').collect();
+
+    // Removes the file name leaving its directory.
+    segments.pop();
+
+    for segment in href.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => {
+                segments.pop();
+            }
+            segment => segments.push(segment),
+        }
+    }
+
+    segments.retain(|segment| !segment.is_empty());
+    segments.join("/")
+}
+
+/// Returns the byte offset, from the start of a spine item, of the element an `epubcfi`'s steps
+/// point to. See [`Location::steps`][steps].
+///
+/// Even steps reference elements and are followed from the document element down. Odd steps
+/// reference the text between elements so the closest preceding element is used instead. If a step
+/// points to an element that doesn't exist or the XML is invalid, the offset of the last element
+/// found is returned.
+///
+/// # Arguments
+///
+/// * `xml` - The contents of the spine item.
+/// * `steps` - The step references within the spine item's document.
+///
+/// [steps]: crate::models::epubcfi::Location::steps
+#[must_use]
+pub fn element_offset(xml: &str, steps: &[usize]) -> usize {
+    // The zero-based index of each element among its parent's child elements.
+    let targets: Vec<usize> = steps
+        .iter()
+        .take_while(|step| **step >= 2 && **step % 2 == 0)
+        .map(|step| step / 2 - 1)
+        .collect();
+
+    let mut reader = Reader::from_str(xml);
+    reader.config_mut().check_end_names = false;
+
+    let mut offset = 0;
+    let mut has_root = false;
+    // The depth of the next element, where the document element is at `0`.
+    let mut depth = 0;
+    // The number of targets found. The last target found is at this depth.
+    let mut found = 0;
+    // The index of the next child element of the last target found.
+    let mut index = 0;
+
+    loop {
+        let position = reader.buffer_position();
+
+        let Ok(event) = reader.read_event() else {
+            break;
+        };
+
+        match event {
+            Event::Start(_) | Event::Empty(_) => {
+                if !has_root {
+                    has_root = true;
+                    offset = position;
+                } else if depth == found + 1 && found < targets.len() {
+                    if index == targets[found] {
+                        offset = position;
+                        found += 1;
+                        index = 0;
+
+                        if found == targets.len() {
+                            break;
+                        }
+                    } else {
+                        index += 1;
+                    }
+                }
+
+                if matches!(event, Event::Start(_)) {
+                    depth += 1;
+                }
+            }
+            Event::End(_) => {
+                depth -= 1;
+
+                // Leaving the last target found means the next one doesn't exist.
+                if depth <= found {
+                    break;
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    usize::try_from(offset).unwrap_or_default()
+}
+
+/// Returns the unescaped value of an element's attribute, matched by its local name.
+///
+/// # Arguments
+///
+/// * `element` - The element.
+/// * `name` - The local name of the attribute.
+fn attribute(element: &BytesStart<'_>, name: &[u8]) -> Option<String> {
+    element
+        .attributes()
+        .flatten()
+        .find(|attribute| attribute.key.local_name().as_ref() == name)
+        .and_then(|attribute| attribute.unescape_value().ok())
+        .map(std::borrow::Cow::into_owned)
+}
+
+/// Converts an XML error into an [`Error`].
+///
+/// # Arguments
+///
+/// * `error` - The XML error.
+#[allow(clippy::needless_pass_by_value)]
+fn invalid(error: quick_xml::Error) -> Error {
+    Error::EpubInvalid {
+        error: error.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    #[test]
+    fn container() {
+        let xml = r#"<?xml version="1.0"?>
+            <container version="1.0" xmlns="urn:oasis:names:tc:opendocument:xmlns:container">
+              <rootfiles>
+                <rootfile full-path="OEBPS/content.opf" media-type="application/oebps-package+xml"/>
+              </rootfiles>
+            </container>"#;
+
+        assert_eq!(parse_container(xml).unwrap(), "OEBPS/content.opf");
+        assert!(parse_container("<container/>").is_err());
+    }
+
+    #[test]
+    fn package() {
+        let xml = r#"<package xmlns="http://www.idpf.org/2007/opf" version="3.0">
+              <manifest>
+                <item id="ncx" href="toc.ncx" media-type="application/x-dtbncx+xml"/>
+                <item id="nav" href="nav.xhtml" properties="nav" media-type="application/xhtml+xml"/>
+                <item id="c01" href="text/c01.xhtml" media-type="application/xhtml+xml"/>
+              </manifest>
+              <spine toc="ncx">
+                <itemref idref="nav" linear="no"/>
+                <itemref idref="c01"/>
+              </spine>
+            </package>"#;
+
+        let package = parse_package(xml).unwrap();
+
+        assert_eq!(package.spine, ["nav", "c01"]);
+        assert_eq!(package.toc.as_deref(), Some("ncx"));
+        assert_eq!(package.manifest["c01"].href, "text/c01.xhtml");
+        assert_eq!(package.nav().unwrap().href, "nav.xhtml");
+    }
+
+    // Tests that the `cover-image` property takes precedence over the `cover` meta element.
+    #[test]
+    fn package_cover_image() {
+        let xml = r#"<package xmlns="http://www.idpf.org/2007/opf" version="3.0">
+              <metadata><meta name="cover" content="thumbnail"/></metadata>
+              <manifest>
+                <item id="thumbnail" href="images/thumbnail.jpg" media-type="image/jpeg"/>
+                <item id="cover" href="images/cover.png" properties="cover-image" media-type="image/png"/>
+              </manifest>
+            </package>"#;
+
+        let mut package = parse_package(xml).unwrap();
+
+        assert_eq!(package.cover.as_deref(), Some("thumbnail"));
+        assert_eq!(package.cover_image().unwrap().href, "images/cover.png");
+
+        package.manifest.remove("cover");
+
+        assert_eq!(package.cover_image().unwrap().href, "images/thumbnail.jpg");
+    }
+
+    // Tests that nested `navPoint`s are flattened in document order.
+    #[test]
+    fn ncx() {
+        let xml = r#"<ncx xmlns="http://www.daisy.org/z3986/2005/ncx/">
+              <navMap>
+                <navPoint id="p1">
+                  <navLabel><text>Part One</text></navLabel>
+                  <content src="text/p1.xhtml"/>
+                  <navPoint id="c1">
+                    <navLabel><text>Chapter 1 &amp; More</text></navLabel>
+                    <content src="text/c01.xhtml#start"/>
+                  </navPoint>
+                </navPoint>
+              </navMap>
+            </ncx>"#;
+
+        assert_eq!(
+            parse_ncx(xml).unwrap(),
+            [
+                TocEntry {
+                    title: "Part One".to_owned(),
+                    href: "text/p1.xhtml".to_owned(),
+                },
+                TocEntry {
+                    title: "Chapter 1 & More".to_owned(),
+                    href: "text/c01.xhtml#start".to_owned(),
+                },
+            ]
+        );
+    }
+
+    // Tests that only links within the table of contents are parsed.
+    #[test]
+    fn nav() {
+        let xml = r#"<html xmlns="http://www.w3.org/1999/xhtml" xmlns:epub="http://www.idpf.org/2007/ops">
+              <body>
+                <nav epub:type="landmarks"><ol><li><a href="cover.xhtml">Cover</a></li></ol></nav>
+                <nav epub:type="toc">
+                  <ol>
+                    <li><a href="text/c01.xhtml"><span>Chapter</span> 1</a>
+                      <ol><li><a href="text/c01.xhtml#s1">Section</a></li></ol>
+                    </li>
+                  </ol>
+                </nav>
+              </body>
+            </html>"#;
+
+        let entries = parse_nav(xml).unwrap();
+
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].title, "Chapter 1");
+        assert_eq!(entries[1].href, "text/c01.xhtml#s1");
+    }
+
+    #[test]
+    fn resolve_href() {
+        assert_eq!(
+            resolve("OEBPS/content.opf", "text/c01.xhtml"),
+            "OEBPS/text/c01.xhtml"
+        );
+        assert_eq!(
+            resolve("OEBPS/toc/toc.ncx", "../text/c01.xhtml#a"),
+            "OEBPS/text/c01.xhtml"
+        );
+        assert_eq!(resolve("content.opf", "./c01.xhtml"), "c01.xhtml");
+    }
+
+    #[test]
+    fn element_offset_follows_steps() {
+        let xml = "<?xml version=\"1.0\"?>\
+            <html><head><title>One</title></head>\
+            <body><p>First</p><p>Second</p><p>Third <em>!</em></p></body></html>";
+
+        let third = xml.find("<p>Third").unwrap();
+
+        // -> <body> -> third <p>
+        assert_eq!(element_offset(xml, &[4, 6]), third);
+        // Odd steps use the closest preceding element.
+        assert_eq!(element_offset(xml, &[4, 6, 1]), third);
+        // A missing element uses the last element found.
+        assert_eq!(element_offset(xml, &[4, 10]), xml.find("<body>").unwrap());
+        assert_eq!(element_offset(xml, &[]), xml.find("<html>").unwrap());
+    }
+}
@@ -0,0 +1,159 @@
+//! Defines functions for writing exports as canonical JSON.
+//!
+//! Canonical JSON is written so that exporting the same data twice produces identical files and
+//! exporting changed data only changes the lines that hold the changes. This keeps exports that
+//! are committed to `git` free of noisy diffs:
+//!
+//! * Object keys are sorted alphabetically.
+//! * Annotations are sorted by their location and then by their id.
+//! * Floats are rounded to [`FLOAT_PRECISION`] decimal places.
+//! * Dates are written in UTC with a fixed number of fractional digits.
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::models::entry::Entries;
+use crate::result::Result;
+
+/// The number of decimal places floats are rounded to.
+pub const FLOAT_PRECISION: i32 = 6;
+
+/// The keys whose values are dates.
+const DATE_KEYS: &[&str] = &["created", "modified", "last_opened"];
+
+/// Sorts each entry's annotations by their location and then by their id.
+///
+/// Annotations are otherwise only sorted by their location so two annotations at the same location
+/// could swap places between exports.
+///
+/// # Arguments
+///
+/// * `entries` - The entries to sort.
+pub fn sort_annotations(entries: &mut Entries) {
+    for entry in entries.values_mut() {
+        entry
+            .annotations
+            .sort_by(|a, b| a.cmp(b).then_with(|| a.metadata.id.cmp(&b.metadata.id)));
+    }
+}
+
+/// Serializes a value into canonical JSON.
+///
+/// # Arguments
+///
+/// * `value` - The value to serialize.
+///
+/// # Errors
+///
+/// Will return `Err` if [`serde_json`][serde-json] encounters any errors.
+///
+/// [serde-json]: https://docs.rs/serde_json/latest/serde_json/
+pub fn to_value<T>(value: &T) -> Result<Value>
+where
+    T: Serialize + ?Sized,
+{
+    // `serde_json`'s objects are backed by a `BTreeMap` so their keys are already sorted.
+    let mut value = serde_json::to_value(value)?;
+
+    self::normalize(&mut value);
+
+    Ok(value)
+}
+
+/// Rounds all floats and reformats all dates within a value, recursively.
+///
+/// # Arguments
+///
+/// * `value` - The value to normalize.
+fn normalize(value: &mut Value) {
+    match value {
+        Value::Number(number) => {
+            if let Some(float) = number.as_f64().filter(|_| number.is_f64()) {
+                let factor = 10_f64.powi(FLOAT_PRECISION);
+
+                if let Some(rounded) =
+                    serde_json::Number::from_f64((float * factor).round() / factor)
+                {
+                    *number = rounded;
+                }
+            }
+        }
+        Value::Array(values) => values.iter_mut().for_each(self::normalize),
+        Value::Object(map) => {
+            for (key, value) in map.iter_mut() {
+                match value {
+                    Value::String(string) if DATE_KEYS.contains(&key.as_str()) => {
+                        if let Ok(date) = DateTime::parse_from_rfc3339(string) {
+                            *string = date
+                                .with_timezone(&Utc)
+                                .to_rfc3339_opts(SecondsFormat::Millis, true);
+                        }
+                    }
+                    value => self::normalize(value),
+                }
+            }
+        }
+        Value::Null | Value::Bool(_) | Value::String(_) => {}
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    use crate::models::annotation::{Annotation, AnnotationMetadata};
+    use crate::models::entry::Entry;
+
+    // Tests that keys are sorted and floats and dates are written with a fixed precision.
+    #[test]
+    fn normalize_values() {
+        let value = serde_json::json!({
+            "title": "The Art Spirit",
+            "progress": 0.333_333_333_3,
+            "last_opened": "2021-11-02T18:04:45.184863090Z",
+            "annotations": [{ "modified": "2021-11-02T18:04:45Z", "count": 2 }],
+        });
+
+        assert_eq!(
+            serde_json::to_string(&to_value(&value).unwrap()).unwrap(),
+            r#"{"annotations":[{"count":2,"modified":"2021-11-02T18:04:45.000Z"}],"last_opened":"2021-11-02T18:04:45.184Z","progress":0.333333,"title":"The Art Spirit"}"#
+        );
+    }
+
+    // Tests that annotations at the same location are ordered by their id.
+    #[test]
+    fn sort_by_location_and_id() {
+        let annotation = |id: &str, location: &str| Annotation {
+            metadata: AnnotationMetadata {
+                id: id.to_string(),
+                location: location.to_string(),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let entry = Entry {
+            annotations: vec![
+                annotation("b", "2"),
+                annotation("c", "1"),
+                annotation("a", "2"),
+            ],
+            ..Default::default()
+        };
+
+        let mut entries = Entries::default();
+        entries.insert(String::new(), entry);
+
+        sort_annotations(&mut entries);
+
+        let ids: Vec<&str> = entries[""]
+            .annotations
+            .iter()
+            .map(|annotation| annotation.metadata.id.as_str())
+            .collect();
+
+        assert_eq!(ids, ["c", "a", "b"]);
+    }
+}
@@ -0,0 +1,456 @@
+//! Defines the [`EpubExporter`] for exporting annotations as EPUBs that can be read on an
+//! e-reader.
+//!
+//! Each EPUB is a "book of highlights" with one chapter per book. A chapter lists the book's
+//! annotations in order, split into sections by the chapter of the book they were made in:
+//!
+//! ```plaintext
+//! [author-title].epub
+//!  │
+//!  ├── mimetype
+//!  ├── META-INF
+//!  │    └── container.xml
+//!  └── OEBPS
+//!       ├── content.opf
+//!       ├── nav.xhtml
+//!       ├── style.css
+//!       ├── book-001.xhtml
+//!       └── ...
+//! ```
+
+use std::borrow::Cow;
+use std::collections::HashSet;
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+
+use quick_xml::escape::escape;
+
+use crate::conflict;
+use crate::models::annotation::Annotation;
+use crate::models::datetime::DateTimeUtc;
+use crate::models::entry::{Entries, Entry};
+use crate::pack::zip::ZipWriter;
+use crate::result::Result;
+
+use super::exporter::Exporter;
+use super::{ExportFormat, ExportOptions};
+
+/// The filename of a single-file EPUB export.
+const SINGLE_FILE_NAME: &str = "library.epub";
+
+/// The title of a single-file EPUB export.
+const LIBRARY_TITLE: &str = "Annotations";
+
+/// The contents of an EPUB's `mimetype` file. It must be the archive's first file and be stored
+/// uncompressed.
+const MIMETYPE: &str = "application/epub+zip";
+
+/// The contents of an EPUB's `META-INF/container.xml` file, pointing to its package document.
+const CONTAINER: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<container version="1.0" xmlns="urn:oasis:names:tc:opendocument:xmlns:container">
+  <rootfiles>
+    <rootfile full-path="OEBPS/content.opf" media-type="application/oebps-package+xml"/>
+  </rootfiles>
+</container>
+"#;
+
+/// The stylesheet shared by all of an EPUB's chapters.
+const STYLESHEET: &str = "\
+h1 { margin-bottom: 0; }
+p.author { font-style: italic; margin-top: 0.25em; }
+blockquote { margin: 1.5em 0 0.5em 0; padding-left: 1em; border-left: 0.2em solid #999; }
+p.notes { margin: 0.5em 0 0 1.2em; }
+p.tags { margin: 0.25em 0 0 1.2em; font-size: 0.85em; color: #666; }
+";
+
+/// An [`Exporter`] that writes each book's annotations into an EPUB. Its name is `epub`.
+///
+/// Each EPUB is named after [`ExportOptions::directory_template`], or
+/// [`ExportOptions::output_template`] if it's set, with an `.epub` extension e.g.
+/// `Robert Henri - The Art Spirit.epub`.
+///
+/// If [`ExportOptions::single_file`] is set, all books are written into a single `library.epub`
+/// instead, one chapter per book. If [`ExportOptions::filename`] is set, it's written to a file
+/// with that name instead.
+///
+/// Compression, [`ExportOptions::archive`], [`ExportOptions::flat`], the filename templates and
+/// [`ExportOptions::include_covers`] have no effect.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct EpubExporter;
+
+impl Exporter for EpubExporter {
+    fn name(&self) -> &str {
+        ExportFormat::Epub.name()
+    }
+
+    /// # Errors
+    ///
+    /// Will return `Err` if:
+    /// * Any IO errors are encountered.
+    /// * A filename template cannot be rendered.
+    fn export(&self, entries: &Entries, destination: &Path, options: &ExportOptions) -> Result<()> {
+        std::fs::create_dir_all(destination)?;
+
+        if options.single_file {
+            let filename = options.filename.as_deref().unwrap_or(SINGLE_FILE_NAME);
+            let entries: Vec<&Entry> = entries.sorted(options.sort_books).iter().copied().collect();
+            let epub = self::build("urn:readstor:library", LIBRARY_TITLE, None, &entries)?;
+
+            conflict::write(&destination.join(filename), epub, options.on_conflict)?;
+
+            return Ok(());
+        }
+
+        let (directory_template, _, _) = super::templates(options)?;
+
+        // Keeps track of all the files written during this export to catch entries that render to
+        // the same filename.
+        let mut written = HashSet::new();
+
+        for entry in entries.sorted(options.sort_books) {
+            let stem = if let Some(template) = &options.output_template {
+                super::render_path(template, entry)?
+            } else {
+                super::render_directory_name(&directory_template, entry)?
+            };
+
+            let path = PathBuf::from(format!("{stem}.epub"));
+
+            if !written.insert(path.clone()) {
+                tracing::warn!(
+                    "skipped exporting '{}' as its filename was already used by another book",
+                    entry.book.title
+                );
+                continue;
+            }
+
+            let path = destination.join(path);
+
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+
+            let epub = self::build(
+                &format!("urn:readstor:{}", entry.book.metadata.id),
+                &entry.book.title,
+                Some(&entry.book.author),
+                &[entry],
+            )?;
+
+            conflict::write(&path, epub, options.on_conflict)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Builds an EPUB with one chapter per entry and returns its bytes.
+///
+/// # Arguments
+///
+/// * `identifier` - The EPUB's unique identifier.
+/// * `title` - The EPUB's title.
+/// * `author` - The EPUB's author, if any.
+/// * `entries` - The entries to write, one chapter each.
+///
+/// # Errors
+///
+/// Will return `Err` if the EPUB cannot be archived.
+fn build(
+    identifier: &str,
+    title: &str,
+    author: Option<&str>,
+    entries: &[&Entry],
+) -> Result<Vec<u8>> {
+    let chapters: Vec<(String, &Entry)> = entries
+        .iter()
+        .enumerate()
+        .map(|(index, entry)| (format!("book-{:03}", index + 1), *entry))
+        .collect();
+
+    let mut writer = ZipWriter::new(Vec::new());
+
+    writer.add_stored("mimetype", MIMETYPE.as_bytes())?;
+    writer.add("META-INF/container.xml", CONTAINER.as_bytes())?;
+    writer.add(
+        "OEBPS/content.opf",
+        self::package(identifier, title, author, entries, &chapters).as_bytes(),
+    )?;
+    writer.add("OEBPS/nav.xhtml", self::nav(title, &chapters).as_bytes())?;
+    writer.add("OEBPS/style.css", STYLESHEET.as_bytes())?;
+
+    for (id, entry) in &chapters {
+        writer.add(
+            &format!("OEBPS/{id}.xhtml"),
+            self::chapter(entry).as_bytes(),
+        )?;
+    }
+
+    writer.finish()
+}
+
+/// Returns an EPUB's package document listing its metadata and files.
+///
+/// The EPUB's modified date is the date its most recently modified annotation was modified so
+/// re-exporting unchanged annotations produces the same document.
+///
+/// # Arguments
+///
+/// * `identifier` - The EPUB's unique identifier.
+/// * `title` - The EPUB's title.
+/// * `author` - The EPUB's author, if any.
+/// * `entries` - The EPUB's entries.
+/// * `chapters` - The id of each chapter and its entry.
+fn package(
+    identifier: &str,
+    title: &str,
+    author: Option<&str>,
+    entries: &[&Entry],
+    chapters: &[(String, &Entry)],
+) -> String {
+    let modified = entries
+        .iter()
+        .flat_map(|entry| &entry.annotations)
+        .map(|annotation| *annotation.metadata.modified)
+        .max()
+        .map(DateTimeUtc::from)
+        .unwrap_or_default();
+
+    let creator = author
+        .map(|author| format!("\n    <dc:creator>{}</dc:creator>", escape(author)))
+        .unwrap_or_default();
+
+    // Writing to a `String` can't fail.
+    let mut manifest = String::new();
+    let mut spine = String::new();
+
+    for (id, _) in chapters {
+        let _ = write!(
+            manifest,
+            "\n    <item id=\"{id}\" href=\"{id}.xhtml\" media-type=\"application/xhtml+xml\"/>"
+        );
+        let _ = write!(spine, "\n    <itemref idref=\"{id}\"/>");
+    }
+
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<package xmlns="http://www.idpf.org/2007/opf" version="3.0" unique-identifier="identifier">
+  <metadata xmlns:dc="http://purl.org/dc/elements/1.1/">
+    <dc:identifier id="identifier">{identifier}</dc:identifier>
+    <dc:title>{title}</dc:title>{creator}
+    <dc:language>en</dc:language>
+    <meta property="dcterms:modified">{modified}</meta>
+  </metadata>
+  <manifest>
+    <item id="nav" href="nav.xhtml" media-type="application/xhtml+xml" properties="nav"/>
+    <item id="style" href="style.css" media-type="text/css"/>{manifest}
+  </manifest>
+  <spine>{spine}
+  </spine>
+</package>
+"#,
+        identifier = escape(identifier),
+        title = escape(title),
+        modified = self::format_modified(modified),
+    )
+}
+
+/// Returns an EPUB's navigation document linking to each chapter.
+///
+/// # Arguments
+///
+/// * `title` - The EPUB's title.
+/// * `chapters` - The id of each chapter and its entry.
+fn nav(title: &str, chapters: &[(String, &Entry)]) -> String {
+    let mut items = String::new();
+
+    for (id, entry) in chapters {
+        let _ = write!(
+            items,
+            "\n        <li><a href=\"{id}.xhtml\">{}</a></li>",
+            escape(&entry.book.title)
+        );
+    }
+
+    self::xhtml(
+        title,
+        &format!(
+            r#"    <nav epub:type="toc" id="toc">
+      <h1>{}</h1>
+      <ol>{items}
+      </ol>
+    </nav>"#,
+            escape(title)
+        ),
+    )
+}
+
+/// Returns a chapter listing a book's annotations. A heading is added each time the chapter of the
+/// book the annotations were made in changes.
+///
+/// # Arguments
+///
+/// * `entry` - The entry to write.
+fn chapter(entry: &Entry) -> String {
+    let mut body = format!(
+        "    <section epub:type=\"chapter\">\n      <h1>{}</h1>\n      <p class=\"author\">{}</p>\n",
+        escape(&entry.book.title),
+        escape(&entry.book.author),
+    );
+
+    let mut chapter = None;
+
+    for annotation in &entry.annotations {
+        if annotation.chapter.is_some() && annotation.chapter != chapter {
+            chapter.clone_from(&annotation.chapter);
+
+            if let Some(chapter) = &chapter {
+                let _ = writeln!(body, "      <h2>{}</h2>", escape(chapter));
+            }
+        }
+
+        body.push_str(&self::annotation(annotation));
+    }
+
+    body.push_str("    </section>");
+
+    self::xhtml(&entry.book.title, &body)
+}
+
+/// Returns an annotation's highlight followed by its notes and tags, if any.
+///
+/// # Arguments
+///
+/// * `annotation` - The annotation to write.
+fn annotation(annotation: &Annotation) -> String {
+    let mut html = format!(
+        "      <blockquote>\n{}      </blockquote>\n",
+        self::paragraphs(&annotation.body, None)
+    );
+
+    if !annotation.notes.trim().is_empty() {
+        html.push_str(&self::paragraphs(&annotation.notes, Some("notes")));
+    }
+
+    if !annotation.tags.is_empty() {
+        let tags: Vec<Cow<'_, str>> = annotation.tags.iter().map(escape).collect();
+
+        let _ = writeln!(html, "      <p class=\"tags\">{}</p>", tags.join(" "));
+    }
+
+    html
+}
+
+/// Splits text into paragraphs, one per non-empty line.
+///
+/// # Arguments
+///
+/// * `text` - The text to split.
+/// * `class` - The class to set on each paragraph, if any.
+fn paragraphs(text: &str, class: Option<&str>) -> String {
+    let class = class
+        .map(|class| format!(" class=\"{class}\""))
+        .unwrap_or_default();
+
+    let mut html = String::new();
+
+    for line in text.lines().map(str::trim).filter(|line| !line.is_empty()) {
+        let _ = writeln!(html, "        <p{class}>{}</p>", escape(line));
+    }
+
+    html
+}
+
+/// Wraps a body in an XHTML document.
+///
+/// # Arguments
+///
+/// * `title` - The document's title.
+/// * `body` - The document's body.
+fn xhtml(title: &str, body: &str) -> String {
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE html>
+<html xmlns="http://www.w3.org/1999/xhtml" xmlns:epub="http://www.idpf.org/2007/ops" lang="en" xml:lang="en">
+  <head>
+    <meta charset="UTF-8"/>
+    <title>{}</title>
+    <link rel="stylesheet" type="text/css" href="style.css"/>
+  </head>
+  <body>
+{body}
+  </body>
+</html>
+"#,
+        escape(title)
+    )
+}
+
+/// Formats a date as required by an EPUB's `dcterms:modified` property e.g.
+/// `2024-01-01T12:00:00Z`.
+///
+/// # Arguments
+///
+/// * `date` - The date to format.
+fn format_modified(date: DateTimeUtc) -> String {
+    date.format("%Y-%m-%dT%H:%M:%SZ").to_string()
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    use crate::models::annotation::AnnotationMetadata;
+    use crate::models::book::Book;
+
+    fn entry() -> Entry {
+        Entry {
+            book: Book {
+                title: "The Art Spirit".to_string(),
+                author: "Robert Henri".to_string(),
+                ..Default::default()
+            },
+            annotations: vec![
+                Annotation {
+                    body: "Art when really understood is the province of every human being."
+                        .to_string(),
+                    notes: "On <art> & life".to_string(),
+                    chapter: Some("Introduction".to_string()),
+                    tags: ["#art".to_string()].into(),
+                    metadata: AnnotationMetadata::default(),
+                    ..Default::default()
+                },
+                Annotation {
+                    body: "Find out what you really like if you can.".to_string(),
+                    chapter: Some("Introduction".to_string()),
+                    ..Default::default()
+                },
+            ],
+        }
+    }
+
+    // Tests that a chapter escapes its text and only adds a heading when the chapter changes.
+    #[test]
+    fn chapter() {
+        let chapter = super::chapter(&entry());
+
+        assert_eq!(chapter.matches("<h2>Introduction</h2>").count(), 1);
+        assert_eq!(chapter.matches("<blockquote>").count(), 2);
+        assert!(chapter.contains("<p class=\"notes\">On &lt;art&gt; &amp; life</p>"));
+        assert!(chapter.contains("<p class=\"tags\">#art</p>"));
+    }
+
+    // Tests that the `mimetype` file is stored uncompressed at the start of the archive.
+    #[test]
+    fn mimetype_first() {
+        let entry = entry();
+        let epub = build("urn:readstor:test", "Test", None, &[&entry]).unwrap();
+
+        // The local file header is 30 bytes followed by the filename and then its contents.
+        assert_eq!(&epub[0..4], &[0x50, 0x4b, 0x03, 0x04]);
+        assert_eq!(u16::from_le_bytes([epub[8], epub[9]]), 0);
+        assert_eq!(&epub[30..38], b"mimetype");
+        assert_eq!(&epub[38..38 + MIMETYPE.len()], MIMETYPE.as_bytes());
+    }
+}
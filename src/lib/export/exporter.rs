@@ -0,0 +1,199 @@
+//! Defines the [`Exporter`] trait and a registry to select exporters by name.
+//!
+//! The built-in exporters are [`JsonExporter`], [`JsonLinesExporter`] and [`EpubExporter`]. New
+//! formats, including ones defined outside of this crate, are added by implementing [`Exporter`]
+//! and registering them:
+//!
+//! ```
+//! use std::path::Path;
+//!
+//! use lib::export::exporter::{Exporter, Exporters};
+//! use lib::export::ExportOptions;
+//! use lib::models::entry::Entries;
+//! use lib::result::Result;
+//!
+//! struct CountExporter;
+//!
+//! impl Exporter for CountExporter {
+//!     fn name(&self) -> &str {
+//!         "count"
+//!     }
+//!
+//!     fn export(&self, entries: &Entries, destination: &Path, _: &ExportOptions) -> Result<()> {
+//!         std::fs::write(destination.join("count.txt"), entries.len().to_string())?;
+//!
+//!         Ok(())
+//!     }
+//! }
+//!
+//! let mut exporters = Exporters::default();
+//! exporters.register(CountExporter);
+//!
+//! assert_eq!(exporters.names().collect::<Vec<_>>(), ["count", "epub", "json", "jsonl"]);
+//! ```
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use crate::models::entry::Entries;
+use crate::result::{Error, Result};
+
+use super::epub::EpubExporter;
+use super::{canonical, redact, ExportOptions, JsonExporter, JsonLinesExporter};
+
+/// A trait for writing entries to disk in a specific format.
+pub trait Exporter {
+    /// Returns the exporter's name. This is used to select the exporter e.g. `json`.
+    fn name(&self) -> &str;
+
+    /// Writes entries to an output directory.
+    ///
+    /// The entries have already been redacted and, if [`ExportOptions::canonical_json`] is set,
+    /// had their annotations sorted. See [`Exporters::run()`].
+    ///
+    /// # Arguments
+    ///
+    /// * `entries` - The entries to export.
+    /// * `destination` - The output directory.
+    /// * `options` - The export options.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the export fails.
+    fn export(&self, entries: &Entries, destination: &Path, options: &ExportOptions) -> Result<()>;
+}
+
+/// A registry of [`Exporter`]s keyed by their names.
+///
+/// The default registry contains all built-in exporters.
+pub struct Exporters(BTreeMap<String, Box<dyn Exporter>>);
+
+impl Default for Exporters {
+    fn default() -> Self {
+        let mut exporters = Self(BTreeMap::new());
+
+        exporters.register(JsonExporter);
+        exporters.register(JsonLinesExporter);
+        exporters.register(EpubExporter);
+
+        exporters
+    }
+}
+
+impl std::fmt::Debug for Exporters {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("Exporters")
+            .field(&self.0.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+impl Exporters {
+    /// Registers an [`Exporter`], replacing any exporter previously registered under its name.
+    ///
+    /// # Arguments
+    ///
+    /// * `exporter` - The exporter to register.
+    pub fn register<E>(&mut self, exporter: E)
+    where
+        E: Exporter + 'static,
+    {
+        self.0
+            .insert(exporter.name().to_owned(), Box::new(exporter));
+    }
+
+    /// Returns the [`Exporter`] registered under a name.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The exporter's name.
+    #[must_use]
+    pub fn get(&self, name: &str) -> Option<&dyn Exporter> {
+        self.0.get(name).map(AsRef::as_ref)
+    }
+
+    /// Returns an iterator over the names of all registered exporters, sorted alphabetically.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.0.keys().map(String::as_str)
+    }
+
+    /// Exports data with the [`Exporter`] registered under a name.
+    ///
+    /// If [`ExportOptions::redact`] is set, the selected fields are redacted before anything is
+    /// written. See [`redact::run()`]. If [`ExportOptions::canonical_json`] is set, each entry's
+    /// annotations are sorted first. See [`canonical`] for more information.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The exporter's name.
+    /// * `entries` - The entries to export.
+    /// * `destination` - The output directory.
+    /// * `options` - The export options.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if:
+    /// * No exporter is registered under the name.
+    /// * The exporter encounters any errors.
+    pub fn run<O>(
+        &self,
+        name: &str,
+        entries: &mut Entries,
+        destination: &Path,
+        options: O,
+    ) -> Result<()>
+    where
+        O: Into<ExportOptions>,
+    {
+        let options: ExportOptions = options.into();
+
+        let exporter = self.get(name).ok_or_else(|| Error::ExportUnknownFormat {
+            name: name.to_owned(),
+            supported: self.names().collect::<Vec<_>>().join(", "),
+        })?;
+
+        redact::run(entries, &options.redact, options.redact_mode);
+
+        if options.canonical_json {
+            canonical::sort_annotations(entries);
+        }
+
+        exporter.export(entries, destination, &options)
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    // Tests that exporters are selected by name and unknown names return an error.
+    #[test]
+    fn select_by_name() {
+        let exporters = Exporters::default();
+
+        assert_eq!(exporters.get("json").unwrap().name(), "json");
+        assert_eq!(exporters.get("jsonl").unwrap().name(), "jsonl");
+        assert_eq!(exporters.get("epub").unwrap().name(), "epub");
+        assert!(exporters.get("csv").is_none());
+    }
+
+    // Tests that running an unregistered exporter returns an error listing the registered ones.
+    #[test]
+    fn unknown_exporter() {
+        let exporters = Exporters::default();
+        let mut entries = Entries::default();
+
+        let result = exporters.run(
+            "csv",
+            &mut entries,
+            Path::new("."),
+            ExportOptions::default(),
+        );
+
+        assert!(matches!(
+            result,
+            Err(Error::ExportUnknownFormat { supported, .. }) if supported == "epub, json, jsonl"
+        ));
+    }
+}
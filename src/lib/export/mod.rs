@@ -1,21 +1,121 @@
 //! Defines types for exporting data.
 
-use std::fs::File;
-use std::path::Path;
+pub mod canonical;
+pub mod epub;
+pub mod exporter;
+pub mod redact;
+mod writer;
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
 
 use serde::Serialize;
 
-use crate::contexts::book::BookContext;
-use crate::models::entry::{Entries, Entry};
-use crate::result::Result;
-use crate::strings;
+use crate::conflict::ConflictMode;
+use crate::contexts::entry::EntryContext;
+use crate::models::annotation::Annotation;
+use crate::models::book::Book;
+use crate::models::entry::{BookSortKey, Entries, Entry};
+use crate::render::names::{NamesContext, NamesRender};
+use crate::result::{Error, Result};
+use crate::{strings, utils};
+
+use self::exporter::{Exporter, Exporters};
+use self::redact::{RedactField, RedactMode};
+use self::writer::{ExportWriter, JsonLinesWriter};
 
 /// The default export directory template.
 ///
 /// Outputs `[author] - [book]` e.g. `Robert Henri - The Art Spirit`.
 const DIRECTORY_TEMPLATE: &str = "{{ book.author }} - {{ book.title }}";
 
-/// Exports data as JSON.
+/// The default book filename template.
+///
+/// Outputs `book.json`.
+const BOOK_FILENAME_TEMPLATE: &str = "book.json";
+
+/// The default annotations filename template.
+///
+/// Outputs `annotations.json`.
+const ANNOTATIONS_FILENAME_TEMPLATE: &str = "annotations.json";
+
+/// The default book filename template for flat exports.
+///
+/// Outputs `[author]-[title].book.json` e.g. `robert-henri-the-art-spirit.book.json`.
+const FLAT_BOOK_FILENAME_TEMPLATE: &str =
+    "{{ book.slugs.author }}-{{ book.slugs.title }}.book.json";
+
+/// The default annotations filename template for flat exports.
+///
+/// Outputs `[author]-[title].annotations.json` e.g.
+/// `robert-henri-the-art-spirit.annotations.json`.
+const FLAT_ANNOTATIONS_FILENAME_TEMPLATE: &str =
+    "{{ book.slugs.author }}-{{ book.slugs.title }}.annotations.json";
+
+/// The default export directory template if titles are redacted.
+///
+/// Outputs `[author] - [book-id]` e.g. `Robert Henri - 1969AF0ECA8AE4965029A34316813924`.
+const REDACTED_DIRECTORY_TEMPLATE: &str = "{{ book.author }} - {{ book.metadata.id }}";
+
+/// The default book filename template for flat exports if titles are redacted.
+///
+/// Outputs `[author]-[book-id].book.json` e.g.
+/// `robert-henri-1969AF0ECA8AE4965029A34316813924.book.json`.
+const REDACTED_FLAT_BOOK_FILENAME_TEMPLATE: &str =
+    "{{ book.slugs.author }}-{{ book.metadata.id }}.book.json";
+
+/// The default annotations filename template for flat exports if titles are redacted.
+///
+/// Outputs `[author]-[book-id].annotations.json` e.g.
+/// `robert-henri-1969AF0ECA8AE4965029A34316813924.annotations.json`.
+const REDACTED_FLAT_ANNOTATIONS_FILENAME_TEMPLATE: &str =
+    "{{ book.slugs.author }}-{{ book.metadata.id }}.annotations.json";
+
+/// The cover filename, without its extension.
+///
+/// Outputs `cover` e.g. `cover.jpg`.
+const COVER_FILENAME: &str = "cover";
+
+/// The cover filename template for flat exports, without its extension.
+///
+/// Outputs `[author]-[title].cover` e.g. `robert-henri-the-art-spirit.cover.jpg`.
+const FLAT_COVER_FILENAME_TEMPLATE: &str = "{{ book.slugs.author }}-{{ book.slugs.title }}.cover";
+
+/// The filename of a single-file export.
+const SINGLE_FILE_NAME: &str = "library.json";
+
+/// The version of the exported JSON format. Every exported book is stamped with it as
+/// `format_version`. Exports written before the format was versioned are version `1`. See
+/// [`migrate`][migrate] for upgrading older exports.
+///
+/// [migrate]: crate::migrate
+pub const FORMAT_VERSION: u64 = 3;
+
+/// Exports data with the exporter selected by [`ExportOptions::format`]. See [`JsonExporter`] and
+/// [`JsonLinesExporter`] for more information.
+///
+/// To export with an exporter that isn't built-in, register it with [`Exporters::register()`] and
+/// call [`Exporters::run()`] instead.
+///
+/// # Arguments
+///
+/// * `entries` - The entries to export.
+/// * `destination` - The output directory.
+/// * `options` - The export options.
+///
+/// # Errors
+///
+/// Will return `Err` if the exporter encounters any errors.
+pub fn run<O>(entries: &mut Entries, destination: &Path, options: O) -> Result<()>
+where
+    O: Into<ExportOptions>,
+{
+    let options: ExportOptions = options.into();
+
+    Exporters::default().run(options.format.name(), entries, destination, options)
+}
+
+/// An [`Exporter`] that writes data as JSON. Its name is `json`.
 ///
 /// The output strucutre is as follows:
 ///
@@ -23,69 +123,345 @@ const DIRECTORY_TEMPLATE: &str = "{{ book.author }} - {{ book.title }}";
 /// [output-directory]
 ///  │
 ///  ├── [author-title]
-///  │    ├── book.json
-///  │    └── annotations.json
+///  │    ├── book.json <- Customizeable
+///  │    ├── annotations.json <- Customizeable
+///  │    └── cover.jpg <- If `include_covers` is set
 ///  │
 ///  ├── [author-title]
 ///  │    └── ...
 ///  └── ...
 /// ```
 ///
+/// If [`ExportOptions::flat`] is set, all files are written directly into the output directory:
+///
+/// ```plaintext
+/// [output-directory]
+///  │
+///  ├── [author-title].book.json <- Customizeable
+///  ├── [author-title].annotations.json <- Customizeable
+///  ├── [author-title].cover.jpg <- If `include_covers` is set
+///  └── ...
+/// ```
+///
+/// If [`ExportOptions::output_template`] is set, each book's files are named after its rendered
+/// path instead, keeping any `/`s:
+///
+/// ```plaintext
+/// [output-directory]
+///  │
+///  ├── [output-template].book.json
+///  ├── [output-template].annotations.json
+///  ├── [output-template].cover.jpg <- If `include_covers` is set
+///  └── ...
+/// ```
+///
+/// All templates share their context with a book's filename when rendering templates. See
+/// [`NamesContext::book()`].
+///
+/// If [`ExportOptions::include_covers`] is set, each book's cover image is copied alongside its
+/// `book.json`, keeping the image's extension. Books without a [`Book::cover_path`] are exported
+/// without one. Covers are never compressed unless they're written into an archive.
+///
+/// If [`ExportOptions::compression`] is set, each file's name is suffixed with the compression's
+/// extension e.g. `book.json.gz`. If [`ExportOptions::archive`] is set, the same structure is
+/// written into a single `readstor-export-[YYYY-MM-DD-HHMMSS].tar` archive in the output directory
+/// instead, where the compression is applied to the archive as a whole e.g. `*.tar.gz`.
+///
+/// If [`ExportOptions::single_file`] is set, all entries are written into a single `library.json`
+/// file instead. The directory and filename templates, [`ExportOptions::flat`] and
+/// [`ExportOptions::include_covers`] have no effect.
+///
+/// If [`ExportOptions::filename`] is set, single-file exports are written to a file with that name
+/// instead.
+///
+/// Entries are redacted and sorted by [`Exporters::run()`] before being passed to the exporter.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct JsonExporter;
+
+impl Exporter for JsonExporter {
+    fn name(&self) -> &str {
+        ExportFormat::Json.name()
+    }
+
+    /// # Errors
+    ///
+    /// Will return `Err` if:
+    /// * Any IO errors are encountered.
+    /// * [`serde_json`][serde-json] encounters any errors.
+    /// * The book and annotations filenames are identical.
+    ///
+    /// [serde-json]: https://docs.rs/serde_json/latest/serde_json/
+    fn export(&self, entries: &Entries, destination: &Path, options: &ExportOptions) -> Result<()> {
+        let redact_titles = options.redact.contains(&RedactField::Titles);
+
+        if options.single_file {
+            let mut writer = self::writer(destination, options)?;
+            let entries: Vec<ExportEntry<'_>> = entries
+                .sorted(options.sort_books)
+                .into_iter()
+                .map(ExportEntry::from)
+                .collect();
+
+            let filename = options.filename.as_deref().unwrap_or(SINGLE_FILE_NAME);
+
+            self::write(&mut writer, Path::new(filename), &entries, options)?;
+            writer.finish()?;
+
+            return Ok(());
+        }
+
+        let (directory_template, book_filename_template, annotations_filename_template) =
+            self::templates(options)?;
+
+        let mut writer = self::writer(destination, options)?;
+
+        // Keeps track of all the files written during this export to catch entries that render to the
+        // same filename. This is most likely to happen with flat exports.
+        let mut written = HashSet::new();
+
+        for entry in entries.sorted(options.sort_books) {
+            let (book_json, annotations_json, cover) =
+                if let Some(template) = &options.output_template {
+                    // -> [output-template] e.g. Robert Henri/the-art-spirit
+                    let stem = self::render_path(template, entry)?;
+
+                    (
+                        // -> [output-directory]/[output-template].book.json
+                        PathBuf::from(format!("{stem}.book.json")),
+                        // -> [output-directory]/[output-template].annotations.json
+                        PathBuf::from(format!("{stem}.annotations.json")),
+                        // -> [output-directory]/[output-template].cover
+                        PathBuf::from(format!("{stem}.cover")),
+                    )
+                } else {
+                    let (item, cover) = if options.flat {
+                        (
+                            // -> [output-directory]
+                            PathBuf::new(),
+                            self::render_directory_name(FLAT_COVER_FILENAME_TEMPLATE, entry)?,
+                        )
+                    } else {
+                        (
+                            // -> [output-directory]/[author-title]
+                            PathBuf::from(self::render_directory_name(&directory_template, entry)?),
+                            COVER_FILENAME.to_string(),
+                        )
+                    };
+
+                    (
+                        // -> [output-directory]/[author-title]/book.json
+                        item.join(self::render_filename(&book_filename_template, entry)?),
+                        // -> [output-directory]/[author-title]/annotation.json
+                        item.join(self::render_filename(
+                            &annotations_filename_template,
+                            entry,
+                        )?),
+                        // -> [output-directory]/[author-title]/cover
+                        item.join(cover),
+                    )
+                };
+
+            if book_json == annotations_json {
+                return Err(Error::ExportFilenameCollision {
+                    path: destination.join(book_json).display().to_string(),
+                });
+            }
+
+            let is_new_book_json = written.insert(book_json.clone());
+            let is_new_annotations_json = written.insert(annotations_json.clone());
+
+            if !(is_new_book_json && is_new_annotations_json) {
+                tracing::warn!(
+                    "skipped exporting '{}' as its filenames were already used by another book",
+                    entry.book.title
+                );
+                continue;
+            }
+
+            self::write(
+                &mut writer,
+                &book_json,
+                &VersionedBook::from(&entry.book),
+                options,
+            )?;
+            self::write(&mut writer, &annotations_json, &entry.annotations, options)?;
+
+            // A cover would reveal a redacted title.
+            if options.include_covers && !redact_titles {
+                if let Some(source) = &entry.book.cover_path {
+                    let cover = self::with_cover_extension(&cover, source);
+
+                    if written.insert(cover.clone()) {
+                        writer.copy(&cover, source)?;
+                    }
+                }
+            }
+        }
+
+        writer.finish()?;
+
+        Ok(())
+    }
+}
+
+/// Returns the directory, book filename and annotations filename templates respectively. Any
+/// templates set in the options are validated, otherwise the defaults are returned.
+///
+/// # Arguments
+///
+/// * `options` - The export options.
+///
+/// # Errors
+///
+/// Will return `Err` if any templates set in the options have syntax errors or are referencing
+/// non-existent fields.
+fn templates(options: &ExportOptions) -> Result<(String, String, String)> {
+    let redact_titles = options.redact.contains(&RedactField::Titles);
+
+    let directory_template = if let Some(template) = options.directory_template.clone() {
+        self::validate_template(&template)?;
+        template
+    } else if redact_titles {
+        REDACTED_DIRECTORY_TEMPLATE.to_string()
+    } else {
+        DIRECTORY_TEMPLATE.to_string()
+    };
+
+    let book_filename_template = if let Some(template) = options.book_filename_template.clone() {
+        self::validate_template(&template)?;
+        template
+    } else if options.flat && redact_titles {
+        REDACTED_FLAT_BOOK_FILENAME_TEMPLATE.to_string()
+    } else if options.flat {
+        FLAT_BOOK_FILENAME_TEMPLATE.to_string()
+    } else {
+        BOOK_FILENAME_TEMPLATE.to_string()
+    };
+
+    let annotations_filename_template =
+        if let Some(template) = options.annotations_filename_template.clone() {
+            self::validate_template(&template)?;
+            template
+        } else if options.flat && redact_titles {
+            REDACTED_FLAT_ANNOTATIONS_FILENAME_TEMPLATE.to_string()
+        } else if options.flat {
+            FLAT_ANNOTATIONS_FILENAME_TEMPLATE.to_string()
+        } else {
+            ANNOTATIONS_FILENAME_TEMPLATE.to_string()
+        };
+
+    if let Some(template) = &options.output_template {
+        self::validate_template(template)?;
+    }
+
+    Ok((
+        directory_template,
+        book_filename_template,
+        annotations_filename_template,
+    ))
+}
+
+/// Returns an [`ExportWriter`] that writes either individual files or a single archive.
+///
 /// # Arguments
 ///
-/// * `entries` - The entries to export.
 /// * `destination` - The output directory.
 /// * `options` - The export options.
 ///
 /// # Errors
 ///
+/// Will return `Err` if any IO errors are encountered.
+fn writer(destination: &Path, options: &ExportOptions) -> Result<ExportWriter> {
+    if options.archive {
+        ExportWriter::archive(destination, options.compression)
+    } else {
+        Ok(ExportWriter::files(
+            destination,
+            options.compression,
+            options.on_conflict,
+        ))
+    }
+}
+
+/// Serializes a value to JSON and writes it, as canonical JSON if
+/// [`ExportOptions::canonical_json`] is set.
+///
+/// # Arguments
+///
+/// * `writer` - The writer to write with.
+/// * `path` - The file's path relative to the output directory.
+/// * `value` - The value to serialize.
+/// * `options` - The export options.
+///
+/// # Errors
+///
 /// Will return `Err` if:
 /// * Any IO errors are encountered.
 /// * [`serde_json`][serde-json] encounters any errors.
 ///
 /// [serde-json]: https://docs.rs/serde_json/latest/serde_json/
-pub fn run<O>(entries: &mut Entries, destination: &Path, options: O) -> Result<()>
+fn write<T>(
+    writer: &mut ExportWriter,
+    path: &Path,
+    value: &T,
+    options: &ExportOptions,
+) -> Result<()>
 where
-    O: Into<ExportOptions>,
+    T: Serialize + ?Sized,
 {
-    let options: ExportOptions = options.into();
-
-    let directory_template = if let Some(template) = options.directory_template {
-        self::validate_template(&template)?;
-        template
+    if options.canonical_json {
+        writer.write(path, &canonical::to_value(value)?)
     } else {
-        DIRECTORY_TEMPLATE.to_string()
-    };
+        writer.write(path, value)
+    }
+}
 
-    for entry in entries.values() {
-        // -> [author-title]
-        let directory_name = self::render_directory_name(&directory_template, entry)?;
+/// An [`Exporter`] that streams all annotations into a single JSON Lines file, one annotation per
+/// line, each with its book embedded under a `book` key. Its name is `jsonl`.
+///
+/// The file is named `readstor-export-[YYYY-MM-DD-HHMMSS].jsonl` unless
+/// [`ExportOptions::filename`] is set. Each line is written as soon as it's serialized so only a
+/// single annotation is held in memory as JSON at a time. The directory and filename templates,
+/// [`ExportOptions::flat`] and [`ExportOptions::archive`] have no effect.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct JsonLinesExporter;
 
-        // -> [output-directory]/[author-title]
-        let item = destination.join(directory_name);
-        // -> [output-directory]/[author-title]/book.json
-        let book_json = item.join("book").with_extension("json");
-        // -> [output-directory]/[author-title]/annotation.json
-        let annotations_json = item.join("annotations").with_extension("json");
+impl Exporter for JsonLinesExporter {
+    fn name(&self) -> &str {
+        ExportFormat::JsonLines.name()
+    }
 
-        std::fs::create_dir_all(&item)?;
+    /// # Errors
+    ///
+    /// Will return `Err` if:
+    /// * Any IO errors are encountered.
+    /// * [`serde_json`][serde-json] encounters any errors.
+    ///
+    /// [serde-json]: https://docs.rs/serde_json/latest/serde_json/
+    fn export(&self, entries: &Entries, destination: &Path, options: &ExportOptions) -> Result<()> {
+        let mut writer = JsonLinesWriter::new(
+            destination,
+            options.filename.as_deref(),
+            options.compression,
+        )?;
 
-        if !options.overwrite_existing && book_json.exists() {
-            log::debug!("skipped writing {}", book_json.display());
-        } else {
-            let book_json = File::create(book_json)?;
-            serde_json::to_writer_pretty(&book_json, &entry.book)?;
-        }
+        for entry in entries.sorted(options.sort_books) {
+            for annotation in &entry.annotations {
+                let line = AnnotationLine {
+                    annotation,
+                    book: VersionedBook::from(&entry.book),
+                };
 
-        if !options.overwrite_existing && annotations_json.exists() {
-            log::debug!("skipped writing {}", annotations_json.display());
-        } else {
-            let annotations_json = File::create(annotations_json)?;
-            serde_json::to_writer_pretty(&annotations_json, &entry.annotations)?;
+                if options.canonical_json {
+                    writer.write(&canonical::to_value(&line)?)?;
+                } else {
+                    writer.write(&line)?;
+                }
+            }
         }
-    }
 
-    Ok(())
+        writer.finish()
+    }
 }
 
 /// Validates a template by rendering it.
@@ -102,59 +478,271 @@ fn validate_template(template: &str) -> Result<()> {
 
 /// Renders the directory name from a template string and an [`Entry`].
 ///
+/// The template has the same context as a book's filename when rendering templates. See
+/// [`NamesContext::book()`].
+///
 /// # Arguments
 ///
 /// * `template` - The template string to render.
 /// * `entry` - The [`Entry`] providing the template context.
 fn render_directory_name(template: &str, entry: &Entry) -> Result<String> {
-    let context = BookContext::from(&entry.book);
-    let context = ExportContext::from(&context);
+    let entry = EntryContext::from(entry);
+    let context = NamesContext::book(&entry.book, &entry.annotations);
     strings::render_and_sanitize(template, context)
 }
 
+/// Renders a relative path from a template string and an [`Entry`], keeping any `/`s. See
+/// [`NamesRender::render_path()`].
+///
+/// # Arguments
+///
+/// * `template` - The template string to render.
+/// * `entry` - The [`Entry`] providing the template context.
+fn render_path(template: &str, entry: &Entry) -> Result<String> {
+    let entry = EntryContext::from(entry);
+    let context = NamesContext::book(&entry.book, &entry.annotations);
+    NamesRender::render_path(template, context)
+}
+
+/// Renders a filename from a template string and an [`Entry`].
+///
+/// A `.json` extension is appended if the rendered filename doesn't already end with one.
+///
+/// # Arguments
+///
+/// * `template` - The template string to render.
+/// * `entry` - The [`Entry`] providing the template context.
+fn render_filename(template: &str, entry: &Entry) -> Result<String> {
+    let mut filename = self::render_directory_name(template, entry)?;
+
+    if utils::get_file_extension(&filename) != Some("json") {
+        filename.push_str(".json");
+    }
+
+    Ok(filename)
+}
+
+/// Appends the extension of a book's cover image to the cover's path.
+///
+/// JPEGs are always given a `.jpg` extension.
+///
+/// # Arguments
+///
+/// * `cover` - The cover's path, without its extension.
+/// * `source` - The path to the cover image.
+fn with_cover_extension(cover: &Path, source: &Path) -> PathBuf {
+    let mut path = cover.as_os_str().to_owned();
+
+    let extension = utils::get_file_extension(&source).map(str::to_lowercase);
+
+    match extension.as_deref() {
+        Some("jpeg") => path.push(".jpg"),
+        Some(extension) => {
+            path.push(".");
+            path.push(extension);
+        }
+        None => {}
+    }
+
+    PathBuf::from(path)
+}
+
 /// A struct representing options for running exports.
-#[derive(Debug)]
+#[derive(Debug, Default)]
+#[allow(clippy::struct_excessive_bools)]
 pub struct ExportOptions {
     /// The template to use for rendering the export's output directories.
     pub directory_template: Option<String>,
 
-    /// Toggles whether or not to overwrite existing files.
-    pub overwrite_existing: bool,
+    /// The template to use for rendering the export's book filenames.
+    pub book_filename_template: Option<String>,
+
+    /// The template to use for rendering the export's annotations filenames.
+    pub annotations_filename_template: Option<String>,
+
+    /// The template to use for rendering the path of each book's files, relative to the output
+    /// directory and without their extensions. Any `/`s in the rendered path are kept. This takes
+    /// precedence over the directory and filename templates and [`ExportOptions::flat`].
+    pub output_template: Option<String>,
+
+    /// Toggles writing all files directly into the output directory instead of one directory per
+    /// book.
+    pub flat: bool,
+
+    /// The compression to apply to each file, or to the archive if [`ExportOptions::archive`] is
+    /// set.
+    pub compression: Option<Compression>,
+
+    /// Toggles writing all files into a single `tar` archive instead of individual files.
+    pub archive: bool,
+
+    /// Toggles writing all entries into a single `library.json` file instead of two files per
+    /// book.
+    pub single_file: bool,
+
+    /// The order in which books are exported. If multiple books render to the same filenames, the
+    /// first one in this order is kept.
+    pub sort_books: BookSortKey,
+
+    /// What to do when an exported file already exists. This has no effect when writing to an
+    /// archive. See [`ConflictMode`] for more information.
+    pub on_conflict: ConflictMode,
+
+    /// The format to export as. This selects the built-in [`Exporter`] used by [`run()`].
+    pub format: ExportFormat,
+
+    /// Toggles copying each book's cover image alongside its `book.json`. This has no effect on
+    /// single-file and JSON Lines exports.
+    pub include_covers: bool,
+
+    /// The name of the file single-file and JSON Lines exports are written to. Defaults to
+    /// `library.json`, `library.epub` for EPUB exports, and
+    /// `readstor-export-[YYYY-MM-DD-HHMMSS].jsonl` respectively.
+    pub filename: Option<String>,
+
+    /// The fields to redact before exporting. See [`redact::run()`].
+    pub redact: Vec<RedactField>,
+
+    /// How redacted fields are written.
+    pub redact_mode: RedactMode,
+
+    /// Toggles writing canonical JSON so that exports are stable between runs. See [`canonical`].
+    pub canonical_json: bool,
 }
 
-/// An struct representing the template context for exports.
-///
-/// This is primarily used for generating directory names.
+/// An enum representing the available export formats.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// Writes a `book.json` and an `annotations.json` file per book.
+    #[default]
+    Json,
+
+    /// Streams all annotations into a single JSON Lines file, one annotation per line.
+    JsonLines,
+
+    /// Writes an EPUB of each book's annotations.
+    Epub,
+}
+
+impl ExportFormat {
+    /// Returns the name of the format's [`Exporter`] e.g. `json`, `jsonl` or `epub`.
+    #[must_use]
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Json => "json",
+            Self::JsonLines => "jsonl",
+            Self::Epub => "epub",
+        }
+    }
+
+    /// Returns the format that writes files with an extension e.g. `json` or `jsonl`. Returns
+    /// `None` if no format writes files with the extension.
+    ///
+    /// Note that a `json` file is written by [`ExportFormat::Json`] together with
+    /// [`ExportOptions::single_file`].
+    ///
+    /// # Arguments
+    ///
+    /// * `extension` - The file extension, without the leading dot.
+    #[must_use]
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        match extension.to_lowercase().as_str() {
+            "json" => Some(Self::Json),
+            "jsonl" | "ndjson" => Some(Self::JsonLines),
+            "epub" => Some(Self::Epub),
+            _ => None,
+        }
+    }
+}
+
+/// An enum representing the available compression formats for exports.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    /// Compresses files with `gzip`. Appends a `.gz` extension.
+    Gzip,
+
+    /// Compresses files with `zstd`. Appends a `.zst` extension.
+    Zstd,
+}
+
+impl Compression {
+    /// Returns the file extension for the compression format.
+    #[must_use]
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Gzip => "gz",
+            Self::Zstd => "zst",
+        }
+    }
+}
+
+/// A struct representing a book stamped with the [`FORMAT_VERSION`] it was exported in.
 #[derive(Debug, Serialize)]
-struct ExportContext<'a> {
-    book: &'a BookContext<'a>,
+pub(crate) struct VersionedBook<'a> {
+    format_version: u64,
+
+    #[serde(flatten)]
+    book: &'a Book,
 }
 
-impl<'a> From<&'a BookContext<'a>> for ExportContext<'a> {
-    fn from(book: &'a BookContext<'a>) -> Self {
-        Self { book }
+impl<'a> From<&'a Book> for VersionedBook<'a> {
+    fn from(book: &'a Book) -> Self {
+        Self {
+            format_version: FORMAT_VERSION,
+            book,
+        }
     }
 }
 
+/// A struct representing a single entry of a single-file export.
+#[derive(Debug, Serialize)]
+pub(crate) struct ExportEntry<'a> {
+    pub(crate) book: VersionedBook<'a>,
+
+    pub(crate) annotations: &'a [Annotation],
+}
+
+impl<'a> From<&'a Entry> for ExportEntry<'a> {
+    fn from(entry: &'a Entry) -> Self {
+        Self {
+            book: VersionedBook::from(&entry.book),
+            annotations: &entry.annotations,
+        }
+    }
+}
+
+/// A struct representing a single line of a JSON Lines export.
+#[derive(Debug, Serialize)]
+pub(crate) struct AnnotationLine<'a> {
+    #[serde(flatten)]
+    pub(crate) annotation: &'a Annotation,
+
+    pub(crate) book: VersionedBook<'a>,
+}
+
 #[cfg(test)]
 mod test {
 
     use super::*;
 
+    use crate::contexts::book::BookContext;
     use crate::defaults::test::TemplatesDirectory;
     use crate::models::book::Book;
     use crate::render::engine::RenderEngine;
-    use crate::utils;
 
     // Tests that the default template returns no error.
     #[test]
     fn default_template() {
         let book = Book::default();
         let context = BookContext::from(&book);
-        let context = ExportContext { book: &context };
+        let context = NamesContext::book(&context, &[]);
+
+        RenderEngine::default()
+            .render_str(DIRECTORY_TEMPLATE, &context)
+            .unwrap();
 
         RenderEngine::default()
-            .render_str(DIRECTORY_TEMPLATE, context)
+            .render_str(REDACTED_DIRECTORY_TEMPLATE, &context)
             .unwrap();
     }
 
@@ -166,7 +754,7 @@ mod test {
 
         let book = Book::default();
         let context = BookContext::from(&book);
-        let context = ExportContext::from(&context);
+        let context = NamesContext::book(&context, &[]);
 
         RenderEngine::default()
             .render_str(&template, context)
@@ -184,10 +772,84 @@ mod test {
 
         let book = Book::default();
         let context = BookContext::from(&book);
-        let context = ExportContext::from(&context);
+        let context = NamesContext::book(&context, &[]);
 
         RenderEngine::default()
             .render_str(&template, context)
             .unwrap();
     }
+
+    // Tests that the default flat filename templates return no error.
+    #[test]
+    fn default_flat_templates() {
+        validate_template(FLAT_BOOK_FILENAME_TEMPLATE).unwrap();
+        validate_template(FLAT_ANNOTATIONS_FILENAME_TEMPLATE).unwrap();
+        validate_template(REDACTED_FLAT_BOOK_FILENAME_TEMPLATE).unwrap();
+        validate_template(REDACTED_FLAT_ANNOTATIONS_FILENAME_TEMPLATE).unwrap();
+    }
+
+    #[test]
+    fn format_from_extension() {
+        assert_eq!(
+            ExportFormat::from_extension("json"),
+            Some(ExportFormat::Json)
+        );
+        assert_eq!(
+            ExportFormat::from_extension("JSONL"),
+            Some(ExportFormat::JsonLines)
+        );
+        assert_eq!(
+            ExportFormat::from_extension("epub"),
+            Some(ExportFormat::Epub)
+        );
+        assert_eq!(ExportFormat::from_extension("csv"), None);
+    }
+
+    #[test]
+    fn cover_filename() {
+        assert_eq!(
+            with_cover_extension(Path::new("cover"), Path::new("images/cover.JPEG")),
+            PathBuf::from("cover.jpg")
+        );
+        assert_eq!(
+            with_cover_extension(
+                Path::new("robert-henri-the-art-spirit.cover"),
+                Path::new("images/cover.png")
+            ),
+            PathBuf::from("robert-henri-the-art-spirit.cover.png")
+        );
+    }
+
+    // Tests that an output template keeps its `/`s and drops relative components.
+    #[test]
+    fn output_template_path() {
+        let mut entry = Entry::dummy();
+        entry.book.title = "The Art Spirit".to_string();
+        entry.book.author = "Robert Henri".to_string();
+
+        assert_eq!(
+            render_path("{{ book.author }}/{{ book.slugs.title }}", &entry).unwrap(),
+            "Robert Henri/the-art-spirit"
+        );
+        assert_eq!(
+            render_path("../{{ book.slugs.author }}//./notes", &entry).unwrap(),
+            "robert-henri/notes"
+        );
+    }
+
+    // Tests that a `.json` extension is only appended when missing.
+    #[test]
+    fn filename_extension() {
+        let mut entry = Entry::dummy();
+        entry.book.title = "The Art Spirit".to_string();
+
+        assert_eq!(
+            render_filename("{{ book.slugs.title }}.annotations", &entry).unwrap(),
+            "the-art-spirit.annotations.json"
+        );
+        assert_eq!(
+            render_filename("{{ book.slugs.title }}.annotations.json", &entry).unwrap(),
+            "the-art-spirit.annotations.json"
+        );
+    }
 }
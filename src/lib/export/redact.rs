@@ -0,0 +1,155 @@
+//! Defines types for redacting fields from exports.
+//!
+//! Redacting lets an export be shared e.g. to publish reading statistics, without exposing what
+//! was highlighted or read. Dates, styles, tags and locations are always kept.
+
+use crate::models::entry::Entries;
+use crate::models::id;
+
+/// An enum representing the fields that can be redacted from an export.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RedactField {
+    /// Redacts each annotation's notes.
+    Notes,
+
+    /// Redacts each annotation's body and its surrounding context text.
+    Body,
+
+    /// Redacts each book's title and Apple Books asset id, and each annotation's chapter title.
+    Titles,
+}
+
+/// An enum representing how redacted fields are written.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RedactMode {
+    /// Replaces each value with a hash of itself. Identical values hash identically so e.g.
+    /// annotations from the same chapter can still be grouped.
+    #[default]
+    Hash,
+
+    /// Replaces each value with an empty string.
+    Omit,
+}
+
+/// Redacts fields from [`Entries`] in place. Empty values are left empty in either mode.
+///
+/// # Arguments
+///
+/// * `entries` - The entries to redact.
+/// * `fields` - The fields to redact.
+/// * `mode` - How to write the redacted fields.
+pub fn run(entries: &mut Entries, fields: &[RedactField], mode: RedactMode) {
+    if fields.is_empty() {
+        return;
+    }
+
+    let redact = |value: &mut String| self::redact(value, mode);
+
+    for entry in entries.values_mut() {
+        if fields.contains(&RedactField::Titles) {
+            redact(&mut entry.book.title);
+            redact(&mut entry.book.metadata.asset_id);
+        }
+
+        for annotation in &mut entry.annotations {
+            if fields.contains(&RedactField::Body) {
+                redact(&mut annotation.body);
+                redact(&mut annotation.context_text);
+            }
+
+            if fields.contains(&RedactField::Notes) {
+                redact(&mut annotation.notes);
+            }
+
+            if fields.contains(&RedactField::Titles) {
+                // An omitted chapter is removed rather than left as an empty string.
+                annotation.chapter = annotation
+                    .chapter
+                    .take()
+                    .map(|mut chapter| {
+                        redact(&mut chapter);
+                        chapter
+                    })
+                    .filter(|chapter| !chapter.is_empty());
+            }
+        }
+    }
+}
+
+/// Redacts a single value.
+///
+/// # Arguments
+///
+/// * `value` - The value to redact.
+/// * `mode` - How to write the redacted value.
+fn redact(value: &mut String, mode: RedactMode) {
+    if value.is_empty() {
+        return;
+    }
+
+    *value = match mode {
+        RedactMode::Hash => id::hash(&[value]),
+        RedactMode::Omit => String::new(),
+    };
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    use crate::models::entry::Entry;
+
+    fn entries() -> Entries {
+        let mut entry = Entry::dummy();
+        entry.book.title = "The Art Spirit".to_string();
+
+        for annotation in &mut entry.annotations {
+            annotation.body = "Art is the province of every human being.".to_string();
+            annotation.notes = "#art".to_string();
+            annotation.chapter = Some("Introduction".to_string());
+        }
+
+        let mut entries = Entries::default();
+        entries.insert(entry.book.metadata.id.clone(), entry);
+        entries
+    }
+
+    #[test]
+    fn hash_fields() {
+        let mut entries = entries();
+        run(&mut entries, &[RedactField::Body], RedactMode::Hash);
+
+        let entry = entries.values().next().unwrap();
+
+        assert_eq!(entry.book.title, "The Art Spirit");
+
+        for annotation in &entry.annotations {
+            assert_eq!(
+                annotation.body,
+                id::hash(&["Art is the province of every human being."])
+            );
+            assert_eq!(annotation.notes, "#art");
+        }
+    }
+
+    #[test]
+    fn omit_fields() {
+        let mut entries = entries();
+        run(
+            &mut entries,
+            &[RedactField::Notes, RedactField::Titles],
+            RedactMode::Omit,
+        );
+
+        let entry = entries.values().next().unwrap();
+
+        assert!(entry.book.title.is_empty());
+
+        for annotation in &entry.annotations {
+            assert!(annotation.notes.is_empty());
+            assert_eq!(annotation.chapter, None);
+            assert!(!annotation.body.is_empty());
+        }
+    }
+}
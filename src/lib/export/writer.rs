@@ -0,0 +1,472 @@
+//! Defines the writer used to output exported files to disk.
+
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use chrono::Local;
+use serde::Serialize;
+
+use crate::conflict::{self, ConflictMode};
+use crate::result::Result;
+
+use super::Compression;
+
+/// The basename of single-file exports i.e. archives and JSON Lines files. A timestamp and the
+/// appropriate extensions are appended.
+const EXPORT_NAME: &str = "readstor-export";
+
+/// A writer that outputs exported files either as individual files or into a single archive.
+pub(super) enum ExportWriter {
+    /// Writes each file individually.
+    Files {
+        /// The output directory.
+        destination: PathBuf,
+
+        /// The compression to apply to each file.
+        compression: Option<Compression>,
+
+        /// What to do when a file already exists.
+        on_conflict: ConflictMode,
+    },
+
+    /// Writes all files into a single `tar` archive.
+    Archive(tar::Builder<Encoder<BufWriter<File>>>),
+}
+
+impl ExportWriter {
+    /// Creates a new instance of [`ExportWriter`] that writes individual files.
+    ///
+    /// # Arguments
+    ///
+    /// * `destination` - The output directory.
+    /// * `compression` - The compression to apply to each file.
+    /// * `on_conflict` - What to do when a file already exists.
+    pub(super) fn files(
+        destination: &Path,
+        compression: Option<Compression>,
+        on_conflict: ConflictMode,
+    ) -> Self {
+        Self::Files {
+            destination: destination.to_owned(),
+            compression,
+            on_conflict,
+        }
+    }
+
+    /// Creates a new instance of [`ExportWriter`] that writes into a single archive.
+    ///
+    /// The archive is named `readstor-export-[YYYY-MM-DD-HHMMSS].tar` with an additional extension
+    /// if it's compressed e.g. `readstor-export-1970-01-01-120000.tar.gz`.
+    ///
+    /// # Arguments
+    ///
+    /// * `destination` - The output directory.
+    /// * `compression` - The compression to apply to the archive.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if any IO errors are encountered.
+    pub(super) fn archive(destination: &Path, compression: Option<Compression>) -> Result<Self> {
+        std::fs::create_dir_all(destination)?;
+
+        let now = Local::now().format(crate::defaults::DATE_FORMAT_SLUG);
+        let filename = self::with_extension(&format!("{EXPORT_NAME}-{now}.tar"), compression);
+        let path = destination.join(filename);
+
+        let file = File::create(&path)?;
+        let writer = Encoder::new(BufWriter::new(file), compression)?;
+
+        tracing::debug!(path = %path.display(), "created archive");
+
+        Ok(Self::Archive(tar::Builder::new(writer)))
+    }
+
+    /// Serializes a value to JSON and writes it.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The file's path relative to the output directory.
+    /// * `value` - The value to serialize.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if:
+    /// * Any IO errors are encountered.
+    /// * [`serde_json`][serde-json] encounters any errors.
+    ///
+    /// [serde-json]: https://docs.rs/serde_json/latest/serde_json/
+    pub(super) fn write<T>(&mut self, path: &Path, value: &T) -> Result<()>
+    where
+        T: Serialize + ?Sized,
+    {
+        match self {
+            Self::Files {
+                destination,
+                compression,
+                on_conflict,
+            } => {
+                let filename = path.to_string_lossy();
+                let path = destination.join(self::with_extension(&filename, *compression));
+
+                if *on_conflict == ConflictMode::Skip && path.exists() {
+                    tracing::debug!(path = %path.display(), "skipped writing");
+                    return Ok(());
+                }
+
+                if let Some(parent) = path.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+
+                // The contents are encoded in memory first so they can be compared against the
+                // existing file's.
+                let mut writer = Encoder::new(Vec::new(), *compression)?;
+                serde_json::to_writer_pretty(&mut writer, value)?;
+                let contents = writer.finish()?;
+
+                conflict::write(&path, contents, *on_conflict)?;
+            }
+            Self::Archive(builder) => {
+                let contents = serde_json::to_vec_pretty(value)?;
+
+                let mut header = tar::Header::new_gnu();
+                header.set_size(contents.len() as u64);
+                header.set_mode(0o644);
+                header.set_mtime(u64::try_from(Local::now().timestamp()).unwrap_or_default());
+                header.set_cksum();
+
+                builder.append_data(&mut header, path, contents.as_slice())?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Copies a file as-is. Compression is only applied if writing into an archive.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The file's path relative to the output directory.
+    /// * `source` - The path to the file to copy.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if any IO errors are encountered.
+    pub(super) fn copy(&mut self, path: &Path, source: &Path) -> Result<()> {
+        match self {
+            Self::Files {
+                destination,
+                on_conflict,
+                ..
+            } => {
+                let path = destination.join(path);
+
+                if *on_conflict == ConflictMode::Skip && path.exists() {
+                    tracing::debug!(path = %path.display(), "skipped writing");
+                    return Ok(());
+                }
+
+                if let Some(parent) = path.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+
+                conflict::write(&path, std::fs::read(source)?, *on_conflict)?;
+            }
+            Self::Archive(builder) => {
+                builder.append_path_with_name(source, path)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Finishes writing. This is required to finalize an archive.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if any IO errors are encountered.
+    pub(super) fn finish(self) -> Result<()> {
+        if let Self::Archive(builder) = self {
+            builder.into_inner()?.finish()?.flush()?;
+        }
+
+        Ok(())
+    }
+}
+
+/// A writer that streams values into a single JSON Lines file, one value per line.
+pub(super) struct JsonLinesWriter(Encoder<BufWriter<File>>);
+
+impl JsonLinesWriter {
+    /// Creates a new instance of [`JsonLinesWriter`].
+    ///
+    /// The file is named `readstor-export-[YYYY-MM-DD-HHMMSS].jsonl` unless a filename is set, with
+    /// an additional extension if it's compressed e.g. `readstor-export-1970-01-01-120000.jsonl.gz`.
+    ///
+    /// # Arguments
+    ///
+    /// * `destination` - The output directory.
+    /// * `filename` - An optional name for the file.
+    /// * `compression` - The compression to apply to the file.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if any IO errors are encountered.
+    pub(super) fn new(
+        destination: &Path,
+        filename: Option<&str>,
+        compression: Option<Compression>,
+    ) -> Result<Self> {
+        std::fs::create_dir_all(destination)?;
+
+        let filename = if let Some(filename) = filename {
+            filename.to_owned()
+        } else {
+            let now = Local::now().format(crate::defaults::DATE_FORMAT_SLUG);
+            format!("{EXPORT_NAME}-{now}.jsonl")
+        };
+
+        let filename = self::with_extension(&filename, compression);
+        let path = destination.join(filename);
+
+        let file = File::create(&path)?;
+
+        tracing::debug!(path = %path.display(), "created file");
+
+        Ok(Self(Encoder::new(BufWriter::new(file), compression)?))
+    }
+
+    /// Serializes a value to JSON and writes it as a single line.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - The value to serialize.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if:
+    /// * Any IO errors are encountered.
+    /// * [`serde_json`][serde-json] encounters any errors.
+    ///
+    /// [serde-json]: https://docs.rs/serde_json/latest/serde_json/
+    pub(super) fn write<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: Serialize,
+    {
+        serde_json::to_writer(&mut self.0, value)?;
+        self.0.write_all(b"\n")?;
+
+        Ok(())
+    }
+
+    /// Finishes writing. This is required to finalize the compression, if any.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if any IO errors are encountered.
+    pub(super) fn finish(self) -> Result<()> {
+        self.0.finish()?.flush()?;
+
+        Ok(())
+    }
+}
+
+/// A writer that optionally compresses its output.
+pub(super) enum Encoder<W>
+where
+    W: Write,
+{
+    /// Writes the output as-is.
+    Plain(W),
+
+    /// Compresses the output with `gzip`.
+    Gzip(flate2::write::GzEncoder<W>),
+
+    /// Compresses the output with `zstd`.
+    Zstd(zstd::Encoder<'static, W>),
+}
+
+impl<W> Encoder<W>
+where
+    W: Write,
+{
+    /// Wraps a writer with an encoder for the given compression.
+    ///
+    /// # Arguments
+    ///
+    /// * `writer` - The writer to wrap.
+    /// * `compression` - The compression to apply.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the encoder cannot be created.
+    fn new(writer: W, compression: Option<Compression>) -> Result<Self> {
+        let encoder = match compression {
+            None => Self::Plain(writer),
+            Some(Compression::Gzip) => Self::Gzip(flate2::write::GzEncoder::new(
+                writer,
+                flate2::Compression::default(),
+            )),
+            Some(Compression::Zstd) => Self::Zstd(zstd::Encoder::new(writer, 0)?),
+        };
+
+        Ok(encoder)
+    }
+
+    /// Finishes compressing the output and returns the inner writer.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if any IO errors are encountered.
+    fn finish(self) -> std::io::Result<W> {
+        match self {
+            Self::Plain(writer) => Ok(writer),
+            Self::Gzip(encoder) => encoder.finish(),
+            Self::Zstd(encoder) => encoder.finish(),
+        }
+    }
+}
+
+impl<W> Write for Encoder<W>
+where
+    W: Write,
+{
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match self {
+            Self::Plain(writer) => writer.write(buf),
+            Self::Gzip(encoder) => encoder.write(buf),
+            Self::Zstd(encoder) => encoder.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self {
+            Self::Plain(writer) => writer.flush(),
+            Self::Gzip(encoder) => encoder.flush(),
+            Self::Zstd(encoder) => encoder.flush(),
+        }
+    }
+}
+
+/// Appends the compression's extension to a filename.
+///
+/// # Arguments
+///
+/// * `filename` - The filename.
+/// * `compression` - The compression being applied.
+fn with_extension(filename: &str, compression: Option<Compression>) -> String {
+    match compression {
+        Some(compression) => format!("{filename}.{}", compression.extension()),
+        None => filename.to_owned(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    use std::io::Read;
+
+    use crate::defaults::TEMP_OUTPUT_DIRECTORY;
+
+    fn setup(name: &str) -> PathBuf {
+        let path = TEMP_OUTPUT_DIRECTORY.join("tests-export-writer").join(name);
+
+        if path.exists() {
+            std::fs::remove_dir_all(&path).unwrap();
+        }
+
+        path
+    }
+
+    // Tests that gzip compressed files can be decompressed back into the original JSON.
+    #[test]
+    fn gzip() {
+        let destination = setup("gzip");
+        let mut writer =
+            ExportWriter::files(&destination, Some(Compression::Gzip), ConflictMode::Skip);
+        writer.write(Path::new("book.json"), &["a", "b"]).unwrap();
+        writer.finish().unwrap();
+
+        let file = File::open(destination.join("book.json.gz")).unwrap();
+        let mut contents = String::new();
+        flate2::read::GzDecoder::new(file)
+            .read_to_string(&mut contents)
+            .unwrap();
+
+        let value: Vec<String> = serde_json::from_str(&contents).unwrap();
+        assert_eq!(value, ["a", "b"]);
+    }
+
+    // Tests that zstd compressed files can be decompressed back into the original JSON.
+    #[test]
+    fn zstd() {
+        let destination = setup("zstd");
+        let mut writer =
+            ExportWriter::files(&destination, Some(Compression::Zstd), ConflictMode::Skip);
+        writer.write(Path::new("book.json"), &["a", "b"]).unwrap();
+        writer.finish().unwrap();
+
+        let file = File::open(destination.join("book.json.zst")).unwrap();
+        let contents = zstd::decode_all(file).unwrap();
+
+        let value: Vec<String> = serde_json::from_slice(&contents).unwrap();
+        assert_eq!(value, ["a", "b"]);
+    }
+
+    // Tests that values are written one per line.
+    #[test]
+    fn json_lines() {
+        let destination = setup("json-lines");
+        let mut writer = JsonLinesWriter::new(&destination, None, None).unwrap();
+        writer.write(&["a"]).unwrap();
+        writer.write(&["b", "c"]).unwrap();
+        writer.finish().unwrap();
+
+        let path = std::fs::read_dir(&destination)
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap()
+            .path();
+
+        assert!(path.to_string_lossy().ends_with(".jsonl"));
+        assert_eq!(
+            std::fs::read_to_string(path).unwrap(),
+            "[\"a\"]\n[\"b\",\"c\"]\n"
+        );
+    }
+
+    // Tests that all files are written into a single archive.
+    #[test]
+    fn archive() {
+        let destination = setup("archive");
+        let mut writer = ExportWriter::archive(&destination, Some(Compression::Gzip)).unwrap();
+        writer.write(Path::new("a/book.json"), &["a"]).unwrap();
+        writer.write(Path::new("b/book.json"), &["b"]).unwrap();
+        writer.finish().unwrap();
+
+        let archives: Vec<_> = std::fs::read_dir(&destination)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect();
+
+        assert_eq!(archives.len(), 1);
+        assert!(archives[0].to_string_lossy().ends_with(".tar.gz"));
+
+        let file = File::open(&archives[0]).unwrap();
+        let mut archive = tar::Archive::new(flate2::read::GzDecoder::new(file));
+
+        let paths: Vec<PathBuf> = archive
+            .entries()
+            .unwrap()
+            .map(|entry| entry.unwrap().path().unwrap().into_owned())
+            .collect();
+
+        assert_eq!(
+            paths,
+            [PathBuf::from("a/book.json"), PathBuf::from("b/book.json")]
+        );
+    }
+}
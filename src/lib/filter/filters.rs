@@ -6,6 +6,10 @@
 
 use std::collections::BTreeSet;
 
+use chrono::{DateTime, Utc};
+
+use super::BookList;
+use crate::models::annotation::{Annotation, AnnotationKind};
 use crate::models::entry::Entries;
 
 /// Filters out [`Entry`][entry]s which have no [`Annotation`][annotation]s.
@@ -172,3 +176,184 @@ pub fn by_tags_exact(tags: &BTreeSet<&String>, entries: &mut Entries) {
             .retain(|annotation| annotation.tags == tags);
     }
 }
+
+/// Filters out [`Annotation`][annotation]s where neither their [`body`][body] nor their
+/// [`notes`][notes] contain any of the queries.
+///
+/// # Arguments
+///
+/// * `queries` - A list of strings to filter against.
+/// * `entries` - The [`Entry`][entry]s to filter.
+///
+/// [annotation]: crate::models::annotation::Annotation
+/// [body]: crate::models::annotation::Annotation::body
+/// [entry]: crate::models::entry::Entry
+/// [notes]: crate::models::annotation::Annotation::notes
+pub fn by_body_any(queries: &[String], entries: &mut Entries) {
+    let queries: Vec<String> = queries.iter().map(|query| query.to_lowercase()).collect();
+
+    for entry in entries.values_mut() {
+        entry.annotations.retain(|annotation| {
+            let text = annotation_text(annotation);
+            queries.iter().any(|query| text.contains(query))
+        });
+    }
+}
+
+/// Filters out [`Annotation`][annotation]s where their [`body`][body] and [`notes`][notes] don't
+/// contain all of the queries.
+///
+/// # Arguments
+///
+/// * `queries` - A list of strings to filter against.
+/// * `entries` - The [`Entry`][entry]s to filter.
+///
+/// [annotation]: crate::models::annotation::Annotation
+/// [body]: crate::models::annotation::Annotation::body
+/// [entry]: crate::models::entry::Entry
+/// [notes]: crate::models::annotation::Annotation::notes
+pub fn by_body_all(queries: &[String], entries: &mut Entries) {
+    let queries: Vec<String> = queries.iter().map(|query| query.to_lowercase()).collect();
+
+    for entry in entries.values_mut() {
+        entry.annotations.retain(|annotation| {
+            let text = annotation_text(annotation);
+            queries.iter().all(|query| text.contains(query))
+        });
+    }
+}
+
+/// Filters out [`Annotation`][annotation]s where neither their [`body`][body] nor their
+/// [`notes`][notes] contain the exact query phrase.
+///
+/// # Arguments
+///
+/// * `query` - A string to filter against.
+/// * `entries` - The [`Entry`][entry]s to filter.
+///
+/// [annotation]: crate::models::annotation::Annotation
+/// [body]: crate::models::annotation::Annotation::body
+/// [entry]: crate::models::entry::Entry
+/// [notes]: crate::models::annotation::Annotation::notes
+pub fn by_body_exact(query: &str, entries: &mut Entries) {
+    let query = query.to_lowercase();
+
+    for entry in entries.values_mut() {
+        entry.annotations.retain(|annotation| {
+            annotation.body.to_lowercase().contains(&query)
+                || annotation.notes.to_lowercase().contains(&query)
+        });
+    }
+}
+
+/// Returns an [`Annotation`]'s lowercased body and notes joined by a newline.
+///
+/// Joining with a newline prevents a query from matching across the boundary of the two fields as
+/// queries never contain newlines.
+fn annotation_text(annotation: &Annotation) -> String {
+    format!("{}\n{}", annotation.body, annotation.notes).to_lowercase()
+}
+
+/// Filters out [`Annotation`][annotation]s where their [`created`][created] date falls outside of
+/// a date range.
+///
+/// # Arguments
+///
+/// * `from` - An optional inclusive lower bound.
+/// * `to` - An optional exclusive upper bound.
+/// * `entries` - The [`Entry`][entry]s to filter.
+///
+/// [annotation]: crate::models::annotation::Annotation
+/// [created]: crate::models::annotation::AnnotationMetadata::created
+/// [entry]: crate::models::entry::Entry
+pub fn by_created_range(
+    from: Option<&DateTime<Utc>>,
+    to: Option<&DateTime<Utc>>,
+    entries: &mut Entries,
+) {
+    for entry in entries.values_mut() {
+        entry
+            .annotations
+            .retain(|annotation| is_in_range(&annotation.metadata.created, from, to));
+    }
+}
+
+/// Filters out [`Annotation`][annotation]s where their [`modified`][modified] date falls outside
+/// of a date range.
+///
+/// # Arguments
+///
+/// * `from` - An optional inclusive lower bound.
+/// * `to` - An optional exclusive upper bound.
+/// * `entries` - The [`Entry`][entry]s to filter.
+///
+/// [annotation]: crate::models::annotation::Annotation
+/// [entry]: crate::models::entry::Entry
+/// [modified]: crate::models::annotation::AnnotationMetadata::modified
+pub fn by_modified_range(
+    from: Option<&DateTime<Utc>>,
+    to: Option<&DateTime<Utc>>,
+    entries: &mut Entries,
+) {
+    for entry in entries.values_mut() {
+        entry
+            .annotations
+            .retain(|annotation| is_in_range(&annotation.metadata.modified, from, to));
+    }
+}
+
+/// Filters out [`Entry`][entry]s that aren't kept by a [`BookList`].
+///
+/// # Arguments
+///
+/// * `list` - The list of books to include and exclude.
+/// * `entries` - The [`Entry`][entry]s to filter.
+///
+/// [entry]: crate::models::entry::Entry
+pub fn by_book_list(list: &BookList, entries: &mut Entries) {
+    entries.retain(|_, entry| list.keeps(&entry.book));
+}
+
+/// Filters out [`Annotation`][annotation]s whose [`kind`][kind] isn't one of the target kinds.
+///
+/// # Arguments
+///
+/// * `kinds` - A list of kinds to filter against.
+/// * `entries` - The [`Entry`][entry]s to filter.
+///
+/// [annotation]: crate::models::annotation::Annotation
+/// [entry]: crate::models::entry::Entry
+/// [kind]: crate::models::annotation::Annotation::kind
+pub fn by_kind(kinds: &[AnnotationKind], entries: &mut Entries) {
+    for entry in entries.values_mut() {
+        entry
+            .annotations
+            .retain(|annotation| kinds.contains(&annotation.kind));
+    }
+}
+
+/// Filters out [`Annotation`][annotation]s that aren't marked as favorites. See
+/// [`Annotation::is_favorite()`][is-favorite].
+///
+/// # Arguments
+///
+/// * `entries` - The [`Entry`][entry]s to filter.
+///
+/// [annotation]: crate::models::annotation::Annotation
+/// [entry]: crate::models::entry::Entry
+/// [is-favorite]: crate::models::annotation::Annotation::is_favorite
+pub fn by_favorite(entries: &mut Entries) {
+    for entry in entries.values_mut() {
+        entry.annotations.retain(Annotation::is_favorite);
+    }
+}
+
+/// Returns `true` if a date is within an optional inclusive lower bound and an optional exclusive
+/// upper bound.
+fn is_in_range(
+    date: &DateTime<Utc>,
+    from: Option<&DateTime<Utc>>,
+    to: Option<&DateTime<Utc>>,
+) -> bool {
+    from.is_none_or(|from| date >= from) && to.is_none_or(|to| date < to)
+}
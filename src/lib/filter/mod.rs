@@ -4,6 +4,11 @@ pub mod filters;
 
 use std::collections::BTreeSet;
 
+use chrono::{DateTime, Utc};
+use glob::{MatchOptions, Pattern};
+
+use crate::models::annotation::AnnotationKind;
+use crate::models::book::Book;
 use crate::models::entry::Entries;
 
 /// Runs filters on [`Entries`]s.
@@ -17,6 +22,14 @@ where
     F: Into<FilterType>,
 {
     let filter_type: FilterType = filter_type.into();
+    let filter_type_is_annotation_level = matches!(
+        filter_type,
+        FilterType::Tags { .. }
+            | FilterType::Body { .. }
+            | FilterType::DateRange { .. }
+            | FilterType::Kind(_)
+            | FilterType::Favorites
+    );
 
     match filter_type {
         FilterType::Title { query, operator } => {
@@ -28,10 +41,23 @@ where
         FilterType::Tags { query, operator } => {
             self::filter_by_tags(&query, operator, entries);
         }
+        FilterType::Body { query, operator } => {
+            self::filter_by_body(&query, operator, entries);
+        }
+        FilterType::DateRange { field, from, to } => {
+            self::filter_by_date_range(field, from.as_ref(), to.as_ref(), entries);
+        }
+        FilterType::BookList(list) => filters::by_book_list(&list, entries),
+        FilterType::Kind(kinds) => filters::by_kind(&kinds, entries),
+        FilterType::Favorites => filters::by_favorite(entries),
     }
 
-    // Remove `Entry`s that have had all their `Annotation`s filtered out.
-    filters::contains_no_annotations(entries);
+    // Remove `Entry`s that have had all their `Annotation`s filtered out. This is only done for
+    // filters that operate on `Annotation`s so `Entry`s that never had any `Annotation`s are left
+    // untouched by `Book` filters.
+    if filter_type_is_annotation_level {
+        filters::contains_no_annotations(entries);
+    }
 }
 
 /// Filters out [`Entry`][entry]s by their [`Book::title`][book].
@@ -90,6 +116,49 @@ fn filter_by_tags(query: &[String], operator: FilterOperator, entries: &mut Entr
     }
 }
 
+/// Filters out [`Annotation`][annotation]s by their [`body`][body] and [`notes`][notes].
+///
+/// # Arguments
+///
+/// * `query` - A list of strings to filter against.
+/// * `operator` - The [`FilterOperator`] to use.
+/// * `entries` - The [`Entry`][entry]s to filter.
+///
+/// [annotation]: crate::models::annotation::Annotation
+/// [body]: crate::models::annotation::Annotation::body
+/// [entry]: crate::models::entry::Entry
+/// [notes]: crate::models::annotation::Annotation::notes
+fn filter_by_body(query: &[String], operator: FilterOperator, entries: &mut Entries) {
+    match operator {
+        FilterOperator::Any => filters::by_body_any(query, entries),
+        FilterOperator::All => filters::by_body_all(query, entries),
+        FilterOperator::Exact => filters::by_body_exact(&query.join(" "), entries),
+    }
+}
+
+/// Filters out [`Annotation`][annotation]s by one of their dates.
+///
+/// # Arguments
+///
+/// * `field` - The [`DateField`] to use.
+/// * `from` - An optional inclusive lower bound.
+/// * `to` - An optional exclusive upper bound.
+/// * `entries` - The [`Entry`][entry]s to filter.
+///
+/// [annotation]: crate::models::annotation::Annotation
+/// [entry]: crate::models::entry::Entry
+fn filter_by_date_range(
+    field: DateField,
+    from: Option<&DateTime<Utc>>,
+    to: Option<&DateTime<Utc>>,
+    entries: &mut Entries,
+) {
+    match field {
+        DateField::Created => filters::by_created_range(from, to, entries),
+        DateField::Modified => filters::by_modified_range(from, to, entries),
+    }
+}
+
 /// An enum representing possible filter types.
 ///
 /// A filter generally consists of three elements: (1) the field to use for filtering, (2) a list of
@@ -125,6 +194,138 @@ pub enum FilterType {
         #[allow(missing_docs)]
         operator: FilterOperator,
     },
+
+    /// Sets the filter to use the [`Annotation::body`][body] and [`Annotation::notes`][notes]
+    /// fields for filtering. An [`FilterOperator::Exact`] filter matches if either field contains
+    /// the unsplit query.
+    ///
+    /// [body]: crate::models::annotation::Annotation::body
+    /// [notes]: crate::models::annotation::Annotation::notes
+    Body {
+        #[allow(missing_docs)]
+        query: Vec<String>,
+        #[allow(missing_docs)]
+        operator: FilterOperator,
+    },
+
+    /// Sets the filter to use one of the [`AnnotationMetadata`][metadata]'s date fields for
+    /// filtering. Either bound can be omitted to leave the range open-ended.
+    ///
+    /// [metadata]: crate::models::annotation::AnnotationMetadata
+    DateRange {
+        /// The date field to filter on.
+        field: DateField,
+        /// The inclusive lower bound.
+        from: Option<DateTime<Utc>>,
+        /// The exclusive upper bound.
+        to: Option<DateTime<Utc>>,
+    },
+
+    /// Sets the filter to keep/discard books listed in a [`BookList`].
+    BookList(BookList),
+
+    /// Sets the filter to keep annotations whose [`Annotation::kind`][kind] is any of the kinds.
+    ///
+    /// [kind]: crate::models::annotation::Annotation::kind
+    Kind(Vec<AnnotationKind>),
+
+    /// Sets the filter to keep annotations that are marked as favorites. See
+    /// [`Annotation::is_favorite()`][is-favorite].
+    ///
+    /// [is-favorite]: crate::models::annotation::Annotation::is_favorite
+    Favorites,
+}
+
+/// A struct representing a list of books to include and exclude.
+///
+/// A list is parsed from text with one book per line. Each line is either a book's
+/// [`id`][id] or a case-insensitive [glob][glob] pattern matched against its
+/// [`title`][title] e.g. `The Art*`. Lines prefixed with `!` exclude books instead. Blank lines
+/// and lines starting with `#` are ignored:
+///
+/// ```plaintext
+/// # Art
+/// The Art Spirit
+/// The Art of *
+/// !The Art of War
+/// ```
+///
+/// If a list contains any includes, only books matching one of them are kept. Books matching any
+/// exclude are always discarded.
+///
+/// [glob]: https://docs.rs/glob/latest/glob/struct.Pattern.html
+/// [id]: crate::models::book::BookMetadata::id
+/// [title]: crate::models::book::Book::title
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BookList {
+    include: Vec<Pattern>,
+    exclude: Vec<Pattern>,
+}
+
+impl BookList {
+    /// Creates a new instance of [`BookList`] from text with one book per line.
+    ///
+    /// # Arguments
+    ///
+    /// * `contents` - The text to parse.
+    #[must_use]
+    pub fn parse(contents: &str) -> Self {
+        let mut list = Self::default();
+
+        for line in contents.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            match line.strip_prefix('!') {
+                Some(line) => list.exclude.push(self::pattern(line.trim())),
+                None => list.include.push(self::pattern(line)),
+            }
+        }
+
+        list
+    }
+
+    /// Returns `true` if the list doesn't contain any books.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.include.is_empty() && self.exclude.is_empty()
+    }
+
+    /// Returns `true` if a book should be kept.
+    ///
+    /// # Arguments
+    ///
+    /// * `book` - The book to check.
+    #[must_use]
+    pub fn keeps(&self, book: &Book) -> bool {
+        let matches = |pattern: &Pattern| {
+            pattern.as_str() == book.metadata.id
+                || pattern.matches_with(
+                    &book.title,
+                    MatchOptions {
+                        case_sensitive: false,
+                        ..MatchOptions::default()
+                    },
+                )
+        };
+
+        (self.include.is_empty() || self.include.iter().any(matches))
+            && !self.exclude.iter().any(matches)
+    }
+}
+
+/// Compiles a line of a [`BookList`] into a pattern. Lines that aren't valid patterns e.g. titles
+/// containing an unclosed `[` are matched literally.
+///
+/// # Arguments
+///
+/// * `line` - The line to compile.
+fn pattern(line: &str) -> Pattern {
+    Pattern::new(line).unwrap_or_else(|_| {
+        // This unwrap is safe as an escaped pattern is always valid.
+        Pattern::new(&Pattern::escape(line)).unwrap()
+    })
 }
 
 #[cfg(test)]
@@ -149,6 +350,32 @@ impl FilterType {
             operator,
         }
     }
+
+    fn body(query: &[&str], operator: FilterOperator) -> Self {
+        Self::Body {
+            query: query.iter().map(std::string::ToString::to_string).collect(),
+            operator,
+        }
+    }
+}
+
+/// An enum representing the [`AnnotationMetadata`][metadata] date fields available for filtering.
+///
+/// See [`FilterType::DateRange`] for more information.
+///
+/// [metadata]: crate::models::annotation::AnnotationMetadata
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DateField {
+    /// Sets the filter to use the [`AnnotationMetadata::created`][created] field.
+    ///
+    /// [created]: crate::models::annotation::AnnotationMetadata::created
+    #[default]
+    Created,
+
+    /// Sets the filter to use the [`AnnotationMetadata::modified`][modified] field.
+    ///
+    /// [modified]: crate::models::annotation::AnnotationMetadata::modified
+    Modified,
 }
 
 /// An enum representing possible filter operators.
@@ -172,8 +399,6 @@ mod test {
 
     use super::*;
 
-    use std::collections::HashMap;
-
     use crate::models::annotation::Annotation;
     use crate::models::book::Book;
     use crate::models::entry::Entry;
@@ -218,7 +443,7 @@ mod test {
             annotations,
         };
 
-        let mut data = HashMap::new();
+        let mut data = Entries::default();
         data.insert("00".to_string(), entry_00);
         data.insert("01".to_string(), entry_01);
 
@@ -447,4 +672,282 @@ mod test {
         assert_eq!(entries.len(), 1);
         assert_eq!(annotations, 2);
     }
+
+    fn create_test_dated_entries() -> Entries {
+        let annotations = ["2024-01-01", "2024-02-01", "2024-03-01"]
+            .into_iter()
+            .map(|date| {
+                let date = create_test_date(date);
+                let mut annotation = Annotation::default();
+                *annotation.metadata.created = date;
+                *annotation.metadata.modified = date;
+                annotation
+            })
+            .collect();
+
+        let entry = Entry {
+            book: Book::default(),
+            annotations,
+        };
+
+        let mut data = Entries::default();
+        data.insert("00".to_string(), entry);
+
+        data
+    }
+
+    fn create_test_date(date: &str) -> DateTime<Utc> {
+        format!("{date}T00:00:00Z").parse().unwrap()
+    }
+
+    // Keeps annotations created on or after 2024-02-01 and before 2024-03-01.
+    #[test]
+    fn date_range_created() {
+        let mut entries = create_test_dated_entries();
+
+        super::run(
+            FilterType::DateRange {
+                field: DateField::Created,
+                from: Some(create_test_date("2024-02-01")),
+                to: Some(create_test_date("2024-03-01")),
+            },
+            &mut entries,
+        );
+
+        let annotations = entries
+            .values()
+            .flat_map(|entry| &entry.annotations)
+            .count();
+
+        assert_eq!(entries.len(), 1);
+        assert_eq!(annotations, 1);
+    }
+
+    // Keeps annotations modified on or after 2024-02-01.
+    #[test]
+    fn date_range_modified_open_ended() {
+        let mut entries = create_test_dated_entries();
+
+        super::run(
+            FilterType::DateRange {
+                field: DateField::Modified,
+                from: Some(create_test_date("2024-02-01")),
+                to: None,
+            },
+            &mut entries,
+        );
+
+        let annotations = entries
+            .values()
+            .flat_map(|entry| &entry.annotations)
+            .count();
+
+        assert_eq!(entries.len(), 1);
+        assert_eq!(annotations, 2);
+    }
+
+    // Removes books when all their annotations fall outside of the range.
+    #[test]
+    fn date_range_empty() {
+        let mut entries = create_test_dated_entries();
+
+        super::run(
+            FilterType::DateRange {
+                field: DateField::Created,
+                from: None,
+                to: Some(create_test_date("2023-01-01")),
+            },
+            &mut entries,
+        );
+
+        assert_eq!(entries.len(), 0);
+    }
+
+    fn create_test_text_entries() -> Entries {
+        let annotations = [
+            ("We are not here to do what has already been done.", ""),
+            (
+                "The object of painting a picture is not to make a picture.",
+                "On art",
+            ),
+            (
+                "Do not let the fact that things are not made for you stop you.",
+                "",
+            ),
+        ]
+        .into_iter()
+        .map(|(body, notes)| Annotation {
+            body: body.to_string(),
+            notes: notes.to_string(),
+            ..Default::default()
+        })
+        .collect();
+
+        let entry = Entry {
+            book: Book::default(),
+            annotations,
+        };
+
+        let mut data = Entries::default();
+        data.insert("00".to_string(), entry);
+
+        data
+    }
+
+    // Keeps annotations where their body or notes contain "picture" or "done".
+    #[test]
+    fn body_any() {
+        let mut entries = create_test_text_entries();
+
+        super::run(
+            FilterType::body(&["picture", "done"], FilterOperator::Any),
+            &mut entries,
+        );
+
+        let annotations = entries
+            .values()
+            .flat_map(|entry| &entry.annotations)
+            .count();
+
+        assert_eq!(entries.len(), 1);
+        assert_eq!(annotations, 2);
+    }
+
+    // Keeps annotations where their body and notes contain both "picture" and "art".
+    #[test]
+    fn body_all() {
+        let mut entries = create_test_text_entries();
+
+        super::run(
+            FilterType::body(&["Picture", "art"], FilterOperator::All),
+            &mut entries,
+        );
+
+        let annotations = entries
+            .values()
+            .flat_map(|entry| &entry.annotations)
+            .count();
+
+        assert_eq!(entries.len(), 1);
+        assert_eq!(annotations, 1);
+    }
+
+    // Keeps annotations where their body or notes contain the phrase "not made for you".
+    #[test]
+    fn body_exact() {
+        let mut entries = create_test_text_entries();
+
+        super::run(
+            FilterType::body(&["not", "made", "for", "you"], FilterOperator::Exact),
+            &mut entries,
+        );
+
+        let annotations = entries
+            .values()
+            .flat_map(|entry| &entry.annotations)
+            .count();
+
+        assert_eq!(entries.len(), 1);
+        assert_eq!(annotations, 1);
+    }
+
+    fn create_test_listed_entries() -> Entries {
+        let mut data = Entries::default();
+
+        for (id, title) in [
+            ("00", "The Art Spirit"),
+            ("01", "The Art of War"),
+            ("02", "Surely You're Joking, Mr. Feynman!"),
+        ] {
+            let mut book = Book {
+                title: title.to_string(),
+                ..Default::default()
+            };
+            book.metadata.id = id.to_string();
+
+            data.insert(
+                id.to_string(),
+                Entry {
+                    book,
+                    annotations: vec![Annotation::default()],
+                },
+            );
+        }
+
+        data
+    }
+
+    // Keeps books whose title matches an include and doesn't match an exclude.
+    #[test]
+    fn book_list_title() {
+        let mut entries = create_test_listed_entries();
+
+        super::run(
+            FilterType::BookList(BookList::parse("# Art\nthe art*\n\n!The Art of War\n")),
+            &mut entries,
+        );
+
+        assert_eq!(entries.keys().collect::<Vec<_>>(), ["00"]);
+    }
+
+    // Keeps all books but the excluded ones when there are no includes.
+    #[test]
+    fn book_list_exclude_id() {
+        let mut entries = create_test_listed_entries();
+
+        super::run(FilterType::BookList(BookList::parse("!00")), &mut entries);
+
+        assert_eq!(entries.len(), 2);
+        assert!(!entries.contains_key("00"));
+    }
+
+    // Keeps annotations that aren't notes and removes entries left without any.
+    #[test]
+    fn kind() {
+        let mut entries = create_test_entries();
+
+        for (id, entry) in entries.iter_mut() {
+            for annotation in &mut entry.annotations {
+                annotation.kind = if id == "00" {
+                    AnnotationKind::Note
+                } else {
+                    AnnotationKind::Underline
+                };
+            }
+        }
+
+        entries.get_mut("01").unwrap().annotations[0].kind = AnnotationKind::Note;
+
+        super::run(
+            FilterType::Kind(vec![AnnotationKind::Highlight, AnnotationKind::Underline]),
+            &mut entries,
+        );
+
+        assert_eq!(entries.keys().collect::<Vec<_>>(), ["01"]);
+        assert_eq!(entries["01"].annotations.len(), 3);
+    }
+
+    // Keeps annotations marked as favorites by their notes or tags and removes entries left
+    // without any.
+    #[test]
+    fn favorites() {
+        let mut entries = create_test_entries();
+
+        for (id, entry) in entries.iter_mut() {
+            for annotation in &mut entry.annotations {
+                annotation.notes = String::new();
+                annotation.tags.clear();
+            }
+
+            if id == "01" {
+                entry.annotations[0].notes = "* A note.".to_string();
+                entry.annotations[1].tags.insert("#favorite".to_string());
+            }
+        }
+
+        super::run(FilterType::Favorites, &mut entries);
+
+        assert_eq!(entries.keys().collect::<Vec<_>>(), ["01"]);
+        assert_eq!(entries["01"].annotations.len(), 2);
+    }
 }
@@ -0,0 +1,660 @@
+//! Defines types for importing annotations from a Kindle's `My Clippings.txt` file.
+//!
+//! A Kindle appends every highlight, note and bookmark to its clippings file as a clipping:
+//!
+//! ```plaintext
+//! The Art Spirit (Robert Henri)
+//! - Your Highlight on page 12 | Location 171-173 | Added on Monday, March 5, 2018 10:07:55 PM
+//!
+//! We are not here to do what has already been done.
+//! ==========
+//! ```
+//!
+//! The clipping's header is written in the Kindle's language. Its kind, page, location and date
+//! are read by keyword so clippings written in English, French, German, Spanish, Italian,
+//! Portuguese, Dutch, Japanese and Chinese are supported.
+
+use std::collections::{BTreeMap, HashSet};
+use std::path::Path;
+
+use chrono::{NaiveDate, NaiveTime};
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex};
+
+use crate::models::annotation::{Annotation, AnnotationKind, AnnotationStyle};
+use crate::models::book::Book;
+use crate::models::datetime::DateTimeUtc;
+use crate::models::entry::Entries;
+use crate::models::{id, pdf};
+use crate::result::{Error, Result};
+
+/// The line separating clippings.
+const SEPARATOR: &str = "==========";
+
+/// Words identifying a highlight in each supported language.
+const HIGHLIGHT_KEYWORDS: &[&str] = &[
+    "highlight",
+    "surlignement",
+    "markierung",
+    "subrayado",
+    "evidenziazione",
+    "destaque",
+    "markering",
+    "ハイライト",
+    "标注",
+];
+
+/// Words identifying a note in each supported language.
+const NOTE_KEYWORDS: &[&str] = &["note", "notiz", "nota", "notitie", "メモ", "笔记"];
+
+/// Words identifying a bookmark in each supported language.
+const BOOKMARK_KEYWORDS: &[&str] = &[
+    "bookmark",
+    "signet",
+    "lesezeichen",
+    "marcador",
+    "segnalibro",
+    "bladwijzer",
+    "ブックマーク",
+    "书签",
+];
+
+/// The names of the months in each supported language, in the order of the months.
+const MONTHS: [&[&str]; 12] = [
+    &[
+        "january", "janvier", "januar", "enero", "gennaio", "janeiro", "januari",
+    ],
+    &[
+        "february",
+        "février",
+        "februar",
+        "febrero",
+        "febbraio",
+        "fevereiro",
+        "februari",
+    ],
+    &["march", "mars", "märz", "marzo", "março", "maart"],
+    &["april", "avril", "abril", "aprile"],
+    &["may", "mai", "mayo", "maggio", "maio", "mei"],
+    &["june", "juin", "juni", "junio", "giugno", "junho"],
+    &["july", "juillet", "juli", "julio", "luglio", "julho"],
+    &["august", "août", "agosto", "augustus"],
+    &[
+        "september",
+        "septembre",
+        "septiembre",
+        "settembre",
+        "setembro",
+    ],
+    &[
+        "october", "octobre", "oktober", "octubre", "ottobre", "outubro",
+    ],
+    &["november", "novembre", "noviembre", "novembro"],
+    &[
+        "december",
+        "décembre",
+        "dezember",
+        "diciembre",
+        "dicembre",
+        "dezembro",
+    ],
+];
+
+/// Captures a clipping's location e.g. `Location 171-173`, `Loc. 171-73` or `位置No. 171-173`.
+static RE_LOCATION: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"(?i)(?:location|loc\.|emplacement|position|posición|posizione|posição|locatie|位置)\s*(?:no\.|#)?\s*(\d+)(?:\s*-\s*(\d+))?",
+    )
+    .unwrap()
+});
+
+/// Captures a clipping's page e.g. `page 12`, `Seite 12` or `12ページ`.
+static RE_PAGE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)(?:page|seite|página|pagina)\s+(\d+)|(\d+)\s*(?:ページ|页)").unwrap()
+});
+
+/// Captures a clipping's time e.g. `10:07:55 PM`, `22:07:55` or `午後10:07:55`.
+static RE_TIME: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"(?i)(上午|下午|午前|午後)?\s*(\d{1,2}):(\d{2})(?::(\d{2}))?(?:\s*([ap])\.?\s?m\b\.?)?",
+    )
+    .unwrap()
+});
+
+/// Captures a date written with CJK characters e.g. `2018年3月5日`.
+static RE_CJK_DATE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(\d{4})\s*年\s*(\d{1,2})\s*月\s*(\d{1,2})\s*日").unwrap());
+
+/// Captures a number.
+static RE_NUMBER: Lazy<Regex> = Lazy::new(|| Regex::new(r"\d+").unwrap());
+
+/// An enum representing the kinds of clippings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ClippingKind {
+    /// A highlighted passage.
+    Highlight,
+
+    /// A note, usually made at the end of a highlight.
+    Note,
+
+    /// A bookmark. These are skipped.
+    Bookmark,
+}
+
+/// A struct representing a single clipping.
+#[derive(Debug, Clone)]
+struct Clipping {
+    /// The title of the clipping's book.
+    title: String,
+
+    /// The author of the clipping's book.
+    author: String,
+
+    /// The kind of clipping.
+    kind: ClippingKind,
+
+    /// The one-based page number, if the book has pages.
+    page: Option<usize>,
+
+    /// The first and last location of the clipping.
+    location: Option<(usize, usize)>,
+
+    /// The date the clipping was added.
+    created: Option<DateTimeUtc>,
+
+    /// The highlighted text or the note's text.
+    text: String,
+}
+
+/// Imports the books and annotations of a Kindle's `My Clippings.txt` file. See [`parse()`].
+///
+/// # Arguments
+///
+/// * `path` - The path to the clippings file.
+///
+/// # Errors
+///
+/// Will return `Err` if:
+/// * The file doesn't contain any highlights or notes.
+/// * Any IO errors are encountered.
+pub fn run(path: &Path) -> Result<Entries> {
+    let text = std::fs::read_to_string(path)?;
+
+    let entries = self::parse(&text);
+
+    if entries.is_empty() {
+        return Err(Error::ImportInvalidClippings {
+            path: path.display().to_string(),
+        });
+    }
+
+    Ok(entries)
+}
+
+/// Parses the contents of a Kindle's `My Clippings.txt` file into [`Entries`].
+///
+/// Books are identified by their title and author. See [`id::book_hash()`]. Highlights become
+/// annotations and notes are added to the highlight they were made on i.e. the highlight whose
+/// locations contain the note's. Notes without a highlight become annotations without any
+/// highlighted text. Bookmarks and clippings that cannot be parsed are skipped, as are duplicate
+/// clippings, which a Kindle writes e.g. when a passage is highlighted twice.
+///
+/// Clippings don't record a time zone so their dates are read as UTC. Locations are used as the
+/// annotations' location, falling back to their page.
+///
+/// # Arguments
+///
+/// * `text` - The contents of the clippings file.
+#[must_use]
+pub fn parse(text: &str) -> Entries {
+    let mut books: BTreeMap<String, Book> = BTreeMap::new();
+    let mut annotations: Vec<Annotation> = Vec::new();
+    let mut notes: Vec<(String, Clipping)> = Vec::new();
+    let mut seen = HashSet::new();
+
+    for clipping in text.split(SEPARATOR).filter_map(self::parse_clipping) {
+        let mut book = Book {
+            title: clipping.title.clone(),
+            author: clipping.author.clone(),
+            ..Default::default()
+        };
+        let book_id = id::book_hash(&book);
+        book.metadata.id.clone_from(&book_id);
+
+        books.entry(book_id.clone()).or_insert(book);
+
+        match clipping.kind {
+            ClippingKind::Highlight if !clipping.text.is_empty() => {
+                let annotation = self::annotation(&book_id, &clipping);
+
+                if seen.insert(annotation.metadata.id.clone()) {
+                    annotations.push(annotation);
+                }
+            }
+            ClippingKind::Note => notes.push((book_id, clipping)),
+            ClippingKind::Highlight | ClippingKind::Bookmark => {}
+        }
+    }
+
+    for (book_id, clipping) in notes {
+        let location = clipping.location.map(|(start, _)| start);
+
+        let highlight = annotations.iter_mut().find(|annotation| {
+            annotation.metadata.book_id == book_id
+                && location.is_some_and(|location| {
+                    self::locations(annotation)
+                        .is_some_and(|(start, end)| (start..=end).contains(&location))
+                })
+        });
+
+        if let Some(highlight) = highlight {
+            if !highlight.notes.contains(&clipping.text) {
+                if !highlight.notes.is_empty() {
+                    highlight.notes.push_str("\n\n");
+                }
+
+                highlight.notes.push_str(&clipping.text);
+            }
+
+            continue;
+        }
+
+        let annotation = self::annotation(&book_id, &clipping);
+
+        if seen.insert(annotation.metadata.id.clone()) {
+            annotations.push(annotation);
+        }
+    }
+
+    tracing::debug!(
+        books = books.len(),
+        annotations = annotations.len(),
+        "parsed Kindle clippings"
+    );
+
+    Entries::from_parts(books.into_values().collect(), annotations)
+}
+
+/// Parses a single clipping. Returns `None` if it's incomplete or its kind isn't recognized.
+///
+/// # Arguments
+///
+/// * `block` - The text between two separators.
+fn parse_clipping(block: &str) -> Option<Clipping> {
+    let block = block.trim_start_matches('\u{feff}').trim();
+
+    let mut lines = block.lines();
+
+    let (title, author) = self::parse_header(lines.next()?);
+    let details = lines.next()?.trim();
+    let text = lines.collect::<Vec<_>>().join("\n").trim().to_owned();
+
+    // The date is always the last section of the details line.
+    let (details, date) = details.rsplit_once('|').unwrap_or((details, ""));
+    let lowercase = details.to_lowercase();
+
+    let contains = |keywords: &[&str]| keywords.iter().any(|keyword| lowercase.contains(keyword));
+
+    let kind = if contains(BOOKMARK_KEYWORDS) {
+        ClippingKind::Bookmark
+    } else if contains(HIGHLIGHT_KEYWORDS) {
+        ClippingKind::Highlight
+    } else if contains(NOTE_KEYWORDS) {
+        ClippingKind::Note
+    } else {
+        tracing::debug!(details, "skipped clipping of an unknown kind");
+        return None;
+    };
+
+    let page = RE_PAGE.captures(details).and_then(|captures| {
+        captures
+            .get(1)
+            .or_else(|| captures.get(2))
+            .and_then(|page| page.as_str().parse().ok())
+    });
+
+    let location = RE_LOCATION
+        .captures(details)
+        .and_then(|captures| self::parse_location(&captures));
+
+    let created = self::parse_date(date);
+
+    if created.is_none() {
+        tracing::warn!(date, "unable to parse the date of a Kindle clipping");
+    }
+
+    Some(Clipping {
+        title,
+        author,
+        kind,
+        page,
+        location,
+        created,
+        text,
+    })
+}
+
+/// Splits a clipping's first line into the book's title and author e.g.
+/// `The Art Spirit (Robert Henri)`. The author is the last parenthesized part, if any.
+///
+/// # Arguments
+///
+/// * `header` - The clipping's first line.
+fn parse_header(header: &str) -> (String, String) {
+    let header = header.trim();
+
+    if let Some(rest) = header.strip_suffix(')') {
+        if let Some(index) = rest.rfind('(') {
+            let title = rest[..index].trim();
+
+            if !title.is_empty() {
+                return (title.to_owned(), rest[index + 1..].trim().to_owned());
+            }
+        }
+    }
+
+    (header.to_owned(), String::new())
+}
+
+/// Returns a clipping's first and last location.
+///
+/// Older Kindles abbreviate the last location by omitting the digits it shares with the first
+/// one e.g. `171-73` is read as `171-173`.
+///
+/// # Arguments
+///
+/// * `captures` - The captures of [`RE_LOCATION`].
+fn parse_location(captures: &Captures<'_>) -> Option<(usize, usize)> {
+    let start = captures.get(1)?.as_str();
+
+    let end = match captures.get(2).map(|end| end.as_str()) {
+        Some(end) if end.len() < start.len() => {
+            format!("{}{end}", &start[..start.len() - end.len()])
+        }
+        Some(end) => end.to_owned(),
+        None => start.to_owned(),
+    };
+
+    Some((start.parse().ok()?, end.parse().ok()?))
+}
+
+/// Parses the date a clipping was added e.g. `Added on Monday, March 5, 2018 10:07:55 PM`.
+///
+/// The time is read first. The date is then read from CJK characters or, otherwise, from the
+/// remaining text's month name, its four-digit year and its first one- or two-digit number as the
+/// day. Returns `None` if the date cannot be read.
+///
+/// # Arguments
+///
+/// * `text` - The date section of a clipping's details line.
+fn parse_date(text: &str) -> Option<DateTimeUtc> {
+    let text = text.to_lowercase();
+
+    let captures = RE_TIME.captures(&text)?;
+    let number = |index: usize| -> Option<u32> {
+        captures
+            .get(index)
+            .map_or(Some(0), |number| number.as_str().parse().ok())
+    };
+
+    let (mut hour, minute, second) = (number(2)?, number(3)?, number(4)?);
+
+    let is_afternoon = matches!(captures.get(1).map(|m| m.as_str()), Some("下午" | "午後"))
+        || captures.get(5).is_some_and(|m| m.as_str() == "p");
+    let is_morning = matches!(captures.get(1).map(|m| m.as_str()), Some("上午" | "午前"))
+        || captures.get(5).is_some_and(|m| m.as_str() == "a");
+
+    if is_afternoon && hour < 12 {
+        hour += 12;
+    } else if is_morning && hour == 12 {
+        hour = 0;
+    }
+
+    let time = NaiveTime::from_hms_opt(hour, minute, second)?;
+    let text = RE_TIME.replace(&text, " ");
+
+    let date = if let Some(captures) = RE_CJK_DATE.captures(&text) {
+        NaiveDate::from_ymd_opt(
+            captures[1].parse().ok()?,
+            captures[2].parse().ok()?,
+            captures[3].parse().ok()?,
+        )?
+    } else {
+        let month = text
+            .split(|c: char| !c.is_alphabetic())
+            .find_map(|word| MONTHS.iter().position(|names| names.contains(&word)))?;
+
+        let numbers: Vec<&str> = RE_NUMBER.find_iter(&text).map(|m| m.as_str()).collect();
+        let year = numbers.iter().find(|number| number.len() == 4)?;
+        let day = numbers.iter().find(|number| number.len() <= 2)?;
+
+        NaiveDate::from_ymd_opt(
+            year.parse().ok()?,
+            u32::try_from(month).ok()? + 1,
+            day.parse().ok()?,
+        )?
+    };
+
+    Some(DateTimeUtc::from(date.and_time(time).and_utc()))
+}
+
+/// Returns an annotation from a highlight or a note clipping.
+///
+/// # Arguments
+///
+/// * `book_id` - The id of the clipping's book.
+/// * `clipping` - The clipping.
+fn annotation(book_id: &str, clipping: &Clipping) -> Annotation {
+    let (body, notes, style) = match clipping.kind {
+        ClippingKind::Note => (String::new(), clipping.text.clone(), AnnotationStyle::None),
+        // Yellow is the Kindle's default highlight color.
+        _ => (
+            clipping.text.clone(),
+            String::new(),
+            AnnotationStyle::Yellow,
+        ),
+    };
+
+    // Kindle locations are numbered like pages so they're sorted the same way.
+    let (location, location_sortkey) = match (clipping.location, clipping.page) {
+        (Some((start, end)), _) => (format!("{start}-{end}"), pdf::sortkey(start)),
+        (None, Some(page)) => (pdf::location(page), pdf::sortkey(page)),
+        (None, None) => (String::new(), String::new()),
+    };
+
+    let mut annotation = Annotation {
+        kind: AnnotationKind::new(style, &body),
+        body,
+        style,
+        notes,
+        ..Default::default()
+    };
+
+    annotation.metadata.id = id::hash(&[book_id, &location, &clipping.text]);
+    book_id.clone_into(&mut annotation.metadata.book_id);
+    annotation.metadata.created = clipping.created.unwrap_or_default();
+    annotation.metadata.modified = annotation.metadata.created;
+    annotation.metadata.location = location;
+    annotation.metadata.location_sortkey = location_sortkey;
+    annotation.metadata.page = clipping.page;
+
+    annotation
+}
+
+/// Returns the first and last location of an annotation created by [`annotation()`].
+///
+/// # Arguments
+///
+/// * `annotation` - The annotation.
+fn locations(annotation: &Annotation) -> Option<(usize, usize)> {
+    let (start, end) = annotation.metadata.location.split_once('-')?;
+
+    Some((start.parse().ok()?, end.parse().ok()?))
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    use crate::defaults::CRATE_ROOT;
+
+    fn clipping(header: &str, details: &str, text: &str) -> Clipping {
+        parse_clipping(&format!("{header}\n{details}\n\n{text}\n")).unwrap()
+    }
+
+    fn date(text: &str) -> String {
+        parse_date(text).unwrap().to_rfc3339()
+    }
+
+    // Tests that highlights are imported and that notes are added to their highlight.
+    #[test]
+    fn clippings_file() {
+        let path = CRATE_ROOT
+            .join("data")
+            .join("kindle")
+            .join("My Clippings.txt");
+
+        let entries = run(&path).unwrap();
+
+        assert_eq!(entries.len(), 4);
+
+        let entry = entries
+            .values()
+            .find(|entry| entry.book.title == "The Art Spirit")
+            .unwrap();
+
+        assert_eq!(entry.book.author, "Robert Henri");
+        assert_eq!(entry.annotations.len(), 1);
+        assert_eq!(
+            entry.annotations[0].body,
+            "We are not here to do what has already been done."
+        );
+        assert_eq!(entry.annotations[0].notes, "#art The reason for painting.");
+        assert_eq!(entry.annotations[0].metadata.page, Some(12));
+
+        let entry = entries
+            .values()
+            .find(|entry| entry.book.title == "Siddhartha")
+            .unwrap();
+
+        assert_eq!(entry.annotations.len(), 2);
+        assert!(entry
+            .annotations
+            .iter()
+            .any(|annotation| annotation.kind == AnnotationKind::Note
+                && annotation.notes == "Vergleichen mit Kapitel 4."));
+    }
+
+    // Tests that the kind, page and location are read from headers in several languages.
+    #[test]
+    fn localized_details() {
+        let cases = [
+            (
+                "- La subrayado en la página 12 | posición 171-173 | Añadido el lunes, 5 de marzo de 2018 22:07:55",
+                ClippingKind::Highlight,
+                Some(12),
+                Some((171, 173)),
+            ),
+            (
+                "- La tua nota a pagina 12 | posizione 173 | Aggiunto in data lunedì 5 marzo 2018 22:07:55",
+                ClippingKind::Note,
+                Some(12),
+                Some((173, 173)),
+            ),
+            (
+                "- Seu destaque ou posição 171-173 | Adicionado: segunda-feira, 5 de março de 2018 22:07:55",
+                ClippingKind::Highlight,
+                None,
+                Some((171, 173)),
+            ),
+            (
+                "- 您在第 12 页（位置 #171-173）的标注 | 添加于 2018年3月5日星期一 下午10:07:55",
+                ClippingKind::Highlight,
+                Some(12),
+                Some((171, 173)),
+            ),
+            (
+                "- Highlight Loc. 1711-23  | Added on Monday, March 5, 2018, 10:07 PM",
+                ClippingKind::Highlight,
+                None,
+                Some((1711, 1723)),
+            ),
+            (
+                "- Votre signet à l'emplacement 201 | Ajouté le lundi 5 mars 2018 22:07:55",
+                ClippingKind::Bookmark,
+                None,
+                Some((201, 201)),
+            ),
+        ];
+
+        for (details, kind, page, location) in cases {
+            let clipping = clipping("The Art Spirit (Robert Henri)", details, "Text");
+
+            assert_eq!(clipping.kind, kind, "{details}");
+            assert_eq!(clipping.page, page, "{details}");
+            assert_eq!(clipping.location, location, "{details}");
+            assert!(clipping.created.is_some(), "{details}");
+        }
+    }
+
+    // Tests that dates are read in several languages and that 12-hour times are converted.
+    #[test]
+    fn localized_dates() {
+        let expected = "2018-03-05T22:07:55+00:00";
+
+        assert_eq!(
+            date(" Added on Monday, March 5, 2018 10:07:55 PM"),
+            expected
+        );
+        assert_eq!(date(" Added on Monday, 5 March 2018 22:07:55"), expected);
+        assert_eq!(date(" Ajouté le lundi 5 mars 2018 22:07:55"), expected);
+        assert_eq!(
+            date(" Hinzugefügt am Montag, 5. März 2018 22:07:55"),
+            expected
+        );
+        assert_eq!(
+            date(" Toegevoegd op maandag 5 maart 2018 22:07:55"),
+            expected
+        );
+        assert_eq!(date("作成日: 2018年3月5日月曜日 午後10:07:55"), expected);
+        assert_eq!(date(" 添加于 2018年3月5日星期一 下午10:07:55"), expected);
+        assert_eq!(
+            date(" Added on Monday, March 5, 2018 12:07:55 AM"),
+            "2018-03-05T00:07:55+00:00"
+        );
+        assert_eq!(
+            date(" Hinzugefügt am Montag, 5. März 2018 12:07:55"),
+            "2018-03-05T12:07:55+00:00"
+        );
+        assert!(parse_date(" Added on 05/03/2018").is_none());
+    }
+
+    // Tests that a book's title and author are split from the clipping's first line.
+    #[test]
+    fn header() {
+        assert_eq!(
+            parse_header("The Art Spirit (Robert Henri)"),
+            ("The Art Spirit".to_string(), "Robert Henri".to_string())
+        );
+        assert_eq!(
+            parse_header("Walden (Life in the Woods) (Henry David Thoreau)"),
+            (
+                "Walden (Life in the Woods)".to_string(),
+                "Henry David Thoreau".to_string()
+            )
+        );
+        assert_eq!(
+            parse_header("Untitled Document"),
+            ("Untitled Document".to_string(), String::new())
+        );
+    }
+
+    // Tests that a file without any highlights or notes is rejected.
+    #[test]
+    fn empty_clippings() {
+        let entries = parse("Not a clippings file.\n==========\n");
+
+        assert!(entries.is_empty());
+    }
+}
@@ -0,0 +1,326 @@
+//! Defines types for importing books and annotations from a Kobo's `KoboReader.sqlite` database.
+//!
+//! A Kobo stores its books in the `content` table and its highlights, notes and bookmarks in the
+//! `Bookmark` table. Both are read the same way Apple Books' databases are. See [`KoboQuery`].
+//!
+//! ```plaintext
+//! Book         content.ContentID ─┐
+//! Annotation   Bookmark.VolumeID ─┘
+//! ```
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+
+use chrono::{DateTime, NaiveDateTime, Utc};
+use rusqlite::{Connection, OpenFlags, Row};
+
+use crate::models::annotation::{Annotation, AnnotationKind, AnnotationMetadata, AnnotationStyle};
+use crate::models::book::{Book, BookMetadata};
+use crate::models::datetime::DateTimeUtc;
+use crate::models::entry::Entries;
+use crate::result::{Error, Result};
+
+/// The width each number of a location's sortkey is zero-padded to.
+const SORTKEY_WIDTH: usize = 6;
+
+/// The number of steps a chapter's progress is divided into for its sortkey.
+const PROGRESS_STEPS: f64 = 1_000_000.0;
+
+/// Defines an interface for converting rows of a Kobo's database into a type. This mirrors
+/// [`ABQuery`][abquery] for Apple Books' databases.
+///
+/// [abquery]: crate::applebooks::macos::ABQuery
+pub trait KoboQuery {
+    /// The query to retrieve rows from the database. The rows are then passed into
+    /// [`KoboQuery::from_row()`] to create instances of the implementing type.
+    const QUERY: &'static str;
+
+    /// Constructs an instance of the implementing type from a [`rusqlite::Row`].
+    fn from_row(row: &Row<'_>) -> Self;
+}
+
+// For creating [`Book`]s from a Kobo's database.
+impl KoboQuery for Book {
+    const QUERY: &'static str = {
+        "SELECT
+            content.ContentID,       -- 0 id
+            content.Title,           -- 1 title
+            content.Attribution,     -- 2 author
+            content.DateLastRead,    -- 3 last_opened
+            content.___PercentRead,  -- 4 progress
+            content.ISBN             -- 5 isbn
+        FROM content
+        WHERE content.ContentType = 6
+        ORDER BY content.Title;"
+    };
+
+    fn from_row(row: &Row<'_>) -> Self {
+        let title: Option<String> = row.get_unwrap(1);
+        let author: Option<String> = row.get_unwrap(2);
+        let last_opened: Option<String> = row.get_unwrap(3);
+        let progress: Option<f64> = row.get_unwrap(4);
+        let isbn: Option<String> = row.get_unwrap(5);
+
+        Self {
+            title: title.unwrap_or_default(),
+            author: author.unwrap_or_default(),
+            metadata: BookMetadata {
+                id: row.get_unwrap(0),
+                asset_id: String::new(),
+                last_opened: last_opened.as_deref().and_then(self::parse_date),
+                progress: progress.map(|progress| progress / 100.0),
+                isbn: isbn
+                    .map(|isbn| isbn.replace('-', ""))
+                    .filter(|isbn| !isbn.is_empty()),
+                is_finished: false,
+                date_finished: None,
+            },
+            cover_path: None,
+            calibre: None,
+        }
+    }
+}
+
+// For creating [`Annotation`]s from a Kobo's database.
+impl KoboQuery for Annotation {
+    const QUERY: &'static str = {
+        "SELECT
+            Bookmark.BookmarkID,       -- 0 id
+            Bookmark.VolumeID,         -- 1 book_id
+            Bookmark.Text,             -- 2 body
+            Bookmark.Annotation,       -- 3 notes
+            Bookmark.DateCreated,      -- 4 created
+            Bookmark.DateModified,     -- 5 modified
+            Bookmark.ChapterProgress,  -- 6 chapter progress
+            content.VolumeIndex,       -- 7 chapter index
+            content.Title              -- 8 chapter
+        FROM Bookmark
+        LEFT JOIN content ON content.ContentID = Bookmark.ContentID
+        WHERE (Bookmark.Text IS NOT NULL AND Bookmark.Text != '')
+            OR (Bookmark.Annotation IS NOT NULL AND Bookmark.Annotation != '') -- Skips dog-ears.
+        ORDER BY Bookmark.VolumeID;"
+    };
+
+    fn from_row(row: &Row<'_>) -> Self {
+        let body: Option<String> = row.get_unwrap(2);
+        let body = body.unwrap_or_default().trim().to_owned();
+        let notes: Option<String> = row.get_unwrap(3);
+        let created: Option<String> = row.get_unwrap(4);
+        let modified: Option<String> = row.get_unwrap(5);
+        let progress: Option<f64> = row.get_unwrap(6);
+        let index: Option<i64> = row.get_unwrap(7);
+        let chapter: Option<String> = row.get_unwrap(8);
+
+        let created = created.as_deref().and_then(self::parse_date);
+        let modified = modified.as_deref().and_then(self::parse_date).or(created);
+
+        // A Kobo highlights in yellow unless its firmware supports choosing a color, which isn't
+        // stored consistently between versions.
+        let style = if body.is_empty() {
+            AnnotationStyle::None
+        } else {
+            AnnotationStyle::Yellow
+        };
+
+        let location = self::sortkey(index, progress);
+
+        Self {
+            kind: AnnotationKind::new(style, &body),
+            body,
+            context_text: String::new(),
+            style,
+            notes: notes.unwrap_or_default(),
+            tags: BTreeSet::new(),
+            favorite: false,
+            properties: BTreeMap::new(),
+            fields: BTreeMap::new(),
+            chapter: chapter.filter(|chapter| !chapter.is_empty()),
+            mentions: Vec::new(),
+            metadata: AnnotationMetadata {
+                id: row.get_unwrap(0),
+                uuid: String::new(),
+                book_id: row.get_unwrap(1),
+                created: created.unwrap_or_default(),
+                modified: modified.unwrap_or_default(),
+                location: location.clone(),
+                location_sortkey: location,
+                epubcfi: String::new(),
+                page: None,
+                rects: Vec::new(),
+            },
+        }
+    }
+}
+
+/// Imports the books and annotations of a Kobo's `KoboReader.sqlite` database.
+///
+/// The database is found in the `.kobo` directory of a connected Kobo. It's opened read-only but
+/// copying it off the device first is recommended. Highlights and notes are imported while
+/// dog-ears i.e. bookmarks are skipped. Books are identified by their Kobo content id.
+///
+/// # Arguments
+///
+/// * `path` - The path to the database.
+///
+/// # Errors
+///
+/// Will return `Err` if the file cannot be opened or isn't a Kobo database.
+pub fn run(path: &Path) -> Result<Entries> {
+    let invalid = |error: rusqlite::Error| Error::ImportInvalidKoboDatabase {
+        path: path.display().to_string(),
+        error: error.to_string(),
+    };
+
+    let connection =
+        Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY).map_err(invalid)?;
+
+    let books = self::query::<Book>(&connection).map_err(invalid)?;
+    let annotations = self::query::<Annotation>(&connection).map_err(invalid)?;
+
+    tracing::debug!(
+        books = books.len(),
+        annotations = annotations.len(),
+        "read Kobo database"
+    );
+
+    Ok(Entries::from_parts(books, annotations))
+}
+
+/// Queries the database and converts the rows into `T`.
+///
+/// # Arguments
+///
+/// * `connection` - The connection to the database.
+///
+/// # Errors
+///
+/// Will return `Err` if the database's schema doesn't match the query.
+fn query<T>(connection: &Connection) -> rusqlite::Result<Vec<T>>
+where
+    T: KoboQuery,
+{
+    let mut statement = connection.prepare(T::QUERY)?;
+
+    let items = statement
+        .query_map([], |row| Ok(T::from_row(row)))?
+        .filter_map(std::result::Result::ok)
+        .collect();
+
+    Ok(items)
+}
+
+/// Parses a date stored by a Kobo. Depending on its firmware, dates are stored in RFC 3339 format
+/// e.g. `2021-11-02T18:27:04Z` or without a time zone e.g. `2021-11-02 18:27:04.000`, in which
+/// case they're read as UTC. Returns `None` if the date cannot be parsed.
+///
+/// # Arguments
+///
+/// * `date` - The stored date.
+fn parse_date(date: &str) -> Option<DateTimeUtc> {
+    let date = date.trim();
+
+    if let Ok(datetime) = DateTime::parse_from_rfc3339(date) {
+        return Some(DateTimeUtc::from(datetime.with_timezone(&Utc)));
+    }
+
+    ["%Y-%m-%dT%H:%M:%S%.f", "%Y-%m-%d %H:%M:%S%.f"]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(date.trim_end_matches('Z'), format).ok())
+        .map(|datetime| DateTimeUtc::from(datetime.and_utc()))
+}
+
+/// Returns a string that sorts annotations into their order of appearance when compared as strings
+/// e.g. `000002.250000`, from the index of their chapter and their progress through it.
+///
+/// # Arguments
+///
+/// * `index` - The chapter's index within the book.
+/// * `progress` - The progress through the chapter from `0.0` to `1.0`.
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+fn sortkey(index: Option<i64>, progress: Option<f64>) -> String {
+    let index = index.unwrap_or_default().max(0);
+    let progress = (progress.unwrap_or_default().clamp(0.0, 1.0) * PROGRESS_STEPS).round() as u64;
+
+    format!("{index:0SORTKEY_WIDTH$}.{progress:0SORTKEY_WIDTH$}")
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    use std::path::PathBuf;
+
+    use crate::defaults::CRATE_ROOT;
+
+    const BOOK_ID: &str = "file:///mnt/onboard/Robert Henri/The Art Spirit.kepub.epub";
+
+    fn database() -> PathBuf {
+        CRATE_ROOT
+            .join("data")
+            .join("kobo")
+            .join("KoboReader.sqlite")
+    }
+
+    // Tests that books and their highlights and notes are read and that dog-ears are skipped.
+    #[test]
+    fn kobo_database() {
+        let entries = run(&database()).unwrap();
+
+        assert_eq!(entries.len(), 2);
+
+        let entry = &entries[BOOK_ID];
+
+        assert_eq!(entry.book.title, "The Art Spirit");
+        assert_eq!(entry.book.author, "Robert Henri");
+        assert_eq!(entry.book.metadata.progress, Some(0.42));
+        assert_eq!(entry.book.metadata.isbn.as_deref(), Some("9780465002634"));
+        assert_eq!(entry.annotations.len(), 2);
+
+        let note = entry
+            .annotations
+            .iter()
+            .find(|annotation| annotation.metadata.id == "bm-2")
+            .unwrap();
+
+        assert_eq!(note.notes, "#favorite A note.");
+        assert_eq!(note.chapter.as_deref(), Some("Introduction"));
+        assert_eq!(note.metadata.location_sortkey, "000001.500000");
+        assert_eq!(
+            note.metadata.created.to_rfc3339(),
+            "2021-10-30T09:30:00+00:00"
+        );
+    }
+
+    // Tests that dates are parsed with and without a time zone.
+    #[test]
+    fn dates() {
+        let expected = "2021-11-02T18:27:04+00:00";
+
+        for date in [
+            "2021-11-02T18:27:04Z",
+            "2021-11-02T18:27:04.000",
+            "2021-11-02 18:27:04.000",
+            "2021-11-02T20:27:04+02:00",
+        ] {
+            assert_eq!(parse_date(date).unwrap().to_rfc3339(), expected, "{date}");
+        }
+
+        assert!(parse_date("").is_none());
+    }
+
+    // Tests that a file that isn't a Kobo database is rejected.
+    #[test]
+    fn invalid_database() {
+        let path = CRATE_ROOT
+            .join("data")
+            .join("exports")
+            .join("v1")
+            .join("library.json");
+
+        assert!(matches!(
+            run(&path),
+            Err(Error::ImportInvalidKoboDatabase { .. })
+        ));
+    }
+}
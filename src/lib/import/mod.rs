@@ -0,0 +1,157 @@
+//! Defines types for importing books and annotations from outside of Apple Books into [`Entries`].
+//!
+//! A previous JSON export can be imported back, which allows filtering, rendering and analyzing an
+//! archived export without access to the Apple Books databases e.g. on a machine other than a Mac.
+//! Exports written by older versions are upgraded to the current format while they're read so
+//! they're imported as if they were exported by the current version. The export itself is left
+//! untouched.
+//!
+//! Annotations made on a Kindle or a Kobo can be imported from the device's clippings file or
+//! database. See [`kindle`] and [`kobo`].
+
+pub mod kindle;
+pub mod kobo;
+
+use std::collections::HashSet;
+use std::path::Path;
+
+use crate::migrate::{self, Upgraded};
+use crate::models::annotation::Annotation;
+use crate::models::book::Book;
+use crate::models::entry::Entries;
+use crate::result::{Error, Result};
+
+/// Imports the books and annotations of a previous export.
+///
+/// The path can be an export directory, a single-file export e.g. `library.json` or a JSON Lines
+/// export. Within a directory, every `book.json` and `annotations.json` file is read, along with
+/// any single-file or JSON Lines export, regardless of its name. Other files e.g. covers and
+/// unrelated JSON files are skipped. Annotations found in more than one file are only imported
+/// once.
+///
+/// # Arguments
+///
+/// * `path` - The path to the export.
+///
+/// # Errors
+///
+/// Will return `Err` if:
+/// * The path doesn't contain any exported books.
+/// * A book was exported by a newer version.
+/// * The export cannot be read as books or annotations.
+/// * Any IO errors are encountered.
+pub fn run(path: &Path) -> Result<Entries> {
+    let (files, _) = migrate::upgrade(path)?;
+
+    let mut books: Vec<Book> = Vec::new();
+    let mut annotations: Vec<Annotation> = Vec::new();
+
+    for file in files {
+        match file.contents {
+            Upgraded::Book(book) => books.push(*book),
+            Upgraded::Annotations(items) => annotations.extend(items),
+            Upgraded::Library(entries) => {
+                for entry in entries {
+                    books.push(entry.book);
+                    annotations.extend(entry.annotations);
+                }
+            }
+            Upgraded::Lines(lines) => {
+                for (book, annotation) in lines {
+                    books.push(book);
+                    annotations.push(annotation);
+                }
+            }
+        }
+    }
+
+    if books.is_empty() {
+        return Err(Error::ImportInvalidSource {
+            path: path.display().to_string(),
+        });
+    }
+
+    let mut seen = HashSet::new();
+    annotations.retain(|annotation| seen.insert(annotation.metadata.id.clone()));
+
+    let entries = Entries::from_parts(books, annotations);
+
+    tracing::debug!(
+        books = entries.len(),
+        annotations = entries
+            .values()
+            .map(|entry| entry.annotations.len())
+            .sum::<usize>(),
+        "imported export"
+    );
+
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    use std::path::PathBuf;
+
+    use crate::defaults::CRATE_ROOT;
+    use crate::models::annotation::AnnotationKind;
+
+    const BOOK_ID: &str = "1969AF0ECA8AE4965029A34316813924";
+
+    fn fixture(name: &str) -> PathBuf {
+        CRATE_ROOT
+            .join("data")
+            .join("exports")
+            .join("v1")
+            .join(name)
+    }
+
+    fn annotation_count(entries: &Entries) -> usize {
+        entries.values().map(|entry| entry.annotations.len()).sum()
+    }
+
+    // Tests that an export directory is imported and upgraded to the current format.
+    #[test]
+    fn export_directory() {
+        let entries = run(&fixture("Robert Henri - The Art Spirit")).unwrap();
+
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[BOOK_ID].book.title, "The Art Spirit");
+        assert_eq!(entries[BOOK_ID].book.metadata.asset_id, BOOK_ID);
+        assert_eq!(annotation_count(&entries), 3);
+        assert!(entries[BOOK_ID]
+            .annotations
+            .iter()
+            .any(|annotation| annotation.kind == AnnotationKind::Note));
+    }
+
+    // Tests that single-file and JSON Lines exports are imported.
+    #[test]
+    fn export_files() {
+        for name in ["library.json", "export.jsonl"] {
+            let entries = run(&fixture(name)).unwrap();
+
+            assert_eq!(entries.len(), 1, "{name}");
+            assert_eq!(annotation_count(&entries), 2, "{name}");
+        }
+    }
+
+    // Tests that annotations found in more than one file are only imported once.
+    #[test]
+    fn duplicate_annotations() {
+        let entries = run(&fixture("")).unwrap();
+
+        assert_eq!(entries.len(), 1);
+        assert_eq!(annotation_count(&entries), 3);
+    }
+
+    // Tests that a path without any exported books is rejected.
+    #[test]
+    fn invalid_source() {
+        let result = run(&fixture("unrelated.json"));
+
+        assert!(matches!(result, Err(Error::ImportInvalidSource { .. })));
+    }
+}
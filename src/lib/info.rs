@@ -0,0 +1,362 @@
+//! Defines types for inspecting a single book along with its annotations.
+//!
+//! This is meant as a quick way to see what data is available for a book before writing filters
+//! or templates for it.
+
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+
+use crate::defaults::DATE_FORMAT_TEMPLATE;
+use crate::models::annotation::{Annotation, AnnotationStyle};
+use crate::models::book::Book;
+use crate::models::datetime::DateTimeUtc;
+use crate::models::entry::{Entries, Entry};
+use crate::result::{Error, Result};
+
+/// Finds a book by its id or its asset id. If neither match exactly, a case-insensitive prefix of
+/// either is accepted as long as it matches a single book.
+///
+/// # Arguments
+///
+/// * `entries` - The entries to search.
+/// * `id` - The book's id, asset id or a prefix of either.
+///
+/// # Errors
+///
+/// Will return `Err` if no book or more than one book matches the id.
+pub fn find<'a>(entries: &'a Entries, id: &str) -> Result<&'a Entry> {
+    let exact = entries
+        .values()
+        .find(|entry| entry.book.metadata.id == id || entry.book.metadata.asset_id == id);
+
+    if let Some(entry) = exact {
+        return Ok(entry);
+    }
+
+    let prefix = id.to_lowercase();
+
+    let mut matches: Vec<&Entry> = entries
+        .values()
+        .filter(|entry| {
+            [&entry.book.metadata.id, &entry.book.metadata.asset_id]
+                .iter()
+                .any(|value| !prefix.is_empty() && value.to_lowercase().starts_with(&prefix))
+        })
+        .collect();
+
+    match matches.len() {
+        0 => Err(Error::BookNotFound { id: id.to_owned() }),
+        1 => Ok(matches.remove(0)),
+        _ => {
+            matches.sort_by(|a, b| a.book.metadata.id.cmp(&b.book.metadata.id));
+
+            Err(Error::BookIdAmbiguous {
+                id: id.to_owned(),
+                matches: matches
+                    .iter()
+                    .map(|entry| format!("{} ({})", entry.book.metadata.id, entry.book.title))
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            })
+        }
+    }
+}
+
+/// A struct representing a book, its stats and its annotations.
+#[derive(Debug, Serialize)]
+pub struct BookInfo<'a> {
+    /// The book.
+    pub book: &'a Book,
+
+    /// The book's stats.
+    pub stats: BookStats,
+
+    /// The book's annotations, sorted by their location.
+    pub annotations: Vec<&'a Annotation>,
+}
+
+impl<'a> From<&'a Entry> for BookInfo<'a> {
+    fn from(entry: &'a Entry) -> Self {
+        let mut annotations: Vec<&Annotation> = entry.annotations.iter().collect();
+        annotations.sort();
+
+        Self {
+            book: &entry.book,
+            stats: BookStats::new(&annotations),
+            annotations,
+        }
+    }
+}
+
+/// A struct representing stats on a book's annotations.
+#[derive(Debug, Default, Serialize)]
+pub struct BookStats {
+    /// The number of annotations.
+    pub annotations: usize,
+
+    /// The number of annotations with notes.
+    pub notes: usize,
+
+    /// The number of annotations per style.
+    pub styles: BTreeMap<AnnotationStyle, usize>,
+
+    /// The number of annotations per `#tag`.
+    pub tags: BTreeMap<String, usize>,
+
+    /// The date of the earliest annotation.
+    pub first_annotated: Option<DateTimeUtc>,
+
+    /// The date of the latest annotation.
+    pub last_annotated: Option<DateTimeUtc>,
+}
+
+impl BookStats {
+    /// Creates a new instance of [`BookStats`].
+    ///
+    /// # Arguments
+    ///
+    /// * `annotations` - The annotations to build the stats from.
+    fn new(annotations: &[&Annotation]) -> Self {
+        let mut stats = Self {
+            annotations: annotations.len(),
+            ..Default::default()
+        };
+
+        for annotation in annotations {
+            if !annotation.notes.is_empty() {
+                stats.notes += 1;
+            }
+
+            *stats.styles.entry(annotation.style).or_default() += 1;
+
+            for tag in &annotation.tags {
+                *stats.tags.entry(tag.clone()).or_default() += 1;
+            }
+        }
+
+        let created = annotations
+            .iter()
+            .map(|annotation| annotation.metadata.created);
+
+        stats.first_annotated = created.clone().min_by_key(|date| **date);
+        stats.last_annotated = created.max_by_key(|date| **date);
+
+        stats
+    }
+}
+
+/// Renders a [`BookInfo`] as readable text.
+///
+/// # Arguments
+///
+/// * `info` - The book info to render.
+#[must_use]
+pub fn to_text(info: &BookInfo<'_>) -> String {
+    let book = info.book;
+    let stats = &info.stats;
+
+    let date = |date: Option<&DateTimeUtc>| {
+        date.map(|date| date.format(DATE_FORMAT_TEMPLATE).to_string())
+            .unwrap_or_default()
+    };
+
+    let counts = |counts: Vec<(String, usize)>| {
+        counts
+            .into_iter()
+            .map(|(key, count)| format!("{key} {count}"))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+
+    let mut lines = vec![format!("{} by {}", book.title, book.author), String::new()];
+
+    lines.extend(self::fields(&[
+        ("id", book.metadata.id.clone()),
+        ("asset-id", book.metadata.asset_id.clone()),
+        ("last-opened", date(book.metadata.last_opened.as_ref())),
+        (
+            "progress",
+            book.metadata
+                .progress
+                .map(|progress| format!("{:.0}%", progress * 100.0))
+                .unwrap_or_default(),
+        ),
+        ("annotations", stats.annotations.to_string()),
+        ("notes", stats.notes.to_string()),
+        ("first-annotated", date(stats.first_annotated.as_ref())),
+        ("last-annotated", date(stats.last_annotated.as_ref())),
+        (
+            "styles",
+            counts(
+                stats
+                    .styles
+                    .iter()
+                    .map(|(style, count)| (format!("{style:?}").to_lowercase(), *count))
+                    .collect(),
+            ),
+        ),
+        (
+            "tags",
+            counts(
+                stats
+                    .tags
+                    .iter()
+                    .map(|(tag, count)| (tag.clone(), *count))
+                    .collect(),
+            ),
+        ),
+    ]));
+
+    for annotation in &info.annotations {
+        lines.push(String::new());
+
+        let mut header = vec![
+            annotation.metadata.location.clone(),
+            date(Some(&annotation.metadata.created)),
+            format!("{:?}", annotation.style).to_lowercase(),
+        ];
+        header.extend(annotation.tags.iter().cloned());
+
+        lines.push(header.join("  "));
+        lines.extend(annotation.body.lines().map(|line| format!("  {line}")));
+
+        if !annotation.notes.is_empty() {
+            lines.extend(annotation.notes.lines().map(|line| format!("  > {line}")));
+        }
+    }
+
+    lines.push(String::new());
+    lines.join("\n")
+}
+
+/// Returns aligned `key  value` lines, skipping any empty values.
+///
+/// # Arguments
+///
+/// * `fields` - The keys and values to align.
+fn fields(fields: &[(&str, String)]) -> Vec<String> {
+    let width = fields.iter().map(|(key, _)| key.len()).max().unwrap_or(0);
+
+    fields
+        .iter()
+        .filter(|(_, value)| !value.is_empty())
+        .map(|(key, value)| format!("  {key:<width$}  {value}"))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    use crate::models::annotation::AnnotationMetadata;
+    use crate::models::book::BookMetadata;
+
+    fn entry(id: &str, title: &str) -> Entry {
+        Entry {
+            book: Book {
+                title: title.to_string(),
+                author: "Robert Henri".to_string(),
+                metadata: BookMetadata {
+                    id: id.to_string(),
+                    asset_id: id.to_string(),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            annotations: Vec::new(),
+        }
+    }
+
+    fn entries() -> Entries {
+        let mut entries = Entries::default();
+
+        for (id, title) in [
+            ("ABC123", "The Art Spirit"),
+            ("ABD456", "Think on These Things"),
+        ] {
+            entries.insert(id.to_string(), entry(id, title));
+        }
+
+        entries
+    }
+
+    // Tests that books are found by their id or by a unique case-insensitive prefix.
+    #[test]
+    fn find_book() {
+        let entries = entries();
+
+        assert_eq!(
+            find(&entries, "ABC123").unwrap().book.title,
+            "The Art Spirit"
+        );
+        assert_eq!(
+            find(&entries, "abd").unwrap().book.title,
+            "Think on These Things"
+        );
+    }
+
+    // Tests that a missing or ambiguous id returns an error.
+    #[test]
+    fn find_book_errors() {
+        let entries = entries();
+
+        assert!(matches!(
+            find(&entries, "XYZ"),
+            Err(Error::BookNotFound { .. })
+        ));
+        assert!(matches!(
+            find(&entries, "AB"),
+            Err(Error::BookIdAmbiguous { .. })
+        ));
+        assert!(matches!(
+            find(&entries, ""),
+            Err(Error::BookNotFound { .. })
+        ));
+    }
+
+    // Tests that stats count annotations, notes, styles and tags.
+    #[test]
+    fn stats() {
+        let annotation =
+            |location: &str, style: AnnotationStyle, notes: &str, tags: &[&str]| Annotation {
+                style,
+                notes: notes.to_string(),
+                tags: tags.iter().map(ToString::to_string).collect(),
+                metadata: AnnotationMetadata {
+                    location: location.to_string(),
+                    ..Default::default()
+                },
+                ..Default::default()
+            };
+
+        let mut entry = entry("ABC123", "The Art Spirit");
+        entry.annotations = vec![
+            annotation("2", AnnotationStyle::Yellow, "", &["#quote"]),
+            annotation(
+                "1",
+                AnnotationStyle::Yellow,
+                "A note.",
+                &["#quote", "#idea"],
+            ),
+            annotation("3", AnnotationStyle::Green, "", &[]),
+        ];
+
+        let info = BookInfo::from(&entry);
+
+        assert_eq!(info.stats.annotations, 3);
+        assert_eq!(info.stats.notes, 1);
+        assert_eq!(info.stats.styles[&AnnotationStyle::Yellow], 2);
+        assert_eq!(info.stats.tags["#quote"], 2);
+        assert_eq!(info.stats.tags["#idea"], 1);
+
+        let locations: Vec<&str> = info
+            .annotations
+            .iter()
+            .map(|annotation| annotation.metadata.location.as_str())
+            .collect();
+
+        assert_eq!(locations, ["1", "2", "3"]);
+    }
+}
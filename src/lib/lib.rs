@@ -1,14 +1,32 @@
 //! Defines types used for interacting with Apple Books.
 
+pub mod analysis;
 pub mod applebooks;
 pub mod backup;
+pub mod calibre;
+pub mod conflict;
 pub mod contexts;
 pub mod defaults;
+pub mod diff;
+pub mod epub;
 pub mod export;
 pub mod filter;
+pub mod import;
+pub mod info;
+pub mod migrate;
 pub mod models;
+pub mod pack;
+pub mod pipeline;
 pub mod process;
+pub mod reading_log;
 pub mod render;
+pub mod restore;
 pub mod result;
+pub mod review;
+pub mod schema;
+pub mod search;
+pub mod snapshot;
 pub mod strings;
+pub mod sync;
+pub mod upload;
 pub mod utils;
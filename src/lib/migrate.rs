@@ -0,0 +1,834 @@
+//! Defines types for upgrading exports written by older versions to the current format.
+//!
+//! Every exported book is stamped with the [`FORMAT_VERSION`] it was exported in. Exports written
+//! before the format was versioned aren't stamped and are treated as version `1`. Each change to
+//! the format adds a migration that upgrades books and annotations from the previous version.
+//! Migrations are applied in order until the data reaches the current version.
+//!
+//! An `annotations.json` file isn't stamped itself. It's migrated from the version of the book
+//! file next to it i.e. `book.json` or `[name].book.json`. If there's none, it's migrated from
+//! version `1`. Migrations only add or rewrite fields that are missing or outdated so migrating
+//! data that's already current leaves it unchanged.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use serde_json::{json, Map, Value};
+
+use crate::export::{AnnotationLine, ExportEntry, VersionedBook, FORMAT_VERSION};
+use crate::models::annotation::{Annotation, AnnotationKind, AnnotationStyle};
+use crate::models::book::Book;
+use crate::models::entry::Entry;
+use crate::models::epubcfi;
+use crate::result::{Error, Result};
+use crate::utils;
+
+/// The version of exports written before the format was versioned.
+const UNVERSIONED: u64 = 1;
+
+/// The key exported books are stamped with.
+const VERSION_KEY: &str = "format_version";
+
+/// The suffix of book filenames an `annotations.json` file can be paired with.
+const BOOK_SUFFIX: &str = "book.json";
+
+/// The suffix of annotations filenames a `book.json` file can be paired with.
+const ANNOTATIONS_SUFFIX: &str = "annotations.json";
+
+/// The migrations in the order they're applied.
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 2,
+        book: self::v2_book,
+        annotation: self::v2_annotation,
+    },
+    Migration {
+        version: 3,
+        book: self::v3_book,
+        annotation: self::v3_annotation,
+    },
+];
+
+/// A struct representing the upgrade of books and annotations from the previous version of the
+/// format.
+#[derive(Debug, Clone, Copy)]
+struct Migration {
+    /// The version the migration upgrades to.
+    version: u64,
+
+    /// Upgrades a book.
+    book: fn(&mut Map<String, Value>),
+
+    /// Upgrades an annotation.
+    annotation: fn(&mut Map<String, Value>),
+}
+
+/// A struct representing options for migrating exports.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct MigrateOptions {
+    /// Toggles reporting which files would be migrated without writing them.
+    pub dry_run: bool,
+}
+
+/// A struct representing the outcome of a migration.
+#[derive(Debug, Default, Clone)]
+pub struct MigrateSummary {
+    /// The files that were, or with a dry-run would be, migrated.
+    pub migrated: Vec<PathBuf>,
+
+    /// The number of export files that were already current.
+    pub current: usize,
+
+    /// The JSON files that aren't part of an export and were left untouched.
+    pub skipped: Vec<PathBuf>,
+}
+
+/// Migrates an export to the current [`FORMAT_VERSION`].
+///
+/// The path can be an export directory, a single-file export e.g. `library.json` or a JSON Lines
+/// export. Within a directory, every `.json`, `.jsonl` and `.ndjson` file is migrated. Compressed
+/// files and archives aren't supported.
+///
+/// Migrated files are rewritten in place as if they were exported by the current version. Files
+/// that are already current are left untouched.
+///
+/// # Arguments
+///
+/// * `path` - The path to the export.
+/// * `options` - The migration options.
+///
+/// # Errors
+///
+/// Will return `Err` if:
+/// * A book was exported by a newer version.
+/// * Any IO errors are encountered.
+/// * [`serde_json`][serde-json] encounters any errors while writing a file.
+///
+/// [serde-json]: https://docs.rs/serde_json/latest/serde_json/
+pub fn run<O>(path: &Path, options: O) -> Result<MigrateSummary>
+where
+    O: Into<MigrateOptions>,
+{
+    let options: MigrateOptions = options.into();
+
+    let (files, skipped) = self::upgrade(path)?;
+
+    let mut summary = MigrateSummary {
+        skipped,
+        ..Default::default()
+    };
+
+    for file in files {
+        if !file.changed {
+            summary.current += 1;
+            continue;
+        }
+
+        if !options.dry_run {
+            self::write(&file.path, &file.contents.to_json()?)?;
+        }
+
+        tracing::debug!(path = %file.path.display(), "migrated");
+        summary.migrated.push(file.path);
+    }
+
+    Ok(summary)
+}
+
+/// A struct representing an export file upgraded to the current format.
+#[derive(Debug)]
+pub(crate) struct UpgradedFile {
+    /// The path to the file.
+    pub(crate) path: PathBuf,
+
+    /// The file's upgraded contents.
+    pub(crate) contents: Upgraded,
+
+    /// Whether the file was written by an older version.
+    pub(crate) changed: bool,
+}
+
+/// An enum representing the contents of an export file upgraded to the current format.
+#[derive(Debug)]
+pub(crate) enum Upgraded {
+    /// A `book.json` file.
+    Book(Box<Book>),
+
+    /// An `annotations.json` file.
+    Annotations(Vec<Annotation>),
+
+    /// A single-file export.
+    Library(Vec<Entry>),
+
+    /// A JSON Lines export. Each annotation is paired with its book.
+    Lines(Vec<(Book, Annotation)>),
+}
+
+impl Upgraded {
+    /// Serializes the contents as if they were exported by the current version.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if [`serde_json`][serde-json] encounters any errors.
+    ///
+    /// [serde-json]: https://docs.rs/serde_json/latest/serde_json/
+    fn to_json(&self) -> Result<String> {
+        let json = match self {
+            Self::Book(book) => serde_json::to_string_pretty(&VersionedBook::from(&**book))?,
+            Self::Annotations(annotations) => serde_json::to_string_pretty(annotations)?,
+            Self::Library(entries) => {
+                let entries: Vec<ExportEntry<'_>> = entries.iter().map(ExportEntry::from).collect();
+
+                serde_json::to_string_pretty(&entries)?
+            }
+            Self::Lines(lines) => {
+                let mut json = String::new();
+
+                for (book, annotation) in lines {
+                    let line = AnnotationLine {
+                        annotation,
+                        book: VersionedBook::from(book),
+                    };
+
+                    json.push_str(&serde_json::to_string(&line)?);
+                    json.push('\n');
+                }
+
+                json
+            }
+        };
+
+        Ok(json)
+    }
+}
+
+/// Reads every file of an export and upgrades it to the current format. Returns the upgraded files
+/// along with the paths of the JSON files that aren't part of an export. See [`run()`] for the
+/// supported exports.
+///
+/// # Arguments
+///
+/// * `path` - The path to the export.
+///
+/// # Errors
+///
+/// Will return `Err` if:
+/// * A book was exported by a newer version.
+/// * The upgraded data cannot be read as books or annotations.
+/// * Any IO errors are encountered.
+pub(crate) fn upgrade(path: &Path) -> Result<(Vec<UpgradedFile>, Vec<PathBuf>)> {
+    let files: Vec<PathBuf> = if path.is_dir() {
+        walkdir::WalkDir::new(path)
+            .sort_by_file_name()
+            .into_iter()
+            .filter_map(std::result::Result::ok)
+            .filter(|entry| entry.file_type().is_file())
+            .map(walkdir::DirEntry::into_path)
+            .filter(|path| {
+                matches!(
+                    utils::get_file_extension(path),
+                    Some("json" | "jsonl" | "ndjson")
+                )
+            })
+            .collect()
+    } else {
+        vec![path.to_owned()]
+    };
+
+    // The versions of all books are read before any file is upgraded so that an
+    // `annotations.json` file is upgraded from its book's version, even if the book is rewritten
+    // first.
+    let mut book_versions = HashMap::new();
+
+    for file in &files {
+        if let Some(ExportFile::Book(book)) = self::read(file)? {
+            book_versions.insert(file.clone(), self::version(&book));
+        }
+    }
+
+    let mut upgraded = Vec::new();
+    let mut skipped = Vec::new();
+
+    for file in files {
+        let Some(export_file) = self::read(&file)? else {
+            tracing::debug!(path = %file.display(), "skipped reading");
+            skipped.push(file);
+            continue;
+        };
+
+        let version = self::paired_book(&file)
+            .and_then(|book| book_versions.get(&book).copied())
+            .unwrap_or(UNVERSIONED);
+
+        let (contents, changed) = self::upgrade_file(export_file, version, &file)?;
+
+        upgraded.push(UpgradedFile {
+            path: file,
+            contents,
+            changed,
+        });
+    }
+
+    Ok((upgraded, skipped))
+}
+
+/// An enum representing the kinds of files an export consists of.
+#[derive(Debug)]
+enum ExportFile {
+    /// A `book.json` file.
+    Book(Map<String, Value>),
+
+    /// An `annotations.json` file.
+    Annotations(Vec<Value>),
+
+    /// A single-file export i.e. a list of books, each with its annotations.
+    Library(Vec<Value>),
+
+    /// A JSON Lines export i.e. one annotation per line, each with its book.
+    Lines(Vec<Value>),
+}
+
+/// Reads an export file. Returns `None` if the file isn't part of an export.
+///
+/// # Arguments
+///
+/// * `path` - The path to the file.
+///
+/// # Errors
+///
+/// Will return `Err` if any IO errors are encountered.
+fn read(path: &Path) -> Result<Option<ExportFile>> {
+    let Ok(contents) = String::from_utf8(std::fs::read(path)?) else {
+        return Ok(None);
+    };
+
+    if matches!(utils::get_file_extension(&path), Some("jsonl" | "ndjson")) {
+        let lines: Option<Vec<Value>> = contents
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| serde_json::from_str::<Value>(line).ok())
+            .collect();
+
+        return Ok(lines
+            .filter(|lines| lines.iter().all(|line| line.get("book").is_some()))
+            .map(ExportFile::Lines));
+    }
+
+    let Ok(value) = serde_json::from_str::<Value>(&contents) else {
+        return Ok(None);
+    };
+
+    let file = match value {
+        Value::Object(object)
+            if object.contains_key("title") && object.contains_key("metadata") =>
+        {
+            ExportFile::Book(object)
+        }
+        Value::Array(items) if items.iter().all(Value::is_object) => {
+            let is_entry =
+                |item: &Value| item.get("book").is_some() && item.get("annotations").is_some();
+            let is_annotation =
+                |item: &Value| item.get("body").is_some() && item.get("metadata").is_some();
+
+            if !items.is_empty() && items.iter().all(is_entry) {
+                ExportFile::Library(items)
+            } else if items.iter().all(is_annotation) {
+                ExportFile::Annotations(items)
+            } else {
+                return Ok(None);
+            }
+        }
+        _ => return Ok(None),
+    };
+
+    Ok(Some(file))
+}
+
+/// Upgrades the contents of an export file. Returns the upgraded contents and whether they
+/// changed.
+///
+/// # Arguments
+///
+/// * `file` - The export file.
+/// * `version` - The version of an `annotations.json` file. Other files are stamped with theirs.
+/// * `path` - The path to the file.
+///
+/// # Errors
+///
+/// Will return `Err` if:
+/// * A book was exported by a newer version.
+/// * The upgraded data cannot be read as books or annotations.
+fn upgrade_file(file: ExportFile, version: u64, path: &Path) -> Result<(Upgraded, bool)> {
+    let invalid = |error: serde_json::Error| Error::MigrateInvalidFile {
+        path: path.display().to_string(),
+        error: error.to_string(),
+    };
+
+    let upgraded = match file {
+        ExportFile::Book(mut book) => {
+            let changed = self::migrate_book(&mut book, path)?;
+            let book: Book = serde_json::from_value(Value::Object(book)).map_err(invalid)?;
+
+            (Upgraded::Book(Box::new(book)), changed)
+        }
+        ExportFile::Annotations(mut annotations) => {
+            let changed = self::migrate_annotations(&mut annotations, version);
+            let annotations: Vec<Annotation> =
+                serde_json::from_value(Value::Array(annotations)).map_err(invalid)?;
+
+            (Upgraded::Annotations(annotations), changed)
+        }
+        ExportFile::Library(entries) => {
+            let mut changed = false;
+            let mut upgraded = Vec::with_capacity(entries.len());
+
+            for entry in entries {
+                let (mut book, mut annotations) =
+                    self::split_entry(entry).ok_or_else(|| Error::MigrateInvalidFile {
+                        path: path.display().to_string(),
+                        error: "expected an entry with a book and its annotations".to_string(),
+                    })?;
+
+                let version = self::version(&book);
+
+                changed |= self::migrate_book(&mut book, path)?;
+                changed |= self::migrate_annotations(&mut annotations, version);
+
+                upgraded.push(Entry {
+                    book: serde_json::from_value(Value::Object(book)).map_err(invalid)?,
+                    annotations: serde_json::from_value(Value::Array(annotations))
+                        .map_err(invalid)?,
+                });
+            }
+
+            (Upgraded::Library(upgraded), changed)
+        }
+        ExportFile::Lines(lines) => {
+            let mut changed = false;
+            let mut upgraded = Vec::with_capacity(lines.len());
+
+            for line in lines {
+                let Value::Object(mut annotation) = line else {
+                    continue;
+                };
+
+                let Some(Value::Object(mut book)) = annotation.remove("book") else {
+                    continue;
+                };
+
+                let version = self::version(&book);
+
+                changed |= self::migrate_book(&mut book, path)?;
+                changed |= self::migrate_annotation(&mut annotation, version);
+
+                upgraded.push((
+                    serde_json::from_value(Value::Object(book)).map_err(invalid)?,
+                    serde_json::from_value(Value::Object(annotation)).map_err(invalid)?,
+                ));
+            }
+
+            (Upgraded::Lines(upgraded), changed)
+        }
+    };
+
+    Ok(upgraded)
+}
+
+/// Migrates a book from the version it's stamped with. Returns `true` if the book changed.
+///
+/// # Arguments
+///
+/// * `book` - The book to migrate.
+/// * `path` - The path to the book's file.
+///
+/// # Errors
+///
+/// Will return `Err` if the book was exported by a newer version.
+fn migrate_book(book: &mut Map<String, Value>, path: &Path) -> Result<bool> {
+    let version = self::version(book);
+
+    if version > FORMAT_VERSION {
+        return Err(Error::MigrateUnsupportedVersion {
+            path: path.display().to_string(),
+            version,
+        });
+    }
+
+    for migration in self::pending(version) {
+        (migration.book)(book);
+    }
+
+    book.insert(VERSION_KEY.to_string(), json!(FORMAT_VERSION));
+
+    Ok(version < FORMAT_VERSION)
+}
+
+/// Migrates a list of annotations from a version. Returns `true` if any annotation changed.
+///
+/// # Arguments
+///
+/// * `annotations` - The annotations to migrate.
+/// * `version` - The version to migrate from.
+fn migrate_annotations(annotations: &mut [Value], version: u64) -> bool {
+    let mut changed = false;
+
+    for annotation in annotations {
+        if let Value::Object(annotation) = annotation {
+            changed |= self::migrate_annotation(annotation, version);
+        }
+    }
+
+    changed
+}
+
+/// Migrates an annotation from a version. Returns `true` if the annotation changed.
+///
+/// # Arguments
+///
+/// * `annotation` - The annotation to migrate.
+/// * `version` - The version to migrate from.
+fn migrate_annotation(annotation: &mut Map<String, Value>, version: u64) -> bool {
+    let original = annotation.clone();
+
+    for migration in self::pending(version) {
+        (migration.annotation)(annotation);
+    }
+
+    *annotation != original
+}
+
+/// Returns the migrations needed to upgrade data from a version.
+///
+/// # Arguments
+///
+/// * `version` - The version to upgrade from.
+fn pending(version: u64) -> impl Iterator<Item = &'static Migration> {
+    MIGRATIONS
+        .iter()
+        .filter(move |migration| migration.version > version)
+}
+
+/// Returns the version a book is stamped with or [`UNVERSIONED`] if it isn't stamped.
+///
+/// # Arguments
+///
+/// * `book` - The book.
+fn version(book: &Map<String, Value>) -> u64 {
+    book.get(VERSION_KEY)
+        .and_then(Value::as_u64)
+        .unwrap_or(UNVERSIONED)
+}
+
+/// Splits an entry of a single-file export into its book and annotations.
+///
+/// # Arguments
+///
+/// * `entry` - The entry to split.
+fn split_entry(entry: Value) -> Option<(Map<String, Value>, Vec<Value>)> {
+    let Value::Object(mut entry) = entry else {
+        return None;
+    };
+
+    match (entry.remove("book"), entry.remove("annotations")) {
+        (Some(Value::Object(book)), Some(Value::Array(annotations))) => Some((book, annotations)),
+        _ => None,
+    }
+}
+
+/// Returns the path of the book file an `annotations.json` file belongs to e.g.
+/// `annotations.json` to `book.json` and `[name].annotations.json` to `[name].book.json`.
+///
+/// # Arguments
+///
+/// * `path` - The path to the `annotations.json` file.
+fn paired_book(path: &Path) -> Option<PathBuf> {
+    let filename = path.file_name()?.to_str()?;
+    let prefix = filename.strip_suffix(ANNOTATIONS_SUFFIX)?;
+
+    Some(path.with_file_name(format!("{prefix}{BOOK_SUFFIX}")))
+}
+
+/// Writes a file. The contents are written to a temporary file first and then renamed so that an
+/// interruption never leaves a partially migrated file.
+///
+/// # Arguments
+///
+/// * `path` - The path to the file.
+/// * `contents` - The file's contents.
+///
+/// # Errors
+///
+/// Will return `Err` if any IO errors are encountered.
+fn write(path: &Path, contents: &str) -> Result<()> {
+    let mut temp = path.as_os_str().to_owned();
+    temp.push(".tmp");
+
+    std::fs::write(&temp, contents)?;
+    std::fs::rename(&temp, path)?;
+
+    Ok(())
+}
+
+/// Sets a key to a value unless it's already set.
+///
+/// # Arguments
+///
+/// * `object` - The object to set the key on.
+/// * `key` - The key.
+/// * `value` - A function returning the value.
+fn insert_missing<F>(object: &mut Map<String, Value>, key: &str, value: F)
+where
+    F: FnOnce(&Map<String, Value>) -> Value,
+{
+    if !object.contains_key(key) {
+        let value = value(object);
+        object.insert(key.to_string(), value);
+    }
+}
+
+/// Upgrades a book to version `2`, adding its asset id, reading progress and ISBN.
+///
+/// Books used to be identified by their asset id only so it's copied from the book's id.
+///
+/// # Arguments
+///
+/// * `book` - The book to upgrade.
+fn v2_book(book: &mut Map<String, Value>) {
+    if let Some(Value::Object(metadata)) = book.get_mut("metadata") {
+        self::insert_missing(metadata, "asset_id", |metadata| {
+            metadata.get("id").cloned().unwrap_or_else(|| json!(""))
+        });
+        self::insert_missing(metadata, "progress", |_| Value::Null);
+        self::insert_missing(metadata, "isbn", |_| Value::Null);
+    }
+}
+
+/// Upgrades an annotation to version `2`, adding its context, kind, properties, chapter, mentions
+/// and its UUID, sortkey, page and rects.
+///
+/// Annotations used to be identified by their UUID only so it's copied from the annotation's id.
+/// The kind and sortkey are derived from the annotation's existing fields.
+///
+/// # Arguments
+///
+/// * `annotation` - The annotation to upgrade.
+fn v2_annotation(annotation: &mut Map<String, Value>) {
+    self::insert_missing(annotation, "context_text", |_| json!(""));
+    self::insert_missing(annotation, "kind", |annotation| {
+        let style: AnnotationStyle = annotation
+            .get("style")
+            .and_then(|style| serde_json::from_value(style.clone()).ok())
+            .unwrap_or_default();
+        let body = annotation
+            .get("body")
+            .and_then(Value::as_str)
+            .unwrap_or_default();
+
+        json!(AnnotationKind::new(style, body))
+    });
+    self::insert_missing(annotation, "properties", |_| json!({}));
+    self::insert_missing(annotation, "chapter", |_| Value::Null);
+    self::insert_missing(annotation, "mentions", |_| json!([]));
+
+    if let Some(Value::Object(metadata)) = annotation.get_mut("metadata") {
+        self::insert_missing(metadata, "uuid", |metadata| {
+            metadata.get("id").cloned().unwrap_or_else(|| json!(""))
+        });
+        self::insert_missing(metadata, "location_sortkey", |metadata| {
+            let epubcfi = metadata
+                .get("epubcfi")
+                .and_then(Value::as_str)
+                .unwrap_or_default();
+
+            json!(epubcfi::sortkey(epubcfi))
+        });
+        self::insert_missing(metadata, "page", |_| Value::Null);
+        self::insert_missing(metadata, "rects", |_| json!([]));
+    }
+}
+
+/// Upgrades a book to version `3`, adding whether and when it was finished.
+///
+/// # Arguments
+///
+/// * `book` - The book to upgrade.
+fn v3_book(book: &mut Map<String, Value>) {
+    if let Some(Value::Object(metadata)) = book.get_mut("metadata") {
+        self::insert_missing(metadata, "is_finished", |_| json!(false));
+        self::insert_missing(metadata, "date_finished", |_| Value::Null);
+    }
+}
+
+/// Upgrades an annotation to version `3`, adding whether it's marked as a favorite and its fields.
+///
+/// # Arguments
+///
+/// * `annotation` - The annotation to upgrade.
+fn v3_annotation(annotation: &mut Map<String, Value>) {
+    self::insert_missing(annotation, "favorite", |_| json!(false));
+    self::insert_missing(annotation, "fields", |_| json!({}));
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    use crate::defaults::{CRATE_ROOT, TEMP_OUTPUT_DIRECTORY};
+
+    /// Copies a fixture export to a test's temporary directory and returns the copy's path.
+    fn fixture(name: &str, test: &str) -> PathBuf {
+        let source = CRATE_ROOT.join("data").join("exports").join(name);
+        let destination = TEMP_OUTPUT_DIRECTORY.join(format!("tests-migrate-{test}"));
+
+        if destination.exists() {
+            std::fs::remove_dir_all(&destination).unwrap();
+        }
+
+        for entry in walkdir::WalkDir::new(&source)
+            .into_iter()
+            .filter_map(std::result::Result::ok)
+        {
+            let path = destination.join(entry.path().strip_prefix(&source).unwrap());
+
+            if entry.file_type().is_dir() {
+                std::fs::create_dir_all(&path).unwrap();
+            } else {
+                std::fs::copy(entry.path(), &path).unwrap();
+            }
+        }
+
+        destination
+    }
+
+    fn read_json(path: &Path) -> Value {
+        serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap()
+    }
+
+    // Tests that a version `1` export directory is upgraded to the current version.
+    #[test]
+    fn v1_directory() {
+        let directory = fixture("v1", "v1-directory");
+
+        let summary = run(&directory, MigrateOptions::default()).unwrap();
+
+        assert_eq!(summary.migrated.len(), 4);
+        assert_eq!(summary.current, 0);
+        assert_eq!(summary.skipped, [directory.join("unrelated.json")]);
+
+        let book = read_json(
+            &directory
+                .join("Robert Henri - The Art Spirit")
+                .join("book.json"),
+        );
+
+        assert_eq!(book[VERSION_KEY], FORMAT_VERSION);
+        assert_eq!(book["metadata"]["asset_id"], book["metadata"]["id"]);
+        assert!(book["metadata"]["isbn"].is_null());
+        assert_eq!(book["metadata"]["is_finished"], false);
+
+        let annotations = read_json(
+            &directory
+                .join("Robert Henri - The Art Spirit")
+                .join("annotations.json"),
+        );
+        let annotation = &annotations[0];
+
+        assert_eq!(annotation["kind"], "highlight");
+        assert_eq!(annotation["metadata"]["uuid"], annotation["metadata"]["id"]);
+        assert_eq!(
+            annotation["metadata"]["location_sortkey"],
+            epubcfi::sortkey(annotation["metadata"]["epubcfi"].as_str().unwrap())
+        );
+        assert_eq!(annotations[1]["kind"], "note");
+        assert_eq!(annotations[2]["kind"], "underline");
+
+        let library = read_json(&directory.join("library.json"));
+
+        assert_eq!(library[0]["book"][VERSION_KEY], FORMAT_VERSION);
+        assert_eq!(library[0]["annotations"][0]["kind"], "highlight");
+
+        let lines = std::fs::read_to_string(directory.join("export.jsonl")).unwrap();
+
+        for line in lines.lines() {
+            let line: Value = serde_json::from_str(line).unwrap();
+
+            assert_eq!(line["book"][VERSION_KEY], FORMAT_VERSION);
+            assert!(line["metadata"]["uuid"].is_string());
+        }
+    }
+
+    // Tests that migrating an already migrated export leaves it untouched.
+    #[test]
+    fn idempotent() {
+        let directory = fixture("v1", "idempotent");
+
+        run(&directory, MigrateOptions::default()).unwrap();
+
+        let book = directory
+            .join("Robert Henri - The Art Spirit")
+            .join("book.json");
+        let contents = std::fs::read_to_string(&book).unwrap();
+
+        let summary = run(&directory, MigrateOptions::default()).unwrap();
+
+        assert!(summary.migrated.is_empty());
+        assert_eq!(summary.current, 4);
+        assert_eq!(std::fs::read_to_string(&book).unwrap(), contents);
+    }
+
+    // Tests that a dry-run reports the files to migrate without writing them.
+    #[test]
+    fn dry_run() {
+        let directory = fixture("v1", "dry-run");
+
+        let book = directory
+            .join("Robert Henri - The Art Spirit")
+            .join("book.json");
+        let contents = std::fs::read_to_string(&book).unwrap();
+
+        let summary = run(&directory, MigrateOptions { dry_run: true }).unwrap();
+
+        assert_eq!(summary.migrated.len(), 4);
+        assert_eq!(std::fs::read_to_string(&book).unwrap(), contents);
+    }
+
+    // Tests that annotations files are paired with the book files next to them.
+    #[test]
+    fn paired_annotations() {
+        assert_eq!(
+            paired_book(Path::new("export/annotations.json")),
+            Some(PathBuf::from("export/book.json"))
+        );
+        assert_eq!(
+            paired_book(Path::new("export/the-art-spirit.annotations.json")),
+            Some(PathBuf::from("export/the-art-spirit.book.json"))
+        );
+        assert_eq!(paired_book(Path::new("export/notes.json")), None);
+    }
+
+    // Tests that books exported by a newer version aren't migrated.
+    #[test]
+    fn unsupported_version() {
+        let directory = TEMP_OUTPUT_DIRECTORY.join("tests-migrate-unsupported");
+        std::fs::create_dir_all(&directory).unwrap();
+
+        let path = directory.join("book.json");
+        std::fs::write(
+            &path,
+            json!({
+                VERSION_KEY: FORMAT_VERSION + 1,
+                "title": "",
+                "author": "",
+                "metadata": { "id": "" },
+            })
+            .to_string(),
+        )
+        .unwrap();
+
+        assert!(matches!(
+            run(&path, MigrateOptions::default()),
+            Err(Error::MigrateUnsupportedVersion { .. })
+        ));
+    }
+}
@@ -1,36 +1,103 @@
 //! Defines the [`Annotation`] struct.
 
 use std::cmp::Ordering;
-use std::collections::BTreeSet;
+use std::collections::{BTreeMap, BTreeSet};
 
 use rusqlite::Row;
-use serde::Serialize;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
 
 use crate::applebooks::ios::models::AnnotationRaw;
 use crate::applebooks::macos::ABQuery;
+use crate::strings;
 
 use super::datetime::DateTimeUtc;
 use super::epubcfi;
+use super::pdf::{self, Rect};
 
 /// A struct representing an annotation and its metadata.
-#[derive(Debug, Default, Clone, Eq, Serialize)]
+#[derive(Debug, Default, Clone, Eq, Serialize, Deserialize, JsonSchema)]
 pub struct Annotation {
-    /// The body of the annotation.
+    /// The body of the annotation i.e. the highlighted text.
     pub body: String,
 
+    /// The text surrounding the annotation's body, usually its paragraph. This is empty if Apple
+    /// Books didn't store any.
+    #[serde(default)]
+    pub context_text: String,
+
     /// The annotation's highlight style.
     pub style: AnnotationStyle,
 
+    /// Whether the annotation is a highlight, an underline or a note without any highlighted
+    /// text.
+    #[serde(default)]
+    pub kind: AnnotationKind,
+
     /// The annotation's notes.
     pub notes: String,
 
     /// The annotation's `#tags`.
     pub tags: BTreeSet<String>,
 
+    /// Whether the annotation is marked as a favorite. This is only set if the
+    /// `extract_favorites` pre-process is run. See [`PreProcessOptions`][options] and
+    /// [`Annotation::is_favorite()`].
+    ///
+    /// [options]: crate::process::pre::PreProcessOptions
+    #[serde(default)]
+    pub favorite: bool,
+
+    /// The annotation's `key:: value` properties. This is only set if the `extract_properties`
+    /// pre-process is run. See [`PreProcessOptions`][options].
+    ///
+    /// [options]: crate::process::pre::PreProcessOptions
+    #[serde(default)]
+    pub properties: BTreeMap<String, String>,
+
+    /// The annotation's `key: value` fields e.g. `rating: 5`. Numbers are stored as numbers and
+    /// everything else as strings. This is only set if the `extract_fields` pre-process is run.
+    /// See [`PreProcessOptions`][options].
+    ///
+    /// [options]: crate::process::pre::PreProcessOptions
+    #[serde(default)]
+    pub fields: BTreeMap<String, Value>,
+
+    /// The title of the chapter the annotation was made in. This is only set if the book's EPUB
+    /// is available. See [`epub::run()`][epub-run].
+    ///
+    /// [epub-run]: crate::epub::run
+    pub chapter: Option<String>,
+
+    /// The asset ids of other books whose titles the annotation mentions. This is only set if the
+    /// `link_mentions` pre-process is run. See [`PreProcessOptions`][options].
+    ///
+    /// [options]: crate::process::pre::PreProcessOptions
+    #[serde(default)]
+    pub mentions: Vec<String>,
+
     /// The annotation's metadata.
     pub metadata: AnnotationMetadata,
 }
 
+impl Annotation {
+    /// Returns `true` if the annotation is marked as a favorite, either by its
+    /// [`Annotation::favorite`] field or by its notes or tags. See
+    /// [`strings::is_favorite()`][is-favorite] for the markers that are recognized.
+    ///
+    /// Unlike [`Annotation::favorite`], this doesn't depend on any pre-processes having run so it
+    /// can be used when filtering.
+    ///
+    /// [is-favorite]: crate::strings::is_favorite
+    #[must_use]
+    pub fn is_favorite(&self) -> bool {
+        self.favorite
+            || strings::is_favorite(&self.notes)
+            || self.tags.iter().any(|tag| strings::is_favorite_tag(tag))
+    }
+}
+
 // For creating [`Annotation`]s from macOS database data.
 impl ABQuery for Annotation {
     const QUERY: &'static str = {
@@ -42,32 +109,72 @@ impl ABQuery for Annotation {
             ZAEANNOTATION.ZANNOTATIONASSETID,  -- 4 book_id
             ZANNOTATIONCREATIONDATE,           -- 5 created
             ZANNOTATIONMODIFICATIONDATE,       -- 6 modified
-            ZANNOTATIONLOCATION                -- 7 location
+            ZANNOTATIONLOCATION,               -- 7 location
+            ZANNOTATIONREPRESENTATIVETEXT,     -- 8 context_text
+            ZPLLOCATIONRANGESTART,             -- 9 page
+            ZPLUSERDATA                        -- 10 rects
         FROM ZAEANNOTATION
-        WHERE ZANNOTATIONSELECTEDTEXT IS NOT NULL
+        WHERE (ZANNOTATIONSELECTEDTEXT IS NOT NULL OR ZANNOTATIONNOTE IS NOT NULL)
+            AND ZANNOTATIONTYPE IS NOT 1 -- Audiobook bookmarks. See `Bookmark`.
             AND ZANNOTATIONDELETED = 0
         ORDER BY ZANNOTATIONASSETID;"
     };
 
     fn from_row(row: &Row<'_>) -> Self {
+        let body: Option<String> = row.get_unwrap(0);
+        let body = body.unwrap_or_default();
         let notes: Option<String> = row.get_unwrap(1);
-        let style: u8 = row.get_unwrap(2);
+        let style = AnnotationStyle::from(row.get_unwrap::<_, u8>(2) as usize);
         let created: f64 = row.get_unwrap(5);
         let modified: f64 = row.get_unwrap(6);
-        let epubcfi: String = row.get_unwrap(7);
+        let epubcfi: Option<String> = row.get_unwrap(7);
+        let epubcfi = epubcfi.unwrap_or_default();
+        let context_text: Option<String> = row.get_unwrap(8);
+
+        // Annotations made in PDFs don't have an `epubcfi` and are located by their page instead.
+        let (location, location_sortkey, page, rects) =
+            if epubcfi::parse_location(&epubcfi).is_some() {
+                (
+                    epubcfi::parse(&epubcfi),
+                    epubcfi::sortkey(&epubcfi),
+                    None,
+                    Vec::new(),
+                )
+            } else {
+                let page = row.get_unwrap::<_, Option<i64>>(9).and_then(pdf::page);
+                let rects: Option<Vec<u8>> = row.get_unwrap(10);
+
+                (
+                    page.map(pdf::location).unwrap_or_default(),
+                    page.map(pdf::sortkey).unwrap_or_default(),
+                    page,
+                    rects.as_deref().map(pdf::parse_rects).unwrap_or_default(),
+                )
+            };
 
         Self {
-            body: row.get_unwrap(0),
-            style: AnnotationStyle::from(style as usize),
+            kind: AnnotationKind::new(style, &body),
+            body,
+            context_text: context_text.unwrap_or_default(),
+            style,
             notes: notes.unwrap_or_default(),
             tags: BTreeSet::new(),
+            favorite: false,
+            properties: BTreeMap::new(),
+            fields: BTreeMap::new(),
+            chapter: None,
+            mentions: Vec::new(),
             metadata: AnnotationMetadata {
                 id: row.get_unwrap(3),
+                uuid: row.get_unwrap(3),
                 book_id: row.get_unwrap(4),
                 created: DateTimeUtc::from(created),
                 modified: DateTimeUtc::from(modified),
-                location: epubcfi::parse(&epubcfi),
+                location,
+                location_sortkey,
                 epubcfi,
+                page,
+                rects,
             },
         }
     }
@@ -76,18 +183,31 @@ impl ABQuery for Annotation {
 // For creating [`Annotation`]s from iOS plist data.
 impl From<AnnotationRaw> for Annotation {
     fn from(annotation: AnnotationRaw) -> Self {
+        let style = AnnotationStyle::from(annotation.style);
+
         Self {
+            kind: AnnotationKind::new(style, &annotation.body),
             body: annotation.body,
-            style: AnnotationStyle::from(annotation.style),
+            context_text: annotation.context_text.unwrap_or_default(),
+            style,
             notes: annotation.notes.unwrap_or_default(),
             tags: BTreeSet::new(),
+            favorite: false,
+            properties: BTreeMap::new(),
+            fields: BTreeMap::new(),
+            chapter: None,
+            mentions: Vec::new(),
             metadata: AnnotationMetadata {
+                uuid: annotation.id.clone(),
                 id: annotation.id,
                 book_id: annotation.book_id,
                 created: DateTimeUtc::from(annotation.created),
                 modified: DateTimeUtc::from(annotation.modified),
                 location: epubcfi::parse(&annotation.epubcfi),
+                location_sortkey: epubcfi::sortkey(&annotation.epubcfi),
                 epubcfi: annotation.epubcfi,
+                page: None,
+                rects: Vec::new(),
             },
         }
     }
@@ -114,12 +234,21 @@ impl PartialEq for Annotation {
 /// A struct representing an annotation's metadata.
 ///
 /// This is all the data that is not directly editable by the user.
-#[derive(Debug, Default, Clone, Eq, Serialize)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct AnnotationMetadata {
-    /// The annotation's unique id.
+    /// The annotation's unique id. This is Apple Books' UUID unless another [`IdScheme`] is used.
+    ///
+    /// [`IdScheme`]: super::id::IdScheme
     pub id: String,
 
-    /// The book id this annotation belongs to.
+    /// Apple Books' UUID for the annotation. It's stable for as long as the annotation exists and,
+    /// unlike [`AnnotationMetadata::id`], is never remapped.
+    #[serde(default)]
+    pub uuid: String,
+
+    /// The book id this annotation belongs to. See [`BookMetadata::id`][book-metadata-id].
+    ///
+    /// [book-metadata-id]: crate::models::book::BookMetadata::id
     pub book_id: String,
 
     /// The date the annotation was created.
@@ -129,11 +258,28 @@ pub struct AnnotationMetadata {
     pub modified: DateTimeUtc,
 
     /// A location string used for sorting annotations into their order of appearance inside their
-    /// respective book. This string is generated from the annotation's `epubcfi`.
+    /// respective book. This string is generated from the annotation's `epubcfi` or, for
+    /// annotations made in PDFs, its page number.
     pub location: String,
 
-    /// The annotation's raw `epubcfi`.
+    /// A zero-padded location string that sorts annotations into their order of appearance when
+    /// compared as a string e.g. `000001.000004.000010.000001:000003`. See
+    /// [`epubcfi::Location::sortkey()`] for more information.
+    #[serde(default)]
+    pub location_sortkey: String,
+
+    /// The annotation's raw `epubcfi`. This is empty for annotations made in PDFs.
     pub epubcfi: String,
+
+    /// The one-based number of the page the annotation was made on. This is only set for
+    /// annotations made in PDFs.
+    #[serde(default)]
+    pub page: Option<usize>,
+
+    /// The areas of the page the annotation covers. This is only set for annotations made in PDFs
+    /// and only if Apple Books stored them. See [`pdf::parse_rects()`].
+    #[serde(default)]
+    pub rects: Vec<Rect>,
 }
 
 impl Ord for AnnotationMetadata {
@@ -154,8 +300,15 @@ impl PartialEq for AnnotationMetadata {
     }
 }
 
+// Metadata is compared by its location only so it's `Eq` even though its rects aren't.
+impl Eq for AnnotationMetadata {}
+
 /// An enum represening all possible annotation highlight styles.
-#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize)]
+///
+/// Styles are ordered as they're declared i.e. underlines before the highlight colors.
+#[derive(
+    Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, JsonSchema,
+)]
 #[serde(rename_all = "lowercase")]
 pub enum AnnotationStyle {
     #[default]
@@ -189,6 +342,40 @@ impl From<usize> for AnnotationStyle {
     }
 }
 
+/// An enum representing the kinds of annotations.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum AnnotationKind {
+    /// A passage highlighted in a color.
+    #[default]
+    Highlight,
+
+    /// An underlined passage.
+    Underline,
+
+    /// A note without any highlighted text.
+    Note,
+}
+
+impl AnnotationKind {
+    /// Returns the kind of an annotation from its style and body.
+    ///
+    /// # Arguments
+    ///
+    /// * `style` - The annotation's style.
+    /// * `body` - The annotation's highlighted text.
+    #[must_use]
+    pub fn new(style: AnnotationStyle, body: &str) -> Self {
+        if body.trim().is_empty() {
+            Self::Note
+        } else if style == AnnotationStyle::Underline {
+            Self::Underline
+        } else {
+            Self::Highlight
+        }
+    }
+}
+
 #[cfg(test)]
 mod test {
 
@@ -206,4 +393,63 @@ mod test {
 
         assert!(a1 < a2);
     }
+
+    // Tests that annotations made in PDFs are located by their page instead of an `epubcfi`.
+    #[test]
+    fn from_row_pdf() {
+        let connection = rusqlite::Connection::open_in_memory().unwrap();
+
+        connection
+            .execute_batch(
+                "CREATE TABLE ZAEANNOTATION (
+                    ZANNOTATIONSELECTEDTEXT, ZANNOTATIONNOTE, ZANNOTATIONSTYLE, ZANNOTATIONUUID,
+                    ZANNOTATIONASSETID, ZANNOTATIONCREATIONDATE, ZANNOTATIONMODIFICATIONDATE,
+                    ZANNOTATIONLOCATION, ZANNOTATIONREPRESENTATIVETEXT, ZPLLOCATIONRANGESTART,
+                    ZPLUSERDATA, ZANNOTATIONDELETED, ZANNOTATIONTYPE
+                );
+                INSERT INTO ZAEANNOTATION VALUES
+                    ('PDF', NULL, 3, 'A', 'B', 0.0, 0.0, NULL, NULL, 11, NULL, 0, 2),
+                    ('EPUB', NULL, 3, 'C', 'B', 0.0, 0.0,
+                        'epubcfi(/6/10[c01]!/4/10/3,:335,:749)', NULL, 4, NULL, 0, 2),
+                    (NULL, 'Bookmark', 3, 'D', 'B', 0.0, 0.0, '12', NULL, NULL, NULL, 0, 1);",
+            )
+            .unwrap();
+
+        let mut statement = connection.prepare(Annotation::QUERY).unwrap();
+        let annotations: Vec<Annotation> = statement
+            .query_map([], |row| Ok(Annotation::from_row(row)))
+            .unwrap()
+            .map(std::result::Result::unwrap)
+            .collect();
+
+        assert_eq!(annotations.len(), 2);
+
+        let pdf = &annotations[0].metadata;
+
+        assert_eq!(pdf.page, Some(12));
+        assert_eq!(pdf.location, "12");
+        assert_eq!(pdf.location_sortkey, "000012");
+        assert!(pdf.epubcfi.is_empty());
+
+        let epub = &annotations[1].metadata;
+
+        assert_eq!(epub.page, None);
+        assert_eq!(epub.location, "6.10.4.10.3:335");
+    }
+
+    #[test]
+    fn kind() {
+        assert_eq!(
+            AnnotationKind::new(AnnotationStyle::Yellow, "The Art Spirit"),
+            AnnotationKind::Highlight
+        );
+        assert_eq!(
+            AnnotationKind::new(AnnotationStyle::Underline, "The Art Spirit"),
+            AnnotationKind::Underline
+        );
+        assert_eq!(
+            AnnotationKind::new(AnnotationStyle::Underline, " "),
+            AnnotationKind::Note
+        );
+    }
 }
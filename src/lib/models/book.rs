@@ -1,15 +1,20 @@
 //! Defines the [`Book`] struct.
 
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
 use rusqlite::Row;
-use serde::Serialize;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
 
 use crate::applebooks::ios::models::BookRaw;
 use crate::applebooks::macos::ABQuery;
+use crate::strings;
 
 use super::datetime::DateTimeUtc;
 
 /// A struct represening a book and its metadata.
-#[derive(Debug, Default, Clone, Serialize)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct Book {
     /// The title of the book.
     pub title: String,
@@ -19,30 +24,59 @@ pub struct Book {
 
     /// The book's metadata.
     pub metadata: BookMetadata,
+
+    /// The absolute path to the book's cover image. This is only set if the book's EPUB is
+    /// available. See [`epub::run()`][epub-run].
+    ///
+    /// [epub-run]: crate::epub::run
+    #[serde(skip)]
+    pub cover_path: Option<PathBuf>,
+
+    /// The book's metadata from a Calibre library. This is only set if a Calibre library is
+    /// available and the book was found in it. See [`calibre::run()`][calibre-run].
+    ///
+    /// [calibre-run]: crate::calibre::run
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub calibre: Option<CalibreMetadata>,
 }
 
 // For creating [`Book`]s from macOS database data.
 impl ABQuery for Book {
     const QUERY: &'static str = {
         "SELECT
-            ZBKLIBRARYASSET.ZTITLE,        -- 0 title
-            ZBKLIBRARYASSET.ZAUTHOR,       -- 1 author
-            ZBKLIBRARYASSET.ZASSETID,      -- 2 id
-            ZBKLIBRARYASSET.ZLASTOPENDATE  -- 3 last_opened
+            ZBKLIBRARYASSET.ZTITLE,          -- 0 title
+            ZBKLIBRARYASSET.ZAUTHOR,         -- 1 author
+            ZBKLIBRARYASSET.ZASSETID,        -- 2 id
+            ZBKLIBRARYASSET.ZLASTOPENDATE,   -- 3 last_opened
+            ZBKLIBRARYASSET.ZREADINGPROGRESS, -- 4 progress
+            ZBKLIBRARYASSET.ZEPUBID,          -- 5 isbn
+            ZBKLIBRARYASSET.ZISFINISHED,      -- 6 is_finished
+            ZBKLIBRARYASSET.ZDATEFINISHED     -- 7 date_finished
         FROM ZBKLIBRARYASSET
         ORDER BY ZBKLIBRARYASSET.ZTITLE;"
     };
 
     fn from_row(row: &Row<'_>) -> Self {
         let last_opened: f64 = row.get_unwrap(3);
+        let epub_id: Option<String> = row.get_unwrap(5);
+        let is_finished: Option<bool> = row.get_unwrap(6);
+        let date_finished: Option<f64> = row.get_unwrap(7);
 
         Self {
             title: row.get_unwrap(0),
             author: row.get_unwrap(1),
             metadata: BookMetadata {
                 id: row.get_unwrap(2),
+                asset_id: row.get_unwrap(2),
                 last_opened: Some(DateTimeUtc::from(last_opened)),
+                progress: row.get_unwrap(4),
+                // The EPUB's unique identifier is often, but not always, its ISBN.
+                isbn: epub_id.as_deref().and_then(strings::to_isbn),
+                is_finished: is_finished.unwrap_or_default(),
+                date_finished: date_finished.map(DateTimeUtc::from),
             },
+            cover_path: None,
+            calibre: None,
         }
     }
 }
@@ -54,20 +88,75 @@ impl From<BookRaw> for Book {
             title: book.title,
             author: book.author,
             metadata: BookMetadata {
+                asset_id: book.id.clone(),
                 id: book.id,
                 // TODO(feat): Does iOS store the `last_opened` date?
                 last_opened: None,
+                progress: None,
+                isbn: None,
+                is_finished: false,
+                date_finished: None,
             },
+            cover_path: None,
+            calibre: None,
         }
     }
 }
 
 /// A struct representing a book's metadata.
-#[derive(Debug, Default, Clone, Serialize)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct BookMetadata {
-    /// The book's unique id.
+    /// The book's unique id. This is Apple Books' asset id unless another [`IdScheme`] is used.
+    ///
+    /// [`IdScheme`]: super::id::IdScheme
     pub id: String,
 
+    /// Apple Books' asset id for the book. It's stable for as long as the book stays in the library
+    /// and, unlike [`BookMetadata::id`], is never remapped.
+    #[serde(default)]
+    pub asset_id: String,
+
     /// The date the book was last opened.
     pub last_opened: Option<DateTimeUtc>,
+
+    /// The book's reading progress from `0.0` to `1.0`.
+    pub progress: Option<f64>,
+
+    /// The book's ISBN, if its EPUB is identified by one. Hyphens are removed.
+    #[serde(default)]
+    pub isbn: Option<String>,
+
+    /// Whether the book was marked as finished.
+    #[serde(default)]
+    pub is_finished: bool,
+
+    /// The date the book was marked as finished. This can be missing even if the book is finished
+    /// e.g. if it was marked as finished before Apple Books recorded the date.
+    #[serde(default)]
+    pub date_finished: Option<DateTimeUtc>,
+}
+
+/// A struct representing a book's metadata from a Calibre library. See [`calibre`][calibre].
+///
+/// [calibre]: crate::calibre
+#[derive(Debug, Default, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct CalibreMetadata {
+    /// The book's id within the Calibre library.
+    pub id: i64,
+
+    /// The name of the series the book belongs to.
+    pub series: Option<String>,
+
+    /// The book's position within its series. Only set if the book belongs to a series.
+    pub series_index: Option<f64>,
+
+    /// The book's publisher.
+    pub publisher: Option<String>,
+
+    /// The book's identifiers keyed by their type e.g. `isbn`, `goodreads` or `amazon`.
+    pub identifiers: BTreeMap<String, String>,
+
+    /// The values of the library's custom columns keyed by their lookup name, without the leading
+    /// `#`. Values are stored as text and columns with multiple values are joined with `, `.
+    pub custom: BTreeMap<String, String>,
 }
@@ -0,0 +1,236 @@
+//! Defines the [`Bookmark`] and [`Audiobook`] structs.
+//!
+//! Apple Books stores audiobook bookmarks in the annotations database alongside highlights. A
+//! bookmark's location is its position within the audiobook in seconds.
+
+use std::collections::HashMap;
+
+use rusqlite::Row;
+use serde::{Deserialize, Serialize};
+
+use crate::applebooks::macos::ABQuery;
+
+use super::book::Book;
+use super::datetime::DateTimeUtc;
+
+/// A struct representing an audiobook and its bookmarks.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct Audiobook {
+    /// The audiobook.
+    pub book: Book,
+
+    /// The audiobook's bookmarks sorted by their position.
+    pub bookmarks: Vec<Bookmark>,
+}
+
+impl Audiobook {
+    /// Attaches bookmarks to their audiobooks and returns the audiobooks with at least one
+    /// bookmark, in the order they were given. Bookmarks that don't belong to any of the
+    /// audiobooks are dropped.
+    ///
+    /// # Arguments
+    ///
+    /// * `audiobooks` - The audiobooks.
+    /// * `bookmarks` - The bookmarks to attach.
+    #[must_use]
+    pub fn from_parts(audiobooks: Vec<Self>, bookmarks: Vec<Bookmark>) -> Vec<Self> {
+        let mut bookmarks_by_book: HashMap<String, Vec<Bookmark>> = HashMap::new();
+
+        for bookmark in bookmarks {
+            bookmarks_by_book
+                .entry(bookmark.metadata.book_id.clone())
+                .or_default()
+                .push(bookmark);
+        }
+
+        audiobooks
+            .into_iter()
+            .filter_map(|mut audiobook| {
+                let mut bookmarks = bookmarks_by_book.remove(&audiobook.book.metadata.id)?;
+                bookmarks.sort_by(|a, b| a.position.total_cmp(&b.position));
+                audiobook.bookmarks = bookmarks;
+                Some(audiobook)
+            })
+            .collect()
+    }
+}
+
+// For creating [`Audiobook`]s from macOS database data.
+impl ABQuery for Audiobook {
+    const QUERY: &'static str = {
+        "SELECT
+            ZBKLIBRARYASSET.ZTITLE,          -- 0 title
+            ZBKLIBRARYASSET.ZAUTHOR,         -- 1 author
+            ZBKLIBRARYASSET.ZASSETID,        -- 2 id
+            ZBKLIBRARYASSET.ZLASTOPENDATE,   -- 3 last_opened
+            ZBKLIBRARYASSET.ZREADINGPROGRESS, -- 4 progress
+            ZBKLIBRARYASSET.ZEPUBID           -- 5 isbn
+        FROM ZBKLIBRARYASSET
+        WHERE ZBKLIBRARYASSET.ZKIND = 'AUDIOBOOK' OR ZBKLIBRARYASSET.ZISSTOREAUDIOBOOK = 1
+        ORDER BY ZBKLIBRARYASSET.ZTITLE;"
+    };
+
+    fn from_row(row: &Row<'_>) -> Self {
+        // The columns are identical to the ones queried for a [`Book`].
+        Self {
+            book: Book::from_row(row),
+            bookmarks: Vec::new(),
+        }
+    }
+}
+
+/// A struct representing an audiobook bookmark and its metadata.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct Bookmark {
+    /// The bookmark's notes.
+    pub notes: String,
+
+    /// The bookmark's position within the audiobook in seconds.
+    pub position: f64,
+
+    /// The bookmark's metadata.
+    pub metadata: BookmarkMetadata,
+}
+
+// For creating [`Bookmark`]s from macOS database data.
+impl ABQuery for Bookmark {
+    const QUERY: &'static str = {
+        "SELECT
+            ZANNOTATIONNOTE,                   -- 0 notes
+            ZANNOTATIONLOCATION,               -- 1 position
+            ZANNOTATIONUUID,                   -- 2 id
+            ZAEANNOTATION.ZANNOTATIONASSETID,  -- 3 book_id
+            ZANNOTATIONCREATIONDATE,           -- 4 created
+            ZANNOTATIONMODIFICATIONDATE        -- 5 modified
+        FROM ZAEANNOTATION
+        WHERE ZANNOTATIONTYPE = 1
+            AND ZANNOTATIONDELETED = 0
+        ORDER BY ZANNOTATIONASSETID;"
+    };
+
+    fn from_row(row: &Row<'_>) -> Self {
+        let notes: Option<String> = row.get_unwrap(0);
+        let position: Option<String> = row.get_unwrap(1);
+        let created: f64 = row.get_unwrap(4);
+        let modified: f64 = row.get_unwrap(5);
+
+        Self {
+            notes: notes.unwrap_or_default(),
+            position: position
+                .and_then(|position| position.trim().parse().ok())
+                .filter(|position: &f64| position.is_finite() && *position >= 0.0)
+                .unwrap_or_default(),
+            metadata: BookmarkMetadata {
+                id: row.get_unwrap(2),
+                book_id: row.get_unwrap(3),
+                created: DateTimeUtc::from(created),
+                modified: DateTimeUtc::from(modified),
+            },
+        }
+    }
+}
+
+/// A struct representing a bookmark's metadata.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct BookmarkMetadata {
+    /// The bookmark's unique id.
+    pub id: String,
+
+    /// The audiobook id this bookmark belongs to. See [`BookMetadata::id`][book-metadata-id].
+    ///
+    /// [book-metadata-id]: crate::models::book::BookMetadata::id
+    pub book_id: String,
+
+    /// The date the bookmark was created.
+    pub created: DateTimeUtc,
+
+    /// The date the bookmark was last modified.
+    pub modified: DateTimeUtc,
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    fn bookmark(book_id: &str, position: f64) -> Bookmark {
+        Bookmark {
+            position,
+            metadata: BookmarkMetadata {
+                book_id: book_id.to_string(),
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    fn audiobook(id: &str) -> Audiobook {
+        let mut audiobook = Audiobook::default();
+        audiobook.book.metadata.id = id.to_string();
+        audiobook
+    }
+
+    // Tests that bookmarks are attached to their audiobooks, sorted by position, and that
+    // audiobooks without bookmarks are dropped.
+    #[test]
+    fn from_parts() {
+        let audiobooks = Audiobook::from_parts(
+            vec![audiobook("A"), audiobook("B"), audiobook("C")],
+            vec![
+                bookmark("C", 90.0),
+                bookmark("A", 30.5),
+                bookmark("C", 12.0),
+                bookmark("D", 1.0),
+            ],
+        );
+
+        let ids: Vec<&str> = audiobooks
+            .iter()
+            .map(|audiobook| audiobook.book.metadata.id.as_str())
+            .collect();
+
+        assert_eq!(ids, ["A", "C"]);
+
+        let positions: Vec<String> = audiobooks[1]
+            .bookmarks
+            .iter()
+            .map(|bookmark| bookmark.position.to_string())
+            .collect();
+
+        assert_eq!(positions, ["12", "90"]);
+    }
+
+    // Tests that bookmarks are read from the annotations database and invalid positions default to
+    // the start of the audiobook.
+    #[test]
+    fn from_row() {
+        let connection = rusqlite::Connection::open_in_memory().unwrap();
+
+        connection
+            .execute_batch(
+                "CREATE TABLE ZAEANNOTATION (
+                    ZANNOTATIONNOTE, ZANNOTATIONLOCATION, ZANNOTATIONUUID, ZANNOTATIONASSETID,
+                    ZANNOTATIONCREATIONDATE, ZANNOTATIONMODIFICATIONDATE, ZANNOTATIONTYPE,
+                    ZANNOTATIONDELETED
+                );
+                INSERT INTO ZAEANNOTATION VALUES
+                    ('Chapter 2', '754.25', 'A', 'B', 0.0, 0.0, 1, 0),
+                    (NULL, 'epubcfi(/6/10)', 'C', 'B', 0.0, 0.0, 1, 0),
+                    (NULL, '12', 'D', 'B', 0.0, 0.0, 2, 0),
+                    (NULL, '12', 'E', 'B', 0.0, 0.0, 1, 1);",
+            )
+            .unwrap();
+
+        let mut statement = connection.prepare(Bookmark::QUERY).unwrap();
+        let bookmarks: Vec<Bookmark> = statement
+            .query_map([], |row| Ok(Bookmark::from_row(row)))
+            .unwrap()
+            .map(std::result::Result::unwrap)
+            .collect();
+
+        assert_eq!(bookmarks.len(), 2);
+        assert_eq!(bookmarks[0].notes, "Chapter 2");
+        assert_eq!(bookmarks[0].position.to_string(), "754.25");
+        assert_eq!(bookmarks[1].position.to_string(), "0");
+    }
+}
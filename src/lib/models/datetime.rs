@@ -4,7 +4,8 @@ use std::ops::{Deref, DerefMut};
 use std::time::UNIX_EPOCH;
 
 use chrono::{DateTime, Utc};
-use serde::Serialize;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
 
 /// A newtype around [`chrono`]'s [`DateTime<Utc>`] to allow implementation of the [`Default`] trait.
 ///
@@ -20,7 +21,8 @@ use serde::Serialize;
 /// [dummy]: crate::models::dummy
 /// [entry]: crate::models::entry::Entry
 /// [renderer]: crate::render::renderer::Renderer
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[schemars(description = "A UTC date and time in RFC 3339 format e.g. `2024-01-01T12:00:00Z`.")]
 pub struct DateTimeUtc(DateTime<Utc>);
 
 impl Default for DateTimeUtc {
@@ -43,6 +45,12 @@ impl DerefMut for DateTimeUtc {
     }
 }
 
+impl From<DateTime<Utc>> for DateTimeUtc {
+    fn from(datetime: DateTime<Utc>) -> Self {
+        Self(datetime)
+    }
+}
+
 /// Converts a `Core Data` timestamp (f64) to `DateTime`.
 ///
 /// A `Core Data` timestamp is the number of seconds (or nanoseconds) since midnight, January 1,
@@ -58,8 +66,12 @@ impl From<f64> for DateTimeUtc {
 
         let seconds = timestamp.trunc() as i64;
         let nanoseconds = timestamp.fract() * 1_000_000_000.0;
-        // Unwrap should be safe here as the timestamps are coming from the OS.
-        let datetime = DateTime::from_timestamp(seconds, nanoseconds as u32).unwrap();
+        // Timestamps come from the OS but a corrupted database could contain any value. Those that
+        // are out of range fall back to the Unix epoch.
+        let datetime = DateTime::from_timestamp(seconds, nanoseconds as u32).unwrap_or_else(|| {
+            tracing::warn!(timestamp = f, "ignored out of range timestamp");
+            DateTime::default()
+        });
 
         DateTimeUtc(datetime)
     }
@@ -1,11 +1,13 @@
 //! Defines dummy implementations for template validation.
 
-use std::collections::BTreeSet;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::PathBuf;
 
 use uuid::Uuid;
 
-use super::annotation::{Annotation, AnnotationMetadata, AnnotationStyle};
-use super::book::{Book, BookMetadata};
+use super::annotation::{Annotation, AnnotationKind, AnnotationMetadata, AnnotationStyle};
+use super::book::{Book, BookMetadata, CalibreMetadata};
+use super::bookmark::{Audiobook, Bookmark, BookmarkMetadata};
 use super::datetime::DateTimeUtc;
 use super::entry::Entry;
 
@@ -24,6 +26,21 @@ impl Entry {
     }
 }
 
+impl Audiobook {
+    #[must_use]
+    pub(crate) fn dummy() -> Self {
+        let id = uuid::Uuid::new_v4();
+        Self {
+            book: Book::dummy(id),
+            bookmarks: vec![
+                Bookmark::dummy(id),
+                Bookmark::dummy(id),
+                Bookmark::dummy(id),
+            ],
+        }
+    }
+}
+
 impl Book {
     #[must_use]
     pub(crate) fn dummy(id: Uuid) -> Self {
@@ -32,8 +49,22 @@ impl Book {
             author: "Laborum Cillum".to_string(),
             metadata: BookMetadata {
                 id: id.to_string(),
+                asset_id: id.to_string(),
                 last_opened: Some(DateTimeUtc::default()),
+                progress: Some(0.0),
+                isbn: Some("9780000000000".to_string()),
+                is_finished: false,
+                date_finished: Some(DateTimeUtc::default()),
             },
+            cover_path: Some(PathBuf::from("cover.jpg")),
+            calibre: Some(CalibreMetadata {
+                id: 1,
+                series: Some("Excepteur".to_string()),
+                series_index: Some(1.0),
+                publisher: Some("Laborum".to_string()),
+                identifiers: BTreeMap::from([("isbn".to_string(), "9780000000000".to_string())]),
+                custom: BTreeMap::new(),
+            }),
         }
     }
 }
@@ -41,18 +72,48 @@ impl Book {
 impl Annotation {
     #[must_use]
     pub(crate) fn dummy(book_id: Uuid) -> Self {
+        let id = Uuid::new_v4().to_string();
+
         Self {
             body: "Elit consequat pariatur incididunt excepteur mollit.".to_string(),
+            context_text: "Nulla elit consequat pariatur incididunt excepteur mollit, sint anim."
+                .to_string(),
             style: AnnotationStyle::Underline,
+            kind: AnnotationKind::Underline,
             notes: "Dolor ipsum officia non cillum.".to_string(),
             tags: BTreeSet::from_iter(["#laboris", "#magna", "#nisi"].map(String::from)),
+            favorite: false,
+            properties: BTreeMap::from_iter([("page".to_string(), "42".to_string())]),
+            fields: BTreeMap::new(),
+            chapter: Some("Chapter 1".to_string()),
+            mentions: Vec::new(),
             metadata: AnnotationMetadata {
-                id: Uuid::new_v4().to_string(),
+                uuid: id.clone(),
+                id,
                 book_id: book_id.to_string(),
                 created: DateTimeUtc::default(),
                 modified: DateTimeUtc::default(),
                 location: String::new(),
+                location_sortkey: String::new(),
                 epubcfi: String::new(),
+                page: None,
+                rects: Vec::new(),
+            },
+        }
+    }
+}
+
+impl Bookmark {
+    #[must_use]
+    pub(crate) fn dummy(book_id: Uuid) -> Self {
+        Self {
+            notes: "Dolor ipsum officia non cillum.".to_string(),
+            position: 3723.5,
+            metadata: BookmarkMetadata {
+                id: Uuid::new_v4().to_string(),
+                book_id: book_id.to_string(),
+                created: DateTimeUtc::default(),
+                modified: DateTimeUtc::default(),
             },
         }
     }
@@ -1,17 +1,22 @@
 //! Defines the [`Entry`] struct.
 
-use std::collections::HashMap;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::ops::{Deref, DerefMut};
 
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
+
+use crate::strings;
 
 use super::annotation::Annotation;
 use super::book::Book;
+use super::id::{self, IdScheme};
 
-/// A type alias represening how [`Entry`]s are organized.
+/// A struct represening how [`Entry`]s are organized.
 ///
-/// [`Entries`] is a `HashMap` composed of `key:value` pairs of where the value is an [`Entry`] and
-/// the key is the unique id of its [`Book`], taken from the [`BookMetadata::id`][book-metadata-id]
-/// field.
+/// [`Entries`] is a newtype around a `HashMap` composed of `key:value` pairs of where the value is
+/// an [`Entry`] and the key is the unique id of its [`Book`], taken from the
+/// [`BookMetadata::id`][book-metadata-id] field.
 ///
 /// For example:
 ///
@@ -24,10 +29,433 @@ use super::book::Book;
 /// ```
 ///
 /// [book-metadata-id]: crate::models::book::BookMetadata::id
-pub type Entries = HashMap<String, Entry>;
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct Entries(HashMap<String, Entry>);
+
+impl Entries {
+    /// Builds [`Entries`] from a list of [`Book`]s and [`Annotation`]s.
+    ///
+    /// An [`Entry`] is created for every [`Book`]. Each [`Annotation`] is then added to the
+    /// [`Entry`] whose [`BookMetadata::id`][book-metadata-id] matches the annotation's
+    /// [`AnnotationMetadata::book_id`][annotation-metadata-book-id]. Annotations without a matching
+    /// [`Book`] are discarded.
+    ///
+    /// Note that [`Book`]s without any [`Annotation`]s are kept. Use
+    /// [`filters::contains_no_annotations()`][contains-no-annotations] to discard them.
+    ///
+    /// This is the primary way [`Entries`] are assembled and allows building them from any source
+    /// capable of producing [`Book`]s and [`Annotation`]s.
+    ///
+    /// # Arguments
+    ///
+    /// * `books` - The [`Book`]s to create [`Entry`]s from.
+    /// * `annotations` - The [`Annotation`]s to distribute between the [`Entry`]s.
+    ///
+    /// [annotation-metadata-book-id]: crate::models::annotation::AnnotationMetadata::book_id
+    /// [book-metadata-id]: crate::models::book::BookMetadata::id
+    /// [contains-no-annotations]: crate::filter::filters::contains_no_annotations
+    #[must_use]
+    pub fn from_parts(books: Vec<Book>, annotations: Vec<Annotation>) -> Self {
+        // `Entry`s are created from `Book`s. Note that `book.metadata.id` is set as the key for
+        // each entry. This is later used to compare with each `Annotation` to determine if the
+        // `Annotation` belongs to a `Book` and therefore its `Entry`.
+        //
+        // See https://stackoverflow.com/q/69274529/16968574
+        let mut entries: Self = books
+            .into_iter()
+            .map(|book| (book.metadata.id.clone(), Entry::from(book)))
+            .collect();
+
+        // `Annotation`s are pushed onto an `Entry` based on their `book_id`.
+        for annotation in annotations {
+            if let Some(entry) = entries.get_mut(&annotation.metadata.book_id) {
+                entry.annotations.push(annotation);
+            }
+        }
+
+        entries
+    }
+
+    /// Merges another set of [`Entries`] into this one e.g. to combine macOS's and iOS's Apple
+    /// Books data into a single library.
+    ///
+    /// Books are matched by their id first, then by their ISBN, ignoring hyphens, and then by their
+    /// title and author, ignoring case and surrounding whitespace. Matched books keep this set's title, author and id while their
+    /// missing metadata is filled in from the other set. Unmatched books are added as is.
+    ///
+    /// Annotations are deduplicated on their id. When an annotation exists in both sets with
+    /// different contents, the most recently modified one is kept. Every conflict is logged.
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - The [`Entries`] to merge into this one.
+    pub fn merge(&mut self, other: Self) -> MergeSummary {
+        let mut summary = MergeSummary::default();
+
+        // Entries are merged in a deterministic order so conflicts are logged consistently.
+        let mut other: Vec<(String, Entry)> = other.into_iter().collect();
+        other.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+        for (id, entry) in other {
+            let existing = self
+                .find_match(&id, &entry.book)
+                .and_then(|key| self.0.get_mut(&key));
+
+            if let Some(existing) = existing {
+                summary.books_merged += 1;
+                existing.merge(entry, &mut summary);
+            } else {
+                summary.books_added += 1;
+                summary.annotations_added += entry.annotations.len();
+                self.insert(id, entry);
+            }
+        }
+
+        tracing::debug!(
+            "merged entries: {} book(s) added, {} book(s) merged, {} annotation(s) added, \
+             {} duplicate annotation(s), {} conflict(s)",
+            summary.books_added,
+            summary.books_merged,
+            summary.annotations_added,
+            summary.annotations_duplicated,
+            summary.conflicts,
+        );
+
+        summary
+    }
+
+    /// Returns the key of the [`Entry`] matching a book by its id, by its ISBN or by its title and
+    /// author.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The book's id.
+    /// * `book` - The book to match.
+    fn find_match(&self, id: &str, book: &Book) -> Option<String> {
+        if self.contains_key(id) {
+            return Some(id.to_owned());
+        }
+
+        // Copies of the same book can have different asset ids and titles e.g. when one was
+        // imported from a file and the other bought from the Book Store.
+        let isbn = book.metadata.isbn.as_deref().and_then(strings::to_isbn);
+
+        if let Some(isbn) = isbn {
+            // Sorted so that the same book is matched if several share an ISBN.
+            let mut matches: Vec<&String> = self
+                .iter()
+                .filter(|(_, entry)| {
+                    entry
+                        .book
+                        .metadata
+                        .isbn
+                        .as_deref()
+                        .and_then(strings::to_isbn)
+                        .is_some_and(|other| other == isbn)
+                })
+                .map(|(key, _)| key)
+                .collect();
+
+            matches.sort();
+
+            if let Some(key) = matches.first() {
+                return Some((*key).clone());
+            }
+        }
+
+        let normalize = |value: &str| value.trim().to_lowercase();
+        let (title, author) = (normalize(&book.title), normalize(&book.author));
+
+        if title.is_empty() {
+            return None;
+        }
+
+        // Sorted so that the same book is matched if several share a title and author.
+        let mut matches: Vec<&String> = self
+            .iter()
+            .filter(|(_, entry)| {
+                normalize(&entry.book.title) == title && normalize(&entry.book.author) == author
+            })
+            .map(|(key, _)| key)
+            .collect();
+
+        matches.sort();
+        matches.first().map(|key| (*key).clone())
+    }
+
+    /// Returns an ordered view over all [`Entry`]s.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The [`BookSortKey`] to sort by.
+    #[must_use]
+    pub fn sorted(&self, key: BookSortKey) -> SortedEntries<'_> {
+        SortedEntries::new(self, key)
+    }
+
+    /// Keeps only the first `len` [`Entry`]s when sorted by `key`.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The [`BookSortKey`] to sort by.
+    /// * `len` - The maximum number of [`Entry`]s to keep.
+    pub fn truncate(&mut self, key: BookSortKey, len: usize) {
+        let keep: HashSet<String> = self
+            .sorted(key)
+            .iter()
+            .take(len)
+            .map(|entry| entry.book.metadata.id.clone())
+            .collect();
+
+        self.retain(|id, _| keep.contains(id));
+    }
+
+    /// Groups all [`Entry`]s by a key e.g. their book's author or the month they were last opened.
+    ///
+    /// This is shorthand for [`SortedEntries::group_by()`] with the default [`BookSortKey`].
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - A function returning the key to group an [`Entry`] by.
+    #[must_use]
+    pub fn group_by<K, F>(&self, key: F) -> GroupedEntries<'_, K>
+    where
+        K: Ord,
+        F: FnMut(&Entry) -> K,
+    {
+        self.sorted(BookSortKey::default()).group_by(key)
+    }
+
+    /// Sets every book's and annotation's id, and each annotation's book id, using an
+    /// [`IdScheme`]. Entries are re-keyed by their new book ids.
+    ///
+    /// Apple Books' ids are always kept in [`BookMetadata::asset_id`][asset-id] and
+    /// [`AnnotationMetadata::uuid`][uuid]. Content-based ids that collide e.g. two editions of a
+    /// book sharing a title and author, are suffixed with `-2`, `-3`, etc. in the order of their
+    /// Apple Books ids.
+    ///
+    /// # Arguments
+    ///
+    /// * `scheme` - The [`IdScheme`] to use.
+    ///
+    /// [asset-id]: crate::models::book::BookMetadata::asset_id
+    /// [uuid]: crate::models::annotation::AnnotationMetadata::uuid
+    pub fn remap_ids(&mut self, scheme: IdScheme) {
+        let mut entries: Vec<Entry> = std::mem::take(&mut self.0).into_values().collect();
+        entries.sort_by(|a, b| a.book.metadata.asset_id.cmp(&b.book.metadata.asset_id));
+
+        let mut book_ids = HashSet::new();
+        let mut annotation_ids = HashSet::new();
+
+        for mut entry in entries {
+            let book_id = match scheme {
+                IdScheme::Uuid => {
+                    id::or_existing(&entry.book.metadata.asset_id, &entry.book.metadata.id)
+                }
+                IdScheme::Hash => id::unique(&id::book_hash(&entry.book), &mut book_ids),
+            };
+
+            // Annotations are remapped in the order of their Apple Books ids while keeping their
+            // current order within the entry.
+            let mut order: Vec<usize> = (0..entry.annotations.len()).collect();
+            order.sort_by(|&a, &b| {
+                let uuid = |index: usize| &entry.annotations[index].metadata.uuid;
+                uuid(a).cmp(uuid(b))
+            });
+
+            for index in order {
+                let annotation = &mut entry.annotations[index];
+
+                annotation.metadata.id = match scheme {
+                    IdScheme::Uuid => {
+                        id::or_existing(&annotation.metadata.uuid, &annotation.metadata.id)
+                    }
+                    IdScheme::Hash => id::unique(
+                        &id::annotation_hash(&book_id, annotation),
+                        &mut annotation_ids,
+                    ),
+                };
+                annotation.metadata.book_id.clone_from(&book_id);
+            }
+
+            entry.book.metadata.id.clone_from(&book_id);
+            self.insert(book_id, entry);
+        }
+    }
+}
+
+impl Deref for Entries {
+    type Target = HashMap<String, Entry>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl DerefMut for Entries {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl FromIterator<(String, Entry)> for Entries {
+    fn from_iter<I>(iter: I) -> Self
+    where
+        I: IntoIterator<Item = (String, Entry)>,
+    {
+        Self(HashMap::from_iter(iter))
+    }
+}
+
+impl IntoIterator for Entries {
+    type Item = (String, Entry);
+    type IntoIter = std::collections::hash_map::IntoIter<String, Entry>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+/// An ordered, read-only view over [`Entries`].
+///
+/// As [`Entries`] is backed by a `HashMap`, iterating over it directly yields its [`Entry`]s in an
+/// arbitrary order. This provides a deterministic order by sorting on a [`BookSortKey`]. Ties are
+/// broken by the book's title, author and then id.
+#[derive(Debug)]
+pub struct SortedEntries<'a>(Vec<&'a Entry>);
+
+impl<'a> SortedEntries<'a> {
+    /// Creates a new instance of [`SortedEntries`].
+    ///
+    /// # Arguments
+    ///
+    /// * `entries` - The [`Entries`] to sort.
+    /// * `key` - The [`BookSortKey`] to sort by.
+    #[must_use]
+    pub fn new(entries: &'a Entries, key: BookSortKey) -> Self {
+        let mut sorted: Vec<&Entry> = entries.values().collect();
+
+        sorted.sort_by(|a, b| {
+            let (a, b) = (&a.book, &b.book);
+
+            let ordering = match key {
+                BookSortKey::Title => Ordering::Equal,
+                BookSortKey::Author => a.author.to_lowercase().cmp(&b.author.to_lowercase()),
+                // Most recently opened first. Books that were never opened are last.
+                BookSortKey::LastOpened => {
+                    let a = a.metadata.last_opened.map(|date| *date);
+                    let b = b.metadata.last_opened.map(|date| *date);
+                    b.cmp(&a)
+                }
+                // Furthest along first. Books without any progress are last.
+                BookSortKey::Progress => b
+                    .metadata
+                    .progress
+                    .unwrap_or(-1.0)
+                    .total_cmp(&a.metadata.progress.unwrap_or(-1.0)),
+            };
+
+            ordering
+                .then_with(|| a.title.to_lowercase().cmp(&b.title.to_lowercase()))
+                .then_with(|| a.author.to_lowercase().cmp(&b.author.to_lowercase()))
+                .then_with(|| a.metadata.id.cmp(&b.metadata.id))
+        });
+
+        Self(sorted)
+    }
+
+    /// Keeps only the first `len` [`Entry`]s.
+    ///
+    /// # Arguments
+    ///
+    /// * `len` - The maximum number of [`Entry`]s to keep.
+    #[must_use]
+    pub fn limit(mut self, len: usize) -> Self {
+        self.0.truncate(len);
+        self
+    }
+
+    /// Groups the [`Entry`]s by a key e.g. their book's author or the month they were last opened.
+    ///
+    /// Groups are ordered by their key. The [`Entry`]s within each group keep their sorted order.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - A function returning the key to group an [`Entry`] by.
+    #[must_use]
+    pub fn group_by<K, F>(self, mut key: F) -> GroupedEntries<'a, K>
+    where
+        K: Ord,
+        F: FnMut(&Entry) -> K,
+    {
+        let mut groups: GroupedEntries<'a, K> = BTreeMap::new();
+
+        for entry in self {
+            groups.entry(key(entry)).or_default().push(entry);
+        }
+
+        groups
+    }
+}
+
+impl<'a> Deref for SortedEntries<'a> {
+    type Target = [&'a Entry];
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<'a> IntoIterator for SortedEntries<'a> {
+    type Item = &'a Entry;
+    type IntoIter = std::vec::IntoIter<&'a Entry>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+/// A map of [`Entry`]s grouped by a key, ordered by the key. See [`SortedEntries::group_by()`].
+pub type GroupedEntries<'a, K> = BTreeMap<K, Vec<&'a Entry>>;
+
+/// A struct summarizing the result of [`Entries::merge()`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct MergeSummary {
+    /// The number of books that didn't match any existing book.
+    pub books_added: usize,
+
+    /// The number of books merged into an existing book.
+    pub books_merged: usize,
+
+    /// The number of annotations added, including those of added books.
+    pub annotations_added: usize,
+
+    /// The number of annotations that already existed.
+    pub annotations_duplicated: usize,
+
+    /// The number of books and annotations whose contents differed.
+    pub conflicts: usize,
+}
+
+/// An enum representing the keys [`Entry`]s can be sorted by.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BookSortKey {
+    /// Sort alphabetically by the book's title.
+    #[default]
+    Title,
+
+    /// Sort alphabetically by the book's author.
+    Author,
+
+    /// Sort by the date the book was last opened, most recent first.
+    LastOpened,
+
+    /// Sort by the book's reading progress, furthest along first.
+    Progress,
+}
 
 /// A container struct that stores a [`Book`] and its respective [`Annotation`]s.
-#[derive(Debug, Default, Clone, Serialize)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
 pub struct Entry {
     /// The entry's [`Book`].
     pub book: Book,
@@ -36,6 +464,93 @@ pub struct Entry {
     pub annotations: Vec<Annotation>,
 }
 
+impl Entry {
+    /// Merges another [`Entry`] for the same book into this one. See [`Entries::merge()`].
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - The [`Entry`] to merge into this one.
+    /// * `summary` - The [`MergeSummary`] to update.
+    fn merge(&mut self, other: Self, summary: &mut MergeSummary) {
+        let book = &mut self.book;
+
+        if book.metadata.id != other.book.metadata.id {
+            tracing::info!(
+                "merged book '{}' by {} with id {} into id {}",
+                book.title,
+                book.author,
+                other.book.metadata.id,
+                book.metadata.id,
+            );
+        } else if book.title != other.book.title || book.author != other.book.author {
+            summary.conflicts += 1;
+            tracing::warn!(
+                "conflicting title/author for book {}: kept '{}' by {}, discarded '{}' by {}",
+                book.metadata.id,
+                book.title,
+                book.author,
+                other.book.title,
+                other.book.author,
+            );
+        }
+
+        // The most recent date and furthest progress are kept.
+        book.metadata.last_opened =
+            match (book.metadata.last_opened, other.book.metadata.last_opened) {
+                (Some(a), Some(b)) => Some(if *b > *a { b } else { a }),
+                (a, b) => a.or(b),
+            };
+        book.metadata.progress = match (book.metadata.progress, other.book.metadata.progress) {
+            (Some(a), Some(b)) => Some(a.max(b)),
+            (a, b) => a.or(b),
+        };
+
+        if book.cover_path.is_none() {
+            book.cover_path = other.book.cover_path;
+        }
+
+        for mut annotation in other.annotations {
+            let existing = self
+                .annotations
+                .iter_mut()
+                .find(|existing| existing.metadata.id == annotation.metadata.id);
+
+            let Some(existing) = existing else {
+                summary.annotations_added += 1;
+                annotation.metadata.book_id.clone_from(&book.metadata.id);
+                self.annotations.push(annotation);
+                continue;
+            };
+
+            summary.annotations_duplicated += 1;
+
+            let is_same = existing.body == annotation.body
+                && existing.notes == annotation.notes
+                && existing.style == annotation.style;
+
+            if is_same {
+                continue;
+            }
+
+            summary.conflicts += 1;
+
+            let is_newer = *annotation.metadata.modified > *existing.metadata.modified;
+
+            tracing::warn!(
+                "conflicting annotation {} in book {}: kept the {} version",
+                annotation.metadata.id,
+                book.metadata.id,
+                if is_newer { "newer" } else { "existing" },
+            );
+
+            if is_newer {
+                annotation.metadata.book_id.clone_from(&book.metadata.id);
+                *existing = annotation;
+            }
+        }
+    }
+}
+
 impl From<Book> for Entry {
     /// Constructs an instance of [`Entry`] via a [`Book`] object. This is the primary way
     /// [`Entry`]s are created.
@@ -46,3 +561,289 @@ impl From<Book> for Entry {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    use crate::models::datetime::DateTimeUtc;
+
+    fn create_test_book(id: &str) -> Book {
+        let mut book = Book::default();
+        book.metadata.id = id.to_string();
+        book
+    }
+
+    fn create_test_annotation(book_id: &str) -> Annotation {
+        let mut annotation = Annotation::default();
+        annotation.metadata.book_id = book_id.to_string();
+        annotation
+    }
+
+    // Tests that annotations are assigned to the entry of the book they belong to.
+    #[test]
+    fn from_parts() {
+        let books = vec![create_test_book("00"), create_test_book("01")];
+        let annotations = vec![
+            create_test_annotation("00"),
+            create_test_annotation("00"),
+            create_test_annotation("01"),
+        ];
+
+        let entries = Entries::from_parts(books, annotations);
+
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries["00"].annotations.len(), 2);
+        assert_eq!(entries["01"].annotations.len(), 1);
+    }
+
+    // Tests that un-annotated books are kept and orphaned annotations are discarded.
+    #[test]
+    fn from_parts_unmatched() {
+        let books = vec![create_test_book("00"), create_test_book("01")];
+        let annotations = vec![create_test_annotation("00"), create_test_annotation("02")];
+
+        let entries = Entries::from_parts(books, annotations);
+
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries["00"].annotations.len(), 1);
+        assert!(entries["01"].annotations.is_empty());
+    }
+
+    fn create_test_entries() -> Entries {
+        [
+            ("00", "The Art Spirit", "Robert Henri", 3, 0.25),
+            ("01", "Think on These Things", "Krishnamurti", 1, 0.75),
+            ("02", "a Study in Scarlet", "Arthur Conan Doyle", 2, 0.5),
+        ]
+        .into_iter()
+        .map(|(id, title, author, last_opened, progress)| {
+            let mut book = create_test_book(id);
+            book.title = title.to_string();
+            book.author = author.to_string();
+            book.metadata.last_opened = Some(DateTimeUtc::from(f64::from(last_opened)));
+            book.metadata.progress = Some(progress);
+            (id.to_string(), Entry::from(book))
+        })
+        .collect()
+    }
+
+    fn sorted_ids(entries: &Entries, key: BookSortKey) -> Vec<&str> {
+        entries
+            .sorted(key)
+            .iter()
+            .map(|entry| entry.book.metadata.id.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn sorted() {
+        let entries = create_test_entries();
+
+        assert_eq!(sorted_ids(&entries, BookSortKey::Title), ["02", "00", "01"]);
+        assert_eq!(
+            sorted_ids(&entries, BookSortKey::Author),
+            ["02", "01", "00"]
+        );
+        assert_eq!(
+            sorted_ids(&entries, BookSortKey::LastOpened),
+            ["00", "02", "01"]
+        );
+        assert_eq!(
+            sorted_ids(&entries, BookSortKey::Progress),
+            ["01", "02", "00"]
+        );
+    }
+
+    // Tests that groups are ordered by key and their entries keep their sorted order.
+    #[test]
+    fn group_by() {
+        let mut entries = create_test_entries();
+        entries.get_mut("01").unwrap().book.author = "Robert Henri".to_string();
+
+        let groups = entries
+            .sorted(BookSortKey::Progress)
+            .group_by(|entry| entry.book.author.clone());
+
+        let ids: Vec<(&str, Vec<&str>)> = groups
+            .iter()
+            .map(|(author, entries)| {
+                let ids = entries
+                    .iter()
+                    .map(|entry| entry.book.metadata.id.as_str())
+                    .collect();
+                (author.as_str(), ids)
+            })
+            .collect();
+
+        assert_eq!(
+            ids,
+            [
+                ("Arthur Conan Doyle", vec!["02"]),
+                ("Robert Henri", vec!["01", "00"]),
+            ]
+        );
+    }
+
+    // Tests that the entries kept are the first ones in sorted order.
+    #[test]
+    fn truncate() {
+        let mut entries = create_test_entries();
+        entries.truncate(BookSortKey::Progress, 2);
+
+        assert_eq!(entries.len(), 2);
+        assert!(entries.contains_key("01"));
+        assert!(entries.contains_key("02"));
+    }
+
+    fn create_test_merge_annotation(
+        id: &str,
+        book_id: &str,
+        body: &str,
+        modified: f64,
+    ) -> Annotation {
+        let mut annotation = create_test_annotation(book_id);
+        annotation.metadata.id = id.to_string();
+        annotation.metadata.modified = DateTimeUtc::from(modified);
+        annotation.body = body.to_string();
+        annotation
+    }
+
+    // Tests that books are matched by id first and then by title and author.
+    #[test]
+    fn merge_books() {
+        let mut entries = create_test_entries();
+
+        let mut other = create_test_entries();
+        other.remove("00");
+        other.remove("01");
+
+        let mut book = create_test_book("IOS-01");
+        book.title = " think on these things".to_string();
+        book.author = "KRISHNAMURTI".to_string();
+        other.insert("IOS-01".to_string(), Entry::from(book));
+
+        other.insert("03".to_string(), Entry::from(create_test_book("03")));
+
+        let summary = entries.merge(other);
+
+        assert_eq!(summary.books_merged, 2);
+        assert_eq!(summary.books_added, 1);
+        assert_eq!(summary.conflicts, 0);
+        assert_eq!(entries.len(), 4);
+        assert!(!entries.contains_key("IOS-01"));
+    }
+
+    // Tests that books with different ids and titles are matched by their ISBN, ignoring hyphens.
+    #[test]
+    fn merge_books_by_isbn() {
+        let mut entries = create_test_entries();
+        entries.get_mut("00").unwrap().book.metadata.isbn = Some("9780060648794".to_string());
+
+        let mut other = Entries::default();
+
+        let mut book = create_test_book("IOS-00");
+        book.title = "The Art Spirit (Centennial Edition)".to_string();
+        book.metadata.isbn = Some("978-0-06-064879-4".to_string());
+        other.insert("IOS-00".to_string(), Entry::from(book));
+
+        let mut book = create_test_book("IOS-01");
+        book.title = "Another Book".to_string();
+        book.metadata.isbn = Some(String::new());
+        other.insert("IOS-01".to_string(), Entry::from(book));
+
+        let summary = entries.merge(other);
+
+        assert_eq!(summary.books_merged, 1);
+        assert_eq!(summary.books_added, 1);
+        assert!(!entries.contains_key("IOS-00"));
+        assert!(entries.contains_key("IOS-01"));
+    }
+
+    // Tests that annotations are deduplicated on their id and the newest version is kept.
+    #[test]
+    fn merge_annotations() {
+        let mut entries = create_test_entries();
+        entries.get_mut("00").unwrap().annotations = vec![
+            create_test_merge_annotation("A", "00", "old", 1.0),
+            create_test_merge_annotation("B", "00", "same", 1.0),
+        ];
+
+        let mut book = entries["00"].book.clone();
+        book.metadata.id = "IOS-00".to_string();
+
+        let mut entry = Entry::from(book);
+        entry.annotations = vec![
+            create_test_merge_annotation("A", "IOS-00", "new", 2.0),
+            create_test_merge_annotation("B", "IOS-00", "same", 1.0),
+            create_test_merge_annotation("C", "IOS-00", "added", 1.0),
+        ];
+
+        let other: Entries = [("IOS-00".to_string(), entry)].into_iter().collect();
+        let summary = entries.merge(other);
+
+        let annotations = &entries["00"].annotations;
+        let bodies: Vec<&str> = annotations.iter().map(|a| a.body.as_str()).collect();
+
+        assert_eq!(bodies, ["new", "same", "added"]);
+        assert!(annotations.iter().all(|a| a.metadata.book_id == "00"));
+        assert_eq!(summary.annotations_added, 1);
+        assert_eq!(summary.annotations_duplicated, 2);
+        assert_eq!(summary.conflicts, 1);
+    }
+
+    // Tests that ids are remapped to content-based hashes, keeping Apple Books' ids and
+    // disambiguating duplicates, and that they can be remapped back.
+    #[test]
+    fn remap_ids() {
+        let mut entries = create_test_entries();
+
+        for entry in entries.values_mut() {
+            entry.book.metadata.asset_id = entry.book.metadata.id.clone();
+        }
+
+        let mut annotations = vec![
+            create_test_annotation("00"),
+            create_test_annotation("00"),
+            create_test_annotation("00"),
+        ];
+
+        for (annotation, uuid) in annotations.iter_mut().zip(["C", "A", "B"]) {
+            annotation.body = "body".to_string();
+            annotation.metadata.id = uuid.to_string();
+            annotation.metadata.uuid = uuid.to_string();
+        }
+
+        annotations[0].body = "other".to_string();
+        entries.get_mut("00").unwrap().annotations = annotations;
+
+        entries.remap_ids(IdScheme::Hash);
+
+        let book = &entries[&id::book_hash(&create_test_entries()["00"].book)];
+        let ids: Vec<&str> = book
+            .annotations
+            .iter()
+            .map(|a| a.metadata.id.as_str())
+            .collect();
+
+        assert_eq!(book.book.metadata.asset_id, "00");
+        assert!(!ids[0].ends_with("-2"));
+        assert_eq!(ids[2], format!("{}-2", ids[1]));
+        assert!(book
+            .annotations
+            .iter()
+            .all(|a| a.metadata.book_id == book.book.metadata.id));
+
+        entries.remap_ids(IdScheme::Uuid);
+
+        let ids: Vec<&str> = entries["00"]
+            .annotations
+            .iter()
+            .map(|a| a.metadata.id.as_str())
+            .collect();
+
+        assert_eq!(ids, ["C", "A", "B"]);
+        assert!(entries.contains_key("01"));
+    }
+}
@@ -7,6 +7,7 @@ use std::borrow::ToOwned;
 
 use once_cell::sync::Lazy;
 use regex::Regex;
+use serde::Serialize;
 
 /// Capture a 'Step Reference' e.g. `/6` `/4`
 ///
@@ -179,6 +180,170 @@ pub fn parse(raw: &str) -> String {
     location
 }
 
+/// A position within a book made up of its step references followed by its character offset, if
+/// any. Positions can be compared to determine their order of appearance.
+pub type Position = Vec<usize>;
+
+/// Returns the start and end [`Position`]s of a range `epubcfi`.
+///
+/// Returns `None` if the `epubcfi` is invalid or isn't a range.
+///
+/// Examples:
+///
+/// ```plaintext
+/// input:  epubcfi(/6/4[chap01ref]!/4[body01]/10[para05],/2/1:1,/3:4)
+/// output: ([6, 4, 4, 10, 2, 1, 1], [6, 4, 4, 10, 3, 4])
+/// ```
+///
+/// See <https://w3c.github.io/epub-specs/epub33/epubcfi/#sec-ranges> for more information.
+#[must_use]
+pub fn parse_range(raw: &str) -> Option<(Position, Position)> {
+    if !raw.starts_with("epubcfi(") || !raw.ends_with(')') {
+        return None;
+    }
+
+    let mut location = raw[8..raw.len() - 1].to_owned();
+
+    location = RE_ASSERTIONS.replace_all(&location, "").into_owned();
+    location = RE_TEMPORAL_OFFSET.replace_all(&location, "").into_owned();
+    location = RE_SPACIAL_OFFSET.replace_all(&location, "").into_owned();
+
+    match location.split(',').collect::<Vec<&str>>()[..] {
+        [parent_path, range_start, range_end] => Some((
+            self::parse_position(&format!("{parent_path}{range_start}")),
+            self::parse_position(&format!("{parent_path}{range_end}")),
+        )),
+        _ => None,
+    }
+}
+
+/// The number of digits each number in a [`Location::sortkey()`] is padded to.
+const SORTKEY_WIDTH: usize = 6;
+
+/// A struct representing the structured parts of an `epubcfi`'s location.
+///
+/// Examples:
+///
+/// ```plaintext
+/// input:  epubcfi(/6/4[chap01ref]!/4[body01]/10[para05]/1:3[xx,y])
+/// output: Location { spine_index: Some(1), steps: [4, 10, 1], character_offset: Some(3) }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct Location {
+    /// The zero-based index of the spine item, usually a chapter, the location is in. This is
+    /// `None` if the `epubcfi` doesn't reference a spine item.
+    pub spine_index: Option<usize>,
+
+    /// The step references within the spine item's document.
+    pub steps: Vec<usize>,
+
+    /// The character offset within the text node the location ends at, if any.
+    pub character_offset: Option<usize>,
+}
+
+impl Location {
+    /// Returns a string that sorts locations into their order of appearance when compared as
+    /// strings. Each number is zero-padded e.g. `000001.000004.000010.000001:000003`.
+    #[must_use]
+    pub fn sortkey(&self) -> String {
+        let steps = std::iter::once(self.spine_index.unwrap_or_default())
+            .chain(self.steps.iter().copied())
+            .map(|step| format!("{step:0SORTKEY_WIDTH$}"))
+            .collect::<Vec<_>>()
+            .join(".");
+
+        match self.character_offset {
+            Some(offset) => format!("{steps}:{offset:0SORTKEY_WIDTH$}"),
+            None => steps,
+        }
+    }
+}
+
+/// Returns the structured [`Location`] of an `epubcfi`.
+///
+/// Like [`parse()`], assertions and temporal/spatial offsets are ignored and only the start of a
+/// range is used. The steps before the first indirection (`!`) point to the spine item in the
+/// package document. The step to the spine item is `2 * (index + 1)` as even steps reference
+/// elements.
+///
+/// Returns `None` if the `epubcfi` is invalid.
+#[must_use]
+pub fn parse_location(raw: &str) -> Option<Location> {
+    if !raw.starts_with("epubcfi(") || !raw.ends_with(')') {
+        return None;
+    }
+
+    let mut location = raw[8..raw.len() - 1].to_owned();
+
+    location = RE_ASSERTIONS.replace_all(&location, "").into_owned();
+    location = RE_TEMPORAL_OFFSET.replace_all(&location, "").into_owned();
+    location = RE_SPACIAL_OFFSET.replace_all(&location, "").into_owned();
+
+    let location = match location.split(',').collect::<Vec<&str>>()[..] {
+        [parent_path, range_start, _] => format!("{parent_path}{range_start}"),
+        _ => location,
+    };
+
+    let steps = |path: &str| -> Vec<usize> {
+        RE_STEP_REFERENCE
+            .find_iter(path)
+            .filter_map(|m| m.as_str()[1..].parse().ok())
+            .collect()
+    };
+
+    let (spine_index, steps) = match location.split_once('!') {
+        Some((package, document)) => (
+            steps(package)
+                .last()
+                .filter(|step| **step >= 2 && *step % 2 == 0)
+                .map(|step| step / 2 - 1),
+            steps(document),
+        ),
+        None => (None, steps(&location)),
+    };
+
+    if spine_index.is_none() && steps.is_empty() {
+        return None;
+    }
+
+    let character_offset = RE_CHARACTER_OFFSET
+        .find(&location)
+        .and_then(|m| m.as_str()[1..].parse().ok());
+
+    Some(Location {
+        spine_index,
+        steps,
+        character_offset,
+    })
+}
+
+/// Returns a [`Location::sortkey()`] for an `epubcfi`. Returns an empty string if the `epubcfi` is
+/// invalid.
+#[must_use]
+pub fn sortkey(raw: &str) -> String {
+    self::parse_location(raw)
+        .map(|location| location.sortkey())
+        .unwrap_or_default()
+}
+
+/// Returns the [`Position`] of a simplified path i.e. one without any assertions or offsets other
+/// than a trailing character offset.
+///
+/// # Arguments
+///
+/// * `path` - The path to parse e.g. `/6/4!/4/10/2/1:1`.
+fn parse_position(path: &str) -> Position {
+    let steps = RE_STEP_REFERENCE
+        .find_iter(path)
+        .filter_map(|m| m.as_str()[1..].parse().ok());
+
+    let character_offset = RE_CHARACTER_OFFSET
+        .find(path)
+        .and_then(|m| m.as_str()[1..].parse().ok());
+
+    steps.chain(character_offset).collect()
+}
+
 #[cfg(test)]
 mod test {
 
@@ -356,4 +521,60 @@ mod test {
             "epubcfi(/2/4!/6[bar]/44!/12:100[hah])" < "epubcfi(/2/4!/6[bar]/44!/12:200[cat])"
         ),
     }
+
+    #[test]
+    fn parse_epubcfi_range() {
+        assert_eq!(
+            parse_range("epubcfi(/6/4[chap01ref]!/4[body01]/10[para05],/2/1:1,/3:4)"),
+            Some((vec![6, 4, 4, 10, 2, 1, 1], vec![6, 4, 4, 10, 3, 4]))
+        );
+        assert_eq!(
+            parse_range("epubcfi(/6/28[chap06]!/4/24[para06]/1,:4,:44)"),
+            Some((vec![6, 28, 4, 24, 1, 4], vec![6, 28, 4, 24, 1, 44]))
+        );
+        assert_eq!(
+            parse_range("epubcfi(/6/4[chap01ref]!/4[body01]/10[para05]/1:3[xx,y])"),
+            None
+        );
+        assert_eq!(parse_range("not-an-epubcfi"), None);
+    }
+
+    #[test]
+    fn parse_epubcfi_location() {
+        assert_eq!(
+            parse_location("epubcfi(/6/4[chap01ref]!/4[body01]/10[para05]/1:3[xx,y])"),
+            Some(Location {
+                spine_index: Some(1),
+                steps: vec![4, 10, 1],
+                character_offset: Some(3),
+            })
+        );
+        assert_eq!(
+            parse_location("epubcfi(/6/28[chap06]!/4/24[para06]/1,:4,:44)"),
+            Some(Location {
+                spine_index: Some(13),
+                steps: vec![4, 24, 1],
+                character_offset: Some(4),
+            })
+        );
+        assert_eq!(
+            parse_location("epubcfi(/1/2)"),
+            Some(Location {
+                spine_index: None,
+                steps: vec![1, 2],
+                character_offset: None,
+            })
+        );
+        assert_eq!(parse_location("not-an-epubcfi"), None);
+    }
+
+    // Tests that sortkeys compare numerically, unlike `parse()` where `6.12` sorts before `6.4`.
+    #[test]
+    fn sortkey_order() {
+        let a = sortkey("epubcfi(/6/4[chap01ref]!/4/10/1:3)");
+        let b = sortkey("epubcfi(/6/12[chap05ref]!/4/2/1:0)");
+
+        assert_eq!(a, "000001.000004.000010.000001:000003");
+        assert!(a < b);
+    }
 }
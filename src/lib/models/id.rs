@@ -0,0 +1,156 @@
+//! Defines the [`IdScheme`] enum and functions for deriving content-based ids.
+//!
+//! By default, books and annotations are identified by the ids Apple Books assigns them: a book's
+//! asset id and an annotation's UUID. These are stable for as long as the book stays in the
+//! library but are unknown outside of Apple Books and change if a book is removed and re-added.
+//!
+//! The [`IdScheme::Hash`] scheme instead derives ids from the data itself, so the same book and
+//! annotation always receive the same id regardless of where they were read from. Apple's ids are
+//! always kept in [`BookMetadata::asset_id`][asset-id] and [`AnnotationMetadata::uuid`][uuid].
+//!
+//! [asset-id]: crate::models::book::BookMetadata::asset_id
+//! [uuid]: crate::models::annotation::AnnotationMetadata::uuid
+
+use std::collections::HashSet;
+
+use sha2::{Digest, Sha256};
+
+use super::annotation::Annotation;
+use super::book::Book;
+
+/// The number of characters a content-based id is truncated to.
+const HASH_LENGTH: usize = 32;
+
+/// An enum representing the schemes used to set book and annotation ids.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum IdScheme {
+    /// Use the ids assigned by Apple Books.
+    #[default]
+    Uuid,
+
+    /// Use ids derived from a hash of the book's title and author and the annotation's location
+    /// and highlighted text.
+    Hash,
+}
+
+/// Returns a book's content-based id, derived from its title and author. Both are trimmed and
+/// lowercased beforehand so minor differences between platforms don't change the id.
+///
+/// # Arguments
+///
+/// * `book` - The book to derive the id from.
+#[must_use]
+pub fn book_hash(book: &Book) -> String {
+    let normalize = |value: &str| value.trim().to_lowercase();
+
+    self::hash(&[&normalize(&book.title), &normalize(&book.author)])
+}
+
+/// Returns an annotation's content-based id, derived from its book's id, its `epubcfi` and its
+/// highlighted text. Editing an annotation's notes or tags doesn't change its id.
+///
+/// # Arguments
+///
+/// * `book_id` - The content-based id of the annotation's book. See [`book_hash()`].
+/// * `annotation` - The annotation to derive the id from.
+#[must_use]
+pub fn annotation_hash(book_id: &str, annotation: &Annotation) -> String {
+    self::hash(&[
+        book_id,
+        &annotation.metadata.epubcfi,
+        annotation.body.trim(),
+    ])
+}
+
+/// Returns an Apple Books id, falling back to the current id if it's missing e.g. for data read
+/// from an export made before Apple Books' ids were kept.
+///
+/// # Arguments
+///
+/// * `apple_id` - The id assigned by Apple Books.
+/// * `id` - The current id.
+pub(crate) fn or_existing(apple_id: &str, id: &str) -> String {
+    if apple_id.is_empty() {
+        id.to_owned()
+    } else {
+        apple_id.to_owned()
+    }
+}
+
+/// Returns an id that's not yet in a set of ids by suffixing it with `-2`, `-3`, etc. The returned
+/// id is added to the set.
+///
+/// # Arguments
+///
+/// * `id` - The id to make unique.
+/// * `ids` - The ids already in use.
+pub(crate) fn unique(id: &str, ids: &mut HashSet<String>) -> String {
+    let mut unique = id.to_owned();
+    let mut count = 1;
+
+    while !ids.insert(unique.clone()) {
+        count += 1;
+        unique = format!("{id}-{count}");
+    }
+
+    unique
+}
+
+/// Returns a truncated, hex-encoded SHA-256 hash of a list of values.
+pub(crate) fn hash(values: &[&str]) -> String {
+    let mut hasher = Sha256::new();
+
+    for value in values {
+        hasher.update(value.as_bytes());
+        // Separates the values so e.g. `("ab", "c")` and `("a", "bc")` hash differently.
+        hasher.update([0]);
+    }
+
+    let mut hash = hex::encode(hasher.finalize());
+    hash.truncate(HASH_LENGTH);
+    hash
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    fn book(title: &str, author: &str) -> Book {
+        Book {
+            title: title.to_string(),
+            author: author.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn book_hash_normalized() {
+        let hash = book_hash(&book("The Art Spirit", "Robert Henri"));
+
+        assert_eq!(hash.len(), HASH_LENGTH);
+        assert_eq!(hash, book_hash(&book(" the art spirit ", "ROBERT HENRI")));
+        assert_ne!(hash, book_hash(&book("The Art Spirit", "Robert")));
+    }
+
+    // Tests that only an annotation's location and body affect its id.
+    #[test]
+    fn annotation_hash_content() {
+        let mut annotation = Annotation {
+            body: "The Art Spirit".to_string(),
+            ..Default::default()
+        };
+        annotation.metadata.epubcfi = "epubcfi(/6/10[c01]!/4/10/3,:335,:749)".to_string();
+
+        let hash = annotation_hash("00", &annotation);
+
+        annotation.notes = "#tag".to_string();
+        annotation.metadata.uuid = "UUID".to_string();
+        assert_eq!(hash, annotation_hash("00", &annotation));
+
+        assert_ne!(hash, annotation_hash("01", &annotation));
+
+        annotation.body = "Art".to_string();
+        assert_ne!(hash, annotation_hash("00", &annotation));
+    }
+}
@@ -2,7 +2,10 @@
 
 pub mod annotation;
 pub mod book;
+pub mod bookmark;
 pub mod datetime;
 pub mod dummy;
 pub mod entry;
 pub mod epubcfi;
+pub mod id;
+pub mod pdf;
@@ -0,0 +1,213 @@
+//! Defines helpers for locating annotations made in PDFs.
+//!
+//! Apple Books doesn't store an `epubcfi` for annotations made in PDFs. Instead, the zero-based
+//! index of the page the annotation was made on is stored in `ZPLLOCATIONRANGESTART` and, where
+//! available, the areas of the page it covers are stored in `ZPLUSERDATA` as a property list.
+
+use std::io::Cursor;
+
+use plist::Value;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// The width each page number is zero-padded to in a [`sortkey()`]. This matches the width used
+/// by [`Location::sortkey()`][sortkey] so PDF and EPUB sortkeys have the same shape.
+///
+/// [sortkey]: super::epubcfi::Location::sortkey
+const SORTKEY_WIDTH: usize = 6;
+
+/// A struct representing an area of a PDF page covered by an annotation, in PDF points.
+///
+/// PDF coordinates start at the bottom-left corner of the page.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct Rect {
+    /// The distance of the area's left edge from the page's left edge.
+    pub x: f64,
+
+    /// The distance of the area's bottom edge from the page's bottom edge.
+    pub y: f64,
+
+    #[allow(missing_docs)]
+    pub width: f64,
+
+    #[allow(missing_docs)]
+    pub height: f64,
+}
+
+impl Rect {
+    /// Parses a rect from its string representation e.g. `{{72, 640.5}, {451.2, 14}}`. Returns
+    /// `None` if the string isn't a rect.
+    ///
+    /// # Arguments
+    ///
+    /// * `raw` - The string to parse.
+    #[must_use]
+    pub fn parse(raw: &str) -> Option<Self> {
+        let raw = raw.trim();
+
+        if !raw.starts_with("{{") || !raw.ends_with("}}") {
+            return None;
+        }
+
+        let numbers = raw
+            .split(['{', '}', ','])
+            .map(str::trim)
+            .filter(|part| !part.is_empty())
+            .map(str::parse::<f64>)
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .ok()?;
+
+        match numbers[..] {
+            [x, y, width, height] => Some(Self {
+                x,
+                y,
+                width,
+                height,
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// Returns the one-based page number of a PDF annotation from its zero-based page index.
+///
+/// # Arguments
+///
+/// * `index` - The zero-based page index stored by Apple Books.
+#[must_use]
+pub fn page(index: i64) -> Option<usize> {
+    usize::try_from(index).ok().map(|index| index + 1)
+}
+
+/// Returns a simplified location string for a page e.g. `12`. See [`epubcfi::parse()`][parse].
+///
+/// [parse]: super::epubcfi::parse
+///
+/// # Arguments
+///
+/// * `page` - The one-based page number.
+#[must_use]
+pub fn location(page: usize) -> String {
+    page.to_string()
+}
+
+/// Returns a string that sorts pages into their order of appearance when compared as strings
+/// e.g. `000012`.
+///
+/// # Arguments
+///
+/// * `page` - The one-based page number.
+#[must_use]
+pub fn sortkey(page: usize) -> String {
+    format!("{page:0SORTKEY_WIDTH$}")
+}
+
+/// Returns the rects found in an annotation's `ZPLUSERDATA`, in the order they're stored.
+///
+/// The data is decoded as a property list and searched for strings representing rects. See
+/// [`Rect::parse()`]. Returns an empty list if the data isn't a property list or doesn't contain
+/// any rects.
+///
+/// # Arguments
+///
+/// * `data` - The raw `ZPLUSERDATA` bytes.
+#[must_use]
+pub fn parse_rects(data: &[u8]) -> Vec<Rect> {
+    let Ok(value) = Value::from_reader(Cursor::new(data)) else {
+        return Vec::new();
+    };
+
+    let mut rects = Vec::new();
+    self::collect_rects(&value, &mut rects);
+    rects
+}
+
+/// Recursively collects rects from a property list value.
+///
+/// # Arguments
+///
+/// * `value` - The value to search.
+/// * `rects` - The rects found so far.
+fn collect_rects(value: &Value, rects: &mut Vec<Rect>) {
+    match value {
+        Value::String(string) => rects.extend(Rect::parse(string)),
+        Value::Array(array) => {
+            for value in array {
+                self::collect_rects(value, rects);
+            }
+        }
+        Value::Dictionary(dictionary) => {
+            for value in dictionary.values() {
+                self::collect_rects(value, rects);
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    // Tests that rects are parsed from their string representation.
+    #[test]
+    fn parse_rect() {
+        assert_eq!(
+            Rect::parse("{{72, 640.5}, {451.2, 14}}"),
+            Some(Rect {
+                x: 72.0,
+                y: 640.5,
+                width: 451.2,
+                height: 14.0,
+            })
+        );
+        assert_eq!(Rect::parse("{72, 640.5}"), None);
+        assert_eq!(Rect::parse("{{72, 640.5}, {451.2}}"), None);
+        assert_eq!(Rect::parse("The Art Spirit"), None);
+    }
+
+    // Tests that rects are found anywhere inside a property list and that invalid data is ignored.
+    #[test]
+    fn parse_rects() {
+        let value = Value::Dictionary(
+            [
+                (
+                    "rects".to_string(),
+                    Value::Array(vec![
+                        Value::String("{{72, 640.5}, {451.2, 14}}".to_string()),
+                        Value::String("{{72, 626}, {120, 14}}".to_string()),
+                    ]),
+                ),
+                ("color".to_string(), Value::String("yellow".to_string())),
+            ]
+            .into_iter()
+            .collect(),
+        );
+
+        let mut data = Vec::new();
+        value.to_writer_binary(&mut data).unwrap();
+
+        let rects = super::parse_rects(&data);
+
+        assert_eq!(rects.len(), 2);
+        assert_eq!(
+            rects[1],
+            Rect {
+                x: 72.0,
+                y: 626.0,
+                width: 120.0,
+                height: 14.0,
+            }
+        );
+        assert!(super::parse_rects(b"not a plist").is_empty());
+    }
+
+    #[test]
+    fn page_sortkey() {
+        assert_eq!(page(0), Some(1));
+        assert_eq!(page(-1), None);
+        assert_eq!(sortkey(12), "000012");
+        assert_eq!(location(12), "12");
+    }
+}
@@ -0,0 +1,207 @@
+//! Defines types for bundling the annotations made within a period into a review pack.
+//!
+//! A pack is a dated directory, or `zip` archive, containing a render of every book annotated
+//! within the period along with an index file:
+//!
+//! ```plaintext
+//! [output-directory]
+//!  │
+//!  └── readstor-pack-[YYYY-MM-DD]-[YYYY-MM-DD]
+//!       │
+//!       ├── index.md
+//!       ├── [author] - [title].md
+//!       ├── [author] - [title].md
+//!       └── ...
+//! ```
+
+pub mod zip;
+
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+
+use crate::defaults::DATE_FORMAT_TEMPLATE;
+use crate::result::Result;
+
+/// The basename of a pack. The period's dates are appended.
+pub const PACK_NAME: &str = "readstor-pack";
+
+/// The filename of a pack's index.
+pub const INDEX_FILENAME: &str = "index.md";
+
+/// Returns a pack's name from its period e.g. `readstor-pack-2024-01-01-2024-01-31`.
+///
+/// # Arguments
+///
+/// * `since` - The start of the period.
+/// * `until` - The end of the period.
+#[must_use]
+pub fn name(since: &DateTime<Utc>, until: &DateTime<Utc>) -> String {
+    format!(
+        "{PACK_NAME}-{}-{}",
+        since.format(DATE_FORMAT_TEMPLATE),
+        until.format(DATE_FORMAT_TEMPLATE)
+    )
+}
+
+/// A struct representing a pack's index, listing every book in the pack and its rendered files.
+#[derive(Debug, Clone)]
+pub struct PackIndex {
+    /// The start of the period.
+    pub since: DateTime<Utc>,
+
+    /// The end of the period.
+    pub until: DateTime<Utc>,
+
+    /// The books in the pack, in the order they're listed.
+    pub books: Vec<PackBook>,
+}
+
+/// A struct representing a book within a [`PackIndex`].
+#[derive(Debug, Clone, Default)]
+pub struct PackBook {
+    /// The title of the book.
+    pub title: String,
+
+    /// The author of the book.
+    pub author: String,
+
+    /// The number of annotations made within the period.
+    pub annotations: usize,
+
+    /// The paths to the book's rendered files, relative to the pack's directory.
+    pub files: Vec<PathBuf>,
+}
+
+impl PackIndex {
+    /// Creates a new, empty instance of [`PackIndex`].
+    ///
+    /// # Arguments
+    ///
+    /// * `since` - The start of the period.
+    /// * `until` - The end of the period.
+    #[must_use]
+    pub fn new(since: DateTime<Utc>, until: DateTime<Utc>) -> Self {
+        Self {
+            since,
+            until,
+            books: Vec::new(),
+        }
+    }
+
+    /// Returns the total number of annotations in the pack.
+    #[must_use]
+    pub fn count_annotations(&self) -> usize {
+        self.books.iter().map(|book| book.annotations).sum()
+    }
+
+    /// Returns the index as Markdown. Each book links to its rendered files.
+    #[must_use]
+    pub fn render(&self) -> String {
+        let mut lines = vec![
+            format!(
+                "# Review: {} to {}",
+                self.since.format(DATE_FORMAT_TEMPLATE),
+                self.until.format(DATE_FORMAT_TEMPLATE)
+            ),
+            String::new(),
+            format!(
+                "{} annotations from {} books.",
+                self.count_annotations(),
+                self.books.len()
+            ),
+        ];
+
+        for book in &self.books {
+            lines.push(String::new());
+            lines.push(format!("## {}", book.title));
+            lines.push(String::new());
+            lines.push(format!(
+                "{} · {} annotations",
+                book.author, book.annotations
+            ));
+
+            if !book.files.is_empty() {
+                lines.push(String::new());
+            }
+
+            // Angle brackets allow paths containing spaces.
+            lines.extend(book.files.iter().map(|file| {
+                let file = file.to_string_lossy().replace('\\', "/");
+                format!("- [{file}](<{file}>)")
+            }));
+        }
+
+        lines.push(String::new());
+        lines.join("\n")
+    }
+
+    /// Writes the index into a pack's directory.
+    ///
+    /// # Arguments
+    ///
+    /// * `directory` - The pack's directory.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if any IO errors are encountered.
+    pub fn write(&self, directory: &Path) -> Result<()> {
+        std::fs::create_dir_all(directory)?;
+        std::fs::write(directory.join(INDEX_FILENAME), self.render())?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    use chrono::TimeZone;
+
+    fn index() -> PackIndex {
+        let mut index = PackIndex::new(
+            Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap(),
+            Utc.with_ymd_and_hms(2024, 1, 31, 0, 0, 0).unwrap(),
+        );
+
+        index.books = vec![
+            PackBook {
+                title: "The Art Spirit".to_string(),
+                author: "Robert Henri".to_string(),
+                annotations: 3,
+                files: vec![PathBuf::from("Robert Henri - The Art Spirit.md")],
+            },
+            PackBook {
+                title: "Think on These Things".to_string(),
+                author: "Krishnamurti".to_string(),
+                annotations: 2,
+                files: Vec::new(),
+            },
+        ];
+
+        index
+    }
+
+    #[test]
+    fn pack_name() {
+        let index = index();
+
+        assert_eq!(
+            name(&index.since, &index.until),
+            "readstor-pack-2024-01-01-2024-01-31"
+        );
+    }
+
+    #[test]
+    fn render_index() {
+        let render = index().render();
+
+        assert!(render.starts_with("# Review: 2024-01-01 to 2024-01-31"));
+        assert!(render.contains("5 annotations from 2 books."));
+        assert!(render.contains("Robert Henri · 3 annotations"));
+        assert!(render
+            .contains("- [Robert Henri - The Art Spirit.md](<Robert Henri - The Art Spirit.md>)"));
+    }
+}
@@ -0,0 +1,374 @@
+//! Defines a minimal writer for `zip` archives.
+//!
+//! Only what's needed to archive a pack or build an EPUB is supported: files are either deflated
+//! or stored as-is under a path relative to the archive's root. Archives larger than 4GB or with
+//! more than 65,535 files require the `zip64` extensions and are unsupported.
+
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use chrono::{Datelike, Local, Timelike};
+use flate2::write::DeflateEncoder;
+use flate2::Crc;
+
+use crate::result::{Error, Result};
+
+/// The signature of a local file header.
+const LOCAL_FILE_HEADER: u32 = 0x0403_4b50;
+
+/// The signature of a central directory file header.
+const CENTRAL_DIRECTORY_HEADER: u32 = 0x0201_4b50;
+
+/// The signature of the end of central directory record.
+const END_OF_CENTRAL_DIRECTORY: u32 = 0x0605_4b50;
+
+/// The version needed to extract deflated files i.e. `2.0`.
+const VERSION: u16 = 20;
+
+/// The general purpose flag marking filenames as UTF-8.
+const FLAG_UTF8: u16 = 1 << 11;
+
+/// The compression method for files stored as-is.
+const METHOD_STORE: u16 = 0;
+
+/// The compression method for deflated files.
+const METHOD_DEFLATE: u16 = 8;
+
+/// A struct representing a file written into an archive, kept to write the central directory.
+#[derive(Debug)]
+struct ZipEntry {
+    /// The file's path within the archive.
+    name: String,
+
+    /// The file's compression method.
+    method: u16,
+
+    /// The file's CRC-32 checksum.
+    crc: u32,
+
+    /// The size of the file as written into the archive.
+    compressed_size: u32,
+
+    /// The size of the file.
+    size: u32,
+
+    /// The offset of the file's local header from the start of the archive.
+    offset: u32,
+}
+
+/// A struct for writing files into a `zip` archive one at a time.
+///
+/// Each file is written as soon as it's added. The archive is only valid once
+/// [`ZipWriter::finish()`] has written its central directory.
+#[derive(Debug)]
+pub struct ZipWriter<W: Write> {
+    writer: W,
+    entries: Vec<ZipEntry>,
+    offset: usize,
+    time: u16,
+    date: u16,
+}
+
+impl<W: Write> ZipWriter<W> {
+    /// Creates a new instance of [`ZipWriter`]. All files are dated to the current local time.
+    ///
+    /// # Arguments
+    ///
+    /// * `writer` - Where to write the archive.
+    pub fn new(writer: W) -> Self {
+        let (time, date) = self::dos_datetime();
+
+        Self {
+            writer,
+            entries: Vec::new(),
+            offset: 0,
+            time,
+            date,
+        }
+    }
+
+    /// Deflates a file and adds it to the archive.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The file's path within the archive e.g. `nested/book.md`.
+    /// * `contents` - The file's contents.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if:
+    /// * Any IO errors are encountered.
+    /// * The archive requires the `zip64` extensions.
+    pub fn add(&mut self, name: &str, contents: &[u8]) -> Result<()> {
+        let mut encoder = DeflateEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(contents)?;
+        let compressed = encoder.finish()?;
+
+        self.add_entry(name, contents, &compressed, METHOD_DEFLATE)
+    }
+
+    /// Adds a file to the archive without compressing it. EPUBs, for example, require their
+    /// `mimetype` file to be stored as-is.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The file's path within the archive.
+    /// * `contents` - The file's contents.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if:
+    /// * Any IO errors are encountered.
+    /// * The archive requires the `zip64` extensions.
+    pub fn add_stored(&mut self, name: &str, contents: &[u8]) -> Result<()> {
+        self.add_entry(name, contents, contents, METHOD_STORE)
+    }
+
+    /// Writes the archive's central directory and returns the underlying writer.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if:
+    /// * Any IO errors are encountered.
+    /// * The archive requires the `zip64` extensions.
+    pub fn finish(mut self) -> Result<W> {
+        let (time, date) = (self.time, self.date);
+        let mut directory = Vec::new();
+
+        for entry in &self.entries {
+            directory.extend(CENTRAL_DIRECTORY_HEADER.to_le_bytes());
+            directory.extend(VERSION.to_le_bytes());
+            directory.extend(VERSION.to_le_bytes());
+            directory.extend(FLAG_UTF8.to_le_bytes());
+            directory.extend(entry.method.to_le_bytes());
+            directory.extend(time.to_le_bytes());
+            directory.extend(date.to_le_bytes());
+            directory.extend(entry.crc.to_le_bytes());
+            directory.extend(entry.compressed_size.to_le_bytes());
+            directory.extend(entry.size.to_le_bytes());
+            directory.extend(self::to_u16(entry.name.len())?.to_le_bytes());
+            // Extra field length, comment length, disk number and internal attributes.
+            directory.extend([0_u8; 8]);
+            // External attributes.
+            directory.extend(0_u32.to_le_bytes());
+            directory.extend(entry.offset.to_le_bytes());
+            directory.extend(entry.name.as_bytes());
+        }
+
+        let count = self::to_u16(self.entries.len())?;
+
+        let mut end = Vec::new();
+        end.extend(END_OF_CENTRAL_DIRECTORY.to_le_bytes());
+        // The number of this disk and of the disk where the central directory starts.
+        end.extend([0_u8; 4]);
+        end.extend(count.to_le_bytes());
+        end.extend(count.to_le_bytes());
+        end.extend(self::to_u32(directory.len())?.to_le_bytes());
+        end.extend(self::to_u32(self.offset)?.to_le_bytes());
+        // Comment length.
+        end.extend(0_u16.to_le_bytes());
+
+        self.writer.write_all(&directory)?;
+        self.writer.write_all(&end)?;
+        self.writer.flush()?;
+
+        Ok(self.writer)
+    }
+
+    /// Writes a file's local header followed by its, possibly compressed, contents.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The file's path within the archive.
+    /// * `contents` - The file's contents.
+    /// * `compressed` - The file's contents as written into the archive.
+    /// * `method` - The compression method used.
+    fn add_entry(
+        &mut self,
+        name: &str,
+        contents: &[u8],
+        compressed: &[u8],
+        method: u16,
+    ) -> Result<()> {
+        let mut crc = Crc::new();
+        crc.update(contents);
+
+        let entry = ZipEntry {
+            name: name.to_owned(),
+            method,
+            crc: crc.sum(),
+            compressed_size: self::to_u32(compressed.len())?,
+            size: self::to_u32(contents.len())?,
+            offset: self::to_u32(self.offset)?,
+        };
+
+        let mut header = Vec::new();
+        header.extend(LOCAL_FILE_HEADER.to_le_bytes());
+        header.extend(VERSION.to_le_bytes());
+        header.extend(FLAG_UTF8.to_le_bytes());
+        header.extend(entry.method.to_le_bytes());
+        header.extend(self.time.to_le_bytes());
+        header.extend(self.date.to_le_bytes());
+        header.extend(entry.crc.to_le_bytes());
+        header.extend(entry.compressed_size.to_le_bytes());
+        header.extend(entry.size.to_le_bytes());
+        header.extend(self::to_u16(entry.name.len())?.to_le_bytes());
+        header.extend(0_u16.to_le_bytes());
+        header.extend(entry.name.as_bytes());
+
+        self.writer.write_all(&header)?;
+        self.writer.write_all(compressed)?;
+
+        self.offset += header.len() + compressed.len();
+        self.entries.push(entry);
+
+        Ok(())
+    }
+}
+
+/// Writes a directory and its contents into a `zip` archive. Files are stored under the
+/// directory's name e.g. `[directory]/index.md` so the archive extracts into a single directory.
+///
+/// # Arguments
+///
+/// * `source` - The directory to archive.
+/// * `destination` - The path to the archive.
+///
+/// # Errors
+///
+/// Will return `Err` if:
+/// * Any IO errors are encountered.
+/// * The archive requires the `zip64` extensions.
+pub fn write_directory(source: &Path, destination: &Path) -> Result<()> {
+    let root = source
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    let mut files: Vec<_> = walkdir::WalkDir::new(source)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_map(std::result::Result::ok)
+        .filter(|entry| entry.file_type().is_file())
+        .map(walkdir::DirEntry::into_path)
+        .collect();
+
+    files.sort();
+
+    let mut writer = ZipWriter::new(BufWriter::new(File::create(destination)?));
+
+    for path in &files {
+        let relative = path.strip_prefix(source).unwrap_or(path);
+        let relative = relative.to_string_lossy().replace('\\', "/");
+        let name = if root.is_empty() {
+            relative
+        } else {
+            format!("{root}/{relative}")
+        };
+
+        writer.add(&name, &std::fs::read(path)?)?;
+    }
+
+    writer.finish()?;
+
+    tracing::debug!(
+        files = files.len(),
+        path = %destination.display(),
+        "wrote archive"
+    );
+
+    Ok(())
+}
+
+/// Returns the current local time and date in MS-DOS format, as used by `zip` archives.
+fn dos_datetime() -> (u16, u16) {
+    let now = Local::now();
+
+    // MS-DOS dates start in 1980 and times have a two second resolution.
+    let year = u16::try_from(now.year() - 1980).unwrap_or_default();
+    #[allow(clippy::cast_possible_truncation)]
+    let time = ((now.hour() << 11) | (now.minute() << 5) | (now.second() / 2)) as u16;
+    #[allow(clippy::cast_possible_truncation)]
+    let date = (year << 9) | ((now.month() << 5) | now.day()) as u16;
+
+    (time, date)
+}
+
+/// Converts a size or offset to a `u32`, failing if the archive would require `zip64`.
+fn to_u32(value: usize) -> Result<u32> {
+    u32::try_from(value).map_err(|_| self::zip64_error())
+}
+
+/// Converts a length or count to a `u16`, failing if the archive would require `zip64`.
+fn to_u16(value: usize) -> Result<u16> {
+    u16::try_from(value).map_err(|_| self::zip64_error())
+}
+
+fn zip64_error() -> Error {
+    Error::OtherError {
+        error: "zip archives larger than 4GB or with more than 65,535 files are unsupported"
+            .to_owned(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    use std::io::Read;
+
+    use flate2::read::DeflateDecoder;
+
+    use crate::defaults::TEMP_OUTPUT_DIRECTORY;
+
+    fn read_u16(bytes: &[u8], offset: usize) -> usize {
+        usize::from(u16::from_le_bytes([bytes[offset], bytes[offset + 1]]))
+    }
+
+    fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+        u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+    }
+
+    // Tests that files are stored under the directory's name and can be inflated back.
+    #[test]
+    fn write_and_read() {
+        let directory = TEMP_OUTPUT_DIRECTORY.join("tests-zip");
+        let source = directory.join("pack");
+
+        if directory.exists() {
+            std::fs::remove_dir_all(&directory).unwrap();
+        }
+
+        std::fs::create_dir_all(source.join("nested")).unwrap();
+        std::fs::write(source.join("index.md"), "# Review").unwrap();
+        std::fs::write(source.join("nested").join("book.md"), "The Art Spirit").unwrap();
+
+        let destination = directory.join("pack.zip");
+        write_directory(&source, &destination).unwrap();
+
+        let bytes = std::fs::read(&destination).unwrap();
+
+        // The end of central directory record is the last 22 bytes.
+        let end = bytes.len() - 22;
+        assert_eq!(read_u32(&bytes, end), END_OF_CENTRAL_DIRECTORY);
+        assert_eq!(read_u16(&bytes, end + 10), 2);
+
+        assert_eq!(read_u32(&bytes, 0), LOCAL_FILE_HEADER);
+
+        let compressed_size = usize::try_from(read_u32(&bytes, 18)).unwrap();
+        let name_length = read_u16(&bytes, 26);
+        let name = std::str::from_utf8(&bytes[30..30 + name_length]).unwrap();
+
+        assert_eq!(name, "pack/index.md");
+
+        let start = 30 + name_length;
+        let mut contents = String::new();
+        DeflateDecoder::new(&bytes[start..start + compressed_size])
+            .read_to_string(&mut contents)
+            .unwrap();
+
+        assert_eq!(contents, "# Review");
+    }
+}
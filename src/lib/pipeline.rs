@@ -0,0 +1,515 @@
+//! Defines the [`Pipeline`] struct for embedding `readstor` in other programs.
+//!
+//! A pipeline runs the same steps as the `readstor` CLI: it loads books and annotations from one
+//! or more [`Source`]s, filters, pre-processes and sorts them and finally renders or exports them.
+//!
+//! ```
+//! use lib::defaults::TEMP_OUTPUT_DIRECTORY;
+//! use lib::filter::{FilterOperator, FilterType};
+//! use lib::models::annotation::{Annotation, AnnotationMetadata};
+//! use lib::models::book::{Book, BookMetadata};
+//! use lib::models::entry::Entries;
+//! use lib::pipeline::{Pipeline, Source};
+//! use lib::render::renderer::{RenderOptions, Renderer};
+//!
+//! let books = vec![
+//!     Book {
+//!         title: "The Art Spirit".to_string(),
+//!         author: "Robert Henri".to_string(),
+//!         metadata: BookMetadata {
+//!             id: "1".to_string(),
+//!             ..Default::default()
+//!         },
+//!         ..Default::default()
+//!     },
+//!     Book {
+//!         title: "Think on These Things".to_string(),
+//!         author: "Krishnamurti".to_string(),
+//!         metadata: BookMetadata {
+//!             id: "2".to_string(),
+//!             ..Default::default()
+//!         },
+//!         ..Default::default()
+//!     },
+//! ];
+//!
+//! let annotations = ["1", "2"]
+//!     .map(|book_id| Annotation {
+//!         body: "...".to_string(),
+//!         metadata: AnnotationMetadata {
+//!             book_id: book_id.to_string(),
+//!             ..Default::default()
+//!         },
+//!         ..Default::default()
+//!     })
+//!     .to_vec();
+//!
+//! let template = "<!-- readstor\n\
+//!                 group: example\n\
+//!                 context: book\n\
+//!                 structure: flat\n\
+//!                 extension: txt\n\
+//!                 -->\n\
+//!                 {{ book.title }} by {{ book.author }}";
+//!
+//! let mut renderer = Renderer::new(RenderOptions::default(), template.to_string());
+//! renderer.init()?;
+//!
+//! let directory = TEMP_OUTPUT_DIRECTORY.join("docs-pipeline");
+//! std::fs::create_dir_all(&directory)?;
+//!
+//! let written = Pipeline::new()
+//!     .source(Source::Entries(Entries::from_parts(books, annotations)))
+//!     .filter(FilterType::Title {
+//!         query: vec!["art".to_string()],
+//!         operator: FilterOperator::Any,
+//!     })
+//!     .render(&mut renderer, &directory)?;
+//!
+//! assert_eq!(written.written() + written.skipped, 1);
+//! # Ok::<(), lib::result::Error>(())
+//! ```
+
+use std::path::{Path, PathBuf};
+
+use crate::applebooks::ios::ABIOs;
+use crate::applebooks::macos::ABMacOs;
+use crate::conflict::WriteSummary;
+use crate::export::ExportOptions;
+use crate::filter::{filters, FilterType};
+use crate::models::entry::{BookSortKey, Entries};
+use crate::process::post::PostProcessOptions;
+use crate::process::pre::PreProcessOptions;
+use crate::process::SortKey;
+use crate::render::renderer::Renderer;
+use crate::result::Result;
+use crate::snapshot::Snapshot;
+
+/// An enum representing where a [`Pipeline`] loads its books and annotations from.
+#[derive(Debug)]
+pub enum Source {
+    /// A directory containing macOS's Apple Books databases. See [`ABMacOs`] for more information
+    /// on how the directory should be structured.
+    MacOs(PathBuf),
+
+    /// A directory containing iOS's Apple Books plists. See [`ABIOs`] for more information on how
+    /// the directory should be structured.
+    IOs(PathBuf),
+
+    /// A snapshot file. See [`Snapshot`] for more information.
+    Snapshot(PathBuf),
+
+    /// Books and annotations that were already loaded e.g. built with [`Entries::from_parts()`].
+    Entries(Entries),
+}
+
+/// A builder for loading, filtering, processing and then rendering or exporting books and
+/// annotations.
+///
+/// Each step is optional and, regardless of the order the builder's methods are called in, they
+/// run in the following order:
+///
+/// 1. Load all [`Source`]s.
+/// 2. Discard books without annotations, unless [`Pipeline::include_unannotated()`] is set.
+/// 3. Run all filters. See [`crate::filter::run()`].
+/// 4. Keep only the first books, if [`Pipeline::limit()`] is set.
+/// 5. Run the pre-processes. See [`crate::process::pre::run()`].
+/// 6. Sort each book's annotations, if [`Pipeline::sort_annotations()`] is set.
+///
+/// The result is then returned by [`Pipeline::run()`], rendered by [`Pipeline::render()`] or
+/// exported by [`Pipeline::export()`].
+#[derive(Debug, Default)]
+pub struct Pipeline {
+    sources: Vec<Source>,
+    include_unannotated: bool,
+    filters: Vec<FilterType>,
+    limit: Option<usize>,
+    preprocess: Option<PreProcessOptions>,
+    postprocess: PostProcessOptions,
+    sort_books: BookSortKey,
+    sort_annotations: Option<SortKey>,
+}
+
+impl Pipeline {
+    /// Creates a new instance of [`Pipeline`] without any sources.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a source to load books and annotations from. The first source is loaded as-is and
+    /// every other source is merged into it. See [`Entries::merge()`] for how books and
+    /// annotations are matched.
+    ///
+    /// # Arguments
+    ///
+    /// * `source` - The source to load.
+    #[must_use]
+    pub fn source(mut self, source: Source) -> Self {
+        self.sources.push(source);
+        self
+    }
+
+    /// Toggles keeping books without annotations. They're discarded by default.
+    ///
+    /// # Arguments
+    ///
+    /// * `include` - Whether to keep books without annotations.
+    #[must_use]
+    pub fn include_unannotated(mut self, include: bool) -> Self {
+        self.include_unannotated = include;
+        self
+    }
+
+    /// Adds a filter. Filters run in the order they're added.
+    ///
+    /// # Arguments
+    ///
+    /// * `filter` - The filter to run.
+    #[must_use]
+    pub fn filter<F>(mut self, filter: F) -> Self
+    where
+        F: Into<FilterType>,
+    {
+        self.filters.push(filter.into());
+        self
+    }
+
+    /// Adds filters. See [`Pipeline::filter()`].
+    ///
+    /// # Arguments
+    ///
+    /// * `filters` - The filters to run.
+    #[must_use]
+    pub fn filters<I, F>(mut self, filters: I) -> Self
+    where
+        I: IntoIterator<Item = F>,
+        F: Into<FilterType>,
+    {
+        self.filters.extend(filters.into_iter().map(Into::into));
+        self
+    }
+
+    /// Keeps only the first books once they're sorted. See [`Pipeline::sort_books()`].
+    ///
+    /// # Arguments
+    ///
+    /// * `limit` - The maximum number of books to keep.
+    #[must_use]
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Sets the pre-processes to run. None are run by default.
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - The pre-process options.
+    #[must_use]
+    pub fn preprocess<O>(mut self, options: O) -> Self
+    where
+        O: Into<PreProcessOptions>,
+    {
+        self.preprocess = Some(options.into());
+        self
+    }
+
+    /// Sets the post-processes to run on each render. Only used by [`Pipeline::render()`].
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - The post-process options.
+    #[must_use]
+    pub fn postprocess<O>(mut self, options: O) -> Self
+    where
+        O: Into<PostProcessOptions>,
+    {
+        self.postprocess = options.into();
+        self
+    }
+
+    /// Sets the order in which books are limited, rendered and exported.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key to sort books by.
+    #[must_use]
+    pub fn sort_books(mut self, key: BookSortKey) -> Self {
+        self.sort_books = key;
+        self
+    }
+
+    /// Sets the order of each book's annotations. Annotations are sorted by their location by
+    /// default.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key to sort annotations by.
+    #[must_use]
+    pub fn sort_annotations(mut self, key: SortKey) -> Self {
+        self.sort_annotations = Some(key);
+        self
+    }
+
+    /// Loads, filters and processes the books and annotations and returns them.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if:
+    /// * A source cannot be loaded. See [`ABMacOs`], [`ABIOs`] and [`Snapshot::read()`].
+    /// * The annotations cannot be sorted.
+    pub fn run(mut self) -> Result<Entries> {
+        let mut entries = Entries::default();
+
+        for (index, source) in std::mem::take(&mut self.sources).into_iter().enumerate() {
+            let loaded = Self::load(source)?;
+
+            if index == 0 {
+                entries = loaded;
+            } else {
+                entries.merge(loaded);
+            }
+        }
+
+        if !self.include_unannotated {
+            filters::contains_no_annotations(&mut entries);
+        }
+
+        for filter in self.filters {
+            crate::filter::run(filter, &mut entries);
+        }
+
+        if let Some(limit) = self.limit {
+            entries.truncate(self.sort_books, limit);
+        }
+
+        if let Some(options) = self.preprocess {
+            crate::process::pre::run(&mut entries, options);
+        }
+
+        if let Some(key) = &self.sort_annotations {
+            for entry in entries.values_mut() {
+                crate::process::sort_annotations_by(entry, key)?;
+            }
+        }
+
+        Ok(entries)
+    }
+
+    /// Runs the pipeline, renders every book with the renderer and writes the results, after
+    /// post-processing them, to a directory.
+    ///
+    /// Books are rendered and written one at a time unless [`RenderOptions::single_file`][single]
+    /// is set. Tag templates are rendered once every book has been.
+    ///
+    /// Returns how many files were written or skipped.
+    ///
+    /// # Arguments
+    ///
+    /// * `renderer` - A renderer initialized with [`Renderer::init()`].
+    /// * `directory` - The directory to write into.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if:
+    /// * The pipeline fails. See [`Pipeline::run()`].
+    /// * The renderer encounters an error.
+    /// * Any IO errors are encountered.
+    ///
+    /// [single]: crate::render::renderer::RenderOptions::single_file
+    pub fn render(self, renderer: &mut Renderer, directory: &Path) -> Result<WriteSummary> {
+        let sort_books = self.sort_books;
+        let postprocess = self.postprocess;
+        let entries = self.run()?;
+
+        let mut written = WriteSummary::default();
+
+        for entry in entries.sorted(sort_books) {
+            renderer.render(entry)?;
+
+            // Single-file renders are only produced once all books are combined. Clearing here
+            // would discard the sections collected so far.
+            if renderer.count_templates_rendered() > 0 {
+                written.merge(Self::write(renderer, directory, postprocess)?);
+            }
+        }
+
+        renderer.render_tags(entries.sorted(sort_books))?;
+        renderer.combine();
+
+        written.merge(Self::write(renderer, directory, postprocess)?);
+
+        Ok(written)
+    }
+
+    /// Runs the pipeline and exports the books and annotations to a directory. The books are
+    /// exported in the order set by [`Pipeline::sort_books()`].
+    ///
+    /// # Arguments
+    ///
+    /// * `directory` - The directory to export into.
+    /// * `options` - The export options.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if:
+    /// * The pipeline fails. See [`Pipeline::run()`].
+    /// * The exporter encounters an error. See [`crate::export::run()`].
+    pub fn export<O>(self, directory: &Path, options: O) -> Result<()>
+    where
+        O: Into<ExportOptions>,
+    {
+        let options = ExportOptions {
+            sort_books: self.sort_books,
+            ..options.into()
+        };
+
+        let mut entries = self.run()?;
+
+        crate::export::run(&mut entries, directory, options)
+    }
+
+    /// Loads the books and annotations from a single source.
+    ///
+    /// # Arguments
+    ///
+    /// * `source` - The source to load.
+    fn load(source: Source) -> Result<Entries> {
+        let entries = match source {
+            Source::MacOs(path) => Entries::from_parts(
+                ABMacOs::extract_books(&path)?,
+                ABMacOs::extract_annotations(&path)?,
+            ),
+            Source::IOs(path) => Entries::from_parts(
+                ABIOs::extract_books(&path)?,
+                ABIOs::extract_annotations(&path)?,
+            ),
+            Source::Snapshot(path) => Snapshot::read(&path)?.into_entries(),
+            Source::Entries(entries) => entries,
+        };
+
+        Ok(entries)
+    }
+
+    /// Post-processes the renderer's renders, writes them to a directory and then clears them.
+    ///
+    /// # Arguments
+    ///
+    /// * `renderer` - The renderer holding the renders.
+    /// * `directory` - The directory to write into.
+    /// * `options` - The post-process options.
+    fn write(
+        renderer: &mut Renderer,
+        directory: &Path,
+        options: PostProcessOptions,
+    ) -> Result<WriteSummary> {
+        crate::process::post::run(renderer.templates_rendered_mut().collect(), options);
+
+        let written = renderer.write(directory)?;
+
+        renderer.clear_rendered();
+
+        Ok(written)
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    use crate::defaults::TEMP_OUTPUT_DIRECTORY;
+    use crate::filter::FilterOperator;
+    use crate::models::annotation::{Annotation, AnnotationMetadata};
+    use crate::models::book::{Book, BookMetadata};
+    use crate::models::entry::Entry;
+
+    fn entries(books: &[(&str, &str, usize)]) -> Entries {
+        books
+            .iter()
+            .map(|(id, title, annotations)| {
+                let entry = Entry {
+                    book: Book {
+                        title: (*title).to_string(),
+                        metadata: BookMetadata {
+                            id: (*id).to_string(),
+                            ..Default::default()
+                        },
+                        ..Default::default()
+                    },
+                    annotations: (0..*annotations)
+                        .map(|index| Annotation {
+                            body: format!("annotation {index}"),
+                            metadata: AnnotationMetadata {
+                                id: format!("{id}-{index}"),
+                                book_id: (*id).to_string(),
+                                ..Default::default()
+                            },
+                            ..Default::default()
+                        })
+                        .collect(),
+                };
+
+                ((*id).to_string(), entry)
+            })
+            .collect()
+    }
+
+    // Tests that unannotated books are discarded, filters run and later sources are merged into
+    // the first.
+    #[test]
+    fn run() {
+        let entries = Pipeline::new()
+            .source(Source::Entries(entries(&[
+                ("1", "The Art Spirit", 2),
+                ("2", "Think on These Things", 1),
+                ("3", "Unread", 0),
+            ])))
+            .source(Source::Entries(entries(&[("4", "The Art of War", 1)])))
+            .filter(FilterType::Title {
+                query: vec!["art".to_string()],
+                operator: FilterOperator::Any,
+            })
+            .run()
+            .unwrap();
+
+        let mut ids: Vec<&String> = entries.keys().collect();
+        ids.sort();
+
+        assert_eq!(ids, ["1", "4"]);
+    }
+
+    // Tests that each book is rendered into its own file.
+    #[test]
+    fn render() {
+        let directory = TEMP_OUTPUT_DIRECTORY.join("tests-pipeline");
+
+        if directory.exists() {
+            std::fs::remove_dir_all(&directory).unwrap();
+        }
+
+        std::fs::create_dir_all(&directory).unwrap();
+
+        let template = "<!-- readstor\ngroup: test\ncontext: book\nstructure: flat\n\
+                        extension: txt\n-->\n{{ book.title }}";
+
+        let mut renderer = Renderer::new(
+            crate::render::renderer::RenderOptions::default(),
+            template.to_string(),
+        );
+        renderer.init().unwrap();
+
+        let written = Pipeline::new()
+            .source(Source::Entries(entries(&[
+                ("1", "The Art Spirit", 2),
+                ("2", "Think on These Things", 1),
+            ])))
+            .limit(1)
+            .render(&mut renderer, &directory)
+            .unwrap();
+
+        assert_eq!(written.created, 1);
+        assert_eq!(std::fs::read_dir(&directory).unwrap().count(), 1);
+    }
+}
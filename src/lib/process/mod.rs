@@ -1,4 +1,202 @@
 //! Defines types for pre- and post-processing.
+//!
+//! This also defines the orders annotations can be sorted in. See [`sort_annotations_by()`].
 
 pub mod post;
 pub mod pre;
+
+use std::str::FromStr;
+
+use serde::Serialize;
+
+use crate::contexts::annotation::AnnotationContext;
+use crate::contexts::book::BookContext;
+use crate::models::entry::Entry;
+use crate::render::engine::RenderEngine;
+use crate::result::{Error, Result};
+
+/// An enum representing the keys [`Annotation`][annotation]s can be sorted by.
+///
+/// [annotation]: crate::models::annotation::Annotation
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SortKey {
+    /// Sort by the annotation's position within the book.
+    Location,
+
+    /// Sort by the date the annotation was created, oldest first.
+    Created,
+
+    /// Sort by the date the annotation was last modified, oldest first.
+    Modified,
+
+    /// Sort by the annotation's highlight style. See [`AnnotationStyle`][style].
+    ///
+    /// [style]: crate::models::annotation::AnnotationStyle
+    Style,
+
+    /// Sort alphabetically by a template string rendered for each annotation e.g.
+    /// `{{ annotation.tags | first }}`. The template has access to the `book` and `annotation`
+    /// contexts.
+    Custom(String),
+}
+
+impl FromStr for SortKey {
+    type Err = Error;
+
+    /// Parses a sort key from its name. Any string containing a template tag i.e. `{{` or `{%` is
+    /// parsed as a [`SortKey::Custom`] template.
+    fn from_str(key: &str) -> Result<Self> {
+        match key {
+            "location" => Ok(Self::Location),
+            "created" => Ok(Self::Created),
+            "modified" => Ok(Self::Modified),
+            "style" => Ok(Self::Style),
+            key if key.contains("{{") || key.contains("{%") => Ok(Self::Custom(key.to_owned())),
+            _ => Err(Error::SortInvalidKey {
+                key: key.to_owned(),
+            }),
+        }
+    }
+}
+
+/// Sorts an [`Entry`]'s annotations by a [`SortKey`].
+///
+/// The sort is stable so annotations with equal keys keep their current order. Pre-processing
+/// sorts annotations by their location so ties are usually in the order they appear in the book.
+///
+/// # Arguments
+///
+/// * `entry` - The [`Entry`] to sort.
+/// * `key` - The [`SortKey`] to sort by.
+///
+/// # Errors
+///
+/// Will return `Err` if a [`SortKey::Custom`] template fails to render.
+pub fn sort_annotations_by(entry: &mut Entry, key: &SortKey) -> Result<()> {
+    let annotations = &mut entry.annotations;
+
+    match key {
+        SortKey::Location => annotations.sort_by(|a, b| {
+            a.metadata
+                .location_sortkey
+                .cmp(&b.metadata.location_sortkey)
+        }),
+        SortKey::Created => annotations.sort_by_key(|annotation| *annotation.metadata.created),
+        SortKey::Modified => annotations.sort_by_key(|annotation| *annotation.metadata.modified),
+        SortKey::Style => annotations.sort_by_key(|annotation| annotation.style),
+        SortKey::Custom(template) => {
+            let mut engine = RenderEngine::default();
+            let book = BookContext::from(&entry.book);
+
+            let keys = annotations
+                .iter()
+                .map(|annotation| {
+                    let context = SortContext {
+                        book: &book,
+                        annotation: AnnotationContext::from(annotation),
+                    };
+                    engine.render_str(template, context)
+                })
+                .collect::<Result<Vec<String>>>()?;
+
+            let mut keyed: Vec<_> = keys.into_iter().zip(annotations.drain(..)).collect();
+            keyed.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+            annotations.extend(keyed.into_iter().map(|(_, annotation)| annotation));
+        }
+    }
+
+    Ok(())
+}
+
+/// A struct representing the template context for [`SortKey::Custom`] templates.
+#[derive(Debug, Serialize)]
+struct SortContext<'a> {
+    book: &'a BookContext<'a>,
+    annotation: AnnotationContext<'a>,
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    use crate::models::annotation::{Annotation, AnnotationStyle};
+    use crate::models::datetime::DateTimeUtc;
+
+    fn entry() -> Entry {
+        let annotations = [
+            ("a", AnnotationStyle::Purple, 3.0, "#two"),
+            ("b", AnnotationStyle::Underline, 1.0, "#three"),
+            ("c", AnnotationStyle::Green, 2.0, "#one"),
+        ]
+        .into_iter()
+        .map(|(body, style, created, tag)| {
+            let mut annotation = Annotation {
+                body: body.to_string(),
+                style,
+                ..Default::default()
+            };
+            annotation.metadata.created = DateTimeUtc::from(created);
+            annotation.tags.insert(tag.to_string());
+            annotation
+        })
+        .collect();
+
+        Entry {
+            annotations,
+            ..Default::default()
+        }
+    }
+
+    fn bodies(entry: &Entry) -> Vec<&str> {
+        entry
+            .annotations
+            .iter()
+            .map(|annotation| annotation.body.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn from_str() {
+        assert_eq!("created".parse::<SortKey>().unwrap(), SortKey::Created);
+        assert_eq!(
+            "{{ annotation.body }}".parse::<SortKey>().unwrap(),
+            SortKey::Custom("{{ annotation.body }}".to_string())
+        );
+        assert!("color".parse::<SortKey>().is_err());
+    }
+
+    #[test]
+    fn sort_by_created() {
+        let mut entry = entry();
+        sort_annotations_by(&mut entry, &SortKey::Created).unwrap();
+
+        assert_eq!(bodies(&entry), ["b", "c", "a"]);
+    }
+
+    #[test]
+    fn sort_by_style() {
+        let mut entry = entry();
+        sort_annotations_by(&mut entry, &SortKey::Style).unwrap();
+
+        assert_eq!(bodies(&entry), ["b", "c", "a"]);
+    }
+
+    #[test]
+    fn sort_by_custom() {
+        let mut entry = entry();
+        let key = SortKey::Custom("{{ annotation.tags | first }}".to_string());
+        sort_annotations_by(&mut entry, &key).unwrap();
+
+        assert_eq!(bodies(&entry), ["c", "b", "a"]);
+    }
+
+    #[test]
+    fn sort_by_invalid_custom() {
+        let mut entry = entry();
+        let key = SortKey::Custom("{{ annotation.invalid }}".to_string());
+
+        assert!(sort_annotations_by(&mut entry, &key).is_err());
+    }
+}
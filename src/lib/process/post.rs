@@ -2,6 +2,8 @@
 //!
 //! Post-processors are used mutate fields within a [`Render`].
 
+use std::borrow::Cow;
+
 use crate::render::template::Render;
 use crate::strings;
 
@@ -22,7 +24,12 @@ where
             self::trim_blocks(render);
         }
 
-        if let Some(width) = options.wrap_text {
+        // A template's own wrapping takes precedence over the default.
+        let width = render
+            .wrap_text
+            .map_or(options.wrap_text, |wrap| wrap.width(options.wrap_text));
+
+        if let Some(width) = width {
             self::wrap_text(render, width);
         }
     }
@@ -41,7 +48,7 @@ fn trim_blocks(render: &mut Render) {
 ///
 /// Maximum line length is not guaranteed as long words are not broken if their length exceeds
 /// the maximum. Hyphenation is not used, however, an existing hyphen can be split on to insert
-/// a line-break.
+/// a line-break. Fenced code blocks are left as is if [`Render::preserve_code_blocks`] is set.
 ///
 /// # Arguments
 ///
@@ -49,7 +56,34 @@ fn trim_blocks(render: &mut Render) {
 /// * `width` - The maximum character width.
 fn wrap_text(render: &mut Render, width: usize) {
     let options = textwrap::Options::new(width).break_words(false);
-    render.contents = textwrap::fill(&render.contents, options);
+
+    if !render.preserve_code_blocks {
+        render.contents = textwrap::fill(&render.contents, options);
+        return;
+    }
+
+    let mut is_code_block = false;
+
+    render.contents = render
+        .contents
+        .split('\n')
+        .map(|line| {
+            let trimmed = line.trim_start();
+
+            // The fences themselves are kept as is along with everything between them.
+            if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+                is_code_block = !is_code_block;
+                return Cow::Borrowed(line);
+            }
+
+            if is_code_block {
+                Cow::Borrowed(line)
+            } else {
+                textwrap::fill(line, &options).into()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
 }
 
 /// A struct representing options for running post-processes.
@@ -58,6 +92,84 @@ pub struct PostProcessOptions {
     /// Toggles trimming blocks left after rendering.
     pub trim_blocks: bool,
 
-    /// Toggles wrapping text to a maximum character width.
+    /// Toggles wrapping text to a maximum character width. Templates can override this with
+    /// `wrap-text`. See [`WrapText`][wrap-text] for more information.
+    ///
+    /// [wrap-text]: crate::render::template::WrapText
     pub wrap_text: Option<usize>,
 }
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    use crate::render::defaults::DEFAULT_WRAP_WIDTH;
+    use crate::render::template::WrapText;
+
+    fn render(wrap_text: Option<WrapText>, preserve_code_blocks: bool) -> Render {
+        Render {
+            contents: "Lorem ipsum dolor\n```\nsit amet consectetur\n```\n".to_string(),
+            wrap_text,
+            preserve_code_blocks,
+            ..Default::default()
+        }
+    }
+
+    fn wrap(mut render: Render, wrap_text: Option<usize>) -> String {
+        super::run(
+            vec![&mut render],
+            PostProcessOptions {
+                trim_blocks: false,
+                wrap_text,
+            },
+        );
+
+        render.contents
+    }
+
+    // Tests that a template's wrapping takes precedence over the default.
+    #[test]
+    fn wrap_text_template() {
+        assert_eq!(
+            wrap(render(None, false), Some(12)),
+            "Lorem ipsum\ndolor\n```\nsit amet\nconsectetur\n```\n"
+        );
+        assert_eq!(
+            wrap(render(Some(WrapText::Enabled(false)), false), Some(12)),
+            "Lorem ipsum dolor\n```\nsit amet consectetur\n```\n"
+        );
+        assert_eq!(
+            wrap(render(Some(WrapText::Enabled(true)), false), Some(12)),
+            "Lorem ipsum\ndolor\n```\nsit amet\nconsectetur\n```\n"
+        );
+        assert_eq!(
+            wrap(render(Some(WrapText::Width(5)), false), Some(100)),
+            "Lorem\nipsum\ndolor\n```\nsit\namet\nconsectetur\n```\n"
+        );
+    }
+
+    // Tests that a template opting into wrapping without a default width is wrapped at the
+    // fallback width.
+    #[test]
+    fn wrap_text_template_without_default() {
+        let mut render = render(Some(WrapText::Enabled(true)), false);
+        render.contents = "word ".repeat(40);
+
+        let contents = wrap(render, None);
+
+        assert!(contents.lines().count() > 1);
+        assert!(contents
+            .lines()
+            .all(|line| line.chars().count() <= DEFAULT_WRAP_WIDTH));
+    }
+
+    // Tests that fenced code blocks are left as is when preserving code blocks.
+    #[test]
+    fn wrap_text_preserve_code_blocks() {
+        assert_eq!(
+            wrap(render(None, true), Some(12)),
+            "Lorem ipsum\ndolor\n```\nsit amet consectetur\n```\n"
+        );
+    }
+}
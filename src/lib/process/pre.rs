@@ -2,40 +2,111 @@
 //!
 //! Pre-processors are used to mutate fields within an [`Entry`].
 
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use serde::Deserialize;
+
+use crate::analysis;
+use crate::models::annotation::Annotation;
 use crate::models::entry::{Entries, Entry};
-use crate::strings;
+use crate::models::epubcfi;
+use crate::models::id;
+use crate::result::{Error, Result};
+use crate::strings::{self, TagSyntax};
 
 /// Runs pre-processes on [`Entries`].
 ///
+/// Returns stats on the changes made by the pre-processes.
+///
 /// # Arguments
 ///
 /// * `entry` - The [`Entry`]s to process.
 /// * `options` - The pre-process options.
-pub fn run<O>(entries: &mut Entries, options: O)
+pub fn run<O>(entries: &mut Entries, options: O) -> PreProcessStats
 where
     O: Into<PreProcessOptions>,
 {
     let options: PreProcessOptions = options.into();
 
+    let mut stats = PreProcessStats::default();
+
+    // Every mapping is reported, including those that didn't touch any annotations.
+    if let Some(tag_map) = &options.tag_map {
+        stats.tags_mapped = tag_map.keys().map(|tag| (tag.to_owned(), 0)).collect();
+    }
+
     for entry in entries.values_mut() {
         self::sort_annotations(entry);
 
+        // This runs before tag extraction so that `#tags` within property values are kept.
+        if options.extract_properties {
+            self::extract_properties(entry);
+        }
+
+        // This runs before tag extraction so that `#tags` within field values are kept.
+        if options.extract_fields {
+            self::extract_fields(entry);
+        }
+
+        // This runs before tag extraction so that favorite `#tags` are still within the notes.
+        if options.extract_favorites {
+            stats.favorites_marked += self::extract_favorites(entry, &options.tag_syntax);
+        }
+
         if options.extract_tags {
-            self::extract_tags(entry);
+            self::extract_tags(entry, &options.tag_syntax);
+        }
+
+        // This runs after tag extraction so that it maps the extracted tags.
+        if let Some(tag_map) = &options.tag_map {
+            self::map_tags(entry, tag_map, &mut stats.tags_mapped);
         }
 
         if options.normalize_whitespace {
             self::normalize_whitespace(entry);
         }
 
+        if options.typography.replace_nbsp {
+            self::convert_text(entry, options.text_fields, strings::replace_nbsp);
+        }
+
+        if options.typography.straighten_quotes {
+            self::convert_text(entry, options.text_fields, strings::straighten_quotes);
+        }
+
+        if options.typography.expand_ellipses {
+            self::convert_text(entry, options.text_fields, strings::expand_ellipses);
+        }
+
         if options.convert_all_to_ascii {
-            self::convert_all_to_ascii(entry);
+            self::convert_text(entry, options.text_fields, strings::convert_all_to_ascii);
         }
 
         if options.convert_symbols_to_ascii {
-            self::convert_symbols_to_ascii(entry);
+            self::convert_text(
+                entry,
+                options.text_fields,
+                strings::convert_symbols_to_ascii,
+            );
+        }
+
+        // These run last so that annotations are compared after their bodies have been processed.
+        if options.dedupe {
+            stats.duplicates_removed += self::dedupe(entry);
+        }
+
+        if options.merge_adjacent {
+            stats.adjacent_merged += self::merge_adjacent(entry);
         }
     }
+
+    // This runs across all entries so it can only start once every book has been processed.
+    if options.link_mentions {
+        stats.mentions_linked = self::link_mentions(entries);
+    }
+
+    stats
 }
 
 /// Sort annotations by [`AnnotationMetadata::location`][location].
@@ -56,74 +127,560 @@ pub fn sort_annotations(entry: &mut Entry) {
 /// # Arguments
 ///
 /// * `entry` - The [`Entry`] to process.
+/// * `syntax` - The syntax of the tags to extract.
+///
+/// [annotation-notes]: crate::models::annotation::Annotation::notes
+/// [annotation-tags]: crate::models::annotation::Annotation::tags
+fn extract_tags(entry: &mut Entry, syntax: &TagSyntax) {
+    for annotation in &mut entry.annotations {
+        annotation.tags = syntax.extract(&annotation.notes);
+        annotation.notes = syntax.remove(&annotation.notes);
+    }
+}
+
+/// Marks [`Annotation`]s whose notes or tags contain a favorite marker as favorites by setting
+/// [`Annotation::favorite`][annotation-favorite]. Lone `*` markers are removed from
+/// [`Annotation::notes`][annotation-notes]. See [`Annotation::is_favorite()`] for more
+/// information.
+///
+/// Returns the number of annotations marked as favorites.
+///
+/// # Arguments
+///
+/// * `entry` - The [`Entry`] to process.
+/// * `syntax` - The syntax of the tags within notes.
 ///
+/// [annotation-favorite]: crate::models::annotation::Annotation::favorite
 /// [annotation-notes]: crate::models::annotation::Annotation::notes
+fn extract_favorites(entry: &mut Entry, syntax: &TagSyntax) -> usize {
+    let mut marked = 0;
+
+    for annotation in &mut entry.annotations {
+        annotation.favorite = annotation.is_favorite()
+            || syntax
+                .extract(&annotation.notes)
+                .iter()
+                .any(|tag| strings::is_favorite_tag(tag));
+        annotation.notes = strings::remove_favorite_markers(&annotation.notes);
+
+        if annotation.favorite {
+            marked += 1;
+        }
+    }
+
+    marked
+}
+
+/// Renames [`Annotation::tags`][annotation-tags] as set by a [`TagMap`]. Several tags can be
+/// mapped to the same tag to merge them and a tag mapped to an empty string is removed.
+///
+/// # Arguments
+///
+/// * `entry` - The [`Entry`] to process.
+/// * `tag_map` - The tags to rename.
+/// * `counts` - The number of annotations each mapping has touched, keyed by the original tag.
+///
 /// [annotation-tags]: crate::models::annotation::Annotation::tags
-fn extract_tags(entry: &mut Entry) {
+fn map_tags(entry: &mut Entry, tag_map: &TagMap, counts: &mut BTreeMap<String, usize>) {
+    for annotation in &mut entry.annotations {
+        if !annotation.tags.iter().any(|tag| tag_map.get(tag).is_some()) {
+            continue;
+        }
+
+        annotation.tags = std::mem::take(&mut annotation.tags)
+            .into_iter()
+            .filter_map(|tag| match tag_map.get(&tag) {
+                Some(renamed) => {
+                    *counts.entry(tag).or_default() += 1;
+                    (!renamed.is_empty()).then(|| renamed.to_owned())
+                }
+                None => Some(tag),
+            })
+            .collect();
+    }
+}
+
+/// Extracts `key:: value` properties from [`Annotation::notes`][annotation-notes] and places them
+/// into [`Annotation::properties`][annotation-properties]. The properties are removed from
+/// [`Annotation::notes`][annotation-notes].
+///
+/// # Arguments
+///
+/// * `entry` - The [`Entry`] to process.
+///
+/// [annotation-notes]: crate::models::annotation::Annotation::notes
+/// [annotation-properties]: crate::models::annotation::Annotation::properties
+fn extract_properties(entry: &mut Entry) {
+    for annotation in &mut entry.annotations {
+        annotation.properties = strings::extract_properties(&annotation.notes);
+        annotation.notes = strings::remove_properties(&annotation.notes);
+    }
+}
+
+/// Extracts `key: value` fields from [`Annotation::notes`][annotation-notes] and places them into
+/// [`Annotation::fields`][annotation-fields]. The fields are removed from
+/// [`Annotation::notes`][annotation-notes].
+///
+/// # Arguments
+///
+/// * `entry` - The [`Entry`] to process.
+///
+/// [annotation-notes]: crate::models::annotation::Annotation::notes
+/// [annotation-fields]: crate::models::annotation::Annotation::fields
+fn extract_fields(entry: &mut Entry) {
     for annotation in &mut entry.annotations {
-        annotation.tags = strings::extract_tags(&annotation.notes);
-        annotation.notes = strings::remove_tags(&annotation.notes);
+        annotation.fields = strings::extract_fields(&annotation.notes);
+        annotation.notes = strings::remove_fields(&annotation.notes);
     }
 }
 
-/// Normalizes whitespace in [`Annotation::body`][body].
+/// Normalizes whitespace in [`Annotation::body`][body] and
+/// [`Annotation::context_text`][context-text].
 ///
 /// # Arguments
 ///
 /// * `entry` - The [`Entry`] to process.
 ///
 /// [body]: crate::models::annotation::Annotation::body
+/// [context-text]: crate::models::annotation::Annotation::context_text
 fn normalize_whitespace(entry: &mut Entry) {
     for annotation in &mut entry.annotations {
         annotation.body = strings::normalize_whitespace(&annotation.body);
+        annotation.context_text = strings::normalize_whitespace(&annotation.context_text);
+    }
+}
+
+/// Runs a text conversion on the fields of an [`Entry`] selected by [`TextFields`].
+///
+/// # Arguments
+///
+/// * `entry` - The [`Entry`] to process.
+/// * `fields` - The fields to convert.
+/// * `convert` - The conversion to run.
+fn convert_text<F>(entry: &mut Entry, fields: TextFields, convert: F)
+where
+    F: Fn(&str) -> String,
+{
+    if fields.title {
+        entry.book.title = convert(&entry.book.title);
+    }
+
+    if fields.author {
+        entry.book.author = convert(&entry.book.author);
+    }
+
+    for annotation in &mut entry.annotations {
+        if fields.body {
+            annotation.body = convert(&annotation.body);
+            annotation.context_text = convert(&annotation.context_text);
+        }
+
+        if fields.notes {
+            annotation.notes = convert(&annotation.notes);
+        }
     }
 }
 
-/// Converts all Unicode characters found in [`Annotation::body`][body], [`Book::title`][title]
-/// and [`Book::author`][author] to their ASCII equivalents.
+/// Removes duplicate [`Annotation`]s, keeping only the one with the longest body. The notes and
+/// tags of a removed annotation are merged into the one that is kept.
+///
+/// Two annotations are considered duplicates if their [`AnnotationMetadata::epubcfi`][epubcfi]
+/// ranges either overlap, or are adjacent and one's [`Annotation::body`][body] contains the
+/// other's. This typically happens when a passage is highlighted more than once e.g. once on a
+/// phone and again on a computer. Annotations without a range are never considered duplicates.
+///
+/// Returns the number of annotations removed.
 ///
 /// # Arguments
 ///
 /// * `entry` - The [`Entry`] to process.
 ///
-/// [author]: crate::models::book::Book::author
 /// [body]: crate::models::annotation::Annotation::body
-/// [title]: crate::models::book::Book::title
-fn convert_all_to_ascii(entry: &mut Entry) {
-    entry.book.title = strings::convert_all_to_ascii(&entry.book.title);
-    entry.book.author = strings::convert_all_to_ascii(&entry.book.author);
+/// [epubcfi]: crate::models::annotation::AnnotationMetadata::epubcfi
+fn dedupe(entry: &mut Entry) -> usize {
+    let ranges: Vec<_> = entry
+        .annotations
+        .iter()
+        .map(|annotation| epubcfi::parse_range(&annotation.metadata.epubcfi))
+        .collect();
 
-    for annotation in &mut entry.annotations {
-        annotation.body = strings::convert_all_to_ascii(&annotation.body);
+    // Annotations are visited from longest to shortest so that the longest of any set of
+    // duplicates is always the one kept. The sort is stable so ties keep their original order.
+    let mut indices: Vec<usize> = (0..entry.annotations.len()).collect();
+    indices.sort_by_key(|&index| std::cmp::Reverse(entry.annotations[index].body.chars().count()));
+
+    let mut kept: Vec<usize> = Vec::with_capacity(indices.len());
+    let mut duplicates: Vec<(usize, usize)> = Vec::new();
+
+    for index in indices {
+        let duplicate_of = kept.iter().copied().find(|&other| {
+            let (Some((start, end)), Some((other_start, other_end))) =
+                (&ranges[index], &ranges[other])
+            else {
+                return false;
+            };
+
+            let is_overlapping = start < other_end && other_start < end;
+            let is_adjacent = start == other_end || other_start == end;
+
+            is_overlapping
+                || (is_adjacent
+                    && self::is_substring(&entry.annotations[index], &entry.annotations[other]))
+        });
+
+        match duplicate_of {
+            Some(other) => duplicates.push((index, other)),
+            None => kept.push(index),
+        }
+    }
+
+    let removed = duplicates.len();
+
+    for (index, other) in duplicates {
+        let notes = std::mem::take(&mut entry.annotations[index].notes);
+        let tags = std::mem::take(&mut entry.annotations[index].tags);
+
+        let annotation = &mut entry.annotations[other];
+
+        // Duplicates created on different devices often share the same notes.
+        if !annotation.notes.contains(notes.trim()) {
+            annotation.notes = self::join_paragraphs(&annotation.notes, &notes);
+        }
+
+        annotation.tags.extend(tags);
     }
+
+    kept.sort_unstable();
+
+    let mut kept = kept.into_iter().peekable();
+    let mut index = 0;
+
+    entry.annotations.retain(|_| {
+        let is_kept = kept.next_if_eq(&index).is_some();
+        index += 1;
+        is_kept
+    });
+
+    removed
+}
+
+/// Returns `true` if an [`Annotation`]'s body is contained within another's.
+///
+/// Bodies are trimmed before being compared and empty bodies never match.
+///
+/// # Arguments
+///
+/// * `annotation` - The shorter [`Annotation`].
+/// * `other` - The longer [`Annotation`].
+fn is_substring(annotation: &Annotation, other: &Annotation) -> bool {
+    let body = annotation.body.trim();
+
+    !body.is_empty() && other.body.contains(body)
 }
 
-/// Converts a subset of "smart" Unicode symbols found in [`Annotation::body`][body],
-/// [`Book::title`][title] and [`Book::author`][author] to their ASCII equivalents.
+/// Merges consecutive [`Annotation`]s that continue one another into a single annotation.
+///
+/// Two annotations are merged if either the first's [`AnnotationMetadata::epubcfi`][epubcfi] range
+/// ends where the second's begins or the first's [`Annotation::body`][body] doesn't end a sentence
+/// and the second's starts with a lowercase letter. Merged annotations keep the first's metadata,
+/// have their bodies and notes concatenated and their tags unioned.
+///
+/// This expects the annotations to already be sorted. See [`sort_annotations()`].
+///
+/// Returns the number of annotations merged into another.
 ///
 /// # Arguments
 ///
 /// * `entry` - The [`Entry`] to process.
 ///
-/// [author]: crate::models::book::Book::author
 /// [body]: crate::models::annotation::Annotation::body
-/// [title]: crate::models::book::Book::title
-fn convert_symbols_to_ascii(entry: &mut Entry) {
-    entry.book.title = strings::convert_symbols_to_ascii(&entry.book.title);
-    entry.book.author = strings::convert_symbols_to_ascii(&entry.book.author);
+/// [epubcfi]: crate::models::annotation::AnnotationMetadata::epubcfi
+fn merge_adjacent(entry: &mut Entry) -> usize {
+    let mut annotations: Vec<Annotation> = Vec::with_capacity(entry.annotations.len());
+    let mut merged = 0;
 
-    for annotation in &mut entry.annotations {
-        annotation.body = strings::convert_symbols_to_ascii(&annotation.body);
+    // The end of the previous annotation's range. This is tracked separately as the metadata of
+    // merged annotations isn't updated.
+    let mut previous_end = None;
+
+    for annotation in entry.annotations.drain(..) {
+        let range = epubcfi::parse_range(&annotation.metadata.epubcfi);
+
+        if let Some(previous) = annotations.last_mut() {
+            let is_contiguous = match (&previous_end, &range) {
+                (Some(end), Some((start, _))) => end == start,
+                _ => false,
+            };
+
+            if is_contiguous || self::is_continuation(&previous.body, &annotation.body) {
+                self::merge_annotation(previous, annotation);
+                previous_end = range.map(|(_, end)| end);
+                merged += 1;
+                continue;
+            }
+        }
+
+        previous_end = range.map(|(_, end)| end);
+        annotations.push(annotation);
+    }
+
+    entry.annotations = annotations;
+
+    merged
+}
+
+/// Returns `true` if a body doesn't end a sentence and the next body starts with a lowercase
+/// letter.
+///
+/// # Arguments
+///
+/// * `body` - The first body.
+/// * `next` - The body that might continue the first.
+fn is_continuation(body: &str, next: &str) -> bool {
+    // Closing quotes and brackets are ignored e.g. `...the end."` ends a sentence.
+    let body = body
+        .trim_end()
+        .trim_end_matches(['"', '\'', '”', '’', ')', ']']);
+
+    let ends_sentence = matches!(body.chars().last(), None | Some('.' | '!' | '?' | '…'));
+
+    let starts_lowercase = next
+        .trim_start()
+        .chars()
+        .next()
+        .is_some_and(char::is_lowercase);
+
+    !ends_sentence && starts_lowercase
+}
+
+/// Merges an [`Annotation`] into another, keeping the first's metadata.
+///
+/// # Arguments
+///
+/// * `annotation` - The [`Annotation`] to merge into.
+/// * `other` - The [`Annotation`] to merge.
+fn merge_annotation(annotation: &mut Annotation, other: Annotation) {
+    annotation.body = format!("{} {}", annotation.body.trim_end(), other.body.trim_start());
+
+    annotation.notes = self::join_paragraphs(&annotation.notes, &other.notes);
+
+    // Adjacent annotations often share a paragraph so identical context is only kept once.
+    if other.context_text.trim() != annotation.context_text.trim() {
+        annotation.context_text =
+            self::join_paragraphs(&annotation.context_text, &other.context_text);
+    }
+
+    annotation.tags.extend(other.tags);
+    annotation.favorite |= other.favorite;
+
+    if *other.metadata.modified > *annotation.metadata.modified {
+        annotation.metadata.modified = other.metadata.modified;
+    }
+}
+
+/// Joins two trimmed strings with two consecutive line-breaks: `\n\n`. Empty strings are skipped.
+///
+/// # Arguments
+///
+/// * `first` - The first string.
+/// * `second` - The string to append.
+fn join_paragraphs(first: &str, second: &str) -> String {
+    [first.trim(), second.trim()]
+        .into_iter()
+        .filter(|text| !text.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+/// Links [`Annotation`]s to the other books whose titles they mention. The asset ids of mentioned
+/// books are placed into [`Annotation::mentions`][mentions].
+///
+/// Titles are matched case-insensitively against whole words in the annotation's body and notes.
+/// Subtitles i.e. anything after a `:` are ignored e.g. `Meditations: A New Translation` is matched
+/// by `Meditations`. Titles that are a single short word or a stop-word are never matched as they
+/// would be mentioned by almost every annotation.
+///
+/// Returns the number of annotations that mention at least one other book.
+///
+/// # Arguments
+///
+/// * `entries` - The [`Entries`] to process.
+///
+/// [mentions]: crate::models::annotation::Annotation::mentions
+fn link_mentions(entries: &mut Entries) -> usize {
+    let titles: Vec<(String, String)> = entries
+        .values()
+        .filter_map(|entry| {
+            let title = entry.book.title.split(':').next().unwrap_or_default();
+            let title = self::normalize_words(title);
+
+            let is_significant = title.contains(' ') || analysis::is_significant(&title);
+
+            is_significant.then(|| {
+                let asset_id =
+                    id::or_existing(&entry.book.metadata.asset_id, &entry.book.metadata.id);
+                // Padding the title with spaces ensures it only matches whole words.
+                (format!(" {title} "), asset_id)
+            })
+        })
+        .collect();
+
+    let mut linked = 0;
+
+    for entry in entries.values_mut() {
+        let own_asset_id = id::or_existing(&entry.book.metadata.asset_id, &entry.book.metadata.id);
+
+        for annotation in &mut entry.annotations {
+            let text = format!(
+                " {} {} ",
+                self::normalize_words(&annotation.body),
+                self::normalize_words(&annotation.notes)
+            );
+
+            let mut mentions: Vec<String> = titles
+                .iter()
+                .filter(|(title, asset_id)| {
+                    *asset_id != own_asset_id && text.contains(title.as_str())
+                })
+                .map(|(_, asset_id)| asset_id.clone())
+                .collect();
+
+            // Entries are unordered so mentions are sorted to keep the output stable.
+            mentions.sort();
+            mentions.dedup();
+
+            if !mentions.is_empty() {
+                linked += 1;
+            }
+
+            annotation.mentions = mentions;
+        }
+    }
+
+    linked
+}
+
+/// Lowercases a string and joins its words with single spaces, dropping all punctuation. See
+/// [`analysis::words()`].
+///
+/// # Arguments
+///
+/// * `string` - The string to normalize.
+fn normalize_words(string: &str) -> String {
+    analysis::words(string).collect::<Vec<_>>().join(" ")
+}
+
+/// A struct representing stats on the changes made by the pre-processes.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PreProcessStats {
+    /// The number of duplicate annotations removed.
+    pub duplicates_removed: usize,
+
+    /// The number of adjacent annotations merged into another.
+    pub adjacent_merged: usize,
+
+    /// The number of annotations that mention at least one other book.
+    pub mentions_linked: usize,
+
+    /// The number of annotations marked as favorites.
+    pub favorites_marked: usize,
+
+    /// The number of annotations each of the [`TagMap`]'s mappings touched, keyed by the original
+    /// tag.
+    pub tags_mapped: BTreeMap<String, usize>,
+}
+
+/// A struct representing a mapping of tags to rename during pre-processing, read from a TOML or
+/// YAML file of `old = new` pairs e.g. `"#todo" = "#task"`.
+///
+/// Tags are matched exactly as they're stored in [`Annotation::tags`][annotation-tags] so they're
+/// compared after any normalization set by [`TagSyntax`]. Mappings aren't chained: each tag is
+/// renamed once at most.
+///
+/// [annotation-tags]: crate::models::annotation::Annotation::tags
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize)]
+#[serde(transparent)]
+pub struct TagMap(BTreeMap<String, String>);
+
+impl TagMap {
+    /// Reads a tag map from a TOML file or, if its extension is `.yaml` or `.yml`, a YAML file.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path to the tag map.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the file cannot be read or isn't a flat map of strings.
+    pub fn from_path(path: &Path) -> Result<Self> {
+        let invalid = |error: String| Error::InvalidTagMap {
+            path: path.display().to_string(),
+            error,
+        };
+
+        let contents = std::fs::read_to_string(path).map_err(|error| invalid(error.to_string()))?;
+
+        let is_yaml = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .is_some_and(|extension| matches!(extension, "yaml" | "yml"));
+
+        if is_yaml {
+            serde_yaml_ng::from_str(&contents).map_err(|error| invalid(error.to_string()))
+        } else {
+            toml::from_str(&contents).map_err(|error| invalid(error.to_string()))
+        }
+    }
+
+    /// Returns the tag a tag is renamed to, if it's mapped.
+    ///
+    /// # Arguments
+    ///
+    /// * `tag` - The tag to look up.
+    #[must_use]
+    pub fn get(&self, tag: &str) -> Option<&str> {
+        self.0.get(tag).map(String::as_str)
+    }
+
+    /// Returns an iterator over the mapped tags.
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.0.keys().map(String::as_str)
+    }
+}
+
+impl<const N: usize> From<[(&str, &str); N]> for TagMap {
+    fn from(mappings: [(&str, &str); N]) -> Self {
+        Self(
+            mappings
+                .into_iter()
+                .map(|(old, new)| (old.to_owned(), new.to_owned()))
+                .collect(),
+        )
     }
 }
 
 /// A struct representing options for running pre-processes.
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone)]
 #[allow(clippy::struct_excessive_bools)]
 pub struct PreProcessOptions {
     /// Toggles running `#tag` extraction from notes.
     pub extract_tags: bool,
 
+    /// The syntax of tags within notes and how they're normalized once extracted.
+    pub tag_syntax: TagSyntax,
+
+    /// The tags to rename, if any.
+    pub tag_map: Option<TagMap>,
+
+    /// Toggles running `key:: value` property extraction from notes.
+    pub extract_properties: bool,
+
+    /// Toggles running `key: value` field extraction from notes.
+    pub extract_fields: bool,
+
+    /// Toggles marking annotations with a favorite marker in their notes as favorites.
+    pub extract_favorites: bool,
+
     /// Toggles running whitespace normalization.
     pub normalize_whitespace: bool,
 
@@ -132,6 +689,74 @@ pub struct PreProcessOptions {
 
     /// Toggles converting "smart" Unicode symbols to ASCII.
     pub convert_symbols_to_ascii: bool,
+
+    /// The typographic conversions to run.
+    pub typography: TypographyOptions,
+
+    /// The fields the typographic and ASCII conversions are run on.
+    pub text_fields: TextFields,
+
+    /// Toggles removing duplicate annotations.
+    pub dedupe: bool,
+
+    /// Toggles merging adjacent annotations.
+    pub merge_adjacent: bool,
+
+    /// Toggles linking annotations to the other books they mention.
+    pub link_mentions: bool,
+}
+
+/// A struct representing the typographic conversions to run. These are finer-grained alternatives
+/// to converting all "smart" Unicode symbols to ASCII.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TypographyOptions {
+    /// Toggles converting curly quotes to straight quotes.
+    pub straighten_quotes: bool,
+
+    /// Toggles expanding ellipsis characters `…` into three periods `...`.
+    pub expand_ellipses: bool,
+
+    /// Toggles replacing non-breaking spaces with regular spaces.
+    pub replace_nbsp: bool,
+}
+
+/// A struct representing the fields text conversions are run on. By default, these are the
+/// book's title and author and each annotation's body.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(clippy::struct_excessive_bools)]
+pub struct TextFields {
+    /// Toggles converting [`Book::title`][book-title].
+    ///
+    /// [book-title]: crate::models::book::Book::title
+    pub title: bool,
+
+    /// Toggles converting [`Book::author`][book-author].
+    ///
+    /// [book-author]: crate::models::book::Book::author
+    pub author: bool,
+
+    /// Toggles converting [`Annotation::body`][annotation-body] and
+    /// [`Annotation::context_text`][annotation-context-text].
+    ///
+    /// [annotation-body]: crate::models::annotation::Annotation::body
+    /// [annotation-context-text]: crate::models::annotation::Annotation::context_text
+    pub body: bool,
+
+    /// Toggles converting [`Annotation::notes`][annotation-notes].
+    ///
+    /// [annotation-notes]: crate::models::annotation::Annotation::notes
+    pub notes: bool,
+}
+
+impl Default for TextFields {
+    fn default() -> Self {
+        Self {
+            title: true,
+            author: true,
+            body: true,
+            notes: false,
+        }
+    }
 }
 
 #[cfg(test)]
@@ -168,12 +793,674 @@ mod test {
                 ],
             };
 
-            super::extract_tags(&mut entry);
+            super::extract_tags(&mut entry, &TagSyntax::default());
 
             for annotation in entry.annotations {
                 assert_eq!(annotation.tags.len(), 2);
                 assert!(annotation.notes.is_empty());
             }
         }
+
+        // Tests that tags are extracted with a custom syntax and normalized.
+        #[test]
+        fn extract_custom_syntax() {
+            let mut entry = Entry {
+                book: Book::default(),
+                annotations: vec![Annotation {
+                    notes: "A note. @Topic/Subtopic, #ignored @fav".to_string(),
+                    ..Default::default()
+                }],
+            };
+
+            let mut syntax = TagSyntax::new('@', Some("/"));
+            syntax.lowercase = true;
+            syntax.strip_prefix = true;
+
+            super::extract_favorites(&mut entry, &syntax);
+            super::extract_tags(&mut entry, &syntax);
+
+            let annotation = &entry.annotations[0];
+
+            assert_eq!(
+                annotation.tags.iter().collect::<Vec<_>>(),
+                ["fav", "topic/subtopic"]
+            );
+            assert_eq!(annotation.notes, "A note. , #ignored");
+            assert!(annotation.favorite);
+        }
+    }
+
+    mod properties {
+
+        use super::*;
+
+        use crate::models::book::Book;
+
+        // Tests that properties are extracted from `Annotation::notes` before tags so that tags
+        // within a property's value are kept.
+        #[test]
+        fn extract() {
+            let mut entries = Entries::default();
+            entries.insert(
+                String::new(),
+                Entry {
+                    book: Book::default(),
+                    annotations: vec![Annotation {
+                        notes: "A note. #tag01\nproject:: #work\nrating:: 5".to_string(),
+                        ..Default::default()
+                    }],
+                },
+            );
+
+            super::run(
+                &mut entries,
+                PreProcessOptions {
+                    extract_tags: true,
+                    tag_syntax: TagSyntax::default(),
+                    tag_map: None,
+                    extract_properties: true,
+                    extract_fields: false,
+                    extract_favorites: false,
+                    normalize_whitespace: false,
+                    convert_all_to_ascii: false,
+                    convert_symbols_to_ascii: false,
+                    typography: TypographyOptions::default(),
+                    text_fields: TextFields::default(),
+                    dedupe: false,
+                    merge_adjacent: false,
+                    link_mentions: false,
+                },
+            );
+
+            let annotation = &entries[""].annotations[0];
+
+            assert_eq!(annotation.properties["project"], "#work");
+            assert_eq!(annotation.properties["rating"], "5");
+            assert_eq!(annotation.tags.len(), 1);
+            assert_eq!(annotation.notes, "A note.");
+        }
+    }
+
+    mod fields {
+
+        use super::*;
+
+        use crate::models::book::Book;
+
+        // Tests that fields are extracted from `Annotation::notes` alongside properties and before
+        // tags so that tags within a field's value are kept.
+        #[test]
+        fn extract() {
+            let mut entries = Entries::default();
+            entries.insert(
+                String::new(),
+                Entry {
+                    book: Book::default(),
+                    annotations: vec![Annotation {
+                        notes: "A note. #tag01\nrating: 5\nq: Why #this?\npage:: 42".to_string(),
+                        ..Default::default()
+                    }],
+                },
+            );
+
+            super::run(
+                &mut entries,
+                PreProcessOptions {
+                    extract_tags: true,
+                    tag_syntax: TagSyntax::default(),
+                    tag_map: None,
+                    extract_properties: true,
+                    extract_fields: true,
+                    extract_favorites: false,
+                    normalize_whitespace: false,
+                    convert_all_to_ascii: false,
+                    convert_symbols_to_ascii: false,
+                    typography: TypographyOptions::default(),
+                    text_fields: TextFields::default(),
+                    dedupe: false,
+                    merge_adjacent: false,
+                    link_mentions: false,
+                },
+            );
+
+            let annotation = &entries[""].annotations[0];
+
+            assert_eq!(annotation.fields["rating"], 5);
+            assert_eq!(annotation.fields["q"], "Why #this?");
+            assert_eq!(annotation.properties["page"], "42");
+            assert_eq!(annotation.tags.len(), 1);
+            assert_eq!(annotation.notes, "A note.");
+        }
+    }
+
+    mod favorites {
+
+        use super::*;
+
+        use crate::models::book::Book;
+
+        // Tests that annotations with a favorite marker are marked as favorites before tags are
+        // extracted and that lone `*` markers are removed from `Annotation::notes`.
+        #[test]
+        fn extract() {
+            let notes = ["* A note.", "A note. #fav", "A note.", "A **bold** note."];
+
+            let mut entries = Entries::default();
+            entries.insert(
+                String::new(),
+                Entry {
+                    book: Book::default(),
+                    annotations: notes
+                        .iter()
+                        .map(|notes| Annotation {
+                            notes: (*notes).to_string(),
+                            ..Default::default()
+                        })
+                        .collect(),
+                },
+            );
+
+            let stats = super::run(
+                &mut entries,
+                PreProcessOptions {
+                    extract_tags: true,
+                    tag_syntax: TagSyntax::default(),
+                    tag_map: None,
+                    extract_properties: false,
+                    extract_fields: false,
+                    extract_favorites: true,
+                    normalize_whitespace: false,
+                    convert_all_to_ascii: false,
+                    convert_symbols_to_ascii: false,
+                    typography: TypographyOptions::default(),
+                    text_fields: TextFields::default(),
+                    dedupe: false,
+                    merge_adjacent: false,
+                    link_mentions: false,
+                },
+            );
+
+            let annotations = &entries[""].annotations;
+
+            assert_eq!(stats.favorites_marked, 2);
+            assert_eq!(
+                annotations
+                    .iter()
+                    .map(|annotation| annotation.favorite)
+                    .collect::<Vec<_>>(),
+                [true, true, false, false]
+            );
+            assert_eq!(annotations[0].notes, "A note.");
+            assert_eq!(annotations[1].notes, "A note.");
+            assert!(annotations[1].tags.contains("#fav"));
+        }
+    }
+
+    mod tag_map {
+
+        use super::*;
+
+        use crate::defaults::TEMP_OUTPUT_DIRECTORY;
+        use crate::models::book::Book;
+
+        // Tests that tags are renamed, merged and removed and that each mapping's annotations are
+        // counted.
+        #[test]
+        fn map() {
+            let notes = ["#todo #later", "#to-do #todo", "#junk", "#keep"];
+
+            let mut entries = Entries::default();
+            entries.insert(
+                String::new(),
+                Entry {
+                    book: Book::default(),
+                    annotations: notes
+                        .iter()
+                        .map(|notes| Annotation {
+                            notes: (*notes).to_string(),
+                            ..Default::default()
+                        })
+                        .collect(),
+                },
+            );
+
+            let stats = super::run(
+                &mut entries,
+                PreProcessOptions {
+                    extract_tags: true,
+                    tag_syntax: TagSyntax::default(),
+                    tag_map: Some(TagMap::from([
+                        ("#todo", "#task"),
+                        ("#to-do", "#task"),
+                        ("#junk", ""),
+                        ("#unused", "#task"),
+                    ])),
+                    extract_properties: false,
+                    extract_fields: false,
+                    extract_favorites: false,
+                    normalize_whitespace: false,
+                    convert_all_to_ascii: false,
+                    convert_symbols_to_ascii: false,
+                    typography: TypographyOptions::default(),
+                    text_fields: TextFields::default(),
+                    dedupe: false,
+                    merge_adjacent: false,
+                    link_mentions: false,
+                },
+            );
+
+            let tags: Vec<Vec<&str>> = entries[""]
+                .annotations
+                .iter()
+                .map(|annotation| annotation.tags.iter().map(String::as_str).collect())
+                .collect();
+
+            assert_eq!(
+                tags,
+                [
+                    vec!["#later", "#task"],
+                    vec!["#task"],
+                    vec![],
+                    vec!["#keep"]
+                ]
+            );
+            assert_eq!(
+                stats.tags_mapped,
+                BTreeMap::from([
+                    ("#junk".to_string(), 1),
+                    ("#to-do".to_string(), 1),
+                    ("#todo".to_string(), 2),
+                    ("#unused".to_string(), 0),
+                ])
+            );
+        }
+
+        // Tests that mappings aren't chained.
+        #[test]
+        fn not_chained() {
+            let mut entry = Entry::default();
+            entry.annotations.push(Annotation {
+                tags: ["#a".to_string()].into(),
+                ..Default::default()
+            });
+
+            let mut counts = BTreeMap::new();
+
+            map_tags(
+                &mut entry,
+                &TagMap::from([("#a", "#b"), ("#b", "#c")]),
+                &mut counts,
+            );
+
+            assert_eq!(entry.annotations[0].tags, ["#b".to_string()].into());
+        }
+
+        // Tests that tag maps are read from TOML and YAML files.
+        #[test]
+        fn from_path() {
+            let directory = TEMP_OUTPUT_DIRECTORY.join("tests-tag-map");
+            std::fs::create_dir_all(&directory).unwrap();
+
+            let expected = TagMap::from([("#todo", "#task"), ("#junk", "")]);
+
+            let toml = directory.join("tag-map.toml");
+            std::fs::write(&toml, "\"#todo\" = \"#task\"\n\"#junk\" = \"\"\n").unwrap();
+
+            let yaml = directory.join("tag-map.yaml");
+            std::fs::write(&yaml, "\"#todo\": \"#task\"\n\"#junk\": \"\"\n").unwrap();
+
+            let invalid = directory.join("invalid.toml");
+            std::fs::write(&invalid, "[todo]\ntask = 1\n").unwrap();
+
+            assert_eq!(TagMap::from_path(&toml).unwrap(), expected);
+            assert_eq!(TagMap::from_path(&yaml).unwrap(), expected);
+            assert!(matches!(
+                TagMap::from_path(&invalid),
+                Err(Error::InvalidTagMap { .. })
+            ));
+            assert!(matches!(
+                TagMap::from_path(&directory.join("missing.toml")),
+                Err(Error::InvalidTagMap { .. })
+            ));
+        }
+    }
+
+    mod text {
+
+        use super::*;
+
+        use crate::models::book::Book;
+
+        // Tests that text conversions are only run on the selected fields.
+        #[test]
+        fn fields() {
+            let mut entry = Entry {
+                book: Book {
+                    title: "“Title”".to_string(),
+                    author: "“Author”".to_string(),
+                    ..Default::default()
+                },
+                annotations: vec![Annotation {
+                    body: "“Body”".to_string(),
+                    context_text: "“Context”".to_string(),
+                    notes: "“Notes”".to_string(),
+                    ..Default::default()
+                }],
+            };
+
+            super::convert_text(
+                &mut entry,
+                TextFields {
+                    title: false,
+                    author: true,
+                    body: false,
+                    notes: true,
+                },
+                strings::straighten_quotes,
+            );
+
+            let annotation = &entry.annotations[0];
+
+            assert_eq!(entry.book.title, "“Title”");
+            assert_eq!(entry.book.author, "\"Author\"");
+            assert_eq!(annotation.body, "“Body”");
+            assert_eq!(annotation.context_text, "“Context”");
+            assert_eq!(annotation.notes, "\"Notes\"");
+        }
+    }
+
+    mod dedupe {
+
+        use super::*;
+
+        use std::collections::BTreeSet;
+
+        use crate::models::annotation::AnnotationMetadata;
+        use crate::models::book::Book;
+
+        fn annotation(body: &str, epubcfi: &str) -> Annotation {
+            Annotation {
+                body: body.to_string(),
+                metadata: AnnotationMetadata {
+                    epubcfi: epubcfi.to_string(),
+                    ..Default::default()
+                },
+                ..Default::default()
+            }
+        }
+
+        // Tests that annotations with overlapping ranges are removed, keeping the longest.
+        #[test]
+        fn overlapping_ranges() {
+            let mut entry = Entry {
+                book: Book::default(),
+                annotations: vec![
+                    annotation("first passage", "epubcfi(/6/4!/4/10/1,:0,:13)"),
+                    annotation("passage and more", "epubcfi(/6/4!/4/10/1,:6,:22)"),
+                    annotation("another passage", "epubcfi(/6/4!/4/12/1,:0,:15)"),
+                ],
+            };
+
+            let removed = super::dedupe(&mut entry);
+
+            let bodies: Vec<&str> = entry.annotations.iter().map(|a| a.body.as_str()).collect();
+
+            assert_eq!(removed, 1);
+            assert_eq!(bodies, ["passage and more", "another passage"]);
+        }
+
+        // Tests that adjacent annotations whose bodies are contained within another's are removed
+        // and that the remaining annotations keep their order.
+        #[test]
+        fn substring_bodies() {
+            let mut entry = Entry {
+                book: Book::default(),
+                annotations: vec![
+                    annotation("art spirit", "epubcfi(/6/4!/4/10/1,:0,:10)"),
+                    annotation("unrelated", "epubcfi(/6/4!/4/12/1,:0,:9)"),
+                    annotation("the art spirit", "epubcfi(/6/4!/4/10/1,:10,:24)"),
+                    annotation("  ", "epubcfi(/6/4!/4/10/1,:24,:26)"),
+                ],
+            };
+
+            let removed = super::dedupe(&mut entry);
+
+            let bodies: Vec<&str> = entry.annotations.iter().map(|a| a.body.as_str()).collect();
+
+            assert_eq!(removed, 1);
+            assert_eq!(bodies, ["unrelated", "the art spirit", "  "]);
+        }
+
+        // Tests that annotations with matching bodies elsewhere in the book, or without ranges,
+        // are kept.
+        #[test]
+        fn distant_substring_bodies() {
+            let mut entry = Entry {
+                book: Book::default(),
+                annotations: vec![
+                    annotation("art", "epubcfi(/6/4!/4/10/1,:0,:3)"),
+                    annotation("the art spirit", "epubcfi(/6/4!/4/20/1,:0,:14)"),
+                    annotation("spirit", ""),
+                ],
+            };
+
+            assert_eq!(super::dedupe(&mut entry), 0);
+            assert_eq!(entry.annotations.len(), 3);
+        }
+
+        // Tests that the notes and tags of removed annotations are merged into the one kept.
+        #[test]
+        fn merge_notes_and_tags() {
+            let mut first = annotation("first passage", "epubcfi(/6/4!/4/10/1,:0,:13)");
+            first.notes = "phone".to_string();
+            first.tags = BTreeSet::from(["#one".to_string()]);
+
+            let mut second = annotation("passage and more", "epubcfi(/6/4!/4/10/1,:6,:22)");
+            second.notes = "computer".to_string();
+            second.tags = BTreeSet::from(["#two".to_string()]);
+
+            let mut third = annotation("and more", "epubcfi(/6/4!/4/10/1,:14,:22)");
+            third.notes = "computer".to_string();
+
+            let mut entry = Entry {
+                book: Book::default(),
+                annotations: vec![first, second, third],
+            };
+
+            assert_eq!(super::dedupe(&mut entry), 2);
+            assert_eq!(entry.annotations.len(), 1);
+            assert_eq!(entry.annotations[0].notes, "computer\n\nphone");
+            assert_eq!(
+                entry.annotations[0].tags,
+                BTreeSet::from(["#one".to_string(), "#two".to_string()])
+            );
+        }
+
+        // Tests that adjacent but non-overlapping ranges are kept.
+        #[test]
+        fn adjacent_ranges() {
+            let mut entry = Entry {
+                book: Book::default(),
+                annotations: vec![
+                    annotation("first", "epubcfi(/6/4!/4/10/1,:0,:5)"),
+                    annotation("second", "epubcfi(/6/4!/4/10/1,:5,:11)"),
+                ],
+            };
+
+            assert_eq!(super::dedupe(&mut entry), 0);
+            assert_eq!(entry.annotations.len(), 2);
+        }
+    }
+
+    mod merge_adjacent {
+
+        use super::*;
+
+        use crate::models::annotation::AnnotationMetadata;
+        use crate::models::book::Book;
+
+        fn annotation(body: &str, notes: &str, tags: &[&str], epubcfi: &str) -> Annotation {
+            Annotation {
+                body: body.to_string(),
+                notes: notes.to_string(),
+                tags: tags.iter().map(ToString::to_string).collect(),
+                metadata: AnnotationMetadata {
+                    epubcfi: epubcfi.to_string(),
+                    ..Default::default()
+                },
+                ..Default::default()
+            }
+        }
+
+        // Tests that annotations with contiguous ranges are merged, including chains of them.
+        #[test]
+        fn contiguous_ranges() {
+            let mut entry = Entry {
+                book: Book::default(),
+                annotations: vec![
+                    annotation("First.", "a", &["#one"], "epubcfi(/6/4!/4/10/1,:0,:6)"),
+                    annotation("Second.", "", &["#two"], "epubcfi(/6/4!/4/10/1,:6,:14)"),
+                    annotation("Third.", "c", &["#one"], "epubcfi(/6/4!/4/10/1,:14,:20)"),
+                    annotation("Fourth.", "", &[], "epubcfi(/6/4!/4/12/1,:0,:7)"),
+                ],
+            };
+
+            let merged = super::merge_adjacent(&mut entry);
+
+            assert_eq!(merged, 2);
+            assert_eq!(entry.annotations.len(), 2);
+            assert_eq!(entry.annotations[0].body, "First. Second. Third.");
+            assert_eq!(entry.annotations[0].notes, "a\n\nc");
+            assert_eq!(entry.annotations[0].tags.len(), 2);
+            assert_eq!(entry.annotations[1].body, "Fourth.");
+        }
+
+        // Tests that the context text shared by merged annotations is only kept once.
+        #[test]
+        fn shared_context_text() {
+            let mut annotations = vec![
+                annotation("First.", "", &[], "epubcfi(/6/4!/4/10/1,:0,:6)"),
+                annotation("Second.", "", &[], "epubcfi(/6/4!/4/10/1,:6,:14)"),
+                annotation("Third.", "", &[], "epubcfi(/6/4!/4/10/1,:14,:20)"),
+            ];
+
+            annotations[0].context_text = "First. Second.".to_string();
+            annotations[1].context_text = "First. Second.".to_string();
+            annotations[2].context_text = "Third.".to_string();
+
+            let mut entry = Entry {
+                book: Book::default(),
+                annotations,
+            };
+
+            super::merge_adjacent(&mut entry);
+
+            assert_eq!(
+                entry.annotations[0].context_text,
+                "First. Second.\n\nThird."
+            );
+        }
+
+        // Tests that bodies that visibly continue each other are merged.
+        #[test]
+        fn continued_bodies() {
+            let mut entry = Entry {
+                book: Book::default(),
+                annotations: vec![
+                    annotation("The art spirit is", "", &[], ""),
+                    annotation("in every human being.", "", &[], ""),
+                    annotation("A new sentence.", "", &[], ""),
+                    annotation("\"Quoted.\"", "", &[], ""),
+                    annotation("lowercase but after a sentence.", "", &[], ""),
+                ],
+            };
+
+            let merged = super::merge_adjacent(&mut entry);
+
+            let bodies: Vec<&str> = entry.annotations.iter().map(|a| a.body.as_str()).collect();
+
+            assert_eq!(merged, 1);
+            assert_eq!(
+                bodies,
+                [
+                    "The art spirit is in every human being.",
+                    "A new sentence.",
+                    "\"Quoted.\"",
+                    "lowercase but after a sentence.",
+                ]
+            );
+        }
+    }
+
+    mod link_mentions {
+
+        use super::*;
+
+        use crate::models::book::{Book, BookMetadata};
+
+        fn entry(asset_id: &str, title: &str, bodies: &[&str]) -> Entry {
+            Entry {
+                book: Book {
+                    title: title.to_string(),
+                    metadata: BookMetadata {
+                        id: asset_id.to_string(),
+                        asset_id: asset_id.to_string(),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                },
+                annotations: bodies
+                    .iter()
+                    .map(|body| Annotation {
+                        body: (*body).to_string(),
+                        ..Default::default()
+                    })
+                    .collect(),
+            }
+        }
+
+        // Tests that titles are matched as whole words, ignoring case, punctuation and subtitles.
+        #[test]
+        fn match_titles() {
+            let mut entries = Entries::default();
+
+            for entry in [
+                entry(
+                    "1",
+                    "The Art Spirit",
+                    &["As Henri wrote in THE ART SPIRIT, art is..."],
+                ),
+                entry(
+                    "2",
+                    "Meditations: A New Translation",
+                    &["Unlike the art spirit."],
+                ),
+                entry(
+                    "3",
+                    "Walden",
+                    &["Re-read meditations and the art-spirit.", "Waldenses"],
+                ),
+                entry("4", "It", &["It is what it is."]),
+            ] {
+                entries.insert(entry.book.metadata.id.clone(), entry);
+            }
+
+            let linked = super::link_mentions(&mut entries);
+
+            let mentions = |id: &str| -> Vec<Vec<String>> {
+                entries[id]
+                    .annotations
+                    .iter()
+                    .map(|annotation| annotation.mentions.clone())
+                    .collect()
+            };
+
+            assert_eq!(linked, 2);
+            // A book never mentions itself.
+            assert_eq!(mentions("1"), [Vec::<String>::new()]);
+            assert_eq!(mentions("2"), [["1"]]);
+            assert_eq!(mentions("3"), [vec!["1", "2"], vec![]]);
+            assert_eq!(mentions("4"), [Vec::<String>::new()]);
+        }
     }
 }
@@ -0,0 +1,224 @@
+//! Defines types for building a chronological history of finished books.
+//!
+//! Unlike most of the library, a reading log is built from books alone and ignores their
+//! annotations. Books marked as finished are grouped by the year and month they were finished in.
+//! Books that are finished without a date and books that are partially read are listed separately.
+
+use chrono::Datelike;
+use serde::Serialize;
+
+use crate::models::book::Book;
+use crate::models::entry::Entries;
+
+/// A struct representing the reading history of a set of books.
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct ReadingLog<'a> {
+    /// The years books were finished in, oldest first.
+    pub years: Vec<ReadingLogYear<'a>>,
+
+    /// Books marked as finished without a date.
+    pub undated: Vec<&'a Book>,
+
+    /// Books with some reading progress that aren't marked as finished, most read first.
+    pub in_progress: Vec<&'a Book>,
+}
+
+impl ReadingLog<'_> {
+    /// Returns the number of finished books, including those without a date.
+    #[must_use]
+    pub fn finished(&self) -> usize {
+        self.years.iter().map(ReadingLogYear::len).sum::<usize>() + self.undated.len()
+    }
+
+    /// Returns `true` if the log contains no books.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.years.is_empty() && self.undated.is_empty() && self.in_progress.is_empty()
+    }
+}
+
+/// A struct representing the books finished within a year.
+#[derive(Debug, Clone, Serialize)]
+pub struct ReadingLogYear<'a> {
+    /// The year e.g. `2024`.
+    pub year: i32,
+
+    /// The months books were finished in, oldest first.
+    pub months: Vec<ReadingLogMonth<'a>>,
+}
+
+impl ReadingLogYear<'_> {
+    /// Returns the number of books finished within the year.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.months.iter().map(|month| month.books.len()).sum()
+    }
+
+    /// Returns `true` if no books were finished within the year.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// A struct representing the books finished within a month.
+#[derive(Debug, Clone, Serialize)]
+pub struct ReadingLogMonth<'a> {
+    /// The month from `1` to `12`.
+    pub month: u32,
+
+    /// The books finished within the month, in the order they were finished.
+    pub books: Vec<&'a Book>,
+}
+
+/// Builds a reading log from the books of a set of entries.
+///
+/// # Arguments
+///
+/// * `entries` - The entries to build the log from.
+#[must_use]
+pub fn run(entries: &Entries) -> ReadingLog<'_> {
+    let mut log = ReadingLog::default();
+
+    // Books without a finished date are grouped under `None`, which is ordered first.
+    let groups = entries.group_by(|entry| {
+        let metadata = &entry.book.metadata;
+
+        metadata
+            .date_finished
+            .as_ref()
+            .filter(|_| metadata.is_finished)
+            .map(|date| (date.year(), date.month()))
+    });
+
+    for (key, entries) in groups {
+        let mut books: Vec<&Book> = entries.into_iter().map(|entry| &entry.book).collect();
+
+        let Some((year, month)) = key else {
+            for book in books {
+                if book.metadata.is_finished {
+                    log.undated.push(book);
+                } else if book.metadata.progress.unwrap_or_default() > 0.0 {
+                    log.in_progress.push(book);
+                }
+            }
+
+            continue;
+        };
+
+        books.sort_by_key(|book| book.metadata.date_finished.as_deref().copied());
+
+        let month = ReadingLogMonth { month, books };
+
+        match log.years.last_mut() {
+            Some(last) if last.year == year => last.months.push(month),
+            _ => log.years.push(ReadingLogYear {
+                year,
+                months: vec![month],
+            }),
+        }
+    }
+
+    log.undated.sort_by(|a, b| a.title.cmp(&b.title));
+    log.in_progress.sort_by(|a, b| {
+        b.metadata
+            .progress
+            .unwrap_or_default()
+            .total_cmp(&a.metadata.progress.unwrap_or_default())
+            .then_with(|| a.title.cmp(&b.title))
+    });
+
+    log
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    use chrono::{TimeZone, Utc};
+
+    use crate::models::datetime::DateTimeUtc;
+    use crate::models::entry::Entry;
+
+    fn entries<const N: usize>(entries: [Entry; N]) -> Entries {
+        entries
+            .into_iter()
+            .map(|entry| (entry.book.metadata.id.clone(), entry))
+            .collect()
+    }
+
+    fn entry(title: &str, progress: Option<f64>, finished: Option<(i32, u32, u32)>) -> Entry {
+        let mut entry = Entry::default();
+
+        entry.book.title = title.to_string();
+        entry.book.metadata.id = title.to_string();
+        entry.book.metadata.progress = progress;
+
+        if let Some((year, month, day)) = finished {
+            entry.book.metadata.is_finished = true;
+            entry.book.metadata.date_finished = (year > 0).then(|| {
+                DateTimeUtc::from(Utc.with_ymd_and_hms(year, month, day, 0, 0, 0).unwrap())
+            });
+        }
+
+        entry
+    }
+
+    fn titles(books: &[&Book]) -> Vec<String> {
+        books.iter().map(|book| book.title.clone()).collect()
+    }
+
+    // Tests that finished books are grouped by year and month in chronological order.
+    #[test]
+    fn chronological() {
+        let entries = entries([
+            entry("C", Some(1.0), Some((2024, 2, 1))),
+            entry("A", Some(1.0), Some((2023, 11, 20))),
+            entry("D", Some(1.0), Some((2024, 2, 10))),
+            entry("B", Some(1.0), Some((2023, 12, 5))),
+        ]);
+
+        let log = run(&entries);
+
+        assert_eq!(log.finished(), 4);
+        assert_eq!(
+            log.years.iter().map(|year| year.year).collect::<Vec<_>>(),
+            [2023, 2024]
+        );
+
+        let months: Vec<u32> = log.years[0].months.iter().map(|m| m.month).collect();
+
+        assert_eq!(months, [11, 12]);
+        assert_eq!(log.years[1].len(), 2);
+        assert_eq!(titles(&log.years[1].months[0].books), ["C", "D"]);
+    }
+
+    // Tests that undated and partially read books are listed separately and unread books are
+    // skipped.
+    #[test]
+    fn undated_and_in_progress() {
+        let entries = entries([
+            entry("Finished", None, Some((0, 0, 0))),
+            entry("Half", Some(0.5), None),
+            entry("Most", Some(0.9), None),
+            entry("Unread", Some(0.0), None),
+            entry("Unopened", None, None),
+        ]);
+
+        let log = run(&entries);
+
+        assert!(log.years.is_empty());
+        assert_eq!(titles(&log.undated), ["Finished"]);
+        assert_eq!(titles(&log.in_progress), ["Most", "Half"]);
+        assert_eq!(log.finished(), 1);
+    }
+
+    // Tests that a log without any read books is empty.
+    #[test]
+    fn empty() {
+        let entries = entries([entry("Unread", None, None)]);
+
+        assert!(run(&entries).is_empty());
+    }
+}
@@ -0,0 +1,180 @@
+//! Defines functions for appending or prepending rendered annotations to a file.
+//!
+//! Templates with `write-mode: append` or `write-mode: prepend` render each annotation into a
+//! single file per book. Each annotation is preceded by a marker containing its id:
+//!
+//! ```markdown
+//! <!-- readstor:annotation 5D9C1B0E-... -->
+//! ...the rendered annotation...
+//!
+//! <!-- readstor:annotation 8A2F4C7D-... -->
+//! ...the rendered annotation...
+//! ```
+//!
+//! When rendering into a file that already has an annotation's marker, the annotation is skipped
+//! so the file accumulates annotations across renders without duplicates.
+
+use std::io;
+use std::path::Path;
+
+use crate::conflict::WriteOutcome;
+
+use super::defaults::{APPEND_MARKER_CLOSE, APPEND_MARKER_OPEN};
+use super::template::{Render, WriteMode};
+
+/// Returns the marker placed before an annotation.
+///
+/// # Arguments
+///
+/// * `id` - The annotation's id.
+#[must_use]
+pub fn marker(id: &str) -> String {
+    format!("{APPEND_MARKER_OPEN}{id}{APPEND_MARKER_CLOSE}")
+}
+
+/// Adds rendered contents preceded by their annotation's marker to the end or start of an existing
+/// file's contents. Returns `None` if the existing contents already have the annotation's marker.
+///
+/// # Arguments
+///
+/// * `existing` - The existing file's contents.
+/// * `contents` - The rendered contents to add.
+/// * `id` - The annotation's id.
+/// * `write_mode` - Whether to add the contents to the end or start.
+#[must_use]
+pub fn merge(existing: &str, contents: &str, id: &str, write_mode: WriteMode) -> Option<String> {
+    let marker = self::marker(id);
+
+    if existing.lines().any(|line| line.trim() == marker) {
+        return None;
+    }
+
+    let contents = format!("{marker}\n{}\n", contents.trim_end_matches('\n'));
+
+    if existing.is_empty() {
+        return Some(contents);
+    }
+
+    let merged = match write_mode {
+        WriteMode::Prepend => format!("{contents}\n{existing}"),
+        WriteMode::Replace | WriteMode::Append => {
+            format!("{}\n\n{contents}", existing.trim_end_matches('\n'))
+        }
+    };
+
+    Some(merged)
+}
+
+/// Appends or prepends a render's contents to a file, creating it if it doesn't exist. The file is
+/// left untouched if it already has the annotation's marker.
+///
+/// The existing file is read with, and the result written with, the render's encoding and line
+/// endings. See [`Render::encode()`].
+///
+/// # Arguments
+///
+/// * `path` - The path to write to.
+/// * `render` - The render to write.
+/// * `id` - The id of the annotation the render was rendered from.
+///
+/// # Errors
+///
+/// Will return `Err` if any IO errors are encountered or if the existing file isn't valid for the
+/// render's encoding.
+pub fn write(path: &Path, render: &Render, id: &str) -> io::Result<WriteOutcome> {
+    let existing = match std::fs::read(path) {
+        Ok(existing) => Some(render.decode(&existing).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("cannot append to {}", path.display()),
+            )
+        })?),
+        Err(error) if error.kind() == io::ErrorKind::NotFound => None,
+        Err(error) => return Err(error),
+    };
+
+    let Some(merged) = self::merge(
+        existing.as_deref().unwrap_or_default(),
+        &render.contents,
+        id,
+        render.write_mode,
+    ) else {
+        tracing::debug!(path = %path.display(), id, "skipped appending");
+        return Ok(WriteOutcome::Skipped);
+    };
+
+    std::fs::write(path, render.encode(&merged))?;
+
+    if existing.is_some() {
+        Ok(WriteOutcome::Rewritten)
+    } else {
+        Ok(WriteOutcome::Created)
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    use crate::defaults::TEMP_OUTPUT_DIRECTORY;
+
+    // Tests that contents are added after or before the existing contents.
+    #[test]
+    fn merge_contents() {
+        let existing = format!("{}\nold\n", marker("1"));
+
+        assert_eq!(
+            merge(&existing, "new\n", "2", WriteMode::Append).unwrap(),
+            format!("{}\nold\n\n{}\nnew\n", marker("1"), marker("2"))
+        );
+        assert_eq!(
+            merge(&existing, "new\n", "2", WriteMode::Prepend).unwrap(),
+            format!("{}\nnew\n\n{}\nold\n", marker("2"), marker("1"))
+        );
+    }
+
+    // Tests that an annotation already in the contents isn't added again.
+    #[test]
+    fn merge_existing_marker() {
+        let existing = format!("# Notes\n\n{}\nold\n", marker("1"));
+
+        assert!(merge(&existing, "new", "1", WriteMode::Append).is_none());
+    }
+
+    // Tests that annotations accumulate in a file across writes.
+    #[test]
+    fn write_annotations() {
+        let directory = TEMP_OUTPUT_DIRECTORY.join("tests-append");
+        std::fs::create_dir_all(&directory).unwrap();
+
+        let path = directory.join("book.md");
+
+        if path.exists() {
+            std::fs::remove_file(&path).unwrap();
+        }
+
+        let render = |contents: &str| Render {
+            contents: contents.to_string(),
+            write_mode: WriteMode::Append,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            write(&path, &render("first"), "1").unwrap(),
+            WriteOutcome::Created
+        );
+        assert_eq!(
+            write(&path, &render("second"), "2").unwrap(),
+            WriteOutcome::Rewritten
+        );
+        assert_eq!(
+            write(&path, &render("first"), "1").unwrap(),
+            WriteOutcome::Skipped
+        );
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            format!("{}\nfirst\n\n{}\nsecond\n", marker("1"), marker("2"))
+        );
+    }
+}
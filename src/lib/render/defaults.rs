@@ -16,6 +16,34 @@ pub const CONFIG_TAG_OPEN: &str = "<!-- readstor\n";
 /// information.
 pub const CONFIG_TAG_CLOSE: &str = "\n-->\n";
 
+/// The marker opening the region of a file managed by `readstor`. See [`managed`][managed] for more
+/// information.
+///
+/// [managed]: super::managed
+pub const MANAGED_REGION_START: &str = "<!-- readstor:start -->";
+
+/// The marker closing the region of a file managed by `readstor`. See [`MANAGED_REGION_START`] for
+/// more information.
+pub const MANAGED_REGION_END: &str = "<!-- readstor:end -->";
+
+/// The start of the marker placed before each annotation appended or prepended to a file. The
+/// annotation's id and [`APPEND_MARKER_CLOSE`] follow it. See [`append`][append] for more
+/// information.
+///
+/// [append]: super::append
+pub const APPEND_MARKER_OPEN: &str = "<!-- readstor:annotation ";
+
+/// The end of the marker placed before each annotation appended or prepended to a file. See
+/// [`APPEND_MARKER_OPEN`] for more information.
+pub const APPEND_MARKER_CLOSE: &str = " -->";
+
+/// The width a template's files are wrapped at when it sets `wrap-text: true` and no default width
+/// is set with `--wrap-text`.
+pub const DEFAULT_WRAP_WIDTH: usize = 80;
+
+/// The suffix appended to a template's id to register its `skip-if` expression under.
+pub const SKIP_IF_SUFFIX: &str = "#skip-if";
+
 /// The default template used to generate the output filename for a template with
 /// [`ContextMode::Book`][book].
 ///
@@ -29,6 +57,18 @@ pub const FILENAME_TEMPLATE_BOOK: &str = "{{ book.author }} - {{ book.title }}";
 pub const FILENAME_TEMPLATE_ANNOTATION: &str =
     "{{ annotation.slugs.metadata.created }}-{{ book.slugs.title }}";
 
+/// The default template used to generate the output filename for a template with
+/// [`ContextMode::Tag`][tag].
+///
+/// [tag]: super::template::ContextMode::Tag
+pub const FILENAME_TEMPLATE_TAG: &str = "{{ tag.slug }}";
+
+/// The default template used to generate the output filename for a template with
+/// [`ContextMode::Bookmark`][bookmark].
+///
+/// [bookmark]: super::template::ContextMode::Bookmark
+pub const FILENAME_TEMPLATE_BOOKMARK: &str = "{{ book.author }} - {{ book.title }}";
+
 /// The default template used to generate the directory name for a template with
 /// [`StructureMode::Nested`][nested] or [`StructureMode::NestedGrouped`][nested-grouped].
 ///
@@ -36,3 +76,20 @@ pub const FILENAME_TEMPLATE_ANNOTATION: &str =
 /// [nested]: super::template::StructureMode::Nested
 /// [nested-grouped]: super::template::StructureMode::NestedGrouped
 pub const DIRECTORY_TEMPLATE: &str = "{{ book.author }} - {{ book.title }}";
+
+/// The default template used to generate the dated directory path for a template with
+/// [`StructureMode::Dated`][dated] or [`StructureMode::DatedGrouped`][dated-grouped].
+///
+/// [dated]: super::template::StructureMode::Dated
+/// [dated-grouped]: super::template::StructureMode::DatedGrouped
+pub const DATED_DIRECTORY_TEMPLATE: &str = "{{ date.year }}/{{ date.month }}";
+
+/// The file stem of the files written when rendering into a single file.
+pub const SINGLE_FILE_NAME: &str = "library";
+
+/// The maximum number of threads used to write rendered templates to disk.
+pub const WRITE_THREADS_MAX: usize = 8;
+
+/// The number of rendered templates at or below which they're written on the current thread, as
+/// spawning threads would cost more than it saves.
+pub const WRITE_PARALLEL_THRESHOLD: usize = 64;
@@ -1,22 +1,31 @@
 //! Defines the interface to the templating engine.
+//!
+//! [`RenderEngine`] can also be used outside of a render to preview user-supplied template strings
+//! e.g. filenames. See [`RenderEngine::render_str()`] for more information.
 
 use std::collections::HashMap;
+use std::sync::mpsc::RecvTimeoutError;
+use std::sync::Arc;
+use std::time::Duration;
 
 use chrono::format::{Item, StrftimeItems};
 use chrono::{DateTime, Utc};
 use serde::Serialize;
 use tera::{try_get_value, Tera};
 
-use crate::result::Result;
+use crate::result::{Error, Result};
 use crate::strings;
 
 /// Templating engine interface.
+///
+/// The engine is reference counted so that renders can be moved onto another thread. See
+/// [`RenderEngine::render_with_limits()`] for more information.
 #[derive(Debug)]
-pub struct RenderEngine(Tera);
+pub struct RenderEngine(Arc<Tera>);
 
 impl Default for RenderEngine {
     fn default() -> Self {
-        let mut engine = Self(Tera::default());
+        let mut engine = Self(Arc::new(Tera::default()));
         engine.register_custom_filters();
         engine
     }
@@ -34,7 +43,7 @@ impl RenderEngine {
     ///
     /// Will return `Err` if the templates contains any errors.
     pub fn register_template(&mut self, name: &str, content: &str) -> Result<()> {
-        self.0.add_raw_template(name, content)?;
+        Arc::make_mut(&mut self.0).add_raw_template(name, content)?;
 
         Ok(())
     }
@@ -61,8 +70,102 @@ impl RenderEngine {
         Ok(string)
     }
 
+    /// Renders a template with a context, failing if it takes longer than a timeout or if its output
+    /// grows larger than a maximum size.
+    ///
+    /// The output is checked against `max_size` as it's written so a template that renders too
+    /// much is stopped before its entire output is held in memory.
+    ///
+    /// With a `timeout`, the template is rendered on a separate thread. As a render cannot be
+    /// interrupted, a render that times out is left to finish in the background and its result is
+    /// discarded. Timeouts are therefore meant to fail a run quickly rather than to be recovered
+    /// from.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The template's name.
+    /// * `context` - The templates's context.
+    /// * `timeout` - The maximum time to wait for the render, if any.
+    /// * `max_size` - The maximum size of the output in bytes, if any.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if:
+    /// * The template doesn't exist.
+    /// * The render takes longer than the timeout.
+    /// * The output is larger than the maximum size.
+    /// * [`serde_json`][serde-json] encounters any errors.
+    pub fn render_with_limits<C>(
+        &self,
+        name: &str,
+        context: C,
+        timeout: Option<Duration>,
+        max_size: Option<usize>,
+    ) -> Result<String>
+    where
+        C: Serialize,
+    {
+        let context = tera::Context::from_serialize(context)?;
+
+        let Some(timeout) = timeout else {
+            return self::render_to_string(&self.0, name, &context, max_size);
+        };
+
+        let engine = Arc::clone(&self.0);
+        let id = name.to_owned();
+        let (sender, receiver) = std::sync::mpsc::channel();
+
+        let handle = std::thread::spawn(move || {
+            // The receiver is dropped if the render times out so the result can be discarded.
+            let _ = sender.send(self::render_to_string(&engine, &id, &context, max_size));
+        });
+
+        match receiver.recv_timeout(timeout) {
+            Ok(result) => result,
+            Err(RecvTimeoutError::Timeout) => Err(Error::TemplateTimeout {
+                path: name.to_owned(),
+                timeout,
+            }),
+            Err(RecvTimeoutError::Disconnected) => {
+                // The sender is only dropped without sending if the render panicked.
+                if let Err(error) = handle.join() {
+                    std::panic::resume_unwind(error);
+                }
+
+                Err(Error::OtherError {
+                    error: format!("Failed to render '{name}'"),
+                })
+            }
+        }
+    }
+
     /// Renders a one-off template string with a context.
     ///
+    /// The template has access to the same filters as registered templates: [Tera][tera]'s
+    /// built-in filters along with `date`, `strip` and `slugify`. Contexts are usually built from
+    /// the types in [`contexts`][contexts] or, for file and directory names, with
+    /// [`NamesContext`][names-context].
+    ///
+    /// ```
+    /// use lib::contexts::book::BookContext;
+    /// use lib::models::book::Book;
+    /// use lib::render::engine::RenderEngine;
+    /// use lib::render::names::NamesContext;
+    ///
+    /// let book = Book {
+    ///     title: "The Art Spirit".to_owned(),
+    ///     author: "Robert Henri".to_owned(),
+    ///     ..Default::default()
+    /// };
+    /// let book = BookContext::from(&book);
+    ///
+    /// let name = RenderEngine::default()
+    ///     .render_str("{{ book.author }} - {{ book.title | slugify }}", NamesContext::directory(&book))
+    ///     .unwrap();
+    ///
+    /// assert_eq!(name, "Robert Henri - the-art-spirit");
+    /// ```
+    ///
     /// # Arguments
     ///
     /// * `template` - The template's contents.
@@ -73,22 +176,100 @@ impl RenderEngine {
     /// Will return `Err` if:
     /// * The templates contains any errors.
     /// * [`serde_json`][serde-json] encounters any errors.
+    ///
+    /// [contexts]: crate::contexts
+    /// [names-context]: crate::render::names::NamesContext
+    /// [tera]: https://keats.github.io/tera/docs/#built-in-filters
     pub fn render_str<C>(&mut self, template: &str, context: C) -> Result<String>
     where
         C: Serialize,
     {
         let context = &tera::Context::from_serialize(context)?;
-        let string = self.0.render_str(template, context)?;
+        let string = Arc::make_mut(&mut self.0).render_str(template, context)?;
 
         Ok(string)
     }
 
-    /// Registers custom template filters.
+    /// Registers custom template filters: `date`, `strip` and `slugify`.
     fn register_custom_filters(&mut self) {
-        self.0.register_filter("date", filter_date);
-        self.0.register_filter("strip", filter_strip);
-        self.0.register_filter("slugify", filter_slugify);
+        let engine = Arc::make_mut(&mut self.0);
+
+        engine.register_filter("date", filter_date);
+        engine.register_filter("strip", filter_strip);
+        engine.register_filter("slugify", filter_slugify);
+    }
+}
+
+/// A writer collecting a render's output that fails once the output exceeds a maximum size.
+struct SizeLimitedWriter {
+    /// The output written so far.
+    buffer: Vec<u8>,
+
+    /// The maximum size of the output in bytes, if any.
+    max_size: Option<usize>,
+
+    /// Whether a write was refused for exceeding the maximum size.
+    is_exceeded: bool,
+}
+
+impl std::io::Write for SizeLimitedWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        if let Some(max_size) = self.max_size {
+            if self.buffer.len() + buf.len() > max_size {
+                self.is_exceeded = true;
+                return Err(std::io::Error::other("maximum render size exceeded"));
+            }
+        }
+
+        self.buffer.extend_from_slice(buf);
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Renders a template into a string, stopping as soon as the output exceeds a maximum size.
+///
+/// # Arguments
+///
+/// * `engine` - The templating engine.
+/// * `name` - The template's name.
+/// * `context` - The templates's context.
+/// * `max_size` - The maximum size of the output in bytes, if any.
+///
+/// # Errors
+///
+/// Will return `Err` if the template doesn't exist, fails to render or its output is larger than
+/// the maximum size.
+fn render_to_string(
+    engine: &Tera,
+    name: &str,
+    context: &tera::Context,
+    max_size: Option<usize>,
+) -> Result<String> {
+    let mut writer = SizeLimitedWriter {
+        buffer: Vec::new(),
+        max_size,
+        is_exceeded: false,
+    };
+
+    let result = engine.render_to(name, context, &mut writer);
+
+    if let (true, Some(max)) = (writer.is_exceeded, max_size) {
+        return Err(Error::TemplateTooLarge {
+            path: name.to_owned(),
+            max,
+        });
     }
+
+    result?;
+
+    String::from_utf8(writer.buffer).map_err(|_| Error::OtherError {
+        error: format!("Failed to render '{name}': output isn't valid UTF-8"),
+    })
 }
 
 /// This is a partial reimplementation of `Tera`'s `date` filter that handles empty dates strings.
@@ -104,7 +285,6 @@ impl RenderEngine {
 /// [fromstr]: std::str::FromStr
 #[allow(clippy::implicit_hasher)]
 #[allow(clippy::missing_errors_doc)]
-#[allow(clippy::missing_panics_doc)]
 pub fn filter_date(
     value: &tera::Value,
     args: &HashMap<String, tera::Value>,
@@ -133,9 +313,12 @@ pub fn filter_date(
         )));
     };
 
-    // This should be safe as we're providing the input string. It's a serialized `DateTime<Utc>`
-    // object. An error here would be critical and should fail.
-    let date = date_str.parse::<DateTime<Utc>>().unwrap();
+    // Dates in the context are serialized `DateTime<Utc>`s but templates can pass any string.
+    let Ok(date) = date_str.parse::<DateTime<Utc>>() else {
+        return Err(tera::Error::msg(format!(
+            "Filter `date` received an invalid date `{date_str}`: expected an RFC 3339 date",
+        )));
+    };
 
     let formatted = date.format(&format).to_string();
 
@@ -220,6 +403,104 @@ mod test {
         }
     }
 
+    // Tests that one-off templates have access to the custom filters.
+    #[test]
+    fn render_str_custom_filters() {
+        let mut engine = RenderEngine::default();
+
+        let string = engine
+            .render_str(
+                r#"{{ "*Hello World*" | strip(chars="*") | slugify }}"#,
+                EmptyContext::default(),
+            )
+            .unwrap();
+
+        assert_eq!(string, "hello-world");
+    }
+
+    mod timeout {
+
+        use super::*;
+
+        // Tests that a render finishing within the timeout returns its output.
+        #[test]
+        fn within_timeout() {
+            let mut engine = RenderEngine::default();
+            engine.register_template("fast", "fast").unwrap();
+
+            let string = engine
+                .render_with_limits(
+                    "fast",
+                    EmptyContext::default(),
+                    Some(Duration::from_secs(30)),
+                    None,
+                )
+                .unwrap();
+
+            assert_eq!(string, "fast");
+        }
+
+        // Tests that a render taking longer than the timeout returns an error.
+        #[test]
+        fn exceeds_timeout() {
+            let mut engine = RenderEngine::default();
+            engine
+                .register_template(
+                    "slow",
+                    "{% for i in range(end=3000) %}{% for j in range(end=3000) %}\
+                     {% endfor %}{% endfor %}",
+                )
+                .unwrap();
+
+            let result = engine.render_with_limits(
+                "slow",
+                EmptyContext::default(),
+                Some(Duration::from_millis(1)),
+                None,
+            );
+
+            assert!(matches!(result, Err(Error::TemplateTimeout { .. })));
+        }
+    }
+
+    mod max_size {
+
+        use super::*;
+
+        // Tests that a render stops as soon as its output exceeds the maximum size.
+        #[test]
+        fn exceeds_max_size() {
+            let mut engine = RenderEngine::default();
+            engine
+                .register_template(
+                    "large",
+                    "{% for i in range(end=1000000) %}{{ i }}{% endfor %}",
+                )
+                .unwrap();
+
+            let result =
+                engine.render_with_limits("large", EmptyContext::default(), None, Some(64));
+
+            assert!(matches!(
+                result,
+                Err(Error::TemplateTooLarge { max: 64, .. })
+            ));
+        }
+
+        // Tests that a render within the maximum size returns its output.
+        #[test]
+        fn within_max_size() {
+            let mut engine = RenderEngine::default();
+            engine.register_template("small", "small").unwrap();
+
+            let string = engine
+                .render_with_limits("small", EmptyContext::default(), None, Some(5))
+                .unwrap();
+
+            assert_eq!(string, "small");
+        }
+    }
+
     mod invalid_filter {
 
         use super::*;
@@ -243,9 +524,7 @@ mod test {
         }
 
         #[test]
-        #[should_panic(
-            expected = "called `Result::unwrap()` on an `Err` value: ParseError(TooShort)"
-        )]
+        #[should_panic(expected = "Failed to render 'invalid-date.txt'")]
         fn date() {
             render_test_template(TemplatesDirectory::InvalidFilter, "invalid-date.txt");
         }
@@ -0,0 +1,196 @@
+//! Defines functions for writing rendered files with a managed region.
+//!
+//! A managed region is the part of a file between two markers that `readstor` owns:
+//!
+//! ```markdown
+//! My own notes, kept across renders.
+//!
+//! <!-- readstor:start -->
+//! ...the rendered template...
+//! <!-- readstor:end -->
+//!
+//! More of my own notes.
+//! ```
+//!
+//! When re-rendering into a file that already has a managed region, only the region is replaced so
+//! anything written outside of it is preserved.
+
+use std::io;
+use std::path::Path;
+
+use crate::conflict::{self, ConflictMode, WriteOutcome};
+
+use super::defaults::{MANAGED_REGION_END, MANAGED_REGION_START};
+use super::template::Render;
+
+/// Wraps rendered contents in managed region markers.
+///
+/// # Arguments
+///
+/// * `contents` - The rendered contents.
+#[must_use]
+pub fn wrap(contents: &str) -> String {
+    format!(
+        "{MANAGED_REGION_START}\n{}\n{MANAGED_REGION_END}\n",
+        contents.trim_end_matches('\n')
+    )
+}
+
+/// Replaces the managed region of an existing file's contents. Returns `None` if the existing
+/// contents don't have a complete managed region.
+///
+/// Only the first region is replaced. Everything before its start marker and after its end marker
+/// is kept as is.
+///
+/// # Arguments
+///
+/// * `existing` - The existing file's contents.
+/// * `contents` - The rendered contents to place inside the region.
+#[must_use]
+pub fn merge(existing: &str, contents: &str) -> Option<String> {
+    let start = existing.find(MANAGED_REGION_START)?;
+    let end = existing[start..].find(MANAGED_REGION_END)? + start + MANAGED_REGION_END.len();
+
+    let region = self::wrap(contents);
+    let region = region.trim_end_matches('\n');
+
+    Some(format!(
+        "{}{region}{}",
+        &existing[..start],
+        &existing[end..]
+    ))
+}
+
+/// Writes a render's contents inside a managed region.
+///
+/// If the file exists and has a managed region, only the region is replaced. The file is then
+/// only rewritten if its contents changed, and backed-up first if `on_conflict` is
+/// [`ConflictMode::Backup`]. Otherwise, the wrapped contents are written based on `on_conflict`.
+///
+/// The existing file is read with, and the result written with, the render's encoding and line
+/// endings. See [`Render::encode()`].
+///
+/// # Arguments
+///
+/// * `path` - The path to write to.
+/// * `render` - The render to write.
+/// * `on_conflict` - What to do if the file exists without a managed region.
+///
+/// # Errors
+///
+/// Will return `Err` if any IO errors are encountered.
+pub fn write(path: &Path, render: &Render, on_conflict: ConflictMode) -> io::Result<WriteOutcome> {
+    let existing = match std::fs::read(path) {
+        Ok(existing) => render.decode(&existing),
+        Err(error) if error.kind() == io::ErrorKind::NotFound => None,
+        Err(error) => return Err(error),
+    };
+
+    match existing.and_then(|existing| self::merge(&existing, &render.contents)) {
+        Some(merged) => {
+            let mode = if on_conflict == ConflictMode::Backup {
+                ConflictMode::Backup
+            } else {
+                ConflictMode::Update
+            };
+
+            conflict::write(path, render.encode(&merged), mode)
+        }
+        None => conflict::write(
+            path,
+            render.encode(&self::wrap(&render.contents)),
+            on_conflict,
+        ),
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    use crate::defaults::TEMP_OUTPUT_DIRECTORY;
+
+    // Tests that only the managed region is replaced and the rest of the file is kept.
+    #[test]
+    fn merge_region() {
+        let existing = [
+            "# My Notes",
+            "",
+            MANAGED_REGION_START,
+            "old",
+            MANAGED_REGION_END,
+            "",
+            "More notes.",
+            "",
+        ]
+        .join("\n");
+
+        let expected = [
+            "# My Notes",
+            "",
+            MANAGED_REGION_START,
+            "new",
+            "lines",
+            MANAGED_REGION_END,
+            "",
+            "More notes.",
+            "",
+        ]
+        .join("\n");
+
+        assert_eq!(merge(&existing, "new\nlines\n").unwrap(), expected);
+    }
+
+    // Tests that contents without a complete managed region aren't merged.
+    #[test]
+    fn merge_missing_region() {
+        assert!(merge("# My Notes", "new").is_none());
+        assert!(merge(&format!("{MANAGED_REGION_START}\nold"), "new").is_none());
+        assert!(merge(
+            &format!("{MANAGED_REGION_END}\n{MANAGED_REGION_START}"),
+            "new"
+        )
+        .is_none());
+    }
+
+    // Tests that a file with a managed region is updated even if existing files are skipped.
+    #[test]
+    fn write_region() {
+        let directory = TEMP_OUTPUT_DIRECTORY.join("tests-managed");
+        std::fs::create_dir_all(&directory).unwrap();
+
+        let path = directory.join("note.md");
+
+        if path.exists() {
+            std::fs::remove_file(&path).unwrap();
+        }
+
+        let render = |contents: &str| Render {
+            contents: contents.to_string(),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            write(&path, &render("old"), ConflictMode::Skip).unwrap(),
+            WriteOutcome::Created
+        );
+
+        let edited = format!("Notes.\n\n{}", std::fs::read_to_string(&path).unwrap());
+        std::fs::write(&path, &edited).unwrap();
+
+        assert_eq!(
+            write(&path, &render("new"), ConflictMode::Skip).unwrap(),
+            WriteOutcome::Rewritten
+        );
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            format!("Notes.\n\n{MANAGED_REGION_START}\nnew\n{MANAGED_REGION_END}\n")
+        );
+
+        assert_eq!(
+            write(&path, &render("new"), ConflictMode::Skip).unwrap(),
+            WriteOutcome::Skipped
+        );
+    }
+}
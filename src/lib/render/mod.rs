@@ -1,8 +1,12 @@
 //! Defines types for parsing and rendering templates.
 
+pub mod append;
 pub mod defaults;
 pub mod engine;
+pub mod managed;
 pub mod names;
+pub mod progress;
 pub mod renderer;
+pub mod sandbox;
 pub mod template;
 pub mod utils;
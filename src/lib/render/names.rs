@@ -7,8 +7,11 @@ use serde::{Deserialize, Serialize};
 
 use crate::contexts::annotation::AnnotationContext;
 use crate::contexts::book::BookContext;
+use crate::contexts::bookmark::{AudiobookContext, BookmarkContext};
 use crate::contexts::entry::EntryContext;
+use crate::contexts::tag::TagContext;
 use crate::models::datetime::DateTimeUtc;
+use crate::render::engine::RenderEngine;
 use crate::render::template::Template;
 use crate::result::Result;
 use crate::strings;
@@ -31,6 +34,20 @@ pub struct Names {
     #[serde(default = "Names::default_annotation")]
     pub annotation: String,
 
+    /// The default template used when generating an output filename for the template when its
+    /// context mode is [`ContextMode::Tag`][tag].
+    ///
+    /// [tag]: crate::render::template::ContextMode::Tag
+    #[serde(default = "Names::default_tag")]
+    pub tag: String,
+
+    /// The default template used when generating an output filename for the template when its
+    /// context mode is [`ContextMode::Bookmark`][bookmark].
+    ///
+    /// [bookmark]: crate::render::template::ContextMode::Bookmark
+    #[serde(default = "Names::default_bookmark")]
+    pub bookmark: String,
+
     /// The default template used when generating a nested output directory for the
     /// template when its structure mode is either [`StructureMode::Nested`][nested] or
     /// [`StructureMode::NestedGrouped`][nested-grouped].
@@ -39,6 +56,16 @@ pub struct Names {
     /// [nested-grouped]: crate::render::template::StructureMode::NestedGrouped
     #[serde(default = "Names::default_directory")]
     pub directory: String,
+
+    /// The default template used when generating a dated output directory for the template when
+    /// its structure mode is either [`StructureMode::Dated`][dated] or
+    /// [`StructureMode::DatedGrouped`][dated-grouped]. Any `/`s in the rendered string are kept to
+    /// allow for multiple levels of directories e.g. `2024/05`.
+    ///
+    /// [dated]: crate::render::template::StructureMode::Dated
+    /// [dated-grouped]: crate::render::template::StructureMode::DatedGrouped
+    #[serde(default = "Names::default_dated_directory", rename = "dated-directory")]
+    pub dated_directory: String,
 }
 
 impl Default for Names {
@@ -46,7 +73,10 @@ impl Default for Names {
         Self {
             book: Self::default_book(),
             annotation: Self::default_annotation(),
+            tag: Self::default_tag(),
+            bookmark: Self::default_bookmark(),
             directory: Self::default_directory(),
+            dated_directory: Self::default_dated_directory(),
         }
     }
 }
@@ -62,10 +92,25 @@ impl Names {
         super::defaults::FILENAME_TEMPLATE_ANNOTATION.to_owned()
     }
 
+    /// Returns the default template for a tag's filename.
+    fn default_tag() -> String {
+        super::defaults::FILENAME_TEMPLATE_TAG.to_owned()
+    }
+
+    /// Returns the default template for an audiobook's filename.
+    fn default_bookmark() -> String {
+        super::defaults::FILENAME_TEMPLATE_BOOKMARK.to_owned()
+    }
+
     /// Returns the default template for a directory.
     fn default_directory() -> String {
         super::defaults::DIRECTORY_TEMPLATE.to_owned()
     }
+
+    /// Returns the default template for a dated directory.
+    fn default_dated_directory() -> String {
+        super::defaults::DATED_DIRECTORY_TEMPLATE.to_owned()
+    }
 }
 
 /// A struct representing the rendered template strings for all the output file and directory names
@@ -131,22 +176,144 @@ impl NamesRender {
         })
     }
 
+    /// Renders the filename for a template with [`ContextMode::Tag`][context-mode]. Unlike other
+    /// names, these aren't part of [`NamesRender`] as a tag isn't tied to a single entry.
+    ///
+    /// # Arguments
+    ///
+    /// * `tag` - The context to inject into the template.
+    /// * `template` - The template containing the filename template.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the filename template has syntax errors or is referencing non-existent
+    /// fields in its context.
+    ///
+    /// [context-mode]: crate::render::template::ContextMode::Tag
+    pub fn render_tag_filename(tag: &TagContext<'_>, template: &Template) -> Result<String> {
+        let context = NamesContext::tag(tag);
+
+        let filename = strings::render_and_sanitize(&template.names.tag, context)?;
+        let filename = strings::build_filename_and_sanitize(&filename, &template.extension);
+
+        Ok(filename)
+    }
+
+    /// Renders the filename for a template with [`ContextMode::Bookmark`][context-mode]. Like tags,
+    /// these aren't part of [`NamesRender`] as an audiobook isn't an entry.
+    ///
+    /// # Arguments
+    ///
+    /// * `audiobook` - The context to inject into the template.
+    /// * `template` - The template containing the filename template.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the filename template has syntax errors or is referencing non-existent
+    /// fields in its context.
+    ///
+    /// [context-mode]: crate::render::template::ContextMode::Bookmark
+    pub fn render_bookmark_filename(
+        audiobook: &AudiobookContext<'_>,
+        template: &Template,
+    ) -> Result<String> {
+        let context = NamesContext::bookmark(audiobook);
+
+        let filename = strings::render_and_sanitize(&template.names.bookmark, context)?;
+        let filename = strings::build_filename_and_sanitize(&filename, &template.extension);
+
+        Ok(filename)
+    }
+
+    /// Renders the directory name for a template with [`ContextMode::Bookmark`][context-mode] and
+    /// [`StructureMode::Nested`][nested] or [`StructureMode::NestedGrouped`][nested-grouped].
+    ///
+    /// # Arguments
+    ///
+    /// * `audiobook` - The context to inject into the template.
+    /// * `template` - The template containing the directory template.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the directory template has syntax errors or is referencing
+    /// non-existent fields in its context.
+    ///
+    /// [context-mode]: crate::render::template::ContextMode::Bookmark
+    /// [nested]: crate::render::template::StructureMode::Nested
+    /// [nested-grouped]: crate::render::template::StructureMode::NestedGrouped
+    pub fn render_bookmark_directory_name(
+        audiobook: &AudiobookContext<'_>,
+        template: &Template,
+    ) -> Result<String> {
+        let context = NamesContext::directory(&audiobook.book);
+
+        strings::render_and_sanitize(&template.names.directory, context)
+    }
+
+    /// Renders a template string into a relative path. Unlike file and directory names, any `/`s
+    /// in the rendered string are kept to allow for multiple levels of directories e.g. `2024/05`.
+    /// Each component is sanitized and empty and relative components are dropped so the path can't
+    /// escape the output directory.
+    ///
+    /// This is shared with exports so that both name their files and directories identically.
+    ///
+    /// # Arguments
+    ///
+    /// * `template` - The template string to render.
+    /// * `context` - The context to inject into the template. See [`NamesContext`].
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the template has syntax errors or is referencing non-existent fields in
+    /// its context.
+    pub fn render_path<C>(template: &str, context: C) -> Result<String>
+    where
+        C: Serialize,
+    {
+        let path = RenderEngine::default()
+            .render_str(template, context)?
+            .split('/')
+            .map(str::trim)
+            .filter(|component| !matches!(*component, "" | "." | ".."))
+            .map(strings::sanitize)
+            .collect::<Vec<_>>()
+            .join("/");
+
+        Ok(path)
+    }
+
     /// Returns the rendered annotation filename based on its id.
     ///
     /// # Arguments
     ///
     /// * `annotation_id` - The annotation's id.
     #[must_use]
-    #[allow(clippy::missing_panics_doc)]
     pub fn get_annotation_filename(&self, annotation_id: &str) -> String {
+        self.get_annotation(annotation_id).filename.clone()
+    }
+
+    /// Returns the rendered annotation dated directory based on its id.
+    ///
+    /// # Arguments
+    ///
+    /// * `annotation_id` - The annotation's id.
+    #[must_use]
+    pub fn get_annotation_dated_directory(&self, annotation_id: &str) -> String {
+        self.get_annotation(annotation_id).dated_directory.clone()
+    }
+
+    /// Returns the rendered annotation names based on its id.
+    ///
+    /// # Arguments
+    ///
+    /// * `annotation_id` - The annotation's id.
+    fn get_annotation(&self, annotation_id: &str) -> &AnnotationNameAttributes {
         self.annotations
             .get(annotation_id)
             // This should theoretically never fail as the `NamesRender` instance is created from
             // the `Entry`. This means they contain the same exact keys and it should therefore be
             // safe to unwrap. An error here would be critical and should fail.
             .expect("`NamesRender` instance missing `Annotation` present in `Entry`")
-            .filename
-            .clone()
     }
 
     /// Renders the filename for a template with [`ContextMode::Book`][context-mode].
@@ -186,9 +353,13 @@ impl NamesRender {
             let filename = strings::render_and_sanitize(&template.names.annotation, context)?;
             let filename = strings::build_filename_and_sanitize(&filename, &template.extension);
 
+            let context = NamesContext::dated_directory(&entry.book, annotation);
+
+            let dated_directory = Self::render_path(&template.names.dated_directory, context)?;
+
             annotations.insert(
                 annotation.metadata.id.clone(),
-                AnnotationNameAttributes::new(annotation, filename),
+                AnnotationNameAttributes::new(annotation, filename, dated_directory),
             );
         }
 
@@ -234,6 +405,13 @@ pub struct AnnotationNameAttributes {
     ///
     /// [context-mode]: crate::render::template::ContextMode
     pub filename: String,
+
+    /// The rendered dated directory for a template with [`StructureMode::Dated`][dated] or
+    /// [`StructureMode::DatedGrouped`][dated-grouped].
+    ///
+    /// [dated]: crate::render::template::StructureMode::Dated
+    /// [dated-grouped]: crate::render::template::StructureMode::DatedGrouped
+    pub dated_directory: String,
     #[allow(missing_docs)]
     pub created: DateTimeUtc,
     #[allow(missing_docs)]
@@ -244,9 +422,10 @@ pub struct AnnotationNameAttributes {
 
 impl AnnotationNameAttributes {
     /// Creates a new instance of [`AnnotationNameAttributes`].
-    fn new(annotation: &AnnotationContext<'_>, filename: String) -> Self {
+    fn new(annotation: &AnnotationContext<'_>, filename: String, dated_directory: String) -> Self {
         Self {
             filename,
+            dated_directory,
             created: annotation.metadata.created,
             modified: annotation.metadata.modified,
             location: annotation.metadata.location.clone(),
@@ -255,41 +434,173 @@ impl AnnotationNameAttributes {
 }
 
 /// An enum representing the different template contexts for rendering file and directory names.
+///
+/// These can be passed to [`RenderEngine::render_str()`] to preview names outside of a render.
 #[derive(Debug, Serialize)]
 #[serde(untagged)]
-enum NamesContext<'a> {
+pub enum NamesContext<'a> {
     /// The context when rendering a filename for a template with [`ContextMode::Book`][context-mode].
     ///
     /// [context-mode]: crate::render::template::ContextMode::Book
     Book {
+        /// The book.
         book: &'a BookContext<'a>,
+        /// The book's annotations.
         annotations: &'a [AnnotationContext<'a>],
     },
     /// The context when rendering a filename for a template with [`ContextMode::Annotation`][context-mode].
     ///
     /// [context-mode]: crate::render::template::ContextMode::Annotation
     Annotation {
+        /// The annotation's book.
         book: &'a BookContext<'a>,
+        /// The annotation.
         annotation: &'a AnnotationContext<'a>,
     },
+    /// The context when rendering a filename for a template with [`ContextMode::Tag`][context-mode].
+    ///
+    /// [context-mode]: crate::render::template::ContextMode::Tag
+    Tag {
+        /// The tag along with its annotations.
+        tag: &'a TagContext<'a>,
+    },
+    /// The context when rendering a filename for a template with [`ContextMode::Bookmark`][context-mode].
+    ///
+    /// [context-mode]: crate::render::template::ContextMode::Bookmark
+    Bookmark {
+        /// The audiobook.
+        book: &'a BookContext<'a>,
+        /// The audiobook's bookmarks.
+        bookmarks: &'a [BookmarkContext<'a>],
+    },
     /// The context when rendering the directory name for a template with
     /// [`StructureMode::Nested`][nested] or [`StructureMode::NestedGouped`][nested-grouped].
     ///
     /// [nested]: crate::render::template::StructureMode::Nested
     /// [nested-grouped]: crate::render::template::StructureMode::NestedGrouped
-    Directory { book: &'a BookContext<'a> },
+    Directory {
+        /// The book.
+        book: &'a BookContext<'a>,
+    },
+    /// The context when rendering the dated directory for a template with
+    /// [`StructureMode::Dated`][dated] or [`StructureMode::DatedGrouped`][dated-grouped].
+    ///
+    /// [dated]: crate::render::template::StructureMode::Dated
+    /// [dated-grouped]: crate::render::template::StructureMode::DatedGrouped
+    DatedDirectory {
+        /// The annotation's book.
+        book: &'a BookContext<'a>,
+        /// The annotation.
+        annotation: &'a AnnotationContext<'a>,
+        /// The annotation's creation date.
+        date: DateContext,
+    },
 }
 
 impl<'a> NamesContext<'a> {
-    fn book(book: &'a BookContext<'a>, annotations: &'a [AnnotationContext<'a>]) -> Self {
+    /// Returns the context for a filename of a template with [`ContextMode::Book`][context-mode].
+    ///
+    /// # Arguments
+    ///
+    /// * `book` - The book.
+    /// * `annotations` - The book's annotations.
+    ///
+    /// [context-mode]: crate::render::template::ContextMode::Book
+    #[must_use]
+    pub fn book(book: &'a BookContext<'a>, annotations: &'a [AnnotationContext<'a>]) -> Self {
         Self::Book { book, annotations }
     }
 
-    fn annotation(book: &'a BookContext<'a>, annotation: &'a AnnotationContext<'a>) -> Self {
+    /// Returns the context for a filename of a template with
+    /// [`ContextMode::Annotation`][context-mode].
+    ///
+    /// # Arguments
+    ///
+    /// * `book` - The annotation's book.
+    /// * `annotation` - The annotation.
+    ///
+    /// [context-mode]: crate::render::template::ContextMode::Annotation
+    #[must_use]
+    pub fn annotation(book: &'a BookContext<'a>, annotation: &'a AnnotationContext<'a>) -> Self {
         Self::Annotation { book, annotation }
     }
 
-    fn directory(book: &'a BookContext<'a>) -> Self {
+    /// Returns the context for a filename of a template with [`ContextMode::Tag`][context-mode].
+    ///
+    /// # Arguments
+    ///
+    /// * `tag` - The tag along with its annotations.
+    ///
+    /// [context-mode]: crate::render::template::ContextMode::Tag
+    #[must_use]
+    pub fn tag(tag: &'a TagContext<'a>) -> Self {
+        Self::Tag { tag }
+    }
+
+    /// Returns the context for a filename of a template with
+    /// [`ContextMode::Bookmark`][context-mode].
+    ///
+    /// # Arguments
+    ///
+    /// * `audiobook` - The audiobook along with its bookmarks.
+    ///
+    /// [context-mode]: crate::render::template::ContextMode::Bookmark
+    #[must_use]
+    pub fn bookmark(audiobook: &'a AudiobookContext<'a>) -> Self {
+        Self::Bookmark {
+            book: &audiobook.book,
+            bookmarks: &audiobook.bookmarks,
+        }
+    }
+
+    /// Returns the context for a template's directory name.
+    ///
+    /// # Arguments
+    ///
+    /// * `book` - The book.
+    #[must_use]
+    pub fn directory(book: &'a BookContext<'a>) -> Self {
         Self::Directory { book }
     }
+
+    /// Returns the context for a template's dated directory name.
+    ///
+    /// # Arguments
+    ///
+    /// * `book` - The annotation's book.
+    /// * `annotation` - The annotation whose creation date is used.
+    #[must_use]
+    pub fn dated_directory(
+        book: &'a BookContext<'a>,
+        annotation: &'a AnnotationContext<'a>,
+    ) -> Self {
+        Self::DatedDirectory {
+            book,
+            annotation,
+            date: DateContext::from(&annotation.metadata.created),
+        }
+    }
+}
+
+/// A struct representing the zero-padded parts of an annotation's creation date.
+#[derive(Debug, Serialize)]
+pub struct DateContext {
+    /// The year e.g. `2024`.
+    year: String,
+
+    /// The month e.g. `05`.
+    month: String,
+
+    /// The day e.g. `09`.
+    day: String,
+}
+
+impl From<&DateTimeUtc> for DateContext {
+    fn from(date: &DateTimeUtc) -> Self {
+        Self {
+            year: date.format("%Y").to_string(),
+            month: date.format("%m").to_string(),
+            day: date.format("%d").to_string(),
+        }
+    }
 }
@@ -0,0 +1,186 @@
+//! Defines a type to persist render progress so that interrupted renders can be resumed.
+
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::result::Result;
+
+/// The name of the file render progress is saved to. This is placed inside the output directory.
+pub const PROGRESS_FILENAME: &str = ".readstor-progress.json";
+
+/// A struct representing which books have been completely rendered and written to disk.
+///
+/// Progress is saved after each book is completed and removed once the entire render finishes. If a
+/// render is interrupted, the saved progress can be loaded to skip the books that were already
+/// completed.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct RenderProgress {
+    /// The path to the progress file.
+    #[serde(skip)]
+    path: PathBuf,
+
+    /// The ids of the requested templates. Progress is only resumed if these match.
+    templates: Vec<String>,
+
+    /// The ids of the completed books.
+    completed: BTreeSet<String>,
+}
+
+impl RenderProgress {
+    /// Creates a new instance of [`RenderProgress`] without any completed books.
+    ///
+    /// # Arguments
+    ///
+    /// * `directory` - The output directory the progress file is saved to.
+    /// * `templates` - The ids of the requested templates.
+    #[must_use]
+    pub fn new(directory: &Path, templates: Vec<String>) -> Self {
+        Self {
+            path: directory.join(PROGRESS_FILENAME),
+            templates,
+            completed: BTreeSet::new(),
+        }
+    }
+
+    /// Loads previously saved progress.
+    ///
+    /// Returns a new instance without any completed books if no progress was saved or if it was
+    /// saved for a different set of templates.
+    ///
+    /// # Arguments
+    ///
+    /// * `directory` - The output directory the progress file was saved to.
+    /// * `templates` - The ids of the requested templates.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the progress file exists but cannot be read or parsed.
+    pub fn load(directory: &Path, templates: Vec<String>) -> Result<Self> {
+        let mut progress = Self::new(directory, templates);
+
+        if !progress.path.exists() {
+            return Ok(progress);
+        }
+
+        let saved: Self = serde_json::from_str(&std::fs::read_to_string(&progress.path)?)?;
+
+        if saved.templates == progress.templates {
+            progress.completed = saved.completed;
+        } else {
+            tracing::warn!("ignored render progress saved for a different set of templates");
+        }
+
+        Ok(progress)
+    }
+
+    /// Returns `true` if a book has already been completed.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The book's id.
+    #[must_use]
+    pub fn is_completed(&self, id: &str) -> bool {
+        self.completed.contains(id)
+    }
+
+    /// Returns the number of completed books.
+    #[must_use]
+    pub fn count_completed(&self) -> usize {
+        self.completed.len()
+    }
+
+    /// Marks a book as completed and saves the progress.
+    ///
+    /// The progress is written to a temporary file first and then renamed so that an interruption
+    /// never leaves a partially written progress file.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The book's id.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if any IO errors are encountered.
+    pub fn complete(&mut self, id: &str) -> Result<()> {
+        self.completed.insert(id.to_owned());
+
+        // The process id keeps concurrent renders to the same directory from clobbering each
+        // other's temporary file.
+        let temp = self
+            .path
+            .with_extension(format!("json.{}.tmp", std::process::id()));
+
+        std::fs::write(&temp, serde_json::to_string(self)?)?;
+        std::fs::rename(&temp, &self.path)?;
+
+        Ok(())
+    }
+
+    /// Removes the saved progress. This should be called once the entire render finishes.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if any IO errors are encountered.
+    pub fn finish(self) -> Result<()> {
+        match std::fs::remove_file(&self.path) {
+            Err(error) if error.kind() != std::io::ErrorKind::NotFound => Err(error.into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    use crate::defaults::TEMP_OUTPUT_DIRECTORY;
+
+    fn directory(name: &str) -> PathBuf {
+        let directory = TEMP_OUTPUT_DIRECTORY.join("tests-progress").join(name);
+
+        if directory.exists() {
+            std::fs::remove_dir_all(&directory).unwrap();
+        }
+
+        std::fs::create_dir_all(&directory).unwrap();
+
+        directory
+    }
+
+    // Tests that completed books are saved, loaded and removed once finished.
+    #[test]
+    fn save_and_load() {
+        let directory = directory("save-and-load");
+        let templates = vec!["template.md".to_string()];
+
+        let mut progress = RenderProgress::new(&directory, templates.clone());
+        progress.complete("01").unwrap();
+        progress.complete("02").unwrap();
+
+        let progress = RenderProgress::load(&directory, templates).unwrap();
+
+        assert!(progress.is_completed("01"));
+        assert!(progress.is_completed("02"));
+        assert!(!progress.is_completed("03"));
+
+        progress.finish().unwrap();
+
+        assert!(!directory.join(PROGRESS_FILENAME).exists());
+    }
+
+    // Tests that progress saved for different templates is ignored.
+    #[test]
+    fn different_templates() {
+        let directory = directory("different-templates");
+
+        let mut progress = RenderProgress::new(&directory, vec!["a.md".to_string()]);
+        progress.complete("01").unwrap();
+
+        let progress = RenderProgress::load(&directory, vec!["b.md".to_string()]).unwrap();
+
+        assert_eq!(progress.count_completed(), 0);
+    }
+}
@@ -1,22 +1,29 @@
 //! Defines types to build and manage templates.
 
-use std::collections::HashSet;
-use std::fs::File;
-use std::io::Write;
+use std::collections::{BTreeMap, HashSet};
 use std::path::{Path, PathBuf};
+use std::time::Duration;
 
+use chrono::Utc;
 use serde::Serialize;
 use walkdir::DirEntry;
 
+use crate::conflict::{ConflictMode, WriteSummary};
 use crate::contexts::annotation::AnnotationContext;
 use crate::contexts::book::BookContext;
+use crate::contexts::bookmark::{AudiobookContext, BookmarkContext};
 use crate::contexts::entry::EntryContext;
+use crate::contexts::tag::TagContext;
+use crate::models::bookmark::Audiobook;
+use crate::models::datetime::DateTimeUtc;
 use crate::models::entry::Entry;
 use crate::result::{Error, Result};
+use crate::strings;
 
+use super::defaults::{SINGLE_FILE_NAME, WRITE_PARALLEL_THRESHOLD, WRITE_THREADS_MAX};
 use super::engine::RenderEngine;
 use super::names::NamesRender;
-use super::template::{ContextMode, Render, StructureMode, Template, TemplatePartial};
+use super::template::{ContextMode, Render, StructureMode, Template, TemplatePartial, WriteMode};
 use super::utils;
 
 /// A struct providing a simple interface to build and render templates.
@@ -34,11 +41,19 @@ pub struct Renderer {
     /// A list of all registed partial templates.
     templates_partial: Vec<TemplatePartial>,
 
-    /// A list of all rendered templates.
-    renders: Vec<Render>,
+    /// A list of all rendered templates, grouped by the book they were rendered from. Renders that
+    /// weren't rendered from a single book e.g. tags are each placed in their own group.
+    renders: Vec<Vec<Render>>,
+
+    /// A list of all rendered books waiting to be combined into single files. Only used when
+    /// [`RenderOptions::single_file`] is set.
+    sections: Vec<Section>,
 
     /// An instance of [`RenderOptions`].
     options: RenderOptions,
+
+    /// The values injected into every template context alongside the book and annotations.
+    globals: TemplateGlobals,
 }
 
 impl Renderer {
@@ -54,9 +69,12 @@ impl Renderer {
     where
         O: Into<RenderOptions>,
     {
+        let options: RenderOptions = options.into();
+
         Self {
             template_default: default,
-            options: options.into(),
+            globals: TemplateGlobals::new(&options.env),
+            options,
             ..Default::default()
         }
     }
@@ -91,7 +109,13 @@ impl Renderer {
     }
 
     /// Iterates through all [`Template`]s and renders them based on their [`StructureMode`] and
-    /// [`ContextMode`]. See respective enums for more information.
+    /// [`ContextMode`]. See respective enums for more information. Books and annotations are
+    /// skipped per template based on its `skip-if` and `only-tags`. [`ContextMode::Tag`] and
+    /// [`ContextMode::Bookmark`] templates are skipped as they're rendered with
+    /// [`Renderer::render_tags()`] and [`Renderer::render_bookmarks()`] respectively.
+    ///
+    /// All [`Render`]s are kept until [`Renderer::clear_rendered()`] is called. See
+    /// [`Renderer::stream()`] to handle each one as it's produced instead.
     ///
     /// # Arguments
     ///
@@ -101,87 +125,495 @@ impl Renderer {
     ///
     /// Will return `Err` if any IO errors are encountered.
     pub fn render(&mut self, entry: &Entry) -> Result<()> {
+        if self.options.single_file {
+            return self.render_section(entry);
+        }
+
         let mut renders = Vec::with_capacity(self.templates.len());
 
-        let entry = EntryContext::from(entry);
+        self.stream(entry, |render| {
+            renders.push(render);
+            Ok(())
+        })?;
+
+        if !renders.is_empty() {
+            self.renders.push(renders);
+        }
+
+        Ok(())
+    }
+
+    /// Renders an [`Entry`] like [`Renderer::render()`] but passes each [`Render`] to a callback as
+    /// soon as it's produced instead of keeping it. This allows post-processing and writing files
+    /// one at a time so only a single render is held in memory. See [`Renderer::write_render()`].
+    ///
+    /// [`RenderOptions::single_file`] is ignored as combining books requires keeping every render.
+    ///
+    /// # Arguments
+    ///
+    /// * `entry` - The entry to be rendered.
+    /// * `f` - The callback receiving each render.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the template renderer encounters an error or if the callback returns an
+    /// error. Rendering stops at the first error.
+    pub fn stream<F>(&self, entry: &Entry, mut f: F) -> Result<()>
+    where
+        F: FnMut(Render) -> Result<()>,
+    {
+        let escaped = self.escape_markdown(entry);
+        let escaped = escaped.as_ref().map(EntryContext::from);
+        let unescaped = EntryContext::from(entry);
 
         for template in self.iter_requested_templates() {
-            let names = NamesRender::new(&entry, template)?;
+            if matches!(
+                template.context_mode,
+                ContextMode::Tag | ContextMode::Bookmark
+            ) {
+                continue;
+            }
+
+            let entry = match &escaped {
+                Some(escaped) if template.escapes_markdown(self.options.escape_markdown) => escaped,
+                _ => &unescaped,
+            };
+
+            let names = NamesRender::new(entry, template)?;
+
+            // -> [output-directory]/[template-output-directory?]
+            let root = template.root_directory();
 
             // Builds a the template's output path, relative to the [output-directory].
             let path = match template.structure_mode {
                 StructureMode::Flat => {
                     // -> [output-directory]
-                    PathBuf::new()
+                    root
                 }
                 StructureMode::FlatGrouped => {
                     // -> [output-directory]/[template-group]
-                    PathBuf::from(&template.group)
+                    root.join(&template.group)
                 }
                 StructureMode::Nested => {
                     // -> [output-directory]/[author-title]
-                    PathBuf::from(&names.directory)
+                    root.join(&names.directory)
                 }
                 StructureMode::NestedGrouped => {
                     // -> [output-directory]/[template-group]/[author-title]
-                    PathBuf::from(&template.group).join(&names.directory)
+                    root.join(&template.group).join(&names.directory)
+                }
+                StructureMode::Dated => {
+                    // -> [output-directory]
+                    //
+                    // The [dated-directory] is appended per annotation.
+                    root
+                }
+                StructureMode::DatedGrouped => {
+                    // -> [output-directory]/[template-group]
+                    //
+                    // The [dated-directory] is appended per annotation.
+                    root.join(&template.group)
                 }
             };
 
             match template.context_mode {
                 ContextMode::Book => {
-                    renders.push(self.render_book(template, &entry, &names, &path)?);
+                    if self.is_book_skipped(template, entry, &names)? {
+                        continue;
+                    }
+
+                    f(self.render_book(template, entry, &names, &path)?)?;
                 }
                 ContextMode::Annotation => {
-                    renders.extend(self.render_annotations(template, &entry, &names, &path)?);
+                    self.render_annotations(template, entry, &names, &path, &mut f)?;
+                }
+                ContextMode::Tag | ContextMode::Bookmark => {
+                    unreachable!("tag and bookmark templates are skipped above")
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Renders all requested [`ContextMode::Tag`] templates once per `#tag` found in a set of
+    /// entries. Each render contains all the annotations with the tag across the entries. Tags are
+    /// skipped per template based on its `skip-if` and `only-tags`.
+    ///
+    /// Unlike [`Renderer::render()`], this requires all entries at once and should be called after
+    /// they've been rendered. Has no effect when [`RenderOptions::single_file`] is set.
+    ///
+    /// # Arguments
+    ///
+    /// * `entries` - The entries to collect the tags from.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the template renderer encounters an error.
+    pub fn render_tags<'a, I>(&mut self, entries: I) -> Result<()>
+    where
+        I: IntoIterator<Item = &'a Entry>,
+    {
+        if self.options.single_file {
+            return Ok(());
+        }
+
+        let mut renders = Vec::new();
+
+        self.stream_tags(entries, |render| {
+            renders.push(render);
+            Ok(())
+        })?;
+
+        self.renders
+            .extend(renders.into_iter().map(|render| vec![render]));
+
+        Ok(())
+    }
+
+    /// Renders all requested [`ContextMode::Tag`] templates like [`Renderer::render_tags()`] but
+    /// passes each [`Render`] to a callback as soon as it's produced instead of keeping it. See
+    /// [`Renderer::stream()`] for more information.
+    ///
+    /// # Arguments
+    ///
+    /// * `entries` - The entries to collect the tags from.
+    /// * `f` - The callback receiving each render.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the template renderer encounters an error or if the callback returns an
+    /// error. Rendering stops at the first error.
+    pub fn stream_tags<'a, I, F>(&self, entries: I, mut f: F) -> Result<()>
+    where
+        I: IntoIterator<Item = &'a Entry>,
+        F: FnMut(Render) -> Result<()>,
+    {
+        let templates: Vec<&Template> = self
+            .iter_requested_templates()
+            .filter(|template| matches!(template.context_mode, ContextMode::Tag))
+            .collect();
+
+        if templates.is_empty() {
+            return Ok(());
+        }
+
+        let entries: Vec<EntryContext<'_>> = entries.into_iter().map(EntryContext::from).collect();
+        let tags = TagContext::from_entries(&entries);
+
+        for template in templates {
+            // -> [output-directory]/[template-output-directory?]
+            let root = template.root_directory();
+
+            let path = match template.structure_mode {
+                // -> [output-directory]/[template-group]
+                StructureMode::FlatGrouped => root.join(&template.group),
+                // -> [output-directory]
+                _ => root,
+            };
+
+            for tag in &tags {
+                if let Some(render) = self.render_tag(template, tag, &path)? {
+                    f(render)?;
                 }
             }
         }
 
-        self.renders.extend(renders);
+        Ok(())
+    }
+
+    /// Renders all requested [`ContextMode::Bookmark`] templates once per audiobook. Each render
+    /// contains the audiobook and all its bookmarks. Audiobooks are skipped per template based on
+    /// its `skip-if`.
+    ///
+    /// Has no effect when [`RenderOptions::single_file`] is set.
+    ///
+    /// # Arguments
+    ///
+    /// * `audiobooks` - The audiobooks to render.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the template renderer encounters an error.
+    pub fn render_bookmarks<'a, I>(&mut self, audiobooks: I) -> Result<()>
+    where
+        I: IntoIterator<Item = &'a Audiobook>,
+    {
+        if self.options.single_file {
+            return Ok(());
+        }
+
+        let mut renders = Vec::new();
+
+        self.stream_bookmarks(audiobooks, |render| {
+            renders.push(render);
+            Ok(())
+        })?;
+
+        self.renders
+            .extend(renders.into_iter().map(|render| vec![render]));
+
+        Ok(())
+    }
+
+    /// Renders all requested [`ContextMode::Bookmark`] templates like
+    /// [`Renderer::render_bookmarks()`] but passes each [`Render`] to a callback as soon as it's
+    /// produced instead of keeping it. See [`Renderer::stream()`] for more information.
+    ///
+    /// # Arguments
+    ///
+    /// * `audiobooks` - The audiobooks to render.
+    /// * `f` - The callback receiving each render.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the template renderer encounters an error or if the callback returns an
+    /// error. Rendering stops at the first error.
+    pub fn stream_bookmarks<'a, I, F>(&self, audiobooks: I, mut f: F) -> Result<()>
+    where
+        I: IntoIterator<Item = &'a Audiobook>,
+        F: FnMut(Render) -> Result<()>,
+    {
+        let templates: Vec<&Template> = self
+            .iter_requested_templates()
+            .filter(|template| matches!(template.context_mode, ContextMode::Bookmark))
+            .collect();
+
+        if templates.is_empty() {
+            return Ok(());
+        }
+
+        let audiobooks: Vec<AudiobookContext<'_>> =
+            audiobooks.into_iter().map(AudiobookContext::from).collect();
+
+        for template in templates {
+            for audiobook in &audiobooks {
+                if let Some(render) = self.render_audiobook(template, audiobook)? {
+                    f(render)?;
+                }
+            }
+        }
 
         Ok(())
     }
 
-    /// Iterates through all [`Render`]s and writes them to disk.
+    /// Iterates through all [`Render`]s and writes them to disk. Returns how many files were
+    /// written or skipped.
+    ///
+    /// If [`RenderOptions::managed_regions`] is set, each file is written inside a managed region
+    /// so edits made outside of it are kept across renders.
+    ///
+    /// Each output directory is created once before any files are written. Appended and prepended
+    /// annotations are written first on the current thread as several can share a file. If there
+    /// are more than [`WRITE_PARALLEL_THRESHOLD`] remaining files to write, each book's files are
+    /// kept together and the books are split into batches written in parallel by at most
+    /// [`WRITE_THREADS_MAX`] threads.
     ///
     /// # Arguments
     ///
-    /// * `path` - The path to the write the rendered templates to. Each rendered template's path is
-    ///   appened to this path to determine its full path.
+    /// * `path` - The path to the write the rendered templates to. Each rendered template's path,
+    ///   which includes its template's `output-directory` if set, is appened to this path to
+    ///   determine its full path.
     ///
     /// # Errors
     ///
     /// Will return `Err` if any IO errors are encountered.
-    pub fn write(&self, path: &Path) -> Result<()> {
-        for render in &self.renders {
+    pub fn write(&self, path: &Path) -> Result<WriteSummary> {
+        let directories: HashSet<&PathBuf> = self
+            .templates_rendered()
+            .map(|render| &render.path)
+            .collect();
+
+        for directory in directories {
             // -> [output-directory]/[template-subdirectory]
-            let root = path.join(&render.path);
+            std::fs::create_dir_all(path.join(directory))?;
+        }
 
-            std::fs::create_dir_all(&root)?;
+        let mut appended = Vec::new();
+        let mut books = Vec::with_capacity(self.renders.len());
 
-            // -> [output-directory]/[template-subdirectory]/[template-filename]
-            let file = root.join(&render.filename);
+        for renders in &self.renders {
+            let (book_appended, files): (Vec<_>, Vec<(PathBuf, &Render)>) = renders
+                .iter()
+                .map(|render| {
+                    // -> [output-directory]/[template-subdirectory]/[template-filename]
+                    let file = path.join(&render.path).join(&render.filename);
+                    (file, render)
+                })
+                .partition(|(_, render)| render.append_id().is_some());
 
-            if !self.options.overwrite_existing && file.exists() {
-                log::debug!("skipped writing {}", file.display());
-            } else {
-                let mut file = File::create(file)?;
-                write!(file, "{}", &render.contents)?;
+            appended.extend(book_appended);
+
+            if !files.is_empty() {
+                books.push(files);
             }
         }
 
-        Ok(())
+        let on_conflict = self.options.on_conflict;
+        let managed_regions = self.options.managed_regions;
+
+        let mut summary = utils::write_files(&appended, on_conflict, managed_regions)?;
+
+        let count: usize = books.iter().map(Vec::len).sum();
+
+        if count <= WRITE_PARALLEL_THRESHOLD {
+            for files in &books {
+                summary.merge(utils::write_files(files, on_conflict, managed_regions)?);
+            }
+
+            return Ok(summary);
+        }
+
+        let threads = std::thread::available_parallelism()
+            .map_or(1, std::num::NonZeroUsize::get)
+            .min(WRITE_THREADS_MAX);
+
+        let batches = utils::batch_groups(books, count.div_ceil(threads));
+
+        let written = std::thread::scope(|scope| {
+            let handles: Vec<_> = batches
+                .iter()
+                .map(|batch| {
+                    scope.spawn(move || utils::write_files(batch, on_conflict, managed_regions))
+                })
+                .collect();
+
+            handles
+                .into_iter()
+                .try_fold(WriteSummary::default(), |mut summary, handle| {
+                    summary.merge(
+                        handle
+                            .join()
+                            .unwrap_or_else(|error| std::panic::resume_unwind(error))?,
+                    );
+
+                    Ok::<_, std::io::Error>(summary)
+                })
+        })?;
+
+        summary.merge(written);
+
+        Ok(summary)
+    }
+
+    /// Writes a single [`Render`] to disk. This is meant to be used with [`Renderer::stream()`]
+    /// to write each file as soon as it's rendered. Returns whether the file was written or
+    /// skipped. See [`Renderer::write()`] for more information.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path to the write the rendered template to. The render's path is appended
+    ///   to this path to determine its full path.
+    /// * `render` - The render to write.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if any IO errors are encountered.
+    pub fn write_render(&self, path: &Path, render: &Render) -> Result<WriteSummary> {
+        // -> [output-directory]/[template-subdirectory]
+        let directory = path.join(&render.path);
+
+        std::fs::create_dir_all(&directory)?;
+
+        // -> [output-directory]/[template-subdirectory]/[template-filename]
+        let file = directory.join(&render.filename);
+
+        Ok(utils::write_files(
+            &[(file, render)],
+            self.options.on_conflict,
+            self.options.managed_regions,
+        )?)
+    }
+
+    /// Combines all books rendered since the last call into a single [`Render`] per requested
+    /// [`ContextMode::Book`] template. Only has an effect when [`RenderOptions::single_file`] is
+    /// set.
+    ///
+    /// Each file starts with a table of contents listing the books in the order they were rendered,
+    /// followed by each book's render. Markdown files link each entry in the table of contents to
+    /// its book.
+    ///
+    /// The file is named `library.[extension]` and placed in the template-group's directory if the
+    /// template's [`StructureMode`] is grouped. If multiple templates would write to the same file,
+    /// the template's file stem is appended e.g. `library-basic.md`.
+    pub fn combine(&mut self) {
+        if self.sections.is_empty() {
+            return;
+        }
+
+        let templates: Vec<&Template> = self
+            .iter_requested_templates()
+            .filter(|template| matches!(template.context_mode, ContextMode::Book))
+            .collect();
+
+        if templates.is_empty() {
+            tracing::warn!("no book templates were requested to render into a single file");
+        }
+
+        let mut files = HashSet::new();
+        let mut renders = Vec::with_capacity(templates.len());
+
+        for (index, template) in templates.into_iter().enumerate() {
+            // -> [output-directory]/[template-output-directory?]/[template-group?]
+            let root = template.root_directory();
+
+            let path = match template.structure_mode {
+                StructureMode::FlatGrouped | StructureMode::NestedGrouped => {
+                    root.join(&template.group)
+                }
+                _ => root,
+            };
+
+            let mut filename = format!("{SINGLE_FILE_NAME}.{}", template.extension);
+
+            if !files.insert(path.join(&filename)) {
+                let stem = Path::new(&template.id)
+                    .file_stem()
+                    .map(|stem| crate::strings::to_slug(&stem.to_string_lossy(), true))
+                    .unwrap_or_default();
+
+                filename = format!("{SINGLE_FILE_NAME}-{stem}.{}", template.extension);
+                files.insert(path.join(&filename));
+            }
+
+            let contents = Section::combine(&self.sections, index, &template.extension);
+
+            renders.push(Render::new(path, filename, contents).with_output_of(template));
+        }
+
+        self.sections.clear();
+        self.renders
+            .extend(renders.into_iter().map(|render| vec![render]));
+    }
+
+    /// Removes all [`Render`]s. This allows rendering and writing entries one at a time.
+    pub fn clear_rendered(&mut self) {
+        self.renders.clear();
+        self.sections.clear();
+    }
+
+    /// Returns the ids of all requested [`Template`]s.
+    #[must_use]
+    pub fn requested_template_ids(&self) -> Vec<String> {
+        self.iter_requested_templates()
+            .map(|template| template.id.clone())
+            .collect()
+    }
+
+    /// Returns all registered [`Template`]s, including those that weren't requested.
+    #[must_use]
+    pub fn templates(&self) -> &[Template] {
+        &self.templates
     }
 
     /// Returns an iterator over all [`Render`]s.
     pub fn templates_rendered(&self) -> impl Iterator<Item = &Render> {
-        self.renders.iter()
+        self.renders.iter().flatten()
     }
 
     /// Returns a mutable iterator over all [`Render`]s.
     pub fn templates_rendered_mut(&mut self) -> impl Iterator<Item = &mut Render> {
-        self.renders.iter_mut()
+        self.renders.iter_mut().flatten()
     }
 
     /// Returns the number of [`Template`]s.
@@ -193,7 +625,7 @@ impl Renderer {
     /// Returns the number of [`Render`]s.
     #[must_use]
     pub fn count_templates_rendered(&self) -> usize {
-        self.renders.len()
+        self.renders.iter().map(Vec::len).sum()
     }
 
     /// Validates that all requested template-groups exist.
@@ -270,9 +702,7 @@ impl Renderer {
             // -->                    nested/template.md
             //
             // This is used to uniquely identify each template.
-            //
-            // This unwrap is safe seeing as both `item` and `path` should both be absolute paths.
-            let path = pathdiff::diff_paths(&item, path).unwrap();
+            let path = Self::relative_template_path(&item, path)?;
 
             let template = std::fs::read_to_string(&item)?;
             let template = TemplatePartial::new(&path, &template);
@@ -282,20 +712,17 @@ impl Renderer {
 
             self.templates_partial.push(template);
 
-            log::debug!("added partial template: {}", path.display());
+            tracing::debug!(path = %path.display(), "added partial template");
         }
 
         for item in Self::iter_templates_directory(&path, TemplateKind::Normal) {
             // See above.
-            //
-            // This unwrap is safe seeing as both `item` and `path` should both be absolute paths.
-            let path = pathdiff::diff_paths(&item, path).unwrap();
+            let path = Self::relative_template_path(&item, path)?;
 
             let template = std::fs::read_to_string(&item)?;
             let template = Template::new(&path, &template)?;
 
-            self.engine
-                .register_template(&template.id, &template.contents)?;
+            self.register_template(&template)?;
 
             // Templates are validated *after* being registered. The registry handles building
             // template inheritances. We need to register the templates before validating them so
@@ -304,17 +731,17 @@ impl Renderer {
 
             self.templates.push(template);
 
-            log::debug!("added template: {}", path.display());
+            tracing::debug!(path = %path.display(), "added template");
         }
 
-        log::debug!("registed partial templates: {:#?}", self.templates_partial);
-        log::debug!("registed templates: {:#?}", self.templates);
+        tracing::debug!("registed partial templates: {:#?}", self.templates_partial);
+        tracing::debug!("registed templates: {:#?}", self.templates);
 
-        log::debug!(
-            "built {} template(s) and {} partial template(s) from {}",
-            self.templates.len(),
-            self.templates_partial.len(),
-            path.display()
+        tracing::debug!(
+            templates = self.templates.len(),
+            partials = self.templates_partial.len(),
+            path = %path.display(),
+            "built templates"
         );
 
         Ok(())
@@ -324,12 +751,31 @@ impl Renderer {
     fn build_default(&mut self) -> Result<()> {
         let template = Template::new("__default", &self.template_default)?;
 
-        self.engine
-            .register_template(&template.id, &template.contents)?;
+        self.register_template(&template)?;
 
         self.templates.push(template);
 
-        log::debug!("built the default template");
+        tracing::debug!("built the default template");
+
+        Ok(())
+    }
+
+    /// Registers a [`Template`] and its `skip-if` expression, if any, into the engine.
+    ///
+    /// # Arguments
+    ///
+    /// * `template` - The template to register.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the template or its `skip-if` expression contain any errors.
+    fn register_template(&mut self, template: &Template) -> Result<()> {
+        self.engine
+            .register_template(&template.id, &template.contents)?;
+
+        if let Some((id, contents)) = template.skip_if_template() {
+            self.engine.register_template(&id, &contents)?;
+        }
 
         Ok(())
     }
@@ -355,86 +801,421 @@ impl Renderer {
 
         match template.context_mode {
             ContextMode::Book => {
-                let context = TemplateContext::book(&entry.book, &entry.annotations, &names);
+                let context =
+                    TemplateContext::book(&entry.book, &entry.annotations, &names, &self.globals);
+
+                self.render_template(template, context)?;
 
-                self.engine.render(&template.id, context)?;
+                let context =
+                    TemplateContext::book(&entry.book, &entry.annotations, &names, &self.globals);
+
+                self.is_skipped(template, context)?;
             }
             ContextMode::Annotation => {
                 // This should be safe as a dummy `Entry` contains three annotations.
                 let annotation = &entry.annotations[0];
-                let context = TemplateContext::annotation(&entry.book, annotation, &names);
+                let context =
+                    TemplateContext::annotation(&entry.book, annotation, &names, &self.globals);
+
+                self.render_template(template, context)?;
+
+                let context =
+                    TemplateContext::annotation(&entry.book, annotation, &names, &self.globals);
+
+                self.is_skipped(template, context)?;
+            }
+            ContextMode::Tag => {
+                let mut tag = TagContext::new("#tag");
+
+                for annotation in &entry.annotations {
+                    tag.push(&entry.book, annotation);
+                }
+
+                NamesRender::render_tag_filename(&tag, template)?;
+
+                self.render_template(template, TemplateContext::tag(&tag, &self.globals))?;
+                self.is_skipped(template, TemplateContext::tag(&tag, &self.globals))?;
+            }
+            ContextMode::Bookmark => {
+                let audiobook = Audiobook::dummy();
+                let audiobook = AudiobookContext::from(&audiobook);
+
+                NamesRender::render_bookmark_filename(&audiobook, template)?;
+                NamesRender::render_bookmark_directory_name(&audiobook, template)?;
+
+                let context = TemplateContext::bookmark(&audiobook, &self.globals);
+                self.render_template(template, context)?;
 
-                self.engine.render(&template.id, context)?;
+                let context = TemplateContext::bookmark(&audiobook, &self.globals);
+                self.is_skipped(template, context)?;
             }
         };
 
         Ok(())
     }
 
-    /// Renders an [`Entry`]'s [`Book`][book] to a single [`Render`].
+    /// Renders a template with a context, enforcing [`RenderOptions::max_render_time`] and
+    /// [`RenderOptions::max_render_size`] if they're set.
     ///
     /// # Arguments
     ///
     /// * `template` - The template to render.
-    /// * `entry` - The context to inject into the template.
-    /// * `names` - The names to inject into the template context.
-    /// * `path` - The path to where the template will be written to. This path should be relative
-    ///   to the final output directory.
+    /// * `context` - The context to inject into the template.
     ///
     /// # Errors
     ///
-    /// Will return `Err` if the template renderer encounters an error.
+    /// Will return `Err` if:
+    /// * The template renderer encounters an error.
+    /// * The render takes longer than the maximum render time.
+    /// * The render's output is larger than the maximum render size.
+    fn render_template(&self, template: &Template, context: TemplateContext<'_>) -> Result<String> {
+        match (self.options.max_render_time, self.options.max_render_size) {
+            (None, None) => self.engine.render(&template.id, context),
+            (timeout, max_size) => {
+                self.engine
+                    .render_with_limits(&template.id, context, timeout, max_size)
+            }
+        }
+    }
+
+    /// Returns a copy of an [`Entry`] with the Markdown-significant characters in its annotation
+    /// bodies escaped. Returns `None` if none of the requested templates escape Markdown. See
+    /// [`Template::escapes_markdown()`] for more information.
     ///
-    /// [book]: crate::models::book::Book
-    fn render_book(
-        &self,
-        template: &Template,
-        entry: &EntryContext<'_>,
-        names: &NamesRender,
-        path: &Path,
-    ) -> Result<Render> {
-        let filename = names.book.clone();
-        let context = TemplateContext::book(&entry.book, &entry.annotations, names);
-        let string = self.engine.render(&template.id, context)?;
-        let render = Render::new(path.to_owned(), filename, string);
+    /// # Arguments
+    ///
+    /// * `entry` - The entry to escape.
+    fn escape_markdown(&self, entry: &Entry) -> Option<Entry> {
+        let is_escaped = self
+            .iter_requested_templates()
+            .any(|template| template.escapes_markdown(self.options.escape_markdown));
+
+        if !is_escaped {
+            return None;
+        }
 
-        Ok(render)
+        let mut entry = entry.clone();
+
+        for annotation in &mut entry.annotations {
+            annotation.body = strings::escape_markdown(&annotation.body);
+        }
+
+        Some(entry)
     }
 
-    /// Renders an [`Entry`]'s [`Annotation`][annotation]s to multiple [`Render`]s.
+    /// Returns `true` if a template's `skip-if` expression is true for a context. Returns `false`
+    /// if the template has no `skip-if`.
     ///
     /// # Arguments
     ///
-    /// * `template` - The template to render.
-    /// * `entry` - The context to inject into the template.
-    /// * `names` - The names to inject into the template context.
-    /// * `path` - The path to where the template will be written to. This path should be relative
-    ///   to the final output directory.
+    /// * `template` - The template to check.
+    /// * `context` - The context to evaluate the expression with.
     ///
     /// # Errors
     ///
     /// Will return `Err` if the template renderer encounters an error.
-    ///
-    /// [annotation]: crate::models::annotation::Annotation
-    fn render_annotations(
-        &self,
-        template: &Template,
-        entry: &EntryContext<'_>,
-        names: &NamesRender,
-        path: &Path,
-    ) -> Result<Vec<Render>> {
-        let mut renders = Vec::with_capacity(entry.annotations.len());
+    fn is_skipped(&self, template: &Template, context: TemplateContext<'_>) -> Result<bool> {
+        let Some((id, _)) = template.skip_if_template() else {
+            return Ok(false);
+        };
 
+        Ok(self.engine.render(&id, context)? == "true")
+    }
+
+    /// Returns `true` if a [`ContextMode::Book`] template should be skipped for an entry, either
+    /// because none of its annotations include the template's `only-tags` or because the template's
+    /// `skip-if` expression is true.
+    ///
+    /// # Arguments
+    ///
+    /// * `template` - The template to check.
+    /// * `entry` - The context to check.
+    /// * `names` - The names to inject into the template context.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the template renderer encounters an error.
+    fn is_book_skipped(
+        &self,
+        template: &Template,
+        entry: &EntryContext<'_>,
+        names: &NamesRender,
+    ) -> Result<bool> {
+        let is_tagged = template.only_tags.is_empty()
+            || entry
+                .annotations
+                .iter()
+                .any(|annotation| template.includes_tags(annotation.tags));
+
+        if !is_tagged {
+            return Ok(true);
+        }
+
+        let context = TemplateContext::book(&entry.book, &entry.annotations, names, &self.globals);
+
+        self.is_skipped(template, context)
+    }
+
+    /// Renders an [`Entry`] with all requested [`ContextMode::Book`] templates into a [`Section`]
+    /// to be combined later. See [`Renderer::combine()`] for more information.
+    ///
+    /// # Arguments
+    ///
+    /// * `entry` - The entry to be rendered.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the template renderer encounters an error.
+    fn render_section(&mut self, entry: &Entry) -> Result<()> {
+        let escaped = self.escape_markdown(entry);
+        let escaped = escaped.as_ref().map(EntryContext::from);
+        let unescaped = EntryContext::from(entry);
+
+        let mut contents = Vec::with_capacity(self.templates.len());
+
+        for template in self.iter_requested_templates() {
+            if !matches!(template.context_mode, ContextMode::Book) {
+                continue;
+            }
+
+            let entry = match &escaped {
+                Some(escaped) if template.escapes_markdown(self.options.escape_markdown) => escaped,
+                _ => &unescaped,
+            };
+
+            let names = NamesRender::new(entry, template)?;
+
+            if self.is_book_skipped(template, entry, &names)? {
+                contents.push(None);
+                continue;
+            }
+
+            let context =
+                TemplateContext::book(&entry.book, &entry.annotations, &names, &self.globals);
+
+            contents.push(Some(self.render_template(template, context)?));
+        }
+
+        self.sections.push(Section {
+            title: format!("{} - {}", unescaped.book.author, unescaped.book.title),
+            anchor: format!(
+                "{}-{}",
+                unescaped.book.slugs.author, unescaped.book.slugs.title
+            ),
+            contents,
+        });
+
+        Ok(())
+    }
+
+    /// Renders an [`Entry`]'s [`Book`][book] to a single [`Render`].
+    ///
+    /// # Arguments
+    ///
+    /// * `template` - The template to render.
+    /// * `entry` - The context to inject into the template.
+    /// * `names` - The names to inject into the template context.
+    /// * `path` - The path to where the template will be written to. This path should be relative
+    ///   to the final output directory.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the template renderer encounters an error.
+    ///
+    /// [book]: crate::models::book::Book
+    fn render_book(
+        &self,
+        template: &Template,
+        entry: &EntryContext<'_>,
+        names: &NamesRender,
+        path: &Path,
+    ) -> Result<Render> {
+        let filename = names.book.clone();
+        let context = TemplateContext::book(&entry.book, &entry.annotations, names, &self.globals);
+        let string = self.render_template(template, context)?;
+        let render = Render::new(path.to_owned(), filename, string).with_output_of(template);
+
+        Ok(render)
+    }
+
+    /// Renders an [`Entry`]'s [`Annotation`][annotation]s to multiple [`Render`]s, passing each to
+    /// a callback.
+    ///
+    /// # Arguments
+    ///
+    /// * `template` - The template to render.
+    /// * `entry` - The context to inject into the template.
+    /// * `names` - The names to inject into the template context.
+    /// * `path` - The path to where the template will be written to. This path should be relative
+    ///   to the final output directory.
+    /// * `f` - The callback receiving each render.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the template renderer encounters an error or if the callback returns an
+    /// error.
+    ///
+    /// [annotation]: crate::models::annotation::Annotation
+    fn render_annotations<F>(
+        &self,
+        template: &Template,
+        entry: &EntryContext<'_>,
+        names: &NamesRender,
+        path: &Path,
+        f: &mut F,
+    ) -> Result<()>
+    where
+        F: FnMut(Render) -> Result<()>,
+    {
         for annotation in &entry.annotations {
+            if !template.includes_tags(annotation.tags) {
+                continue;
+            }
+
+            let context =
+                TemplateContext::annotation(&entry.book, annotation, names, &self.globals);
+
+            if self.is_skipped(template, context)? {
+                continue;
+            }
+
+            let context =
+                TemplateContext::annotation(&entry.book, annotation, names, &self.globals);
+            let string = self.render_template(template, context)?;
+
+            // Appended and prepended annotations share a single file per book.
+            if template.write_mode != WriteMode::Replace {
+                let render = Render::new(path.to_owned(), names.book.clone(), string)
+                    .with_output_of(template)
+                    .with_annotation_id(&annotation.metadata.id);
+
+                f(render)?;
+                continue;
+            }
+
             let filename = names.get_annotation_filename(&annotation.metadata.id);
-            let context = TemplateContext::annotation(&entry.book, annotation, names);
-            let string = self.engine.render(&template.id, context)?;
-            let render = Render::new(path.to_owned(), filename, string);
 
-            renders.push(render);
+            let path = match template.structure_mode {
+                // -> [output-directory]/[template-group?]/[dated-directory]
+                StructureMode::Dated | StructureMode::DatedGrouped => {
+                    path.join(names.get_annotation_dated_directory(&annotation.metadata.id))
+                }
+                _ => path.to_owned(),
+            };
+
+            f(Render::new(path, filename, string).with_output_of(template))?;
+        }
+
+        Ok(())
+    }
+
+    /// Renders a `#tag` and its annotations to a single [`Render`]. Returns `None` if the tag is
+    /// skipped, either because it isn't one of the template's `only-tags` or because the
+    /// template's `skip-if` expression is true.
+    ///
+    /// # Arguments
+    ///
+    /// * `template` - The template to render.
+    /// * `tag` - The context to inject into the template.
+    /// * `path` - The path to where the template will be written to. This path should be relative
+    ///   to the final output directory.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the template renderer encounters an error.
+    fn render_tag(
+        &self,
+        template: &Template,
+        tag: &TagContext<'_>,
+        path: &Path,
+    ) -> Result<Option<Render>> {
+        if !template.only_tags.is_empty() && !template.only_tags.contains(tag.name) {
+            return Ok(None);
+        }
+
+        if self.is_skipped(template, TemplateContext::tag(tag, &self.globals))? {
+            return Ok(None);
         }
 
-        Ok(renders)
+        let filename = NamesRender::render_tag_filename(tag, template)?;
+        let string = self.render_template(template, TemplateContext::tag(tag, &self.globals))?;
+        let render = Render::new(path.to_owned(), filename, string).with_output_of(template);
+
+        Ok(Some(render))
+    }
+
+    /// Renders an audiobook and its bookmarks to a single [`Render`]. Returns `None` if the
+    /// template's `skip-if` expression is true.
+    ///
+    /// # Arguments
+    ///
+    /// * `template` - The template to render.
+    /// * `audiobook` - The context to inject into the template.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the template renderer encounters an error.
+    fn render_audiobook(
+        &self,
+        template: &Template,
+        audiobook: &AudiobookContext<'_>,
+    ) -> Result<Option<Render>> {
+        if self.is_skipped(
+            template,
+            TemplateContext::bookmark(audiobook, &self.globals),
+        )? {
+            return Ok(None);
+        }
+
+        // -> [output-directory]/[template-output-directory?]
+        let root = template.root_directory();
+
+        // Builds a the template's output path, relative to the [output-directory]. Dated structure
+        // modes are rejected when the template is created.
+        let path = match template.structure_mode {
+            // -> [output-directory]/[template-group]
+            StructureMode::FlatGrouped => root.join(&template.group),
+            // -> [output-directory]/[author-title]
+            StructureMode::Nested => root.join(NamesRender::render_bookmark_directory_name(
+                audiobook, template,
+            )?),
+            // -> [output-directory]/[template-group]/[author-title]
+            StructureMode::NestedGrouped => {
+                root.join(&template.group)
+                    .join(NamesRender::render_bookmark_directory_name(
+                        audiobook, template,
+                    )?)
+            }
+            // -> [output-directory]
+            _ => root,
+        };
+
+        let filename = NamesRender::render_bookmark_filename(audiobook, template)?;
+        let string = self.render_template(
+            template,
+            TemplateContext::bookmark(audiobook, &self.globals),
+        )?;
+        let render = Render::new(path, filename, string).with_output_of(template);
+
+        Ok(Some(render))
+    }
+
+    /// Returns the path to a template relative to the root templates directory.
+    ///
+    /// # Arguments
+    ///
+    /// * `item` - The path to the template.
+    /// * `root` - The path to the root templates directory.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the template's path cannot be made relative to the root templates
+    /// directory.
+    fn relative_template_path(item: &Path, root: &Path) -> Result<PathBuf> {
+        pathdiff::diff_paths(item, root).ok_or_else(|| Error::TemplateInvalidPath {
+            path: item.display().to_string(),
+            directory: root.display().to_string(),
+        })
     }
 
     /// Returns an iterator over all template-like files in a directory.
@@ -476,8 +1257,137 @@ pub struct RenderOptions {
     /// are considered 'requested' templates and are set to be rendered.
     pub template_groups: Vec<String>,
 
-    /// Toggles whether or not to overwrite existing files.
-    pub overwrite_existing: bool,
+    /// What to do when a rendered file already exists. See [`ConflictMode`] for more information.
+    pub on_conflict: ConflictMode,
+
+    /// Toggles wrapping rendered files in managed region markers. When re-rendering into a file
+    /// with a managed region, only the region is replaced. See [`managed`][managed] for more
+    /// information.
+    ///
+    /// [managed]: super::managed
+    pub managed_regions: bool,
+
+    /// Toggles rendering all books into a single file per [`ContextMode::Book`] template instead
+    /// of one file per book. [`ContextMode::Annotation`], [`ContextMode::Tag`] and
+    /// [`ContextMode::Bookmark`] templates are skipped. See [`Renderer::combine()`] for more
+    /// information.
+    pub single_file: bool,
+
+    /// The maximum time a single template may take to render. Guards against templates that
+    /// accidentally loop for too long. Each render is moved onto its own thread when this is set.
+    /// See [`RenderEngine::render_with_limits()`] for more information.
+    pub max_render_time: Option<Duration>,
+
+    /// The maximum size of a single template's output in bytes. The output is checked as it's
+    /// rendered.
+    pub max_render_size: Option<usize>,
+
+    /// The names of the environment variables exposed to templates under `env`. See
+    /// [`TemplateGlobals`] for more information.
+    pub env: Vec<String>,
+
+    /// Toggles escaping Markdown-significant characters in annotation bodies for templates with
+    /// an `md` or `markdown` extension. Templates can override this with `escape-markdown`. See
+    /// [`Template::escapes_markdown()`] for more information.
+    pub escape_markdown: bool,
+}
+
+/// A struct representing the values injected into every template context.
+#[derive(Debug, Default, Serialize)]
+pub struct TemplateGlobals {
+    /// The date and time the render started. This is the same for every template so all files
+    /// from a single render share it.
+    pub now: DateTimeUtc,
+
+    /// The whitelisted environment variables. Whitelisted variables that aren't set are empty
+    /// strings so templates referencing them still validate. Environment variables that aren't
+    /// whitelisted are never exposed.
+    pub env: BTreeMap<String, String>,
+}
+
+impl TemplateGlobals {
+    /// Returns a new instance of [`TemplateGlobals`], reading the whitelisted environment
+    /// variables.
+    ///
+    /// # Arguments
+    ///
+    /// * `env` - The names of the environment variables to expose.
+    #[must_use]
+    pub fn new(env: &[String]) -> Self {
+        let env = env
+            .iter()
+            .map(|name| (name.clone(), std::env::var(name).unwrap_or_default()))
+            .collect();
+
+        Self {
+            now: DateTimeUtc::from(Utc::now()),
+            env,
+        }
+    }
+}
+
+/// A struct representing a single book rendered by each requested [`ContextMode::Book`] template.
+#[derive(Debug)]
+struct Section {
+    /// The book's title, used in the table of contents.
+    title: String,
+
+    /// The book's anchor, used to link to the book from the table of contents.
+    anchor: String,
+
+    /// The book's rendered contents, one per requested [`ContextMode::Book`] template. `None` if
+    /// the template skipped the book.
+    contents: Vec<Option<String>>,
+}
+
+impl Section {
+    /// Combines a template's renders of all sections into a single document, starting with a table
+    /// of contents.
+    ///
+    /// # Arguments
+    ///
+    /// * `sections` - The sections to combine.
+    /// * `index` - The index of the template's render within each section.
+    /// * `extension` - The template's file extension.
+    fn combine(sections: &[Self], index: usize, extension: &str) -> String {
+        let is_markdown = matches!(extension, "md" | "markdown");
+
+        let sections: Vec<(&Self, &str)> = sections
+            .iter()
+            .filter_map(|section| Some((section, section.contents[index].as_deref()?)))
+            .collect();
+
+        let mut contents = if is_markdown {
+            String::from("# Contents\n\n")
+        } else {
+            String::from("Contents\n\n")
+        };
+
+        for (number, (section, _)) in sections.iter().enumerate() {
+            let number = number + 1;
+
+            let line = if is_markdown {
+                format!("{number}. [{}](#{})\n", section.title, section.anchor)
+            } else {
+                format!("{number}. {}\n", section.title)
+            };
+
+            contents.push_str(&line);
+        }
+
+        for (section, string) in sections {
+            contents.push('\n');
+
+            if is_markdown {
+                let anchor = format!("---\n\n<a id=\"{}\"></a>\n\n", section.anchor);
+                contents.push_str(&anchor);
+            }
+
+            contents.push_str(string);
+        }
+
+        contents
+    }
 }
 
 /// An enum representing the two different template types.
@@ -497,7 +1407,7 @@ enum TemplateKind {
 #[serde(untagged)]
 enum TemplateContext<'a> {
     /// Used when rendering both a [`Book`][book] and its [`Annotation`][annotation]s in a template.
-    /// Includes all the output filenames and the nested directory name.
+    /// Includes all the output filenames, the nested directory name and the [`TemplateGlobals`].
     ///
     /// [book]: crate::models::book::Book
     /// [annotation]: crate::models::annotation::Annotation
@@ -505,15 +1415,39 @@ enum TemplateContext<'a> {
         book: &'a BookContext<'a>,
         annotations: &'a [AnnotationContext<'a>],
         names: &'a NamesRender,
+        now: &'a DateTimeUtc,
+        env: &'a BTreeMap<String, String>,
     },
     /// Used when rendering a single [`Annotation`][annotation] in a template. Includes all the
-    /// output filenames and the nested directory name.
+    /// output filenames, the nested directory name and the [`TemplateGlobals`].
     ///
     /// [annotation]: crate::models::annotation::Annotation
     Annotation {
         book: &'a BookContext<'a>,
         annotation: &'a AnnotationContext<'a>,
         names: &'a NamesRender,
+        now: &'a DateTimeUtc,
+        env: &'a BTreeMap<String, String>,
+    },
+    /// Used when rendering a single `#tag` and its [`Annotation`][annotation]s across all
+    /// [`Book`][book]s in a template. Includes the [`TemplateGlobals`].
+    ///
+    /// [book]: crate::models::book::Book
+    /// [annotation]: crate::models::annotation::Annotation
+    Tag {
+        tag: &'a TagContext<'a>,
+        now: &'a DateTimeUtc,
+        env: &'a BTreeMap<String, String>,
+    },
+    /// Used when rendering an audiobook and its [`Bookmark`][bookmark]s in a template. Includes
+    /// the [`TemplateGlobals`].
+    ///
+    /// [bookmark]: crate::models::bookmark::Bookmark
+    Bookmark {
+        book: &'a BookContext<'a>,
+        bookmarks: &'a [BookmarkContext<'a>],
+        now: &'a DateTimeUtc,
+        env: &'a BTreeMap<String, String>,
     },
 }
 
@@ -522,11 +1456,14 @@ impl<'a> TemplateContext<'a> {
         book: &'a BookContext<'a>,
         annotations: &'a [AnnotationContext<'a>],
         names: &'a NamesRender,
+        globals: &'a TemplateGlobals,
     ) -> Self {
         Self::Book {
             book,
             annotations,
             names,
+            now: &globals.now,
+            env: &globals.env,
         }
     }
 
@@ -534,11 +1471,31 @@ impl<'a> TemplateContext<'a> {
         book: &'a BookContext<'a>,
         annotation: &'a AnnotationContext<'a>,
         names: &'a NamesRender,
+        globals: &'a TemplateGlobals,
     ) -> Self {
         Self::Annotation {
             book,
             annotation,
             names,
+            now: &globals.now,
+            env: &globals.env,
+        }
+    }
+
+    fn tag(tag: &'a TagContext<'a>, globals: &'a TemplateGlobals) -> Self {
+        Self::Tag {
+            tag,
+            now: &globals.now,
+            env: &globals.env,
+        }
+    }
+
+    fn bookmark(audiobook: &'a AudiobookContext<'a>, globals: &'a TemplateGlobals) -> Self {
+        Self::Bookmark {
+            book: &audiobook.book,
+            bookmarks: &audiobook.bookmarks,
+            now: &globals.now,
+            env: &globals.env,
         }
     }
 }
@@ -707,6 +1664,676 @@ mod test {
         }
     }
 
+    mod template_path {
+
+        use super::*;
+
+        // Tests that a template's path is made relative to the templates directory.
+        #[test]
+        fn relative() {
+            let path = Renderer::relative_template_path(
+                Path::new("/templates/nested/template.md"),
+                Path::new("/templates"),
+            )
+            .unwrap();
+
+            assert_eq!(path, PathBuf::from("nested").join("template.md"));
+        }
+
+        // Tests that a path that cannot be made relative returns an error instead of panicking.
+        #[test]
+        fn unresolvable() {
+            let result = Renderer::relative_template_path(
+                Path::new("templates/template.md"),
+                Path::new("/templates"),
+            );
+
+            assert!(matches!(result, Err(Error::TemplateInvalidPath { .. })));
+        }
+    }
+
+    mod dated_structure {
+
+        use super::*;
+
+        use crate::models::annotation::{Annotation, AnnotationMetadata};
+        use crate::models::book::Book;
+
+        fn annotation(id: &str, created: &str) -> Annotation {
+            let mut annotation = Annotation {
+                metadata: AnnotationMetadata {
+                    id: id.to_string(),
+                    ..Default::default()
+                },
+                ..Default::default()
+            };
+
+            *annotation.metadata.created = chrono::DateTime::parse_from_rfc3339(created)
+                .unwrap()
+                .with_timezone(&chrono::Utc);
+
+            annotation
+        }
+
+        fn render_paths(config: &str) -> Vec<PathBuf> {
+            let template = Template::new("dated.txt", config).unwrap();
+
+            let mut renderer = Renderer::default();
+            renderer
+                .engine
+                .register_template(&template.id, &template.contents)
+                .unwrap();
+            renderer.templates.push(template);
+
+            let entry = Entry {
+                book: Book::default(),
+                annotations: vec![
+                    annotation("01", "2024-05-09T12:00:00Z"),
+                    annotation("02", "2023-12-31T12:00:00Z"),
+                ],
+            };
+
+            renderer.render(&entry).unwrap();
+            renderer
+                .templates_rendered()
+                .map(|render| render.path.clone())
+                .collect()
+        }
+
+        // Tests that annotations are placed into year/month directories by default.
+        #[test]
+        fn dated_default() {
+            let paths = render_paths(
+                "<!-- readstor\ngroup: test\ncontext: annotation\nstructure: dated\n\
+                 extension: txt\n-->\n",
+            );
+
+            assert_eq!(paths, [PathBuf::from("2024/05"), PathBuf::from("2023/12")]);
+        }
+
+        // Tests that the `dated-directory` name is used and unsafe components are dropped.
+        #[test]
+        fn dated_grouped_custom() {
+            let paths = render_paths(
+                "<!-- readstor\ngroup: test\ncontext: annotation\nstructure: dated-grouped\n\
+                 extension: txt\nnames:\n  dated-directory: \"../{{ date.year }}/{{ date.month \
+                 }}-{{ date.day }}\"\n-->\n",
+            );
+
+            assert_eq!(
+                paths,
+                [
+                    PathBuf::from("test/2024/05-09"),
+                    PathBuf::from("test/2023/12-31")
+                ]
+            );
+        }
+
+        // Tests that the template's `output-directory` is placed before its structure.
+        #[test]
+        fn output_directory() {
+            let paths = render_paths(
+                "<!-- readstor\ngroup: test\ncontext: annotation\nstructure: dated-grouped\n\
+                 extension: txt\noutput-directory: site/notes\n-->\n",
+            );
+
+            assert_eq!(
+                paths,
+                [
+                    PathBuf::from("site/notes/test/2024/05"),
+                    PathBuf::from("site/notes/test/2023/12")
+                ]
+            );
+        }
+    }
+
+    mod skip {
+
+        use super::*;
+
+        use crate::models::annotation::{Annotation, AnnotationMetadata};
+        use crate::models::book::Book;
+
+        fn annotation(id: &str, tags: &[&str]) -> Annotation {
+            Annotation {
+                metadata: AnnotationMetadata {
+                    id: id.to_string(),
+                    ..Default::default()
+                },
+                tags: tags.iter().map(ToString::to_string).collect(),
+                ..Default::default()
+            }
+        }
+
+        fn render_contents(config: &str, contents: &str) -> Vec<String> {
+            let template = Template::new("skip.txt", &format!("{config}{contents}")).unwrap();
+
+            let mut renderer = Renderer::default();
+            renderer.register_template(&template).unwrap();
+            renderer.templates.push(template);
+
+            let entry = Entry {
+                book: Book::default(),
+                annotations: vec![
+                    annotation("01", &["#quote"]),
+                    annotation("02", &[]),
+                    annotation("03", &["#quote", "#idea"]),
+                ],
+            };
+
+            renderer.render(&entry).unwrap();
+            renderer
+                .templates_rendered()
+                .map(|render| render.contents.clone())
+                .collect()
+        }
+
+        // Tests that annotations are skipped when the `skip-if` expression is true.
+        #[test]
+        fn skip_if_annotation() {
+            let contents = render_contents(
+                "<!-- readstor\ngroup: test\ncontext: annotation\nstructure: flat\n\
+                 extension: txt\nskip-if: \"annotation.tags | length == 0\"\n-->\n",
+                "{{ annotation.metadata.id }}",
+            );
+
+            assert_eq!(contents, ["01", "03"]);
+        }
+
+        // Tests that books are skipped when the `skip-if` expression is true.
+        #[test]
+        fn skip_if_book() {
+            let config = |expression: &str| {
+                format!(
+                    "<!-- readstor\ngroup: test\ncontext: book\nstructure: flat\n\
+                     extension: txt\nskip-if: \"{expression}\"\n-->\n"
+                )
+            };
+
+            let contents = render_contents(&config("annotations | length < 3"), "{{ book.title }}");
+            assert_eq!(contents, [""]);
+
+            let contents = render_contents(&config("annotations | length > 2"), "{{ book.title }}");
+            assert!(contents.is_empty());
+        }
+
+        // Tests that only annotations with one of the `only-tags` are rendered.
+        #[test]
+        fn only_tags_annotation() {
+            let contents = render_contents(
+                "<!-- readstor\ngroup: test\ncontext: annotation\nstructure: flat\n\
+                 extension: txt\nonly-tags: [idea, \"#other\"]\n-->\n",
+                "{{ annotation.metadata.id }}",
+            );
+
+            assert_eq!(contents, ["03"]);
+        }
+
+        // Tests that books without any annotations with one of the `only-tags` are skipped.
+        #[test]
+        fn only_tags_book() {
+            let contents = render_contents(
+                "<!-- readstor\ngroup: test\ncontext: book\nstructure: flat\n\
+                 extension: txt\nonly-tags: [\"#other\"]\n-->\n",
+                "{{ book.title }}",
+            );
+
+            assert!(contents.is_empty());
+        }
+
+        // Tests that an invalid `skip-if` expression returns an error.
+        #[test]
+        fn invalid_skip_if() {
+            let template = Template::new(
+                "skip.txt",
+                "<!-- readstor\ngroup: test\ncontext: book\nstructure: flat\n\
+                 extension: txt\nskip-if: \"annotations |\"\n-->\n",
+            )
+            .unwrap();
+
+            let mut renderer = Renderer::default();
+
+            assert!(renderer.register_template(&template).is_err());
+        }
+    }
+
+    mod escape_markdown {
+
+        use super::*;
+
+        use crate::models::annotation::Annotation;
+        use crate::models::book::Book;
+
+        fn render_contents(extension: &str, config: &str, escape_markdown: bool) -> String {
+            let template = Template::new(
+                format!("escape.{extension}"),
+                &format!(
+                    "<!-- readstor\ngroup: test\ncontext: book\nstructure: flat\n\
+                     extension: {extension}\n{config}-->\n\
+                     {{{{ book.title }}}}: {{{{ annotations[0].body }}}}"
+                ),
+            )
+            .unwrap();
+
+            let mut renderer = Renderer::new(
+                RenderOptions {
+                    escape_markdown,
+                    ..Default::default()
+                },
+                String::new(),
+            );
+            renderer.register_template(&template).unwrap();
+            renderer.templates.push(template);
+
+            let entry = Entry {
+                book: Book {
+                    title: "*Title*".to_string(),
+                    ..Default::default()
+                },
+                annotations: vec![Annotation {
+                    body: "*Lorem* _ipsum_".to_string(),
+                    ..Default::default()
+                }],
+            };
+
+            renderer.render(&entry).unwrap();
+            renderer
+                .templates_rendered()
+                .map(|render| render.contents.clone())
+                .collect()
+        }
+
+        // Tests that only the bodies rendered with Markdown templates are escaped by default.
+        #[test]
+        fn markdown_templates() {
+            assert_eq!(
+                render_contents("md", "", true),
+                r"*Title*: \*Lorem\* \_ipsum\_"
+            );
+            assert_eq!(render_contents("txt", "", true), "*Title*: *Lorem* _ipsum_");
+            assert_eq!(render_contents("md", "", false), "*Title*: *Lorem* _ipsum_");
+        }
+
+        // Tests that a template's `escape-markdown` overrides the default.
+        #[test]
+        fn template_config() {
+            assert_eq!(
+                render_contents("txt", "escape-markdown: true\n", false),
+                r"*Title*: \*Lorem\* \_ipsum\_"
+            );
+            assert_eq!(
+                render_contents("md", "escape-markdown: false\n", true),
+                "*Title*: *Lorem* _ipsum_"
+            );
+        }
+    }
+
+    mod tags {
+
+        use super::*;
+
+        use crate::models::annotation::{Annotation, AnnotationMetadata};
+        use crate::models::book::Book;
+
+        fn entry(title: &str, annotations: &[(&str, &[&str])]) -> Entry {
+            Entry {
+                book: Book {
+                    title: title.to_string(),
+                    ..Default::default()
+                },
+                annotations: annotations
+                    .iter()
+                    .map(|(id, tags)| Annotation {
+                        metadata: AnnotationMetadata {
+                            id: (*id).to_string(),
+                            ..Default::default()
+                        },
+                        tags: tags.iter().map(ToString::to_string).collect(),
+                        ..Default::default()
+                    })
+                    .collect(),
+            }
+        }
+
+        fn render_tags(config: &str) -> Vec<(PathBuf, String)> {
+            let contents = "{% for tagged in tag.books %}{{ tagged.book.title }}:\
+                            {% for annotation in tagged.annotations %}\
+                            {{ annotation.metadata.id }}{% endfor %};{% endfor %}";
+
+            let template = Template::new("tag.txt", &format!("{config}{contents}")).unwrap();
+
+            let mut renderer = Renderer::default();
+            renderer.register_template(&template).unwrap();
+            renderer.validate_template(&template).unwrap();
+            renderer.templates.push(template);
+
+            let entries = [
+                entry("A", &[("01", &["#quote"]), ("02", &["#quote", "#idea"])]),
+                entry("B", &[("03", &[]), ("04", &["#quote"])]),
+            ];
+
+            for entry in &entries {
+                renderer.render(entry).unwrap();
+            }
+
+            renderer.render_tags(&entries).unwrap();
+            renderer
+                .templates_rendered()
+                .map(|render| (render.path.join(&render.filename), render.contents.clone()))
+                .collect()
+        }
+
+        // Tests that one file is rendered per tag with its annotations grouped by book.
+        #[test]
+        fn tag_pages() {
+            let renders = render_tags(
+                "<!-- readstor\ngroup: tags\ncontext: tag\nstructure: flat-grouped\n\
+                 extension: txt\n-->\n",
+            );
+
+            assert_eq!(
+                renders,
+                [
+                    (PathBuf::from("tags/idea.txt"), "A:02;".to_string()),
+                    (PathBuf::from("tags/quote.txt"), "A:0102;B:04;".to_string()),
+                ]
+            );
+        }
+
+        // Tests that only tags in the `only-tags` are rendered.
+        #[test]
+        fn only_tags() {
+            let renders = render_tags(
+                "<!-- readstor\ngroup: tags\ncontext: tag\nstructure: flat\n\
+                 extension: txt\nonly-tags: [idea]\nnames:\n  tag: \"tag-{{ tag.slug }}\"\n-->\n",
+            );
+
+            assert_eq!(
+                renders,
+                [(PathBuf::from("tag-idea.txt"), "A:02;".to_string())]
+            );
+        }
+    }
+
+    mod bookmarks {
+
+        use super::*;
+
+        use crate::models::book::Book;
+        use crate::models::bookmark::Bookmark;
+
+        fn audiobook(title: &str, positions: &[f64]) -> Audiobook {
+            Audiobook {
+                book: Book {
+                    title: title.to_string(),
+                    author: "Author".to_string(),
+                    ..Default::default()
+                },
+                bookmarks: positions
+                    .iter()
+                    .map(|position| Bookmark {
+                        position: *position,
+                        ..Default::default()
+                    })
+                    .collect(),
+            }
+        }
+
+        fn render_bookmarks(config: &str) -> Vec<(PathBuf, String)> {
+            let contents = "{% for bookmark in bookmarks %}{{ bookmark.timestamp }};{% endfor %}";
+
+            let template = Template::new("bookmark.txt", &format!("{config}{contents}")).unwrap();
+
+            let mut renderer = Renderer::default();
+            renderer.register_template(&template).unwrap();
+            renderer.validate_template(&template).unwrap();
+            renderer.templates.push(template);
+
+            let audiobooks = [audiobook("A", &[5.0, 3723.5]), audiobook("B", &[])];
+
+            renderer.render_bookmarks(&audiobooks).unwrap();
+            renderer
+                .templates_rendered()
+                .map(|render| (render.path.join(&render.filename), render.contents.clone()))
+                .collect()
+        }
+
+        // Tests that one file is rendered per audiobook with its bookmarks.
+        #[test]
+        fn audiobook_pages() {
+            let renders = render_bookmarks(
+                "<!-- readstor\ngroup: bookmarks\ncontext: bookmark\nstructure: nested-grouped\n\
+                 extension: txt\nskip-if: \"bookmarks | length == 0\"\n-->\n",
+            );
+
+            assert_eq!(
+                renders,
+                [(
+                    PathBuf::from("bookmarks/Author - A/Author - A.txt"),
+                    "00:00:05;01:02:03;".to_string()
+                )]
+            );
+        }
+
+        // Tests that dated structures are rejected for bookmark templates.
+        #[test]
+        fn invalid_structure() {
+            let template = Template::new(
+                "bookmark.txt",
+                "<!-- readstor\ngroup: bookmarks\ncontext: bookmark\nstructure: dated\n\
+                 extension: txt\n-->\n",
+            );
+
+            assert!(template.is_err());
+        }
+    }
+
+    mod single_file {
+
+        use super::*;
+
+        use crate::models::book::Book;
+
+        fn entry(title: &str) -> Entry {
+            Entry {
+                book: Book {
+                    title: title.to_string(),
+                    author: "Author".to_string(),
+                    ..Default::default()
+                },
+                annotations: Vec::new(),
+            }
+        }
+
+        // Tests that books are combined into a single file starting with a table of contents.
+        #[test]
+        fn combine() {
+            let template = Template::new(
+                "single.md",
+                "<!-- readstor\ngroup: test\ncontext: book\nstructure: flat\nextension: md\n-->\n\
+                 {{ book.title }}\n",
+            )
+            .unwrap();
+
+            let mut renderer = Renderer::new(
+                RenderOptions {
+                    single_file: true,
+                    ..Default::default()
+                },
+                String::new(),
+            );
+            renderer
+                .engine
+                .register_template(&template.id, &template.contents)
+                .unwrap();
+            renderer.templates.push(template);
+
+            renderer.render(&entry("First")).unwrap();
+            renderer.render(&entry("Second")).unwrap();
+
+            assert_eq!(renderer.count_templates_rendered(), 0);
+
+            renderer.combine();
+
+            let renders: Vec<&Render> = renderer.templates_rendered().collect();
+
+            assert_eq!(renders.len(), 1);
+            assert_eq!(renders[0].filename, "library.md");
+            assert_eq!(
+                renders[0].contents,
+                "# Contents\n\n\
+                 1. [Author - First](#author-first)\n\
+                 2. [Author - Second](#author-second)\n\
+                 \n---\n\n<a id=\"author-first\"></a>\n\nFirst\n\
+                 \n---\n\n<a id=\"author-second\"></a>\n\nSecond\n"
+            );
+        }
+    }
+
+    mod globals {
+
+        use super::*;
+
+        // Tests that whitelisted environment variables are exposed, even if they're unset, and
+        // that `now` is the same for every render.
+        #[test]
+        fn now_and_env() {
+            let template = Template::new(
+                "globals.txt",
+                "<!-- readstor\ngroup: test\ncontext: annotation\nstructure: flat\nextension: txt\n-->\n\
+                 {{ now }}|{{ env.READSTOR_TEST_UNSET }}|{{ env | length }}",
+            )
+            .unwrap();
+
+            let mut renderer = Renderer::new(
+                RenderOptions {
+                    env: vec!["READSTOR_TEST_UNSET".to_string()],
+                    ..Default::default()
+                },
+                String::new(),
+            );
+            renderer
+                .engine
+                .register_template(&template.id, &template.contents)
+                .unwrap();
+            renderer.validate_template(&template).unwrap();
+            renderer.templates.push(template);
+
+            renderer.render(&Entry::dummy()).unwrap();
+
+            let renders: Vec<&Render> = renderer.templates_rendered().collect();
+            let now = serde_json::to_value(renderer.globals.now).unwrap();
+            let expected = format!("{}||1", now.as_str().unwrap());
+
+            assert_eq!(renders.len(), 3);
+            assert!(renders.iter().all(|render| render.contents == expected));
+        }
+    }
+
+    mod guards {
+
+        use super::*;
+
+        // Tests that a render larger than the maximum render size returns an error.
+        #[test]
+        fn max_render_size() {
+            let template = Template::new(
+                "large.txt",
+                "<!-- readstor\ngroup: test\ncontext: book\nstructure: flat\nextension: txt\n-->\n\
+                 {{ book.title }}",
+            )
+            .unwrap();
+
+            let mut renderer = Renderer::new(
+                RenderOptions {
+                    max_render_size: Some(4),
+                    ..Default::default()
+                },
+                String::new(),
+            );
+            renderer
+                .engine
+                .register_template(&template.id, &template.contents)
+                .unwrap();
+            renderer.templates.push(template);
+
+            let result = renderer.render(&Entry::dummy());
+
+            assert!(matches!(result, Err(Error::TemplateTooLarge { .. })));
+        }
+    }
+
+    mod write {
+
+        use super::*;
+
+        use crate::defaults::TEMP_OUTPUT_DIRECTORY;
+
+        fn write_renders(name: &str, count: usize) -> PathBuf {
+            let directory = TEMP_OUTPUT_DIRECTORY.join("tests-write").join(name);
+
+            if directory.exists() {
+                std::fs::remove_dir_all(&directory).unwrap();
+            }
+
+            // Each book is rendered to five files.
+            let renders = (0..count)
+                .map(|i| {
+                    Render::new(
+                        PathBuf::from(format!("{:02}", i % 3)),
+                        format!("{i}.txt"),
+                        i.to_string(),
+                    )
+                })
+                .fold(Vec::<Vec<Render>>::new(), |mut books, render| {
+                    match books.last_mut() {
+                        Some(book) if book.len() < 5 => book.push(render),
+                        _ => books.push(vec![render]),
+                    }
+                    books
+                });
+
+            let renderer = Renderer {
+                renders,
+                ..Default::default()
+            };
+
+            renderer.write(&directory).unwrap();
+
+            directory
+        }
+
+        // Tests that all files are written when written on the current thread.
+        #[test]
+        fn write_sequential() {
+            let directory = write_renders("sequential", WRITE_PARALLEL_THRESHOLD);
+
+            assert_eq!(
+                std::fs::read_to_string(directory.join("01").join("4.txt")).unwrap(),
+                "4"
+            );
+        }
+
+        // Tests that all files are written when written in parallel batches.
+        #[test]
+        fn write_parallel() {
+            let count = WRITE_PARALLEL_THRESHOLD * 4 + 1;
+            let directory = write_renders("parallel", count);
+
+            let written = walkdir::WalkDir::new(&directory)
+                .into_iter()
+                .filter_map(std::result::Result::ok)
+                .filter(|entry| entry.file_type().is_file())
+                .count();
+
+            assert_eq!(written, count);
+            assert_eq!(
+                std::fs::read_to_string(directory.join("01").join("256.txt")).unwrap(),
+                "256"
+            );
+        }
+    }
+
     mod example_templates {
 
         use super::*;
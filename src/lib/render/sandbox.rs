@@ -0,0 +1,381 @@
+//! Defines a type to render into a temporary directory before syncing into the output directory.
+
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::result::Result;
+
+use super::template::Render;
+
+/// The name of the file listing the files written by the last sync. This is placed inside the
+/// output directory.
+pub const MANIFEST_FILENAME: &str = ".readstor-manifest.json";
+
+/// A struct representing a temporary directory that a render is written into.
+///
+/// Once the entire render has been written, [`Sandbox::sync()`] updates the output directory to
+/// match the sandbox. If the render fails before then, the output directory is left untouched. The
+/// temporary directory is removed when the [`Sandbox`] is dropped.
+#[derive(Debug)]
+pub struct Sandbox {
+    /// The path to the temporary directory.
+    path: PathBuf,
+
+    /// The ids of the requested templates. Stale files are only deleted if these match the ones
+    /// saved in the manifest.
+    templates: Vec<String>,
+}
+
+/// A struct representing the changes made to the output directory by [`Sandbox::sync()`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct SyncSummary {
+    /// The number of files that didn't exist in the output directory.
+    pub added: usize,
+
+    /// The number of files whose contents changed.
+    pub updated: usize,
+
+    /// The number of files whose contents didn't change.
+    pub unchanged: usize,
+
+    /// The number of files written by the previous sync that are no longer rendered.
+    pub deleted: usize,
+}
+
+/// A struct representing the files written by the last sync.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Manifest {
+    /// The ids of the requested templates.
+    templates: Vec<String>,
+
+    /// The paths of the written files, relative to the output directory.
+    files: BTreeSet<PathBuf>,
+}
+
+impl Sandbox {
+    /// Creates a new instance of [`Sandbox`] with an empty temporary directory.
+    ///
+    /// # Arguments
+    ///
+    /// * `templates` - The ids of the requested templates.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the temporary directory cannot be created.
+    pub fn new(templates: Vec<String>) -> Result<Self> {
+        let path = std::env::temp_dir().join(format!("readstor-sandbox-{}", uuid::Uuid::new_v4()));
+
+        std::fs::create_dir_all(&path)?;
+
+        tracing::debug!(path = %path.display(), "created sandbox");
+
+        Ok(Self { path, templates })
+    }
+
+    /// Returns the path to the temporary directory.
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Copies the existing files that renders are appended or prepended to from the output
+    /// directory into the sandbox. Files already in the sandbox are left as is. This must be
+    /// called before the renders are written, otherwise the sync would replace the existing files
+    /// with ones only containing the newly added annotations. See [`append`][append].
+    ///
+    /// # Arguments
+    ///
+    /// * `destination` - The output directory.
+    /// * `renders` - The renders about to be written into the sandbox.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if any IO errors are encountered.
+    ///
+    /// [append]: super::append
+    pub fn seed<'a, I>(&self, destination: &Path, renders: I) -> Result<()>
+    where
+        I: IntoIterator<Item = &'a Render>,
+    {
+        for render in renders {
+            if render.append_id().is_none() {
+                continue;
+            }
+
+            let file = render.path.join(&render.filename);
+            let source = destination.join(&file);
+            let target = self.path.join(&file);
+
+            if target.exists() || !source.is_file() {
+                continue;
+            }
+
+            if let Some(parent) = target.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+
+            std::fs::copy(&source, &target)?;
+        }
+
+        Ok(())
+    }
+
+    /// Syncs the sandbox into the output directory.
+    ///
+    /// Files that don't exist in the output directory are added and files whose contents changed
+    /// are updated. Files written by the previous sync, but not by this one, are deleted. Files
+    /// that weren't written by a sync are never deleted. Deletions are skipped if the previous sync
+    /// rendered a different set of templates.
+    ///
+    /// # Arguments
+    ///
+    /// * `destination` - The output directory.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if any IO errors are encountered.
+    pub fn sync(self, destination: &Path) -> Result<SyncSummary> {
+        let mut summary = SyncSummary::default();
+
+        let files: BTreeSet<PathBuf> = walkdir::WalkDir::new(&self.path)
+            .into_iter()
+            .filter_map(std::result::Result::ok)
+            .filter(|entry| entry.file_type().is_file())
+            .filter_map(|entry| {
+                entry
+                    .path()
+                    .strip_prefix(&self.path)
+                    .ok()
+                    .map(Path::to_path_buf)
+            })
+            .collect();
+
+        for file in &files {
+            let source = self.path.join(file);
+            let target = destination.join(file);
+
+            if !target.exists() {
+                summary.added += 1;
+            } else if std::fs::read(&source)? != std::fs::read(&target)? {
+                summary.updated += 1;
+            } else {
+                summary.unchanged += 1;
+                continue;
+            }
+
+            if let Some(parent) = target.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+
+            std::fs::copy(&source, &target)?;
+        }
+
+        let manifest_path = destination.join(MANIFEST_FILENAME);
+        let previous = Self::load_manifest(&manifest_path)?;
+
+        if previous.templates == self.templates {
+            for file in previous.files.difference(&files) {
+                let target = destination.join(file);
+
+                if target.is_file() {
+                    std::fs::remove_file(&target)?;
+                    self::remove_empty_parents(&target, destination);
+                    summary.deleted += 1;
+                }
+            }
+        } else if !previous.files.is_empty() {
+            tracing::warn!("skipped deleting files synced for a different set of templates");
+        }
+
+        let manifest = Manifest {
+            templates: self.templates.clone(),
+            files,
+        };
+
+        // See `RenderProgress::complete()`.
+        let temp = manifest_path.with_extension(format!("json.{}.tmp", std::process::id()));
+
+        std::fs::write(&temp, serde_json::to_string(&manifest)?)?;
+        std::fs::rename(&temp, &manifest_path)?;
+
+        tracing::debug!(
+            path = %self.path.display(),
+            destination = %destination.display(),
+            "synced sandbox"
+        );
+
+        Ok(summary)
+    }
+
+    /// Loads the manifest saved by the previous sync. Returns an empty manifest if none exists.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path to the manifest.
+    fn load_manifest(path: &Path) -> Result<Manifest> {
+        if !path.exists() {
+            return Ok(Manifest::default());
+        }
+
+        Ok(serde_json::from_str(&std::fs::read_to_string(path)?)?)
+    }
+}
+
+impl Drop for Sandbox {
+    fn drop(&mut self) {
+        if let Err(error) = std::fs::remove_dir_all(&self.path) {
+            tracing::warn!(path = %self.path.display(), %error, "failed to remove sandbox");
+        }
+    }
+}
+
+/// Removes a file's parent directories, up to but excluding the root, while they're empty.
+///
+/// # Arguments
+///
+/// * `file` - The removed file.
+/// * `root` - The directory to stop at.
+fn remove_empty_parents(file: &Path, root: &Path) {
+    for directory in file.ancestors().skip(1) {
+        if directory == root || !directory.starts_with(root) {
+            break;
+        }
+
+        // This fails if the directory isn't empty.
+        if std::fs::remove_dir(directory).is_err() {
+            break;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    use crate::defaults::TEMP_OUTPUT_DIRECTORY;
+
+    fn directory(name: &str) -> PathBuf {
+        let directory = TEMP_OUTPUT_DIRECTORY.join("tests-sandbox").join(name);
+
+        if directory.exists() {
+            std::fs::remove_dir_all(&directory).unwrap();
+        }
+
+        std::fs::create_dir_all(&directory).unwrap();
+
+        directory
+    }
+
+    fn render(files: &[(&str, &str)]) -> Sandbox {
+        let sandbox = Sandbox::new(vec!["template.md".to_string()]).unwrap();
+
+        for (file, contents) in files {
+            let path = sandbox.path().join(file);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, contents).unwrap();
+        }
+
+        sandbox
+    }
+
+    // Tests that files are added, updated and deleted to match the sandbox.
+    #[test]
+    fn sync() {
+        let destination = directory("sync");
+
+        let summary = render(&[("a/one.md", "1"), ("a/two.md", "2")])
+            .sync(&destination)
+            .unwrap();
+
+        assert_eq!(summary.added, 2);
+
+        let summary = render(&[("a/one.md", "1"), ("b/three.md", "3")])
+            .sync(&destination)
+            .unwrap();
+
+        assert_eq!(
+            summary,
+            SyncSummary {
+                added: 1,
+                updated: 0,
+                unchanged: 1,
+                deleted: 1,
+            }
+        );
+        assert!(!destination.join("a").join("two.md").exists());
+
+        let summary = render(&[("a/one.md", "one")]).sync(&destination).unwrap();
+
+        assert_eq!(summary.updated, 1);
+        assert_eq!(summary.deleted, 1);
+        assert!(!destination.join("b").exists());
+    }
+
+    // Tests that files not written by a sync are never deleted.
+    #[test]
+    fn keep_unsynced_files() {
+        let destination = directory("keep-unsynced-files");
+        std::fs::write(destination.join("notes.md"), "").unwrap();
+
+        render(&[("one.md", "1")]).sync(&destination).unwrap();
+        render(&[]).sync(&destination).unwrap();
+
+        assert!(destination.join("notes.md").exists());
+        assert!(!destination.join("one.md").exists());
+    }
+
+    // Tests that annotations appended in earlier sandboxed renders are kept.
+    #[test]
+    fn seed_appended() {
+        use crate::render::append;
+        use crate::render::template::WriteMode;
+
+        let destination = directory("seed-appended");
+
+        let render_into = |id: &str, contents: &str| {
+            let render = Render {
+                path: PathBuf::from("log"),
+                filename: "book.md".to_string(),
+                contents: contents.to_string(),
+                write_mode: WriteMode::Append,
+                annotation_id: Some(id.to_string()),
+                ..Default::default()
+            };
+
+            let sandbox = Sandbox::new(vec!["template.md".to_string()]).unwrap();
+            sandbox.seed(&destination, [&render]).unwrap();
+
+            let path = sandbox.path().join(&render.path);
+            std::fs::create_dir_all(&path).unwrap();
+            append::write(&path.join(&render.filename), &render, id).unwrap();
+
+            sandbox.sync(&destination).unwrap();
+        };
+
+        render_into("1", "first");
+        render_into("2", "second");
+
+        assert_eq!(
+            std::fs::read_to_string(destination.join("log").join("book.md")).unwrap(),
+            format!(
+                "{}\nfirst\n\n{}\nsecond\n",
+                append::marker("1"),
+                append::marker("2")
+            )
+        );
+    }
+
+    // Tests that the temporary directory is removed once the sandbox is dropped.
+    #[test]
+    fn remove_on_drop() {
+        let sandbox = render(&[("one.md", "1")]);
+        let path = sandbox.path().to_owned();
+
+        drop(sandbox);
+
+        assert!(!path.exists());
+    }
+}
@@ -1,12 +1,14 @@
 //! Defines types to represent a template's content and metadata.
 
-use std::path::{Path, PathBuf};
+use std::borrow::Cow;
+use std::collections::BTreeSet;
+use std::path::{Component, Path, PathBuf};
 
 use serde::Deserialize;
 
 use crate::result::{Error, Result};
 
-use super::defaults::{CONFIG_TAG_CLOSE, CONFIG_TAG_OPEN};
+use super::defaults::{CONFIG_TAG_CLOSE, CONFIG_TAG_OPEN, DEFAULT_WRAP_WIDTH, SKIP_IF_SUFFIX};
 use super::names::Names;
 
 /// A struct representing a fully configured template.
@@ -52,9 +54,96 @@ pub struct Template {
     /// The template's file extension.
     pub extension: String,
 
+    /// An optional directory to write the template's files to, relative to the output directory.
+    /// This allows templates rendered together to write to different destinations e.g. one for
+    /// an Obsidian vault and another for a website. The template's [`StructureMode`] is applied
+    /// within this directory.
+    ///
+    /// ```yaml
+    /// output-directory: vault/books
+    /// ```
+    #[serde(default)]
+    pub output_directory: Option<PathBuf>,
+
+    /// An optional [Tera][tera] expression that, when true, skips rendering the template for a
+    /// book or an annotation. The expression has access to the same context as the template.
+    ///
+    /// ```yaml
+    /// skip-if: "annotations | length < 3"
+    /// ```
+    ///
+    /// [tera]: https://keats.github.io/tera/docs/#expressions
+    #[serde(default)]
+    pub skip_if: Option<String>,
+
+    /// An optional set of `#tags` to limit the template to. An annotation template only renders
+    /// annotations with at least one of the tags. A book template only renders books with at
+    /// least one annotation with one of the tags. The leading `#` is optional.
+    ///
+    /// ```yaml
+    /// only-tags: ["#quote", "#definition"]
+    /// ```
+    #[serde(default)]
+    pub only_tags: BTreeSet<String>,
+
     /// The template strings for generating output file and directory names.
     #[serde(default)]
     pub names: Names,
+
+    /// Whether to escape Markdown-significant characters in annotation bodies before rendering
+    /// them. Defaults to [`RenderOptions::escape_markdown`][escape-markdown] for templates with an
+    /// `md` or `markdown` extension and to `false` for all others.
+    ///
+    /// ```yaml
+    /// escape-markdown: false
+    /// ```
+    ///
+    /// [escape-markdown]: super::renderer::RenderOptions::escape_markdown
+    #[serde(default)]
+    pub escape_markdown: Option<bool>,
+
+    /// Whether and how wide to wrap the template's files. Defaults to
+    /// [`PostProcessOptions::wrap_text`][wrap-text]. See [`WrapText`] for more information.
+    ///
+    /// ```yaml
+    /// wrap-text: 72
+    /// ```
+    ///
+    /// [wrap-text]: crate::process::post::PostProcessOptions::wrap_text
+    #[serde(default)]
+    pub wrap_text: Option<WrapText>,
+
+    /// Whether to leave fenced code blocks unwrapped when wrapping the template's files.
+    ///
+    /// ```yaml
+    /// preserve-code-blocks: true
+    /// ```
+    #[serde(default)]
+    pub preserve_code_blocks: bool,
+
+    /// How the template's files are written. See [`WriteMode`] for more information.
+    ///
+    /// ```yaml
+    /// write-mode: append
+    /// ```
+    #[serde(default)]
+    pub write_mode: WriteMode,
+
+    /// The text encoding of the template's files. See [`Encoding`] for more information.
+    ///
+    /// ```yaml
+    /// encoding: utf-8-bom
+    /// ```
+    #[serde(default)]
+    pub encoding: Encoding,
+
+    /// The line endings of the template's files. See [`Newline`] for more information.
+    ///
+    /// ```yaml
+    /// newline: crlf
+    /// ```
+    #[serde(default)]
+    pub newline: Newline,
 }
 
 impl Template {
@@ -85,9 +174,114 @@ impl Template {
         template.id = path.display().to_string();
         template.contents = contents;
 
+        // A dated directory is based on an annotation's date so a book has no single directory. A
+        // tag spans multiple books so it has neither a nested nor a dated directory.
+        let is_invalid_structure = match template.context_mode {
+            ContextMode::Book | ContextMode::Bookmark => matches!(
+                template.structure_mode,
+                StructureMode::Dated | StructureMode::DatedGrouped
+            ),
+            ContextMode::Annotation => false,
+            ContextMode::Tag => !matches!(
+                template.structure_mode,
+                StructureMode::Flat | StructureMode::FlatGrouped
+            ),
+        };
+
+        if is_invalid_structure {
+            return Err(Error::TemplateInvalidStructure {
+                path: template.id,
+                structure: template.structure_mode.to_string(),
+                context: template.context_mode.to_string(),
+            });
+        }
+
+        // Only annotations are rendered one at a time into a shared file.
+        if template.write_mode != WriteMode::Replace
+            && !matches!(template.context_mode, ContextMode::Annotation)
+        {
+            return Err(Error::TemplateInvalidWriteMode {
+                path: template.id,
+                write_mode: template.write_mode.to_string(),
+                context: template.context_mode.to_string(),
+            });
+        }
+
+        // Tags are stored with their leading `#`.
+        template.only_tags = template
+            .only_tags
+            .into_iter()
+            .map(|tag| {
+                if tag.starts_with('#') {
+                    tag
+                } else {
+                    format!("#{tag}")
+                }
+            })
+            .collect();
+
+        // The output directory must stay within the output directory passed to the renderer.
+        if let Some(directory) = &template.output_directory {
+            let is_contained = directory
+                .components()
+                .all(|component| matches!(component, Component::Normal(_) | Component::CurDir));
+
+            if !is_contained {
+                return Err(Error::TemplateInvalidOutputDirectory {
+                    path: template.id,
+                    directory: directory.display().to_string(),
+                });
+            }
+        }
+
         Ok(template)
     }
 
+    /// Returns the directory the template's files are written to before its [`StructureMode`] is
+    /// applied, relative to the output directory. This is empty unless the template sets an
+    /// `output-directory`.
+    #[must_use]
+    pub fn root_directory(&self) -> PathBuf {
+        self.output_directory.clone().unwrap_or_default()
+    }
+
+    /// Returns `true` if a set of `#tags` contains at least one of the template's `only-tags` or if
+    /// the template has no `only-tags`.
+    ///
+    /// # Arguments
+    ///
+    /// * `tags` - The `#tags` to check.
+    #[must_use]
+    pub fn includes_tags(&self, tags: &BTreeSet<String>) -> bool {
+        self.only_tags.is_empty() || !self.only_tags.is_disjoint(tags)
+    }
+
+    /// Returns `true` if annotation bodies should have their Markdown-significant characters
+    /// escaped before being rendered with this template.
+    ///
+    /// # Arguments
+    ///
+    /// * `default` - Whether to escape Markdown templates if the template doesn't set
+    ///   `escape-markdown`.
+    #[must_use]
+    pub fn escapes_markdown(&self, default: bool) -> bool {
+        self.escape_markdown.unwrap_or_else(|| {
+            default && matches!(self.extension.to_lowercase().as_str(), "md" | "markdown")
+        })
+    }
+
+    /// Returns a tuple containing the id and contents of a template that renders `true` if the
+    /// template's `skip-if` expression is true. Returns `None` if the template has no `skip-if`.
+    #[must_use]
+    pub fn skip_if_template(&self) -> Option<(String, String)> {
+        self.skip_if.as_ref().map(|expression| {
+            (
+                format!("{}{SKIP_IF_SUFFIX}", self.id),
+                format!("{{% if {expression} %}}true{{% endif %}}"),
+            )
+        })
+    }
+
     /// Returns a tuple containing the template's configuration and its contents respectively.
     ///
     /// Returns `None` if the template's config block is formatted incorrectly.
@@ -199,6 +393,25 @@ pub struct Render {
 
     /// The rendered content.
     pub contents: String,
+
+    /// The text encoding the contents are written with.
+    pub encoding: Encoding,
+
+    /// The line endings the contents are written with.
+    pub newline: Newline,
+
+    /// Whether and how wide to wrap the contents, overriding the default. See [`WrapText`].
+    pub wrap_text: Option<WrapText>,
+
+    /// Whether to leave fenced code blocks unwrapped when wrapping the contents.
+    pub preserve_code_blocks: bool,
+
+    /// How the contents are written. See [`WriteMode`].
+    pub write_mode: WriteMode,
+
+    /// The id of the annotation the contents were rendered from, if any. Used to mark the
+    /// contents when they're appended or prepended to a file.
+    pub annotation_id: Option<String>,
 }
 
 impl Render {
@@ -209,8 +422,68 @@ impl Render {
             path,
             filename,
             contents,
+            ..Default::default()
         }
     }
+
+    /// Sets the encoding, line endings, wrapping and write mode to those of a [`Template`].
+    ///
+    /// # Arguments
+    ///
+    /// * `template` - The template the contents were rendered from.
+    #[must_use]
+    pub fn with_output_of(mut self, template: &Template) -> Self {
+        self.encoding = template.encoding;
+        self.newline = template.newline;
+        self.wrap_text = template.wrap_text;
+        self.preserve_code_blocks = template.preserve_code_blocks;
+        self.write_mode = template.write_mode;
+        self
+    }
+
+    /// Sets the id of the annotation the contents were rendered from.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The annotation's id.
+    #[must_use]
+    pub fn with_annotation_id(mut self, id: &str) -> Self {
+        self.annotation_id = Some(id.to_owned());
+        self
+    }
+
+    /// Returns the annotation id to mark the contents with if they're appended or prepended to a
+    /// file. Returns `None` if the contents replace the file.
+    #[must_use]
+    pub fn append_id(&self) -> Option<&str> {
+        match self.write_mode {
+            WriteMode::Replace => None,
+            WriteMode::Append | WriteMode::Prepend => self.annotation_id.as_deref(),
+        }
+    }
+
+    /// Converts contents to the bytes written to disk, applying the line endings and encoding.
+    ///
+    /// # Arguments
+    ///
+    /// * `contents` - The contents to convert.
+    #[must_use]
+    pub fn encode(&self, contents: &str) -> Vec<u8> {
+        self.encoding.encode(&self.newline.apply(contents))
+    }
+
+    /// Converts bytes read from disk back to contents with `\n` line endings. Returns `None` if
+    /// the bytes aren't valid for the encoding. This is the inverse of [`Render::encode()`].
+    ///
+    /// # Arguments
+    ///
+    /// * `bytes` - The bytes to convert.
+    #[must_use]
+    pub fn decode(&self, bytes: &[u8]) -> Option<String> {
+        self.encoding
+            .decode(bytes)
+            .map(|string| string.replace("\r\n", "\n"))
+    }
 }
 
 impl std::fmt::Debug for Render {
@@ -320,6 +593,65 @@ pub enum StructureMode {
     ///  └─ ...
     /// ```
     NestedGrouped,
+
+    /// When selected, each annotation is rendered to the output directory and placed inside a
+    /// directory named after its `dated-directory` which defaults to the year and month the
+    /// annotation was created. This is useful for journaling-style layouts. Only templates with
+    /// [`ContextMode::Annotation`] can use this structure mode.
+    ///
+    /// ```yaml
+    /// output-mode: dated
+    /// ```
+    ///
+    /// ```plaintext
+    /// [output-directory]
+    ///  │
+    ///  ├─ [year]
+    ///  │   ├─ [month]
+    ///  │   │   ├─ [template-name-01].[extension]
+    ///  │   │   ├─ [template-name-02].[extension]
+    ///  │   │   └─ ...
+    ///  │   └─ ...
+    ///  └─ ...
+    /// ```
+    Dated,
+
+    /// When selected, each annotation is rendered to the output directory and placed inside a
+    /// directory named after its `group` and then inside its `dated-directory`. See
+    /// [`StructureMode::Dated`] for more information.
+    ///
+    /// ```yaml
+    /// output-mode: dated-grouped
+    /// ```
+    ///
+    /// ```plaintext
+    /// [output-directory]
+    ///  │
+    ///  ├─ [template-group-01]
+    ///  │   ├─ [year]
+    ///  │   │   ├─ [month]
+    ///  │   │   │   ├─ [template-name-01].[extension]
+    ///  │   │   │   └─ ...
+    ///  │   │   └─ ...
+    ///  │   └─ ...
+    ///  └─ ...
+    /// ```
+    DatedGrouped,
+}
+
+impl std::fmt::Display for StructureMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Self::Flat => "flat",
+            Self::FlatGrouped => "flat-grouped",
+            Self::Nested => "nested",
+            Self::NestedGrouped => "nested-grouped",
+            Self::Dated => "dated",
+            Self::DatedGrouped => "dated-grouped",
+        };
+
+        write!(f, "{name}")
+    }
 }
 
 /// An enum representing what a template intends to render.
@@ -360,6 +692,274 @@ pub enum ContextMode {
     /// [book]: crate::models::book::Book
     /// [annotation]: crate::models::annotation::Annotation
     Annotation,
+
+    /// When selected, the template is rendered to multiple files, one per `#tag`, each containing
+    /// all the [`Annotation`][annotation]s with the tag across all [`Book`][book]s. Requires a
+    /// `flat` or `flat-grouped` structure.
+    ///
+    /// ```yaml
+    /// render-context: tag
+    /// ```
+    ///
+    /// ```plaintext
+    /// [output-directory]
+    ///  ├─ [tag-name].[extension]
+    ///  ├─ [tag-name].[extension]
+    ///  └─ ...
+    /// ```
+    ///
+    /// [book]: crate::models::book::Book
+    /// [annotation]: crate::models::annotation::Annotation
+    Tag,
+
+    /// When selected, the template is rendered to a single file per audiobook containing the
+    /// audiobook and all its [`Bookmark`][bookmark]s. Audiobooks are only extracted when
+    /// requested and are rendered separately from books. Requires a `flat`, `flat-grouped`,
+    /// `nested` or `nested-grouped` structure.
+    ///
+    /// ```yaml
+    /// render-context: bookmark
+    /// ```
+    ///
+    /// ```plaintext
+    /// [output-directory]
+    ///  └─ [template-name].[extension]
+    /// ```
+    ///
+    /// [bookmark]: crate::models::bookmark::Bookmark
+    Bookmark,
+}
+
+impl std::fmt::Display for ContextMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Self::Book => "book",
+            Self::Annotation => "annotation",
+            Self::Tag => "tag",
+            Self::Bookmark => "bookmark",
+        };
+
+        write!(f, "{name}")
+    }
+}
+
+/// An enum representing whether and how wide a template's files are wrapped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(untagged)]
+pub enum WrapText {
+    /// Toggles wrapping at the default width. `true` wraps at [`DEFAULT_WRAP_WIDTH`] if no default
+    /// width is set.
+    ///
+    /// ```yaml
+    /// wrap-text: false
+    /// ```
+    Enabled(bool),
+
+    /// Wraps at a width, regardless of the default width.
+    ///
+    /// ```yaml
+    /// wrap-text: 72
+    /// ```
+    Width(usize),
+}
+
+impl WrapText {
+    /// Returns the width to wrap at or `None` if wrapping is disabled.
+    ///
+    /// # Arguments
+    ///
+    /// * `default` - The default width, if any.
+    #[must_use]
+    pub fn width(self, default: Option<usize>) -> Option<usize> {
+        match self {
+            Self::Enabled(true) => Some(default.unwrap_or(DEFAULT_WRAP_WIDTH)),
+            Self::Enabled(false) => None,
+            Self::Width(width) => Some(width),
+        }
+    }
+}
+
+/// An enum representing how a template's files are written.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WriteMode {
+    /// Writes one file per render, replacing any existing file based on the conflict mode.
+    ///
+    /// ```yaml
+    /// write-mode: replace
+    /// ```
+    #[default]
+    Replace,
+
+    /// Adds each annotation to the end of a single file per book. Annotations already in the file
+    /// are skipped. Only valid for [`ContextMode::Annotation`].
+    ///
+    /// ```yaml
+    /// write-mode: append
+    /// ```
+    Append,
+
+    /// Adds each annotation to the start of a single file per book. Annotations already in the
+    /// file are skipped. Only valid for [`ContextMode::Annotation`].
+    ///
+    /// ```yaml
+    /// write-mode: prepend
+    /// ```
+    Prepend,
+}
+
+impl std::fmt::Display for WriteMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Self::Replace => "replace",
+            Self::Append => "append",
+            Self::Prepend => "prepend",
+        };
+
+        write!(f, "{name}")
+    }
+}
+
+/// An enum representing the text encodings a template's files can be written with.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum Encoding {
+    /// UTF-8 without a byte order mark.
+    ///
+    /// ```yaml
+    /// encoding: utf-8
+    /// ```
+    #[default]
+    #[serde(rename = "utf-8")]
+    Utf8,
+
+    /// UTF-8 with a byte order mark. Some apps, like Excel, need this to detect UTF-8 CSV files.
+    ///
+    /// ```yaml
+    /// encoding: utf-8-bom
+    /// ```
+    #[serde(rename = "utf-8-bom")]
+    Utf8Bom,
+
+    /// Little-endian UTF-16 with a byte order mark.
+    ///
+    /// ```yaml
+    /// encoding: utf-16le
+    /// ```
+    #[serde(rename = "utf-16le")]
+    Utf16Le,
+}
+
+impl Encoding {
+    /// The UTF-8 byte order mark.
+    const UTF8_BOM: [u8; 3] = [0xEF, 0xBB, 0xBF];
+
+    /// The little-endian UTF-16 byte order mark.
+    const UTF16LE_BOM: [u8; 2] = [0xFF, 0xFE];
+
+    /// Encodes a string, prepending a byte order mark if required.
+    ///
+    /// # Arguments
+    ///
+    /// * `string` - The string to encode.
+    #[must_use]
+    pub fn encode(self, string: &str) -> Vec<u8> {
+        match self {
+            Self::Utf8 => string.as_bytes().to_vec(),
+            Self::Utf8Bom => [&Self::UTF8_BOM, string.as_bytes()].concat(),
+            Self::Utf16Le => Self::UTF16LE_BOM
+                .into_iter()
+                .chain(string.encode_utf16().flat_map(u16::to_le_bytes))
+                .collect(),
+        }
+    }
+
+    /// Decodes bytes, stripping the byte order mark if present. Returns `None` if the bytes aren't
+    /// valid for the encoding.
+    ///
+    /// # Arguments
+    ///
+    /// * `bytes` - The bytes to decode.
+    #[must_use]
+    pub fn decode(self, bytes: &[u8]) -> Option<String> {
+        match self {
+            Self::Utf8 | Self::Utf8Bom => {
+                let bytes = bytes.strip_prefix(&Self::UTF8_BOM).unwrap_or(bytes);
+                String::from_utf8(bytes.to_vec()).ok()
+            }
+            Self::Utf16Le => {
+                let bytes = bytes.strip_prefix(&Self::UTF16LE_BOM).unwrap_or(bytes);
+
+                if bytes.len() % 2 != 0 {
+                    return None;
+                }
+
+                let units: Vec<u16> = bytes
+                    .chunks_exact(2)
+                    .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+                    .collect();
+
+                String::from_utf16(&units).ok()
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for Encoding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Self::Utf8 => "utf-8",
+            Self::Utf8Bom => "utf-8-bom",
+            Self::Utf16Le => "utf-16le",
+        };
+
+        write!(f, "{name}")
+    }
+}
+
+/// An enum representing the line endings a template's files can be written with.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Newline {
+    /// Writes line endings as they're rendered, usually `\n`.
+    ///
+    /// ```yaml
+    /// newline: lf
+    /// ```
+    #[default]
+    Lf,
+
+    /// Converts all line endings to `\r\n`.
+    ///
+    /// ```yaml
+    /// newline: crlf
+    /// ```
+    Crlf,
+}
+
+impl Newline {
+    /// Applies the line endings to a string.
+    ///
+    /// # Arguments
+    ///
+    /// * `string` - The string to apply the line endings to.
+    #[must_use]
+    pub fn apply(self, string: &str) -> Cow<'_, str> {
+        match self {
+            Self::Lf => Cow::Borrowed(string),
+            Self::Crlf => Cow::Owned(string.replace("\r\n", "\n").replace('\n', "\r\n")),
+        }
+    }
+}
+
+impl std::fmt::Display for Newline {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Self::Lf => "lf",
+            Self::Crlf => "crlf",
+        };
+
+        write!(f, "{name}")
+    }
 }
 
 #[cfg(test)]
@@ -463,6 +1063,111 @@ mod test {
         }
     }
 
+    mod invalid_structure {
+
+        use super::*;
+
+        // Tests that a `dated` template with a `book` context returns an error.
+        #[test]
+        fn dated_book_context() {
+            let template = "<!-- readstor\ngroup: test\ncontext: book\nstructure: dated\n\
+                            extension: txt\n-->\n";
+
+            let result = Template::new("dated.txt", template);
+
+            assert!(matches!(
+                result,
+                Err(Error::TemplateInvalidStructure { .. })
+            ));
+        }
+
+        // Tests that a `tag` context only allows `flat` and `flat-grouped` structures.
+        #[test]
+        fn tag_context() {
+            let template = |structure: &str| {
+                format!(
+                    "<!-- readstor\ngroup: test\ncontext: tag\nstructure: {structure}\n\
+                     extension: txt\n-->\n"
+                )
+            };
+
+            assert!(Template::new("tag.txt", &template("flat")).is_ok());
+            assert!(Template::new("tag.txt", &template("flat-grouped")).is_ok());
+
+            for structure in ["nested", "nested-grouped", "dated", "dated-grouped"] {
+                assert!(matches!(
+                    Template::new("tag.txt", &template(structure)),
+                    Err(Error::TemplateInvalidStructure { .. })
+                ));
+            }
+        }
+    }
+
+    mod invalid_output_directory {
+
+        use super::*;
+
+        fn template(output_directory: &str) -> Result<Template> {
+            let template = format!(
+                "<!-- readstor\ngroup: test\ncontext: book\nstructure: flat\nextension: txt\n\
+                 output-directory: {output_directory}\n-->\n"
+            );
+
+            Template::new("output-directory.txt", &template)
+        }
+
+        // Tests that an output directory outside of the output directory returns an error.
+        #[test]
+        fn outside_output_directory() {
+            for directory in ["../site", "site/../../vault", "/tmp/site"] {
+                assert!(matches!(
+                    template(directory),
+                    Err(Error::TemplateInvalidOutputDirectory { .. })
+                ));
+            }
+        }
+
+        // Tests that a relative output directory is kept.
+        #[test]
+        fn relative_output_directory() {
+            let template = template("./site/books").unwrap();
+
+            assert_eq!(template.root_directory(), PathBuf::from("./site/books"));
+        }
+    }
+
+    mod write_mode {
+
+        use super::*;
+
+        fn template(context: &str) -> Result<Template> {
+            let template = format!(
+                "<!-- readstor\ngroup: test\ncontext: {context}\nstructure: flat\n\
+                 extension: md\nwrite-mode: append\n-->\n"
+            );
+
+            Template::new("write-mode.md", &template)
+        }
+
+        // Tests that the write mode is read from an annotation template's config.
+        #[test]
+        fn annotation_context() {
+            assert_eq!(
+                template("annotation").unwrap().write_mode,
+                WriteMode::Append
+            );
+        }
+
+        // Tests that appending with a `book` context returns an error.
+        #[test]
+        fn book_context() {
+            assert!(matches!(
+                template("book"),
+                Err(Error::TemplateInvalidWriteMode { .. })
+            ));
+        }
+    }
+
     mod valid_config {
 
         use super::*;
@@ -496,6 +1201,15 @@ mod test {
             Template::parse(&template).unwrap();
         }
 
+        // Tests that a `dated` template with an `annotation` context returns no error.
+        #[test]
+        fn dated_annotation_context() {
+            let template = "<!-- readstor\ngroup: test\ncontext: annotation\nstructure: dated\n\
+                            extension: txt\n-->\n";
+
+            Template::new("dated.txt", template).unwrap();
+        }
+
         // Tests that a template with post-config-content returns no error.
         #[test]
         fn post_config_content() {
@@ -506,4 +1220,84 @@ mod test {
             Template::parse(&template).unwrap();
         }
     }
+    mod output_encoding {
+
+        use super::*;
+
+        // Tests that the encoding and line endings are read from a template's config.
+        #[test]
+        fn config() {
+            let template = "<!-- readstor\ngroup: test\ncontext: book\nstructure: flat\n\
+                            extension: csv\nencoding: utf-16le\nnewline: crlf\n-->\n";
+
+            let template = Template::new("test.csv", template).unwrap();
+
+            assert_eq!(template.encoding, Encoding::Utf16Le);
+            assert_eq!(template.newline, Newline::Crlf);
+        }
+
+        // Tests that the wrapping is read from a template's config.
+        #[test]
+        fn wrap_text() {
+            let template = |wrap: &str| {
+                let template = format!(
+                    "<!-- readstor\ngroup: test\ncontext: book\nstructure: flat\n\
+                     extension: md\n{wrap}-->\n"
+                );
+
+                Template::new("test.md", &template).unwrap()
+            };
+
+            assert_eq!(template("").wrap_text, None);
+            assert_eq!(
+                template("wrap-text: false\n").wrap_text,
+                Some(WrapText::Enabled(false))
+            );
+
+            let template = template("wrap-text: 72\npreserve-code-blocks: true\n");
+
+            assert_eq!(template.wrap_text, Some(WrapText::Width(72)));
+            assert!(template.preserve_code_blocks);
+        }
+
+        // Tests that each encoding adds the expected byte order mark and line endings.
+        #[test]
+        fn encode() {
+            let render = |encoding: Encoding, newline: Newline| Render {
+                encoding,
+                newline,
+                ..Default::default()
+            };
+
+            assert_eq!(render(Encoding::Utf8, Newline::Lf).encode("a\nb"), b"a\nb");
+            assert_eq!(
+                render(Encoding::Utf8, Newline::Crlf).encode("a\nb\r\n"),
+                b"a\r\nb\r\n"
+            );
+            assert_eq!(
+                render(Encoding::Utf8Bom, Newline::Lf).encode("a"),
+                [0xEF, 0xBB, 0xBF, b'a']
+            );
+            assert_eq!(
+                render(Encoding::Utf16Le, Newline::Crlf).encode("a\n"),
+                [0xFF, 0xFE, b'a', 0, b'\r', 0, b'\n', 0]
+            );
+        }
+
+        // Tests that decoding reverses encoding.
+        #[test]
+        fn decode() {
+            for encoding in [Encoding::Utf8, Encoding::Utf8Bom, Encoding::Utf16Le] {
+                let render = Render {
+                    encoding,
+                    newline: Newline::Crlf,
+                    ..Default::default()
+                };
+
+                let bytes = render.encode("Hé\nllo");
+
+                assert_eq!(render.decode(&bytes).unwrap(), "Hé\nllo");
+            }
+        }
+    }
 }
@@ -1,7 +1,15 @@
 //! Defines utilities for working with templates.
 
+use std::path::PathBuf;
+
 use walkdir::DirEntry;
 
+use crate::conflict::{self, ConflictMode, WriteSummary};
+
+use super::append;
+use super::managed;
+use super::template::Render;
+
 /// Helper function for [`walkdir`][walkdir]. Filter "hidden" entries e.g. `.hidden`.
 ///
 /// [walkdir]: https://docs.rs/walkdir/latest/walkdir/
@@ -34,3 +42,93 @@ pub fn is_partial_template(entry: &DirEntry) -> bool {
         .to_str()
         .is_some_and(|s| s.starts_with('_'))
 }
+
+/// Splits groups of items into batches of at least `size` items without splitting any group. As
+/// every batch but the last is full, this creates at most `items.div_ceil(size)` batches.
+///
+/// # Arguments
+///
+/// * `groups` - The groups to batch, in order.
+/// * `size` - The number of items at which a batch is full.
+#[must_use]
+pub fn batch_groups<T>(groups: Vec<Vec<T>>, size: usize) -> Vec<Vec<T>> {
+    let mut batches: Vec<Vec<T>> = Vec::new();
+
+    for group in groups {
+        match batches.last_mut() {
+            Some(batch) if batch.len() < size => batch.extend(group),
+            _ => batches.push(group),
+        }
+    }
+
+    batches
+}
+
+/// Writes a batch of files to disk and returns how many were written or skipped. Each file's
+/// parent directory must already exist.
+///
+/// # Arguments
+///
+/// * `files` - The paths to write to along with their renders. Each render's contents are encoded
+///   with its encoding and line endings. See [`Render::encode()`].
+/// * `on_conflict` - What to do if a file already exists. See [`conflict::write()`].
+/// * `managed_regions` - Toggles writing the contents inside a managed region. See
+///   [`managed::write()`]. Renders that are appended or prepended to a file ignore this and
+///   `on_conflict`. See [`append::write()`].
+///
+/// # Errors
+///
+/// Will return `Err` if any IO errors are encountered.
+pub fn write_files(
+    files: &[(PathBuf, &Render)],
+    on_conflict: ConflictMode,
+    managed_regions: bool,
+) -> std::io::Result<WriteSummary> {
+    let mut summary = WriteSummary::default();
+
+    for (path, render) in files {
+        let outcome = if let Some(id) = render.append_id() {
+            append::write(path, render, id)?
+        } else if managed_regions {
+            managed::write(path, render, on_conflict)?
+        } else {
+            conflict::write(path, render.encode(&render.contents), on_conflict)?
+        };
+
+        summary.add(outcome);
+    }
+
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    // Tests that groups are never split across batches.
+    #[test]
+    fn batch_groups_whole() {
+        let groups = vec![
+            vec![0, 1, 2],
+            vec![3],
+            vec![4, 5],
+            vec![6, 7, 8, 9],
+            vec![10],
+        ];
+
+        assert_eq!(
+            batch_groups(groups, 4),
+            vec![vec![0, 1, 2, 3], vec![4, 5, 6, 7, 8, 9], vec![10]]
+        );
+    }
+
+    // Tests that no more batches are created than the items would fill.
+    #[test]
+    fn batch_groups_count() {
+        let groups: Vec<Vec<usize>> = (0..100).map(|i| vec![i; i % 7 + 1]).collect();
+        let count: usize = groups.iter().map(Vec::len).sum();
+
+        assert!(batch_groups(groups, count.div_ceil(8)).len() <= 8);
+    }
+}
@@ -0,0 +1,385 @@
+//! Defines types for restoring Apple Books data from back-ups.
+
+use std::path::{Path, PathBuf};
+
+use crate::applebooks::ios::ABPlist;
+use crate::applebooks::macos::{ABDatabase, ABMacOs};
+use crate::applebooks::Platform;
+use crate::backup::{self, BackupOptions};
+use crate::result::{Error, Result};
+
+/// The directory template for the safety back-up created before restoring.
+///
+/// Outputs `[YYYY-MM-DD-HHMMSS]-[VERSION]-pre-restore` e.g.
+/// `1970-01-01-120000-v0.1-0000-pre-restore`.
+pub const SAFETY_BACKUP_DIRECTORY_TEMPLATE: &str =
+    "{{ now |  date(format='%Y-%m-%d-%H%M%S')}}-{{ version }}-pre-restore";
+
+/// Restores data from a back-up.
+///
+/// The back-up is expected to have the same structure as one created by [`backup::run()`]. Before
+/// any files are overwritten, the current data is backed-up to `backups` using
+/// [`SAFETY_BACKUP_DIRECTORY_TEMPLATE`] as its directory name.
+///
+/// Returns a list of all the files that were restored, or that would have been restored if
+/// [`RestoreOptions::dry_run`] is set.
+///
+/// # Arguments
+///
+/// * `platform` - Which platform to perform the restore for.
+/// * `source` - The back-up directory to restore from.
+/// * `destination` - An optional data directory to restore to. If no destination is provided, the
+///   default Apple Books data directory will be used. This is only available on macOS.
+/// * `backups` - Where to place the safety back-up.
+/// * `options` - The restore options.
+///
+/// # Errors
+///
+/// Will return `Err` if:
+/// * The back-up's structure is invalid.
+/// * No destination is provided for iOS.
+/// * Any IO errors are encountered.
+pub fn run<O>(
+    platform: Platform,
+    source: &Path,
+    destination: Option<&Path>,
+    backups: &Path,
+    options: O,
+) -> Result<Vec<PathBuf>>
+where
+    O: Into<RestoreOptions>,
+{
+    let options: RestoreOptions = options.into();
+
+    self::validate_backup(platform, source)?;
+
+    let destination = match (platform, destination) {
+        (_, Some(destination)) => destination.to_owned(),
+        (Platform::MacOs, None) => crate::applebooks::macos::defaults::DATA_DIRECTORY.to_owned(),
+        (Platform::IOs, None) => return Err(Error::IOsRestoreToDeviceUnsupported),
+    };
+
+    let items = self::collect_items(platform, source, &destination)?;
+
+    if options.dry_run {
+        return Ok(items.into_iter().map(|(_, item)| item).collect());
+    }
+
+    let backup_options = BackupOptions {
+        directory_template: Some(SAFETY_BACKUP_DIRECTORY_TEMPLATE.to_owned()),
+    };
+
+    backup::run(platform, &destination, backups, backup_options)?;
+
+    if let Platform::MacOs = platform {
+        self::remove_databases(&destination)?;
+    }
+
+    for (item_source, item_destination) in &items {
+        if let Some(parent) = item_destination.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        std::fs::copy(item_source, item_destination)?;
+
+        tracing::debug!(path = %item_destination.display(), "restored");
+    }
+
+    Ok(items.into_iter().map(|(_, item)| item).collect())
+}
+
+/// Validates that a back-up directory contains all the expected files.
+///
+/// See [`ABMacOs`] and [`ABIOs`][abios] for more information on how the back-up directory should
+/// be structured.
+///
+/// # Arguments
+///
+/// * `platform` - Which platform the back-up was created for.
+/// * `source` - The back-up directory to validate.
+///
+/// [abios]: crate::applebooks::ios::ABIOs
+fn validate_backup(platform: Platform, source: &Path) -> Result<()> {
+    let is_valid = match platform {
+        Platform::MacOs => [ABDatabase::Books, ABDatabase::Annotations]
+            .into_iter()
+            .all(|database| ABMacOs::get_database(source, database).is_ok()),
+        Platform::IOs => [ABPlist::Books, ABPlist::Annotations]
+            .into_iter()
+            .all(|plist| source.join(plist.to_string()).is_file()),
+    };
+
+    if !is_valid {
+        return Err(Error::RestoreInvalidBackup {
+            path: source.display().to_string(),
+        });
+    }
+
+    Ok(())
+}
+
+/// Returns a list of source and destination paths for all the files to restore.
+///
+/// # Arguments
+///
+/// * `platform` - Which platform to perform the restore for.
+/// * `source` - The back-up directory to restore from.
+/// * `destination` - The data directory to restore to.
+///
+/// # Errors
+///
+/// Will return `Err` if any IO errors are encountered.
+fn collect_items(
+    platform: Platform,
+    source: &Path,
+    destination: &Path,
+) -> Result<Vec<(PathBuf, PathBuf)>> {
+    let mut items = Vec::new();
+
+    match platform {
+        Platform::MacOs => {
+            for database in &[ABDatabase::Books, ABDatabase::Annotations] {
+                let name = database.to_string();
+
+                // -> [back-up]/[name]
+                let database_source = source.join(&name);
+
+                // -> [data-directory]/[name]
+                let database_destination = destination.join(&name);
+
+                for entry in std::fs::read_dir(&database_source)? {
+                    let entry = entry?;
+
+                    if !entry.path().is_file() {
+                        continue;
+                    }
+
+                    items.push((entry.path(), database_destination.join(entry.file_name())));
+                }
+            }
+        }
+        Platform::IOs => {
+            for plist in &[ABPlist::Books, ABPlist::Annotations] {
+                let name = plist.to_string();
+                items.push((source.join(&name), destination.join(&name)));
+            }
+        }
+    }
+
+    items.sort();
+
+    Ok(items)
+}
+
+/// Removes all `*.sqlite` files, including any `*.sqlite-wal` and `*.sqlite-shm` files, from the
+/// database directories.
+///
+/// Leftover write-ahead logs would otherwise be applied on top of the restored databases.
+///
+/// # Arguments
+///
+/// * `destination` - The data directory to restore to.
+///
+/// # Errors
+///
+/// Will return `Err` if any IO errors are encountered.
+fn remove_databases(destination: &Path) -> Result<()> {
+    for database in &[ABDatabase::Books, ABDatabase::Annotations] {
+        // -> [data-directory]/[name]/[name]*.sqlite*
+        let directory = destination.join(database.to_string());
+
+        // The directory is escaped as user-supplied paths may contain glob characters e.g. `[`.
+        let pattern = format!(
+            "{}/{database}*.sqlite*",
+            glob::Pattern::escape(&directory.to_string_lossy())
+        );
+
+        let paths = glob::glob(&pattern).map_err(|error| Error::OtherError {
+            error: format!("invalid path {}: {error}", directory.display()),
+        })?;
+
+        for path in paths.filter_map(std::result::Result::ok) {
+            std::fs::remove_file(&path)?;
+            tracing::debug!(path = %path.display(), "removed");
+        }
+    }
+
+    Ok(())
+}
+
+/// A struct representing options for running restores.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RestoreOptions {
+    /// Toggles listing the files to restore without writing anything.
+    pub dry_run: bool,
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    use crate::defaults::{CRATE_ROOT, TEMP_OUTPUT_DIRECTORY};
+    use crate::utils;
+
+    fn test_data(directory: &str, name: &str) -> PathBuf {
+        let mut path = CRATE_ROOT.to_owned();
+        path.extend(["data", directory, name].iter());
+        path
+    }
+
+    /// Creates a fresh copy of a test data directory to restore into.
+    fn setup(platform: Platform, name: &str) -> (PathBuf, PathBuf) {
+        let root = TEMP_OUTPUT_DIRECTORY.join("tests-restore").join(name);
+
+        if root.exists() {
+            std::fs::remove_dir_all(&root).unwrap();
+        }
+
+        let data = root.join("data");
+        let backups = root.join("backups");
+
+        match platform {
+            Platform::MacOs => {
+                utils::copy_dir(test_data("databases", "books-new"), &data).unwrap();
+            }
+            Platform::IOs => {
+                utils::copy_dir(test_data("plists", "books-new"), &data).unwrap();
+            }
+        }
+
+        (data, backups)
+    }
+
+    mod macos {
+
+        use super::*;
+
+        // Tests that a valid back-up passes validation.
+        #[test]
+        fn valid_backup() {
+            validate_backup(Platform::MacOs, &test_data("databases", "books-annotated")).unwrap();
+        }
+
+        // Tests that an invalid back-up returns an error.
+        #[test]
+        #[should_panic(expected = "RestoreInvalidBackup")]
+        fn invalid_backup() {
+            validate_backup(Platform::MacOs, &test_data("plists", "books-annotated")).unwrap();
+        }
+
+        // Tests that a dry-run leaves the data directory untouched and creates no back-up.
+        #[test]
+        fn dry_run() {
+            let (data, backups) = setup(Platform::MacOs, "macos-dry-run");
+            let source = test_data("databases", "books-annotated");
+            let options = RestoreOptions { dry_run: true };
+
+            let restored = run(Platform::MacOs, &source, Some(&data), &backups, options).unwrap();
+
+            assert!(!restored.is_empty());
+            assert!(restored.iter().all(|path| path.starts_with(&data)));
+            assert!(!backups.exists());
+
+            let database = ABMacOs::get_database(&data, ABDatabase::Books).unwrap();
+            let original =
+                ABMacOs::get_database(&test_data("databases", "books-new"), ABDatabase::Books)
+                    .unwrap();
+
+            assert_eq!(
+                std::fs::read(database).unwrap(),
+                std::fs::read(original).unwrap()
+            );
+        }
+
+        // Tests that restoring copies the back-up's databases and creates a safety back-up.
+        #[test]
+        fn restore() {
+            let (data, backups) = setup(Platform::MacOs, "macos-restore");
+            let source = test_data("databases", "books-annotated");
+
+            run(
+                Platform::MacOs,
+                &source,
+                Some(&data),
+                &backups,
+                RestoreOptions::default(),
+            )
+            .unwrap();
+
+            for database in [ABDatabase::Books, ABDatabase::Annotations] {
+                let restored = ABMacOs::get_database(&data, database).unwrap();
+                let original = ABMacOs::get_database(&source, database).unwrap();
+
+                assert_eq!(
+                    std::fs::read(restored).unwrap(),
+                    std::fs::read(original).unwrap()
+                );
+            }
+
+            assert_eq!(std::fs::read_dir(&backups).unwrap().count(), 1);
+        }
+    }
+
+    mod ios {
+
+        use super::*;
+
+        // Tests that a valid back-up passes validation.
+        #[test]
+        fn valid_backup() {
+            validate_backup(Platform::IOs, &test_data("plists", "books-annotated")).unwrap();
+        }
+
+        // Tests that an invalid back-up returns an error.
+        #[test]
+        #[should_panic(expected = "RestoreInvalidBackup")]
+        fn invalid_backup() {
+            validate_backup(Platform::IOs, &test_data("databases", "books-annotated")).unwrap();
+        }
+
+        // Tests that restoring copies the back-up's plists and creates a safety back-up.
+        #[test]
+        fn restore() {
+            let (data, backups) = setup(Platform::IOs, "ios-restore");
+            let source = test_data("plists", "books-annotated");
+
+            run(
+                Platform::IOs,
+                &source,
+                Some(&data),
+                &backups,
+                RestoreOptions::default(),
+            )
+            .unwrap();
+
+            for plist in [ABPlist::Books, ABPlist::Annotations] {
+                let name = plist.to_string();
+
+                assert_eq!(
+                    std::fs::read(data.join(&name)).unwrap(),
+                    std::fs::read(source.join(&name)).unwrap()
+                );
+            }
+
+            assert_eq!(std::fs::read_dir(&backups).unwrap().count(), 1);
+        }
+
+        // Tests that restoring to a device returns an error.
+        #[test]
+        #[should_panic(expected = "IOsRestoreToDeviceUnsupported")]
+        fn restore_to_device() {
+            let (_, backups) = setup(Platform::IOs, "ios-restore-to-device");
+            let source = test_data("plists", "books-annotated");
+
+            run(
+                Platform::IOs,
+                &source,
+                None,
+                &backups,
+                RestoreOptions::default(),
+            )
+            .unwrap();
+        }
+    }
+}
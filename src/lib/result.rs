@@ -1,16 +1,30 @@
 //! Defines the result and error types for this crate.
 
-use rusty_libimobiledevice::error::AfcError;
-
 /// A generic result type.
 pub type Result<T> = std::result::Result<T, Error>;
 
 /// An enum representing all possible library errors.
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
-    /// Error returned when the default Apple Books database cannot be found.
-    #[error("Missing default Apple Books databases")]
-    MacOsMissingDefaultDatabase,
+    /// Error returned when an Apple Books database cannot be found.
+    #[error("Missing '{name}*.sqlite' in {path}")]
+    MacOsMissingDatabase {
+        /// The basename of the database: `BKLibrary` or `AEAnnotation`.
+        name: String,
+        /// The path to the directory expected to contain the database.
+        path: String,
+    },
+
+    /// Error returned when more than one file matches an Apple Books database's name.
+    #[error("Found {count} '{name}*.sqlite' databases in {path}, expected one")]
+    MacOsAmbiguousDatabase {
+        /// The basename of the database: `BKLibrary` or `AEAnnotation`.
+        name: String,
+        /// The path to the directory containing the databases.
+        path: String,
+        /// The number of matching databases.
+        count: usize,
+    },
 
     /// Error returned when there are issues connecting to a database.
     #[error("Unable to connect to '{name}*.sqlite' at {path}")]
@@ -21,6 +35,14 @@ pub enum Error {
         path: String,
     },
 
+    /// Error returned when macOS denies access to the Apple Books data. This usually means the app
+    /// running `readstor` hasn't been granted Full Disk Access.
+    #[error("Permission denied while reading Apple Books data at {path}")]
+    MacOsPermissionDenied {
+        /// The path that couldn't be read.
+        path: String,
+    },
+
     /// Error returned when the currently installed version of Apple Books for macOS is unsupported.
     ///
     /// This most likely means that the database schema is different than the one the query has been
@@ -48,8 +70,8 @@ pub enum Error {
     /// Error returned if there are any errors reading the device's disk.
     #[error("Unable to read iOS device: {error}")]
     IOsDeviceReadError {
-        /// Forwarded error from `libmobiledevice`.
-        error: AfcError,
+        /// The source error string e.g. forwarded from `libimobiledevice`.
+        error: String,
     },
 
     /// Error returned when the currently installed version of Apple Books for iOS is unsupported.
@@ -59,6 +81,202 @@ pub enum Error {
     /// considered unsupported.
     #[error("Unsupported version of Apple Books for iOS: {error}")]
     IOsUnsupportedAppleBooksVersion {
+        /// The source error string followed by a report of the plist's mismatched keys and
+        /// structure, if it could be read.
+        error: String,
+    },
+
+    /// Error returned when an iOS plist deserializes but is missing the expected data.
+    #[error("Invalid iOS plist at {path}: {error}")]
+    IOsInvalidPlist {
+        /// The path to the plist.
+        path: String,
+        /// A description of what's missing.
+        error: String,
+    },
+
+    /// Error returned if an iOS back-up's manifest cannot be read e.g. the back-up is encrypted.
+    #[error("Unable to read iOS back-up manifest at: {path}")]
+    IOsBackupManifestError {
+        /// The path to the manifest.
+        path: String,
+    },
+
+    /// Error returned if a file is missing from an iOS back-up.
+    #[error("Missing '{name}' in iOS back-up at: {path}")]
+    IOsBackupMissingFile {
+        /// The name of the missing file.
+        name: String,
+
+        /// The path to the back-up.
+        path: String,
+    },
+
+    /// Error returned when attempting to restore data directly to an iOS device.
+    #[error("Restoring to an iOS device is unsupported, a data directory is required")]
+    IOsRestoreToDeviceUnsupported,
+
+    /// Error returned when a back-up is missing any of the expected files.
+    #[error("Invalid back-up at: {path}")]
+    RestoreInvalidBackup {
+        /// The path to the back-up.
+        path: String,
+    },
+
+    /// Error returned when a path is neither an export nor a back-up.
+    #[error("No export or back-up found at: {path}")]
+    DiffInvalidSource {
+        /// The path to the export or back-up.
+        path: String,
+    },
+
+    /// Error returned when a file isn't a snapshot.
+    #[error("Invalid snapshot at: {path}")]
+    SnapshotInvalid {
+        /// The path to the snapshot.
+        path: String,
+    },
+
+    /// Error returned when a book was exported by a newer version than the one migrating it.
+    #[error("Unsupported export format version {version} at: {path}")]
+    MigrateUnsupportedVersion {
+        /// The path to the book's file.
+        path: String,
+        /// The book's format version.
+        version: u64,
+    },
+
+    /// Error returned when a migrated export file cannot be read as books or annotations.
+    #[error("Unable to migrate {path}: {error}")]
+    MigrateInvalidFile {
+        /// The path to the file.
+        path: String,
+        /// The reason the file cannot be read.
+        error: String,
+    },
+
+    /// Error returned when a path doesn't contain a JSON export.
+    #[error("No export found at: {path}")]
+    ImportInvalidSource {
+        /// The path to the export.
+        path: String,
+    },
+
+    /// Error returned when a file doesn't contain any Kindle highlights or notes.
+    #[error("No Kindle clippings found at: {path}")]
+    ImportInvalidClippings {
+        /// The path to the clippings file.
+        path: String,
+    },
+
+    /// Error returned when a file cannot be read as a Kobo database.
+    #[error("Unable to read Kobo database at {path}: {error}")]
+    ImportInvalidKoboDatabase {
+        /// The path to the database.
+        path: String,
+        /// The source error string.
+        error: String,
+    },
+
+    /// Error returned when a tag map cannot be read or parsed.
+    #[error("Unable to read tag map at {path}: {error}")]
+    InvalidTagMap {
+        /// The path to the tag map.
+        path: String,
+        /// The source error string.
+        error: String,
+    },
+
+    /// Error returned when a file isn't a search index or was built with a different version.
+    #[error("Invalid or outdated search index at: {path}")]
+    SearchInvalidIndex {
+        /// The path to the index.
+        path: String,
+    },
+
+    /// Error returned when a snapshot was created with a newer, unsupported format version.
+    #[error("Unsupported snapshot version {version} at: {path}")]
+    SnapshotUnsupportedVersion {
+        /// The path to the snapshot.
+        path: String,
+        /// The snapshot's format version.
+        version: u32,
+    },
+
+    /// Error returned when no book matches an id.
+    #[error("No book found with id: {id}")]
+    BookNotFound {
+        /// The requested id.
+        id: String,
+    },
+
+    /// Error returned when more than one book matches an id prefix.
+    #[error("More than one book matches id `{id}`: {matches}")]
+    BookIdAmbiguous {
+        /// The requested id.
+        id: String,
+        /// The ids and titles of the matching books.
+        matches: String,
+    },
+
+    /// Error returned when no exporter is registered under a name.
+    #[error("Unknown export format `{name}`. Supported formats: {supported}")]
+    ExportUnknownFormat {
+        /// The requested exporter's name.
+        name: String,
+        /// The names of all registered exporters.
+        supported: String,
+    },
+
+    /// Error returned when an export's book and annotations filenames are identical.
+    #[error("Book and annotations export filenames are identical: {path}")]
+    ExportFilenameCollision {
+        /// The path to the colliding file.
+        path: String,
+    },
+
+    /// Error returned when an upload target cannot be parsed.
+    #[error("Invalid upload target: '{target}'")]
+    UploadInvalidTarget {
+        /// The upload target string.
+        target: String,
+    },
+
+    /// Error returned when credentials required for an upload are missing.
+    #[error("Missing upload credentials: '{name}' is not set")]
+    UploadMissingCredentials {
+        /// The name of the missing environment variable.
+        name: String,
+    },
+
+    /// Error returned when an upload request fails.
+    #[error("Failed to upload to {url}: {error}")]
+    UploadError {
+        /// The request's URL.
+        url: String,
+        /// The source error string.
+        error: String,
+    },
+
+    /// Error returned when credentials required for a sync are missing.
+    #[error("Missing sync credentials: '{name}' is not set")]
+    SyncMissingCredentials {
+        /// The name of the missing environment variable.
+        name: String,
+    },
+
+    /// Error returned when a Notion database cannot be synced to.
+    #[error("Notion database '{id}' does not have a title property")]
+    SyncInvalidDatabase {
+        /// The database's id.
+        id: String,
+    },
+
+    /// Error returned when a sync request fails.
+    #[error("Failed to sync with {url}: {error}")]
+    SyncError {
+        /// The request's URL.
+        url: String,
         /// The source error string.
         error: String,
     },
@@ -71,6 +289,49 @@ pub enum Error {
         path: String,
     },
 
+    /// Error returned when a template's structure mode cannot be used with its context mode.
+    #[error("Invalid template structure `{structure}` for `context: {context}` in: {path}")]
+    TemplateInvalidStructure {
+        /// The partial path to the template e.g. `nested/template.md`.
+        path: String,
+        /// The name of the structure mode.
+        structure: String,
+        /// The name of the context mode.
+        context: String,
+    },
+
+    /// Error returned if a template's output directory isn't a relative path within the output
+    /// directory.
+    #[error(
+        "Invalid template output directory `{directory}` for: {path}. Requires a relative path without `..`"
+    )]
+    TemplateInvalidOutputDirectory {
+        /// The partial path to the template e.g. `nested/template.md`.
+        path: String,
+        /// The template's output directory.
+        directory: String,
+    },
+
+    /// Error returned when a template's write mode cannot be used with its context mode.
+    #[error("Invalid template write mode `{write_mode}` for `context: {context}` in: {path}")]
+    TemplateInvalidWriteMode {
+        /// The partial path to the template e.g. `nested/template.md`.
+        path: String,
+        /// The name of the write mode.
+        write_mode: String,
+        /// The name of the context mode.
+        context: String,
+    },
+
+    /// Error returned when a template's path cannot be made relative to the templates directory.
+    #[error("Unable to resolve template {path} relative to templates directory: {directory}")]
+    TemplateInvalidPath {
+        /// The path to the template.
+        path: String,
+        /// The path to the templates directory.
+        directory: String,
+    },
+
     /// Error returned when a requested template-group does not exist.
     #[error("No template-group named: '{name}'")]
     TemplateInvalidGroup {
@@ -78,6 +339,47 @@ pub enum Error {
         name: String,
     },
 
+    /// Error returned when a template takes longer to render than the maximum render time.
+    #[error("Template took longer than {timeout:?} to render: {path}")]
+    TemplateTimeout {
+        /// The partial path to the template e.g. `nested/template.md`.
+        path: String,
+        /// The maximum render time.
+        timeout: std::time::Duration,
+    },
+
+    /// Error returned when a template's output is larger than the maximum render size.
+    #[error("Template output exceeds the maximum of {max} bytes: {path}")]
+    TemplateTooLarge {
+        /// The partial path to the template e.g. `nested/template.md`.
+        path: String,
+        /// The maximum render size in bytes.
+        max: usize,
+    },
+
+    /// Error returned when an EPUB's container, package document or table of contents is invalid.
+    #[error("Invalid EPUB: {error}")]
+    EpubInvalid {
+        /// The source error string.
+        error: String,
+    },
+
+    /// Error returned when a Calibre library's database cannot be read.
+    #[error("Unable to read Calibre library at {path}: {error}")]
+    CalibreLibraryError {
+        /// The path to the library's database.
+        path: String,
+        /// The source error string.
+        error: String,
+    },
+
+    /// Error returned when an annotation sort key cannot be parsed.
+    #[error("Invalid sort key: '{key}'")]
+    SortInvalidKey {
+        /// The sort key string.
+        key: String,
+    },
+
     /// Error returned if [`tera`][tera] encounters any errors.
     ///
     /// [tera]: https://docs.rs/tera/latest/tera/
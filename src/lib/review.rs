@@ -0,0 +1,278 @@
+//! Defines types for selecting annotations to review each day.
+//!
+//! Annotations are laid out in a shuffled rotation and each day reviews the next few annotations
+//! in it. The rotation is seeded by the annotations themselves so running a review twice on the
+//! same day selects the same annotations, and consecutive days continue where the previous day
+//! left off. Every annotation is reviewed once per rotation before any annotation is repeated.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeSet, HashSet};
+use std::hash::{Hash, Hasher};
+
+use chrono::{Datelike, NaiveDate};
+use serde::Serialize;
+
+use crate::models::annotation::Annotation;
+use crate::models::book::Book;
+use crate::models::entry::Entry;
+
+/// The default number of annotations reviewed per day.
+pub const DEFAULT_REVIEW_COUNT: usize = 5;
+
+/// The number of times an annotation with a weighted tag appears in each rotation.
+pub const TAG_WEIGHT: usize = 3;
+
+/// A struct representing options for selecting annotations to review.
+#[derive(Debug, Clone)]
+pub struct ReviewOptions {
+    /// The number of annotations to select.
+    pub count: usize,
+
+    /// The date to select annotations for.
+    pub date: NaiveDate,
+
+    /// Tags, including their leading `#`, whose annotations are reviewed more often e.g.
+    /// `#favorite`.
+    pub weight_tags: BTreeSet<String>,
+}
+
+impl Default for ReviewOptions {
+    fn default() -> Self {
+        Self {
+            count: DEFAULT_REVIEW_COUNT,
+            date: NaiveDate::default(),
+            weight_tags: BTreeSet::new(),
+        }
+    }
+}
+
+/// A struct representing an annotation selected for review along with its book.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct ReviewItem<'a> {
+    /// The annotation's book.
+    pub book: &'a Book,
+
+    /// The annotation.
+    pub annotation: &'a Annotation,
+}
+
+/// Selects annotations to review on a date.
+///
+/// Annotations with any of the weighted tags take up [`TAG_WEIGHT`] places in the rotation and
+/// are therefore reviewed more often. No annotation is selected more than once per day. If there
+/// are fewer annotations than requested, all of them are selected.
+///
+/// # Arguments
+///
+/// * `entries` - The entries to select annotations from.
+/// * `options` - The review options.
+#[must_use]
+pub fn run<'a, I>(entries: I, options: &ReviewOptions) -> Vec<ReviewItem<'a>>
+where
+    I: IntoIterator<Item = &'a Entry>,
+{
+    let candidates: Vec<ReviewItem<'a>> = entries
+        .into_iter()
+        .flat_map(|entry| {
+            entry.annotations.iter().map(|annotation| ReviewItem {
+                book: &entry.book,
+                annotation,
+            })
+        })
+        .collect();
+
+    let count = options.count.min(candidates.len());
+
+    if count == 0 {
+        return Vec::new();
+    }
+
+    // Each annotation takes up one slot in the rotation per unit of weight.
+    let slots: Vec<(usize, usize)> = candidates
+        .iter()
+        .enumerate()
+        .flat_map(|(index, item)| {
+            let weight = if item
+                .annotation
+                .tags
+                .iter()
+                .any(|tag| options.weight_tags.contains(tag))
+            {
+                TAG_WEIGHT
+            } else {
+                1
+            };
+
+            (0..weight).map(move |copy| (index, copy))
+        })
+        .collect();
+
+    let length = slots.len() as u64;
+    let day = u64::try_from(options.date.num_days_from_ce()).unwrap_or_default();
+
+    let mut selected = Vec::with_capacity(count);
+    let mut seen = HashSet::new();
+    let mut rotation: Option<(u64, Vec<usize>)> = None;
+
+    // Every annotation appears at least once per rotation so two rotations always suffice.
+    for position in (day * options.count as u64..).take(slots.len() * 2) {
+        let cycle = position / length;
+
+        let order = match &rotation {
+            Some((current, order)) if *current == cycle => order,
+            _ => {
+                let order = self::shuffle(&candidates, &slots, cycle);
+                &rotation.insert((cycle, order)).1
+            }
+        };
+
+        // This is safe as `position % length` is always within the rotation.
+        #[allow(clippy::cast_possible_truncation)]
+        let index = order[(position % length) as usize];
+
+        if seen.insert(index) {
+            selected.push(candidates[index]);
+        }
+
+        if selected.len() == count {
+            break;
+        }
+    }
+
+    selected
+}
+
+/// Returns the order of the candidates' indices for a rotation.
+///
+/// # Arguments
+///
+/// * `candidates` - The annotations to order.
+/// * `slots` - Each slot's candidate index and copy number.
+/// * `cycle` - The rotation's number.
+fn shuffle(candidates: &[ReviewItem<'_>], slots: &[(usize, usize)], cycle: u64) -> Vec<usize> {
+    let mut keyed: Vec<(u64, usize)> = slots
+        .iter()
+        .map(|&(index, copy)| {
+            let mut hasher = DefaultHasher::new();
+            cycle.hash(&mut hasher);
+            candidates[index].annotation.metadata.id.hash(&mut hasher);
+            copy.hash(&mut hasher);
+
+            (hasher.finish(), index)
+        })
+        .collect();
+
+    keyed.sort_unstable();
+
+    keyed.into_iter().map(|(_, index)| index).collect()
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    fn entry(ids: &[&str]) -> Entry {
+        let mut entry = Entry::default();
+
+        for id in ids {
+            let mut annotation = Annotation::default();
+            annotation.metadata.id = (*id).to_string();
+
+            if id.starts_with('f') {
+                annotation.tags.insert("#favorite".to_string());
+            }
+
+            entry.annotations.push(annotation);
+        }
+
+        entry
+    }
+
+    fn ids(items: &[ReviewItem<'_>]) -> Vec<String> {
+        items
+            .iter()
+            .map(|item| item.annotation.metadata.id.clone())
+            .collect()
+    }
+
+    fn options(count: usize, day: u32) -> ReviewOptions {
+        ReviewOptions {
+            count,
+            date: NaiveDate::from_ymd_opt(2024, 1, day).unwrap(),
+            ..Default::default()
+        }
+    }
+
+    // Tests that the same annotations are selected for the same date.
+    #[test]
+    fn deterministic() {
+        let entries = [entry(&["A", "B", "C", "D", "E", "F", "G", "H"])];
+
+        let first = run(&entries, &options(3, 1));
+        let second = run(&entries, &options(3, 1));
+
+        assert_eq!(first.len(), 3);
+        assert_eq!(ids(&first), ids(&second));
+    }
+
+    // Tests that consecutive days within a rotation never repeat an annotation.
+    #[test]
+    fn rotation() {
+        let entries = [entry(&["A", "B", "C", "D", "E", "F", "G", "H", "I"])];
+
+        let mut reviewed: Vec<String> = (3..=5)
+            .flat_map(|day| ids(&run(&entries, &options(3, day))))
+            .collect();
+
+        reviewed.sort();
+        reviewed.dedup();
+
+        // 2024-01-03 is day 738,888 so three annotations a day starts a rotation of nine.
+        assert_eq!(reviewed.len(), 9);
+    }
+
+    // Tests that every annotation is selected if there are fewer than requested.
+    #[test]
+    fn fewer_than_count() {
+        let entries = [entry(&["A", "B"]), entry(&["C"])];
+
+        let mut selected = ids(&run(&entries, &options(5, 1)));
+        selected.sort();
+
+        assert_eq!(selected, ["A", "B", "C"]);
+        assert!(run(&[Entry::default()], &options(5, 1)).is_empty());
+    }
+
+    // Tests that annotations with a weighted tag are selected more often.
+    #[test]
+    fn weighted() {
+        let entries = [entry(&["f1", "A", "B", "C", "D", "E", "F", "G", "H", "I"])];
+
+        let mut options = options(1, 1);
+        options.weight_tags.insert("#favorite".to_string());
+
+        let mut weighted = 0;
+        let mut unweighted = 0;
+
+        for day in 1..=120 {
+            options.date = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap() + chrono::Days::new(day);
+
+            if ids(&run(&entries, &options))[0] == "f1" {
+                weighted += 1;
+            }
+        }
+
+        options.weight_tags.clear();
+
+        for day in 1..=120 {
+            options.date = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap() + chrono::Days::new(day);
+
+            if ids(&run(&entries, &options))[0] == "f1" {
+                unweighted += 1;
+            }
+        }
+
+        assert!(weighted > unweighted);
+    }
+}
@@ -0,0 +1,524 @@
+//! Defines helpers for describing the exported data with machine-readable schemas.
+//!
+//! The schemas are derived from the same models that are serialized to `book.json` and
+//! `annotations.json` so they always match what an export writes. They can be emitted as
+//! [JSON Schema][json-schema] or as TypeScript declarations.
+//!
+//! [json-schema]: https://json-schema.org
+
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde_json::{json, Map, Value};
+
+use crate::export::FORMAT_VERSION;
+use crate::models::annotation::Annotation;
+use crate::models::book::Book;
+use crate::result::Result;
+
+/// The name of the file the `book.json` JSON Schema is written to.
+pub const BOOK_SCHEMA_FILENAME: &str = "book.schema.json";
+
+/// The name of the file the `annotations.json` JSON Schema is written to.
+pub const ANNOTATIONS_SCHEMA_FILENAME: &str = "annotations.schema.json";
+
+/// The name of the file the TypeScript declarations are written to.
+pub const TYPESCRIPT_FILENAME: &str = "readstor.d.ts";
+
+/// The prefix of references to a schema's definitions.
+const DEFINITIONS_PREFIX: &str = "#/definitions/";
+
+/// Captures a rustdoc link reference on its own line e.g. `[epub-run]: crate::epub::run`.
+static RE_LINK_REFERENCE: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?m)^\[[^\]]+\]: .*$").unwrap());
+
+/// Captures a rustdoc link and its text e.g. ``[`epub::run()`][epub-run]``.
+static RE_LINK: Lazy<Regex> = Lazy::new(|| Regex::new(r"\[(`[^`]+`)\](?:\[[^\]]+\])?").unwrap());
+
+/// An enum representing the available schema formats.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum SchemaFormat {
+    /// JSON Schema documents, one for `book.json` and one for `annotations.json`.
+    #[default]
+    JsonSchema,
+
+    /// TypeScript declarations for both files.
+    TypeScript,
+}
+
+/// Returns the JSON Schema of a `book.json` file.
+#[must_use]
+pub fn book() -> Value {
+    let schema = schemars::schema_for!(Book);
+
+    let mut schema = serde_json::to_value(schema).unwrap_or_default();
+    self::tidy(&mut schema);
+
+    // Exported books are stamped with the format's version, which isn't part of the model.
+    if let Some(object) = schema.as_object_mut() {
+        if let Some(Value::Object(properties)) = object.get_mut("properties") {
+            properties.insert(
+                "format_version".to_string(),
+                json!({
+                    "description": "The version of the export format the book was exported in.",
+                    "type": "integer",
+                    "const": FORMAT_VERSION,
+                }),
+            );
+        }
+
+        if let Some(Value::Array(required)) = object.get_mut("required") {
+            required.push(json!("format_version"));
+        }
+    }
+
+    schema
+}
+
+/// Returns the JSON Schema of an `annotations.json` file.
+#[must_use]
+pub fn annotations() -> Value {
+    let schema = schemars::schema_for!(Vec<Annotation>);
+
+    let mut schema = serde_json::to_value(schema).unwrap_or_default();
+    self::tidy(&mut schema);
+
+    // The generated title e.g. `Array_of_Annotation` isn't very meaningful.
+    if let Some(object) = schema.as_object_mut() {
+        object.insert("title".to_string(), json!("Annotations"));
+        object.insert(
+            "description".to_string(),
+            json!("A book's annotations as exported to `annotations.json`."),
+        );
+    }
+
+    schema
+}
+
+/// Returns the TypeScript declarations of the `book.json` and `annotations.json` files.
+///
+/// Each schema definition is declared as an exported `interface` or `type` along with `Book` and
+/// `Annotations` for the files themselves.
+#[must_use]
+pub fn typescript() -> String {
+    let mut definitions: BTreeMap<String, Value> = BTreeMap::new();
+
+    for (name, mut schema) in [("Book", self::book()), ("Annotations", self::annotations())] {
+        let Some(object) = schema.as_object_mut() else {
+            continue;
+        };
+
+        if let Some(Value::Object(nested)) = object.remove("definitions") {
+            definitions.extend(nested);
+        }
+
+        object.remove("$schema");
+        object.remove("title");
+
+        definitions.insert(name.to_string(), schema);
+    }
+
+    let declarations: Vec<String> = definitions
+        .iter()
+        .map(|(name, schema)| self::declaration(name, schema))
+        .collect();
+
+    format!(
+        "// Generated by `readstor schema --format typescript`.\n\n{}",
+        declarations.join("\n")
+    )
+}
+
+/// Returns the schemas in a format serialized as a string.
+///
+/// JSON Schemas are returned as a single JSON object keyed by the file each schema describes.
+///
+/// # Arguments
+///
+/// * `format` - The schema format.
+///
+/// # Errors
+///
+/// Will return `Err` if the schemas cannot be serialized.
+pub fn to_string(format: SchemaFormat) -> Result<String> {
+    let string = match format {
+        SchemaFormat::JsonSchema => serde_json::to_string_pretty(&json!({
+            "book.json": self::book(),
+            "annotations.json": self::annotations(),
+        }))?,
+        SchemaFormat::TypeScript => self::typescript(),
+    };
+
+    Ok(string)
+}
+
+/// Writes the schemas in a format to a directory. Returns the paths of the written files.
+///
+/// JSON Schemas are written to [`BOOK_SCHEMA_FILENAME`] and [`ANNOTATIONS_SCHEMA_FILENAME`] and
+/// TypeScript declarations to [`TYPESCRIPT_FILENAME`].
+///
+/// # Arguments
+///
+/// * `directory` - The directory to write the schemas to.
+/// * `format` - The schema format.
+///
+/// # Errors
+///
+/// Will return `Err` if:
+/// * The schemas cannot be serialized.
+/// * Any IO errors are encountered.
+pub fn write(directory: &Path, format: SchemaFormat) -> Result<Vec<PathBuf>> {
+    let files = match format {
+        SchemaFormat::JsonSchema => vec![
+            (
+                BOOK_SCHEMA_FILENAME,
+                serde_json::to_string_pretty(&self::book())?,
+            ),
+            (
+                ANNOTATIONS_SCHEMA_FILENAME,
+                serde_json::to_string_pretty(&self::annotations())?,
+            ),
+        ],
+        SchemaFormat::TypeScript => vec![(TYPESCRIPT_FILENAME, self::typescript())],
+    };
+
+    std::fs::create_dir_all(directory)?;
+
+    let mut paths = Vec::with_capacity(files.len());
+
+    for (filename, contents) in files {
+        let path = directory.join(filename);
+        std::fs::write(&path, contents)?;
+        paths.push(path);
+    }
+
+    Ok(paths)
+}
+
+/// Removes rustdoc links from a schema's descriptions, leaving only their text.
+///
+/// Descriptions are taken from the models' doc comments, whose links only make sense within the
+/// library's documentation.
+///
+/// # Arguments
+///
+/// * `schema` - The schema to tidy.
+fn tidy(schema: &mut Value) {
+    match schema {
+        Value::Object(object) => {
+            for (key, value) in object.iter_mut() {
+                if let ("description", Value::String(description)) = (key.as_str(), &value) {
+                    let description = RE_LINK_REFERENCE.replace_all(description, "");
+                    let description = RE_LINK.replace_all(&description, "$1");
+
+                    *value = Value::String(description.trim().to_string());
+                } else {
+                    self::tidy(value);
+                }
+            }
+        }
+        Value::Array(values) => values.iter_mut().for_each(self::tidy),
+        _ => {}
+    }
+}
+
+/// Returns the TypeScript declaration of a schema definition.
+///
+/// Objects with properties are declared as an `interface` and everything else as a `type`.
+///
+/// # Arguments
+///
+/// * `name` - The definition's name.
+/// * `schema` - The definition's schema.
+fn declaration(name: &str, schema: &Value) -> String {
+    let mut declaration = self::comment(schema, "");
+
+    if let Some(properties) = schema.get("properties").and_then(Value::as_object) {
+        let _ = writeln!(
+            declaration,
+            "export interface {name} {}",
+            self::interface(schema, properties, "")
+        );
+    } else {
+        let _ = writeln!(
+            declaration,
+            "export type {name} = {};",
+            self::type_of(schema, "")
+        );
+    }
+
+    declaration
+}
+
+/// Returns the body of an interface, including its braces.
+///
+/// Properties that aren't required are declared as optional.
+///
+/// # Arguments
+///
+/// * `schema` - The object's schema.
+/// * `properties` - The object's properties.
+/// * `indent` - The indentation of the line the interface starts on.
+fn interface(schema: &Value, properties: &Map<String, Value>, indent: &str) -> String {
+    let required: Vec<&str> = schema
+        .get("required")
+        .and_then(Value::as_array)
+        .map(|required| required.iter().filter_map(Value::as_str).collect())
+        .unwrap_or_default();
+
+    let inner = format!("{indent}  ");
+
+    let mut body = String::from("{\n");
+
+    for (key, property) in properties {
+        let optional = if required.contains(&key.as_str()) {
+            ""
+        } else {
+            "?"
+        };
+
+        body.push_str(&self::comment(property, &inner));
+        let _ = writeln!(
+            body,
+            "{inner}{key}{optional}: {};",
+            self::type_of(property, &inner)
+        );
+    }
+
+    body.push_str(indent);
+    body.push('}');
+
+    body
+}
+
+/// Returns the TypeScript type of a schema.
+///
+/// # Arguments
+///
+/// * `schema` - The schema.
+/// * `indent` - The indentation of the line the type starts on.
+fn type_of(schema: &Value, indent: &str) -> String {
+    if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+        return reference.trim_start_matches(DEFINITIONS_PREFIX).to_string();
+    }
+
+    for (keyword, separator) in [("anyOf", " | "), ("oneOf", " | "), ("allOf", " & ")] {
+        if let Some(schemas) = schema.get(keyword).and_then(Value::as_array) {
+            return self::join(
+                schemas.iter().map(|schema| self::type_of(schema, indent)),
+                separator,
+            );
+        }
+    }
+
+    if let Some(values) = schema.get("enum").and_then(Value::as_array) {
+        return self::join(values.iter().map(Value::to_string), " | ");
+    }
+
+    if let Some(value) = schema.get("const") {
+        return value.to_string();
+    }
+
+    let types: Vec<&str> = match schema.get("type") {
+        Some(Value::String(kind)) => vec![kind.as_str()],
+        Some(Value::Array(kinds)) => kinds.iter().filter_map(Value::as_str).collect(),
+        _ => return "unknown".to_string(),
+    };
+
+    self::join(
+        types.into_iter().map(|kind| match kind {
+            "string" => "string".to_string(),
+            "number" | "integer" => "number".to_string(),
+            "boolean" => "boolean".to_string(),
+            "null" => "null".to_string(),
+            "array" => {
+                let items = schema.get("items").map_or_else(
+                    || "unknown".to_string(),
+                    |items| self::type_of(items, indent),
+                );
+
+                if items.contains(' ') {
+                    format!("({items})[]")
+                } else {
+                    format!("{items}[]")
+                }
+            }
+            "object" => {
+                if let Some(properties) = schema.get("properties").and_then(Value::as_object) {
+                    return self::interface(schema, properties, indent);
+                }
+
+                let values = schema
+                    .get("additionalProperties")
+                    .filter(|values| values.is_object())
+                    .map_or_else(
+                        || "unknown".to_string(),
+                        |values| self::type_of(values, indent),
+                    );
+
+                format!("Record<string, {values}>")
+            }
+            _ => "unknown".to_string(),
+        }),
+        " | ",
+    )
+}
+
+/// Joins types, skipping duplicates.
+///
+/// # Arguments
+///
+/// * `types` - The types to join.
+/// * `separator` - The separator to join the types with.
+fn join<I>(types: I, separator: &str) -> String
+where
+    I: Iterator<Item = String>,
+{
+    let mut joined: Vec<String> = Vec::new();
+
+    for kind in types {
+        if !joined.contains(&kind) {
+            joined.push(kind);
+        }
+    }
+
+    joined.join(separator)
+}
+
+/// Returns a schema's description as a `JSDoc` comment. Returns an empty string if the schema has
+/// no description.
+///
+/// # Arguments
+///
+/// * `schema` - The schema.
+/// * `indent` - The indentation of the comment.
+fn comment(schema: &Value, indent: &str) -> String {
+    let Some(description) = schema.get("description").and_then(Value::as_str) else {
+        return String::new();
+    };
+
+    let lines: Vec<&str> = description.lines().collect();
+
+    if let [line] = lines.as_slice() {
+        return format!("{indent}/** {line} */\n");
+    }
+
+    let mut comment = format!("{indent}/**\n");
+
+    for line in lines {
+        if line.is_empty() {
+            let _ = writeln!(comment, "{indent} *");
+        } else {
+            let _ = writeln!(comment, "{indent} * {line}");
+        }
+    }
+
+    let _ = writeln!(comment, "{indent} */");
+
+    comment
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    use crate::defaults::TEMP_OUTPUT_DIRECTORY;
+    use crate::export::VersionedBook;
+    use crate::models::entry::Entry;
+
+    // Tests that the schemas describe the exported fields and skip the ones that aren't exported.
+    #[test]
+    fn json_schema() {
+        let book = book();
+
+        assert_eq!(book["title"], "Book");
+        assert!(book["properties"]["title"].is_object());
+        assert!(book["properties"]["metadata"].is_object());
+        assert!(book["properties"].get("cover_path").is_none());
+        assert_eq!(
+            book["properties"]["format_version"]["const"],
+            FORMAT_VERSION
+        );
+
+        let annotations = annotations();
+
+        assert_eq!(annotations["title"], "Annotations");
+        assert_eq!(annotations["type"], "array");
+        assert_eq!(
+            annotations["items"]["$ref"],
+            format!("{DEFINITIONS_PREFIX}Annotation")
+        );
+        assert!(annotations["definitions"]["AnnotationMetadata"].is_object());
+    }
+
+    // Tests that exported data only contains fields described by the schemas.
+    #[test]
+    fn json_schema_matches_export() {
+        let entry = Entry::dummy();
+
+        let book = serde_json::to_value(VersionedBook::from(&entry.book)).unwrap();
+        let schema = self::book();
+
+        for key in book.as_object().unwrap().keys() {
+            assert!(schema["properties"].get(key).is_some(), "{key}");
+        }
+
+        let annotation = serde_json::to_value(&entry.annotations[0]).unwrap();
+        let schema = self::annotations();
+
+        for key in annotation.as_object().unwrap().keys() {
+            assert!(
+                schema["definitions"]["Annotation"]["properties"]
+                    .get(key)
+                    .is_some(),
+                "{key}"
+            );
+        }
+    }
+
+    // Tests that TypeScript declarations are generated for each definition.
+    #[test]
+    fn typescript_declarations() {
+        let typescript = typescript();
+
+        assert!(typescript.contains("export interface Book {"));
+        assert!(typescript.contains("export interface Annotation {"));
+        assert!(typescript.contains("export type Annotations = Annotation[];"));
+        assert!(typescript.contains("  title: string;"));
+        assert!(typescript.contains("  tags: string[];"));
+        assert!(typescript.contains("  progress?: number | null;"));
+        assert!(typescript.contains("  calibre?: CalibreMetadata | null;"));
+        assert!(typescript.contains("  custom: Record<string, string>;"));
+        assert!(typescript.contains("\"none\" | \"underline\""));
+        assert!(!typescript.contains("unknown"));
+        assert!(!typescript.contains("]["));
+        assert!(!typescript.contains("crate::"));
+    }
+
+    // Tests that the schemas are written to their files.
+    #[test]
+    fn write_files() {
+        let directory = TEMP_OUTPUT_DIRECTORY.join("tests-schema");
+
+        if directory.exists() {
+            std::fs::remove_dir_all(&directory).unwrap();
+        }
+
+        let paths = write(&directory, SchemaFormat::JsonSchema).unwrap();
+
+        assert_eq!(
+            paths,
+            [
+                directory.join(BOOK_SCHEMA_FILENAME),
+                directory.join(ANNOTATIONS_SCHEMA_FILENAME)
+            ]
+        );
+
+        let paths = write(&directory, SchemaFormat::TypeScript).unwrap();
+
+        assert_eq!(paths, [directory.join(TYPESCRIPT_FILENAME)]);
+    }
+}
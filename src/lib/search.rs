@@ -0,0 +1,809 @@
+//! Defines types for building and querying a full-text search index of annotations.
+//!
+//! The index is a simple inverted index saved as a JSON file. Each annotation is stored as an
+//! [`IndexDocument`] and each of its words is recorded under its field e.g. `body:art` or
+//! `title:spirit` along with the number of times it occurs. Queries look up each of their terms
+//! and only return annotations matching all of them.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::analysis;
+use crate::models::annotation::Annotation;
+use crate::models::book::Book;
+use crate::models::datetime::DateTimeUtc;
+use crate::models::entry::Entry;
+use crate::result::{Error, Result};
+
+/// The name of the file the index is written to.
+pub const INDEX_FILENAME: &str = "search-index.json";
+
+/// The current version of the index format. Indexes with a different version must be rebuilt.
+pub const INDEX_VERSION: u32 = 1;
+
+/// The number of characters shown on either side of a match.
+const CONTEXT_LENGTH: usize = 60;
+
+/// An enum representing the fields of an [`IndexDocument`] that can be searched.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Field {
+    /// The book's title.
+    Title,
+
+    /// The book's author.
+    Author,
+
+    /// The annotation's body.
+    Body,
+
+    /// The annotation's notes.
+    Notes,
+
+    /// The annotation's tags, without their leading `#`.
+    Tag,
+}
+
+impl Field {
+    /// All fields in the order they're searched when a term doesn't have a prefix.
+    const ALL: [Self; 5] = [
+        Self::Body,
+        Self::Notes,
+        Self::Title,
+        Self::Author,
+        Self::Tag,
+    ];
+
+    /// Returns the field's name as used in query prefixes and index keys.
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Title => "title",
+            Self::Author => "author",
+            Self::Body => "body",
+            Self::Notes => "notes",
+            Self::Tag => "tag",
+        }
+    }
+
+    /// Returns the field for a query prefix, if any.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The prefix without its trailing `:`.
+    fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|field| field.name() == name)
+    }
+}
+
+/// A struct representing an indexed annotation.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IndexDocument {
+    /// The id of the annotation's book.
+    pub book_id: String,
+
+    /// The title of the annotation's book.
+    pub title: String,
+
+    /// The author of the annotation's book.
+    pub author: String,
+
+    /// The annotation's body.
+    pub body: String,
+
+    /// The annotation's notes.
+    pub notes: String,
+
+    /// The annotation's tags including their leading `#`.
+    pub tags: BTreeSet<String>,
+
+    /// The date the annotation was last modified.
+    pub modified: DateTimeUtc,
+}
+
+impl IndexDocument {
+    /// Creates a new instance of [`IndexDocument`].
+    ///
+    /// # Arguments
+    ///
+    /// * `book` - The annotation's book.
+    /// * `annotation` - The annotation.
+    #[must_use]
+    pub fn new(book: &Book, annotation: &Annotation) -> Self {
+        Self {
+            book_id: book.metadata.id.clone(),
+            title: book.title.clone(),
+            author: book.author.clone(),
+            body: annotation.body.clone(),
+            notes: annotation.notes.clone(),
+            tags: annotation.tags.clone(),
+            modified: annotation.metadata.modified,
+        }
+    }
+
+    /// Returns the number of times each word occurs in each field, keyed by `field:word`.
+    fn terms(&self) -> BTreeMap<String, usize> {
+        let mut terms = BTreeMap::new();
+
+        let fields = [
+            (Field::Title, self.title.as_str()),
+            (Field::Author, self.author.as_str()),
+            (Field::Body, self.body.as_str()),
+            (Field::Notes, self.notes.as_str()),
+        ];
+
+        for (field, text) in fields {
+            for token in self::tokenize(text) {
+                *terms
+                    .entry(format!("{}:{token}", field.name()))
+                    .or_insert(0) += 1;
+            }
+        }
+
+        for tag in &self.tags {
+            for token in self::tokenize(tag) {
+                *terms
+                    .entry(format!("{}:{token}", Field::Tag.name()))
+                    .or_insert(0) += 1;
+            }
+        }
+
+        terms
+    }
+}
+
+/// A struct representing the changes made by [`SearchIndex::update()`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct IndexUpdate {
+    /// The number of annotations added to the index.
+    pub added: usize,
+
+    /// The number of annotations re-indexed after being modified.
+    pub updated: usize,
+
+    /// The number of annotations removed from the index.
+    pub removed: usize,
+}
+
+/// A struct representing a search result.
+#[derive(Debug)]
+pub struct SearchMatch<'a> {
+    /// The annotation's id.
+    pub id: &'a str,
+
+    /// The indexed annotation.
+    pub document: &'a IndexDocument,
+
+    /// The number of times the query's terms occur in the annotation. Higher is better.
+    pub score: usize,
+
+    /// The text surrounding the first match in the annotation's body or notes. Falls back to the
+    /// start of the body if the annotation only matched on its book or tags.
+    pub context: String,
+}
+
+/// A struct representing a full-text search index of annotations.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct SearchIndex {
+    /// The version of the index format.
+    pub version: u32,
+
+    /// The indexed annotations keyed by their id.
+    documents: BTreeMap<String, IndexDocument>,
+
+    /// The ids of the annotations containing each term and the term's frequency, keyed by
+    /// `field:word`.
+    terms: BTreeMap<String, BTreeMap<String, usize>>,
+}
+
+impl SearchIndex {
+    /// Builds a new index from a set of entries.
+    ///
+    /// # Arguments
+    ///
+    /// * `entries` - The entries to index.
+    #[must_use]
+    pub fn build<'a, I>(entries: I) -> Self
+    where
+        I: IntoIterator<Item = &'a Entry>,
+    {
+        let mut index = Self {
+            version: INDEX_VERSION,
+            ..Default::default()
+        };
+
+        index.update(entries);
+
+        index
+    }
+
+    /// Updates the index to match a set of entries. New annotations are added, modified ones are
+    /// re-indexed and ones that no longer exist are removed.
+    ///
+    /// # Arguments
+    ///
+    /// * `entries` - The entries to index.
+    pub fn update<'a, I>(&mut self, entries: I) -> IndexUpdate
+    where
+        I: IntoIterator<Item = &'a Entry>,
+    {
+        let mut update = IndexUpdate::default();
+        let mut seen = BTreeSet::new();
+
+        for entry in entries {
+            for annotation in &entry.annotations {
+                let id = &annotation.metadata.id;
+                let document = IndexDocument::new(&entry.book, annotation);
+
+                seen.insert(id.clone());
+
+                match self.documents.get(id) {
+                    Some(existing) if *existing == document => continue,
+                    Some(_) => {
+                        self.remove(id);
+                        update.updated += 1;
+                    }
+                    None => update.added += 1,
+                }
+
+                self.insert(id.clone(), document);
+            }
+        }
+
+        let removed: Vec<String> = self
+            .documents
+            .keys()
+            .filter(|id| !seen.contains(*id))
+            .cloned()
+            .collect();
+
+        for id in &removed {
+            self.remove(id);
+        }
+
+        update.removed = removed.len();
+
+        update
+    }
+
+    /// Returns the number of indexed annotations.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.documents.len()
+    }
+
+    /// Returns `true` if no annotations are indexed.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.documents.is_empty()
+    }
+
+    /// Searches the index and returns the annotations matching every term in the query, best
+    /// matches first.
+    ///
+    /// Terms are separated by whitespace and can be prefixed with a field to only search that
+    /// field e.g. `title:spirit` or `tag:#favorite`. Terms without a prefix search every field.
+    /// Quoted terms e.g. `"go on anyway"` or `author:"robert henri"` require all of their words. A
+    /// trailing `*` matches words starting with the term e.g. `paint*`.
+    ///
+    /// # Arguments
+    ///
+    /// * `query` - The query.
+    /// * `limit` - The maximum number of matches to return.
+    #[must_use]
+    pub fn search(&self, query: &str, limit: usize) -> Vec<SearchMatch<'_>> {
+        let terms = self::parse_query(query);
+
+        if terms.is_empty() {
+            return Vec::new();
+        }
+
+        let mut scores: Option<BTreeMap<&str, usize>> = None;
+
+        for term in &terms {
+            let matches = self.lookup(term);
+
+            scores = Some(match scores {
+                None => matches,
+                Some(scores) => scores
+                    .into_iter()
+                    .filter_map(|(id, score)| matches.get(id).map(|other| (id, score + other)))
+                    .collect(),
+            });
+        }
+
+        let mut matches: Vec<SearchMatch<'_>> = scores
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|(id, score)| {
+                let (id, document) = self.documents.get_key_value(id)?;
+
+                Some(SearchMatch {
+                    id,
+                    document,
+                    score,
+                    context: self::context(document, &terms),
+                })
+            })
+            .collect();
+
+        matches.sort_by(|a, b| {
+            b.score
+                .cmp(&a.score)
+                .then_with(|| a.document.title.cmp(&b.document.title))
+                .then_with(|| a.id.cmp(b.id))
+        });
+        matches.truncate(limit);
+
+        matches
+    }
+
+    /// Writes the index to a JSON file.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path to write to.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if:
+    /// * The index cannot be serialized.
+    /// * Any IO errors are encountered.
+    pub fn write(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let temp = path.with_extension("json.tmp");
+
+        std::fs::write(&temp, serde_json::to_string(self)?)?;
+        std::fs::rename(&temp, path)?;
+
+        Ok(())
+    }
+
+    /// Reads an index from a JSON file.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path to the index.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if:
+    /// * The file isn't an index or was built with a different version of the index format.
+    /// * Any IO errors are encountered.
+    pub fn read(path: &Path) -> Result<Self> {
+        let invalid = || Error::SearchInvalidIndex {
+            path: path.display().to_string(),
+        };
+
+        let bytes = std::fs::read(path)?;
+        let index: Self = serde_json::from_slice(&bytes).map_err(|_| invalid())?;
+
+        if index.version != INDEX_VERSION {
+            return Err(invalid());
+        }
+
+        Ok(index)
+    }
+
+    /// Adds a document and its terms to the index.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The annotation's id.
+    /// * `document` - The document to add.
+    fn insert(&mut self, id: String, document: IndexDocument) {
+        for (term, frequency) in document.terms() {
+            self.terms
+                .entry(term)
+                .or_default()
+                .insert(id.clone(), frequency);
+        }
+
+        self.documents.insert(id, document);
+    }
+
+    /// Removes a document and its terms from the index.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The annotation's id.
+    fn remove(&mut self, id: &str) {
+        let Some(document) = self.documents.remove(id) else {
+            return;
+        };
+
+        for term in document.terms().into_keys() {
+            if let Some(postings) = self.terms.get_mut(&term) {
+                postings.remove(id);
+
+                if postings.is_empty() {
+                    self.terms.remove(&term);
+                }
+            }
+        }
+    }
+
+    /// Returns the ids of the documents matching a query term along with the number of times its
+    /// words occur in them.
+    ///
+    /// # Arguments
+    ///
+    /// * `term` - The query term.
+    fn lookup(&self, term: &QueryTerm) -> BTreeMap<&str, usize> {
+        let mut matches: Option<BTreeMap<&str, usize>> = None;
+
+        for word in &term.words {
+            let mut word_matches: BTreeMap<&str, usize> = BTreeMap::new();
+
+            let fields = term
+                .field
+                .as_ref()
+                .map_or(&Field::ALL[..], std::slice::from_ref);
+
+            for field in fields {
+                let key = format!("{}:{word}", field.name());
+
+                let postings: Box<dyn Iterator<Item = _>> = if term.prefix {
+                    Box::new(
+                        self.terms
+                            .range(key.clone()..)
+                            .take_while(|(candidate, _)| candidate.starts_with(&key)),
+                    )
+                } else {
+                    Box::new(self.terms.get_key_value(&key).into_iter())
+                };
+
+                for (_, documents) in postings {
+                    for (id, frequency) in documents {
+                        *word_matches.entry(id.as_str()).or_insert(0) += frequency;
+                    }
+                }
+            }
+
+            matches = Some(match matches {
+                None => word_matches,
+                Some(matches) => matches
+                    .into_iter()
+                    .filter_map(|(id, score)| word_matches.get(id).map(|other| (id, score + other)))
+                    .collect(),
+            });
+        }
+
+        matches.unwrap_or_default()
+    }
+}
+
+/// A struct representing a single term of a query.
+#[derive(Debug, PartialEq, Eq)]
+struct QueryTerm {
+    /// The field to search, or every field if `None`.
+    field: Option<Field>,
+
+    /// The term's lowercase words. All of them must match.
+    words: Vec<String>,
+
+    /// Whether the last word matches any word starting with it.
+    prefix: bool,
+}
+
+/// Parses a query into its terms. See [`SearchIndex::search()`] for the query syntax.
+///
+/// # Arguments
+///
+/// * `query` - The query.
+fn parse_query(query: &str) -> Vec<QueryTerm> {
+    let mut terms = Vec::new();
+    let mut chars = query.chars().peekable();
+
+    loop {
+        while chars.next_if(|char| char.is_whitespace()).is_some() {}
+
+        if chars.peek().is_none() {
+            break;
+        }
+
+        let mut raw = String::new();
+        let mut quoted = false;
+
+        for char in chars.by_ref() {
+            match char {
+                '"' => quoted = !quoted,
+                char if char.is_whitespace() && !quoted => break,
+                char => raw.push(char),
+            }
+        }
+
+        let (field, text) = match raw.split_once(':') {
+            Some((name, text)) => match Field::from_name(&name.to_lowercase()) {
+                Some(field) => (Some(field), text),
+                None => (None, raw.as_str()),
+            },
+            None => (None, raw.as_str()),
+        };
+
+        let prefix = text.ends_with('*');
+        let words = self::tokenize(text);
+
+        if !words.is_empty() {
+            terms.push(QueryTerm {
+                field,
+                words,
+                prefix,
+            });
+        }
+    }
+
+    terms
+}
+
+/// Splits a string into lowercase words. See [`analysis::words()`].
+///
+/// # Arguments
+///
+/// * `text` - The text to split.
+fn tokenize(text: &str) -> Vec<String> {
+    analysis::words(text).collect()
+}
+
+/// Returns the text surrounding the first word of a query found in a document's body or notes.
+///
+/// # Arguments
+///
+/// * `document` - The matching document.
+/// * `terms` - The query's terms.
+fn context(document: &IndexDocument, terms: &[QueryTerm]) -> String {
+    let words: Vec<&str> = terms
+        .iter()
+        .filter(|term| matches!(term.field, None | Some(Field::Body | Field::Notes)))
+        .flat_map(|term| term.words.iter().map(String::as_str))
+        .collect();
+
+    for text in [&document.body, &document.notes] {
+        let lowercase = text.to_lowercase();
+
+        // Lowercasing can change a string's length so the match is only used if it didn't.
+        if lowercase.len() != text.len() {
+            continue;
+        }
+
+        let found = words
+            .iter()
+            .filter_map(|word| self::find_word(&lowercase, word).map(|start| (start, word.len())))
+            .min();
+
+        if let Some((start, length)) =
+            found.filter(|(start, length)| text.is_char_boundary(*start + *length))
+        {
+            return self::excerpt(text, start, start + length);
+        }
+    }
+
+    self::excerpt(&document.body, 0, 0)
+}
+
+/// Returns the byte index of the first occurrence of a word that starts at a word boundary.
+///
+/// # Arguments
+///
+/// * `text` - The text to search.
+/// * `word` - The word to find.
+fn find_word(text: &str, word: &str) -> Option<usize> {
+    text.match_indices(word)
+        .map(|(start, _)| start)
+        .find(|start| {
+            !text[..*start]
+                .chars()
+                .next_back()
+                .is_some_and(char::is_alphanumeric)
+        })
+}
+
+/// Returns up to [`CONTEXT_LENGTH`] characters on either side of a range of a string. An ellipsis
+/// is added to either side if the string was cut.
+///
+/// # Arguments
+///
+/// * `text` - The text to cut.
+/// * `start` - The byte index the range starts at.
+/// * `end` - The byte index the range ends at.
+fn excerpt(text: &str, start: usize, end: usize) -> String {
+    let before: Vec<char> = text[..start].chars().collect();
+    let after: Vec<char> = text[end..].chars().collect();
+
+    let before_start = before.len().saturating_sub(CONTEXT_LENGTH);
+    let after_end = after.len().min(CONTEXT_LENGTH);
+
+    let mut excerpt = String::new();
+
+    if before_start > 0 {
+        excerpt.push('…');
+    }
+
+    excerpt.extend(&before[before_start..]);
+    excerpt.push_str(&text[start..end]);
+    excerpt.extend(&after[..after_end]);
+
+    if after_end < after.len() {
+        excerpt.push('…');
+    }
+
+    crate::strings::normalize_whitespace(&excerpt)
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    use crate::defaults::TEMP_OUTPUT_DIRECTORY;
+
+    fn entry(title: &str, annotations: &[(&str, &str, &str)]) -> Entry {
+        let mut entry = Entry::default();
+        entry.book.title = title.to_string();
+        entry.book.author = "Robert Henri".to_string();
+        entry.book.metadata.id = title.to_string();
+
+        for (id, body, tags) in annotations {
+            let mut annotation = Annotation {
+                body: (*body).to_string(),
+                tags: tags.split_whitespace().map(str::to_string).collect(),
+                ..Default::default()
+            };
+            annotation.metadata.id = (*id).to_string();
+            entry.annotations.push(annotation);
+        }
+
+        entry
+    }
+
+    fn entries() -> Vec<Entry> {
+        vec![
+            entry(
+                "The Art Spirit",
+                &[
+                    (
+                        "A",
+                        "Go on anyway. Everything depends on those who go on anyway.",
+                        "",
+                    ),
+                    ("B", "Paint what you feel. Paint what you see.", "#favorite"),
+                ],
+            ),
+            entry(
+                "Letters",
+                &[("C", "The painter should not paint what he sees.", "#art")],
+            ),
+        ]
+    }
+
+    fn ids(index: &SearchIndex, query: &str) -> Vec<String> {
+        index
+            .search(query, 10)
+            .iter()
+            .map(|result| result.id.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn query_syntax() {
+        assert_eq!(
+            parse_query(r#"title:"Art Spirit" paint* http://example"#),
+            [
+                QueryTerm {
+                    field: Some(Field::Title),
+                    words: vec!["art".to_string(), "spirit".to_string()],
+                    prefix: false,
+                },
+                QueryTerm {
+                    field: None,
+                    words: vec!["paint".to_string()],
+                    prefix: true,
+                },
+                QueryTerm {
+                    field: None,
+                    words: vec!["http".to_string(), "example".to_string()],
+                    prefix: false,
+                },
+            ]
+        );
+    }
+
+    // Tests that terms must all match and that more frequent matches are ranked first.
+    #[test]
+    fn search_terms() {
+        let index = SearchIndex::build(&entries());
+
+        assert_eq!(ids(&index, "paint"), ["B", "C"]);
+        assert_eq!(ids(&index, "paint see"), ["B"]);
+        assert_eq!(ids(&index, "paint*"), ["C", "B"]);
+        assert_eq!(ids(&index, "anyway"), ["A"]);
+        assert!(ids(&index, "").is_empty());
+        assert!(ids(&index, "nothing").is_empty());
+    }
+
+    // Tests that prefixed terms only search their field.
+    #[test]
+    fn search_fields() {
+        let index = SearchIndex::build(&entries());
+
+        assert_eq!(ids(&index, "art"), ["C", "A", "B"]);
+        assert_eq!(ids(&index, "tag:art"), ["C"]);
+        assert_eq!(ids(&index, "tag:#favorite"), ["B"]);
+        assert_eq!(ids(&index, r#"title:"art spirit" paint"#), ["B"]);
+        assert_eq!(ids(&index, "author:henri").len(), 3);
+    }
+
+    #[test]
+    fn search_context() {
+        let mut entries = entries();
+        entries[0].annotations[0].body = format!("{} anyway {}", "a ".repeat(50), "b ".repeat(50));
+
+        let index = SearchIndex::build(&entries);
+        let results = index.search("anyway", 1);
+
+        assert!(results[0].context.starts_with('…'));
+        assert!(results[0].context.ends_with('…'));
+        assert!(results[0].context.contains("anyway"));
+
+        assert_eq!(find_word("ago, go on", "go"), Some(5));
+        assert_eq!(find_word("ago", "go"), None);
+    }
+
+    // Tests that updates add new, re-index modified and remove deleted annotations.
+    #[test]
+    fn update() {
+        let mut entries = entries();
+        let mut index = SearchIndex::build(&entries);
+
+        entries[0].annotations.remove(0);
+        entries[0].annotations[0].body = "Draw what you feel.".to_string();
+        entries[1].annotations.push(Annotation {
+            body: "Everything depends on drawing.".to_string(),
+            metadata: crate::models::annotation::AnnotationMetadata {
+                id: "D".to_string(),
+                ..Default::default()
+            },
+            ..Default::default()
+        });
+
+        let update = index.update(&entries);
+
+        assert_eq!(
+            update,
+            IndexUpdate {
+                added: 1,
+                updated: 1,
+                removed: 1,
+            }
+        );
+        assert_eq!(index.len(), 3);
+        assert_eq!(ids(&index, "everything"), ["D"]);
+        assert_eq!(ids(&index, "draw*"), ["D", "B"]);
+        assert_eq!(ids(&index, "paint"), ["C"]);
+        assert_eq!(index.update(&entries), IndexUpdate::default());
+    }
+
+    #[test]
+    fn write_and_read() {
+        let directory = TEMP_OUTPUT_DIRECTORY.join("tests-search");
+        let path = directory.join(INDEX_FILENAME);
+
+        SearchIndex::build(&entries()).write(&path).unwrap();
+
+        let index = SearchIndex::read(&path).unwrap();
+
+        assert_eq!(index.len(), 3);
+        assert_eq!(ids(&index, "paint"), ["B", "C"]);
+
+        std::fs::write(&path, r#"{"version":0,"documents":{},"terms":{}}"#).unwrap();
+
+        assert!(matches!(
+            SearchIndex::read(&path),
+            Err(Error::SearchInvalidIndex { .. })
+        ));
+    }
+}
@@ -0,0 +1,276 @@
+//! Defines types for creating and loading snapshots of extracted books and annotations.
+//!
+//! A snapshot is a single gzip-compressed JSON file holding every extracted [`Entry`] along with a
+//! header describing how and when they were extracted. Unlike a back-up, which copies Apple Books'
+//! raw databases, a snapshot doesn't depend on Apple Books' schema so it can be loaded as a data
+//! source on a machine without Apple Books e.g. to render on a different Mac.
+
+use std::io::{Read, Write};
+use std::path::Path;
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+
+use crate::defaults::DATE_FORMAT_SLUG;
+use crate::models::datetime::DateTimeUtc;
+use crate::models::entry::{Entries, Entry};
+use crate::result::{Error, Result};
+
+/// The value of a snapshot's `format` field. Used to identify a file as a snapshot.
+pub const SNAPSHOT_FORMAT: &str = "readstor-snapshot";
+
+/// The current version of the snapshot format. Snapshots with a newer version can't be loaded.
+pub const SNAPSHOT_VERSION: u32 = 1;
+
+/// The extension of a snapshot's file.
+pub const SNAPSHOT_EXTENSION: &str = "snapshot.json.gz";
+
+/// The first two bytes of a gzip-compressed file.
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// A struct representing a snapshot's header.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnapshotHeader {
+    /// Identifies the file as a snapshot. Always [`SNAPSHOT_FORMAT`].
+    pub format: String,
+
+    /// The version of the snapshot format.
+    pub version: u32,
+
+    /// The version of `readstor` that created the snapshot.
+    pub readstor_version: String,
+
+    /// The platform the books and annotations were extracted from e.g. `macos`.
+    pub platform: String,
+
+    /// The date the snapshot was created.
+    pub created: DateTimeUtc,
+
+    /// The number of books in the snapshot.
+    pub books: usize,
+
+    /// The number of annotations in the snapshot.
+    pub annotations: usize,
+}
+
+/// A struct representing a snapshot of extracted books and annotations.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Snapshot {
+    /// The snapshot's header.
+    #[serde(flatten)]
+    pub header: SnapshotHeader,
+
+    /// The snapshot's entries, sorted by their book's id.
+    pub entries: Vec<Entry>,
+}
+
+impl Snapshot {
+    /// Creates a new instance of [`Snapshot`].
+    ///
+    /// # Arguments
+    ///
+    /// * `entries` - The entries to snapshot.
+    /// * `platform` - The platform the entries were extracted from e.g. `macos`.
+    #[must_use]
+    pub fn new(entries: &Entries, platform: &str) -> Self {
+        let mut entries: Vec<Entry> = entries.values().cloned().collect();
+        entries.sort_by(|a, b| a.book.metadata.id.cmp(&b.book.metadata.id));
+
+        Self {
+            header: SnapshotHeader {
+                format: SNAPSHOT_FORMAT.to_owned(),
+                version: SNAPSHOT_VERSION,
+                readstor_version: env!("CARGO_PKG_VERSION").to_owned(),
+                platform: platform.to_owned(),
+                created: Utc::now().into(),
+                books: entries.len(),
+                annotations: entries.iter().map(|entry| entry.annotations.len()).sum(),
+            },
+            entries,
+        }
+    }
+
+    /// Returns the snapshot's default filename e.g. `1970-01-01-120000-macos.snapshot.json.gz`.
+    #[must_use]
+    pub fn filename(&self) -> String {
+        format!(
+            "{}-{}.{SNAPSHOT_EXTENSION}",
+            self.header.created.format(DATE_FORMAT_SLUG),
+            self.header.platform
+        )
+    }
+
+    /// Writes the snapshot to a gzip-compressed JSON file.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path to write to.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if:
+    /// * The snapshot cannot be serialized.
+    /// * Any IO errors are encountered.
+    pub fn write(&self, path: &Path) -> Result<()> {
+        let file = std::fs::File::create(path)?;
+        let mut encoder = flate2::write::GzEncoder::new(file, flate2::Compression::default());
+
+        serde_json::to_writer(&mut encoder, self)?;
+        encoder.finish()?.flush()?;
+
+        Ok(())
+    }
+
+    /// Reads a snapshot from a JSON file, gzip-compressed or not.
+    ///
+    /// The header is checked before the entries are read so a file that isn't a snapshot, or one
+    /// created by a newer version of `readstor`, is reported as such.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path to the snapshot.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if:
+    /// * The file isn't a snapshot.
+    /// * The snapshot's version is unsupported.
+    /// * Any IO errors are encountered.
+    pub fn read(path: &Path) -> Result<Self> {
+        let invalid = || Error::SnapshotInvalid {
+            path: path.display().to_string(),
+        };
+
+        let bytes = std::fs::read(path)?;
+
+        let bytes = if bytes.starts_with(&GZIP_MAGIC) {
+            let mut decoded = Vec::new();
+            flate2::read::GzDecoder::new(bytes.as_slice())
+                .read_to_end(&mut decoded)
+                .map_err(|_| invalid())?;
+            decoded
+        } else {
+            bytes
+        };
+
+        let format: SnapshotFormat = serde_json::from_slice(&bytes).map_err(|_| invalid())?;
+
+        if format.format != SNAPSHOT_FORMAT {
+            return Err(invalid());
+        }
+
+        if format.version > SNAPSHOT_VERSION {
+            return Err(Error::SnapshotUnsupportedVersion {
+                path: path.display().to_string(),
+                version: format.version,
+            });
+        }
+
+        Ok(serde_json::from_slice(&bytes)?)
+    }
+
+    /// Converts the snapshot into [`Entries`].
+    #[must_use]
+    pub fn into_entries(self) -> Entries {
+        self.entries
+            .into_iter()
+            .map(|entry| (entry.book.metadata.id.clone(), entry))
+            .collect()
+    }
+}
+
+/// A struct representing the fields used to identify a snapshot. Any other fields are ignored.
+#[derive(Deserialize)]
+struct SnapshotFormat {
+    #[serde(default)]
+    format: String,
+
+    #[serde(default)]
+    version: u32,
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    use crate::defaults::TEMP_OUTPUT_DIRECTORY;
+    use crate::models::annotation::Annotation;
+    use crate::models::book::{Book, BookMetadata};
+
+    fn entries() -> Entries {
+        let mut entries = Entries::default();
+
+        for (id, title) in [
+            ("ABC123", "The Art Spirit"),
+            ("ABD456", "Think on These Things"),
+        ] {
+            entries.insert(
+                id.to_string(),
+                Entry {
+                    book: Book {
+                        title: title.to_string(),
+                        metadata: BookMetadata {
+                            id: id.to_string(),
+                            ..Default::default()
+                        },
+                        ..Default::default()
+                    },
+                    annotations: vec![Annotation::default()],
+                },
+            );
+        }
+
+        entries
+    }
+
+    fn setup(name: &str) -> std::path::PathBuf {
+        let directory = TEMP_OUTPUT_DIRECTORY.join("tests-snapshot");
+        std::fs::create_dir_all(&directory).unwrap();
+
+        directory.join(name)
+    }
+
+    // Tests that a written snapshot is read back with the same header and entries.
+    #[test]
+    fn roundtrip() {
+        let path = setup("roundtrip.snapshot.json.gz");
+
+        let snapshot = Snapshot::new(&entries(), "macos");
+        snapshot.write(&path).unwrap();
+
+        let snapshot = Snapshot::read(&path).unwrap();
+
+        assert_eq!(snapshot.header.format, SNAPSHOT_FORMAT);
+        assert_eq!(snapshot.header.platform, "macos");
+        assert_eq!(snapshot.header.books, 2);
+        assert_eq!(snapshot.header.annotations, 2);
+
+        let entries = snapshot.into_entries();
+
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries["ABD456"].book.title, "Think on These Things");
+    }
+
+    // Tests that files that aren't snapshots or have a newer version are rejected.
+    #[test]
+    fn invalid() {
+        let path = setup("invalid.json");
+
+        for contents in ["not json", "[]", r#"{"format": "other", "version": 1}"#] {
+            std::fs::write(&path, contents).unwrap();
+
+            assert!(matches!(
+                Snapshot::read(&path),
+                Err(Error::SnapshotInvalid { .. })
+            ));
+        }
+
+        std::fs::write(&path, r#"{"format": "readstor-snapshot", "version": 999}"#).unwrap();
+
+        assert!(matches!(
+            Snapshot::read(&path),
+            Err(Error::SnapshotUnsupportedVersion { version: 999, .. })
+        ));
+    }
+}
@@ -1,6 +1,6 @@
 //! Defines functions for string creation/manipulation.
 
-use std::collections::BTreeSet;
+use std::collections::{BTreeMap, BTreeSet};
 use std::ffi::OsStr;
 
 use chrono::DateTime;
@@ -9,14 +9,38 @@ use deunicode::deunicode;
 use once_cell::sync::Lazy;
 use regex::Regex;
 use serde::Serialize;
+use serde_json::Value;
 
 use super::result::Result;
+use crate::analysis;
 use crate::render::engine::RenderEngine;
 
+/// The character tags start with unless another is set. See [`TagSyntax`].
+pub const DEFAULT_TAG_PREFIX: char = '#';
+
+/// The `#tags` that mark an annotation as a favorite.
+pub const FAVORITE_TAGS: &[&str] = &["#fav", "#favorite"];
+
 /// Captures a `#tag`. Tags *must* start with a hash symbol `#` followed by a letter in `[a-zA-Z]`
 /// and then a series of any characters. A tag ends when a space or another `#` is encountered.
 static RE_TAG: Lazy<Regex> = Lazy::new(|| Regex::new(r"#[a-zA-Z][^\s#]+\s?").unwrap());
 
+/// Captures a favorite marker i.e. a `*` surrounded by whitespace or at the start or end of a
+/// string, along with the whitespace around it.
+static RE_FAVORITE: Lazy<Regex> = Lazy::new(|| Regex::new(r"(^|\s)\*(\s|$)").unwrap());
+
+/// Captures a `key:: value` property on its own line. Keys *must* start with a letter in `[a-zA-Z]`
+/// followed by any letters, numbers, `_`s or `-`s. The value is the rest of the line.
+static RE_PROPERTY: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?m)^[ \t]*([a-zA-Z][\w-]*)::[ \t]*([^\n]*?)[ \t]*(?:\n|$)").unwrap()
+});
+
+/// Captures a `key: value` field on its own line. Keys *must* start with a letter in `[a-zA-Z]`
+/// followed by any letters, numbers, `_`s or `-`s and the `:` *must* be followed by whitespace so
+/// that `key:: value` properties and URLs aren't captured. The value is the rest of the line.
+static RE_FIELD: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?m)^[ \t]*([a-zA-Z][\w-]*):[ \t]+([^\n]*?)[ \t]*(?:\n|$)").unwrap());
+
 /// Captures three or more consecutive linebreaks.
 static RE_BLOCKS: Lazy<Regex> = Lazy::new(|| Regex::new(r"\n{3,}").unwrap());
 
@@ -56,7 +80,7 @@ pub fn sanitize(string: &str) -> String {
     let sanitized = sanitized.to_string_lossy().to_string();
 
     if sanitized != string {
-        log::warn!("the string '{}' contained invalid characters", string);
+        tracing::warn!("the string '{}' contained invalid characters", string);
     };
 
     sanitized
@@ -129,6 +153,64 @@ pub fn to_slug_date(date: &DateTime<Utc>) -> String {
     date.format(crate::defaults::DATE_FORMAT_SLUG).to_string()
 }
 
+/// Returns the number of whole days between a date and now. Dates in the future return `0`.
+///
+/// # Arguments
+///
+/// * `date` - The date to compare.
+/// * `now` - The current date.
+#[must_use]
+pub fn days_since(date: &DateTime<Utc>, now: &DateTime<Utc>) -> i64 {
+    (*now - *date).num_days().max(0)
+}
+
+/// Returns a human-readable relative date e.g. `yesterday` or `3 months ago`.
+///
+/// # Arguments
+///
+/// * `date` - The date to describe.
+/// * `now` - The current date.
+#[must_use]
+pub fn to_relative_date(date: &DateTime<Utc>, now: &DateTime<Utc>) -> String {
+    let days = days_since(date, now);
+
+    let (count, unit) = match days {
+        0 => return "today".to_owned(),
+        1 => return "yesterday".to_owned(),
+        2..=6 => (days, "day"),
+        7..=29 => (days / 7, "week"),
+        30..=364 => (days / 30, "month"),
+        _ => (days / 365, "year"),
+    };
+
+    if count == 1 {
+        format!("1 {unit} ago")
+    } else {
+        format!("{count} {unit}s ago")
+    }
+}
+
+/// Returns a short title made from the first significant words of a string e.g. `Art is the
+/// province of every human being.` becomes `Art province every human`.
+///
+/// Words are split on whitespace and stripped of surrounding punctuation. Short words and common
+/// English stop-words are skipped.
+///
+/// # Arguments
+///
+/// * `string` - The string to derive the title from.
+/// * `count` - The maximum number of words in the title.
+#[must_use]
+pub fn to_title_hint(string: &str, count: usize) -> String {
+    string
+        .split_whitespace()
+        .map(|word| word.trim_matches(|c: char| !c.is_alphanumeric()))
+        .filter(|word| analysis::is_significant(&word.to_lowercase()))
+        .take(count)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
 /// Renders a one-off template string with a context and sanitizes the output string.
 ///
 /// # Errors
@@ -176,33 +258,284 @@ pub fn normalize_whitespace(string: &str) -> String {
         .join("\n\n")
 }
 
-/// Extracts all `#tags` from a string.
+/// Normalizes an ISBN by removing any prefix, e.g. `urn:isbn:` or `isbn_`, along with hyphens and
+/// spaces. Returns `None` if the result isn't a 10 or 13 character ISBN.
+///
+/// # Arguments
+///
+/// * `string` - The string to normalize.
+#[must_use]
+pub fn to_isbn(string: &str) -> Option<String> {
+    let isbn: String = string
+        .rsplit([':', '_'])
+        .next()
+        .unwrap_or_default()
+        .chars()
+        .filter(|c| !matches!(c, '-' | ' '))
+        .map(|c| c.to_ascii_uppercase())
+        .collect();
+
+    if !matches!(isbn.len(), 10 | 13) || !isbn.is_ascii() {
+        return None;
+    }
+
+    let (body, check) = isbn.split_at(isbn.len() - 1);
+
+    let is_valid = body.bytes().all(|b| b.is_ascii_digit())
+        && (check.bytes().all(|b| b.is_ascii_digit()) || (isbn.len() == 10 && check == "X"));
+
+    is_valid.then_some(isbn)
+}
+
+/// A struct representing the syntax of tags within notes and how extracted tags are normalized.
+///
+/// By default, tags start with a `#` followed by a letter and end when a space or another `#` is
+/// encountered. See [`TagSyntax::new()`] for using another prefix e.g. `@tag` or limiting the
+/// characters allowed within tags.
+#[derive(Debug, Clone)]
+pub struct TagSyntax {
+    /// The character tags start with.
+    prefix: char,
+
+    /// The compiled pattern tags are matched with.
+    regex: Regex,
+
+    /// Toggles lowercasing extracted tags e.g. `#Tag` becomes `#tag`.
+    pub lowercase: bool,
+
+    /// Toggles removing the prefix from extracted tags e.g. `#tag` becomes `tag`.
+    pub strip_prefix: bool,
+}
+
+impl Default for TagSyntax {
+    fn default() -> Self {
+        Self {
+            prefix: DEFAULT_TAG_PREFIX,
+            regex: RE_TAG.clone(),
+            lowercase: false,
+            strip_prefix: false,
+        }
+    }
+}
+
+impl TagSyntax {
+    /// Returns a new instance of [`TagSyntax`].
+    ///
+    /// Tags start with the prefix followed by a letter. If `chars` is set, the rest of a tag can
+    /// only contain letters, numbers, `_`s and the characters in `chars` e.g. `-/` to allow
+    /// nested tags like `#topic/subtopic`. Otherwise a tag ends when a space or another prefix is
+    /// encountered.
+    ///
+    /// # Arguments
+    ///
+    /// * `prefix` - The character tags start with e.g. `#` or `@`.
+    /// * `chars` - The characters allowed within tags besides letters, numbers and `_`s.
+    #[allow(clippy::missing_panics_doc)]
+    #[must_use]
+    pub fn new(prefix: char, chars: Option<&str>) -> Self {
+        let escaped = regex::escape(&prefix.to_string());
+
+        let rest = match chars {
+            Some(chars) => format!(r"[\w{}]", regex::escape(chars)),
+            None => format!(r"[^\s{escaped}]"),
+        };
+
+        // This unwrap is safe as the prefix and characters are escaped.
+        let regex = Regex::new(&format!(r"{escaped}[a-zA-Z]{rest}+\s?")).unwrap();
+
+        Self {
+            prefix,
+            regex,
+            lowercase: false,
+            strip_prefix: false,
+        }
+    }
+
+    /// Returns the character tags start with.
+    #[must_use]
+    pub fn prefix(&self) -> char {
+        self.prefix
+    }
+
+    /// Extracts all tags from a string. Tags are normalized as set by [`TagSyntax::lowercase`]
+    /// and [`TagSyntax::strip_prefix`].
+    ///
+    /// # Arguments
+    ///
+    /// * `string` - The string to extract from.
+    #[must_use]
+    pub fn extract(&self, string: &str) -> BTreeSet<String> {
+        self.regex
+            .find_iter(string)
+            .map(|tag| self.normalize(tag.as_str().trim()))
+            .collect()
+    }
+
+    /// Removes all tags from a string.
+    ///
+    /// # Arguments
+    ///
+    /// * `string` - The string to remove from.
+    #[must_use]
+    pub fn remove(&self, string: &str) -> String {
+        self.regex.replace_all(string, "").trim().to_owned()
+    }
+
+    /// Normalizes a tag as set by [`TagSyntax::lowercase`] and [`TagSyntax::strip_prefix`].
+    ///
+    /// # Arguments
+    ///
+    /// * `tag` - The tag to normalize, including its prefix.
+    fn normalize(&self, tag: &str) -> String {
+        let tag = if self.strip_prefix {
+            tag.strip_prefix(self.prefix).unwrap_or(tag)
+        } else {
+            tag
+        };
+
+        if self.lowercase {
+            tag.to_lowercase()
+        } else {
+            tag.to_owned()
+        }
+    }
+}
+
+/// Extracts all `#tags` from a string using the default [`TagSyntax`].
 ///
 /// # Arguments
 ///
 /// * `string` - The string to extract from.
 #[must_use]
 pub fn extract_tags(string: &str) -> BTreeSet<String> {
-    let mut tags = RE_TAG
-        .find_iter(string)
-        .map(|t| t.as_str())
-        .map(str::trim)
-        .map(ToOwned::to_owned)
-        .collect::<Vec<String>>();
+    TagSyntax::default().extract(string)
+}
 
-    tags.sort();
+/// Removes all `#tags` from a string using the default [`TagSyntax`].
+///
+/// # Arguments
+///
+/// * `string` - The string to remove from.
+#[must_use]
+pub fn remove_tags(string: &str) -> String {
+    TagSyntax::default().remove(string)
+}
 
-    BTreeSet::from_iter(tags)
+/// Returns `true` if a tag is one of the [`FAVORITE_TAGS`], ignoring its prefix and case e.g.
+/// `@Fav` or `favorite`.
+///
+/// # Arguments
+///
+/// * `tag` - The tag to check.
+#[must_use]
+pub fn is_favorite_tag(tag: &str) -> bool {
+    let name = tag.trim_start_matches(|char: char| !char.is_alphanumeric());
+
+    FAVORITE_TAGS.iter().any(|favorite| {
+        favorite
+            .trim_start_matches(DEFAULT_TAG_PREFIX)
+            .eq_ignore_ascii_case(name)
+    })
 }
 
-/// Removes all `#tags` from a string.
+/// Returns `true` if a string contains a favorite marker. This is either a lone `*` e.g. `* A
+/// note.` or one of the [`FAVORITE_TAGS`] e.g. `A note. #fav`.
+///
+/// # Arguments
+///
+/// * `string` - The string to check.
+#[must_use]
+pub fn is_favorite(string: &str) -> bool {
+    RE_FAVORITE.is_match(string)
+        || self::extract_tags(string)
+            .iter()
+            .any(|tag| self::is_favorite_tag(tag))
+}
+
+/// Removes all lone `*` favorite markers from a string. Any [`FAVORITE_TAGS`] are left in place
+/// as they're removed along with other `#tags`.
 ///
 /// # Arguments
 ///
 /// * `string` - The string to remove from.
 #[must_use]
-pub fn remove_tags(string: &str) -> String {
-    RE_TAG.replace_all(string, "").trim().to_owned()
+pub fn remove_favorite_markers(string: &str) -> String {
+    RE_FAVORITE
+        .replace_all(string, |captures: &regex::Captures<'_>| {
+            let (before, after) = (&captures[1], &captures[2]);
+
+            // Line-breaks around the marker are kept so the lines it sat between stay apart.
+            if before.contains('\n') || after.contains('\n') {
+                "\n"
+            } else if before.is_empty() || after.is_empty() {
+                ""
+            } else {
+                " "
+            }
+        })
+        .trim()
+        .to_owned()
+}
+
+/// Extracts all `key:: value` properties from a string. If a key appears more than once, its last
+/// value is kept.
+///
+/// # Arguments
+///
+/// * `string` - The string to extract from.
+#[must_use]
+pub fn extract_properties(string: &str) -> BTreeMap<String, String> {
+    RE_PROPERTY
+        .captures_iter(string)
+        .map(|captures| (captures[1].to_owned(), captures[2].to_owned()))
+        .collect()
+}
+
+/// Removes all `key:: value` properties from a string.
+///
+/// # Arguments
+///
+/// * `string` - The string to remove from.
+#[must_use]
+pub fn remove_properties(string: &str) -> String {
+    RE_PROPERTY.replace_all(string, "").trim().to_owned()
+}
+
+/// Extracts all `key: value` fields from a string. Values that are numbers e.g. `rating: 5` are
+/// parsed as numbers while everything else is kept as a string. If a key appears more than once,
+/// its last value is kept.
+///
+/// # Arguments
+///
+/// * `string` - The string to extract from.
+#[must_use]
+pub fn extract_fields(string: &str) -> BTreeMap<String, Value> {
+    RE_FIELD
+        .captures_iter(string)
+        .map(|captures| {
+            let value = &captures[2];
+
+            let value = value
+                .parse::<i64>()
+                .map(Value::from)
+                .or_else(|_| value.parse::<f64>().map(Value::from))
+                .ok()
+                .filter(Value::is_number)
+                .unwrap_or_else(|| Value::from(value));
+
+            (captures[1].to_owned(), value)
+        })
+        .collect()
+}
+
+/// Removes all `key: value` fields from a string.
+///
+/// # Arguments
+///
+/// * `string` - The string to remove from.
+#[must_use]
+pub fn remove_fields(string: &str) -> String {
+    RE_FIELD.replace_all(string, "").trim().to_owned()
 }
 
 /// Converts all Unicode characters to their ASCII equivalent.
@@ -235,6 +568,73 @@ pub fn convert_symbols_to_ascii(string: &str) -> String {
     string
 }
 
+/// Converts curly quotes to straight quotes, leaving all other symbols untouched.
+///
+/// See [`CURLY_QUOTES_TO_ASCII`][quotes] for list of quotes and their ASCII equivalents.
+///
+/// # Arguments
+///
+/// * `string` - The string to convert.
+///
+/// [quotes]: crate::defaults::CURLY_QUOTES_TO_ASCII
+#[must_use]
+pub fn straighten_quotes(string: &str) -> String {
+    string
+        .chars()
+        .map(|char| {
+            crate::defaults::CURLY_QUOTES_TO_ASCII
+                .iter()
+                .find(|(from, _)| *from == char)
+                .map_or(char, |(_, to)| *to)
+        })
+        .collect()
+}
+
+/// Expands ellipsis characters `…` into three periods `...`.
+///
+/// # Arguments
+///
+/// * `string` - The string to convert.
+#[must_use]
+pub fn expand_ellipses(string: &str) -> String {
+    string.replace('…', "...")
+}
+
+/// Replaces non-breaking spaces with regular spaces.
+///
+/// See [`NON_BREAKING_SPACES`][spaces] for the list of spaces replaced.
+///
+/// # Arguments
+///
+/// * `string` - The string to convert.
+///
+/// [spaces]: crate::defaults::NON_BREAKING_SPACES
+#[must_use]
+pub fn replace_nbsp(string: &str) -> String {
+    string.replace(crate::defaults::NON_BREAKING_SPACES, " ")
+}
+
+/// Escapes the characters that are significant in Markdown: `\`, `*`, `_`, `[`, `]` and `` ` ``, so
+/// they're rendered as is instead of being read as formatting.
+///
+/// # Arguments
+///
+/// * `string` - The string to escape.
+#[must_use]
+pub fn escape_markdown(string: &str) -> String {
+    let mut escaped = String::with_capacity(string.len());
+
+    for char in string.chars() {
+        if crate::defaults::MARKDOWN_SPECIAL_CHARS.contains(&char) {
+            escaped.push('\\');
+        }
+
+        escaped.push(char);
+    }
+
+    escaped
+}
+
 /// Normalizes linebreaks by replacing three or more consecutive linebreaks with two consecutive
 /// linebreaks while leaving a single trailing linebreak.
 ///
@@ -264,6 +664,110 @@ mod test {
 
     use super::*;
 
+    #[test]
+    fn isbn() {
+        assert_eq!(
+            to_isbn("978-0-06-202639-2").as_deref(),
+            Some("9780062026392")
+        );
+        assert_eq!(
+            to_isbn("isbn_9780393316049").as_deref(),
+            Some("9780393316049")
+        );
+        assert_eq!(
+            to_isbn("urn:isbn:0-306-40615-x").as_deref(),
+            Some("030640615X")
+        );
+        assert_eq!(to_isbn("urn:uuid:1f2e3d4c-5b6a-7980"), None);
+        assert_eq!(to_isbn(""), None);
+    }
+
+    #[test]
+    fn favorites() {
+        assert!(is_favorite("*"));
+        assert!(is_favorite("* A note."));
+        assert!(is_favorite("A note. *"));
+        assert!(is_favorite("A note. #fav"));
+        assert!(is_favorite("#favorite"));
+        assert!(!is_favorite("A **bold** note."));
+        assert!(!is_favorite("A note. #favorites"));
+
+        assert_eq!(remove_favorite_markers("* A note."), "A note.");
+        assert_eq!(remove_favorite_markers("A note. *"), "A note.");
+        assert_eq!(remove_favorite_markers("A * note."), "A note.");
+        assert_eq!(
+            remove_favorite_markers("A note.\n*\nMore."),
+            "A note.\nMore."
+        );
+        assert_eq!(remove_favorite_markers("A note. #fav"), "A note. #fav");
+    }
+
+    #[test]
+    fn tag_syntax() {
+        let syntax = TagSyntax::new('@', Some("-/"));
+
+        assert_eq!(
+            syntax.extract("@topic/sub-topic. @Tag_01 #tag @1"),
+            BTreeSet::from(["@topic/sub-topic".to_string(), "@Tag_01".to_string()])
+        );
+        assert_eq!(syntax.remove("A note. @tag #tag"), "A note. #tag");
+
+        let syntax = TagSyntax {
+            lowercase: true,
+            strip_prefix: true,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            syntax.extract("#Tag01 #tag01 #TAG02"),
+            BTreeSet::from(["tag01".to_string(), "tag02".to_string()])
+        );
+
+        assert!(is_favorite_tag("#fav"));
+        assert!(is_favorite_tag("@Favorite"));
+        assert!(is_favorite_tag("fav"));
+        assert!(!is_favorite_tag("#favorites"));
+    }
+
+    #[test]
+    fn title_hint() {
+        assert_eq!(
+            super::to_title_hint("Art is the province of every human being.", 4),
+            "Art province every human"
+        );
+        assert_eq!(
+            super::to_title_hint("\"Don't,\" she said -- and left.", 4),
+            "Don't said left"
+        );
+        assert_eq!(super::to_title_hint("It is so.", 4), "");
+    }
+
+    #[test]
+    fn relative_date() {
+        let now = DateTime::parse_from_rfc3339("2024-06-01T12:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+
+        let cases = [
+            ("2024-06-01T09:00:00Z", "today"),
+            ("2024-05-31T12:00:00Z", "yesterday"),
+            ("2024-05-28T12:00:00Z", "4 days ago"),
+            ("2024-05-25T12:00:00Z", "1 week ago"),
+            ("2024-05-04T12:00:00Z", "4 weeks ago"),
+            ("2024-03-01T12:00:00Z", "3 months ago"),
+            ("2022-01-01T12:00:00Z", "2 years ago"),
+            ("2024-07-01T12:00:00Z", "today"),
+        ];
+
+        for (date, expected) in cases {
+            let date = DateTime::parse_from_rfc3339(date)
+                .unwrap()
+                .with_timezone(&Utc);
+
+            assert_eq!(super::to_relative_date(&date, &now), expected);
+        }
+    }
+
     #[test]
     fn strip() {
         assert_eq!(
@@ -414,4 +918,73 @@ mod test {
             ["#tag01", "#tag02"]
         ),
     }
+
+    // Tests that only `key:: value` properties on their own lines are extracted and removed.
+    #[test]
+    fn remove_and_extract_properties() {
+        let string =
+            "Lorem ipsum.\npage:: 42\n  rating::5  \nsource: web\nIpsum:: a::b\nrating:: 4";
+
+        let properties = super::extract_properties(string);
+        let expected = BTreeMap::from_iter(
+            [("Ipsum", "a::b"), ("page", "42"), ("rating", "4")]
+                .map(|(key, value)| (key.to_owned(), value.to_owned())),
+        );
+
+        assert_eq!(properties, expected);
+        assert_eq!(
+            super::remove_properties(string),
+            "Lorem ipsum.\nsource: web"
+        );
+    }
+
+    // Tests that only curly quotes are straightened.
+    #[test]
+    fn straighten_quotes() {
+        assert_eq!(
+            super::straighten_quotes("‘Lorem’ “ipsum”… — «dolor»"),
+            "'Lorem' \"ipsum\"… — «dolor»"
+        );
+    }
+
+    // Tests that ellipses are expanded and non-breaking spaces are replaced.
+    #[test]
+    fn expand_ellipses_and_replace_nbsp() {
+        assert_eq!(super::expand_ellipses("Lorem… ipsum…"), "Lorem... ipsum...");
+        assert_eq!(
+            super::replace_nbsp("Lorem\u{00A0}ipsum\u{202F}dolor\u{2007}1"),
+            "Lorem ipsum dolor 1"
+        );
+    }
+
+    // Tests that Markdown-significant characters are escaped, including existing backslashes.
+    #[test]
+    fn escape_markdown() {
+        assert_eq!(
+            super::escape_markdown(r"*Lorem* _ipsum_ [dolor](sit) `amet` \ #tag"),
+            r"\*Lorem\* \_ipsum\_ \[dolor\](sit) \`amet\` \\ #tag"
+        );
+    }
+
+    // Tests that only `key: value` fields on their own lines are extracted and removed and that
+    // numbers are parsed.
+    #[test]
+    fn remove_and_extract_fields() {
+        let string =
+            "Lorem ipsum.\nrating: 5\n  q: Why?  \nscore: 4.5\npage:: 42\nhttps://a.com\nnan: NaN";
+
+        let fields = super::extract_fields(string);
+        let expected = BTreeMap::from_iter([
+            ("nan".to_owned(), Value::from("NaN")),
+            ("q".to_owned(), Value::from("Why?")),
+            ("rating".to_owned(), Value::from(5)),
+            ("score".to_owned(), Value::from(4.5)),
+        ]);
+
+        assert_eq!(fields, expected);
+        assert_eq!(
+            super::remove_fields(string),
+            "Lorem ipsum.\npage:: 42\nhttps://a.com"
+        );
+    }
 }
@@ -0,0 +1,457 @@
+//! Defines a minimal Hypothes.is client for syncing annotations.
+//!
+//! See the [Hypothes.is API reference][api] for more information.
+//!
+//! [api]: https://h.readthedocs.io/en/latest/api-reference/v1/
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::models::annotation::Annotation;
+use crate::models::book::Book;
+use crate::models::datetime::DateTimeUtc;
+use crate::models::entry::Entry;
+use crate::result::{Error, Result};
+use crate::strings;
+
+/// The base URL of the Hypothes.is API.
+const API_URL: &str = "https://api.hypothes.is/api";
+
+/// The environment variable the token is read from if none is set.
+const TOKEN_VARIABLE: &str = "READSTOR_HYPOTHESIS_TOKEN";
+
+/// The name of the state file saved to the output directory.
+const STATE_FILENAME: &str = ".readstor-hypothesis.json";
+
+/// The id of the public group. Annotations posted to it without a group set are kept private.
+const PUBLIC_GROUP: &str = "__world__";
+
+/// The minimum time between requests. Hypothes.is doesn't publish a limit so this errs on the
+/// side of caution.
+const REQUEST_INTERVAL: Duration = Duration::from_millis(250);
+
+/// A struct representing options for syncing to Hypothes.is.
+#[derive(Debug, Clone, Default)]
+pub struct HypothesisOptions {
+    /// The developer token. Read from `READSTOR_HYPOTHESIS_TOKEN` if unset.
+    pub token: Option<String>,
+
+    /// The id of the group to post annotations to. Annotations are private if unset.
+    pub group: Option<String>,
+}
+
+/// A struct representing the outcome of a sync.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct SyncStats {
+    /// The number of annotations created for annotations synced for the first time.
+    pub annotations_created: usize,
+
+    /// The number of existing annotations updated after being modified in Apple Books.
+    pub annotations_updated: usize,
+}
+
+/// Syncs annotations to Hypothes.is.
+///
+/// Each annotation is created as a Hypothes.is annotation anchored to its book's URI: `urn:isbn:`
+/// followed by the book's ISBN if it has one, otherwise a URI derived from its title and author.
+/// The annotation's body is the quoted text, its notes are the annotation's text and its tags are
+/// the annotation's tags without the leading `#`.
+///
+/// The ids of the created annotations are saved to `.readstor-hypothesis.json` in the output
+/// directory. On subsequent runs, annotations that haven't been synced yet are created and ones
+/// that have been modified since they were synced are updated. Deleting this file causes
+/// everything to be synced again.
+///
+/// # Arguments
+///
+/// * `entries` - The entries to sync.
+/// * `directory` - The output directory the sync state is saved to.
+/// * `options` - The Hypothes.is options.
+///
+/// # Errors
+///
+/// Will return `Err` if:
+/// * No token is set.
+/// * Any requests fail.
+/// * Any IO errors are encountered.
+pub fn run<'a, I, O>(entries: I, directory: &Path, options: O) -> Result<SyncStats>
+where
+    I: IntoIterator<Item = &'a Entry>,
+    O: Into<HypothesisOptions>,
+{
+    let options: HypothesisOptions = options.into();
+
+    let token = match options.token {
+        Some(token) => token,
+        None => std::env::var(TOKEN_VARIABLE).map_err(|_| Error::SyncMissingCredentials {
+            name: TOKEN_VARIABLE.to_owned(),
+        })?,
+    };
+
+    let mut client = Client::new(token);
+    let mut sync_state = SyncState::load(directory)?;
+    let mut stats = SyncStats::default();
+
+    let read = match &options.group {
+        Some(group) => format!("group:{group}"),
+        None => client.userid()?,
+    };
+    let group = options.group.as_deref().unwrap_or(PUBLIC_GROUP);
+
+    for entry in entries {
+        let mut created = 0;
+        let mut updated = 0;
+
+        for annotation in &entry.annotations {
+            let id = &annotation.metadata.id;
+            let body = self::annotation_body(&entry.book, annotation, group, &read);
+
+            match sync_state.annotations.get(id) {
+                Some(synced) if synced.modified == annotation.metadata.modified => continue,
+                Some(synced) => {
+                    client.update_annotation(&synced.id, &body)?;
+                    updated += 1;
+                }
+                None => {
+                    let hypothesis_id = client.create_annotation(&body)?;
+
+                    // The id is saved right after each annotation is created so that an
+                    // interrupted sync never creates duplicate annotations.
+                    sync_state.annotations.insert(
+                        id.clone(),
+                        SyncedAnnotation {
+                            id: hypothesis_id,
+                            modified: annotation.metadata.modified,
+                        },
+                    );
+                    created += 1;
+                }
+            }
+
+            if let Some(synced) = sync_state.annotations.get_mut(id) {
+                synced.modified = annotation.metadata.modified;
+            }
+            sync_state.save()?;
+        }
+
+        stats.annotations_created += created;
+        stats.annotations_updated += updated;
+
+        tracing::debug!(
+            created,
+            updated,
+            title = %entry.book.title,
+            "synced book to Hypothes.is"
+        );
+    }
+
+    Ok(stats)
+}
+
+/// A client for the Hypothes.is API that rate-limits its requests.
+struct Client {
+    /// The developer token.
+    token: String,
+
+    /// When the last request was sent, if any.
+    last_request: Option<Instant>,
+}
+
+impl Client {
+    /// Creates a new instance of [`Client`].
+    ///
+    /// # Arguments
+    ///
+    /// * `token` - The developer token.
+    fn new(token: String) -> Self {
+        Self {
+            token,
+            last_request: None,
+        }
+    }
+
+    /// Returns the id of the user the token belongs to e.g. `acct:username@hypothes.is`.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the request fails or the token isn't valid.
+    fn userid(&mut self) -> Result<String> {
+        let profile = self.request("GET", "profile", None)?;
+
+        profile["userid"]
+            .as_str()
+            .map(str::to_owned)
+            .ok_or_else(|| Error::SyncError {
+                url: format!("{API_URL}/profile"),
+                error: "token is not valid".to_owned(),
+            })
+    }
+
+    /// Creates an annotation and returns its id.
+    ///
+    /// # Arguments
+    ///
+    /// * `body` - The annotation's JSON body.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the request fails.
+    fn create_annotation(&mut self, body: &Value) -> Result<String> {
+        let annotation = self.request("POST", "annotations", Some(body))?;
+
+        annotation["id"]
+            .as_str()
+            .map(str::to_owned)
+            .ok_or_else(|| Error::SyncError {
+                url: format!("{API_URL}/annotations"),
+                error: "response is missing the annotation's id".to_owned(),
+            })
+    }
+
+    /// Updates an existing annotation.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The annotation's id.
+    /// * `body` - The annotation's JSON body.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the request fails.
+    fn update_annotation(&mut self, id: &str, body: &Value) -> Result<()> {
+        self.request("PATCH", &format!("annotations/{id}"), Some(body))?;
+
+        Ok(())
+    }
+
+    /// Sends a request and returns the response's body.
+    ///
+    /// Requests are spaced at least [`REQUEST_INTERVAL`] apart. See [`super::send_with_retry()`]
+    /// for how rate-limited requests are retried.
+    ///
+    /// # Arguments
+    ///
+    /// * `method` - The request's method.
+    /// * `path` - The request's path relative to the API's base URL.
+    /// * `body` - The request's JSON body, if any.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the request fails or the response cannot be parsed.
+    fn request(&mut self, method: &str, path: &str, body: Option<&Value>) -> Result<Value> {
+        let url = format!("{API_URL}/{path}");
+
+        let response = super::send_with_retry("Hypothes.is", &url, "reason", body, || {
+            self.wait();
+
+            ureq::request(method, &url)
+                .set("Authorization", &format!("Bearer {}", self.token))
+                .set("Accept", "application/vnd.hypothesis.v1+json")
+        })?;
+
+        Ok(serde_json::from_str(&response.into_string()?)?)
+    }
+
+    /// Blocks until at least [`REQUEST_INTERVAL`] has passed since the last request.
+    fn wait(&mut self) {
+        if let Some(remaining) = self
+            .last_request
+            .and_then(|last_request| REQUEST_INTERVAL.checked_sub(last_request.elapsed()))
+        {
+            std::thread::sleep(remaining);
+        }
+
+        self.last_request = Some(Instant::now());
+    }
+}
+
+/// A struct representing which annotations have been synced to Hypothes.is.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct SyncState {
+    /// The path to the state file.
+    #[serde(skip)]
+    path: PathBuf,
+
+    /// The synced annotations keyed by the annotation's id.
+    annotations: BTreeMap<String, SyncedAnnotation>,
+}
+
+/// A struct representing an annotation that has been synced to Hypothes.is.
+#[derive(Debug, Serialize, Deserialize)]
+struct SyncedAnnotation {
+    /// The id of the Hypothes.is annotation.
+    id: String,
+
+    /// The annotation's modification date when it was last synced.
+    modified: DateTimeUtc,
+}
+
+impl SyncState {
+    /// Loads the state. Returns an empty state if none was saved.
+    ///
+    /// # Arguments
+    ///
+    /// * `directory` - The output directory the state is saved to.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the state file exists but cannot be read or parsed.
+    fn load(directory: &Path) -> Result<Self> {
+        let path = directory.join(STATE_FILENAME);
+        let state: Self = super::load_state(&path)?;
+
+        Ok(Self { path, ..state })
+    }
+
+    /// Saves the state. See [`super::save_state()`].
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if any IO errors are encountered.
+    fn save(&self) -> Result<()> {
+        super::save_state(&self.path, self)
+    }
+}
+
+/// Returns the URI a book's annotations are anchored to.
+///
+/// # Arguments
+///
+/// * `book` - The book.
+fn book_uri(book: &Book) -> String {
+    match &book.metadata.isbn {
+        Some(isbn) => format!("urn:isbn:{isbn}"),
+        None => format!(
+            "urn:x-readstor:{}",
+            strings::to_slug(&format!("{} {}", book.author, book.title), true)
+        ),
+    }
+}
+
+/// Returns the JSON body an annotation is created or updated with.
+///
+/// # Arguments
+///
+/// * `book` - The annotation's book.
+/// * `annotation` - The annotation to convert.
+/// * `group` - The id of the group to post to.
+/// * `read` - Who can read the annotation e.g. a user or group id.
+fn annotation_body(book: &Book, annotation: &Annotation, group: &str, read: &str) -> Value {
+    let uri = self::book_uri(book);
+
+    let tags: Vec<&str> = annotation
+        .tags
+        .iter()
+        .map(|tag| tag.trim_start_matches('#'))
+        .collect();
+
+    json!({
+        "uri": uri,
+        "document": { "title": [book.title] },
+        "text": annotation.notes,
+        "tags": tags,
+        "group": group,
+        "permissions": { "read": [read] },
+        "target": [{
+            "source": uri,
+            "selector": [{ "type": "TextQuoteSelector", "exact": annotation.body }],
+        }],
+    })
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    use std::collections::BTreeSet;
+
+    use crate::defaults::TEMP_OUTPUT_DIRECTORY;
+
+    fn book(isbn: Option<&str>) -> Book {
+        let mut book = Book {
+            title: "The Art Spirit".to_string(),
+            author: "Robert Henri".to_string(),
+            ..Default::default()
+        };
+        book.metadata.isbn = isbn.map(str::to_owned);
+        book
+    }
+
+    // Tests that a book's ISBN is preferred over its title and author.
+    #[test]
+    fn uri() {
+        assert_eq!(
+            book_uri(&book(Some("9780465002634"))),
+            "urn:isbn:9780465002634"
+        );
+        assert_eq!(
+            book_uri(&book(None)),
+            "urn:x-readstor:robert-henri-the-art-spirit"
+        );
+    }
+
+    // Tests that notes are mapped to the annotation's text and tags are stripped of their `#`.
+    #[test]
+    fn body() {
+        let annotation = Annotation {
+            body: "We are not here to do what has already been done.".to_string(),
+            notes: "Robert Henri".to_string(),
+            tags: BTreeSet::from(["#art".to_string(), "#quote".to_string()]),
+            ..Default::default()
+        };
+
+        let body = annotation_body(
+            &book(None),
+            &annotation,
+            "__world__",
+            "acct:user@hypothes.is",
+        );
+
+        assert_eq!(body["text"], "Robert Henri");
+        assert_eq!(body["tags"], json!(["art", "quote"]));
+        assert_eq!(body["document"]["title"], json!(["The Art Spirit"]));
+        assert_eq!(
+            body["permissions"]["read"],
+            json!(["acct:user@hypothes.is"])
+        );
+        assert_eq!(body["target"][0]["source"], body["uri"]);
+        assert_eq!(
+            body["target"][0]["selector"][0]["exact"],
+            "We are not here to do what has already been done."
+        );
+    }
+
+    #[test]
+    fn state_save_and_load() {
+        let directory = TEMP_OUTPUT_DIRECTORY.join("tests-hypothesis");
+
+        if directory.exists() {
+            std::fs::remove_dir_all(&directory).unwrap();
+        }
+
+        let mut state = SyncState::load(&directory).unwrap();
+
+        assert!(state.annotations.is_empty());
+
+        state.annotations.insert(
+            "A".to_string(),
+            SyncedAnnotation {
+                id: "hypothesis".to_string(),
+                modified: DateTimeUtc::default(),
+            },
+        );
+        state.save().unwrap();
+
+        assert!(directory.join(STATE_FILENAME).exists());
+
+        let state = SyncState::load(&directory).unwrap();
+
+        assert_eq!(state.annotations["A"].id, "hypothesis");
+        assert_eq!(state.annotations["A"].modified, DateTimeUtc::default());
+    }
+}
@@ -0,0 +1,185 @@
+//! Defines types for syncing books and annotations to third-party services.
+
+pub mod hypothesis;
+pub mod notion;
+pub mod webhook;
+
+use std::path::Path;
+use std::time::Duration;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::result::{Error, Result};
+
+/// The maximum number of times a rate-limited request is retried.
+const MAX_RETRIES: u32 = 3;
+
+/// The delay before retrying a rate-limited request if the service doesn't return one.
+const DEFAULT_RETRY_DELAY: Duration = Duration::from_secs(1);
+
+/// Sends a request and returns its response. Rate-limited requests are retried after the delay
+/// returned by the service, up to [`MAX_RETRIES`] times.
+///
+/// # Arguments
+///
+/// * `service` - The service's name, used for logging.
+/// * `url` - The request's URL.
+/// * `error_field` - The field of an error response's JSON body containing the error message.
+/// * `body` - The request's JSON body, if any.
+/// * `request` - A closure that builds the request. This is called once per attempt.
+///
+/// # Errors
+///
+/// Will return `Err` if the request fails.
+pub(crate) fn send_with_retry<F>(
+    service: &str,
+    url: &str,
+    error_field: &str,
+    body: Option<&Value>,
+    mut request: F,
+) -> Result<ureq::Response>
+where
+    F: FnMut() -> ureq::Request,
+{
+    let body = body.map(Value::to_string);
+
+    let mut retries = 0;
+
+    loop {
+        let response = match &body {
+            Some(body) => request()
+                .set("Content-Type", "application/json")
+                .send_string(body),
+            None => request().call(),
+        };
+
+        match response {
+            Ok(response) => return Ok(response),
+            Err(ureq::Error::Status(429, response)) if retries < MAX_RETRIES => {
+                let delay = response
+                    .header("Retry-After")
+                    .and_then(|seconds| seconds.parse().ok())
+                    .map_or(DEFAULT_RETRY_DELAY, Duration::from_secs);
+
+                tracing::warn!(retry_in = ?delay, "rate-limited by {service}, retrying");
+
+                std::thread::sleep(delay);
+                retries += 1;
+            }
+            Err(error) => return Err(self::request_error(url, error, error_field)),
+        }
+    }
+}
+
+/// Maps a failed request to an [`Error`]. The service's error message is included if there is
+/// one.
+///
+/// # Arguments
+///
+/// * `url` - The request's URL.
+/// * `error` - The request's error.
+/// * `error_field` - The field of the response's JSON body containing the error message.
+fn request_error(url: &str, error: ureq::Error, error_field: &str) -> Error {
+    let error = match error {
+        ureq::Error::Status(status, response) => {
+            let message = response
+                .into_string()
+                .ok()
+                .and_then(|body| serde_json::from_str::<Value>(&body).ok())
+                .and_then(|body| body[error_field].as_str().map(str::to_owned));
+
+            match message {
+                Some(message) => format!("status code {status}: {message}"),
+                None => format!("status code {status}"),
+            }
+        }
+        error @ ureq::Error::Transport(_) => error.to_string(),
+    };
+
+    Error::SyncError {
+        url: url.to_owned(),
+        error,
+    }
+}
+
+/// Loads a backend's sync state. Returns the default state if none was saved.
+///
+/// # Arguments
+///
+/// * `path` - The path to the state file.
+///
+/// # Errors
+///
+/// Will return `Err` if the state file exists but cannot be read or parsed.
+pub(crate) fn load_state<T>(path: &Path) -> Result<T>
+where
+    T: DeserializeOwned + Default,
+{
+    if !path.exists() {
+        return Ok(T::default());
+    }
+
+    Ok(serde_json::from_str(&std::fs::read_to_string(path)?)?)
+}
+
+/// Saves a backend's sync state. The state is written to a temporary file first and then renamed
+/// so that an interruption never leaves a partially written state file.
+///
+/// # Arguments
+///
+/// * `path` - The path to the state file.
+/// * `state` - The state to save.
+///
+/// # Errors
+///
+/// Will return `Err` if any IO errors are encountered.
+pub(crate) fn save_state<T>(path: &Path, state: &T) -> Result<()>
+where
+    T: Serialize,
+{
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    let temp = path.with_extension("json.tmp");
+
+    std::fs::write(&temp, serde_json::to_string(state)?)?;
+    std::fs::rename(&temp, path)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    use std::collections::BTreeSet;
+
+    use crate::defaults::TEMP_OUTPUT_DIRECTORY;
+
+    // Tests that a saved state is loaded back and a missing one is loaded as the default.
+    #[test]
+    fn state_save_and_load() {
+        let directory = TEMP_OUTPUT_DIRECTORY.join("tests-sync");
+
+        if directory.exists() {
+            std::fs::remove_dir_all(&directory).unwrap();
+        }
+
+        let path = directory.join(".readstor-test.json");
+
+        let state: BTreeSet<String> = load_state(&path).unwrap();
+
+        assert!(state.is_empty());
+
+        save_state(&path, &BTreeSet::from(["A".to_string()])).unwrap();
+
+        let state: BTreeSet<String> = load_state(&path).unwrap();
+
+        assert!(state.contains("A"));
+        assert!(!path.with_extension("json.tmp").exists());
+    }
+}
@@ -0,0 +1,516 @@
+//! Defines a minimal Notion client for syncing books and annotations to a database.
+//!
+//! See the [Notion API reference][api] for more information.
+//!
+//! [api]: https://developers.notion.com/reference/intro
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::models::annotation::Annotation;
+use crate::models::book::Book;
+use crate::models::entry::Entry;
+use crate::result::{Error, Result};
+
+/// The base URL of the Notion API.
+const API_URL: &str = "https://api.notion.com/v1";
+
+/// The version of the Notion API requests are made against.
+const API_VERSION: &str = "2022-06-28";
+
+/// The environment variable the token is read from if none is set.
+const TOKEN_VARIABLE: &str = "READSTOR_NOTION_TOKEN";
+
+/// The minimum time between requests. Notion allows an average of three requests per second.
+const REQUEST_INTERVAL: Duration = Duration::from_millis(334);
+
+/// The maximum number of annotations appended per request. Each annotation is at most two blocks
+/// and Notion allows appending at most 100 blocks per request.
+const MAX_ANNOTATIONS_PER_REQUEST: usize = 50;
+
+/// The maximum number of characters in a single rich text object.
+const MAX_TEXT_LENGTH: usize = 2000;
+
+/// A struct representing options for syncing to Notion.
+#[derive(Debug, Clone, Default)]
+pub struct NotionOptions {
+    /// The integration token. Read from `READSTOR_NOTION_TOKEN` if unset.
+    pub token: Option<String>,
+
+    /// The id of the database to sync to.
+    pub database_id: String,
+}
+
+/// A struct representing the outcome of a sync.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct SyncStats {
+    /// The number of pages created for books synced for the first time.
+    pub pages_created: usize,
+
+    /// The number of existing pages new annotations were appended to.
+    pub pages_updated: usize,
+
+    /// The number of annotations appended.
+    pub annotations_appended: usize,
+}
+
+/// Syncs books and annotations to a Notion database.
+///
+/// A page is created in the database for each book, titled after the book, and each annotation is
+/// appended to it as a quote block followed by its notes, if any. The integration must be
+/// connected to the database for it to be accessible.
+///
+/// The ids of the pages and annotations that have been synced are saved to
+/// `.readstor-notion-[database-id].json` in the output directory. On subsequent runs, only
+/// annotations that haven't been synced yet are appended, to each book's existing page. Deleting
+/// this file causes everything to be synced again.
+///
+/// # Arguments
+///
+/// * `entries` - The entries to sync.
+/// * `directory` - The output directory the sync state is saved to.
+/// * `options` - The Notion options.
+///
+/// # Errors
+///
+/// Will return `Err` if:
+/// * No token is set.
+/// * The database doesn't exist or doesn't have a title property.
+/// * Any requests fail.
+/// * Any IO errors are encountered.
+pub fn run<'a, I, O>(entries: I, directory: &Path, options: O) -> Result<SyncStats>
+where
+    I: IntoIterator<Item = &'a Entry>,
+    O: Into<NotionOptions>,
+{
+    let options: NotionOptions = options.into();
+
+    let token = match options.token {
+        Some(token) => token,
+        None => std::env::var(TOKEN_VARIABLE).map_err(|_| Error::SyncMissingCredentials {
+            name: TOKEN_VARIABLE.to_owned(),
+        })?,
+    };
+
+    let mut client = Client::new(token);
+    let mut sync_state = SyncState::load(directory, &options.database_id)?;
+    let mut stats = SyncStats::default();
+
+    let title_property = client.title_property(&options.database_id)?;
+
+    for entry in entries {
+        let id = &entry.book.metadata.id;
+        let synced = sync_state.books.get(id);
+
+        let annotations: Vec<&Annotation> = entry
+            .annotations
+            .iter()
+            .filter(|annotation| {
+                !synced.is_some_and(|book| book.annotations.contains(&annotation.metadata.id))
+            })
+            .collect();
+
+        let page_id = match synced {
+            Some(_) if annotations.is_empty() => continue,
+            Some(book) => {
+                stats.pages_updated += 1;
+                book.page_id.clone()
+            }
+            None => {
+                let page_id =
+                    client.create_page(&options.database_id, &title_property, &entry.book)?;
+
+                // The page is saved before appending annotations so that an interrupted sync
+                // never creates a duplicate page.
+                sync_state.books.insert(
+                    id.clone(),
+                    SyncedBook {
+                        page_id: page_id.clone(),
+                        annotations: BTreeSet::new(),
+                    },
+                );
+                sync_state.save()?;
+
+                stats.pages_created += 1;
+                page_id
+            }
+        };
+
+        for chunk in annotations.chunks(MAX_ANNOTATIONS_PER_REQUEST) {
+            let blocks: Vec<Value> = chunk
+                .iter()
+                .flat_map(|annotation| self::annotation_blocks(annotation))
+                .collect();
+
+            client.append_blocks(&page_id, &blocks)?;
+
+            if let Some(book) = sync_state.books.get_mut(id) {
+                book.annotations.extend(
+                    chunk
+                        .iter()
+                        .map(|annotation| annotation.metadata.id.clone()),
+                );
+            }
+            sync_state.save()?;
+
+            stats.annotations_appended += chunk.len();
+        }
+
+        tracing::debug!(
+            annotations = annotations.len(),
+            title = %entry.book.title,
+            page = %page_id,
+            "synced book to Notion"
+        );
+    }
+
+    Ok(stats)
+}
+
+/// A client for the Notion API that rate-limits its requests.
+struct Client {
+    /// The integration token.
+    token: String,
+
+    /// When the last request was sent, if any.
+    last_request: Option<Instant>,
+}
+
+impl Client {
+    /// Creates a new instance of [`Client`].
+    ///
+    /// # Arguments
+    ///
+    /// * `token` - The integration token.
+    fn new(token: String) -> Self {
+        Self {
+            token,
+            last_request: None,
+        }
+    }
+
+    /// Returns the name of a database's title property.
+    ///
+    /// # Arguments
+    ///
+    /// * `database_id` - The database's id.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the request fails or the database doesn't have a title property.
+    fn title_property(&mut self, database_id: &str) -> Result<String> {
+        let database = self.request("GET", &format!("databases/{database_id}"), None)?;
+
+        self::find_title_property(&database).ok_or_else(|| Error::SyncInvalidDatabase {
+            id: database_id.to_owned(),
+        })
+    }
+
+    /// Creates a page for a book in a database and returns the page's id.
+    ///
+    /// # Arguments
+    ///
+    /// * `database_id` - The database's id.
+    /// * `title_property` - The name of the database's title property.
+    /// * `book` - The book to create the page for.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the request fails.
+    fn create_page(
+        &mut self,
+        database_id: &str,
+        title_property: &str,
+        book: &Book,
+    ) -> Result<String> {
+        let body = json!({
+            "parent": { "database_id": database_id },
+            "properties": {
+                title_property: { "title": self::rich_text(&book.title) }
+            },
+        });
+
+        let page = self.request("POST", "pages", Some(&body))?;
+
+        page["id"]
+            .as_str()
+            .map(str::to_owned)
+            .ok_or_else(|| Error::SyncError {
+                url: format!("{API_URL}/pages"),
+                error: "response is missing the page's id".to_owned(),
+            })
+    }
+
+    /// Appends blocks to the end of a page.
+    ///
+    /// # Arguments
+    ///
+    /// * `page_id` - The page's id.
+    /// * `blocks` - The blocks to append.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the request fails.
+    fn append_blocks(&mut self, page_id: &str, blocks: &[Value]) -> Result<()> {
+        let body = json!({ "children": blocks });
+
+        self.request("PATCH", &format!("blocks/{page_id}/children"), Some(&body))?;
+
+        Ok(())
+    }
+
+    /// Sends a request and returns the response's body.
+    ///
+    /// Requests are spaced at least [`REQUEST_INTERVAL`] apart. See [`super::send_with_retry()`]
+    /// for how rate-limited requests are retried.
+    ///
+    /// # Arguments
+    ///
+    /// * `method` - The request's method.
+    /// * `path` - The request's path relative to the API's base URL.
+    /// * `body` - The request's JSON body, if any.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the request fails or the response cannot be parsed.
+    fn request(&mut self, method: &str, path: &str, body: Option<&Value>) -> Result<Value> {
+        let url = format!("{API_URL}/{path}");
+
+        let response = super::send_with_retry("Notion", &url, "message", body, || {
+            self.wait();
+
+            ureq::request(method, &url)
+                .set("Authorization", &format!("Bearer {}", self.token))
+                .set("Notion-Version", API_VERSION)
+        })?;
+
+        Ok(serde_json::from_str(&response.into_string()?)?)
+    }
+
+    /// Blocks until at least [`REQUEST_INTERVAL`] has passed since the last request.
+    fn wait(&mut self) {
+        if let Some(remaining) = self
+            .last_request
+            .and_then(|last_request| REQUEST_INTERVAL.checked_sub(last_request.elapsed()))
+        {
+            std::thread::sleep(remaining);
+        }
+
+        self.last_request = Some(Instant::now());
+    }
+}
+
+/// A struct representing which books and annotations have been synced to a database.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct SyncState {
+    /// The path to the state file.
+    #[serde(skip)]
+    path: PathBuf,
+
+    /// The synced books keyed by the book's id.
+    books: BTreeMap<String, SyncedBook>,
+}
+
+/// A struct representing a book that has been synced to a database.
+#[derive(Debug, Serialize, Deserialize)]
+struct SyncedBook {
+    /// The id of the book's page.
+    page_id: String,
+
+    /// The ids of the annotations appended to the page.
+    annotations: BTreeSet<String>,
+}
+
+impl SyncState {
+    /// Loads the state for a database. Returns an empty state if none was saved.
+    ///
+    /// # Arguments
+    ///
+    /// * `directory` - The output directory the state is saved to.
+    /// * `database_id` - The database's id.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the state file exists but cannot be read or parsed.
+    fn load(directory: &Path, database_id: &str) -> Result<Self> {
+        let path = directory.join(self::state_filename(database_id));
+        let state: Self = super::load_state(&path)?;
+
+        Ok(Self { path, ..state })
+    }
+
+    /// Saves the state. See [`super::save_state()`].
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if any IO errors are encountered.
+    fn save(&self) -> Result<()> {
+        super::save_state(&self.path, self)
+    }
+}
+
+/// Returns the name of a database's state file.
+///
+/// Notion ids can be written with or without dashes so these are removed.
+///
+/// # Arguments
+///
+/// * `database_id` - The database's id.
+fn state_filename(database_id: &str) -> String {
+    let id: String = database_id
+        .chars()
+        .filter(char::is_ascii_alphanumeric)
+        .collect();
+
+    format!(".readstor-notion-{id}.json")
+}
+
+/// Returns the name of a database's title property, if any.
+///
+/// # Arguments
+///
+/// * `database` - A database object returned by the Notion API.
+fn find_title_property(database: &Value) -> Option<String> {
+    database["properties"]
+        .as_object()?
+        .iter()
+        .find(|(_, property)| property["type"] == "title")
+        .map(|(name, _)| name.clone())
+}
+
+/// Returns the blocks an annotation is appended as: a quote block with the annotation's body,
+/// followed by a paragraph block with its notes, if any.
+///
+/// # Arguments
+///
+/// * `annotation` - The annotation to convert.
+fn annotation_blocks(annotation: &Annotation) -> Vec<Value> {
+    let mut blocks = vec![json!({
+        "object": "block",
+        "type": "quote",
+        "quote": { "rich_text": self::rich_text(&annotation.body) },
+    })];
+
+    if !annotation.notes.is_empty() {
+        blocks.push(json!({
+            "object": "block",
+            "type": "paragraph",
+            "paragraph": { "rich_text": self::rich_text(&annotation.notes) },
+        }));
+    }
+
+    blocks
+}
+
+/// Returns a list of rich text objects for a string. Long strings are split as Notion limits the
+/// length of each object.
+///
+/// # Arguments
+///
+/// * `text` - The text to convert.
+fn rich_text(text: &str) -> Vec<Value> {
+    let characters: Vec<char> = text.chars().collect();
+
+    characters
+        .chunks(MAX_TEXT_LENGTH)
+        .map(|chunk| {
+            json!({
+                "type": "text",
+                "text": { "content": chunk.iter().collect::<String>() },
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    use crate::defaults::TEMP_OUTPUT_DIRECTORY;
+
+    #[test]
+    fn rich_text_split() {
+        let text = "a".repeat(MAX_TEXT_LENGTH * 2 + 500);
+
+        let lengths: Vec<usize> = rich_text(&text)
+            .iter()
+            .map(|object| object["text"]["content"].as_str().unwrap().len())
+            .collect();
+
+        assert_eq!(lengths, [MAX_TEXT_LENGTH, MAX_TEXT_LENGTH, 500]);
+    }
+
+    // Tests that notes are only appended if the annotation has any.
+    #[test]
+    fn blocks_with_notes() {
+        let mut annotation = Annotation {
+            body: "We are not here to do what has already been done.".to_string(),
+            ..Default::default()
+        };
+
+        let blocks = annotation_blocks(&annotation);
+
+        assert_eq!(blocks.len(), 1);
+        assert_eq!(blocks[0]["type"], "quote");
+
+        annotation.notes = "Robert Henri".to_string();
+
+        let blocks = annotation_blocks(&annotation);
+
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(
+            blocks[1]["paragraph"]["rich_text"][0]["text"]["content"],
+            "Robert Henri"
+        );
+    }
+
+    #[test]
+    fn title_property() {
+        let database = json!({
+            "object": "database",
+            "properties": {
+                "Author": { "type": "rich_text" },
+                "Book": { "type": "title" },
+            },
+        });
+
+        assert_eq!(find_title_property(&database), Some("Book".to_string()));
+        assert_eq!(find_title_property(&json!({})), None);
+    }
+
+    // Tests that the state is saved and loaded per database.
+    #[test]
+    fn state_save_and_load() {
+        let directory = TEMP_OUTPUT_DIRECTORY.join("tests-notion");
+
+        if directory.exists() {
+            std::fs::remove_dir_all(&directory).unwrap();
+        }
+
+        let mut state = SyncState::load(&directory, "1234-abcd").unwrap();
+        state.books.insert(
+            "01".to_string(),
+            SyncedBook {
+                page_id: "page".to_string(),
+                annotations: BTreeSet::from(["A".to_string()]),
+            },
+        );
+        state.save().unwrap();
+
+        assert!(directory.join(".readstor-notion-1234abcd.json").exists());
+
+        let state = SyncState::load(&directory, "1234abcd").unwrap();
+
+        assert_eq!(state.books["01"].page_id, "page");
+        assert!(state.books["01"].annotations.contains("A"));
+
+        let state = SyncState::load(&directory, "5678").unwrap();
+
+        assert!(state.books.is_empty());
+    }
+}
@@ -0,0 +1,283 @@
+//! Defines a generic webhook client for sending new annotations to a user-provided endpoint.
+
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+
+use crate::models::annotation::Annotation;
+use crate::models::book::Book;
+use crate::models::entry::Entry;
+use crate::result::Result;
+
+/// The default number of annotations sent per request.
+pub const DEFAULT_BATCH_SIZE: usize = 1;
+
+/// A struct representing options for syncing to a webhook.
+#[derive(Debug, Clone)]
+pub struct WebhookOptions {
+    /// The URL annotations are sent to.
+    pub url: String,
+
+    /// Additional headers sent with each request e.g. for authentication.
+    pub headers: Vec<(String, String)>,
+
+    /// The maximum number of annotations sent per request.
+    pub batch_size: usize,
+}
+
+impl Default for WebhookOptions {
+    fn default() -> Self {
+        Self {
+            url: String::new(),
+            headers: Vec::new(),
+            batch_size: DEFAULT_BATCH_SIZE,
+        }
+    }
+}
+
+/// A struct representing the outcome of a sync.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct SyncStats {
+    /// The number of requests sent.
+    pub requests: usize,
+
+    /// The number of annotations sent.
+    pub annotations_sent: usize,
+}
+
+/// Sends new annotations to a webhook.
+///
+/// Annotations are sent in batches of at most `batch_size` as a JSON object with an
+/// `annotations` list. Each item contains the `book`, the `annotation` and its `tags` without the
+/// leading `#`.
+///
+/// The ids of the sent annotations are saved to `.readstor-webhook-[HASH].json` in the output
+/// directory, where the hash is derived from the URL. On subsequent runs, only annotations that
+/// haven't been sent to the URL yet are sent. Deleting this file causes everything to be sent
+/// again.
+///
+/// # Arguments
+///
+/// * `entries` - The entries to sync.
+/// * `directory` - The output directory the sync state is saved to.
+/// * `options` - The webhook options.
+///
+/// # Errors
+///
+/// Will return `Err` if:
+/// * Any requests fail.
+/// * Any IO errors are encountered.
+pub fn run<'a, I, O>(entries: I, directory: &Path, options: O) -> Result<SyncStats>
+where
+    I: IntoIterator<Item = &'a Entry>,
+    O: Into<WebhookOptions>,
+{
+    let options: WebhookOptions = options.into();
+
+    let mut sync_state = SyncState::load(directory, &options.url)?;
+    let mut stats = SyncStats::default();
+
+    let annotations: Vec<(&Book, &Annotation)> = entries
+        .into_iter()
+        .flat_map(|entry| {
+            entry
+                .annotations
+                .iter()
+                .map(move |annotation| (&entry.book, annotation))
+        })
+        .filter(|(_, annotation)| !sync_state.annotations.contains(&annotation.metadata.id))
+        .collect();
+
+    for chunk in annotations.chunks(options.batch_size.max(1)) {
+        let body = self::payload(chunk);
+
+        self::send(&options, &body)?;
+
+        // The state is saved after each request so that an interrupted sync never sends the same
+        // annotations twice.
+        sync_state.annotations.extend(
+            chunk
+                .iter()
+                .map(|(_, annotation)| annotation.metadata.id.clone()),
+        );
+        sync_state.save()?;
+
+        stats.requests += 1;
+        stats.annotations_sent += chunk.len();
+    }
+
+    tracing::debug!(
+        requests = stats.requests,
+        annotations = stats.annotations_sent,
+        "synced annotations to webhook"
+    );
+
+    Ok(stats)
+}
+
+/// Returns the JSON body a batch of annotations is sent as.
+///
+/// # Arguments
+///
+/// * `annotations` - The annotations to send, each paired with its book.
+fn payload(annotations: &[(&Book, &Annotation)]) -> Value {
+    let annotations: Vec<Value> = annotations
+        .iter()
+        .map(|(book, annotation)| {
+            let tags: Vec<&str> = annotation
+                .tags
+                .iter()
+                .map(|tag| tag.trim_start_matches('#'))
+                .collect();
+
+            json!({
+                "book": book,
+                "annotation": annotation,
+                "tags": tags,
+            })
+        })
+        .collect();
+
+    json!({ "annotations": annotations })
+}
+
+/// Sends a JSON body to the webhook. See [`super::send_with_retry()`] for how rate-limited requests
+/// are retried.
+///
+/// # Arguments
+///
+/// * `options` - The webhook options.
+/// * `body` - The request's JSON body.
+///
+/// # Errors
+///
+/// Will return `Err` if the request fails.
+fn send(options: &WebhookOptions, body: &Value) -> Result<()> {
+    super::send_with_retry("webhook", &options.url, "message", Some(body), || {
+        options
+            .headers
+            .iter()
+            .fold(ureq::post(&options.url), |request, (name, value)| {
+                request.set(name, value)
+            })
+    })?;
+
+    Ok(())
+}
+
+/// A struct representing which annotations have been sent to a webhook.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct SyncState {
+    /// The path to the state file.
+    #[serde(skip)]
+    path: PathBuf,
+
+    /// The ids of the sent annotations.
+    annotations: BTreeSet<String>,
+}
+
+impl SyncState {
+    /// Loads the state for a URL. Returns an empty state if none was saved.
+    ///
+    /// # Arguments
+    ///
+    /// * `directory` - The output directory the state is saved to.
+    /// * `url` - The webhook's URL.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the state file exists but cannot be read or parsed.
+    fn load(directory: &Path, url: &str) -> Result<Self> {
+        let path = directory.join(self::state_filename(url));
+        let state: Self = super::load_state(&path)?;
+
+        Ok(Self { path, ..state })
+    }
+
+    /// Saves the state. See [`super::save_state()`].
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if any IO errors are encountered.
+    fn save(&self) -> Result<()> {
+        super::save_state(&self.path, self)
+    }
+}
+
+/// Returns the name of a URL's state file.
+///
+/// URLs can contain characters that aren't valid in filenames, and secrets, so a short hash of the
+/// URL is used instead.
+///
+/// # Arguments
+///
+/// * `url` - The webhook's URL.
+fn state_filename(url: &str) -> String {
+    let hash = hex::encode(Sha256::digest(url.as_bytes()));
+
+    format!(".readstor-webhook-{}.json", &hash[..12])
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    use crate::defaults::TEMP_OUTPUT_DIRECTORY;
+
+    // Tests that each item contains the book, the annotation and its tags without the `#`.
+    #[test]
+    fn payload_items() {
+        let book = Book {
+            title: "The Art Spirit".to_string(),
+            ..Default::default()
+        };
+        let annotation = Annotation {
+            body: "We are not here to do what has already been done.".to_string(),
+            tags: BTreeSet::from(["#art".to_string(), "#quote".to_string()]),
+            ..Default::default()
+        };
+
+        let body = payload(&[(&book, &annotation), (&book, &annotation)]);
+
+        assert_eq!(body["annotations"].as_array().unwrap().len(), 2);
+        assert_eq!(body["annotations"][0]["book"]["title"], "The Art Spirit");
+        assert_eq!(
+            body["annotations"][0]["annotation"]["body"],
+            "We are not here to do what has already been done."
+        );
+        assert_eq!(body["annotations"][0]["tags"], json!(["art", "quote"]));
+    }
+
+    // Tests that the state is saved and loaded per URL.
+    #[test]
+    fn state_save_and_load() {
+        let directory = TEMP_OUTPUT_DIRECTORY.join("tests-webhook");
+
+        if directory.exists() {
+            std::fs::remove_dir_all(&directory).unwrap();
+        }
+
+        let url = "https://example.com/hooks/readstor?token=secret";
+
+        let mut state = SyncState::load(&directory, url).unwrap();
+        state.annotations.insert("A".to_string());
+        state.save().unwrap();
+
+        let filename = state_filename(url);
+
+        assert!(directory.join(&filename).exists());
+        assert!(!filename.contains("secret"));
+
+        let state = SyncState::load(&directory, url).unwrap();
+
+        assert!(state.annotations.contains("A"));
+
+        let state = SyncState::load(&directory, "https://example.com").unwrap();
+
+        assert!(state.annotations.is_empty());
+    }
+}
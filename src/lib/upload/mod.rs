@@ -0,0 +1,295 @@
+//! Defines types for uploading exports and back-ups to remote storage.
+
+mod s3;
+mod webdav;
+
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use crate::result::{Error, Result};
+
+/// Uploads a file or directory to a remote target.
+///
+/// The source's name is kept when uploading. For example, uploading the directory
+/// `[output-directory]/1970-01-01-120000-v0.1-0000` to `s3://bucket/backups` results in the
+/// following structure:
+///
+/// ```plaintext
+/// s3://bucket/backups
+///  │
+///  └── 1970-01-01-120000-v0.1-0000
+///       │
+///       ├── AEAnnotation
+///       │   ├── AEAnnotation*.sqlite
+///       │   └── ...
+///       │
+///       └── BKLibrary
+///           ├── BKLibrary*.sqlite
+///           └── ...
+/// ```
+///
+/// Hidden files e.g. `.DS_Store` are skipped.
+///
+/// Returns a list of the URLs of all the uploaded files.
+///
+/// # Arguments
+///
+/// * `target` - Where to upload to.
+/// * `source` - The file or directory to upload.
+///
+/// # Errors
+///
+/// Will return `Err` if:
+/// * The target's credentials are missing.
+/// * Any requests fail.
+/// * Any IO errors are encountered.
+pub fn run(target: &UploadTarget, source: &Path) -> Result<Vec<String>> {
+    let files = self::collect_files(source);
+
+    let mut uploaded = Vec::with_capacity(files.len());
+
+    match target {
+        UploadTarget::S3 { bucket, prefix } => {
+            let client = s3::Client::from_env(bucket)?;
+
+            for (path, key) in &files {
+                uploaded.push(client.put(&self::join_key(prefix, key), path)?);
+            }
+        }
+        UploadTarget::WebDav { url } => {
+            let mut client = webdav::Client::from_env(url);
+
+            for (path, key) in &files {
+                uploaded.push(client.put(key, path)?);
+            }
+        }
+    }
+
+    Ok(uploaded)
+}
+
+/// An enum representing the supported remote targets.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UploadTarget {
+    /// An S3 or S3-compatible bucket e.g. `s3://[bucket]/[prefix]`.
+    ///
+    /// Credentials are read from the `AWS_ACCESS_KEY_ID`, `AWS_SECRET_ACCESS_KEY` and optional
+    /// `AWS_SESSION_TOKEN` environment variables. The region is read from `AWS_REGION` or
+    /// `AWS_DEFAULT_REGION` and defaults to `us-east-1`. S3-compatible services can be used by
+    /// setting `AWS_ENDPOINT_URL`.
+    S3 {
+        /// The bucket's name.
+        bucket: String,
+
+        /// The key prefix to upload under. This can be empty.
+        prefix: String,
+    },
+
+    /// A `WebDAV` server e.g. `https://example.com/remote.php/dav/files/user/readstor`.
+    ///
+    /// Credentials are read from the optional `READSTOR_WEBDAV_USERNAME` and
+    /// `READSTOR_WEBDAV_PASSWORD` environment variables.
+    WebDav {
+        /// The base URL to upload under.
+        url: String,
+    },
+}
+
+impl FromStr for UploadTarget {
+    type Err = Error;
+
+    fn from_str(target: &str) -> Result<Self> {
+        if let Some(location) = target.strip_prefix("s3://") {
+            let (bucket, prefix) = location.split_once('/').unwrap_or((location, ""));
+
+            if bucket.is_empty() {
+                return Err(Error::UploadInvalidTarget {
+                    target: target.to_owned(),
+                });
+            }
+
+            return Ok(Self::S3 {
+                bucket: bucket.to_owned(),
+                prefix: prefix.trim_matches('/').to_owned(),
+            });
+        }
+
+        if target.starts_with("http://") || target.starts_with("https://") {
+            return Ok(Self::WebDav {
+                url: target.trim_end_matches('/').to_owned(),
+            });
+        }
+
+        Err(Error::UploadInvalidTarget {
+            target: target.to_owned(),
+        })
+    }
+}
+
+impl std::fmt::Display for UploadTarget {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::S3 { bucket, prefix } if prefix.is_empty() => write!(f, "s3://{bucket}"),
+            Self::S3 { bucket, prefix } => write!(f, "s3://{bucket}/{prefix}"),
+            Self::WebDav { url } => write!(f, "{url}"),
+        }
+    }
+}
+
+/// Returns a list of all the files to upload along with their remote keys.
+///
+/// Each key is the file's path relative to the source's parent directory, using `/` as the
+/// separator, so that the source's name is kept.
+///
+/// # Arguments
+///
+/// * `source` - The file or directory to upload.
+fn collect_files(source: &Path) -> Vec<(PathBuf, String)> {
+    let root = source.parent().unwrap_or(source);
+
+    walkdir::WalkDir::new(source)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_entry(|e| e.depth() == 0 || !e.file_name().to_string_lossy().starts_with('.'))
+        .filter_map(std::result::Result::ok)
+        .filter(|e| e.file_type().is_file())
+        .map(|e| {
+            let key = e
+                .path()
+                .strip_prefix(root)
+                .unwrap_or_else(|_| e.path())
+                .components()
+                .map(|component| component.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+
+            (e.path().to_owned(), key)
+        })
+        .collect()
+}
+
+/// Joins a key prefix and a key.
+///
+/// # Arguments
+///
+/// * `prefix` - The key prefix. This can be empty.
+/// * `key` - The key.
+fn join_key(prefix: &str, key: &str) -> String {
+    if prefix.is_empty() {
+        key.to_owned()
+    } else {
+        format!("{prefix}/{key}")
+    }
+}
+
+/// Percent-encodes a key for use in a URL path. Unreserved characters and `/` are kept as-is.
+///
+/// # Arguments
+///
+/// * `key` - The key to encode.
+fn encode_key(key: &str) -> String {
+    key.bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{byte:02X}"),
+        })
+        .collect()
+}
+
+/// Maps a failed request to an [`Error`].
+///
+/// # Arguments
+///
+/// * `url` - The request's URL.
+/// * `error` - The request's error.
+fn request_error(url: &str, error: &ureq::Error) -> Error {
+    Error::UploadError {
+        url: url.to_owned(),
+        error: error.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    use crate::defaults::TEMP_OUTPUT_DIRECTORY;
+
+    #[test]
+    fn parse_s3() {
+        assert_eq!(
+            UploadTarget::from_str("s3://bucket/some/prefix/").unwrap(),
+            UploadTarget::S3 {
+                bucket: "bucket".to_string(),
+                prefix: "some/prefix".to_string(),
+            }
+        );
+        assert_eq!(
+            UploadTarget::from_str("s3://bucket").unwrap(),
+            UploadTarget::S3 {
+                bucket: "bucket".to_string(),
+                prefix: String::new(),
+            }
+        );
+    }
+
+    #[test]
+    fn parse_webdav() {
+        assert_eq!(
+            UploadTarget::from_str("https://example.com/dav/").unwrap(),
+            UploadTarget::WebDav {
+                url: "https://example.com/dav".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn parse_invalid() {
+        assert!(UploadTarget::from_str("s3://").is_err());
+        assert!(UploadTarget::from_str("ftp://example.com").is_err());
+        assert!(UploadTarget::from_str("bucket/prefix").is_err());
+    }
+
+    // Tests that keys keep the source's name and hidden files are skipped.
+    #[test]
+    fn collect_files_directory() {
+        let source = TEMP_OUTPUT_DIRECTORY
+            .join("tests-upload")
+            .join("collect-files");
+
+        if source.exists() {
+            std::fs::remove_dir_all(&source).unwrap();
+        }
+
+        std::fs::create_dir_all(source.join("nested")).unwrap();
+        std::fs::write(source.join("book.json"), "").unwrap();
+        std::fs::write(source.join("nested").join("annotations.json"), "").unwrap();
+        std::fs::write(source.join(".DS_Store"), "").unwrap();
+
+        let keys: Vec<String> = collect_files(&source)
+            .into_iter()
+            .map(|(_, key)| key)
+            .collect();
+
+        assert_eq!(
+            keys,
+            [
+                "collect-files/book.json",
+                "collect-files/nested/annotations.json"
+            ]
+        );
+    }
+
+    #[test]
+    fn keys() {
+        assert_eq!(join_key("", "a/b.json"), "a/b.json");
+        assert_eq!(join_key("prefix", "a/b.json"), "prefix/a/b.json");
+        assert_eq!(
+            encode_key("Robert Henri - The Art Spirit/book.json"),
+            "Robert%20Henri%20-%20The%20Art%20Spirit/book.json"
+        );
+    }
+}
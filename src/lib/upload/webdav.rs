@@ -0,0 +1,118 @@
+//! Defines a minimal `WebDAV` client for uploading files.
+
+use std::collections::HashSet;
+use std::path::Path;
+
+use base64::Engine;
+
+use crate::result::Result;
+
+/// A client for uploading files to a `WebDAV` server.
+pub(super) struct Client {
+    /// The base URL to upload under.
+    url: String,
+
+    /// The value of the `authorization` header, if any credentials are set.
+    authorization: Option<String>,
+
+    /// The collections i.e. directories that have already been created.
+    collections: HashSet<String>,
+}
+
+impl Client {
+    /// Creates a new instance of [`Client`] with credentials read from the environment.
+    ///
+    /// Basic authentication is used if `READSTOR_WEBDAV_USERNAME` is set.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - The base URL to upload under.
+    pub(super) fn from_env(url: &str) -> Self {
+        let authorization = std::env::var("READSTOR_WEBDAV_USERNAME")
+            .ok()
+            .map(|username| {
+                let password = std::env::var("READSTOR_WEBDAV_PASSWORD").unwrap_or_default();
+                let credentials = base64::engine::general_purpose::STANDARD
+                    .encode(format!("{username}:{password}"));
+                format!("Basic {credentials}")
+            });
+
+        Self {
+            url: url.to_owned(),
+            authorization,
+            collections: HashSet::new(),
+        }
+    }
+
+    /// Uploads a file and returns its URL. Any missing parent collections are created first.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The file's path relative to the base URL.
+    /// * `path` - The path to the file to upload.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if:
+    /// * Any requests fail.
+    /// * Any IO errors are encountered.
+    pub(super) fn put(&mut self, key: &str, path: &Path) -> Result<String> {
+        let mut collection = String::new();
+
+        if let Some((parents, _)) = key.rsplit_once('/') {
+            for parent in parents.split('/') {
+                collection = super::join_key(&collection, parent);
+
+                if self.collections.insert(collection.clone()) {
+                    self.mkcol(&collection)?;
+                }
+            }
+        }
+
+        let body = std::fs::read(path)?;
+        let url = format!("{}/{}", self.url, super::encode_key(key));
+
+        self.request("PUT", &url)
+            .send_bytes(&body)
+            .map_err(|error| super::request_error(&url, &error))?;
+
+        tracing::debug!(path = %path.display(), %url, "uploaded");
+
+        Ok(url)
+    }
+
+    /// Creates a collection i.e. a directory.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The collection's path relative to the base URL.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the request fails for any reason other than the collection already
+    /// existing.
+    fn mkcol(&self, key: &str) -> Result<()> {
+        let url = format!("{}/{}/", self.url, super::encode_key(key));
+
+        match self.request("MKCOL", &url).call() {
+            // `405 Method Not Allowed` is returned if the collection already exists.
+            Ok(_) | Err(ureq::Error::Status(405, _)) => Ok(()),
+            Err(error) => Err(super::request_error(&url, &error)),
+        }
+    }
+
+    /// Builds a request with the `authorization` header set, if any.
+    ///
+    /// # Arguments
+    ///
+    /// * `method` - The request's method.
+    /// * `url` - The request's URL.
+    fn request(&self, method: &str, url: &str) -> ureq::Request {
+        let request = ureq::request(method, url);
+
+        match &self.authorization {
+            Some(authorization) => request.set("Authorization", authorization),
+            None => request,
+        }
+    }
+}
@@ -43,7 +43,7 @@ where
         }
     }
 
-    log::debug!(
+    tracing::debug!(
         "copied directory {} to {}",
         &source.display(),
         &destination.display(),
@@ -127,11 +127,20 @@ where
     seq.end()
 }
 
-#[cfg(test)]
-pub(crate) mod testing {
+/// Defines utilities for testing against the mock Apple Books data and test templates. Only
+/// available with the `test-utils` feature.
+///
+/// See [`defaults::test`][defaults-test] for where the mock data is located.
+///
+/// [defaults-test]: crate::defaults::test
+#[cfg(any(test, feature = "test-utils"))]
+pub mod testing {
     use std::path::PathBuf;
 
-    use crate::defaults::test::TemplatesDirectory;
+    use crate::applebooks::ios::ABIOs;
+    use crate::applebooks::macos::ABMacOs;
+    use crate::defaults::test::{MockDatabases, MockPlists, TemplatesDirectory};
+    use crate::models::entry::Entries;
 
     /// Loads a test template from the [`TEST_TEMPLATES`][test-templates] directory.
     ///
@@ -140,11 +149,72 @@ pub(crate) mod testing {
     /// * `directory` - The template directory.
     /// * `filename` - The template filename.
     ///
-    /// [test-templates]: crate::defaults::TEST_TEMPLATES
+    /// [test-templates]: crate::defaults::test::TEST_TEMPLATES_DIRECTORY
     #[allow(clippy::missing_panics_doc)]
+    #[must_use]
     pub fn load_template_str(directory: TemplatesDirectory, filename: &str) -> String {
         let path = PathBuf::from(directory).join(filename);
 
         std::fs::read_to_string(path).unwrap()
     }
+
+    /// Loads [`Entries`] from a set of mock macOS databases.
+    ///
+    /// # Arguments
+    ///
+    /// * `databases` - The mock databases to load.
+    #[allow(clippy::missing_panics_doc)]
+    #[must_use]
+    pub fn load_macos_entries(databases: MockDatabases) -> Entries {
+        let path = PathBuf::from(databases);
+
+        let books = ABMacOs::extract_books(&path).unwrap();
+        let annotations = ABMacOs::extract_annotations(&path).unwrap();
+
+        Entries::from_parts(books, annotations)
+    }
+
+    /// Loads [`Entries`] from a set of mock iOS plists.
+    ///
+    /// # Arguments
+    ///
+    /// * `plists` - The mock plists to load.
+    #[allow(clippy::missing_panics_doc)]
+    #[must_use]
+    pub fn load_ios_entries(plists: MockPlists) -> Entries {
+        let path = PathBuf::from(plists);
+
+        let books = ABIOs::extract_books(&path).unwrap();
+        let annotations = ABIOs::extract_annotations(&path).unwrap();
+
+        Entries::from_parts(books, annotations)
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    use crate::defaults::test::{MockDatabases, MockPlists};
+    use crate::models::entry::Entries;
+
+    // Tests that the mock macOS databases and iOS plists are loaded into entries.
+    #[test]
+    fn load_mock_entries() {
+        let macos = testing::load_macos_entries(MockDatabases::BooksAnnotated);
+        let ios = testing::load_ios_entries(MockPlists::BooksAnnotated);
+
+        let count = |entries: &Entries| {
+            entries
+                .values()
+                .map(|entry| entry.annotations.len())
+                .sum::<usize>()
+        };
+
+        assert_eq!(macos.len(), 3);
+        assert_eq!(ios.len(), 3);
+        assert_eq!(count(&macos), 10);
+        assert_eq!(count(&ios), 7);
+    }
 }
@@ -10,10 +10,19 @@ use cli::args::Args;
 use cli::CliResult;
 
 fn main() -> CliResult<()> {
-    cli::utils::init_logger();
     color_eyre::install()?;
 
     let args = Args::parse();
 
-    cli::run(args.command)
+    cli::utils::init_logger(
+        args.command
+            .as_ref()
+            .and_then(cli::args::Command::global_options),
+    )?;
+
+    match args.command {
+        Some(command) => cli::run(command),
+        // Clap prints the help if no arguments are passed so `--shortcut-json` must be set.
+        None => cli::shortcut::run(),
+    }
 }
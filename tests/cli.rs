@@ -1,5 +1,7 @@
 #![allow(missing_docs)]
 
+use std::path::{Path, PathBuf};
+
 use assert_cmd::Command;
 use once_cell::sync::Lazy;
 
@@ -29,6 +31,19 @@ static TEMPLATES_DIRECTORY: Lazy<String> = Lazy::new(|| {
     path.display().to_string()
 });
 
+/// Returns an empty directory within the test output directory, removing any previous contents.
+fn fresh_output_directory(name: &str) -> PathBuf {
+    let path = Path::new(&*OUTPUT_DIRECTORY).join(name);
+
+    if path.exists() {
+        std::fs::remove_dir_all(&path).unwrap();
+    }
+
+    std::fs::create_dir_all(&path).unwrap();
+
+    path
+}
+
 #[test]
 fn version() {
     let mut c = Command::cargo_bin(NAME).unwrap();
@@ -143,6 +158,291 @@ fn default_backup_ios() {
     .success();
 }
 
+// Tests that a back-up can be made from a mock device with partial and failed reads.
+#[test]
+fn mock_device_backup_ios() {
+    let device = Path::new(&*OUTPUT_DIRECTORY).join("mock-device");
+    let books = device.join("Books");
+
+    std::fs::create_dir_all(&books).unwrap();
+
+    for name in ["Books.plist", "com.apple.ibooks-sync.plist"] {
+        std::fs::copy(Path::new(&*PLISTS_DIRECTORY).join(name), books.join(name)).unwrap();
+    }
+
+    let mut c = Command::cargo_bin(NAME).unwrap();
+    c.env(lib::applebooks::ios::device::MOCK_DEVICE, &device)
+        .env(lib::applebooks::ios::device::MOCK_DEVICE_READ_SIZE, "512")
+        .env(lib::applebooks::ios::device::MOCK_DEVICE_READ_FAILURES, "1")
+        .args([
+            "backup",
+            "ios",
+            "--force",
+            "--output-directory",
+            &OUTPUT_DIRECTORY,
+        ])
+        .assert()
+        .code(0)
+        .success();
+}
+
+// Tests that plists are read from a Finder/iTunes back-up.
+#[test]
+fn ios_backup_export_ios() {
+    let mut backup = lib::defaults::CRATE_ROOT.to_owned();
+    backup.extend(["data", "mobilesync", "books-annotated"].iter());
+
+    let mut c = Command::cargo_bin(NAME).unwrap();
+    c.args([
+        "export",
+        "ios",
+        "--force",
+        "--output-directory",
+        &OUTPUT_DIRECTORY,
+        "--ios-backup",
+        &backup.display().to_string(),
+    ])
+    .assert()
+    .code(0)
+    .success();
+}
+
+#[test]
+fn invalid_ios_backup_macos() {
+    let mut backup = lib::defaults::CRATE_ROOT.to_owned();
+    backup.extend(["data", "mobilesync", "books-annotated"].iter());
+
+    let mut c = Command::cargo_bin(NAME).unwrap();
+    c.args([
+        "export",
+        "macos",
+        "--force",
+        "--output-directory",
+        &OUTPUT_DIRECTORY,
+        "--ios-backup",
+        &backup.display().to_string(),
+    ])
+    .assert()
+    .failure();
+}
+
+// Tests that databases are read from a container set by path.
+#[test]
+fn container_export_macos() {
+    let mut c = Command::cargo_bin(NAME).unwrap();
+    c.args([
+        "export",
+        "macos",
+        "--force",
+        "--output-directory",
+        &OUTPUT_DIRECTORY,
+        "--container",
+        &DATABASES_DIRECTORY,
+    ])
+    .assert()
+    .code(0)
+    .success();
+}
+
+#[test]
+fn invalid_container_macos() {
+    let mut c = Command::cargo_bin(NAME).unwrap();
+    c.args([
+        "export",
+        "macos",
+        "--force",
+        "--output-directory",
+        &OUTPUT_DIRECTORY,
+        "--container",
+        &PLISTS_DIRECTORY,
+    ])
+    .assert()
+    .failure();
+}
+
+// Tests that ids are derived from the data and Apple Books' ids are kept.
+#[test]
+fn id_scheme_hash_export_macos() {
+    let output_directory = fresh_output_directory("id-scheme-hash");
+
+    let mut c = Command::cargo_bin(NAME).unwrap();
+    c.args([
+        "export",
+        "macos",
+        "--force",
+        "--output-directory",
+        &output_directory.display().to_string(),
+        "--data-directory",
+        &DATABASES_DIRECTORY,
+        "--id-scheme",
+        "hash",
+    ])
+    .assert()
+    .code(0)
+    .success();
+
+    let books: Vec<_> = walkdir::WalkDir::new(&output_directory)
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_name() == "book.json")
+        .collect();
+
+    assert!(!books.is_empty());
+
+    for book in books {
+        let book: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(book.path()).unwrap()).unwrap();
+
+        assert_eq!(book["metadata"]["id"].as_str().unwrap().len(), 32);
+        assert_ne!(book["metadata"]["id"], book["metadata"]["asset_id"]);
+    }
+}
+
+// Tests that redacted fields are omitted and that titles don't leak into directory names.
+#[test]
+fn redact_export_macos() {
+    let output_directory = fresh_output_directory("redact");
+
+    let mut c = Command::cargo_bin(NAME).unwrap();
+    c.args([
+        "export",
+        "macos",
+        "--force",
+        "--output-directory",
+        &output_directory.display().to_string(),
+        "--data-directory",
+        &DATABASES_DIRECTORY,
+        "--redact",
+        "body",
+        "--redact",
+        "titles",
+        "--redact-mode",
+        "omit",
+    ])
+    .assert()
+    .code(0)
+    .success();
+
+    let directories: Vec<_> = std::fs::read_dir(&output_directory)
+        .unwrap()
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .collect();
+
+    assert_eq!(directories.len(), 3);
+
+    for directory in directories {
+        let book: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(directory.join("book.json")).unwrap())
+                .unwrap();
+        let annotations: serde_json::Value = serde_json::from_str(
+            &std::fs::read_to_string(directory.join("annotations.json")).unwrap(),
+        )
+        .unwrap();
+
+        assert_eq!(book["title"], "");
+        assert!(directory
+            .display()
+            .to_string()
+            .ends_with(book["metadata"]["id"].as_str().unwrap()));
+
+        for annotation in annotations.as_array().unwrap() {
+            assert_eq!(annotation["body"], "");
+        }
+    }
+}
+
+// Tests that a pack is written as a zip archive named after its period.
+#[test]
+fn zip_pack_macos() {
+    let output_directory = fresh_output_directory("pack");
+
+    let mut c = Command::cargo_bin(NAME).unwrap();
+    c.args([
+        "pack",
+        "macos",
+        "--force",
+        "--output-directory",
+        &output_directory.display().to_string(),
+        "--data-directory",
+        &DATABASES_DIRECTORY,
+        "--since",
+        "2000-01-01",
+        "--zip",
+    ])
+    .assert()
+    .code(0)
+    .success();
+
+    let files: Vec<_> = std::fs::read_dir(&output_directory)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .collect();
+
+    assert_eq!(files.len(), 1);
+    assert!(files[0]
+        .to_string_lossy()
+        .contains("readstor-pack-2000-01-01-"));
+    assert_eq!(files[0].extension().unwrap(), "zip");
+}
+
+#[test]
+fn invalid_pack_since_macos() {
+    let mut c = Command::cargo_bin(NAME).unwrap();
+    c.args([
+        "pack",
+        "macos",
+        "--force",
+        "--output-directory",
+        &OUTPUT_DIRECTORY,
+        "--data-directory",
+        &DATABASES_DIRECTORY,
+        "--filter-created-after",
+        "30d",
+    ])
+    .assert()
+    .failure();
+}
+
+// Tests that macOS's and iOS's data are merged.
+#[test]
+fn merge_export_all() {
+    let output_directory = fresh_output_directory("merge-all");
+
+    let mut backup = lib::defaults::CRATE_ROOT.to_owned();
+    backup.extend(["data", "mobilesync", "books-annotated"].iter());
+
+    let mut c = Command::cargo_bin(NAME).unwrap();
+    c.args([
+        "export",
+        "all",
+        "--force",
+        "--output-directory",
+        &output_directory.display().to_string(),
+        "--data-directory",
+        &DATABASES_DIRECTORY,
+        "--ios-backup",
+        &backup.display().to_string(),
+    ])
+    .assert()
+    .code(0)
+    .success();
+}
+
+#[test]
+fn invalid_backup_all() {
+    let mut c = Command::cargo_bin(NAME).unwrap();
+    c.args([
+        "backup",
+        "all",
+        "--force",
+        "--output-directory",
+        &OUTPUT_DIRECTORY,
+    ])
+    .assert()
+    .failure();
+}
+
 #[test]
 fn render_example_templates_macos() {
     let mut c = Command::cargo_bin(NAME).unwrap();
@@ -182,53 +482,557 @@ fn render_example_templates_ios() {
 }
 
 #[test]
-fn missing_output_directory_macos() {
+fn render_presets_macos() {
+    for preset in ["org", "logseq"] {
+        let mut c = Command::cargo_bin(NAME).unwrap();
+        c.args([
+            "render",
+            "macos",
+            "--force",
+            "--output-directory",
+            &OUTPUT_DIRECTORY,
+            "--data-directory",
+            &DATABASES_DIRECTORY,
+            "--preset",
+            preset,
+        ])
+        .assert()
+        .code(0)
+        .success();
+    }
+}
+
+// Tests that all books are rendered into a single file per book template.
+#[test]
+fn render_single_file_macos() {
+    let output_directory = fresh_output_directory("single-file");
+
     let mut c = Command::cargo_bin(NAME).unwrap();
     c.args([
-        "export",
+        "render",
         "macos",
         "--force",
         "--output-directory",
-        "./path/does/not/exist",
+        &output_directory.display().to_string(),
+        "--data-directory",
+        &DATABASES_DIRECTORY,
+        "--single-file",
     ])
     .assert()
-    .code(2)
-    .failure();
+    .code(0)
+    .success();
+
+    assert!(output_directory.join("basic").join("library.md").exists());
 }
 
+// Tests that one file is rendered per tag across all books.
 #[test]
-fn missing_output_directory_ios() {
+fn render_tags_macos() {
+    let output_directory = fresh_output_directory("tags");
+
     let mut c = Command::cargo_bin(NAME).unwrap();
     c.args([
-        "export",
-        "ios",
+        "render",
+        "macos",
         "--force",
         "--output-directory",
-        "./path/does/not/exist",
+        &output_directory.display().to_string(),
+        "--data-directory",
+        &DATABASES_DIRECTORY,
+        "--templates-directory",
+        &TEMPLATES_DIRECTORY,
+        "--template-group",
+        "using-tags",
+        "--extract-tags",
     ])
     .assert()
-    .code(2)
-    .failure();
+    .code(0)
+    .success();
+
+    let tag = output_directory.join("using-tags").join("inspiration.md");
+
+    assert!(std::fs::read_to_string(tag)
+        .unwrap()
+        .starts_with("# #inspiration"));
 }
 
+// Tests that streaming writes the same files as the default render.
 #[test]
-fn missing_data_directory_macos() {
+fn render_stream_macos() {
+    let render = |name: &str, args: &[&str]| {
+        let directory = fresh_output_directory(&format!("stream-{name}"));
+
+        let mut c = Command::cargo_bin(NAME).unwrap();
+        c.args([
+            "render",
+            "macos",
+            "--force",
+            "--output-directory",
+            &directory.display().to_string(),
+            "--data-directory",
+            &DATABASES_DIRECTORY,
+            "--templates-directory",
+            &TEMPLATES_DIRECTORY,
+            "--extract-tags",
+        ])
+        .args(args)
+        .assert()
+        .code(0)
+        .success();
+
+        let mut files: Vec<_> = walkdir::WalkDir::new(&directory)
+            .into_iter()
+            .filter_map(std::result::Result::ok)
+            .filter(|entry| entry.file_type().is_file())
+            .map(|entry| {
+                (
+                    entry.path().strip_prefix(&directory).unwrap().to_owned(),
+                    std::fs::read(entry.path()).unwrap(),
+                )
+            })
+            .collect();
+
+        files.sort();
+        files
+    };
+
+    let buffered = render("buffered", &[]);
+    let streamed = render("streamed", &["--stream"]);
+
+    assert!(!buffered.is_empty());
+    assert_eq!(buffered, streamed);
+}
+
+// Tests that defaults are read from a config file and reported with `--verbose`.
+#[test]
+fn render_config_file_macos() {
+    let directory = fresh_output_directory("config-file");
+    let output_directory = directory.join("output");
+
+    std::fs::create_dir_all(&output_directory).unwrap();
+
+    let config_path = directory.join("config.toml");
+
+    std::fs::write(
+        &config_path,
+        format!(
+            "output-directory = {:?}\n\
+             [preprocess]\n\
+             extract-tags = true\n\
+             [filter-sets.all]\n\
+             filters = [\"title:the\"]\n",
+            output_directory.display().to_string()
+        ),
+    )
+    .unwrap();
+
+    let mut c = Command::cargo_bin(NAME).unwrap();
+    let output = c
+        .args([
+            "render",
+            "macos",
+            "--force",
+            "--data-directory",
+            &DATABASES_DIRECTORY,
+            "--config",
+            &config_path.display().to_string(),
+            "--filter-set",
+            "all",
+            "--auto-confirm-filter",
+            "--verbose",
+        ])
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+
+    assert!(stdout.contains("extract-tags = true (config file)"));
+    assert!(output_directory.join("basic").exists());
+}
+
+// Tests that the export format is inferred from the output file's extension.
+#[test]
+fn export_format_from_extension_macos() {
+    let output_directory = fresh_output_directory("format-from-extension");
+
+    let export = |filename: &str| {
+        Command::cargo_bin(NAME)
+            .unwrap()
+            .args([
+                "export",
+                "macos",
+                "--force",
+                "--output-directory",
+                &output_directory.join(filename).display().to_string(),
+                "--data-directory",
+                &DATABASES_DIRECTORY,
+            ])
+            .assert()
+    };
+
+    export("highlights.jsonl").success();
+    export("library.json").success();
+    export("library.sqlite").failure();
+
+    assert!(output_directory.join("highlights.jsonl").exists());
+    assert!(output_directory.join("library.json").exists());
+}
+
+// Tests that a sandboxed render syncs into the output directory and removes stale files.
+#[test]
+fn render_sandbox_macos() {
+    let output_directory = fresh_output_directory("sandbox");
+
+    let render = |args: &[&str]| {
+        Command::cargo_bin(NAME)
+            .unwrap()
+            .args([
+                "render",
+                "macos",
+                "--force",
+                "--output-directory",
+                &output_directory.display().to_string(),
+                "--data-directory",
+                &DATABASES_DIRECTORY,
+                "--sandbox",
+            ])
+            .args(args)
+            .assert()
+            .success();
+    };
+
+    render(&[]);
+
+    let count = || {
+        walkdir::WalkDir::new(output_directory.join("basic"))
+            .into_iter()
+            .filter_map(Result::ok)
+            .filter(|entry| entry.file_type().is_file())
+            .count()
+    };
+
+    let before = count();
+
+    render(&["--filter", "title:the", "--auto-confirm-filter"]);
+
+    assert!(before > 0);
+    assert!(count() < before);
+}
+
+// Tests that books are filtered by a list file and that an empty list is rejected.
+#[test]
+fn export_books_from_macos() {
+    let directory = fresh_output_directory("books-from");
+    let output_directory = directory.join("output");
+
+    std::fs::create_dir_all(&output_directory).unwrap();
+
+    let export = |list: &str| {
+        let path = directory.join("books.txt");
+        std::fs::write(&path, list).unwrap();
+
+        Command::cargo_bin(NAME)
+            .unwrap()
+            .args([
+                "export",
+                "macos",
+                "--force",
+                "--output-directory",
+                &output_directory.display().to_string(),
+                "--data-directory",
+                &DATABASES_DIRECTORY,
+                "--books-from",
+                &path.display().to_string(),
+                "--auto-confirm-filter",
+            ])
+            .assert()
+    };
+
+    export("# Everything but...\n*\n!*the*\n").success();
+    export("# Nothing\n").failure();
+}
+
+// Tests that all entries are exported into a single file.
+#[test]
+fn export_single_file_macos() {
+    let output_directory = fresh_output_directory("single-file-export");
+
     let mut c = Command::cargo_bin(NAME).unwrap();
     c.args([
         "export",
         "macos",
         "--force",
+        "--output-directory",
+        &output_directory.display().to_string(),
         "--data-directory",
-        "./path/does/not/exist",
+        &DATABASES_DIRECTORY,
+        "--single-file",
     ])
     .assert()
-    .code(2)
-    .failure();
+    .code(0)
+    .success();
+
+    assert!(output_directory.join("library.json").exists());
 }
 
+// Tests that each book is exported as an EPUB and that the whole library is exported as a single
+// EPUB when exporting to an `.epub` file.
 #[test]
-fn missing_data_directory_ios() {
-    let mut c = Command::cargo_bin(NAME).unwrap();
+fn export_epub_macos() {
+    let output_directory = fresh_output_directory("epub-export");
+
+    let export = |output: &Path, format: &[&str]| {
+        let mut c = Command::cargo_bin(NAME).unwrap();
+        c.args([
+            "export",
+            "macos",
+            "--force",
+            "--output-directory",
+            &output.display().to_string(),
+            "--data-directory",
+            &DATABASES_DIRECTORY,
+        ])
+        .args(format)
+        .assert()
+        .code(0)
+        .success();
+    };
+
+    export(&output_directory, &["--format", "epub"]);
+
+    assert!(output_directory
+        .join("Robert Henri - The Art Spirit.epub")
+        .exists());
+
+    let library = output_directory.join("highlights.epub");
+
+    export(&library, &[]);
+
+    let epub = std::fs::read(library).unwrap();
+
+    assert_eq!(&epub[30..38], b"mimetype");
+    assert_eq!(&epub[38..58], b"application/epub+zip");
+}
+
+// Tests that canonical exports are identical between runs and have their keys sorted.
+#[test]
+fn canonical_json_export_macos() {
+    let output_directory = fresh_output_directory("canonical-json");
+
+    let export = || {
+        let mut c = Command::cargo_bin(NAME).unwrap();
+        c.args([
+            "export",
+            "macos",
+            "--force",
+            "--output-directory",
+            &output_directory.display().to_string(),
+            "--data-directory",
+            &DATABASES_DIRECTORY,
+            "--single-file",
+            "--overwrite-existing",
+            "--canonical-json",
+        ])
+        .assert()
+        .success();
+
+        std::fs::read_to_string(output_directory.join("library.json")).unwrap()
+    };
+
+    let first = export();
+    let second = export();
+
+    assert_eq!(first, second);
+
+    // Each entry's `annotations` key sorts before its `book` key.
+    let annotations = first.find("\"annotations\"").unwrap();
+    let book = first.find("\"book\"").unwrap();
+
+    assert!(annotations < book);
+}
+
+// Tests that `doctor` passes for readable databases and fails for a directory without any.
+#[test]
+fn doctor_macos() {
+    let mut c = Command::cargo_bin(NAME).unwrap();
+    let output = c
+        .args(["doctor", "--data-directory", &DATABASES_DIRECTORY])
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    assert!(String::from_utf8(output.stdout)
+        .unwrap()
+        .contains("All checks passed."));
+
+    let mut c = Command::cargo_bin(NAME).unwrap();
+    c.args(["doctor", "--data-directory", &TEMPLATES_DIRECTORY])
+        .assert()
+        .failure();
+}
+
+// Tests that re-rendering unchanged files with `--on-conflict backup` leaves them untouched.
+#[test]
+fn on_conflict_backup_render_macos() {
+    let output_directory = fresh_output_directory("on-conflict-backup");
+
+    let render = || {
+        let mut c = Command::cargo_bin(NAME).unwrap();
+        c.args([
+            "render",
+            "macos",
+            "--force",
+            "--output-directory",
+            &output_directory.display().to_string(),
+            "--data-directory",
+            &DATABASES_DIRECTORY,
+            "--on-conflict",
+            "backup",
+        ])
+        .assert()
+        .success();
+    };
+
+    render();
+    render();
+
+    let backups = walkdir::WalkDir::new(&output_directory)
+        .into_iter()
+        .filter_map(std::result::Result::ok)
+        .filter(|entry| entry.path().extension().is_some_and(|ext| ext == "bak"))
+        .count();
+
+    assert_eq!(backups, 0);
+}
+
+// Tests that chapters are resolved from the EPUBs in the data directory's `Books` directory.
+#[test]
+fn export_chapters_macos() {
+    let output_directory = fresh_output_directory("chapters");
+
+    let mut c = Command::cargo_bin(NAME).unwrap();
+    c.args([
+        "export",
+        "macos",
+        "--force",
+        "--output-directory",
+        &output_directory.join("library.json").display().to_string(),
+        "--data-directory",
+        &DATABASES_DIRECTORY,
+    ])
+    .assert()
+    .code(0)
+    .success();
+
+    let library = std::fs::read_to_string(output_directory.join("library.json")).unwrap();
+
+    assert!(library.contains(r#""chapter": "Chapter 17""#));
+}
+
+// Tests that covers are copied alongside `book.json` from the EPUBs in the data directory.
+#[test]
+fn export_include_covers_macos() {
+    let output_directory = fresh_output_directory("include-covers");
+
+    let mut c = Command::cargo_bin(NAME).unwrap();
+    c.args([
+        "export",
+        "macos",
+        "--force",
+        "--include-covers",
+        "--output-directory",
+        &output_directory.display().to_string(),
+        "--data-directory",
+        &DATABASES_DIRECTORY,
+    ])
+    .assert()
+    .code(0)
+    .success();
+
+    let covers: Vec<PathBuf> = std::fs::read_dir(&output_directory)
+        .unwrap()
+        .map(|entry| entry.unwrap().path().join("cover.jpg"))
+        .filter(|path| path.exists())
+        .collect();
+
+    assert_eq!(covers.len(), 1);
+    assert!(covers[0].with_file_name("book.json").exists());
+}
+
+// Tests that a render exceeding its limits is aborted when not run from a terminal.
+#[test]
+fn render_max_files_macos() {
+    let output_directory = fresh_output_directory("max-files");
+
+    let mut c = Command::cargo_bin(NAME).unwrap();
+    c.args([
+        "render",
+        "macos",
+        "--force",
+        "--output-directory",
+        &output_directory.display().to_string(),
+        "--data-directory",
+        &DATABASES_DIRECTORY,
+        "--max-files",
+        "1",
+    ])
+    .assert()
+    .code(1)
+    .failure();
+
+    assert_eq!(std::fs::read_dir(&output_directory).unwrap().count(), 0);
+}
+
+#[test]
+fn missing_output_directory_macos() {
+    let mut c = Command::cargo_bin(NAME).unwrap();
+    c.args([
+        "export",
+        "macos",
+        "--force",
+        "--output-directory",
+        "./path/does/not/exist",
+    ])
+    .assert()
+    .code(2)
+    .failure();
+}
+
+#[test]
+fn missing_output_directory_ios() {
+    let mut c = Command::cargo_bin(NAME).unwrap();
+    c.args([
+        "export",
+        "ios",
+        "--force",
+        "--output-directory",
+        "./path/does/not/exist",
+    ])
+    .assert()
+    .code(2)
+    .failure();
+}
+
+#[test]
+fn missing_data_directory_macos() {
+    let mut c = Command::cargo_bin(NAME).unwrap();
+    c.args([
+        "export",
+        "macos",
+        "--force",
+        "--data-directory",
+        "./path/does/not/exist",
+    ])
+    .assert()
+    .code(2)
+    .failure();
+}
+
+#[test]
+fn missing_data_directory_ios() {
+    let mut c = Command::cargo_bin(NAME).unwrap();
     c.args([
         "export",
         "ios",
@@ -240,3 +1044,1135 @@ fn missing_data_directory_ios() {
     .code(2)
     .failure();
 }
+
+#[test]
+fn dry_run_restore_macos() {
+    let mut c = Command::cargo_bin(NAME).unwrap();
+    c.args([
+        "restore",
+        "macos",
+        &DATABASES_DIRECTORY,
+        "--dry-run",
+        "--force",
+        "--output-directory",
+        &OUTPUT_DIRECTORY,
+        "--data-directory",
+        &DATABASES_DIRECTORY,
+    ])
+    .assert()
+    .code(0)
+    .success();
+}
+
+#[test]
+fn dry_run_restore_ios() {
+    let mut c = Command::cargo_bin(NAME).unwrap();
+    c.args([
+        "restore",
+        "ios",
+        &PLISTS_DIRECTORY,
+        "--dry-run",
+        "--force",
+        "--output-directory",
+        &OUTPUT_DIRECTORY,
+        "--data-directory",
+        &PLISTS_DIRECTORY,
+    ])
+    .assert()
+    .code(0)
+    .success();
+}
+
+#[test]
+fn invalid_backup_restore_macos() {
+    let mut c = Command::cargo_bin(NAME).unwrap();
+    c.args([
+        "restore",
+        "macos",
+        &PLISTS_DIRECTORY,
+        "--dry-run",
+        "--force",
+        "--output-directory",
+        &OUTPUT_DIRECTORY,
+        "--data-directory",
+        &DATABASES_DIRECTORY,
+    ])
+    .assert()
+    .failure();
+}
+
+#[test]
+fn list_books_macos() {
+    let mut c = Command::cargo_bin(NAME).unwrap();
+    c.args([
+        "list",
+        "books",
+        "macos",
+        "--force",
+        "--data-directory",
+        &DATABASES_DIRECTORY,
+    ])
+    .assert()
+    .code(0)
+    .success();
+}
+
+#[test]
+fn list_annotations_ios() {
+    let mut c = Command::cargo_bin(NAME).unwrap();
+    c.args([
+        "list",
+        "annotations",
+        "ios",
+        "--force",
+        "--data-directory",
+        &PLISTS_DIRECTORY,
+    ])
+    .assert()
+    .code(0)
+    .success();
+}
+
+#[test]
+fn filename_templates_export_macos() {
+    let mut c = Command::cargo_bin(NAME).unwrap();
+    c.args([
+        "export",
+        "macos",
+        "--force",
+        "--output-directory",
+        &OUTPUT_DIRECTORY,
+        "--data-directory",
+        &DATABASES_DIRECTORY,
+        "--book-filename-template",
+        "{{ book.slugs.title }}.book",
+        "--annotations-filename-template",
+        "{{ book.slugs.title }}.annotations.json",
+    ])
+    .assert()
+    .code(0)
+    .success();
+}
+
+#[test]
+fn date_range_filter_export_macos() {
+    let mut c = Command::cargo_bin(NAME).unwrap();
+    c.args([
+        "export",
+        "macos",
+        "--force",
+        "--output-directory",
+        &OUTPUT_DIRECTORY,
+        "--data-directory",
+        &DATABASES_DIRECTORY,
+        "--filter-created-after",
+        "2020-01-01",
+        "--filter-created-before",
+        "1d",
+        "--auto-confirm-filter",
+    ])
+    .assert()
+    .code(0)
+    .success();
+}
+
+#[test]
+fn invalid_date_range_filter_macos() {
+    let mut c = Command::cargo_bin(NAME).unwrap();
+    c.args([
+        "export",
+        "macos",
+        "--force",
+        "--filter-created-after",
+        "yesterday",
+    ])
+    .assert()
+    .code(2)
+    .failure();
+}
+
+#[test]
+fn body_filter_render_macos() {
+    let mut c = Command::cargo_bin(NAME).unwrap();
+    c.args([
+        "render",
+        "macos",
+        "--force",
+        "--output-directory",
+        &OUTPUT_DIRECTORY,
+        "--data-directory",
+        &DATABASES_DIRECTORY,
+        "--filter",
+        "=body:the",
+        "--auto-confirm-filter",
+    ])
+    .assert()
+    .code(0)
+    .success();
+}
+
+#[test]
+fn invalid_upload_target_macos() {
+    let mut c = Command::cargo_bin(NAME).unwrap();
+    c.args([
+        "backup",
+        "macos",
+        "--force",
+        "--upload",
+        "ftp://example.com",
+    ])
+    .assert()
+    .code(2)
+    .failure();
+}
+
+// Tests that a failed webhook doesn't fail the run.
+#[test]
+fn notify_webhook_unreachable_macos() {
+    let mut c = Command::cargo_bin(NAME).unwrap();
+    c.args([
+        "export",
+        "macos",
+        "--force",
+        "--output-directory",
+        &OUTPUT_DIRECTORY,
+        "--data-directory",
+        &DATABASES_DIRECTORY,
+        "--notify-webhook",
+        "http://127.0.0.1:9",
+    ])
+    .assert()
+    .code(0)
+    .success();
+}
+
+// Tests that a failing `--on-success` hook fails the run.
+#[test]
+fn on_success_hook_macos() {
+    let mut c = Command::cargo_bin(NAME).unwrap();
+    c.args([
+        "export",
+        "macos",
+        "--force",
+        "--output-directory",
+        &OUTPUT_DIRECTORY,
+        "--data-directory",
+        &DATABASES_DIRECTORY,
+        "--on-success",
+        "test \"$READSTOR_BOOKS\" -gt 0",
+    ])
+    .assert()
+    .code(0)
+    .success();
+
+    let mut c = Command::cargo_bin(NAME).unwrap();
+    c.args([
+        "export",
+        "macos",
+        "--force",
+        "--output-directory",
+        &OUTPUT_DIRECTORY,
+        "--data-directory",
+        &DATABASES_DIRECTORY,
+        "--on-success",
+        "exit 1",
+    ])
+    .assert()
+    .code(1)
+    .failure();
+}
+
+// Tests that an interrupted render can be resumed.
+#[test]
+fn resume_render_macos() {
+    let output_directory = Path::new(&*OUTPUT_DIRECTORY).join("resume");
+    std::fs::create_dir_all(&output_directory).unwrap();
+    std::fs::write(
+        output_directory.join(".readstor-progress.json"),
+        r#"{"templates":["default.jinja2"],"completed":["unknown"]}"#,
+    )
+    .unwrap();
+
+    let mut c = Command::cargo_bin(NAME).unwrap();
+    c.args([
+        "render",
+        "macos",
+        "--force",
+        "--output-directory",
+        &output_directory.display().to_string(),
+        "--data-directory",
+        &DATABASES_DIRECTORY,
+        "--resume",
+    ])
+    .assert()
+    .code(0)
+    .success();
+
+    assert!(!output_directory.join(".readstor-progress.json").exists());
+}
+
+#[test]
+fn sort_limit_export_macos() {
+    let mut c = Command::cargo_bin(NAME).unwrap();
+    c.args([
+        "export",
+        "macos",
+        "--force",
+        "--output-directory",
+        &OUTPUT_DIRECTORY,
+        "--data-directory",
+        &DATABASES_DIRECTORY,
+        "--sort-books",
+        "last-opened",
+        "--limit",
+        "2",
+    ])
+    .assert()
+    .code(0)
+    .success();
+}
+
+#[test]
+fn cluster_macos() {
+    let output_directory = fresh_output_directory("cluster");
+
+    let mut c = Command::cargo_bin(NAME).unwrap();
+    c.args([
+        "cluster",
+        "macos",
+        "--force",
+        "--output-directory",
+        &output_directory.display().to_string(),
+        "--data-directory",
+        &DATABASES_DIRECTORY,
+        "--threshold",
+        "0.3",
+    ])
+    .assert()
+    .code(0)
+    .success();
+
+    assert!(output_directory.join("clusters.json").exists());
+}
+
+// Tests that n-grams are printed as CSV, most frequent first.
+#[test]
+fn analyze_ngrams_csv_macos() {
+    let mut c = Command::cargo_bin(NAME).unwrap();
+    let output = c
+        .args([
+            "analyze",
+            "ngrams",
+            "macos",
+            "--force",
+            "--output-directory",
+            &OUTPUT_DIRECTORY,
+            "--data-directory",
+            &DATABASES_DIRECTORY,
+            "--size",
+            "2",
+            "--top",
+            "5",
+            "--format",
+            "csv",
+        ])
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let lines: Vec<&str> = stdout.lines().collect();
+
+    assert_eq!(lines[0], "ngram,count,books");
+    assert_eq!(lines.len(), 6);
+
+    let counts: Vec<usize> = lines[1..]
+        .iter()
+        .map(|line| line.split(',').nth(1).unwrap().parse().unwrap())
+        .collect();
+
+    assert!(counts.windows(2).all(|pair| pair[0] >= pair[1]));
+}
+
+// Tests that the same annotations are reviewed when run twice on the same date.
+#[test]
+fn review_deterministic_macos() {
+    let review = || {
+        let mut c = Command::cargo_bin(NAME).unwrap();
+        let output = c
+            .args([
+                "review",
+                "macos",
+                "--quiet",
+                "--data-directory",
+                &DATABASES_DIRECTORY,
+                "--count",
+                "3",
+                "--date",
+                "2024-01-01",
+            ])
+            .output()
+            .unwrap();
+
+        assert!(output.status.success());
+
+        String::from_utf8(output.stdout).unwrap()
+    };
+
+    let first = review();
+
+    assert_eq!(first.matches(" — ").count(), 3);
+    assert_eq!(first, review());
+}
+
+// Tests that a reading log is printed and rendered with a template.
+#[test]
+fn reading_log_macos() {
+    let mut c = Command::cargo_bin(NAME).unwrap();
+    let output = c
+        .args([
+            "reading-log",
+            "macos",
+            "--quiet",
+            "--data-directory",
+            &DATABASES_DIRECTORY,
+        ])
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    assert!(String::from_utf8(output.stdout)
+        .unwrap()
+        .contains("In progress"));
+
+    let template = Path::new(&*OUTPUT_DIRECTORY).join("reading-log.jinja2");
+    std::fs::write(
+        &template,
+        "finished={{ finished_count }} reading={{ in_progress | length }}",
+    )
+    .unwrap();
+
+    let mut c = Command::cargo_bin(NAME).unwrap();
+    let output = c
+        .args([
+            "reading-log",
+            "macos",
+            "--quiet",
+            "--data-directory",
+            &DATABASES_DIRECTORY,
+            "--template",
+            template.to_str().unwrap(),
+        ])
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    assert!(String::from_utf8(output.stdout)
+        .unwrap()
+        .starts_with("finished=0 reading="));
+}
+
+// Tests that annotations are searchable once the index is built.
+#[test]
+fn index_build_and_search_macos() {
+    let output_directory = format!("{}/tests-search-index", *OUTPUT_DIRECTORY);
+    std::fs::create_dir_all(&output_directory).unwrap();
+
+    let mut c = Command::cargo_bin(NAME).unwrap();
+    c.args([
+        "index",
+        "build",
+        "macos",
+        "--output-directory",
+        &output_directory,
+        "--data-directory",
+        &DATABASES_DIRECTORY,
+    ])
+    .assert()
+    .success();
+
+    let mut c = Command::cargo_bin(NAME).unwrap();
+    let output = c
+        .args([
+            "search",
+            "author:henri anyway",
+            "--output-directory",
+            &output_directory,
+        ])
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+
+    assert!(stdout.starts_with("The Art Spirit — Robert Henri"));
+}
+
+// Tests that annotation positions are written with the requested number of buckets.
+#[test]
+fn analyze_heatmap_macos() {
+    let output_directory = fresh_output_directory("heatmap");
+
+    let mut c = Command::cargo_bin(NAME).unwrap();
+    c.args([
+        "analyze",
+        "heatmap",
+        "macos",
+        "--force",
+        "--output-directory",
+        &output_directory.display().to_string(),
+        "--data-directory",
+        &DATABASES_DIRECTORY,
+        "--buckets",
+        "5",
+    ])
+    .assert()
+    .code(0)
+    .success();
+
+    let heatmaps: serde_json::Value = serde_json::from_str(
+        &std::fs::read_to_string(output_directory.join("heatmap.json")).unwrap(),
+    )
+    .unwrap();
+
+    for heatmap in heatmaps.as_array().unwrap() {
+        assert_eq!(heatmap["buckets"].as_array().unwrap().len(), 5);
+    }
+}
+
+#[test]
+fn invalid_ngram_size_macos() {
+    let mut c = Command::cargo_bin(NAME).unwrap();
+    c.args(["analyze", "ngrams", "macos", "--force", "--size", "4"])
+        .assert()
+        .failure();
+}
+
+// Tests that every example template is listed along with its group.
+#[test]
+fn templates_list() {
+    let mut c = Command::cargo_bin(NAME).unwrap();
+    let output = c
+        .args([
+            "templates",
+            "list",
+            "--templates-directory",
+            &TEMPLATES_DIRECTORY,
+        ])
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+
+    assert!(stdout.contains("using-backlinks/book.jinja2"));
+    assert!(stdout.contains("using-partials"));
+    assert!(stdout
+        .lines()
+        .last()
+        .is_some_and(|line| line.starts_with("Found ")));
+}
+
+// Tests that a back-up and an export can be compared.
+#[test]
+fn diff_backup_export_macos() {
+    let output_directory = fresh_output_directory("diff");
+
+    let mut c = Command::cargo_bin(NAME).unwrap();
+    c.args([
+        "export",
+        "macos",
+        "--force",
+        "--output-directory",
+        &output_directory.display().to_string(),
+        "--data-directory",
+        &DATABASES_DIRECTORY,
+    ])
+    .assert()
+    .code(0)
+    .success();
+
+    let mut backup = lib::defaults::CRATE_ROOT.to_owned();
+    backup.extend(["data", "databases", "books-new"].iter());
+
+    let mut c = Command::cargo_bin(NAME).unwrap();
+    let output = c
+        .args([
+            "diff",
+            &backup.display().to_string(),
+            &output_directory.display().to_string(),
+            "--format",
+            "json",
+        ])
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+
+    let diff = serde_json::from_slice::<serde_json::Value>(&output.stdout).unwrap();
+
+    assert_eq!(diff["summary"]["added"], 10);
+    assert_eq!(diff["summary"]["removed"], 0);
+}
+
+#[test]
+fn sync_notion_missing_token_macos() {
+    let mut c = Command::cargo_bin(NAME).unwrap();
+    c.env_remove("READSTOR_NOTION_TOKEN")
+        .args([
+            "sync",
+            "notion",
+            "macos",
+            "--force",
+            "--output-directory",
+            &OUTPUT_DIRECTORY,
+            "--data-directory",
+            &DATABASES_DIRECTORY,
+            "--database-id",
+            "00000000000000000000000000000000",
+        ])
+        .assert()
+        .code(1)
+        .failure();
+}
+
+#[test]
+fn sync_hypothesis_missing_token_macos() {
+    let mut c = Command::cargo_bin(NAME).unwrap();
+    c.env_remove("READSTOR_HYPOTHESIS_TOKEN")
+        .args([
+            "sync",
+            "hypothesis",
+            "macos",
+            "--force",
+            "--output-directory",
+            &OUTPUT_DIRECTORY,
+            "--data-directory",
+            &DATABASES_DIRECTORY,
+        ])
+        .assert()
+        .code(1)
+        .failure();
+}
+
+#[test]
+fn sync_webhook_invalid_header() {
+    let mut c = Command::cargo_bin(NAME).unwrap();
+    c.args([
+        "sync",
+        "webhook",
+        "macos",
+        "--url",
+        "http://localhost",
+        "--header",
+        "Authorization",
+    ])
+    .assert()
+    .code(2)
+    .failure();
+}
+
+#[test]
+fn schedule_invalid_command() {
+    let mut c = Command::cargo_bin(NAME).unwrap();
+    c.args([
+        "schedule",
+        "install",
+        "--interval",
+        "daily",
+        "--command",
+        "schedule list",
+    ])
+    .assert()
+    .code(1)
+    .failure();
+}
+
+#[test]
+fn flat_export_macos() {
+    let mut c = Command::cargo_bin(NAME).unwrap();
+    c.args([
+        "export",
+        "macos",
+        "--force",
+        "--output-directory",
+        &OUTPUT_DIRECTORY,
+        "--data-directory",
+        &DATABASES_DIRECTORY,
+        "--flat",
+    ])
+    .assert()
+    .code(0)
+    .success();
+}
+
+#[test]
+fn compressed_export_macos() {
+    let mut c = Command::cargo_bin(NAME).unwrap();
+    c.args([
+        "export",
+        "macos",
+        "--force",
+        "--output-directory",
+        &OUTPUT_DIRECTORY,
+        "--data-directory",
+        &DATABASES_DIRECTORY,
+        "--compress-output",
+        "zstd",
+    ])
+    .assert()
+    .code(0)
+    .success();
+}
+
+#[test]
+fn jsonl_export_macos() {
+    let mut c = Command::cargo_bin(NAME).unwrap();
+    c.args([
+        "export",
+        "macos",
+        "--force",
+        "--output-directory",
+        &OUTPUT_DIRECTORY,
+        "--data-directory",
+        &DATABASES_DIRECTORY,
+        "--format",
+        "jsonl",
+    ])
+    .assert()
+    .code(0)
+    .success();
+}
+
+#[test]
+fn archived_export_ios() {
+    let mut c = Command::cargo_bin(NAME).unwrap();
+    c.args([
+        "export",
+        "ios",
+        "--force",
+        "--output-directory",
+        &OUTPUT_DIRECTORY,
+        "--data-directory",
+        &PLISTS_DIRECTORY,
+        "--compress-output",
+        "gzip",
+        "--archive",
+    ])
+    .assert()
+    .code(0)
+    .success();
+}
+
+#[test]
+fn shortcut_json_export_macos() {
+    let output_directory = fresh_output_directory("shortcut-json");
+
+    let run = |request: serde_json::Value| {
+        let output = Command::cargo_bin(NAME)
+            .unwrap()
+            .arg("--shortcut-json")
+            .write_stdin(request.to_string())
+            .assert()
+            .success()
+            .get_output()
+            .stdout
+            .clone();
+
+        serde_json::from_slice::<serde_json::Value>(&output).unwrap()
+    };
+
+    let response = run(serde_json::json!({
+        "command": "export",
+        "args": ["macos"],
+        "options": {
+            "force": true,
+            "filter": ["title:the"],
+            "output-directory": output_directory.join("export.jsonl"),
+            "data-directory": &*DATABASES_DIRECTORY,
+        },
+    }));
+
+    assert_eq!(response["status"], "success");
+    assert_eq!(response["command"], "export");
+    assert!(response["books"].as_u64().unwrap() > 0);
+    assert!(output_directory.join("export.jsonl").exists());
+
+    let response = run(serde_json::json!({ "command": "export", "args": ["android"] }));
+
+    assert_eq!(response["status"], "failure");
+    assert!(response["error"].as_str().unwrap().contains("android"));
+}
+
+#[test]
+fn snapshot_create_load_macos() {
+    let output_directory = fresh_output_directory("snapshot");
+
+    let mut c = Command::cargo_bin(NAME).unwrap();
+    c.args([
+        "snapshot",
+        "create",
+        "macos",
+        "--force",
+        "--output-directory",
+        &output_directory.display().to_string(),
+        "--data-directory",
+        &DATABASES_DIRECTORY,
+    ])
+    .assert()
+    .code(0)
+    .success();
+
+    let snapshot = std::fs::read_dir(&output_directory)
+        .unwrap()
+        .next()
+        .unwrap()
+        .unwrap()
+        .path();
+
+    let mut c = Command::cargo_bin(NAME).unwrap();
+    c.args(["snapshot", "load", &snapshot.display().to_string()])
+        .assert()
+        .code(0)
+        .success();
+
+    let list = |source: &[&str]| {
+        let mut c = Command::cargo_bin(NAME).unwrap();
+        let output = c
+            .args(["list", "annotations", "macos", "--force"])
+            .args(source)
+            .output()
+            .unwrap();
+
+        assert!(output.status.success());
+
+        output.stdout
+    };
+
+    assert_eq!(
+        list(&["--snapshot", &snapshot.display().to_string()]),
+        list(&["--data-directory", &DATABASES_DIRECTORY])
+    );
+}
+
+#[test]
+fn render_json_progress_macos() {
+    let mut c = Command::cargo_bin(NAME).unwrap();
+    let output = c
+        .args([
+            "render",
+            "macos",
+            "--force",
+            "--json-progress",
+            "--output-directory",
+            &OUTPUT_DIRECTORY,
+            "--data-directory",
+            &DATABASES_DIRECTORY,
+        ])
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+
+    let events: Vec<serde_json::Value> = String::from_utf8(output.stdout)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+
+    let (first, last) = (&events[0], &events[events.len() - 1]);
+
+    assert_eq!(first["event"], "start");
+    assert_eq!(last["event"], "finish");
+    assert_eq!(last["books"], last["total"]);
+    assert_eq!(events.len() as u64, last["total"].as_u64().unwrap() + 2);
+}
+
+#[test]
+fn render_report_macos() {
+    let output_directory = fresh_output_directory("report");
+
+    let render = || {
+        let mut c = Command::cargo_bin(NAME).unwrap();
+        let output = c
+            .args([
+                "render",
+                "macos",
+                "--force",
+                "--quiet",
+                "--report",
+                "-",
+                "--output-directory",
+                &output_directory.display().to_string(),
+                "--data-directory",
+                &DATABASES_DIRECTORY,
+            ])
+            .output()
+            .unwrap();
+
+        assert!(output.status.success());
+
+        serde_json::from_slice::<serde_json::Value>(&output.stdout).unwrap()
+    };
+
+    let first = render();
+
+    assert_eq!(first["status"], "success");
+    assert_eq!(first["files_skipped"], 0);
+    assert!(first["files_written"].as_u64().unwrap() > 0);
+    assert_eq!(first["phases"][0]["name"], "load");
+
+    // Existing files are skipped by default.
+    let second = render();
+
+    assert_eq!(second["files_written"], 0);
+    assert_eq!(second["files_skipped"], first["files_written"]);
+}
+
+#[test]
+fn render_log_file_macos() {
+    let output_directory = fresh_output_directory("log-file");
+
+    let log_file = output_directory.join("readstor.log");
+
+    let render = || {
+        let mut c = Command::cargo_bin(NAME).unwrap();
+        c.args([
+            "render",
+            "macos",
+            "--force",
+            "--quiet",
+            "--log-file",
+            &log_file.display().to_string(),
+            "--output-directory",
+            &output_directory.display().to_string(),
+            "--data-directory",
+            &DATABASES_DIRECTORY,
+        ])
+        .assert()
+        .success()
+        .stderr("");
+    };
+
+    render();
+    render();
+
+    // Runs are appended to the log file, each tagged with the command's name.
+    let log = std::fs::read_to_string(&log_file).unwrap();
+
+    assert!(log
+        .lines()
+        .all(|line| line.contains("run{command=\"render\"}")));
+    assert_eq!(log.matches(": started").count(), 2);
+    assert_eq!(log.matches(": finished").count(), 2);
+    assert!(!log.contains('\x1b'));
+}
+
+// Tests that completion scripts and man pages are generated for the CLI.
+#[test]
+fn completions_and_man() {
+    let mut c = Command::cargo_bin(NAME).unwrap();
+    let output = c.args(["completions", "zsh"]).output().unwrap();
+
+    assert!(output.status.success());
+    assert!(String::from_utf8(output.stdout)
+        .unwrap()
+        .contains("#compdef readstor"));
+
+    let mut c = Command::cargo_bin(NAME).unwrap();
+    let output = c.args(["man"]).output().unwrap();
+
+    assert!(output.status.success());
+    assert!(String::from_utf8(output.stdout).unwrap().contains(".TH"));
+
+    let output_directory = Path::new(&*OUTPUT_DIRECTORY).join("man");
+
+    let mut c = Command::cargo_bin(NAME).unwrap();
+    c.args([
+        "man",
+        "--output-directory",
+        &output_directory.display().to_string(),
+    ])
+    .assert()
+    .success();
+
+    assert!(output_directory.join("readstor.1").exists());
+    assert!(output_directory.join("readstor-render.1").exists());
+}
+
+// Tests that schemas are printed in each format and written to a directory.
+#[test]
+fn schema() {
+    let mut c = Command::cargo_bin(NAME).unwrap();
+    let output = c.args(["schema"]).output().unwrap();
+
+    assert!(output.status.success());
+
+    let schemas: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+
+    assert_eq!(schemas["book.json"]["title"], "Book");
+    assert_eq!(schemas["annotations.json"]["type"], "array");
+
+    let mut c = Command::cargo_bin(NAME).unwrap();
+    let output = c
+        .args(["schema", "--format", "typescript"])
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    assert!(String::from_utf8(output.stdout)
+        .unwrap()
+        .contains("export interface Book {"));
+
+    let output_directory = Path::new(&*OUTPUT_DIRECTORY).join("schema");
+
+    let mut c = Command::cargo_bin(NAME).unwrap();
+    c.args([
+        "schema",
+        "--output-directory",
+        &output_directory.display().to_string(),
+    ])
+    .assert()
+    .success();
+
+    assert!(output_directory.join("book.schema.json").exists());
+    assert!(output_directory.join("annotations.schema.json").exists());
+}
+
+// Tests that an export is imported as a snapshot that other commands can read.
+#[test]
+fn import() {
+    let output_directory = fresh_output_directory("import");
+
+    let mut source = lib::defaults::CRATE_ROOT.to_owned();
+    source.extend(["data", "exports", "v1", "Robert Henri - The Art Spirit"].iter());
+
+    let mut c = Command::cargo_bin(NAME).unwrap();
+    let output = c
+        .args([
+            "import",
+            "json",
+            &source.display().to_string(),
+            "--output-directory",
+            &output_directory.display().to_string(),
+        ])
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    assert!(String::from_utf8(output.stdout)
+        .unwrap()
+        .contains("Imported 3 annotations from 1 books"));
+
+    let snapshot = std::fs::read_dir(&output_directory)
+        .unwrap()
+        .next()
+        .unwrap()
+        .unwrap()
+        .path();
+
+    let mut c = Command::cargo_bin(NAME).unwrap();
+    let output = c
+        .args([
+            "list",
+            "annotations",
+            "macos",
+            "--force",
+            "--snapshot",
+            &snapshot.display().to_string(),
+        ])
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    assert!(String::from_utf8(output.stdout)
+        .unwrap()
+        .contains("The Art Spirit"));
+}
+
+// Tests that a Kindle's clippings file is imported as a snapshot.
+#[test]
+fn import_kindle() {
+    let output_directory = fresh_output_directory("import-kindle");
+
+    let mut source = lib::defaults::CRATE_ROOT.to_owned();
+    source.extend(["data", "kindle", "My Clippings.txt"].iter());
+
+    let mut c = Command::cargo_bin(NAME).unwrap();
+    let output = c
+        .args([
+            "import",
+            "kindle",
+            &source.display().to_string(),
+            "--output-directory",
+            &output_directory.display().to_string(),
+        ])
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    assert!(String::from_utf8(output.stdout)
+        .unwrap()
+        .contains("Imported 5 annotations from 4 books"));
+}
+
+// Tests that a Kobo's database is imported as a snapshot.
+#[test]
+fn import_kobo() {
+    let output_directory = fresh_output_directory("import-kobo");
+
+    let mut source = lib::defaults::CRATE_ROOT.to_owned();
+    source.extend(["data", "kobo", "KoboReader.sqlite"].iter());
+
+    let mut c = Command::cargo_bin(NAME).unwrap();
+    let output = c
+        .args([
+            "import",
+            "kobo",
+            &source.display().to_string(),
+            "--output-directory",
+            &output_directory.display().to_string(),
+        ])
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    assert!(String::from_utf8(output.stdout)
+        .unwrap()
+        .contains("Imported 3 annotations from 2 books"));
+}
+
+// Tests that an unversioned export is migrated and that migrating it again changes nothing.
+#[test]
+fn migrate() {
+    let output_directory = fresh_output_directory("migrate");
+
+    let mut source = lib::defaults::CRATE_ROOT.to_owned();
+    source.extend(["data", "exports", "v1", "library.json"].iter());
+
+    let library = output_directory.join("library.json");
+    std::fs::copy(&source, &library).unwrap();
+
+    let migrate = || {
+        let mut c = Command::cargo_bin(NAME).unwrap();
+        let output = c
+            .args(["migrate", &library.display().to_string()])
+            .output()
+            .unwrap();
+
+        assert!(output.status.success());
+
+        String::from_utf8(output.stdout).unwrap()
+    };
+
+    assert!(migrate().contains("Migrated 1 file(s), 0 already current."));
+    assert!(migrate().contains("Migrated 0 file(s), 1 already current."));
+
+    let library: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&library).unwrap()).unwrap();
+
+    assert_eq!(library[0]["book"]["format_version"], 3);
+}